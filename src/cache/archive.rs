@@ -0,0 +1,411 @@
+//! `cache export` / `cache import`: move built images between machines
+//!
+//! An archive is a gzipped tarball holding `manifest.json` (the cache entries),
+//! the recorded Dockerfiles and `images.tar` from `finch save`. Image names are
+//! derived from content hashes, so an imported entry is only ever used for a
+//! source with the same content and build options.
+//!
+//! Import only archives from sources you trust: the checks here keep an archive from
+//! retagging images finch-mcp didn't build and catch images that don't match their
+//! entries, but they can't tell what the images themselves run.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::cache::{CacheEntry, CacheManager};
+use crate::finch::client::FinchClient;
+use crate::status;
+use crate::workspace;
+
+/// Format version of the archives this build writes and can read
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const IMAGES: &str = "images.tar";
+/// OCI layout index of a `finch save` tarball
+const OCI_INDEX: &str = "index.json";
+/// Annotation containerd names a loaded image after
+const CONTAINERD_NAME: &str = "io.containerd.image.name";
+/// OCI reference annotation, used for the name when containerd's is missing
+const OCI_REF_NAME: &str = "org.opencontainers.image.ref.name";
+
+/// What an archive holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub version: u32,
+    /// finch-mcp version that wrote the archive
+    pub finch_mcp_version: String,
+    pub created_at: u64,
+    pub entries: Vec<CacheEntry>,
+    /// Image ID of each entry's image, checked after loading
+    #[serde(default)]
+    pub image_ids: BTreeMap<String, String>,
+}
+
+/// Path of an image's recorded Dockerfile inside an archive
+fn dockerfile_name(image_name: &str) -> String {
+    format!("dockerfiles/{}.Dockerfile", image_name.replace([':', '/'], "_"))
+}
+
+fn repository(image_name: &str) -> &str {
+    image_name.split(':').next().unwrap_or(image_name)
+}
+
+/// Write an archive from a manifest, recorded Dockerfiles (by image name) and a `finch save` tarball
+pub fn write_archive(output: &Path, manifest: &ArchiveManifest, dockerfiles: &HashMap<String, String>, images_tar: &Path) -> Result<()> {
+    let file = File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    let mut append = |name: &str, content: &[u8]| -> Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.created_at);
+        header.set_cksum();
+        builder.append_data(&mut header, name, content).with_context(|| format!("Failed to add {} to the archive", name))
+    };
+    append(MANIFEST, &serde_json::to_vec_pretty(manifest)?)?;
+    for (image_name, dockerfile) in dockerfiles {
+        append(&dockerfile_name(image_name), dockerfile.as_bytes())?;
+    }
+
+    builder.append_path_with_name(images_tar, IMAGES).context("Failed to add the images to the archive")?;
+    builder.into_inner()?.finish().context("Failed to finish the archive")?;
+    Ok(())
+}
+
+/// Unpack an archive into a directory and return its manifest
+pub fn read_archive(archive: &Path, dest: &Path) -> Result<ArchiveManifest> {
+    let file = File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(dest)
+        .with_context(|| format!("{} is not a finch-mcp cache archive", archive.display()))?;
+
+    let content = std::fs::read(dest.join(MANIFEST))
+        .with_context(|| format!("{} has no {}; was it made by `finch-mcp cache export`?", archive.display(), MANIFEST))?;
+    let manifest: ArchiveManifest = serde_json::from_slice(&content).context("Invalid archive manifest")?;
+    if manifest.version > ARCHIVE_VERSION {
+        anyhow::bail!(
+            "{} was made by finch-mcp {} (archive version {}); upgrade finch-mcp to import it",
+            archive.display(), manifest.finch_mcp_version, manifest.version
+        );
+    }
+    if let Some(entry) = manifest.entries.iter().find(|entry| !entry.image_name.starts_with("mcp-") || entry.image_name.contains('/')) {
+        anyhow::bail!("Refusing to import '{}': not a finch-mcp image name", entry.image_name);
+    }
+    Ok(manifest)
+}
+
+/// Names a `finch save` tarball gives its images on load: the `RepoTags` of its
+/// Docker-format `manifest.json` and the names annotated in its OCI `index.json`
+fn saved_tags(images_tar: &Path) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct SavedImage {
+        #[serde(default)]
+        repo_tags: Vec<String>,
+    }
+    #[derive(Deserialize)]
+    struct Index {
+        #[serde(default)]
+        manifests: Vec<Descriptor>,
+    }
+    #[derive(Deserialize)]
+    struct Descriptor {
+        #[serde(default)]
+        annotations: HashMap<String, String>,
+    }
+
+    let file = File::open(images_tar).with_context(|| format!("Failed to open {}", images_tar.display()))?;
+    let mut tarball = tar::Archive::new(file);
+    let mut tags = Vec::new();
+    let mut parsed = false;
+    for item in tarball.entries()? {
+        let mut item = item?;
+        let path = item.path()?.into_owned();
+        if path == Path::new(MANIFEST) {
+            let mut content = String::new();
+            item.read_to_string(&mut content)?;
+            let saved: Vec<SavedImage> = serde_json::from_str(&content).context("Invalid manifest.json in images.tar")?;
+            tags.extend(saved.into_iter().flat_map(|image| image.repo_tags));
+            parsed = true;
+        } else if path == Path::new(OCI_INDEX) {
+            let mut content = String::new();
+            item.read_to_string(&mut content)?;
+            let index: Index = serde_json::from_str(&content).context("Invalid index.json in images.tar")?;
+            for mut descriptor in index.manifests {
+                let annotations = &mut descriptor.annotations;
+                tags.extend(annotations.remove(CONTAINERD_NAME).or_else(|| annotations.remove(OCI_REF_NAME)));
+            }
+            parsed = true;
+        }
+    }
+    if !parsed {
+        anyhow::bail!("images.tar has neither a {} nor an {}, so its images can't be checked", MANIFEST, OCI_INDEX);
+    }
+    Ok(tags)
+}
+
+/// Names in a `finch save` tarball other than the manifest's images (and their `:latest`)
+pub fn foreign_tags(images_tar: &Path, entries: &[CacheEntry]) -> Result<Vec<String>> {
+    let allowed = |tag: &str| entries.iter().any(|entry| {
+        tag == entry.image_name || tag == format!("{}:latest", repository(&entry.image_name))
+    });
+    Ok(saved_tags(images_tar)?
+        .into_iter()
+        .map(|tag| tag.trim_start_matches("docker.io/library/").to_string())
+        .filter(|tag| !allowed(tag))
+        .collect())
+}
+
+/// Entries whose loaded image is missing or has another ID than the archive recorded
+async fn mismatched_images<'a>(finch_client: &FinchClient, manifest: &'a ArchiveManifest) -> Vec<&'a str> {
+    let mut mismatched = Vec::new();
+    for entry in &manifest.entries {
+        let loaded = finch_client.image_id(&entry.image_name).await;
+        if loaded.is_none() || loaded.as_ref() != manifest.image_ids.get(&entry.image_name) {
+            mismatched.push(entry.image_name.as_str());
+        }
+    }
+    mismatched
+}
+
+/// Bundle the cache entries matching `targets` (all of them if empty) and their images
+pub async fn export_cache(output: &Path, targets: &[String]) -> Result<ArchiveManifest> {
+    let cache_manager = CacheManager::new()?;
+    let mut selected: Vec<CacheEntry> = Vec::new();
+    if targets.is_empty() {
+        selected.extend(cache_manager.entries().cloned());
+    }
+    for target in targets {
+        let matches = cache_manager.find_entries(target);
+        if matches.is_empty() {
+            anyhow::bail!("No cached image matches '{}'. Run `finch-mcp cache stats` to see what is cached", target);
+        }
+        for entry in matches {
+            if !selected.iter().any(|chosen| chosen.image_name == entry.image_name) {
+                selected.push(entry.clone());
+            }
+        }
+    }
+
+    let finch_client = FinchClient::new();
+    let mut entries = Vec::new();
+    let mut image_ids = BTreeMap::new();
+    for entry in selected {
+        match finch_client.image_id(&entry.image_name).await {
+            Some(id) => {
+                image_ids.insert(entry.image_name.clone(), id);
+                entries.push(entry);
+            }
+            None => status!("  ⚠️  Skipping {}: the image is no longer in finch", entry.image_name),
+        }
+    }
+    if entries.is_empty() {
+        anyhow::bail!("Nothing to export: no cached image is present in finch");
+    }
+    entries.sort_by(|a, b| a.image_name.cmp(&b.image_name));
+
+    let temp_dir = workspace::temp_dir("export")?;
+    let images_tar = temp_dir.path().join(IMAGES);
+    status!("📦 Saving {} image(s)...", entries.len());
+    let output_status = Command::new("finch")
+        .arg("save")
+        .arg("-o")
+        .arg(&images_tar)
+        .args(entries.iter().map(|entry| entry.image_name.as_str()))
+        .output()
+        .await
+        .context("Failed to run finch save")?;
+    if !output_status.status.success() {
+        anyhow::bail!("finch save failed: {}", String::from_utf8_lossy(&output_status.stderr).trim());
+    }
+
+    let dockerfiles: HashMap<String, String> = entries.iter()
+        .filter_map(|entry| cache_manager.recorded_dockerfile(&entry.image_name).map(|dockerfile| (entry.image_name.clone(), dockerfile)))
+        .collect();
+    let manifest = ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        finch_mcp_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        entries,
+        image_ids,
+    };
+    write_archive(output, &manifest, &dockerfiles, &images_tar)?;
+    Ok(manifest)
+}
+
+/// Load an archive's images into finch and add its entries to the cache
+pub async fn import_cache(archive: &Path) -> Result<ArchiveManifest> {
+    let temp_dir = workspace::temp_dir("import")?;
+    let manifest = read_archive(archive, temp_dir.path())?;
+    let images_tar = temp_dir.path().join(IMAGES);
+
+    // `finch load` restores every tag in the tarball, so don't let an archive overwrite e.g. node:20
+    let foreign = foreign_tags(&images_tar, &manifest.entries)?;
+    if !foreign.is_empty() {
+        anyhow::bail!("Refusing to import {}: it also contains images finch-mcp didn't build: {}", archive.display(), foreign.join(", "));
+    }
+
+    status!("📥 Loading {} image(s)...", manifest.entries.len());
+    let output = Command::new("finch")
+        .arg("load")
+        .arg("-i")
+        .arg(&images_tar)
+        .output()
+        .await
+        .context("Failed to run finch load")?;
+    if !output.status.success() {
+        anyhow::bail!("finch load failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    // Loaded images must be the ones the entries were recorded for; archives from before
+    // image IDs were recorded can't be checked, so they are refused too
+    let finch_client = FinchClient::new();
+    let mismatched = mismatched_images(&finch_client, &manifest).await;
+    if !mismatched.is_empty() {
+        let _ = Command::new("finch").args(["rmi", "--force"]).args(&mismatched).output().await;
+        anyhow::bail!(
+            "Refusing to import {}: the loaded images don't match the image IDs it records for {}. \
+             Export it again with this finch-mcp version",
+            archive.display(), mismatched.join(", ")
+        );
+    }
+
+    let mut cache_manager = CacheManager::new()?;
+    let mut newest: HashMap<&str, &CacheEntry> = HashMap::new();
+    for entry in &manifest.entries {
+        if let Ok(dockerfile) = std::fs::read_to_string(temp_dir.path().join(dockerfile_name(&entry.image_name))) {
+            cache_manager.store_dockerfile(&entry.image_name, &dockerfile)?;
+        }
+        let newest_for_repository = newest.entry(repository(&entry.image_name)).or_insert(entry);
+        if entry.created_at > newest_for_repository.created_at {
+            *newest_for_repository = entry;
+        }
+    }
+
+    // Give each repository a `:latest` tag unless it already has a local one
+    for (repository, entry) in newest {
+        let latest = format!("{}:latest", repository);
+        if !finch_client.image_exists(&latest).await? {
+            let _ = Command::new("finch").args(["tag", &entry.image_name, &latest]).output().await;
+        }
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    cache_manager.insert_entries(manifest.entries.iter().cloned().map(|mut entry| {
+        entry.last_accessed = now;
+        entry
+    }))?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(image: &str) -> CacheEntry {
+        CacheEntry {
+            content_hash: "abc".to_string(),
+            image_name: image.to_string(),
+            created_at: 1,
+            last_accessed: 1,
+            project_type: "NodeJs".to_string(),
+            source_path: "https://github.com/user/demo".to_string(),
+            build_options_hash: "def".to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        }
+    }
+
+    /// A stand-in for `finch save` output holding `files` (name and JSON content)
+    fn saved_tar(dir: &Path, files: &[(&str, serde_json::Value)]) -> std::path::PathBuf {
+        let path = dir.join("saved.tar");
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        for (name, json) in files {
+            let content = serde_json::to_vec(json).unwrap();
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, content.as_slice()).unwrap();
+        }
+        builder.finish().unwrap();
+        path
+    }
+
+    /// A stand-in for `finch save` output with a Docker-style manifest
+    fn images_tar(dir: &Path, tags: &[&str]) -> std::path::PathBuf {
+        saved_tar(dir, &[(MANIFEST, serde_json::json!([{ "RepoTags": tags }]))])
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = ArchiveManifest {
+            version: ARCHIVE_VERSION,
+            finch_mcp_version: "0.0.0".to_string(),
+            created_at: 1,
+            entries: vec![entry("mcp-demo:abc12345")],
+            image_ids: BTreeMap::from([("mcp-demo:abc12345".to_string(), "sha256:1234".to_string())]),
+        };
+        let dockerfiles = HashMap::from([("mcp-demo:abc12345".to_string(), "FROM node:20\n".to_string())]);
+        let archive = dir.path().join("cache.tar.gz");
+        write_archive(&archive, &manifest, &dockerfiles, &images_tar(dir.path(), &["mcp-demo:abc12345"])).unwrap();
+
+        let unpacked = dir.path().join("unpacked");
+        let read = read_archive(&archive, &unpacked).unwrap();
+        assert_eq!(read.entries[0].image_name, "mcp-demo:abc12345");
+        assert_eq!(read.image_ids["mcp-demo:abc12345"], "sha256:1234");
+        assert_eq!(std::fs::read_to_string(unpacked.join(dockerfile_name("mcp-demo:abc12345"))).unwrap(), "FROM node:20\n");
+        assert!(foreign_tags(&unpacked.join(IMAGES), &read.entries).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_foreign_tags_and_newer_archives_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let saved = images_tar(dir.path(), &["docker.io/library/mcp-demo:latest", "node:20"]);
+        assert_eq!(foreign_tags(&saved, &[entry("mcp-demo:abc12345")]).unwrap(), ["node:20"]);
+
+        let manifest = ArchiveManifest {
+            version: ARCHIVE_VERSION + 1,
+            finch_mcp_version: "99.0.0".to_string(),
+            created_at: 1,
+            entries: Vec::new(),
+            image_ids: BTreeMap::new(),
+        };
+        let archive = dir.path().join("future.tar.gz");
+        write_archive(&archive, &manifest, &HashMap::new(), &saved).unwrap();
+        let err = read_archive(&archive, &dir.path().join("unpacked")).unwrap_err();
+        assert!(err.to_string().contains("upgrade finch-mcp"));
+    }
+    #[test]
+    fn test_foreign_tags_in_oci_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = serde_json::json!({ "manifests": [
+            { "annotations": { CONTAINERD_NAME: "docker.io/library/mcp-demo:abc12345", OCI_REF_NAME: "abc12345" } },
+            { "annotations": { OCI_REF_NAME: "node:20" } },
+            { "annotations": {} },
+        ] });
+        let saved = saved_tar(dir.path(), &[(OCI_INDEX, index)]);
+        assert_eq!(foreign_tags(&saved, &[entry("mcp-demo:abc12345")]).unwrap(), ["node:20"]);
+
+        let saved = saved_tar(dir.path(), &[("blobs/sha256/1234", serde_json::json!({}))]);
+        let err = foreign_tags(&saved, &[entry("mcp-demo:abc12345")]).unwrap_err();
+        assert!(err.to_string().contains("neither a manifest.json nor an index.json"), "{}", err);
+    }
+}
@@ -5,6 +5,8 @@ use anyhow::{Context, Result};
 use sha2::{Digest, Sha256};
 use log::debug;
 
+use crate::cache::git_refs;
+
 /// Content hasher for projects to detect changes
 pub struct ContentHasher {
     ignore_patterns: Vec<String>,
@@ -37,6 +39,7 @@ impl ContentHasher {
     }
     
     /// Hash the contents of a directory
+    #[tracing::instrument(name = "hash", skip_all, fields(path = %dir_path.display()), err)]
     pub fn hash_directory(&self, dir_path: &Path) -> Result<String> {
         debug!("Hashing directory: {:?}", dir_path);
         
@@ -54,20 +57,14 @@ impl ContentHasher {
         Ok(result)
     }
     
-    /// Hash content of a git repository URL
-    pub fn hash_git_repository(&self, repo_url: &str, branch: Option<&str>) -> Result<String> {
+    /// Hash content of a git repository (`url` or `url#ref`): the commit it points at,
+    /// so pushes upstream invalidate the cached image. `offline` keeps to the last known commit,
+    /// `refresh` asks the remote even if the commit was resolved recently
+    #[tracing::instrument(name = "hash", skip_all, fields(url = repo_url), err)]
+    pub fn hash_git_repository(&self, repo_url: &str, offline: bool, refresh: bool) -> Result<String> {
         debug!("Hashing git repository: {}", repo_url);
         
-        // For git repos, we use the URL + branch as the content identifier
-        // In a real implementation, you might want to fetch the latest commit hash
-        let mut hasher = Sha256::new();
-        hasher.update(repo_url.as_bytes());
-        if let Some(branch) = branch {
-            hasher.update(b":");
-            hasher.update(branch.as_bytes());
-        }
-        
-        let result = format!("{:x}", hasher.finalize());
+        let result = git_refs::resolve_commit(repo_url, offline, refresh)?;
         debug!("Git repository hash: {}", result);
         Ok(result)
     }
@@ -136,8 +133,7 @@ impl ContentHasher {
         for pattern in &self.ignore_patterns {
             if pattern.contains('*') {
                 // Simple glob matching for patterns with *
-                if pattern.starts_with("*.") {
-                    let ext = &pattern[2..];
+                if let Some(ext) = pattern.strip_prefix("*.") {
                     if name.ends_with(ext) {
                         return true;
                     }
@@ -186,12 +182,10 @@ mod tests {
     #[test]
     fn test_hash_git_repository() {
         let hasher = ContentHasher::new();
-        let hash1 = hasher.hash_git_repository("https://github.com/user/repo", None).unwrap();
-        let hash2 = hasher.hash_git_repository("https://github.com/user/repo", Some("main")).unwrap();
-        let hash3 = hasher.hash_git_repository("https://github.com/user/repo", Some("dev")).unwrap();
+        let pinned = |commit: &str| hasher.hash_git_repository(&format!("https://github.com/user/repo#{}", commit.repeat(40)), false, false).unwrap();
         
-        assert_ne!(hash1, hash2);
-        assert_ne!(hash2, hash3);
+        assert_eq!(pinned("a"), pinned("a"));
+        assert_ne!(pinned("a"), pinned("b"));
     }
     
     #[test]
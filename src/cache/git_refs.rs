@@ -0,0 +1,154 @@
+//! Resolving git targets to the commit they point at
+//!
+//! Git repositories are cached by commit, so a push upstream rebuilds the image. Asking
+//! the remote (`git ls-remote`) on every start would slow down each MCP client launch,
+//! so resolutions are remembered in `git-refs.json` for an hour. `--refresh` (and
+//! `--force`) resolve again; when the remote can't be reached, or `--offline` rules it
+//! out, the last known commit is used, however old.
+
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::cache::CacheManager;
+use crate::utils::git_repository::GitRepository;
+use crate::utils::offline;
+
+/// How long a resolved commit is trusted before the remote is asked again
+pub const REFRESH_AFTER_SECS: u64 = 60 * 60;
+
+const REFS_FILE: &str = "git-refs.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Resolved {
+    commit: String,
+    resolved_at: u64,
+}
+
+/// The commit a git target (`url` or `url#ref`) points at. A full commit SHA as the
+/// ref is used as-is; `offline` never asks the remote, `refresh` always does
+pub fn resolve_commit(target: &str, offline: bool, refresh: bool) -> Result<String> {
+    let repo = GitRepository::new(target);
+    if let Some(commit) = repo.branch.as_deref().filter(|git_ref| is_commit_sha(git_ref)) {
+        return Ok(commit.to_lowercase());
+    }
+
+    let path = CacheManager::get_cache_dir()?.join(REFS_FILE);
+    let mut known: HashMap<String, Resolved> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+    if let Some(resolved) = known.get(target) {
+        if offline {
+            debug!("{} resolved to {} (remembered, offline)", target, resolved.commit);
+            return Ok(resolved.commit.clone());
+        }
+        if !refresh && now.saturating_sub(resolved.resolved_at) < REFRESH_AFTER_SECS {
+            debug!("{} resolved to {} (remembered)", target, resolved.commit);
+            return Ok(resolved.commit.clone());
+        }
+    }
+
+    offline::ensure_online(offline, &format!("Finding the commit {} points at", target))?;
+    let commit = match ls_remote(&repo) {
+        Ok(commit) => commit,
+        Err(e) => match known.get(target) {
+            Some(resolved) => {
+                warn!("Could not reach {} ({}); using the last known commit {}", repo.url, e, &resolved.commit[..12.min(resolved.commit.len())]);
+                return Ok(resolved.commit.clone());
+            }
+            None => return Err(e),
+        },
+    };
+    debug!("{} resolved to {}", target, commit);
+
+    known.insert(target.to_string(), Resolved { commit: commit.clone(), resolved_at: now });
+    if let Err(e) = save(&path, &known) {
+        debug!("Failed to remember resolved commit: {}", e);
+    }
+    Ok(commit)
+}
+
+/// Ask the remote which commit the repository's ref (or `HEAD`) points at
+fn ls_remote(repo: &GitRepository) -> Result<String> {
+    let git_ref = repo.branch.as_deref().unwrap_or("HEAD");
+    let output = Command::new("git")
+        .args(["ls-remote", "--", &repo.url, git_ref])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to run git ls-remote")?;
+    if !output.status.success() {
+        anyhow::bail!("git ls-remote {} failed: {}", repo.url, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    parse_ls_remote(&String::from_utf8_lossy(&output.stdout), git_ref)
+        .with_context(|| format!("{} has no branch or tag named '{}'", repo.url, git_ref))
+}
+
+/// The commit for `git_ref` in `git ls-remote` output: a branch before a tag, and a
+/// tag's commit rather than the annotated tag object
+fn parse_ls_remote(output: &str, git_ref: &str) -> Option<String> {
+    let refs: Vec<(&str, &str)> = output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect();
+    let find = |name: &str| refs.iter().find(|(_, reference)| *reference == name).map(|(commit, _)| commit.to_string());
+
+    if git_ref == "HEAD" {
+        return find("HEAD");
+    }
+    find(&format!("refs/heads/{}", git_ref))
+        .or_else(|| find(&format!("refs/tags/{}^{{}}", git_ref)))
+        .or_else(|| find(&format!("refs/tags/{}", git_ref)))
+        .or_else(|| find(git_ref))
+}
+
+fn is_commit_sha(git_ref: &str) -> bool {
+    git_ref.len() == 40 && git_ref.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn save(path: &std::path::Path, known: &HashMap<String, Resolved>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp_file = path.with_extension(format!("json.{}.tmp", uuid::Uuid::new_v4()));
+    fs::write(&temp_file, serde_json::to_string_pretty(known)?)?;
+    fs::rename(&temp_file, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ls_remote() {
+        let output = "\
+1111111111111111111111111111111111111111\tHEAD
+2222222222222222222222222222222222222222\trefs/heads/dev
+3333333333333333333333333333333333333333\trefs/tags/v1.0
+4444444444444444444444444444444444444444\trefs/tags/v1.0^{}
+5555555555555555555555555555555555555555\trefs/tags/dev
+";
+        assert_eq!(parse_ls_remote(output, "HEAD").unwrap(), "1".repeat(40));
+        assert_eq!(parse_ls_remote(output, "dev").unwrap(), "2".repeat(40));
+        assert_eq!(parse_ls_remote(output, "v1.0").unwrap(), "4".repeat(40));
+        assert_eq!(parse_ls_remote(output, "refs/tags/v1.0").unwrap(), "3".repeat(40));
+        assert_eq!(parse_ls_remote(output, "missing"), None);
+    }
+
+    #[test]
+    fn test_pinned_commit_needs_no_remote() {
+        let sha = "0123456789abcdef0123456789ABCDEF01234567";
+        let commit = resolve_commit(&format!("https://example.invalid/user/repo#{}", sha), false, false).unwrap();
+        assert_eq!(commit, sha.to_lowercase());
+        assert!(!is_commit_sha("main"));
+    }
+}
@@ -3,14 +3,57 @@ use std::fs;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 use anyhow::{Context, Result};
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use crate::core::ca_certs::CaBundle;
+use crate::utils::units::format_age;
+use crate::error::FinchMcpError;
+
+pub mod archive;
 pub mod content_hasher;
+pub mod git_refs;
+pub mod tags;
+pub mod verify;
 pub use content_hasher::ContentHasher;
 
+/// Image label holding the Dockerfile an image was built from
+pub const DOCKERFILE_LABEL: &str = "dev.finch-mcp.dockerfile";
+
+/// `--label` value that embeds a Dockerfile in the built image
+pub fn dockerfile_label(dockerfile: &str) -> String {
+    format!("{}={}", DOCKERFILE_LABEL, dockerfile)
+}
+
+/// Arguments for `finch build` that skip its layer cache when `no_cache` (`--no-cache`)
+pub fn no_cache_build_args(no_cache: bool) -> Vec<String> {
+    if no_cache {
+        vec!["--no-cache".to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Which cached images to prune. An image is pruned only when every rule that
+/// is set allows it, e.g. unused for 14 days *and* not among the newest 2 of its source
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Prune images not used for this many seconds
+    pub max_age_secs: Option<u64>,
+    /// Keep this many of the most recently built images per source
+    pub keep_latest: Option<usize>,
+}
+
+impl RetentionPolicy {
+    /// No rule set, so nothing would be pruned
+    pub fn is_empty(&self) -> bool {
+        self.max_age_secs.is_none() && self.keep_latest.is_none()
+    }
+}
+
 /// Cache entry for a built container image
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CacheEntry {
     /// Content hash of the source code/project
     pub content_hash: String,
@@ -32,11 +75,76 @@ pub struct CacheEntry {
     
     /// Build options hash (for different build configurations)
     pub build_options_hash: String,
+    
+    /// Number of times a server was started from this image
+    #[serde(default)]
+    pub run_count: u64,
+    
+    /// Timestamp of the last time a server was started from this image
+    #[serde(default)]
+    pub last_run: Option<u64>,
+    
+    /// The `package@version` a `uvx`/`npx` command was pinned to when the image was built
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_package: Option<String>,
+    
+    /// ID of the finch-mcp run that built the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    
+    /// How long the build took, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_secs: Option<u64>,
+}
+
+impl CacheEntry {
+    /// How much the image is used, e.g. `12 runs, last 3d ago` or `never run`
+    pub fn usage_label(&self, now: u64) -> String {
+        match self.last_run {
+            Some(last_run) => format!(
+                "{} run{}, last {} ago",
+                self.run_count,
+                if self.run_count == 1 { "" } else { "s" },
+                format_age(now.saturating_sub(last_run))
+            ),
+            None => "never run".to_string(),
+        }
+    }
+}
+
+/// Version of the per-entry file format this build writes
+pub const ENTRY_SCHEMA_VERSION: u32 = 1;
+
+/// The single file every entry lived in before entries got a file each
+const LEGACY_CACHE_FILE: &str = "finch-mcp-cache.json";
+
+/// One cache entry on disk, in `entries/<hash of key>.json`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryFile {
+    schema_version: u32,
+    key: String,
+    entry: CacheEntry,
+}
+
+/// Serializes read-modify-write updates of an entry within this process
+static STORE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Count a server start from `image_name` if it is a cached image. Failing to record it
+/// never stops the server
+pub fn record_run(image_name: &str) {
+    crate::stats::record_start(image_name);
+    if let Err(e) = CacheManager::new().and_then(|mut cache_manager| cache_manager.record_run(image_name)) {
+        debug!("Failed to record a run of {}: {}", image_name, e);
+    }
 }
 
 /// Cache manager for finch-mcp container images
+///
+/// Each entry is its own file, replaced atomically, so a crash or a bad write loses at
+/// most that entry and builds running in parallel never overwrite each other's entries
 pub struct CacheManager {
-    cache_file: PathBuf,
+    cache_dir: PathBuf,
     entries: HashMap<String, CacheEntry>,
 }
 
@@ -46,51 +154,127 @@ impl CacheManager {
         let cache_dir = Self::get_cache_dir()?;
         fs::create_dir_all(&cache_dir).context("Failed to create cache directory")?;
         
-        let cache_file = cache_dir.join("finch-mcp-cache.json");
-        
         let mut manager = Self {
-            cache_file,
+            cache_dir,
             entries: HashMap::new(),
         };
         
+        manager.migrate_legacy_file()?;
         manager.load_cache()?;
         Ok(manager)
     }
     
     /// Get the cache directory path
-    fn get_cache_dir() -> Result<PathBuf> {
-        if let Ok(xdg_cache) = std::env::var("XDG_CACHE_HOME") {
-            Ok(PathBuf::from(xdg_cache).join("finch-mcp"))
-        } else if let Ok(home) = std::env::var("HOME") {
-            Ok(PathBuf::from(home).join(".cache").join("finch-mcp"))
-        } else {
-            // Windows fallback
-            Ok(PathBuf::from(std::env::var("APPDATA").unwrap_or_else(|_| ".".to_string()))
-                .join("finch-mcp")
-                .join("cache"))
-        }
+    pub(crate) fn get_cache_dir() -> Result<PathBuf> {
+        crate::paths::cache_dir()
     }
     
-    /// Load cache from disk
+    fn entries_dir(&self) -> PathBuf {
+        self.cache_dir.join("entries")
+    }
+    
+    /// The file holding the entry for a cache key
+    fn entry_path(&self, cache_key: &str) -> PathBuf {
+        let hash = format!("{:x}", Sha256::digest(cache_key.as_bytes()));
+        self.entries_dir().join(format!("{}.json", &hash[..32]))
+    }
+    
+    /// Load every entry file over what's in memory. A corrupt file only loses its own entry
     fn load_cache(&mut self) -> Result<()> {
-        if self.cache_file.exists() {
-            let content = fs::read_to_string(&self.cache_file)
-                .context("Failed to read cache file")?;
-            self.entries = serde_json::from_str(&content)
-                .context("Failed to parse cache file")?;
+        let dir = self.entries_dir();
+        let Ok(files) = fs::read_dir(&dir) else {
+            return Ok(());
+        };
+        
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| serde_json::from_str::<EntryFile>(&content).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(stored) if stored.schema_version > ENTRY_SCHEMA_VERSION => {
+                    debug!("Skipping {}: written by a newer finch-mcp (schema {})", path.display(), stored.schema_version);
+                }
+                Ok(stored) => {
+                    self.entries.insert(stored.key, stored.entry);
+                }
+                Err(reason) => warn!("{}; ignoring it", FinchMcpError::CacheCorrupted { path, reason }),
+            }
         }
         Ok(())
     }
     
-    /// Save cache to disk
-    pub fn save_cache(&self) -> Result<()> {
-        let content = serde_json::to_string_pretty(&self.entries)
-            .context("Failed to serialize cache")?;
-        fs::write(&self.cache_file, content)
-            .context("Failed to write cache file")?;
+    /// Move entries from the single cache file older versions kept into entry files,
+    /// keeping the old file as `finch-mcp-cache.json.migrated`
+    fn migrate_legacy_file(&mut self) -> Result<()> {
+        let legacy = self.cache_dir.join(LEGACY_CACHE_FILE);
+        let Ok(content) = fs::read_to_string(&legacy) else {
+            return Ok(());
+        };
+        let _guard = STORE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        
+        match serde_json::from_str::<HashMap<String, CacheEntry>>(&content) {
+            Ok(entries) => {
+                for (key, entry) in &entries {
+                    // Another process may have migrated (and updated) it already
+                    if !self.entry_path(key).exists() {
+                        self.write_entry(key, entry)?;
+                    }
+                }
+                debug!("Migrated {} cache entries from {}", entries.len(), legacy.display());
+                let _ = fs::rename(&legacy, legacy.with_extension("json.migrated"));
+            }
+            Err(e) => {
+                warn!("{}; starting with an empty cache", FinchMcpError::CacheCorrupted { path: legacy.clone(), reason: e.to_string() });
+                let _ = fs::rename(&legacy, legacy.with_extension("json.corrupted"));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Write one entry's file, replacing it atomically so readers never see half of it
+    fn write_entry(&self, cache_key: &str, entry: &CacheEntry) -> Result<()> {
+        let path = self.entry_path(cache_key);
+        fs::create_dir_all(self.entries_dir()).context("Failed to create cache entries directory")?;
+        let stored = EntryFile { schema_version: ENTRY_SCHEMA_VERSION, key: cache_key.to_string(), entry: entry.clone() };
+        let content = serde_json::to_string_pretty(&stored)
+            .context("Failed to serialize cache entry")?;
+        let temp_file = path.with_extension(format!("json.{}.tmp", uuid::Uuid::new_v4()));
+        fs::write(&temp_file, content)
+            .context("Failed to write cache entry")?;
+        fs::rename(&temp_file, &path)
+            .context("Failed to write cache entry")?;
         Ok(())
     }
     
+    /// Forget an entry, in memory and on disk
+    fn remove_entry(&mut self, cache_key: &str) -> Result<()> {
+        self.entries.remove(cache_key);
+        match fs::remove_file(self.entry_path(cache_key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e).context("Failed to remove cache entry"),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Forget every entry matching `predicate`, returning them
+    fn remove_entries_where(&mut self, predicate: impl Fn(&CacheEntry) -> bool) -> Result<Vec<CacheEntry>> {
+        let keys: Vec<String> = self.entries.iter()
+            .filter(|(_, entry)| predicate(entry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut removed = Vec::new();
+        for key in keys {
+            if let Some(entry) = self.entries.get(&key).cloned() {
+                self.remove_entry(&key)?;
+                removed.push(entry);
+            }
+        }
+        Ok(removed)
+    }
+    
     /// Generate a cache key for a project
     pub fn generate_cache_key(&self, source_path: &str, content_hash: &str, build_options_hash: &str) -> String {
         format!("{}:{}:{}", source_path, content_hash, build_options_hash)
@@ -110,15 +294,21 @@ impl CacheManager {
                         .duration_since(UNIX_EPOCH)
                         .unwrap_or_default()
                         .as_secs();
+                    let entry = entry.clone();
+                    if let Err(e) = self.write_entry(&cache_key, &entry) {
+                        debug!("Failed to update {}: {}", cache_key, e);
+                    }
                 }
                 
+                crate::stats::record_lookup(source_path, Some(&image_name));
                 return Some(image_name);
             } else {
                 // Image no longer exists, remove from cache
-                self.entries.remove(&cache_key);
+                let _ = self.remove_entry(&cache_key);
             }
         }
         
+        crate::stats::record_lookup(source_path, None);
         None
     }
     
@@ -130,6 +320,7 @@ impl CacheManager {
         build_options_hash: &str,
         image_name: &str,
         project_type: &str,
+        build_secs: u64,
     ) -> Result<()> {
         let cache_key = self.generate_cache_key(source_path, content_hash, build_options_hash);
         let now = SystemTime::now()
@@ -145,17 +336,117 @@ impl CacheManager {
             project_type: project_type.to_string(),
             source_path: source_path.to_string(),
             build_options_hash: build_options_hash.to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: Some(crate::run_id::get().to_string()),
+            build_secs: Some(build_secs),
         };
         
+        self.write_entry(&cache_key, &entry)?;
         self.entries.insert(cache_key, entry);
-        self.save_cache()?;
         Ok(())
     }
     
+    /// The cache entry for an image: an exact match, else the newest build of a `:latest`
+    /// or untagged name
+    pub fn entry_for_image(&self, image_name: &str) -> Option<&CacheEntry> {
+        let matches: Vec<&CacheEntry> = self.find_entries(image_name).into_iter()
+            .filter(|entry| entry.image_name == image_name || !image_name.contains(':') || image_name.ends_with(":latest"))
+            .collect();
+        matches.iter()
+            .find(|entry| entry.image_name == image_name)
+            .or_else(|| matches.iter().max_by_key(|entry| entry.created_at))
+            .copied()
+    }
+    
+    /// Record the `package@version` an image's command was pinned to
+    pub fn set_pinned_package(&mut self, image_name: &str, pinned_package: &str) -> Result<()> {
+        let keys: Vec<String> = self.entries.iter()
+            .filter(|(_, entry)| entry.image_name == image_name)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.pinned_package = Some(pinned_package.to_string());
+                let entry = entry.clone();
+                self.write_entry(&key, &entry)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Count a server start from `image_name` (exact, or the newest build of a `:latest` or
+    /// untagged name), returning whether it is a cached image
+    pub fn record_run(&mut self, image_name: &str) -> Result<bool> {
+        let _guard = STORE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = self.load_cache();
+        
+        let Some(image) = self.entry_for_image(image_name).map(|entry| entry.image_name.clone()) else {
+            return Ok(false);
+        };
+        
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let keys: Vec<String> = self.entries.iter()
+            .filter(|(_, entry)| entry.image_name == image)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.run_count += 1;
+                entry.last_run = Some(now);
+                entry.last_accessed = now;
+                let entry = entry.clone();
+                self.write_entry(&key, &entry)?;
+            }
+        }
+        Ok(true)
+    }
+    
+    /// Add (or replace) cache entries, e.g. from an imported archive
+    pub fn insert_entries(&mut self, entries: impl IntoIterator<Item = CacheEntry>) -> Result<usize> {
+        let mut inserted = 0;
+        for entry in entries {
+            let cache_key = self.generate_cache_key(&entry.source_path, &entry.content_hash, &entry.build_options_hash);
+            self.write_entry(&cache_key, &entry)?;
+            self.entries.insert(cache_key, entry);
+            inserted += 1;
+        }
+        Ok(inserted)
+    }
+    
+    /// Where the Dockerfile used to build an image is recorded
+    pub fn dockerfile_path(&self, image_name: &str) -> PathBuf {
+        self.cache_dir.join("dockerfiles").join(format!("{}.Dockerfile", image_name.replace([':', '/'], "_")))
+    }
+    
+    /// Record the exact Dockerfile an image was built from
+    pub fn store_dockerfile(&self, image_name: &str, dockerfile: &str) -> Result<PathBuf> {
+        let path = self.dockerfile_path(image_name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("Failed to create Dockerfile directory")?;
+        }
+        fs::write(&path, dockerfile).context("Failed to record Dockerfile")?;
+        Ok(path)
+    }
+    
+    /// The Dockerfile recorded when an image was built, if any
+    pub fn recorded_dockerfile(&self, image_name: &str) -> Option<String> {
+        fs::read_to_string(self.dockerfile_path(image_name)).ok()
+    }
+    
     /// Check if a finch image exists
     async fn image_exists(&self, image_name: &str) -> bool {
         use tokio::process::Command;
         
+        // A VM still booting in the background can't answer; the build reports why
+        if crate::finch::vm::wait_until_ready().is_err() {
+            return false;
+        }
+        
         let output = Command::new("finch")
             .args(["image", "inspect", image_name])
             .output()
@@ -183,14 +474,10 @@ impl CacheManager {
         }
         
         for key in to_remove {
-            self.entries.remove(&key);
+            self.remove_entry(&key)?;
             removed_count += 1;
         }
         
-        if removed_count > 0 {
-            self.save_cache()?;
-        }
-        
         Ok(removed_count)
     }
     
@@ -213,10 +500,125 @@ impl CacheManager {
         }
     }
     
-    /// Clear all cache entries
+    /// All cache entries
+    pub fn entries(&self) -> impl Iterator<Item = &CacheEntry> {
+        self.entries.values()
+    }
+    
+    /// Image names of the cache entries built from a source
+    pub fn images_for_source(&self, source_path: &str) -> Vec<String> {
+        // Local paths may be given relative to a different directory than at build time
+        let canonical = |path: &str| fs::canonicalize(path).ok();
+        let wanted = canonical(source_path);
+        
+        self.entries.values()
+            .filter(|entry| entry.source_path == source_path
+                || (wanted.is_some() && canonical(&entry.source_path) == wanted))
+            .map(|entry| entry.image_name.clone())
+            .collect()
+    }
+    
+    /// Cache entries for an image (with or without its tag, or as `:latest`) or a source
+    pub fn find_entries(&self, target: &str) -> Vec<&CacheEntry> {
+        let repository = |image: &str| image.split(':').next().unwrap_or(image).to_string();
+        let by_image: Vec<&CacheEntry> = self.entries.values()
+            .filter(|entry| entry.image_name == target
+                || ((!target.contains(':') || target.ends_with(":latest")) && repository(&entry.image_name) == repository(target)))
+            .collect();
+        if !by_image.is_empty() {
+            return by_image;
+        }
+        
+        let images = self.images_for_source(target);
+        self.entries.values()
+            .filter(|entry| images.contains(&entry.image_name))
+            .collect()
+    }
+    
+    /// Remove all cache entries built from a source, returning their image names
+    pub fn remove_entries_for_source(&mut self, source_path: &str) -> Result<Vec<String>> {
+        let removed: Vec<String> = self.remove_entries_where(|entry| entry.source_path == source_path)?
+            .into_iter()
+            .map(|entry| entry.image_name)
+            .collect();
+        for image_name in &removed {
+            let _ = fs::remove_file(self.dockerfile_path(image_name));
+        }
+        Ok(removed)
+    }
+    
+    /// Cache entries a retention policy prunes, as of `now` (seconds since the epoch)
+    pub fn expired_entries(&self, policy: &RetentionPolicy, now: u64) -> Vec<CacheEntry> {
+        if policy.is_empty() {
+            return Vec::new();
+        }
+        
+        let mut by_source: HashMap<&str, Vec<&CacheEntry>> = HashMap::new();
+        for entry in self.entries.values() {
+            by_source.entry(entry.source_path.as_str()).or_default().push(entry);
+        }
+        
+        let mut expired = Vec::new();
+        for mut entries in by_source.into_values() {
+            entries.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| a.image_name.cmp(&b.image_name)));
+            for (rank, entry) in entries.into_iter().enumerate() {
+                let beyond_latest = policy.keep_latest.is_none_or(|keep| rank >= keep);
+                let too_old = policy.max_age_secs.is_none_or(|max_age| now.saturating_sub(entry.last_accessed) > max_age);
+                if beyond_latest && too_old {
+                    expired.push(entry.clone());
+                }
+            }
+        }
+        expired.sort_by(|a, b| a.image_name.cmp(&b.image_name));
+        expired
+    }
+    
+    /// Earlier builds an image supersedes: entries for the same source and build options,
+    /// beyond the `keep_previous` most recent
+    pub fn superseded_entries(&self, image_name: &str, keep_previous: usize) -> Vec<CacheEntry> {
+        let Some(current) = self.entry_for_image(image_name) else {
+            return Vec::new();
+        };
+        let mut previous: Vec<&CacheEntry> = self.entries.values()
+            .filter(|entry| entry.image_name != image_name)
+            .filter(|entry| entry.source_path == current.source_path && entry.build_options_hash == current.build_options_hash)
+            .collect();
+        previous.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| a.image_name.cmp(&b.image_name)));
+        previous.into_iter().skip(keep_previous).cloned().collect()
+    }
+    
+    /// Image references to remove along with some entries: each entry's image, plus
+    /// the `:latest` tag of any repository left with no other cached image
+    pub fn image_refs_to_remove(&self, pruned: &[CacheEntry]) -> Vec<String> {
+        let repository = |image: &str| image.split(':').next().unwrap_or(image).to_string();
+        let mut refs: Vec<String> = pruned.iter().map(|entry| entry.image_name.clone()).collect();
+        for entry in pruned {
+            let base = repository(&entry.image_name);
+            let latest = format!("{}:latest", base);
+            let still_used = self.entries.values()
+                .any(|other| repository(&other.image_name) == base && !pruned.iter().any(|p| p.image_name == other.image_name));
+            if !still_used && !refs.contains(&latest) {
+                refs.push(latest);
+            }
+        }
+        refs
+    }
+    
+    /// Remove the cache entries (and recorded Dockerfiles) of some images
+    pub fn remove_images(&mut self, image_names: &[String]) -> Result<usize> {
+        let removed = self.remove_entries_where(|entry| image_names.contains(&entry.image_name))?;
+        for image_name in image_names {
+            let _ = fs::remove_file(self.dockerfile_path(image_name));
+        }
+        Ok(removed.len())
+    }
+    
+    /// Clear all cache entries, along with the git mirrors repositories are built from
     pub fn clear_cache(&mut self) -> Result<()> {
-        self.entries.clear();
-        self.save_cache()?;
+        for entry in self.remove_entries_where(|_| true)? {
+            let _ = fs::remove_file(self.dockerfile_path(&entry.image_name));
+        }
+        let _ = fs::remove_dir_all(self.cache_dir.join("git"));
         Ok(())
     }
     
@@ -259,8 +661,8 @@ impl CacheManager {
         
         // Trim leading and trailing special characters
         result
-            .trim_start_matches(&['-', '_', '.'])
-            .trim_end_matches(&['-', '_', '.'])
+            .trim_start_matches(['-', '_', '.'])
+            .trim_end_matches(['-', '_', '.'])
             .to_string()
     }
     
@@ -268,12 +670,12 @@ impl CacheManager {
     pub fn extract_identifier(source_path: &str) -> String {
         if source_path.contains("github.com") || source_path.contains("gitlab.com") || source_path.contains(".git") {
             // Git repository - extract repo name
-            if let Some(repo_name) = source_path.split('/').last() {
+            if let Some(repo_name) = source_path.split('/').next_back() {
                 return repo_name.trim_end_matches(".git").to_string();
             }
         } else if source_path.starts_with('/') || source_path.contains("\\") {
             // Local path - extract directory name
-            if let Some(dir_name) = source_path.split(['/', '\\']).last() {
+            if let Some(dir_name) = source_path.split(['/', '\\']).next_back() {
                 return dir_name.to_string();
             }
         } else {
@@ -302,14 +704,64 @@ pub struct CacheStats {
     pub estimated_size_bytes: u64,
 }
 
+/// Part of every build options hash, so images cached by another finch-mcp are rebuilt:
+/// any release may change the generated Dockerfiles. The suffix is bumped when generated
+/// images change shape between releases (e.g. how they take server arguments)
+const CACHE_REVISION: &str = concat!(env!("CARGO_PKG_VERSION"), "/", "entrypoint-args");
+
+/// The monorepo workspace package being built, and a start command or entry point given
+/// with `--cmd` or `--entry`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PackageBuild<'a> {
+    pub package: Option<&'a str>,
+    pub command: Option<&'a str>,
+    pub entry: Option<&'a str>,
+}
+
 /// Generate hash of build options for cache key
-pub fn hash_build_options(host_network: bool, forward_registry: bool, env_vars: &[String]) -> String {
+/// Generate hash of build options for cache key; `forward_timezone` images install `tzdata`
+pub fn hash_build_options(host_network: bool, forward_registry: bool, env_vars: &[String], healthcheck: Option<&str>, forward_timezone: bool, ca_bundle: Option<&CaBundle>) -> String {
+    hash_package_build_options(host_network, forward_registry, env_vars, PackageBuild::default(), healthcheck, false, forward_timezone, ca_bundle)
+}
+
+/// Like `hash_build_options`, also covering the package being built and whether the
+/// project's dev container is the recipe (`--use-devcontainer`)
+#[allow(clippy::too_many_arguments)]
+pub fn hash_package_build_options(host_network: bool, forward_registry: bool, env_vars: &[String], package_build: PackageBuild, healthcheck: Option<&str>, use_devcontainer: bool, forward_timezone: bool, ca_bundle: Option<&CaBundle>) -> String {
     let mut hasher = Sha256::new();
+    hasher.update(CACHE_REVISION.as_bytes());
     hasher.update(host_network.to_string().as_bytes());
     hasher.update(forward_registry.to_string().as_bytes());
     for env_var in env_vars {
         hasher.update(env_var.as_bytes());
     }
+    let PackageBuild { package, command, entry } = package_build;
+    if let Some(package) = package {
+        hasher.update(b"package:");
+        hasher.update(package.as_bytes());
+    }
+    if let Some(command) = command {
+        hasher.update(b"cmd:");
+        hasher.update(command.as_bytes());
+    }
+    if let Some(entry) = entry {
+        hasher.update(b"entry:");
+        hasher.update(entry.as_bytes());
+    }
+    if let Some(fingerprint) = ca_bundle.map(CaBundle::fingerprint) {
+        hasher.update(b"ca:");
+        hasher.update(fingerprint.as_bytes());
+    }
+    if let Some(command) = healthcheck {
+        hasher.update(b"healthcheck:");
+        hasher.update(command.as_bytes());
+    }
+    if use_devcontainer {
+        hasher.update(b"devcontainer");
+    }
+    if forward_timezone {
+        hasher.update(b"tzdata");
+    }
     format!("{:x}", hasher.finalize())[..16].to_string()
 }
 
@@ -326,12 +778,27 @@ mod tests {
     
     #[test]
     fn test_hash_build_options() {
-        let hash1 = hash_build_options(true, false, &[]);
-        let hash2 = hash_build_options(false, true, &[]);
-        let hash3 = hash_build_options(true, false, &[]);
+        let hash1 = hash_build_options(true, false, &[], None, true, None);
+        let hash2 = hash_build_options(false, true, &[], None, true, None);
+        let hash3 = hash_build_options(true, false, &[], None, true, None);
         
         assert_ne!(hash1, hash2);
         assert_eq!(hash1, hash3);
+        let package = |package, command, entry| PackageBuild { package, command, entry };
+        assert_eq!(hash_package_build_options(true, false, &[], PackageBuild::default(), None, false, true, None), hash1);
+        assert_ne!(hash_package_build_options(true, false, &[], package(Some("weather"), None, None), None, false, true, None), hash1);
+        assert_ne!(hash_package_build_options(true, false, &[], package(None, Some("node dist/server.js"), None), None, false, true, None), hash1);
+        assert_ne!(hash_package_build_options(true, false, &[], package(None, None, Some("serve")), None, false, true, None), hash1);
+        assert_ne!(hash_package_build_options(true, false, &[], PackageBuild::default(), None, true, true, None), hash1);
+        assert_ne!(hash_build_options(true, false, &[], None, false, None), hash1);
+        assert_ne!(hash_build_options(true, false, &[], Some(crate::core::healthcheck::PROCESS_CHECK), true, None), hash1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let cert = dir.path().join("corp.pem");
+        std::fs::write(&cert, "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n").unwrap();
+        let bundle = CaBundle::from_files(&[cert]).unwrap();
+        assert_ne!(hash_build_options(true, false, &[], None, true, Some(&bundle)), hash1);
+        assert!(CACHE_REVISION.starts_with(&format!("{}/", env!("CARGO_PKG_VERSION"))));
     }
     
     #[test]
@@ -358,6 +825,223 @@ mod tests {
         assert_eq!(name, "mcp-auto-uvx-time-server-fedcba98");
     }
     
+    #[test]
+    fn test_dockerfile_recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = CacheManager {
+            cache_dir: dir.path().to_path_buf(),
+            entries: HashMap::new(),
+        };
+        manager.store_cache_entry("./demo", "abc", "def", "mcp-demo:abc12345", "NodeJs", 42).unwrap();
+        
+        assert!(manager.recorded_dockerfile("mcp-demo:abc12345").is_none());
+        let path = manager.store_dockerfile("mcp-demo:abc12345", "FROM node:20\n").unwrap();
+        assert_eq!(path, dir.path().join("dockerfiles").join("mcp-demo_abc12345.Dockerfile"));
+        assert_eq!(manager.recorded_dockerfile("mcp-demo:abc12345").unwrap(), "FROM node:20\n");
+        
+        manager.remove_entries_for_source("./demo").unwrap();
+        assert!(!path.exists());
+        assert_eq!(dockerfile_label("FROM node:20"), "dev.finch-mcp.dockerfile=FROM node:20");
+    }
+    
+    #[test]
+    fn test_find_entries() {
+        let entry = |image: &str, source: &str| CacheEntry {
+            content_hash: "abc".to_string(),
+            image_name: image.to_string(),
+            created_at: 0,
+            last_accessed: 0,
+            project_type: "NodeJs".to_string(),
+            source_path: source.to_string(),
+            build_options_hash: "def".to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        };
+        let manager = CacheManager {
+            cache_dir: PathBuf::from("unused"),
+            entries: HashMap::from([
+                ("a".to_string(), entry("mcp-demo:abc12345", "https://github.com/user/demo")),
+                ("b".to_string(), entry("mcp-time:fedcba98", "uvx mcp-server-time")),
+            ]),
+        };
+        
+        let images = |target: &str| -> Vec<String> {
+            manager.find_entries(target).iter().map(|entry| entry.image_name.clone()).collect()
+        };
+        assert_eq!(images("mcp-demo:abc12345"), ["mcp-demo:abc12345"]);
+        assert_eq!(images("mcp-demo"), ["mcp-demo:abc12345"]);
+        assert_eq!(images("mcp-demo:latest"), ["mcp-demo:abc12345"]);
+        assert_eq!(images("uvx mcp-server-time"), ["mcp-time:fedcba98"]);
+        assert!(images("mcp-demo:00000000").is_empty());
+    }
+    
+    #[test]
+    fn test_record_run() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let entry = |image: &str, created_at: u64| CacheEntry {
+            content_hash: image.to_string(),
+            image_name: image.to_string(),
+            created_at,
+            last_accessed: created_at,
+            project_type: "NodeJs".to_string(),
+            source_path: "./demo".to_string(),
+            build_options_hash: "def".to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        };
+        let mut manager = CacheManager {
+            cache_dir: temp_dir.path().to_path_buf(),
+            entries: HashMap::from([
+                ("old".to_string(), entry("mcp-demo:old", 1)),
+                ("new".to_string(), entry("mcp-demo:new", 2)),
+            ]),
+        };
+        
+        assert!(manager.record_run("mcp-demo:old").unwrap());
+        assert!(manager.record_run("mcp-demo:latest").unwrap());
+        assert!(manager.record_run("mcp-demo:latest").unwrap());
+        assert!(!manager.record_run("ghcr.io/acme/other:1.0").unwrap());
+        
+        let runs = |key: &str| manager.entries[key].run_count;
+        assert_eq!((runs("old"), runs("new")), (1, 2));
+        assert!(manager.entries["new"].last_run.is_some());
+        assert_eq!(manager.entries["new"].usage_label(manager.entries["new"].last_run.unwrap() + 7200), "2 runs, last 2h ago");
+        assert_eq!(entry("mcp-demo:unused", 0).usage_label(0), "never run");
+    }
+    
+    #[test]
+    fn test_migrate_legacy_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let entry = CacheEntry {
+            content_hash: "abc".to_string(),
+            image_name: "mcp-demo:abc".to_string(),
+            created_at: 1,
+            last_accessed: 1,
+            project_type: "NodeJs".to_string(),
+            source_path: "./demo".to_string(),
+            build_options_hash: "def".to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        };
+        let legacy = temp_dir.path().join(LEGACY_CACHE_FILE);
+        fs::write(&legacy, serde_json::to_string(&HashMap::from([("./demo:abc:def", &entry)])).unwrap()).unwrap();
+        
+        let load = || {
+            let mut manager = CacheManager { cache_dir: temp_dir.path().to_path_buf(), entries: HashMap::new() };
+            manager.migrate_legacy_file().unwrap();
+            manager.load_cache().unwrap();
+            manager
+        };
+        let manager = load();
+        assert_eq!(manager.entries.get("./demo:abc:def"), Some(&entry));
+        assert!(!legacy.exists());
+        assert!(temp_dir.path().join("finch-mcp-cache.json.migrated").exists());
+        
+        // A corrupt entry file, or one from a newer version, only loses that entry
+        let entries_dir = temp_dir.path().join("entries");
+        fs::write(entries_dir.join("broken.json"), "{\"schemaVersion\": 1, \"key\": ").unwrap();
+        fs::write(
+            entries_dir.join("future.json"),
+            serde_json::json!({"schemaVersion": ENTRY_SCHEMA_VERSION + 1, "key": "future", "entry": entry}).to_string(),
+        ).unwrap();
+        let manager = load();
+        assert_eq!(manager.entries.len(), 1);
+        
+        // A corrupt legacy file is set aside rather than failing every command
+        fs::write(&legacy, "{not json").unwrap();
+        assert_eq!(load().entries.len(), 1);
+        assert!(temp_dir.path().join("finch-mcp-cache.json.corrupted").exists());
+    }
+    
+    #[test]
+    fn test_expired_entries() {
+        let day = 24 * 60 * 60;
+        let entry = |image: &str, source: &str, built_days_ago: u64, used_days_ago: u64| CacheEntry {
+            content_hash: image.to_string(),
+            image_name: image.to_string(),
+            created_at: 100 * day - built_days_ago * day,
+            last_accessed: 100 * day - used_days_ago * day,
+            project_type: "NodeJs".to_string(),
+            source_path: source.to_string(),
+            build_options_hash: "def".to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        };
+        let manager = CacheManager {
+            cache_dir: PathBuf::from("unused"),
+            entries: HashMap::from([
+                ("a1".to_string(), entry("mcp-demo:a1", "./demo", 30, 30)),
+                ("a2".to_string(), entry("mcp-demo:a2", "./demo", 20, 20)),
+                ("a3".to_string(), entry("mcp-demo:a3", "./demo", 1, 1)),
+                ("b1".to_string(), entry("mcp-time:b1", "uvx mcp-server-time", 40, 2)),
+            ]),
+        };
+        let expired = |max_age_days: Option<u64>, keep_latest: Option<usize>| -> Vec<String> {
+            let policy = RetentionPolicy { max_age_secs: max_age_days.map(|days| days * day), keep_latest };
+            manager.expired_entries(&policy, 100 * day).into_iter().map(|entry| entry.image_name).collect()
+        };
+        
+        assert!(expired(None, None).is_empty());
+        assert_eq!(expired(Some(14), None), ["mcp-demo:a1", "mcp-demo:a2"]);
+        assert_eq!(expired(None, Some(1)), ["mcp-demo:a1", "mcp-demo:a2"]);
+        assert_eq!(expired(None, Some(2)), ["mcp-demo:a1"]);
+        assert_eq!(expired(Some(25), Some(1)), ["mcp-demo:a1"]);
+        
+        let pruned = manager.expired_entries(&RetentionPolicy { max_age_secs: Some(14 * day), keep_latest: None }, 100 * day);
+        assert_eq!(manager.image_refs_to_remove(&pruned), ["mcp-demo:a1", "mcp-demo:a2"]);
+        let all_demo: Vec<CacheEntry> = manager.find_entries("./demo").into_iter().cloned().collect();
+        assert!(manager.image_refs_to_remove(&all_demo).contains(&"mcp-demo:latest".to_string()));
+    }
+    
+    #[test]
+    fn test_superseded_entries() {
+        let entry = |image: &str, source: &str, options: &str, created_at: u64| CacheEntry {
+            content_hash: image.to_string(),
+            image_name: image.to_string(),
+            created_at,
+            last_accessed: created_at,
+            project_type: "NodeJs".to_string(),
+            source_path: source.to_string(),
+            build_options_hash: options.to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        };
+        let manager = CacheManager {
+            cache_dir: PathBuf::from("unused"),
+            entries: HashMap::from([
+                ("a1".to_string(), entry("mcp-demo:a1", "./demo", "def", 1)),
+                ("a2".to_string(), entry("mcp-demo:a2", "./demo", "def", 2)),
+                ("a3".to_string(), entry("mcp-demo:a3", "./demo", "def", 3)),
+                ("p1".to_string(), entry("mcp-demo:p1", "./demo", "package", 2)),
+                ("b1".to_string(), entry("mcp-time:b1", "uvx mcp-server-time", "def", 1)),
+            ]),
+        };
+        let superseded = |image: &str, keep: usize| -> Vec<String> {
+            manager.superseded_entries(image, keep).into_iter().map(|entry| entry.image_name).collect()
+        };
+        
+        assert_eq!(superseded("mcp-demo:a3", 0), ["mcp-demo:a2", "mcp-demo:a1"]);
+        assert_eq!(superseded("mcp-demo:a3", 1), ["mcp-demo:a1"]);
+        assert!(superseded("mcp-demo:a3", 2).is_empty());
+        assert!(superseded("mcp-time:b1", 0).is_empty());
+        assert!(superseded("mcp-unknown:x", 0).is_empty());
+    }
+    
     #[test]
     fn test_sanitize_docker_name() {
         assert_eq!(CacheManager::sanitize_docker_name("My-App"), "my-app");
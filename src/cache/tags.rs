@@ -0,0 +1,222 @@
+//! Friendly tags that follow a server's builds (`finch-mcp tag ./my-server mytools:stable`)
+//!
+//! Image names carry a content hash, so a client config naming one breaks on the next
+//! rebuild. A friendly tag is a plain image reference for such configs: it is recorded
+//! in `tags.json` with the source and build options of the image it was given to, and
+//! moved to every later successful build of them.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::cache::{CacheEntry, CacheManager};
+use crate::status;
+
+const TAGS_FILE: &str = "tags.json";
+
+/// What a friendly tag follows, and the image it points at now
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FriendlyTag {
+    pub source: String,
+    pub build_options_hash: String,
+    pub image: String,
+}
+
+/// Every friendly tag, by name
+pub fn load() -> Result<BTreeMap<String, FriendlyTag>> {
+    let path = CacheManager::get_cache_dir()?.join(TAGS_FILE);
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).with_context(|| format!("Invalid tag file {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn save(tags: &BTreeMap<String, FriendlyTag>) -> Result<()> {
+    let dir = CacheManager::get_cache_dir()?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(TAGS_FILE), serde_json::to_string_pretty(tags)?).context("Failed to save tags")
+}
+
+/// Check a tag name, adding `:latest` when it has no tag. `mcp-` names are finch-mcp's
+/// own, which `cleanup` removes, so they can't be used
+pub fn normalize_name(name: &str) -> Result<String> {
+    let (repository, tag) = match name.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+        _ => (name, "latest"),
+    };
+    let parts: Vec<&str> = repository.split('/').collect();
+    // Only a registry host (`localhost:5000/...`) may carry a port
+    let valid_repository = parts.iter().enumerate().all(|(index, part)| {
+        let allowed = if index == 0 && parts.len() > 1 { "._-:" } else { "._-" };
+        part.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+            && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || allowed.contains(c))
+    });
+    let valid_tag = !tag.is_empty()
+        && tag.len() <= 128
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+        && !tag.starts_with(['.', '-']);
+    if !valid_repository || !valid_tag {
+        anyhow::bail!("'{}' is not a valid image name: use lowercase letters, digits, '.', '_', '-' and '/', with an optional :tag", name);
+    }
+    if repository.starts_with("mcp-") {
+        anyhow::bail!("'{}' looks like one of finch-mcp's own image names (mcp-*), which cleanup removes; pick another name", name);
+    }
+    Ok(format!("{}:{}", repository, tag))
+}
+
+/// The newest build of what `target` names (a source or an image), which must come
+/// from a single source
+fn newest_build<'a>(cache_manager: &'a CacheManager, target: &str) -> Result<&'a CacheEntry> {
+    let entries = cache_manager.find_entries(target);
+    let mut sources: Vec<&str> = entries.iter().map(|entry| entry.source_path.as_str()).collect();
+    sources.sort_unstable();
+    sources.dedup();
+    if sources.len() > 1 {
+        anyhow::bail!("{} matches images built from several sources ({}); name the source or the image instead", target, sources.join(", "));
+    }
+    entries.into_iter()
+        .max_by_key(|entry| entry.created_at)
+        .with_context(|| format!("No cached image for {}; build it first with `finch-mcp build {}`", target, target))
+}
+
+/// Point `name` at the newest build of `target`, and keep it on that source's builds
+pub async fn add_tag(target: &str, name: &str) -> Result<(String, FriendlyTag)> {
+    let name = normalize_name(name)?;
+    let cache_manager = CacheManager::new()?;
+    let entry = newest_build(&cache_manager, target)?;
+    tag_image(&entry.image_name, &name).await?;
+
+    let tag = FriendlyTag {
+        source: entry.source_path.clone(),
+        build_options_hash: entry.build_options_hash.clone(),
+        image: entry.image_name.clone(),
+    };
+    let mut tags = load()?;
+    tags.insert(name.clone(), tag.clone());
+    save(&tags)?;
+    Ok((name, tag))
+}
+
+/// Stop `name` following builds and remove it from its image; the image itself stays
+pub async fn remove_tag(name: &str) -> Result<FriendlyTag> {
+    let name = normalize_name(name)?;
+    let mut tags = load()?;
+    let tag = tags.remove(&name).with_context(|| format!("No friendly tag named {}", name))?;
+    save(&tags)?;
+    let output = Command::new("finch").args(["rmi", &name]).output().await.context("Failed to run finch rmi")?;
+    if !output.status.success() {
+        debug!("finch rmi {}: {}", name, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(tag)
+}
+
+/// Move the friendly tags following the source of a new build to it; never fails the build
+pub async fn after_build(image_name: &str) {
+    let followed = match (load(), CacheManager::new()) {
+        (Ok(tags), Ok(cache_manager)) => match cache_manager.entries().find(|entry| entry.image_name == image_name) {
+            Some(entry) => following(&tags, entry),
+            None => return,
+        },
+        (Err(e), _) | (_, Err(e)) => {
+            warn!("Skipping friendly tags: {:#}", e);
+            return;
+        }
+    };
+    if followed.is_empty() {
+        return;
+    }
+
+    let mut moved = Vec::new();
+    for name in followed {
+        match tag_image(image_name, &name).await {
+            Ok(()) => moved.push(name),
+            Err(e) => warn!("Failed to move {} to {}: {:#}", name, image_name, e),
+        }
+    }
+    // Reload so tags added by a concurrent run aren't lost
+    let result = load().and_then(|mut tags| {
+        for name in &moved {
+            if let Some(tag) = tags.get_mut(name) {
+                tag.image = image_name.to_string();
+            }
+        }
+        save(&tags)
+    });
+    match result {
+        Ok(()) if !moved.is_empty() => status!("🏷️  Moved {} to the new build", moved.join(", ")),
+        Ok(()) => {}
+        Err(e) => warn!("Failed to record moved tags: {:#}", e),
+    }
+}
+
+/// Names of the tags that follow the source and build options of `entry`
+fn following(tags: &BTreeMap<String, FriendlyTag>, entry: &CacheEntry) -> Vec<String> {
+    tags.iter()
+        .filter(|(_, tag)| tag.source == entry.source_path && tag.build_options_hash == entry.build_options_hash)
+        .filter(|(_, tag)| tag.image != entry.image_name)
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+async fn tag_image(image_name: &str, name: &str) -> Result<()> {
+    let output = Command::new("finch").args(["tag", image_name, name]).output().await.context("Failed to run finch tag")?;
+    if !output.status.success() {
+        anyhow::bail!("finch tag {} {} failed: {}", image_name, name, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("mytools").unwrap(), "mytools:latest");
+        assert_eq!(normalize_name("mytools:stable").unwrap(), "mytools:stable");
+        assert_eq!(normalize_name("localhost:5000/acme/notes:v1").unwrap(), "localhost:5000/acme/notes:v1");
+        assert_eq!(normalize_name("localhost:5000/acme/notes").unwrap(), "localhost:5000/acme/notes:latest");
+        for bad in ["MyTools", "my tools", "tools:", ":stable", "tools:-x", "mcp-notes:stable", "a//b"] {
+            assert!(normalize_name(bad).is_err(), "{} was accepted", bad);
+        }
+    }
+
+    #[test]
+    fn test_following() {
+        let entry = |image: &str, source: &str, options: &str| CacheEntry {
+            content_hash: image.to_string(),
+            image_name: image.to_string(),
+            created_at: 1,
+            last_accessed: 1,
+            project_type: "NodeJs".to_string(),
+            source_path: source.to_string(),
+            build_options_hash: options.to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        };
+        let tag = |source: &str, options: &str, image: &str| FriendlyTag {
+            source: source.to_string(),
+            build_options_hash: options.to_string(),
+            image: image.to_string(),
+        };
+        let tags = BTreeMap::from([
+            ("mytools:stable".to_string(), tag("./demo", "def", "mcp-demo:a1")),
+            ("mytools:dev".to_string(), tag("./demo", "def", "mcp-demo:a1")),
+            ("weather:latest".to_string(), tag("./demo", "package", "mcp-demo:p1")),
+            ("time:latest".to_string(), tag("uvx mcp-server-time", "def", "mcp-time:b1")),
+        ]);
+
+        assert_eq!(following(&tags, &entry("mcp-demo:a2", "./demo", "def")), ["mytools:dev", "mytools:stable"]);
+        assert!(following(&tags, &entry("mcp-demo:a1", "./demo", "def")).is_empty());
+        assert_eq!(following(&tags, &entry("mcp-demo:p2", "./demo", "package")), ["weather:latest"]);
+    }
+}
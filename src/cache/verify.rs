@@ -0,0 +1,245 @@
+//! `cache verify`: reconcile the cache index with the images Finch actually has
+//!
+//! Images removed with `finch rmi`, or left behind when their entry was dropped, put
+//! the index and Finch out of step until some cleanup happens to notice. `--fix`
+//! forgets entries whose image is gone and removes images nothing points at.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use anyhow::Result;
+use console::style;
+use tokio::process::Command;
+
+use crate::cache::{CacheEntry, CacheManager, ContentHasher};
+use crate::status;
+use crate::utils::git_repository::GitRepository;
+
+/// A mismatch between a cache entry and Finch or the entry's source
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// The entry's image no longer exists; fixed by forgetting the entry
+    MissingImage(CacheEntry),
+    /// The local directory the image was built from no longer exists; fixed by removing
+    /// the image and its entry
+    SourceMissing(CacheEntry),
+    /// An `mcp-*` image the cache has no entry for; fixed by removing the image
+    UnknownImage(String),
+    /// The local directory changed since the image was built. Not a problem: the next
+    /// run rebuilds it
+    SourceChanged(CacheEntry),
+}
+
+impl Issue {
+    /// Whether `--fix` has anything to do for this issue
+    pub fn is_problem(&self) -> bool {
+        !matches!(self, Issue::SourceChanged(_))
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::MissingImage(entry) => write!(f, "{} is cached for {} but the image is gone", entry.image_name, entry.source_path),
+            Issue::SourceMissing(entry) => write!(f, "{} was built from {}, which no longer exists", entry.image_name, entry.source_path),
+            Issue::UnknownImage(image) => write!(f, "{} is not in the cache", image),
+            Issue::SourceChanged(entry) => write!(f, "{} is out of date: {} changed since it was built", entry.image_name, entry.source_path),
+        }
+    }
+}
+
+/// What `cache verify` found and fixed
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub issues: Vec<Issue>,
+    pub fixed: usize,
+}
+
+impl VerifyReport {
+    pub fn problems(&self) -> usize {
+        self.issues.iter().filter(|issue| issue.is_problem()).count()
+    }
+}
+
+/// Compare cache entries with the images Finch has (`repository:tag`). `content_hash`
+/// hashes an existing local source directory
+pub fn find_issues(entries: &[CacheEntry], images: &[String], content_hash: impl Fn(&Path) -> Option<String>) -> Vec<Issue> {
+    let repository = |image: &str| image.split(':').next().unwrap_or(image).to_string();
+    let images: HashSet<&str> = images.iter().map(String::as_str).collect();
+    let mut issues = Vec::new();
+
+    for entry in entries {
+        if !images.contains(entry.image_name.as_str()) {
+            issues.push(Issue::MissingImage(entry.clone()));
+            continue;
+        }
+        // Commands and git URLs aren't directories. A relative path resolves against the
+        // current directory, so one that's missing may just have been built from elsewhere
+        if GitRepository::is_git_url(&entry.source_path) {
+            continue;
+        }
+        let source = Path::new(&entry.source_path);
+        if source.is_dir() {
+            if content_hash(source).is_some_and(|hash| hash != entry.content_hash) {
+                issues.push(Issue::SourceChanged(entry.clone()));
+            }
+        } else if source.is_absolute() && !source.exists() {
+            issues.push(Issue::SourceMissing(entry.clone()));
+        }
+    }
+
+    let cached_repositories: HashSet<String> = entries.iter().map(|entry| repository(&entry.image_name)).collect();
+    let mut unknown: Vec<String> = images
+        .iter()
+        .filter(|image| image.starts_with("mcp-") && !image.ends_with(":<none>"))
+        .filter(|image| !entries.iter().any(|entry| entry.image_name == **image))
+        // `:latest` tags of cached repositories are expected
+        .filter(|image| !(image.ends_with(":latest") && cached_repositories.contains(&repository(image))))
+        .map(|image| image.to_string())
+        .collect();
+    unknown.sort();
+    issues.extend(unknown.into_iter().map(Issue::UnknownImage));
+    issues
+}
+
+/// Check every cache entry against Finch and its source, repairing what's found with `fix`
+pub async fn verify_cache(fix: bool) -> Result<VerifyReport> {
+    let mut cache_manager = CacheManager::new()?;
+    let mut entries: Vec<CacheEntry> = cache_manager.entries().cloned().collect();
+    entries.sort_by(|a, b| a.image_name.cmp(&b.image_name));
+    let images = list_images().await?;
+    let content_hasher = ContentHasher::new();
+    let issues = find_issues(&entries, &images, |path| content_hasher.hash_directory(path).ok());
+
+    let mut report = VerifyReport { checked: entries.len(), issues, fixed: 0 };
+    if !fix {
+        return Ok(report);
+    }
+
+    let mut forget = Vec::new();
+    for issue in &report.issues {
+        let fixed = match issue {
+            Issue::MissingImage(entry) => {
+                forget.push(entry.image_name.clone());
+                true
+            }
+            Issue::SourceMissing(entry) => {
+                let removed = remove_image(&entry.image_name).await;
+                if removed {
+                    // Along with the `:latest` tag if nothing else is cached under it
+                    for reference in cache_manager.image_refs_to_remove(std::slice::from_ref(entry)).iter().skip(1) {
+                        remove_image(reference).await;
+                    }
+                    forget.push(entry.image_name.clone());
+                }
+                removed
+            }
+            Issue::UnknownImage(image) => remove_image(image).await,
+            Issue::SourceChanged(_) => false,
+        };
+        if fixed {
+            report.fixed += 1;
+        }
+    }
+    cache_manager.remove_images(&forget)?;
+    Ok(report)
+}
+
+/// Print a report from `finch-mcp cache verify`
+pub fn print_report(report: &VerifyReport, fix: bool) {
+    for issue in &report.issues {
+        if issue.is_problem() {
+            status!("  {} {}", style("✗").red(), issue);
+        } else {
+            status!("  {} {}", style("ℹ").blue(), issue);
+        }
+    }
+
+    let problems = report.problems();
+    if problems == 0 {
+        status!("{} {} cache entries match Finch", style("✅").green(), report.checked);
+    } else if fix {
+        status!("\n{} Fixed {} of {} problem(s)", style("🔧").green(), report.fixed, problems);
+    } else {
+        status!("\n{} {} problem(s) in {} cache entries; repair them with {}", style("⚠️").yellow(), problems, report.checked, style("finch-mcp cache verify --fix").cyan());
+    }
+}
+
+/// Every image Finch has, as `repository:tag`
+async fn list_images() -> Result<Vec<String>> {
+    let output = Command::new("finch")
+        .args(["images", "--format", "{{.Repository}}:{{.Tag}}"])
+        .output()
+        .await?;
+    // Without the list every entry would look broken, so never guess
+    if !output.status.success() {
+        anyhow::bail!("Failed to list images: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+async fn remove_image(reference: &str) -> bool {
+    Command::new("finch")
+        .args(["rmi", reference])
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(image: &str, source: &str, content_hash: &str) -> CacheEntry {
+        CacheEntry {
+            content_hash: content_hash.to_string(),
+            image_name: image.to_string(),
+            created_at: 0,
+            last_accessed: 0,
+            project_type: "NodeJs".to_string(),
+            source_path: source.to_string(),
+            build_options_hash: "def".to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_find_issues() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().display().to_string();
+        let gone = temp_dir.path().join("deleted").display().to_string();
+
+        let entries = vec![
+            entry("mcp-time:a1", "uvx mcp-server-time ", "a1"),
+            entry("mcp-demo:b2", "https://github.com/user/demo", "b2"),
+            entry("mcp-local:c3", &source, "c3"),
+            entry("mcp-local:old", &source, "old"),
+            entry("mcp-gone:d4", &gone, "d4"),
+        ];
+        let images: Vec<String> = [
+            "mcp-time:a1", "mcp-time:latest", "mcp-local:c3", "mcp-local:old", "mcp-gone:d4",
+            "mcp-stray:e5", "mcp-stray:latest", "mcp-none:<none>", "node:20-alpine",
+        ].iter().map(|image| image.to_string()).collect();
+
+        let issues = find_issues(&entries, &images, |_| Some("c3".to_string()));
+        assert_eq!(issues, [
+            Issue::MissingImage(entries[1].clone()),
+            Issue::SourceChanged(entries[3].clone()),
+            Issue::SourceMissing(entries[4].clone()),
+            Issue::UnknownImage("mcp-stray:e5".to_string()),
+            Issue::UnknownImage("mcp-stray:latest".to_string()),
+        ]);
+        assert_eq!(issues.iter().filter(|issue| issue.is_problem()).count(), 4);
+    }
+}
@@ -1,11 +1,36 @@
-use clap::{Parser, Subcommand, ArgAction};
+use clap::{Args, Parser, Subcommand, ArgAction};
+use clap_complete::ArgValueCompleter;
 use log::debug;
-use std::path::Path;
+use crate::output::{self, OutputMode};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use crate::completions::{complete_target, CompletionShell};
+use crate::events::Events;
 use crate::run::RunOptions;
 use crate::core::auto_containerize::AutoContainerizeOptions;
+use crate::core::ca_certs::CaBundle;
+use crate::core::client_config::{self, ClientEntry, ConfigMode, McpClient};
+use crate::core::healthcheck;
+use crate::core::list::ListSort;
+use crate::core::sbom::SbomFormat;
+use crate::core::scan::{Scanner, Severity};
 use crate::core::git_containerize::{GitContainerizeOptions, LocalContainerizeOptions};
-use crate::utils::git_repository::GitRepository;
+use crate::core::server_manifest::BuildDefaults;
+use crate::utils::allowed_paths::{self, AllowedPath};
+use crate::utils::env_vars::{self, parse_env};
+use crate::utils::mcp_manifest::shell_quote;
+use crate::utils::target_kind::{self, TargetKind};
+use crate::utils::target_scheme::{self, SchemeTarget};
+use crate::utils::offline;
+use crate::utils::units::{parse_age, parse_size};
+use crate::utils::user_map::UserMap;
+use crate::utils::volumes::parse_volume;
+use crate::finch::client::{NetworkMode, StdioRunOptions};
+use crate::finch::pull::PullPolicy;
+use crate::finch::vm::VmResources;
+use crate::mcp::supervisor::RestartPolicy;
 
 /// Finch-MCP - Tool for running MCP servers using Finch containers
 #[derive(Parser, Debug)]
@@ -15,36 +40,193 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
     
-    /// Environment variables to pass to the container
-    /// Format: KEY=VALUE
-    #[arg(short, long, value_name = "KEY=VALUE", global = true)]
+    /// Environment variables to pass to the container (a bare KEY copies the host's value)
+    /// Format: KEY=VALUE or KEY
+    #[arg(short, long, value_name = "KEY=VALUE", global = true, value_parser = parse_env)]
     pub env: Option<Vec<String>>,
     
-    /// Mount volumes in the container
-    /// Format: /host/path:/container/path
-    #[arg(short, long, value_name = "HOST_PATH:CONTAINER_PATH", global = true)]
+    /// Mount volumes in the container (`~` and relative host paths are expanded)
+    /// Format: /host/path:/container/path[:ro]
+    #[arg(short, long, value_name = "HOST_PATH:CONTAINER_PATH", global = true, value_parser = parse_volume)]
     pub volume: Option<Vec<String>>,
     
+    /// Share a host directory with the server at the same path, adding it to `ALLOWED_PATHS`
+    /// Format: /host/path[:ro]
+    #[arg(long, value_name = "PATH[:ro]", global = true)]
+    pub allow_path: Option<Vec<AllowedPath>>,
+    
     /// Enable verbose logging
     #[arg(short = 'V', long, action = ArgAction::Count, global = true)]
     pub verbose: u8,
     
+    /// Only print errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    
+    /// Treat stdin/stdout as an MCP session, as when started by an MCP client (usually detected)
+    #[arg(long, global = true, conflicts_with = "no_mcp")]
+    pub mcp: bool,
+    
+    /// Run interactively even if finch-mcp looks like it was started by an MCP client
+    #[arg(long, global = true)]
+    pub no_mcp: bool,
+    
     /// Force treating target as a container image (usually auto-detected)
     #[arg(long, global = true)]
     pub direct: bool,
     
+    /// When to pull an image target: always, missing (default) or never
+    #[arg(long, global = true, value_enum, value_name = "POLICY")]
+    pub pull: Option<PullPolicy>,
+    
+    /// What the target is, when guessing gets it wrong: image, git, path or command
+    #[arg(long, global = true, value_enum, value_name = "KIND")]
+    pub kind: Option<TargetKind>,
+    
     /// Force rebuild even if cached image exists
     #[arg(short, long, global = true)]
     pub force: bool,
     
-    /// Use host network for package registry access
+    /// Rebuild without finch's layer cache either, re-running every build step (implies --force)
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+    
+    /// Ask git remotes which commit a repository target points at, instead of reusing
+    /// the answer from the last hour
     #[arg(long, global = true)]
+    pub refresh: bool,
+    
+    /// Never use the network: run only cached images, with `--network none`, and fail
+    /// fast on anything that would clone, build or download
+    #[arg(long, global = true, conflicts_with_all = ["force", "no_cache", "refresh", "host_network", "network"])]
+    pub offline: bool,
+    
+    /// Use host network for package registry access
+    #[arg(long, global = true, conflicts_with = "network")]
     pub host_network: bool,
     
+    /// Network mode for the container
+    /// Values: bridge (default), host, none, or the name of a finch network
+    #[arg(long, value_name = "MODE", global = true)]
+    pub network: Option<NetworkMode>,
+    
+    /// Publish a container port to the host (repeatable)
+    /// Format: [IP:]HOST_PORT:CONTAINER_PORT[/PROTOCOL]
+    #[arg(short, long, value_name = "HOST_PORT:CONTAINER_PORT", global = true)]
+    pub publish: Option<Vec<String>>,
+    
+    /// In MCP mode, check that the server answers `initialize` before connecting the client
+    #[arg(long, global = true)]
+    pub health_check: bool,
+    
+    /// Abort if the server writes nothing to stdout within this many seconds of the client's first message
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub startup_timeout: Option<u64>,
+    
+    /// In MCP mode, record every JSON-RPC frame to this NDJSON file (secrets masked)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub trace_mcp: Option<PathBuf>,
+    
+    /// Export clone, hash, detect, build and run spans to this OTLP/HTTP collector, e.g. http://localhost:4318
+    #[arg(long, global = true, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+    
+    /// Keep the image cache here instead of the platform cache directory [env: FINCH_MCP_CACHE_DIR]
+    #[arg(long, global = true, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+    
+    /// Keep build and session logs here instead of the platform state directory [env: FINCH_MCP_DATA_DIR]
+    #[arg(long, global = true, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+    
+    /// Print one line per build step instead of a progress bar (for CI logs)
+    #[arg(long, global = true)]
+    pub plain_progress: bool,
+    
+    /// Kill `finch build` if it runs longer than this many seconds (overrides `build.timeout`)
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub build_timeout: Option<u64>,
+    
+    /// Refuse to build a context larger than this, e.g. 10GB (overrides `build.maxContextSize`)
+    #[arg(long, global = true, value_name = "SIZE", value_parser = parse_size)]
+    pub max_context_size: Option<u64>,
+    
+    /// For `run` and `build`: detect the project and print the Dockerfile, image name,
+    /// cache key and `finch` commands without building or running anything.
+    /// For `gc`: list what would be removed
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    
+    /// Boot the Finch VM in the background while the project is prepared, waiting
+    /// at most this many seconds (default 120) once Finch is needed
+    #[arg(long, global = true, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "120")]
+    pub lazy_vm: Option<u64>,
+    
     /// Forward registry configuration from host
     /// Supports: npmrc, pip.conf, poetry config, requirements.txt with --index-url
     #[arg(long, global = true)]
     pub forward_registry: bool,
+    
+    /// Forward HTTP_PROXY, HTTPS_PROXY and NO_PROXY from the host into builds and containers
+    #[arg(long, global = true)]
+    pub forward_proxy: bool,
+    
+    /// Pass the host's timezone (TZ) and locale into containers (the default)
+    #[arg(long, global = true, overrides_with = "no_forward_timezone")]
+    pub forward_timezone: bool,
+    
+    /// Leave containers in UTC without a locale
+    #[arg(long, global = true, overrides_with = "forward_timezone")]
+    pub no_forward_timezone: bool,
+    
+    /// Run containers as the host user (UID:GID), even with no host directory mounted
+    #[arg(long, global = true, overrides_with = "no_user_map")]
+    pub user_map: bool,
+    
+    /// Run containers as the image's user, even with host directories mounted
+    #[arg(long, global = true, overrides_with = "user_map")]
+    pub no_user_map: bool,
+    
+    /// Trust this PEM CA certificate in builds and containers (repeatable; adds to `caCerts`
+    /// in the user config)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub ca_cert: Option<Vec<String>>,
+    
+    /// The certificates from `--ca-cert` and `caCerts`, read by [`Cli::load_ca_certs`]
+    #[arg(skip)]
+    pub ca_bundle: Option<CaBundle>,
+    
+    /// Add a HEALTHCHECK to built images: a process-alive check, or COMMAND
+    /// (overrides `build.healthcheck`)
+    #[arg(long, global = true, value_name = "COMMAND", num_args = 0..=1, require_equals = true, default_missing_value = "")]
+    pub image_healthcheck: Option<String>,
+    
+    /// Build from the repository's `.devcontainer/devcontainer.json` (its image or Dockerfile
+    /// and setup commands) instead of the generated recipe
+    #[arg(long, global = true)]
+    pub use_devcontainer: bool,
+    
+    /// Restart the server when it crashes: `no` or `on-failure[:MAX]`, with backoff
+    /// (`run` in MCP mode, `serve`, and client entries written by `up`)
+    #[arg(long, global = true, value_name = "POLICY")]
+    pub restart: Option<RestartPolicy>,
+    
+    /// What printed MCP client entries run: the source (rebuilt when it changes), the exact
+    /// image, or its `:latest` tag
+    #[arg(long, value_name = "MODE", global = true)]
+    pub config_mode: Option<ConfigMode>,
+    
+    /// In a Node.js monorepo, the workspace package to build (overrides `build.package`)
+    #[arg(long, value_name = "NAME", global = true)]
+    pub package: Option<String>,
+    
+    /// Start the server with this command instead of the detected one (overrides `runtime.command`)
+    #[arg(long, visible_alias = "entrypoint", value_name = "COMMAND", global = true)]
+    pub cmd: Option<String>,
+    
+    /// Start the server with this bin or script when the project has several
+    #[arg(long, value_name = "NAME", global = true)]
+    pub entry: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -52,11 +234,216 @@ pub enum Commands {
     /// Run an MCP server
     Run {
         /// MCP server image, command, git repository URL, or local directory to run
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        target: String,
+        
+        /// Arguments for the command (when containerizing a command)
+        #[arg(trailing_var_arg = true)]
+        args: Vec<String>,
+        
+        /// Treat the target and arguments as separate servers and combine them into one
+        /// MCP server, namespacing tools as `<server>__<tool>` (use `name=target` to pick names)
+        #[arg(long)]
+        aggregate: bool,
+        
+        /// Start the server in the background and print how to connect to it (see `attach`)
+        #[arg(short, long, conflicts_with = "aggregate")]
+        detach: bool,
+        
+        /// Container name for `--detach` (default: derived from the image name)
+        #[arg(long, requires = "detach")]
+        name: Option<String>,
+    },
+    /// Connect to a server started with `run --detach` over stdio (for MCP client configs)
+    Attach {
+        /// Container name given to (or printed by) `run --detach`
+        name: String,
+    },
+    /// Stop and remove servers started with `run --detach`
+    Stop {
+        /// Container names
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// List containers started by finch-mcp, with their status and health
+    Ps {
+        /// Include stopped containers
+        #[arg(short, long)]
+        all: bool,
+    },
+    /// Serve an MCP server to HTTP clients (streamable HTTP and SSE)
+    Serve {
+        /// MCP server image, command, git repository URL, or local directory to serve
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        target: String,
+        
+        /// Arguments for the command (when containerizing a command)
+        #[arg(trailing_var_arg = true)]
+        args: Vec<String>,
+        
+        /// Port to listen on
+        #[arg(long, default_value = "3333")]
+        port: u16,
+        
+        /// Address to bind to (use 0.0.0.0 to accept remote clients)
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+    },
+    /// Start an MCP server and check that it speaks the protocol correctly
+    Test {
+        /// MCP server image, command, git repository URL, or local directory to test
+        #[arg(add = ArgValueCompleter::new(complete_target))]
         target: String,
         
         /// Arguments for the command (when containerizing a command)
         #[arg(trailing_var_arg = true)]
         args: Vec<String>,
+        
+        /// Seconds to wait for each response from the server
+        #[arg(long, default_value = "30")]
+        timeout: u64,
+        
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Start an MCP server briefly and list the tools it advertises
+    Tools {
+        /// MCP server image, command, git repository URL, or local directory to inspect
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        target: String,
+        
+        /// Arguments for the command (when containerizing a command)
+        #[arg(trailing_var_arg = true)]
+        args: Vec<String>,
+        
+        /// Seconds to wait for each response from the server
+        #[arg(long, default_value = "30")]
+        timeout: u64,
+        
+        /// Print the full tool definitions (including input schemas) as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Time a server's warm (and cold) startup: hashing, cache lookup and the first byte it writes
+    Bench {
+        /// Image, command, git repository URL, or local directory to benchmark (built if needed)
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        target: String,
+        
+        /// Arguments for the command (when containerizing a command)
+        #[arg(trailing_var_arg = true)]
+        args: Vec<String>,
+        
+        /// How many starts to time
+        #[arg(short = 'n', long, default_value = "5", value_parser = clap::value_parser!(u64).range(1..))]
+        iterations: u64,
+        
+        /// Also time cold starts, rebuilding the image before each one
+        #[arg(long)]
+        cold: bool,
+        
+        /// Seconds to wait for the server's first byte
+        #[arg(long, default_value = "60")]
+        timeout: u64,
+        
+        /// Print the timings as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Scan a server's image for known vulnerabilities with Trivy or Grype
+    Scan {
+        /// Image, command, git repository URL, or local directory to scan (built if needed)
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        target: String,
+        
+        /// Arguments for the command (when containerizing a command)
+        #[arg(trailing_var_arg = true)]
+        args: Vec<String>,
+        
+        /// Scanner to use (default: trivy, else grype, whichever is installed)
+        #[arg(long, value_enum)]
+        scanner: Option<Scanner>,
+        
+        /// Exit with an error if any finding is this severe or worse
+        #[arg(long, value_enum, value_name = "SEVERITY")]
+        fail_on: Option<Severity>,
+        
+        /// Print the findings as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Open a shell (or run COMMAND) in a server's container, with the env and volumes `run` uses
+    Exec {
+        /// MCP server image, command, git repository URL, or local directory (built if needed)
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        target: String,
+        
+        /// Arguments for the command (when containerizing a command)
+        args: Vec<String>,
+        
+        /// Command to run instead of `sh`, after `--`
+        #[arg(last = true)]
+        command: Vec<String>,
+    },
+    /// Build every server declared in a servers manifest and print their MCP client configuration
+    Up {
+        /// Servers to bring up (default: all enabled servers)
+        servers: Vec<String>,
+        
+        /// Path to the servers manifest (default: finch-mcp.servers.yaml)
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+        
+        /// Enable servers in this profile (repeatable)
+        #[arg(long)]
+        profile: Vec<String>,
+        
+        /// Build at most this many servers at once
+        #[arg(short, long, value_name = "N", default_value = "1")]
+        jobs: usize,
+    },
+    /// Build and cache servers without running them, e.g. to pre-populate a CI cache
+    Warm {
+        /// Commands, git repositories, local directories or images (default: every
+        /// enabled server in the servers manifest)
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        targets: Vec<String>,
+        
+        /// Also build the servers in this manifest (default: finch-mcp.servers.yaml when no targets are given)
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+        
+        /// Enable manifest servers in this profile (repeatable)
+        #[arg(long)]
+        profile: Vec<String>,
+        
+        /// Build at most this many targets at once
+        #[arg(short, long, value_name = "N", default_value = "1")]
+        jobs: usize,
+    },
+    /// Rebuild cached command and git servers that have a newer package release or commit
+    Upgrade {
+        /// Image or source of the server to upgrade
+        #[arg(required_unless_present = "all", add = ArgValueCompleter::new(complete_target))]
+        target: Option<String>,
+        
+        /// Upgrade every cached command and git server
+        #[arg(long, conflicts_with = "target")]
+        all: bool,
+    },
+    /// Remove images built for servers declared in a servers manifest
+    Down {
+        /// Servers to tear down (default: all enabled servers)
+        servers: Vec<String>,
+        
+        /// Path to the servers manifest (default: finch-mcp.servers.yaml)
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+        
+        /// Include servers in this profile (repeatable)
+        #[arg(long)]
+        profile: Vec<String>,
     },
     /// Build a container image without running it
     Build {
@@ -66,12 +453,51 @@ pub enum Commands {
         /// Arguments for the build
         #[arg(trailing_var_arg = true)]
         args: Vec<String>,
+        
+        /// Generate an SBOM of the built image (default format: cyclonedx)
+        #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "cyclonedx")]
+        sbom: Option<SbomFormat>,
     },
-    /// List finch-mcp containers and images
+    /// List finch-mcp containers, and images with their size, build time and last use
     List {
         /// Show all containers (including stopped ones)
         #[arg(short, long)]
         all: bool,
+        
+        /// Sort images by size (largest first), age (newest first) or last-used (most recent first)
+        #[arg(long, value_enum, value_name = "KEY")]
+        sort: Option<ListSort>,
+    },
+    /// Give a cached server a friendly image name that follows its rebuilds; lists them without arguments
+    #[command(visible_alias = "rename")]
+    Tag {
+        /// Image or source of the server to tag
+        #[arg(requires = "name", add = ArgValueCompleter::new(complete_target))]
+        target: Option<String>,
+        
+        /// Friendly image name, e.g. mytools:stable (default tag: latest)
+        name: Option<String>,
+        
+        /// Remove a friendly name; the image it points at is kept
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["target", "name"])]
+        remove: Option<String>,
+    },
+    /// Log in to a container registry for image targets (wraps `finch login`)
+    Login {
+        /// Registry host, e.g. ghcr.io (default: docker.io)
+        registry: Option<String>,
+        
+        /// Username (prompted for when not given)
+        #[arg(short, long)]
+        username: Option<String>,
+        
+        /// Read the password or token from stdin
+        #[arg(long)]
+        password_stdin: bool,
+        
+        /// List the registries logged in to through finch-mcp
+        #[arg(long, conflicts_with_all = ["registry", "username", "password_stdin"])]
+        list: bool,
     },
     /// Clean up finch-mcp containers and images
     Cleanup {
@@ -87,22 +513,222 @@ pub enum Commands {
         #[arg(short, long)]
         images: bool,
         
+        /// Prune only images not used for this long, e.g. 14d, 12h or 2w
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<u64>,
+        
+        /// Prune all but the N most recently built images of each source
+        #[arg(long, value_name = "N")]
+        keep_latest: Option<usize>,
+        
+        /// Prune untagged finch-mcp images left behind by rebuilds
+        #[arg(long)]
+        dangling: bool,
+        
+        /// Remove scratch directories (clones, build contexts) left by interrupted runs
+        #[arg(long)]
+        workspace: bool,
+        
         /// Force removal without confirmation
         #[arg(short, long)]
         force: bool,
     },
     
+    /// Remove cached images under the retention policy (`gc` in the finch-mcp config)
+    Gc {
+        /// Remove images not used for this long, e.g. 30d (overrides `gc.maxAge`)
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        max_age: Option<u64>,
+        
+        /// Keep at most this many images per source (overrides `gc.maxImagesPerSource`)
+        #[arg(long, value_name = "N")]
+        max_images_per_source: Option<usize>,
+        
+        /// Remove least recently used images until the cache fits, e.g. 10GiB (overrides `gc.maxTotalSize`)
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_total_size: Option<u64>,
+    },
+    
     /// Manage build cache
     Cache {
         #[command(subcommand)]
         action: CacheCommands,
     },
     
+    /// Show where a cached image came from: source, hashes, build log, size and Dockerfile
+    Inspect {
+        /// Image name (tag optional) or the source it was built from
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        target: String,
+        
+        /// Print the details as JSON
+        #[arg(long)]
+        json: bool,
+        
+        /// Print the SBOM stored by `build --sbom` instead
+        #[arg(long, conflicts_with = "json")]
+        sbom: bool,
+    },
+    
+    /// Show locally recorded usage: runs, cache hit rate, build times and startup latency
+    Stats {
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
+        
+        /// Delete the recorded statistics
+        #[arg(long, conflicts_with = "json")]
+        reset: bool,
+    },
+    
     /// Manage build logs
     Logs {
         #[command(subcommand)]
         action: LogCommands,
     },
+    
+    /// Bundle version info, finch and VM status, recent build logs, cache entries and the
+    /// Dockerfile into a redacted tarball to attach to bug reports
+    Report {
+        /// Failing image, directory, repository or command to report on (default: the latest builds)
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        target: Option<String>,
+        
+        /// Number of recent build logs to include
+        #[arg(long, default_value_t = 3)]
+        logs: usize,
+        
+        /// Tarball to write (default: finch-mcp-report-<time>.tar.gz in the current directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// Manage the Finch VM
+    Vm {
+        #[command(subcommand)]
+        action: VmCommands,
+    },
+    
+    /// Install and set up tools finch-mcp depends on
+    Setup {
+        #[command(subcommand)]
+        action: SetupCommands,
+    },
+    
+    /// Manage the finch-mcp binary itself
+    #[command(name = "self")]
+    SelfCommand {
+        #[command(subcommand)]
+        action: SelfCommands,
+    },
+    
+    /// Print a shell completion script, e.g. `source <(finch-mcp completions bash)`
+    Completions {
+        /// Shell to generate the script for
+        shell: CompletionShell,
+    },
+    
+    /// Generate MCP client configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Print (or add to the client's config file) an entry that runs the target, shaped for the client
+    Generate {
+        /// MCP server image, command, git repository URL, or local directory
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        target: String,
+        
+        /// Arguments for the command (when containerizing a command)
+        #[arg(trailing_var_arg = true)]
+        args: Vec<String>,
+        
+        /// Client whose config layout to use
+        #[arg(long, value_enum, default_value_t)]
+        client: McpClient,
+        
+        /// Server name in the config (default: derived from the target)
+        #[arg(long)]
+        name: Option<String>,
+        
+        /// Add the entry to the client's config file, or to PATH, instead of printing it
+        #[arg(long, value_name = "PATH", num_args = 0..=1, require_equals = true)]
+        write: Option<Option<PathBuf>>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VmCommands {
+    /// Show whether the VM exists and is running, and its configured CPUs and memory
+    Status,
+    
+    /// Start the VM, initializing it first if needed
+    Start,
+    
+    /// Stop the VM
+    Stop {
+        /// Stop even if containers are still running
+        #[arg(short, long)]
+        force: bool,
+    },
+    
+    /// Create the VM, optionally with custom CPUs and memory
+    Init {
+        #[command(flatten)]
+        resources: VmResourceArgs,
+    },
+    
+    /// Delete and re-create the VM, e.g. to apply new CPUs or memory (removes all its containers and images)
+    Recreate {
+        #[command(flatten)]
+        resources: VmResourceArgs,
+        
+        /// Recreate without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+/// VM resources, written to Finch's `finch.yaml` before the VM is created
+#[derive(Args, Debug)]
+pub struct VmResourceArgs {
+    /// Number of CPUs for the VM
+    #[arg(long)]
+    pub cpus: Option<u32>,
+    
+    /// Memory for the VM, e.g. 4GiB
+    #[arg(long, value_name = "SIZE")]
+    pub memory: Option<String>,
+}
+
+impl VmResourceArgs {
+    pub fn to_resources(&self) -> VmResources {
+        VmResources { cpus: self.cpus, memory: self.memory.clone() }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SetupCommands {
+    /// Install Finch for this platform (Homebrew, or the verified release installer) and initialize its VM
+    Finch {
+        /// Don't ask for confirmation before downloading and installing
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SelfCommands {
+    /// Replace finch-mcp with the latest GitHub release, after verifying its checksum
+    Update {
+        /// Only check whether a newer release exists
+        #[arg(long)]
+        check: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -123,6 +749,31 @@ pub enum CacheCommands {
         #[arg(short, long, default_value = "7")]
         max_age: u64,
     },
+    
+    /// Bundle cached images and their cache entries into an archive for another machine
+    Export {
+        /// Archive to write, e.g. mcp-cache.tar.gz
+        #[arg(short, long)]
+        output: PathBuf,
+        
+        /// Images or sources to include (default: every cached image)
+        #[arg(add = ArgValueCompleter::new(complete_target))]
+        targets: Vec<String>,
+    },
+    
+    /// Load images and cache entries from an archive made by `cache export`
+    Import {
+        /// Archive to import
+        archive: PathBuf,
+    },
+    
+    /// Check that cached images still exist, find `mcp-*` images the cache doesn't know,
+    /// and find local sources that changed or were deleted
+    Verify {
+        /// Forget entries whose image is gone and remove images nothing uses
+        #[arg(long)]
+        fix: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -147,71 +798,389 @@ pub enum LogCommands {
         max_age: u32,
     },
     
-    /// Show logs directory path
-    Path,
-}
-
-impl Cli {
-    /// Parse CLI arguments and initialize logging
-    pub fn parse_and_init() -> Self {
-        let cli = Self::parse();
-        
-        // Check if we're in MCP STDIO mode
-        let is_mcp_stdio = std::env::var("MCP_STDIO").is_ok();
-        
-        // Initialize logging based on verbosity and MCP mode
-        let log_level = if is_mcp_stdio {
-            // In MCP STDIO mode, suppress all logs except errors
-            log::LevelFilter::Error
+    /// Show logs directory path
+    Path,
+    
+    /// Find build logs by source, outcome and age
+    Search {
+        /// Only builds whose source (URL, path or command) contains this
+        #[arg(long, value_name = "TARGET")]
+        source: Option<String>,
+        
+        /// Only failed builds
+        #[arg(long)]
+        failed: bool,
+        
+        /// Only builds started within this long, e.g. `7d` or `12h`
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        since: Option<u64>,
+        
+        /// Only builds of this run (its ID, or the first characters of it)
+        #[arg(long, value_name = "ID")]
+        run: Option<String>,
+        
+        /// Print the matches as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Stream the output of a running MCP server container
+    Follow {
+        /// Container name or ID, image, or the source it was built from (default: newest container)
+        target: Option<String>,
+    },
+}
+
+/// How finch-mcp decided it was started by an MCP client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum McpDetection {
+    /// `--mcp`
+    Flag,
+    /// `MCP_STDIO` is set
+    McpStdio,
+    /// stdin and stdout are pipes and there is no terminal
+    Pipes,
+    /// The parent process looks like an MCP client
+    ParentProcess,
+    /// `MCP_CLIENT` or `CLAUDE_DESKTOP` is set
+    ClientEnv,
+}
+
+impl McpDetection {
+    /// Whether this is a guess that can be wrong for someone at a terminal
+    pub fn is_heuristic(self) -> bool {
+        matches!(self, McpDetection::ParentProcess | McpDetection::ClientEnv)
+    }
+}
+
+impl std::fmt::Display for McpDetection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            McpDetection::Flag => "--mcp",
+            McpDetection::McpStdio => "MCP_STDIO is set",
+            McpDetection::Pipes => "stdin and stdout are pipes",
+            McpDetection::ParentProcess => "the parent process looks like an MCP client",
+            McpDetection::ClientEnv => "MCP_CLIENT or CLAUDE_DESKTOP is set",
+        })
+    }
+}
+
+/// Note where data from a pre-platform-convention location went
+fn log_migrations(migrated: &[(PathBuf, PathBuf)]) {
+    for (from, to) in migrated {
+        log::info!("Moved {} to {}", from.display(), to.display());
+    }
+}
+
+/// stdin and stdout are pipes or sockets, and none of stdin, stdout or stderr is a terminal
+fn stdio_is_piped() -> bool {
+    if std::io::stdin().is_terminal() || std::io::stdout().is_terminal() || std::io::stderr().is_terminal() {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        let is_pipe = |path: &str| std::fs::metadata(path).is_ok_and(|metadata| {
+            let file_type = metadata.file_type();
+            file_type.is_fifo() || file_type.is_socket()
+        });
+        is_pipe("/dev/stdin") && is_pipe("/dev/stdout")
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+impl Cli {
+    /// Parse CLI arguments, decide the output mode and initialize logging
+    pub fn parse_and_init() -> Self {
+        let cli = Self::parse();
+        // Something may have asked for the mode first; log by the one in effect
+        let wanted = cli.output_mode();
+        let mode = if output::set_output_mode(wanted) { wanted } else { output::mode() };
+        // Before anything opens a log or the cache
+        let migrated = crate::paths::configure(cli.cache_dir.clone(), cli.data_dir.clone());
+        
+        // A guess can be wrong; say how to get the output back when someone is watching
+        if let Some(detection) = cli.mcp_client_detection().filter(|detection| detection.is_heuristic()) {
+            if std::io::stderr().is_terminal() {
+                eprintln!("ℹ️  Running as an MCP server ({}), so status output is off. Pass --no-mcp to run interactively", detection);
+            }
+        }
+        
+        // While stdio carries MCP traffic, log to a per-session file instead
+        if mode == OutputMode::McpSafe {
+            let file_level = if cli.verbose >= 2 { log::LevelFilter::Trace } else { log::LevelFilter::Debug };
+            if let Ok(path) = crate::logging::session::init(file_level) {
+                debug!("finch-mcp v{} run {} session log: {}", env!("CARGO_PKG_VERSION"), crate::run_id::get(), path.display());
+                debug!("CLI arguments: {:?}", cli);
+                log_migrations(&migrated);
+                return cli;
+            }
+        }
+        
+        // Logs always go to stderr, keeping stdout for MCP traffic and reports
+        env_logger::Builder::new()
+            .filter_level(mode.log_level(cli.verbose))
+            .format_timestamp(None)
+            .target(env_logger::Target::Stderr)
+            .init();
+            
+        debug!("finch-mcp v{} run {}", env!("CARGO_PKG_VERSION"), crate::run_id::get());
+        debug!("CLI arguments: {:?}", cli);
+        log_migrations(&migrated);
+        
+        cli
+    }
+    
+    /// How much to print: MCP-safe while stdout carries an MCP session, JSON for `--json`
+    /// reports, then `--quiet` and `-V`
+    pub fn output_mode(&self) -> OutputMode {
+        let mcp_session = self.is_aggregate() || self.is_mcp_client_context();
+        let json = matches!(
+            self.command,
+            Commands::Test { json: true, .. }
+                | Commands::Tools { json: true, .. }
+                | Commands::Scan { json: true, .. }
+                | Commands::Bench { json: true, .. }
+                | Commands::Inspect { json: true, .. }
+                | Commands::Stats { json: true, .. }
+                | Commands::Logs { action: LogCommands::Search { json: true, .. } }
+        );
+        if mcp_session {
+            OutputMode::McpSafe
+        } else if json {
+            OutputMode::Json
+        } else if self.quiet {
+            OutputMode::Silent
+        } else if self.verbose > 0 {
+            OutputMode::Verbose
+        } else {
+            OutputMode::Normal
+        }
+    }
+    
+    /// Get the target string (for run, serve, build and config operations)
+    pub fn get_target(&self) -> &str {
+        match &self.command {
+            Commands::Run { target, .. } => target,
+            Commands::Serve { target, .. } => target,
+            Commands::Test { target, .. } => target,
+            Commands::Tools { target, .. } => target,
+            Commands::Scan { target, .. } => target,
+            Commands::Bench { target, .. } => target,
+            Commands::Exec { target, .. } => target,
+            Commands::Build { target, .. } => target,
+            Commands::Config { action: ConfigCommands::Generate { target, .. } } => target,
+            _ => unreachable!("Only run/serve/test/tools/scan/bench/exec/build/config commands should call this"),
+        }
+    }
+    
+    /// The target of a command that has one, to replace it (e.g. with an unpacked archive)
+    pub fn target_mut(&mut self) -> Option<&mut String> {
+        match &mut self.command {
+            Commands::Run { target, .. }
+            | Commands::Serve { target, .. }
+            | Commands::Test { target, .. }
+            | Commands::Tools { target, .. }
+            | Commands::Scan { target, .. }
+            | Commands::Bench { target, .. }
+            | Commands::Exec { target, .. }
+            | Commands::Build { target, .. }
+            | Commands::Config { action: ConfigCommands::Generate { target, .. } } => Some(target),
+            _ => None,
+        }
+    }
+    
+    /// Read the certificates from `--ca-cert` and the user config's `caCerts`, for the
+    /// options built from this command line
+    pub fn load_ca_certs(&mut self) -> anyhow::Result<()> {
+        self.ca_bundle = CaBundle::load(self.ca_cert.as_deref().unwrap_or_default())?;
+        Ok(())
+    }
+    
+    /// Replace an `npm:`, `pypi:`, `oci:` or `github:` target with the command, image or
+    /// repository it names
+    pub fn apply_target_scheme(&mut self) -> anyhow::Result<()> {
+        let Some(target) = self.target_mut() else {
+            return Ok(());
+        };
+        match target_scheme::parse(target)? {
+            Some(SchemeTarget::Command { command, args }) => {
+                *target = command;
+                if let Some(server_args) = self.args_mut() {
+                    server_args.splice(0..0, args);
+                }
+            }
+            Some(SchemeTarget::Image(image)) => {
+                *target = image;
+                self.direct = true;
+            }
+            Some(SchemeTarget::Git(url)) => *target = url,
+            None => {}
+        }
+        Ok(())
+    }
+    
+    fn args_mut(&mut self) -> Option<&mut Vec<String>> {
+        match &mut self.command {
+            Commands::Run { args, .. }
+            | Commands::Serve { args, .. }
+            | Commands::Test { args, .. }
+            | Commands::Tools { args, .. }
+            | Commands::Scan { args, .. }
+            | Commands::Bench { args, .. }
+            | Commands::Exec { args, .. }
+            | Commands::Build { args, .. }
+            | Commands::Config { action: ConfigCommands::Generate { args, .. } } => Some(args),
+            _ => None,
+        }
+    }
+    
+    /// Get the args (for run, serve, test, tools, scan, bench, exec, build and config operations)
+    pub fn get_args(&self) -> &[String] {
+        match &self.command {
+            Commands::Run { args, .. } => args,
+            Commands::Serve { args, .. } => args,
+            Commands::Test { args, .. } => args,
+            Commands::Tools { args, .. } => args,
+            Commands::Scan { args, .. } => args,
+            Commands::Bench { args, .. } => args,
+            Commands::Exec { args, .. } => args,
+            Commands::Build { args, .. } => args,
+            Commands::Config { action: ConfigCommands::Generate { args, .. } } => args,
+            _ => unreachable!("Only run/serve/test/tools/scan/bench/exec/build/config commands should call this"),
+        }
+    }
+    
+    /// Arguments passed to the server after the image name. A command target has its
+    /// arguments baked into the image, so nothing is appended for it
+    pub fn run_args(&self) -> Vec<String> {
+        if self.is_direct_container() || self.is_git_repository() || self.is_local_directory() {
+            self.get_args().to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+    
+    /// Whether cached images should be ignored: `--force`, or `--no-cache` which also skips
+    /// finch's layer cache
+    pub fn force_rebuild(&self) -> bool {
+        self.force || self.no_cache
+    }
+    
+    /// How long a build may run before it is killed (`--build-timeout`)
+    pub fn build_timeout(&self) -> Option<Duration> {
+        self.build_timeout.map(Duration::from_secs)
+    }
+    
+    /// How long a server may stay silent after the client's first message (`--startup-timeout`)
+    pub fn startup_timeout(&self) -> Option<Duration> {
+        self.startup_timeout.map(Duration::from_secs)
+    }
+    
+    /// The `HEALTHCHECK` command `--image-healthcheck` asks for, if it was given
+    pub fn image_healthcheck_command(&self) -> Option<String> {
+        self.image_healthcheck.as_deref().map(healthcheck::cli_command)
+    }
+    
+    /// Whether git targets and downloaded archives are checked again (`--refresh`, or
+    /// anything forcing a rebuild)
+    pub fn refresh(&self) -> bool {
+        self.refresh || self.force_rebuild()
+    }
+    
+    /// Whether `run --aggregate` was requested
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self.command, Commands::Run { aggregate: true, .. })
+    }
+    
+    /// Whether finch-mcp has to stay between the client and the server, so `run` can't
+    /// hand the session straight to `finch`. Each option that wraps the session belongs here
+    pub fn needs_supervision(&self) -> bool {
+        self.restart.is_some_and(|policy| policy != RestartPolicy::No)
+            || self.health_check
+            || self.trace_mcp.is_some()
+            || self.otlp_endpoint.is_some()
+            || self.startup_timeout.is_some()
+    }
+    
+    /// Resolve the network mode (`--host-network` is shorthand for `--network host`)
+    pub fn network_mode(&self) -> NetworkMode {
+        if self.host_network {
+            NetworkMode::Host
         } else {
-            match cli.verbose {
-                0 => log::LevelFilter::Info,
-                1 => log::LevelFilter::Debug,
-                _ => log::LevelFilter::Trace,
-            }
-        };
-        
-        // Configure logger
-        let mut builder = env_logger::Builder::new();
-        builder.filter_level(log_level)
-               .format_timestamp(None);
-        
-        // In MCP STDIO mode, redirect logs to stderr to avoid polluting stdout
-        if is_mcp_stdio {
-            builder.target(env_logger::Target::Stderr);
+            self.network.clone().unwrap_or_default()
         }
-        
-        builder.init();
-            
-        debug!("CLI arguments: {:?}", cli);
-        
-        cli
     }
     
-    /// Get the target string (for run and build operations)
-    pub fn get_target(&self) -> &str {
-        match &self.command {
-            Commands::Run { target, .. } => target,
-            Commands::Build { target, .. } => target,
-            _ => unreachable!("Only run/build commands should call this"),
+    /// When containers of built images run as the host user (`--user-map`, `--no-user-map`)
+    pub fn user_map_mode(&self) -> UserMap {
+        if self.user_map {
+            UserMap::Always
+        } else if self.no_user_map {
+            UserMap::Never
+        } else {
+            UserMap::Auto
         }
     }
     
-    /// Get the args (for run and build operations)  
-    pub fn get_args(&self) -> &[String] {
-        match &self.command {
-            Commands::Run { args, .. } => args,
-            Commands::Build { args, .. } => args,
-            _ => unreachable!("Only run/build commands should call this"),
+    /// How to run `image_name` for this target. Images run directly never run as the host
+    /// user: only images finch-mcp builds have a working directory any user can write to
+    pub fn stdio_run_options(&self, image_name: String) -> StdioRunOptions {
+        StdioRunOptions {
+            image_name,
+            env_vars: self.env_vars(),
+            volumes: self.volumes(),
+            network: offline::network(self.offline, &self.network_mode()),
+            publish: self.publish.clone().unwrap_or_default(),
+            args: self.run_args(),
+            user_map: if self.is_direct_container() { UserMap::Never } else { self.user_map_mode() },
+            health_check: self.health_check,
+            startup_timeout: self.startup_timeout(),
+            trace_mcp: self.trace_mcp.clone(),
+            forward_proxy: self.forward_proxy,
+            no_forward_timezone: self.no_forward_timezone,
+            restart: self.restart.unwrap_or_default(),
+            events: Events::default(),
         }
     }
     
+    /// `-e` variables plus `ALLOWED_PATHS` for `--allow-path`
+    pub fn env_vars(&self) -> Vec<String> {
+        self.mounts().0
+    }
+    
+    /// `-v` mounts plus the `--allow-path` directories
+    pub fn volumes(&self) -> Vec<String> {
+        self.mounts().1
+    }
+    
+    fn mounts(&self) -> (Vec<String>, Vec<String>) {
+        let mut env_vars = env_vars::resolve(self.env.clone().unwrap_or_default());
+        let mut volumes = self.volume.clone().unwrap_or_default();
+        allowed_paths::apply(self.allow_path.as_deref().unwrap_or_default(), &mut env_vars, &mut volumes);
+        (env_vars, volumes)
+    }
+    
     /// Convert CLI args to RunOptions (for direct container mode)
     pub fn to_run_options(&self) -> RunOptions {
         RunOptions {
             image_name: self.get_target().to_string(),
-            env_vars: self.env.clone(),
-            volumes: self.volume.clone(),
+            env_vars: Some(self.env_vars()),
+            volumes: Some(self.volumes()),
+            network: self.network_mode(),
+            publish: self.publish.clone(),
+            args: self.get_args().to_vec(),
+            pull: self.pull.unwrap_or_default(),
+            offline: self.offline,
+            health_check: self.health_check,
+            startup_timeout: self.startup_timeout(),
+            trace_mcp: self.trace_mcp.clone(),
+            forward_proxy: self.forward_proxy,
+            no_forward_timezone: self.no_forward_timezone,
+            restart: self.restart.unwrap_or_default(),
         }
     }
     
@@ -222,34 +1191,42 @@ impl Cli {
         
         // Check if the command looks like a quoted command string
         // (contains spaces and common command patterns)
-        if args.is_empty() && (
+        let (command, args) = if args.is_empty() && (
             target.contains(" -") || 
             target.contains(" @") || 
             target.starts_with("npx ") ||
             target.starts_with("uvx ")
         ) {
             // Parse as a quoted command string
-            let (parsed_command, parsed_args) = crate::utils::command_parser::parse_command_string(target);
-            AutoContainerizeOptions {
-                command: parsed_command,
-                args: parsed_args,
-                env_vars: self.env.clone().unwrap_or_default(),
-                volumes: self.volume.clone().unwrap_or_default(),
-                host_network: self.host_network,
-                forward_registry: self.forward_registry,
-                force_rebuild: self.force,
-            }
+            crate::utils::command_parser::parse_command_string(target)
         } else {
             // Use as separate command and args
-            AutoContainerizeOptions {
-                command: target.to_string(),
-                args: args.to_vec(),
-                env_vars: self.env.clone().unwrap_or_default(),
-                volumes: self.volume.clone().unwrap_or_default(),
-                host_network: self.host_network,
-                forward_registry: self.forward_registry,
-                force_rebuild: self.force,
-            }
+            (target.to_string(), args.to_vec())
+        };
+        
+        AutoContainerizeOptions {
+            command,
+            args,
+            env_vars: self.env_vars(),
+            volumes: self.volumes(),
+            network: self.network_mode(),
+            publish: self.publish.clone().unwrap_or_default(),
+            forward_registry: self.forward_registry,
+            force_rebuild: self.force_rebuild(),
+            no_cache: self.no_cache,
+            offline: self.offline,
+            user_map: self.user_map_mode(),
+            health_check: self.health_check,
+            startup_timeout: self.startup_timeout(),
+            trace_mcp: self.trace_mcp.clone(),
+            forward_proxy: self.forward_proxy,
+            no_forward_timezone: self.no_forward_timezone,
+            restart: self.restart.unwrap_or_default(),
+            ca_bundle: self.ca_bundle.clone(),
+            events: Events::default(),
+            config_mode: self.config_mode.unwrap_or_default(),
+            image_healthcheck: self.image_healthcheck_command(),
+            build_timeout: self.build_timeout(),
         }
     }
     
@@ -258,11 +1235,33 @@ impl Cli {
         GitContainerizeOptions {
             repo_url: self.get_target().to_string(),
             args: self.get_args().to_vec(),
-            env_vars: self.env.clone().unwrap_or_default(),
-            volumes: self.volume.clone().unwrap_or_default(),
-            host_network: self.host_network,
+            env_vars: self.env_vars(),
+            volumes: self.volumes(),
+            network: self.network_mode(),
+            publish: self.publish.clone().unwrap_or_default(),
             forward_registry: self.forward_registry,
-            force_rebuild: self.force,
+            force_rebuild: self.force_rebuild(),
+            no_cache: self.no_cache,
+            offline: self.offline,
+            refresh: self.refresh(),
+            user_map: self.user_map_mode(),
+            health_check: self.health_check,
+            startup_timeout: self.startup_timeout(),
+            trace_mcp: self.trace_mcp.clone(),
+            forward_proxy: self.forward_proxy,
+            no_forward_timezone: self.no_forward_timezone,
+            restart: self.restart.unwrap_or_default(),
+            ca_bundle: self.ca_bundle.clone(),
+            events: Events::default(),
+            config_mode: self.config_mode.unwrap_or_default(),
+            image_healthcheck: self.image_healthcheck_command(),
+            build_timeout: self.build_timeout(),
+            max_context_size: self.max_context_size,
+            use_devcontainer: self.use_devcontainer,
+            plain_progress: self.plain_progress,
+            package: self.package.clone(),
+            command: self.cmd.clone(),
+            entry: self.entry.clone(),
         }
     }
     
@@ -271,103 +1270,163 @@ impl Cli {
         LocalContainerizeOptions {
             local_path: self.get_target().to_string(),
             args: self.get_args().to_vec(),
-            env_vars: self.env.clone().unwrap_or_default(),
-            volumes: self.volume.clone().unwrap_or_default(),
-            host_network: self.host_network,
+            env_vars: self.env_vars(),
+            volumes: self.volumes(),
+            network: self.network_mode(),
+            publish: self.publish.clone().unwrap_or_default(),
             forward_registry: self.forward_registry,
-            force_rebuild: self.force,
+            force_rebuild: self.force_rebuild(),
+            no_cache: self.no_cache,
+            offline: self.offline,
+            user_map: self.user_map_mode(),
+            health_check: self.health_check,
+            startup_timeout: self.startup_timeout(),
+            trace_mcp: self.trace_mcp.clone(),
+            forward_proxy: self.forward_proxy,
+            no_forward_timezone: self.no_forward_timezone,
+            restart: self.restart.unwrap_or_default(),
+            ca_bundle: self.ca_bundle.clone(),
+            events: Events::default(),
+            config_mode: self.config_mode.unwrap_or_default(),
+            image_healthcheck: self.image_healthcheck_command(),
+            build_timeout: self.build_timeout(),
+            max_context_size: self.max_context_size,
+            use_devcontainer: self.use_devcontainer,
+            plain_progress: self.plain_progress,
+            package: self.package.clone(),
+            command: self.cmd.clone(),
+            entry: self.entry.clone(),
         }
     }
     
-    /// Determine if we should use direct container mode or auto-containerization
-    pub fn is_direct_container(&self) -> bool {
+    /// Settings for the servers built from a manifest (`up`, `warm`, `upgrade`)
+    pub fn build_defaults(&self) -> BuildDefaults {
+        BuildDefaults {
+            force_rebuild: self.force_rebuild(),
+            no_cache: self.no_cache,
+            forward_registry: self.forward_registry,
+            offline: self.offline,
+            refresh: self.refresh(),
+            config_mode: self.config_mode.unwrap_or_default(),
+            image_healthcheck: self.image_healthcheck_command(),
+            build_timeout: self.build_timeout(),
+            max_context_size: self.max_context_size,
+            use_devcontainer: self.use_devcontainer,
+            plain_progress: self.plain_progress,
+            forward_proxy: self.forward_proxy,
+            no_forward_timezone: self.no_forward_timezone,
+            restart: self.restart.unwrap_or_default(),
+            ca_bundle: self.ca_bundle.clone(),
+        }
+    }
+    
+    /// The MCP client entry that runs the target with these options (no image name yet
+    /// unless the target is one)
+    pub fn client_entry(&self) -> ClientEntry {
         let target = self.get_target();
+        let args = self.get_args();
+        let network = self.network_mode();
         
-        // Explicit --direct flag
-        if self.direct {
-            return true;
+        let mut options = Vec::new();
+        for allowed in self.allow_path.iter().flatten() {
+            options.extend(["--allow-path".to_string(), allowed.to_string()]);
+        }
+        if let Some(policy) = self.restart {
+            options.extend(["--restart".to_string(), policy.to_string()]);
         }
         
-        // Auto-detect container image patterns (but not for MCP clients)
-        if Self::looks_like_container_image(target) {
-            return true;
+        let source = if self.is_direct_container() {
+            None
+        } else if self.is_git_repository() || self.is_local_directory() || self.is_archive() {
+            options.extend(client_config::source_options(&network, self.package.as_deref(), self.cmd.as_deref(), self.entry.as_deref()));
+            if self.is_local_directory() || Path::new(target).is_file() {
+                Some(std::path::absolute(target).unwrap_or_else(|_| PathBuf::from(target)).display().to_string())
+            } else {
+                Some(target.to_string())
+            }
+        } else if args.is_empty() {
+            Some(target.to_string())
+        } else {
+            Some(std::iter::once(target).chain(args.iter().map(String::as_str)).map(shell_quote).collect::<Vec<_>>().join(" "))
+        };
+        if source.is_none() || !(self.is_git_repository() || self.is_local_directory() || self.is_archive()) {
+            options.extend(client_config::source_options(&network, None, None, None));
         }
         
-        // Fallback to existing logic
-        !target.contains(' ') && target.contains('/') && !GitRepository::is_git_url(target) && !self.is_local_directory()
+        ClientEntry {
+            image_name: if source.is_none() { target.to_string() } else { String::new() },
+            source,
+            env_vars: env_vars::resolve(self.env.clone().unwrap_or_default()),
+            volumes: self.volume.clone().unwrap_or_default(),
+            options,
+            server_args: self.run_args(),
+        }
+    }
+    
+    /// What the target is: `--kind`, or an image with `--direct`, else guessed from the target
+    pub fn target_kind(&self) -> TargetKind {
+        let kind = self.kind.or(self.direct.then_some(TargetKind::Image));
+        target_kind::resolve(self.get_target(), kind)
+    }
+    
+    /// Determine if we should use direct container mode or auto-containerization
+    pub fn is_direct_container(&self) -> bool {
+        self.target_kind() == TargetKind::Image
     }
     
     /// Check if we're running in an MCP client context
     pub fn is_mcp_client_context(&self) -> bool {
-        Self::is_mcp_client_context_static()
+        self.mcp_client_detection().is_some()
     }
     
-    /// Static version for use in auto-detection
-    fn is_mcp_client_context_static() -> bool {
-        // MCP_STDIO environment variable (set by MCP clients)
+    /// Why stdin/stdout look like an MCP session, if they do. `--mcp`/`--no-mcp` decide;
+    /// then `MCP_STDIO`. The guesses only apply to `run`, the command MCP clients start
+    pub fn mcp_client_detection(&self) -> Option<McpDetection> {
+        if self.no_mcp {
+            return None;
+        }
+        if self.mcp {
+            return Some(McpDetection::Flag);
+        }
         if std::env::var("MCP_STDIO").is_ok() {
-            return true;
+            return Some(McpDetection::McpStdio);
+        }
+        if !matches!(self.command, Commands::Run { detach: false, .. }) {
+            return None;
+        }
+        
+        // Both ends piped and no terminal anywhere: nobody is typing
+        if stdio_is_piped() {
+            return Some(McpDetection::Pipes);
         }
         
         // Check if parent process looks like an MCP client
         if let Ok(parent) = std::env::var("_") {
             if (parent.contains("claude") || parent.contains("mcp")) && !parent.contains("finch-mcp") {
-                return true;
+                return Some(McpDetection::ParentProcess);
             }
         }
         
         // Check for common MCP client environment indicators
-        std::env::var("MCP_CLIENT").is_ok() || 
-        std::env::var("CLAUDE_DESKTOP").is_ok()
-    }
-    
-    /// Check if the target looks like a container image
-    fn looks_like_container_image(target: &str) -> bool {
-        // First check if it's an existing local path - if so, it's NOT a container image
-        let path = std::path::Path::new(target);
-        if path.exists() {
-            return false;
-        }
-        
-        // Standard Docker image patterns
-        if target.contains(':') && !target.starts_with("http") && !target.contains(' ') {
-            // registry.com/namespace/image:tag
-            if target.matches('/').count() >= 1 && target.contains(':') {
-                return true;
-            }
-            
-            // image:tag (simple case)
-            if !target.contains('/') && target.contains(':') && target.split(':').count() == 2 {
-                let parts: Vec<&str> = target.split(':').collect();
-                // Ensure tag doesn't look like a port number or URL
-                if !parts[1].chars().all(|c| c.is_ascii_digit()) && !parts[1].contains('.') {
-                    return true;
-                }
-            }
-        }
-        
-        // Registry patterns (no tag, implies :latest)
-        if target.contains('/') && !target.contains(' ') && !target.contains(':') {
-            // Skip obvious file paths
-            if !target.starts_with('.') && !target.starts_with('/') && !target.starts_with('~') {
-                return true;
-            }
+        if std::env::var("MCP_CLIENT").is_ok() || std::env::var("CLAUDE_DESKTOP").is_ok() {
+            return Some(McpDetection::ClientEnv);
         }
-        
-        false
+        None
     }
     
     /// Determine if the command is a git repository URL
     pub fn is_git_repository(&self) -> bool {
-        let target = self.get_target();
-        GitRepository::is_git_url(target)
+        self.target_kind() == TargetKind::Git
+    }
+    
+    /// Determine if the target is a `.tar.gz`, `.tgz`, `.tar` or `.zip` archive (URL or file)
+    pub fn is_archive(&self) -> bool {
+        self.target_kind() == TargetKind::Archive
     }
     
     /// Determine if the command is a local directory path
     pub fn is_local_directory(&self) -> bool {
-        let target = self.get_target();
-        let path = Path::new(target);
-        path.exists() && path.is_dir()
+        self.target_kind() == TargetKind::Path && Path::new(self.get_target()).is_dir()
     }
 }
 
@@ -387,14 +1446,53 @@ mod tests {
             command: Commands::Run {
                 target: "test-image:latest".to_string(),
                 args: vec![],
+                aggregate: false,
+                detach: false,
+                name: None,
             },
             env: Some(vec!["KEY=VALUE".to_string(), "DEBUG=true".to_string()]),
             volume: Some(vec!["/host:/container".to_string()]),
             verbose: 0,
+            quiet: false,
+            mcp: false,
+            no_mcp: false,
             direct: true,
+            pull: None,
+            kind: None,
             force: false,
+            no_cache: false,
+            refresh: false,
+            offline: false,
             host_network: false,
+            network: None,
+            publish: None,
+            health_check: false,
+            trace_mcp: None,
+            otlp_endpoint: None,
+            cache_dir: None,
+            data_dir: None,
+            startup_timeout: None,
+            plain_progress: false,
+            build_timeout: None,
+            max_context_size: None,
+            dry_run: false,
+            lazy_vm: None,
+            package: None,
+            cmd: None,
+            entry: None,
             forward_registry: false,
+            forward_proxy: false,
+            forward_timezone: false,
+            no_forward_timezone: false,
+            user_map: false,
+            no_user_map: false,
+            ca_cert: None,
+            ca_bundle: None,
+            use_devcontainer: false,
+            image_healthcheck: None,
+            restart: None,
+            config_mode: None,
+            allow_path: None,
         };
         
         let run_options = cli.to_run_options();
@@ -410,14 +1508,53 @@ mod tests {
             command: Commands::Run {
                 target: "uvx".to_string(),
                 args: vec!["mcp-server-time".to_string()],
+                aggregate: false,
+                detach: false,
+                name: None,
             },
             env: Some(vec!["DEBUG=true".to_string()]),
             volume: Some(vec!["/host:/container".to_string()]),
             verbose: 0,
+            quiet: false,
+            mcp: false,
+            no_mcp: false,
             direct: false,
+            pull: None,
+            kind: None,
             force: false,
+            no_cache: false,
+            refresh: false,
+            offline: false,
             host_network: false,
+            network: None,
+            publish: None,
+            health_check: false,
+            trace_mcp: None,
+            otlp_endpoint: None,
+            cache_dir: None,
+            data_dir: None,
+            startup_timeout: None,
+            plain_progress: false,
+            build_timeout: None,
+            max_context_size: None,
+            dry_run: false,
+            lazy_vm: None,
+            package: None,
+            cmd: None,
+            entry: None,
             forward_registry: false,
+            forward_proxy: false,
+            forward_timezone: false,
+            no_forward_timezone: false,
+            user_map: false,
+            no_user_map: false,
+            ca_cert: None,
+            ca_bundle: None,
+            use_devcontainer: false,
+            image_healthcheck: None,
+            restart: None,
+            config_mode: None,
+            allow_path: None,
         };
         
         let options = cli.to_auto_containerize_options();
@@ -435,14 +1572,53 @@ mod tests {
             command: Commands::Run {
                 target: "uvx".to_string(),
                 args: vec![],
+                aggregate: false,
+                detach: false,
+                name: None,
             },
             env: None,
             volume: None,
             verbose: 0,
+            quiet: false,
+            mcp: false,
+            no_mcp: false,
             direct: true,
+            pull: None,
+            kind: None,
             force: false,
+            no_cache: false,
+            refresh: false,
+            offline: false,
             host_network: false,
+            network: None,
+            publish: None,
+            health_check: false,
+            trace_mcp: None,
+            otlp_endpoint: None,
+            cache_dir: None,
+            data_dir: None,
+            startup_timeout: None,
+            plain_progress: false,
+            build_timeout: None,
+            max_context_size: None,
+            dry_run: false,
+            lazy_vm: None,
+            package: None,
+            cmd: None,
+            entry: None,
             forward_registry: false,
+            forward_proxy: false,
+            forward_timezone: false,
+            no_forward_timezone: false,
+            user_map: false,
+            no_user_map: false,
+            ca_cert: None,
+            ca_bundle: None,
+            use_devcontainer: false,
+            image_healthcheck: None,
+            restart: None,
+            config_mode: None,
+            allow_path: None,
         };
         assert!(cli1.is_direct_container());
         
@@ -451,14 +1627,53 @@ mod tests {
             command: Commands::Run {
                 target: "ghcr.io/user/image:tag".to_string(),
                 args: vec![],
+                aggregate: false,
+                detach: false,
+                name: None,
             },
             env: None,
             volume: None,
             verbose: 0,
+            quiet: false,
+            mcp: false,
+            no_mcp: false,
             direct: false,
+            pull: None,
+            kind: None,
             force: false,
+            no_cache: false,
+            refresh: false,
+            offline: false,
             host_network: false,
+            network: None,
+            publish: None,
+            health_check: false,
+            trace_mcp: None,
+            otlp_endpoint: None,
+            cache_dir: None,
+            data_dir: None,
+            startup_timeout: None,
+            plain_progress: false,
+            build_timeout: None,
+            max_context_size: None,
+            dry_run: false,
+            lazy_vm: None,
+            package: None,
+            cmd: None,
+            entry: None,
             forward_registry: false,
+            forward_proxy: false,
+            forward_timezone: false,
+            no_forward_timezone: false,
+            user_map: false,
+            no_user_map: false,
+            ca_cert: None,
+            ca_bundle: None,
+            use_devcontainer: false,
+            image_healthcheck: None,
+            restart: None,
+            config_mode: None,
+            allow_path: None,
         };
         assert!(cli2.is_direct_container());
         
@@ -467,14 +1682,53 @@ mod tests {
             command: Commands::Run {
                 target: "uvx".to_string(),
                 args: vec!["mcp-server-time".to_string()],
+                aggregate: false,
+                detach: false,
+                name: None,
             },
             env: None,
             volume: None,
             verbose: 0,
+            quiet: false,
+            mcp: false,
+            no_mcp: false,
             direct: false,
+            pull: None,
+            kind: None,
             force: false,
+            no_cache: false,
+            refresh: false,
+            offline: false,
             host_network: false,
+            network: None,
+            publish: None,
+            health_check: false,
+            trace_mcp: None,
+            otlp_endpoint: None,
+            cache_dir: None,
+            data_dir: None,
+            startup_timeout: None,
+            plain_progress: false,
+            build_timeout: None,
+            max_context_size: None,
+            dry_run: false,
+            lazy_vm: None,
+            package: None,
+            cmd: None,
+            entry: None,
             forward_registry: false,
+            forward_proxy: false,
+            forward_timezone: false,
+            no_forward_timezone: false,
+            user_map: false,
+            no_user_map: false,
+            ca_cert: None,
+            ca_bundle: None,
+            use_devcontainer: false,
+            image_healthcheck: None,
+            restart: None,
+            config_mode: None,
+            allow_path: None,
         };
         assert!(!cli3.is_direct_container());
     }
@@ -486,14 +1740,53 @@ mod tests {
             command: Commands::Run {
                 target: ".".to_string(),
                 args: vec![],
+                aggregate: false,
+                detach: false,
+                name: None,
             },
             env: None,
             volume: None,
             verbose: 0,
+            quiet: false,
+            mcp: false,
+            no_mcp: false,
             direct: false,
+            pull: None,
+            kind: None,
             force: false,
+            no_cache: false,
+            refresh: false,
+            offline: false,
             host_network: false,
+            network: None,
+            publish: None,
+            health_check: false,
+            trace_mcp: None,
+            otlp_endpoint: None,
+            cache_dir: None,
+            data_dir: None,
+            startup_timeout: None,
+            plain_progress: false,
+            build_timeout: None,
+            max_context_size: None,
+            dry_run: false,
+            lazy_vm: None,
+            package: None,
+            cmd: None,
+            entry: None,
             forward_registry: false,
+            forward_proxy: false,
+            forward_timezone: false,
+            no_forward_timezone: false,
+            user_map: false,
+            no_user_map: false,
+            ca_cert: None,
+            ca_bundle: None,
+            use_devcontainer: false,
+            image_healthcheck: None,
+            restart: None,
+            config_mode: None,
+            allow_path: None,
         };
         assert!(cli1.is_local_directory());
         
@@ -502,14 +1795,53 @@ mod tests {
             command: Commands::Run {
                 target: "./non-existent-dir-12345".to_string(),
                 args: vec![],
+                aggregate: false,
+                detach: false,
+                name: None,
             },
             env: None,
             volume: None,
             verbose: 0,
+            quiet: false,
+            mcp: false,
+            no_mcp: false,
             direct: false,
+            pull: None,
+            kind: None,
             force: false,
+            no_cache: false,
+            refresh: false,
+            offline: false,
             host_network: false,
+            network: None,
+            publish: None,
+            health_check: false,
+            trace_mcp: None,
+            otlp_endpoint: None,
+            cache_dir: None,
+            data_dir: None,
+            startup_timeout: None,
+            plain_progress: false,
+            build_timeout: None,
+            max_context_size: None,
+            dry_run: false,
+            lazy_vm: None,
+            package: None,
+            cmd: None,
+            entry: None,
             forward_registry: false,
+            forward_proxy: false,
+            forward_timezone: false,
+            no_forward_timezone: false,
+            user_map: false,
+            no_user_map: false,
+            ca_cert: None,
+            ca_bundle: None,
+            use_devcontainer: false,
+            image_healthcheck: None,
+            restart: None,
+            config_mode: None,
+            allow_path: None,
         };
         assert!(!cli2.is_local_directory());
         
@@ -518,32 +1850,652 @@ mod tests {
             command: Commands::Run {
                 target: "uvx".to_string(),
                 args: vec![],
+                aggregate: false,
+                detach: false,
+                name: None,
             },
             env: None,
             volume: None,
             verbose: 0,
+            quiet: false,
+            mcp: false,
+            no_mcp: false,
             direct: false,
+            pull: None,
+            kind: None,
             force: false,
+            no_cache: false,
+            refresh: false,
+            offline: false,
             host_network: false,
+            network: None,
+            publish: None,
+            health_check: false,
+            trace_mcp: None,
+            otlp_endpoint: None,
+            cache_dir: None,
+            data_dir: None,
+            startup_timeout: None,
+            plain_progress: false,
+            build_timeout: None,
+            max_context_size: None,
+            dry_run: false,
+            lazy_vm: None,
+            package: None,
+            cmd: None,
+            entry: None,
             forward_registry: false,
+            forward_proxy: false,
+            forward_timezone: false,
+            no_forward_timezone: false,
+            user_map: false,
+            no_user_map: false,
+            ca_cert: None,
+            ca_bundle: None,
+            use_devcontainer: false,
+            image_healthcheck: None,
+            restart: None,
+            config_mode: None,
+            allow_path: None,
         };
         assert!(!cli3.is_local_directory());
     }
     
+    #[test]
+    fn test_network_mode() {
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "--network", "none", "uvx", "mcp-server-time"]).unwrap();
+        assert_eq!(cli.network_mode(), NetworkMode::None);
+        assert_eq!(cli.to_auto_containerize_options().network, NetworkMode::None);
+        
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "--host-network", "uvx"]).unwrap();
+        assert_eq!(cli.network_mode(), NetworkMode::Host);
+        
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "uvx"]).unwrap();
+        assert_eq!(cli.network_mode(), NetworkMode::Bridge);
+        
+        // --host-network and --network are mutually exclusive
+        assert!(Cli::try_parse_from(["finch-mcp", "run", "--host-network", "--network", "none", "uvx"]).is_err());
+        
+        // --offline can't rebuild, re-resolve or pick another network
+        assert!(Cli::try_parse_from(["finch-mcp", "run", "--offline", "uvx"]).unwrap().offline);
+        for flag in ["--force", "--no-cache", "--refresh", "--host-network", "--network=host"] {
+            assert!(Cli::try_parse_from(["finch-mcp", "run", "--offline", flag, "uvx"]).is_err(), "{}", flag);
+        }
+    }
+
+    #[test]
+    fn test_detached_commands() {
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "-d", "--name", "my-time-server", "uvx", "mcp-server-time"]).unwrap();
+        assert!(matches!(&cli.command, Commands::Run { detach: true, name: Some(name), .. } if name == "my-time-server"));
+        assert_eq!(cli.get_args(), ["mcp-server-time"]);
+        assert!(Cli::try_parse_from(["finch-mcp", "run", "--name", "x", "uvx"]).is_err());
+        assert!(Cli::try_parse_from(["finch-mcp", "run", "--detach", "--aggregate", "a", "b"]).is_err());
+
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "attach", "my-time-server"]).unwrap().command, Commands::Attach { .. }));
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "ps", "-a"]).unwrap().command, Commands::Ps { all: true }));
+        assert!(Cli::try_parse_from(["finch-mcp", "stop"]).is_err());
+    }
+
+    #[test]
+    fn test_bench_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "bench", "-n", "10", "--cold", "uvx", "mcp-server-time"]).unwrap();
+        assert!(matches!(&cli.command, Commands::Bench { target, iterations: 10, cold: true, timeout: 60, json: false, .. } if target == "uvx"));
+        assert_eq!(cli.get_args(), ["mcp-server-time"]);
+        assert!(Cli::try_parse_from(["finch-mcp", "bench", "-n", "0", "./server"]).is_err());
+    }
+
+    #[test]
+    fn test_logs_search_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "logs", "search", "--source", "./server", "--failed", "--since", "7d"]).unwrap();
+        assert!(matches!(&cli.command, Commands::Logs { action: LogCommands::Search { source: Some(source), failed: true, since: Some(604800), run: None, json: false } } if source == "./server"));
+        assert_eq!(Cli::try_parse_from(["finch-mcp", "logs", "search", "--json"]).unwrap().output_mode(), OutputMode::Json);
+        assert!(Cli::try_parse_from(["finch-mcp", "logs", "search", "--since", "soon"]).is_err());
+    }
+
+    #[test]
+    fn test_report_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "report", "./server", "--logs", "5", "-o", "bug.tar.gz"]).unwrap();
+        assert!(matches!(&cli.command, Commands::Report { target: Some(target), logs: 5, output: Some(output) }
+            if target == "./server" && output == &PathBuf::from("bug.tar.gz")));
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "report"]).unwrap().command, Commands::Report { target: None, logs: 3, output: None }));
+    }
+
+    #[test]
+    fn test_list_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "list", "--sort", "last-used", "-a"]).unwrap();
+        assert!(matches!(cli.command, Commands::List { all: true, sort: Some(ListSort::LastUsed) }));
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "list"]).unwrap().command, Commands::List { all: false, sort: None }));
+        assert!(Cli::try_parse_from(["finch-mcp", "list", "--sort", "name"]).is_err());
+    }
+
+    #[test]
+    fn test_pull_policy() {
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "--pull", "always", "ghcr.io/acme/notes:latest"]).unwrap();
+        assert_eq!(cli.to_run_options().pull, PullPolicy::Always);
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "ghcr.io/acme/notes:latest"]).unwrap();
+        assert_eq!(cli.to_run_options().pull, PullPolicy::Missing);
+        assert_eq!(Cli::try_parse_from(["finch-mcp", "test", "--pull=never", "mcp-demo:abc"]).unwrap().pull, Some(PullPolicy::Never));
+        assert!(Cli::try_parse_from(["finch-mcp", "run", "--pull", "sometimes", "redis"]).is_err());
+    }
+
+    #[test]
+    fn test_flags_reach_options() {
+        const GIT: &str = "https://github.com/acme/server";
+        /// Arguments after `finch-mcp`, the option they should reach, and its expected value
+        type Row = (&'static [&'static str], fn(&Cli) -> String, &'static str);
+        let rows: &[Row] = &[
+            (&["--offline", "run", GIT], |cli| cli.to_git_containerize_options().offline.to_string(), "true"),
+            (&["--offline", "run", GIT], |cli| cli.to_local_containerize_options().offline.to_string(), "true"),
+            (&["--offline", "run", GIT], |cli| cli.to_auto_containerize_options().offline.to_string(), "true"),
+            (&["--offline", "run", GIT], |cli| cli.to_run_options().offline.to_string(), "true"),
+            (&["--offline", "run", GIT], |cli| cli.build_defaults().offline.to_string(), "true"),
+            (&["run", GIT], |cli| cli.to_git_containerize_options().offline.to_string(), "false"),
+
+            (&["--health-check", "run", "uvx", "mcp-server-time"], |cli| cli.to_auto_containerize_options().health_check.to_string(), "true"),
+            (&["--health-check", "run", "uvx", "mcp-server-time"], |cli| cli.stdio_run_options("mcp-server".to_string()).health_check.to_string(), "true"),
+            (&["run", "--health-check", "ghcr.io/acme/notes:1.0"], |cli| cli.to_run_options().health_check.to_string(), "true"),
+            (&["run", "./server"], |cli| cli.to_local_containerize_options().health_check.to_string(), "false"),
+
+            (&["serve", "--restart", "on-failure:3", GIT], |cli| format!("{:?}", cli.to_git_containerize_options().restart), "OnFailure { max_restarts: Some(3) }"),
+            (&["serve", "--restart", "on-failure:3", GIT], |cli| format!("{:?}", cli.stdio_run_options("mcp-server".to_string()).restart), "OnFailure { max_restarts: Some(3) }"),
+            (&["serve", "--restart", "on-failure:3", GIT], |cli| format!("{:?}", cli.build_defaults().restart), "OnFailure { max_restarts: Some(3) }"),
+            (&["run", "./server"], |cli| format!("{:?}", cli.to_local_containerize_options().restart), "No"),
+
+            (&["--no-forward-timezone", "run", "uvx", "mcp-server-time"], |cli| cli.to_auto_containerize_options().no_forward_timezone.to_string(), "true"),
+            (&["--no-forward-timezone", "run", "uvx", "mcp-server-time"], |cli| cli.stdio_run_options("mcp-server".to_string()).no_forward_timezone.to_string(), "true"),
+            (&["--no-forward-timezone", "run", "uvx", "mcp-server-time"], |cli| cli.build_defaults().no_forward_timezone.to_string(), "true"),
+            (&["run", "./server"], |cli| cli.to_local_containerize_options().no_forward_timezone.to_string(), "false"),
+
+            (&["--forward-proxy", "run", GIT], |cli| cli.to_git_containerize_options().forward_proxy.to_string(), "true"),
+            (&["--forward-proxy", "run", GIT], |cli| cli.stdio_run_options("mcp-server".to_string()).forward_proxy.to_string(), "true"),
+            (&["--forward-proxy", "run", GIT], |cli| cli.build_defaults().forward_proxy.to_string(), "true"),
+            (&["run", "./server"], |cli| cli.to_local_containerize_options().forward_proxy.to_string(), "false"),
+
+            (&["--build-timeout", "600", "build", "./server"], |cli| format!("{:?}", cli.to_local_containerize_options().build_timeout), "Some(600s)"),
+            (&["--build-timeout", "600", "build", "./server"], |cli| format!("{:?}", cli.build_defaults().build_timeout), "Some(600s)"),
+            (&["run", "uvx", "mcp-server-time"], |cli| format!("{:?}", cli.to_auto_containerize_options().build_timeout), "None"),
+
+            (&["--plain-progress", "build", "./server"], |cli| cli.to_local_containerize_options().plain_progress.to_string(), "true"),
+            (&["--plain-progress", "build", "./server"], |cli| cli.build_defaults().plain_progress.to_string(), "true"),
+            (&["run", GIT], |cli| cli.to_git_containerize_options().plain_progress.to_string(), "false"),
+
+            (&["build", "--use-devcontainer", "./server"], |cli| cli.to_local_containerize_options().use_devcontainer.to_string(), "true"),
+            (&["build", "--use-devcontainer", "./server"], |cli| cli.build_defaults().use_devcontainer.to_string(), "true"),
+            (&["run", GIT], |cli| cli.to_git_containerize_options().use_devcontainer.to_string(), "false"),
+
+            (&["--trace-mcp", "trace.ndjson", "run", GIT], |cli| format!("{:?}", cli.to_git_containerize_options().trace_mcp), r#"Some("trace.ndjson")"#),
+            (&["--trace-mcp", "trace.ndjson", "run", GIT], |cli| format!("{:?}", cli.stdio_run_options("mcp-server".to_string()).trace_mcp), r#"Some("trace.ndjson")"#),
+            (&["run", "./server"], |cli| format!("{:?}", cli.to_local_containerize_options().trace_mcp), "None"),
+
+            (&["--startup-timeout", "45", "run", GIT], |cli| format!("{:?}", cli.to_git_containerize_options().startup_timeout), "Some(45s)"),
+            (&["--startup-timeout", "45", "run", GIT], |cli| format!("{:?}", cli.stdio_run_options("mcp-server".to_string()).startup_timeout), "Some(45s)"),
+            (&["run", "./server"], |cli| format!("{:?}", cli.to_local_containerize_options().startup_timeout), "None"),
+
+            (&["build", "--image-healthcheck", "uvx", "mcp-server-time"], |cli| (cli.to_auto_containerize_options().image_healthcheck.as_deref() == Some(healthcheck::PROCESS_CHECK)).to_string(), "true"),
+            (&["build", "--image-healthcheck=curl -fs localhost:8080", "./server"], |cli| format!("{:?}", cli.to_local_containerize_options().image_healthcheck), r#"Some("curl -fs localhost:8080")"#),
+            (&["build", "--image-healthcheck=curl -fs localhost:8080", "./server"], |cli| format!("{:?}", cli.build_defaults().image_healthcheck), r#"Some("curl -fs localhost:8080")"#),
+            (&["build", "./server"], |cli| format!("{:?}", cli.to_local_containerize_options().image_healthcheck), "None"),
+
+            (&["build", "--config-mode", "latest", "./server"], |cli| format!("{:?}", cli.to_local_containerize_options().config_mode), "Latest"),
+            (&["--config-mode", "image", "up"], |cli| format!("{:?}", cli.build_defaults().config_mode), "Image"),
+            (&["build", "uvx", "mcp-server-time"], |cli| format!("{:?}", cli.to_auto_containerize_options().config_mode), "Source"),
+
+            (&["--no-cache", "run", "./server"], |cli| cli.to_local_containerize_options().no_cache.to_string(), "true"),
+            (&["--no-cache", "run", "./server"], |cli| cli.to_local_containerize_options().force_rebuild.to_string(), "true"),
+            (&["--no-cache", "run", "./server"], |cli| cli.build_defaults().no_cache.to_string(), "true"),
+            // --force reuses finch's layer cache
+            (&["--force", "run", "uvx", "mcp-server-time"], |cli| cli.to_auto_containerize_options().no_cache.to_string(), "false"),
+
+            (&["--refresh", "run", GIT], |cli| cli.to_git_containerize_options().refresh.to_string(), "true"),
+            (&["--refresh", "run", GIT], |cli| cli.build_defaults().refresh.to_string(), "true"),
+            // Rebuilding from scratch checks the remote as well
+            (&["--no-cache", "run", GIT], |cli| cli.to_git_containerize_options().refresh.to_string(), "true"),
+            (&["run", GIT], |cli| cli.build_defaults().refresh.to_string(), "false"),
+
+            (&["--user-map", "run", "./server"], |cli| format!("{:?}", cli.to_local_containerize_options().user_map), "Always"),
+            (&["--user-map", "run", "./server"], |cli| format!("{:?}", cli.stdio_run_options("mcp-server".to_string()).user_map), "Always"),
+            (&["--no-user-map", "run", GIT], |cli| format!("{:?}", cli.to_git_containerize_options().user_map), "Never"),
+            (&["run", "uvx", "mcp-server-time"], |cli| format!("{:?}", cli.to_auto_containerize_options().user_map), "Auto"),
+            // Images run directly never run as the host user
+            (&["--user-map", "run", "redis:7-alpine"], |cli| format!("{:?}", cli.stdio_run_options(cli.get_target().to_string()).user_map), "Never"),
+        ];
+
+        for (args, option, expected) in rows {
+            let cli = Cli::try_parse_from(std::iter::once(&"finch-mcp").chain(args.iter())).unwrap();
+            assert_eq!(option(&cli), *expected, "finch-mcp {}", args.join(" "));
+        }
+        assert_eq!(crate::cache::no_cache_build_args(true), ["--no-cache"]);
+        assert!(crate::cache::no_cache_build_args(false).is_empty());
+    }
+
+    #[test]
+    fn test_ca_bundle_reaches_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert = dir.path().join("corp.pem");
+        std::fs::write(&cert, "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n").unwrap();
+        let bundle = CaBundle::from_files(&[cert]).unwrap();
+
+        let mut cli = Cli::try_parse_from(["finch-mcp", "run", "uvx", "mcp-server-time"]).unwrap();
+        assert_eq!(cli.to_auto_containerize_options().ca_bundle, None);
+        cli.ca_bundle = Some(bundle.clone());
+        assert_eq!(cli.to_auto_containerize_options().ca_bundle.as_ref(), Some(&bundle));
+        assert_eq!(cli.to_local_containerize_options().ca_bundle.as_ref(), Some(&bundle));
+        assert_eq!(cli.build_defaults().ca_bundle, Some(bundle));
+    }
+
+    #[test]
+    fn test_login_command() {
+        match Cli::try_parse_from(["finch-mcp", "login", "ghcr.io", "-u", "octocat", "--password-stdin"]).unwrap().command {
+            Commands::Login { registry, username, password_stdin: true, list: false } => {
+                assert_eq!(registry.as_deref(), Some("ghcr.io"));
+                assert_eq!(username.as_deref(), Some("octocat"));
+            }
+            _ => panic!("Expected Login command"),
+        }
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "login"]).unwrap().command, Commands::Login { registry: None, username: None, password_stdin: false, list: false }));
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "login", "--list"]).unwrap().command, Commands::Login { list: true, .. }));
+        assert!(Cli::try_parse_from(["finch-mcp", "login", "--list", "ghcr.io"]).is_err());
+    }
+
+    #[test]
+    fn test_tag_command() {
+        match Cli::try_parse_from(["finch-mcp", "tag", "./my-server", "mytools:stable"]).unwrap().command {
+            Commands::Tag { target, name, remove: None } => {
+                assert_eq!(target.as_deref(), Some("./my-server"));
+                assert_eq!(name.as_deref(), Some("mytools:stable"));
+            }
+            _ => panic!("Expected Tag command"),
+        }
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "rename", "mcp-demo:abc", "demo"]).unwrap().command, Commands::Tag { target: Some(_), name: Some(_), remove: None }));
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "tag", "--remove", "mytools:stable"]).unwrap().command, Commands::Tag { target: None, name: None, remove: Some(_) }));
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "tag"]).unwrap().command, Commands::Tag { target: None, name: None, remove: None }));
+        assert!(Cli::try_parse_from(["finch-mcp", "tag", "./my-server"]).is_err());
+        assert!(Cli::try_parse_from(["finch-mcp", "tag", "./my-server", "mytools", "--remove", "mytools"]).is_err());
+    }
+
+    #[test]
+    fn test_stats_command() {
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "stats"]).unwrap().command, Commands::Stats { json: false, reset: false }));
+        assert_eq!(Cli::try_parse_from(["finch-mcp", "stats", "--json"]).unwrap().output_mode(), OutputMode::Json);
+        assert!(Cli::try_parse_from(["finch-mcp", "stats", "--json", "--reset"]).is_err());
+    }
+
+    #[test]
+    fn test_upgrade_needs_target_or_all() {
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "upgrade", "uvx mcp-server-time"]).unwrap().command, Commands::Upgrade { target: Some(_), all: false }));
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "upgrade", "--all"]).unwrap().command, Commands::Upgrade { target: None, all: true }));
+        assert!(Cli::try_parse_from(["finch-mcp", "upgrade"]).is_err());
+        assert!(Cli::try_parse_from(["finch-mcp", "upgrade", "--all", "mcp-time"]).is_err());
+    }
+
+    #[test]
+    fn test_allow_path() {
+        let cli = Cli::try_parse_from([
+            "finch-mcp", "run", "-e", "DEBUG=1", "-v", "/tmp:/tmp",
+            "--allow-path", "/srv/notes:ro", "--allow-path", "/srv/inbox", "--direct", "mcp/filesystem",
+        ]).unwrap();
+        assert_eq!(cli.env_vars(), ["DEBUG=1", "ALLOWED_PATHS=/srv/notes:/srv/inbox"]);
+        assert_eq!(cli.volumes(), ["/tmp:/tmp", "/srv/notes:/srv/notes:ro", "/srv/inbox:/srv/inbox"]);
+    }
+
+    #[test]
+    fn test_output_mode() {
+        if std::env::var("MCP_STDIO").is_ok() {
+            return;
+        }
+        let mode = |args: &[&str]| Cli::try_parse_from(args).unwrap().output_mode();
+        assert_eq!(mode(&["finch-mcp", "build", "./server"]), OutputMode::Normal);
+        assert_eq!(mode(&["finch-mcp", "build", "-q", "./server"]), OutputMode::Silent);
+        assert_eq!(mode(&["finch-mcp", "build", "-VV", "./server"]), OutputMode::Verbose);
+        assert_eq!(mode(&["finch-mcp", "tools", "--json", "-V", "uvx", "mcp-server-time"]), OutputMode::Json);
+        assert_eq!(mode(&["finch-mcp", "run", "--aggregate", "a", "b"]), OutputMode::McpSafe);
+        assert!(Cli::try_parse_from(["finch-mcp", "build", "-q", "-V", "./server"]).is_err());
+    }
+
+    #[test]
+    fn test_mcp_flags() {
+        let detection = |args: &[&str]| Cli::try_parse_from(args).unwrap().mcp_client_detection();
+        assert_eq!(detection(&["finch-mcp", "build", "--mcp", "./server"]), Some(McpDetection::Flag));
+        assert_eq!(detection(&["finch-mcp", "--no-mcp", "run", "uvx", "mcp-server-time"]), None);
+        if std::env::var("MCP_STDIO").is_err() {
+            assert_eq!(detection(&["finch-mcp", "build", "./server"]), None);
+        }
+        assert!(Cli::try_parse_from(["finch-mcp", "run", "--mcp", "--no-mcp", "uvx"]).is_err());
+        assert!(McpDetection::ParentProcess.is_heuristic() && !McpDetection::Pipes.is_heuristic());
+    }
+
+    #[test]
+    fn test_env_values() {
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "-e", "DEBUG=1", "-e", "FINCH_MCP_TEST_UNSET_VAR", "--direct", "mcp/time"]).unwrap();
+        assert_eq!(cli.env_vars(), ["DEBUG=1"]);
+        assert!(Cli::try_parse_from(["finch-mcp", "run", "-e", "=1", "mcp/time"]).is_err());
+        assert!(Cli::try_parse_from(["finch-mcp", "run", "-e", "API_KEY:abc", "mcp/time"]).is_err());
+    }
+
+    #[test]
+    fn test_exec_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "exec", "uvx", "mcp-server-time", "--", "ls", "-la", "/app"]).unwrap();
+        assert_eq!(cli.get_target(), "uvx");
+        assert_eq!(cli.get_args(), ["mcp-server-time"]);
+        assert!(matches!(&cli.command, Commands::Exec { command, .. } if command == &["ls", "-la", "/app"]));
+
+        let cli = Cli::try_parse_from(["finch-mcp", "exec", "-e", "DEBUG=1", "./server"]).unwrap();
+        assert!(matches!(&cli.command, Commands::Exec { args, command, .. } if args.is_empty() && command.is_empty()));
+    }
+
+    #[test]
+    fn test_serve_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "serve", "--port", "4000", "uvx", "mcp-server-time"]).unwrap();
+        match &cli.command {
+            Commands::Serve { port, bind, .. } => {
+                assert_eq!(*port, 4000);
+                assert_eq!(bind, "127.0.0.1");
+            }
+            _ => panic!("Expected serve command"),
+        }
+        assert_eq!(cli.get_target(), "uvx");
+        assert_eq!(cli.get_args(), ["mcp-server-time"]);
+
+        let cli = Cli::try_parse_from(["finch-mcp", "serve", "./server"]).unwrap();
+        assert!(matches!(cli.command, Commands::Serve { port: 3333, .. }));
+
+        let cli = Cli::try_parse_from(["finch-mcp", "serve", "--restart", "on-failure:5", "./server"]).unwrap();
+        assert_eq!(cli.restart, Some(RestartPolicy::OnFailure { max_restarts: Some(5) }));
+        assert!(Cli::try_parse_from(["finch-mcp", "serve", "--restart", "always", "./server"]).is_err());
+    }
+
+    #[test]
+    fn test_needs_supervision() {
+        // Only these stop `run` from exec'ing finch directly under an MCP client
+        assert!(!Cli::try_parse_from(["finch-mcp", "run", "./server"]).unwrap().needs_supervision());
+        assert!(!Cli::try_parse_from(["finch-mcp", "run", "--restart", "no", "./server"]).unwrap().needs_supervision());
+        for flags in [
+            &["--restart", "on-failure"][..],
+            &["--health-check"],
+            &["--trace-mcp", "trace.ndjson"],
+            &["--otlp-endpoint", "http://localhost:4318"],
+            &["--startup-timeout", "30"],
+        ] {
+            let args = ["finch-mcp", "run"].into_iter().chain(flags.iter().copied()).chain(["./server"]);
+            assert!(Cli::try_parse_from(args).unwrap().needs_supervision(), "{:?}", flags);
+        }
+    }
+
+    #[test]
+    fn test_test_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "test", "--json", "uvx", "mcp-server-time"]).unwrap();
+        assert!(matches!(cli.command, Commands::Test { timeout: 30, json: true, .. }));
+        assert_eq!(cli.get_target(), "uvx");
+        assert_eq!(cli.get_args(), ["mcp-server-time"]);
+    }
+
+    #[test]
+    fn test_tools_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "tools", "--timeout", "5", "https://github.com/user/repo"]).unwrap();
+        assert!(matches!(cli.command, Commands::Tools { timeout: 5, json: false, .. }));
+        assert!(cli.is_git_repository());
+    }
+
+    #[test]
+    fn test_scan_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "scan", "--fail-on", "high", "--scanner", "grype", "uvx", "mcp-server-time"]).unwrap();
+        assert!(matches!(cli.command, Commands::Scan { fail_on: Some(Severity::High), scanner: Some(Scanner::Grype), json: false, .. }));
+        assert_eq!(cli.get_target(), "uvx");
+        assert_eq!(cli.get_args(), ["mcp-server-time"]);
+    }
+
+    #[test]
+    fn test_build_flags() {
+        let cli = Cli::try_parse_from(["finch-mcp", "build", "--plain-progress", "--build-timeout", "600", "./server"]).unwrap();
+        assert!(cli.plain_progress);
+        assert_eq!(cli.build_timeout, Some(600));
+        let cli = Cli::try_parse_from(["finch-mcp", "build", "--max-context-size", "2GB", "./server"]).unwrap();
+        assert_eq!(cli.to_local_containerize_options().max_context_size, Some(2_000_000_000));
+        assert_eq!(cli.build_defaults().max_context_size, Some(2_000_000_000));
+        assert!(Cli::try_parse_from(["finch-mcp", "build", "--max-context-size", "huge", "./server"]).is_err());
+        assert!(!Cli::try_parse_from(["finch-mcp", "build", "./server"]).unwrap().plain_progress);
+        assert!(Cli::try_parse_from(["finch-mcp", "run", "--dry-run", "uvx", "mcp-server-time"]).unwrap().dry_run);
+        assert_eq!(Cli::try_parse_from(["finch-mcp", "run", "--lazy-vm", "--", "uvx", "mcp-server-time"]).unwrap().lazy_vm, Some(120));
+        assert_eq!(Cli::try_parse_from(["finch-mcp", "run", "--lazy-vm=30", "./server"]).unwrap().lazy_vm, Some(30));
+        
+        let cli = Cli::try_parse_from(["finch-mcp", "build", "--sbom", "./server"]).unwrap();
+        assert!(matches!(&cli.command, Commands::Build { sbom: Some(SbomFormat::Cyclonedx), target, .. } if target == "./server"));
+        let cli = Cli::try_parse_from(["finch-mcp", "build", "--sbom=spdx", "./server"]).unwrap();
+        assert!(matches!(cli.command, Commands::Build { sbom: Some(SbomFormat::Spdx), .. }));
+    }
+
+    #[test]
+    fn test_inspect_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "inspect", "mcp-demo:abc12345", "--json"]).unwrap();
+        assert!(matches!(&cli.command, Commands::Inspect { target, json: true, sbom: false } if target == "mcp-demo:abc12345"));
+    }
+
+    #[test]
+    fn test_completions_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "completions", "zsh"]).unwrap();
+        assert!(matches!(cli.command, Commands::Completions { shell: CompletionShell::Zsh }));
+        assert!(Cli::try_parse_from(["finch-mcp", "completions", "tcsh"]).is_err());
+    }
+
+    #[test]
+    fn test_target_schemes() {
+        let mut cli = Cli::try_parse_from(["finch-mcp", "run", "npm:@modelcontextprotocol/server-filesystem@0.6.2", "/data"]).unwrap();
+        cli.apply_target_scheme().unwrap();
+        assert_eq!(cli.get_target(), "npx");
+        assert_eq!(cli.get_args(), ["-y", "@modelcontextprotocol/server-filesystem@0.6.2", "/data"]);
+        assert!(!cli.is_direct_container());
+        
+        let mut cli = Cli::try_parse_from(["finch-mcp", "run", "oci:mcp/time"]).unwrap();
+        cli.apply_target_scheme().unwrap();
+        assert_eq!(cli.get_target(), "mcp/time");
+        assert!(cli.is_direct_container());
+        
+        let mut cli = Cli::try_parse_from(["finch-mcp", "build", "git+https://git.example.com/team/server"]).unwrap();
+        cli.apply_target_scheme().unwrap();
+        assert!(cli.is_git_repository());
+        
+        let mut cli = Cli::try_parse_from(["finch-mcp", "run", "pypi:mcp-server-time>=0.6"]).unwrap();
+        assert!(cli.apply_target_scheme().is_err());
+    }
+    
+    #[test]
+    fn test_archive_targets() {
+        let url = "https://github.com/user/server/releases/download/v1.2.0/server-v1.2.0.tar.gz";
+        let cli = Cli::try_parse_from(["finch-mcp", "run", url]).unwrap();
+        assert!(cli.is_archive());
+        assert!(!cli.is_git_repository());
+        assert!(!cli.is_direct_container());
+        
+        let cli = Cli::try_parse_from(["finch-mcp", "config", "generate", url]).unwrap();
+        assert_eq!(cli.client_entry().source.as_deref(), Some(url));
+        
+        let mut cli = Cli::try_parse_from(["finch-mcp", "build", url]).unwrap();
+        *cli.target_mut().unwrap() = "./server".to_string();
+        assert_eq!(cli.get_target(), "./server");
+    }
+    
+    #[test]
+    fn test_config_generate_command() {
+        let cli = Cli::try_parse_from([
+            "finch-mcp", "-e", "API_KEY=abc", "--restart", "on-failure", "config", "generate",
+            "--client", "vscode", "--write", "npx", "--", "-y", "@modelcontextprotocol/server-time",
+        ]).unwrap();
+        match &cli.command {
+            Commands::Config { action: ConfigCommands::Generate { client: McpClient::Vscode, write: Some(None), name: None, .. } } => {}
+            other => panic!("unexpected command: {:?}", other),
+        }
+        
+        let entry = cli.client_entry();
+        assert_eq!(entry.source.as_deref(), Some("npx -y @modelcontextprotocol/server-time"));
+        assert!(entry.server_args.is_empty());
+        assert_eq!(entry.args(ConfigMode::Source), [
+            "run", "-e", "API_KEY", "--restart", "on-failure", "npx -y @modelcontextprotocol/server-time",
+        ]);
+        
+        let cli = Cli::try_parse_from([
+            "finch-mcp", "config", "generate", "--write=mcp.json", "ghcr.io/acme/mcp-search:1.0", "--", "--verbose",
+        ]).unwrap();
+        let entry = cli.client_entry();
+        assert_eq!(entry.source, None);
+        assert_eq!(entry.image_name, "ghcr.io/acme/mcp-search:1.0");
+        assert_eq!(entry.server_args, ["--verbose"]);
+        assert!(matches!(cli.command, Commands::Config { action: ConfigCommands::Generate { client: McpClient::Generic, write: Some(Some(_)), .. } }));
+    }
+    
+    #[test]
+    fn test_rebuild_flags() {
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "--force", "./server"]).unwrap();
+        assert!(cli.force_rebuild() && !cli.no_cache);
+        
+        let cli = Cli::try_parse_from(["finch-mcp", "build", "--no-cache", "./server"]).unwrap();
+        assert!(cli.force_rebuild() && !cli.force);
+        assert!(cli.to_local_containerize_options().force_rebuild);
+        
+        assert!(!Cli::try_parse_from(["finch-mcp", "build", "./server"]).unwrap().force_rebuild());
+    }
+    
+    #[test]
+    fn test_vm_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "vm", "recreate", "--cpus", "4", "--memory", "8GiB", "--force"]).unwrap();
+        match cli.command {
+            Commands::Vm { action: VmCommands::Recreate { resources, force: true } } => {
+                assert_eq!(resources.to_resources(), VmResources { cpus: Some(4), memory: Some("8GiB".to_string()) });
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "vm", "status"]).unwrap().command, Commands::Vm { action: VmCommands::Status }));
+    }
+
+    #[test]
+    fn test_cleanup_prune_options() {
+        let cli = Cli::try_parse_from(["finch-mcp", "cleanup", "--older-than", "14d", "--keep-latest", "2", "--dangling"]).unwrap();
+        assert!(matches!(cli.command, Commands::Cleanup { older_than: Some(1_209_600), keep_latest: Some(2), dangling: true, .. }));
+        assert!(Cli::try_parse_from(["finch-mcp", "cleanup", "--older-than", "14"]).is_err());
+        assert!(matches!(Cli::try_parse_from(["finch-mcp", "cleanup", "--workspace"]).unwrap().command, Commands::Cleanup { workspace: true, all: false, .. }));
+    }
+
+    #[test]
+    fn test_cmd_option() {
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "./server", "--cmd", "node dist/server.js"]).unwrap();
+        assert_eq!(cli.to_local_containerize_options().command.as_deref(), Some("node dist/server.js"));
+        let cli = Cli::try_parse_from(["finch-mcp", "build", "--entrypoint", "node bin/cli.js", "https://github.com/acme/server"]).unwrap();
+        assert_eq!(cli.to_git_containerize_options().command.as_deref(), Some("node bin/cli.js"));
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "--entry", "serve", "./server"]).unwrap();
+        assert_eq!(cli.to_local_containerize_options().entry.as_deref(), Some("serve"));
+    }
+
+    #[test]
+    fn test_server_args_after_separator() {
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "ghcr.io/acme/server:1.0", "--", "--port", "8080", "--flag", "has space"]).unwrap();
+        assert_eq!(cli.run_args(), ["--port", "8080", "--flag", "has space"]);
+        assert_eq!(cli.to_run_options().args, cli.run_args());
+
+        // A command target's arguments are baked into its image
+        let cli = Cli::try_parse_from(["finch-mcp", "run", "uvx", "mcp-server-time", "--local-timezone", "UTC"]).unwrap();
+        assert!(cli.run_args().is_empty());
+    }
+
+    #[test]
+    fn test_package_option() {
+        let cli = Cli::try_parse_from(["finch-mcp", "build", "./servers", "--package", "@acme/weather"]).unwrap();
+        assert_eq!(cli.to_local_containerize_options().package.as_deref(), Some("@acme/weather"));
+    }
+
+    #[test]
+    fn test_cache_export_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "cache", "export", "-o", "cache.tar.gz", "mcp-demo", "./server"]).unwrap();
+        match cli.command {
+            Commands::Cache { action: CacheCommands::Export { output, targets } } => {
+                assert_eq!(output, PathBuf::from("cache.tar.gz"));
+                assert_eq!(targets, ["mcp-demo", "./server"]);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_warm_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "warm", "uvx mcp-server-time", "./server", "-j", "4"]).unwrap();
+        assert!(matches!(&cli.command, Commands::Warm { targets, file: None, jobs: 4, .. } if targets == &["uvx mcp-server-time", "./server"]));
+    }
+
+    #[test]
+    fn test_gc_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "gc", "--max-age", "30d", "--max-total-size", "10GiB", "--dry-run"]).unwrap();
+        assert!(cli.dry_run);
+        assert!(matches!(cli.command, Commands::Gc { max_age: Some(2_592_000), max_images_per_source: None, max_total_size: Some(10_737_418_240) }));
+    }
+
+    #[test]
+    fn test_setup_finch_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "setup", "finch", "--yes"]).unwrap();
+        assert!(matches!(cli.command, Commands::Setup { action: SetupCommands::Finch { yes: true } }));
+        let cli = Cli::try_parse_from(["finch-mcp", "self", "update", "--check"]).unwrap();
+        assert!(matches!(cli.command, Commands::SelfCommand { action: SelfCommands::Update { check: true } }));
+    }
+
+    #[test]
+    fn test_logs_follow_command() {
+        let cli = Cli::try_parse_from(["finch-mcp", "logs", "follow", "./my-server"]).unwrap();
+        assert!(matches!(&cli.command, Commands::Logs { action: LogCommands::Follow { target: Some(target) } } if target == "./my-server"));
+
+        let cli = Cli::try_parse_from(["finch-mcp", "logs", "follow"]).unwrap();
+        assert!(matches!(cli.command, Commands::Logs { action: LogCommands::Follow { target: None } }));
+    }
+
     #[test]
     fn test_to_local_containerize_options() {
         let cli = Cli {
             command: Commands::Run {
                 target: "./test-dir".to_string(),
                 args: vec!["arg1".to_string(), "arg2".to_string()],
+                aggregate: false,
+                detach: false,
+                name: None,
             },
             env: Some(vec!["KEY=VALUE".to_string()]),
             volume: Some(vec!["/host:/container".to_string()]),
             verbose: 0,
+            quiet: false,
+            mcp: false,
+            no_mcp: false,
             direct: false,
+            pull: None,
+            kind: None,
             force: false,
+            no_cache: false,
+            refresh: false,
+            offline: false,
             host_network: false,
+            network: None,
+            publish: None,
+            health_check: false,
+            trace_mcp: None,
+            otlp_endpoint: None,
+            cache_dir: None,
+            data_dir: None,
+            startup_timeout: None,
+            plain_progress: false,
+            build_timeout: None,
+            max_context_size: None,
+            dry_run: false,
+            lazy_vm: None,
+            package: None,
+            cmd: None,
+            entry: None,
             forward_registry: false,
+            forward_proxy: false,
+            forward_timezone: false,
+            no_forward_timezone: false,
+            user_map: false,
+            no_user_map: false,
+            ca_cert: None,
+            ca_bundle: None,
+            use_devcontainer: false,
+            image_healthcheck: None,
+            restart: None,
+            config_mode: None,
+            allow_path: None,
         };
         
         let options = cli.to_local_containerize_options();
@@ -0,0 +1,86 @@
+//! Shell completions, including cached image names for targets
+//!
+//! `finch-mcp completions <shell>` prints a script that calls back into finch-mcp
+//! (with `COMPLETE=<shell>` set) on every completion request, so target completions
+//! reflect the image cache at the time of typing.
+
+use std::ffi::OsStr;
+use std::io::Write;
+
+use clap::ValueEnum;
+use clap_complete::env::{Bash, EnvCompleter, Fish, Powershell, Zsh};
+use clap_complete::engine::ValueCompleter;
+use clap_complete::{CompletionCandidate, PathCompleter};
+
+use crate::cache::CacheManager;
+
+/// Environment variable that switches finch-mcp into completion mode
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Shells `finch-mcp completions` can generate a script for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl CompletionShell {
+    fn completer(self) -> &'static dyn EnvCompleter {
+        match self {
+            Self::Bash => &Bash,
+            Self::Zsh => &Zsh,
+            Self::Fish => &Fish,
+            Self::Powershell => &Powershell,
+        }
+    }
+}
+
+/// Write the completion script for a shell
+pub fn write_script(shell: CompletionShell, buf: &mut dyn Write) -> std::io::Result<()> {
+    let bin = env!("CARGO_PKG_NAME");
+    shell.completer().write_registration(COMPLETE_VAR, bin, bin, bin, buf)
+}
+
+/// Complete a target: cached image names and their `:latest` aliases, then local directories
+pub fn complete_target(current: &OsStr) -> Vec<CompletionCandidate> {
+    let prefix = current.to_string_lossy();
+    let mut candidates: Vec<CompletionCandidate> = cached_images()
+        .into_iter()
+        .filter(|(image, _)| image.starts_with(prefix.as_ref()))
+        .map(|(image, source)| CompletionCandidate::new(image).help(Some(source.into())))
+        .collect();
+    candidates.extend(PathCompleter::dir().complete(current));
+    candidates
+}
+
+/// Cached image names and `:latest` aliases, each with the source it was built from
+fn cached_images() -> Vec<(String, String)> {
+    let Ok(cache_manager) = CacheManager::new() else {
+        return Vec::new();
+    };
+
+    let mut images = Vec::new();
+    for entry in cache_manager.entries() {
+        let repository = entry.image_name.split(':').next().unwrap_or(&entry.image_name);
+        images.push((entry.image_name.clone(), entry.source_path.clone()));
+        images.push((format!("{}:latest", repository), entry.source_path.clone()));
+    }
+    images.sort();
+    images.dedup_by(|a, b| a.0 == b.0);
+    images
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_calls_back_into_finch_mcp() {
+        let mut script = Vec::new();
+        write_script(CompletionShell::Fish, &mut script).unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("COMPLETE=fish finch-mcp"));
+    }
+}
@@ -1,27 +1,246 @@
 use std::fs;
-use std::process::{Command, Stdio};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
 use anyhow::{Context, Result};
 use log::{debug, info};
-use tempfile::TempDir;
 use serde_json::json;
 
-use crate::utils::command_detector::{detect_command_type, generate_dockerfile_content, CommandType};
-use crate::finch::client::{FinchClient, StdioRunOptions};
-use crate::cache::{CacheManager, ContentHasher, hash_build_options};
+use crate::utils::command_detector::{detect_command_type, generate_dockerfile_content};
+use crate::error::FinchMcpError;
+use crate::events::{Event, EventSink, Events};
+use crate::finch::client::{FinchClient, NetworkMode, StdioRunOptions};
+use crate::cache::{dockerfile_label, no_cache_build_args, tags, CacheManager, ContentHasher, hash_build_options};
+use crate::core::{ca_certs, gc, healthcheck};
+use crate::core::ca_certs::CaBundle;
+use crate::core::client_config::{self, ClientEntry, ConfigMode};
+use crate::utils::offline;
+use crate::utils::package_version;
+use crate::utils::timezone;
+use crate::mcp::supervisor::RestartPolicy;
+use crate::utils::user_map::{self, UserMap};
 use crate::logging::LogManager;
+use crate::run_id;
+use crate::utils::progress::{blocking, build_policy, run_build_logged};
+use crate::utils::proxy;
 use crate::status;
+use crate::workspace;
 
+/// Options for containerizing a package-runner command (e.g. `uvx mcp-server-time`)
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct AutoContainerizeOptions {
     pub command: String,
     pub args: Vec<String>,
     pub env_vars: Vec<String>,
     pub volumes: Vec<String>,
-    pub host_network: bool,
+    pub network: NetworkMode,
+    pub publish: Vec<String>,
     pub forward_registry: bool,
     pub force_rebuild: bool,
+    /// Build without finch's layer cache as well (`--no-cache`)
+    pub no_cache: bool,
+    /// Never touch the network: only cached images run, with no network (`--offline`)
+    pub offline: bool,
+    /// When the server runs as the host user (`--user-map`, `--no-user-map`)
+    pub user_map: UserMap,
+    /// In MCP mode, check that the server answers `initialize` before connecting the client (`--health-check`)
+    pub health_check: bool,
+    /// Abort if the server writes nothing to stdout this long after the client's first message,
+    /// over `mcp.startupTimeout` (`--startup-timeout`)
+    pub startup_timeout: Option<Duration>,
+    /// In MCP mode, record every JSON-RPC frame to this NDJSON file (`--trace-mcp`)
+    pub trace_mcp: Option<PathBuf>,
+    /// Pass the host's proxy variables to the build and the server (`--forward-proxy`)
+    pub forward_proxy: bool,
+    /// Leave the host's timezone and locale out of the image and the server (`--no-forward-timezone`)
+    pub no_forward_timezone: bool,
+    /// Restart the server when it exits with an error (`--restart`)
+    pub restart: RestartPolicy,
+    /// CA certificates to trust in the build and the server (`--ca-cert`, `caCerts`)
+    pub ca_bundle: Option<CaBundle>,
+    /// Receives the flow's progress events (`with_event_sink`)
+    pub events: Events,
+    /// What the printed client entry runs (`--config-mode`)
+    pub config_mode: ConfigMode,
+    /// `HEALTHCHECK` command for the image, over `build.healthcheck` (`--image-healthcheck`)
+    pub image_healthcheck: Option<String>,
+    /// Kill a build running longer than this, over `build.timeout` (`--build-timeout`)
+    pub build_timeout: Option<Duration>,
 }
 
-pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> Result<()> {
+impl AutoContainerizeOptions {
+    /// The command line as typed, which is the source part of its cache key
+    pub fn command_key(&self) -> String {
+        format!("{} {}", self.command, self.args.join(" "))
+    }
+    
+    /// Build options part of the command's cache key
+    pub fn build_options_hash(&self) -> String {
+        hash_build_options(self.network.is_host(), self.forward_registry, &self.env_vars, self.image_healthcheck.as_deref(), !self.no_forward_timezone, self.ca_bundle.as_ref())
+    }
+    
+    /// Defaults to the bridge network with no extra args, env vars, volumes or ports
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            args: Vec::new(),
+            env_vars: Vec::new(),
+            volumes: Vec::new(),
+            network: NetworkMode::default(),
+            publish: Vec::new(),
+            forward_registry: false,
+            force_rebuild: false,
+            no_cache: false,
+            offline: false,
+            user_map: UserMap::Auto,
+            health_check: false,
+            startup_timeout: None,
+            trace_mcp: None,
+            forward_proxy: false,
+            no_forward_timezone: false,
+            restart: RestartPolicy::No,
+            ca_bundle: None,
+            events: Events::default(),
+            config_mode: ConfigMode::default(),
+            image_healthcheck: None,
+            build_timeout: None,
+        }
+    }
+    
+    /// Extra arguments passed to the server
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+    
+    /// `KEY=VALUE` environment variables for the container
+    pub fn with_env_vars(mut self, env_vars: Vec<String>) -> Self {
+        self.env_vars = env_vars;
+        self
+    }
+    
+    /// `host:container` volume mounts
+    pub fn with_volumes(mut self, volumes: Vec<String>) -> Self {
+        self.volumes = volumes;
+        self
+    }
+    
+    pub fn with_network(mut self, network: NetworkMode) -> Self {
+        self.network = network;
+        self
+    }
+    
+    /// `host:container` port mappings
+    pub fn with_publish(mut self, publish: Vec<String>) -> Self {
+        self.publish = publish;
+        self
+    }
+    
+    /// Make the host's npm/pip registry config available to the build
+    pub fn with_forward_registry(mut self, forward_registry: bool) -> Self {
+        self.forward_registry = forward_registry;
+        self
+    }
+    
+    /// Build even if a cached image exists
+    pub fn with_force_rebuild(mut self, force_rebuild: bool) -> Self {
+        self.force_rebuild = force_rebuild;
+        self
+    }
+    
+    /// Build without finch's layer cache, so every step runs again
+    pub fn with_no_cache(mut self, no_cache: bool) -> Self {
+        self.no_cache = no_cache;
+        self
+    }
+    
+    /// Run only cached images, with no network, and fail whatever needs the network
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+    
+    /// When the server runs as the host user, so files it writes to mounts belong to them
+    pub fn with_user_map(mut self, user_map: UserMap) -> Self {
+        self.user_map = user_map;
+        self
+    }
+    
+    /// In MCP mode, check that the server answers `initialize` before connecting the client
+    pub fn with_health_check(mut self, health_check: bool) -> Self {
+        self.health_check = health_check;
+        self
+    }
+    
+    /// Abort if the server stays silent this long after the client's first message
+    pub fn with_startup_timeout(mut self, startup_timeout: Option<Duration>) -> Self {
+        self.startup_timeout = startup_timeout;
+        self
+    }
+    
+    /// In MCP mode, record every JSON-RPC frame of the session to `path`, secrets masked
+    pub fn with_trace_mcp(mut self, path: Option<PathBuf>) -> Self {
+        self.trace_mcp = path;
+        self
+    }
+    
+    /// Pass the host's `HTTP_PROXY` and friends to the build and the server, by name only
+    pub fn with_forward_proxy(mut self, forward_proxy: bool) -> Self {
+        self.forward_proxy = forward_proxy;
+        self
+    }
+    
+    /// Keep the container in UTC with no locale, and skip installing `tzdata`
+    pub fn with_no_forward_timezone(mut self, no_forward_timezone: bool) -> Self {
+        self.no_forward_timezone = no_forward_timezone;
+        self
+    }
+    
+    /// Restart the server after it crashes, as `policy` says
+    pub fn with_restart(mut self, policy: RestartPolicy) -> Self {
+        self.restart = policy;
+        self
+    }
+    
+    /// Trust the certificates in `bundle` in the build and the server
+    pub fn with_ca_bundle(mut self, bundle: Option<CaBundle>) -> Self {
+        self.ca_bundle = bundle;
+        self
+    }
+    
+    /// Report what the flow does to `sink`, which then owns the build's progress display
+    pub fn with_event_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.events = Events::new(sink);
+        self
+    }
+    
+    /// What the printed client entry runs: the source, the exact image or its `:latest` tag
+    pub fn with_config_mode(mut self, config_mode: ConfigMode) -> Self {
+        self.config_mode = config_mode;
+        self
+    }
+    
+    /// Give the image a `HEALTHCHECK` running `command`, whatever the project's `build.healthcheck`
+    pub fn with_image_healthcheck(mut self, command: Option<String>) -> Self {
+        self.image_healthcheck = command;
+        self
+    }
+    
+    /// Kill a build running longer than `timeout`, whatever the project's `build.timeout`
+    pub fn with_build_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.build_timeout = timeout;
+        self
+    }
+}
+
+/// Build (or reuse) an image for a package-runner command and run it
+pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> crate::error::Result<()> {
+    auto_containerize_and_run_inner(options).await.map_err(FinchMcpError::from)
+}
+
+async fn auto_containerize_and_run_inner(options: AutoContainerizeOptions) -> Result<()> {
     use console::style;
     
     // Initialize cache and content hasher
@@ -30,8 +249,8 @@ pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> Resu
     
     // Generate content hash for the command
     let content_hash = content_hasher.hash_command(&options.command, &options.args)?;
-    let build_options_hash = hash_build_options(options.host_network, options.forward_registry, &options.env_vars);
-    let command_key = format!("{} {}", options.command, options.args.join(" "));
+    let build_options_hash = options.build_options_hash();
+    let command_key = options.command_key();
     
     // Check if we have a cached image
     if let Some(cached_image) = cache_manager.get_cached_image(&command_key, &content_hash, &build_options_hash).await {
@@ -39,16 +258,13 @@ pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> Resu
             status!("🔨 Force rebuild requested, ignoring cached image: {}", style(&cached_image).cyan());
             info!("Force rebuild for command: {}", command_key);
         } else {
+            options.events.emit(Event::CacheHit { image: cached_image.clone() });
             status!("⚡ Using cached image: {}", style(&cached_image).cyan());
             status!("💡 To rebuild, use: {}", style("finch-mcp run --force <target>").yellow());
             info!("Cache hit for command: {}", command_key);
             
-            // Build extra args environment variable if needed (MCP env vars are added by finch client)
-            let mut env_vars = options.env_vars;
-            if !options.args.is_empty() {
-                let extra_args = options.args.join(" ");
-                env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-            }
+            // The command's arguments are baked into the image's entrypoint (MCP env vars are added by finch client)
+            let env_vars = options.env_vars;
             
             // Run the cached container
             status!("🚀 Starting server...\n");
@@ -58,24 +274,42 @@ pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> Resu
                 image_name: cached_image,
                 env_vars,
                 volumes: options.volumes,
-                host_network: options.host_network,
+                network: offline::network(options.offline, &options.network),
+                publish: options.publish.clone(),
+                args: Vec::new(),
+                user_map: options.user_map,
+                health_check: options.health_check,
+                startup_timeout: options.startup_timeout,
+                trace_mcp: options.trace_mcp.clone(),
+                forward_proxy: options.forward_proxy,
+                no_forward_timezone: options.no_forward_timezone,
+                restart: options.restart,
+                events: options.events.clone(),
             };
             
-            return finch_client.run_stdio_container(&run_options, None).await;
+            return Ok(finch_client.run_stdio_container(&run_options, None).await?);
         }
     }
     
     // Cache miss - need to build
+    options.events.emit(Event::CacheMiss { source: command_key.clone() });
     status!("🔨 Cache miss - building container...");
     
+    offline::ensure_online(options.offline, &format!("Building {}", command_key))?;
+    
     // Initialize logging
     let log_manager = LogManager::new()?;
     let log_filename = log_manager.log_build_start("auto", &command_key)?;
     let build_start = std::time::Instant::now();
     
     // Detect command type
-    let command_details = detect_command_type(&options.command, &options.args);
+    let mut command_details = detect_command_type(&options.command, &options.args);
     debug!("Detected command type: {:?}", command_details);
+    let pinned_package = blocking(|| package_version::pin(&mut command_details));
+    if let Some(pinned_package) = &pinned_package {
+        status!("📌 Pinned to {}", style(pinned_package).cyan());
+    }
+    options.events.emit(Event::DetectionResult { project_type: format!("{:?}", command_details.cmd_type) });
     
     // Generate smart, human-readable image name
     let identifier = CacheManager::extract_identifier(&command_key);
@@ -87,15 +321,15 @@ pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> Resu
     );
     
     // Create temp directory for Dockerfile
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+    let temp_dir = workspace::temp_dir("build")?;
     let dockerfile_path = temp_dir.path().join("Dockerfile");
     
     // Generate Dockerfile content
-    let dockerfile_content = generate_dockerfile_content(&command_details);
+    let dockerfile_content = healthcheck::apply(user_map::apply(timezone::apply(ca_certs::apply(generate_dockerfile_content(&command_details), options.ca_bundle.as_ref()), !options.no_forward_timezone)), options.image_healthcheck.as_deref());
     debug!("Generated Dockerfile:\n{}", dockerfile_content);
     
     // Write Dockerfile
-    fs::write(&dockerfile_path, dockerfile_content).context("Failed to write Dockerfile")?;
+    fs::write(&dockerfile_path, &dockerfile_content).context("Failed to write Dockerfile")?;
     info!("Created Dockerfile at: {:?}", dockerfile_path);
     
     // Build the container image
@@ -104,12 +338,19 @@ pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> Resu
     build_command
         .arg("build")
         .arg("-t")
-        .arg(&image_name);
+        .arg(&image_name)
+        .arg("--label")
+        .arg(dockerfile_label(&dockerfile_content))
+        .arg("--label")
+        .arg(run_id::label());
     
     // Add host network option if enabled
-    if options.host_network {
+    if options.network.is_host() {
         build_command.arg("--network").arg("host");
     }
+    build_command.args(proxy::build_args(options.forward_proxy));
+    build_command.args(ca_certs::build_args(temp_dir.path(), options.ca_bundle.as_ref())?);
+    build_command.args(no_cache_build_args(options.no_cache));
     
     build_command
         .arg("-f")
@@ -119,18 +360,17 @@ pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> Resu
     // Log build command
     log_manager.append_to_log(&log_filename, &format!("Build command: {:?}", build_command))?;
     
-    let build_status = build_command
-        .stdout(Stdio::null())
-        .stderr(Stdio::inherit())
-        .status()
-        .context("Failed to execute finch build command")?;
+    options.events.emit(Event::BuildStarted { image: image_name.clone(), log_path: log_manager.log_path(&log_filename) });
+    let build_log = log_manager.open_sink(&log_filename)?;
+    let build_status = run_build_logged(&mut build_command, &build_log, true, &build_policy(None, options.build_timeout), &options.events)?;
     
     let build_duration = build_start.elapsed().as_secs();
+    options.events.emit(Event::BuildFinished { image: image_name.clone(), success: build_status.success(), duration_secs: build_duration });
     
     if !build_status.success() {
         log_manager.append_to_log(&log_filename, &format!("Build failed with status: {}", build_status))?;
         log_manager.finish_build_log(&log_filename, false, build_duration)?;
-        return Err(anyhow::anyhow!("Container build failed with status: {}", build_status));
+        return Err(FinchMcpError::build_failed(log_manager.log_path(&log_filename), format!("finch build exited with {}", build_status)).into());
     }
     
     log_manager.append_to_log(&log_filename, "Build completed successfully")?;
@@ -156,27 +396,22 @@ pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> Resu
         &build_options_hash,
         &image_name,
         &format!("{:?}", command_details.cmd_type),
+        build_duration,
     )?;
+    if let Some(pinned_package) = &pinned_package {
+        cache_manager.set_pinned_package(&image_name, pinned_package)?;
+    }
+    cache_manager.store_dockerfile(&image_name, &dockerfile_content)?;
+    tags::after_build(&image_name).await;
+    gc::after_build(&image_name).await;
     
     status!("💾 Image cached for future use");
     
     // Output MCP configuration
-    output_mcp_config(&command_key, &image_name, &options.env_vars)?;
+    output_mcp_config(&command_key, &image_name, &options)?;
     
-    // Build extra args environment variable if needed (MCP env vars are added by finch client)
-    let mut env_vars = options.env_vars;
-    
-    // For some command types (like NPX), arguments are already built into the CMD
-    // Only add EXTRA_ARGS for command types that use the ${EXTRA_ARGS} placeholder generically
-    let should_add_extra_args = match command_details.cmd_type {
-        CommandType::NodeNpx => false, // NPX args already built into CMD
-        _ => !options.args.is_empty(), // Other commands can use EXTRA_ARGS
-    };
-    
-    if should_add_extra_args {
-        let extra_args = options.args.join(" ");
-        env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-    }
+    // The command's arguments are baked into the image's entrypoint (MCP env vars are added by finch client)
+    let env_vars = options.env_vars;
     
     // Run the container
     info!("Running containerized command");
@@ -185,14 +420,28 @@ pub async fn auto_containerize_and_run(options: AutoContainerizeOptions) -> Resu
         image_name,
         env_vars,
         volumes: options.volumes,
-        host_network: options.host_network,
+        network: offline::network(options.offline, &options.network),
+        publish: options.publish.clone(),
+        args: Vec::new(),
+        user_map: options.user_map,
+        health_check: options.health_check,
+        startup_timeout: options.startup_timeout,
+        trace_mcp: options.trace_mcp.clone(),
+        forward_proxy: options.forward_proxy,
+        no_forward_timezone: options.no_forward_timezone,
+        restart: options.restart,
+        events: options.events.clone(),
     };
     
-    finch_client.run_stdio_container(&run_options, None).await
+    Ok(finch_client.run_stdio_container(&run_options, None).await?)
 }
 
 /// Auto-containerize and run for MCP clients (build-then-run in one step)
-pub async fn auto_containerize_and_run_mcp(options: AutoContainerizeOptions) -> Result<()> {
+pub async fn auto_containerize_and_run_mcp(options: AutoContainerizeOptions) -> crate::error::Result<()> {
+    auto_containerize_and_run_mcp_inner(options).await.map_err(FinchMcpError::from)
+}
+
+async fn auto_containerize_and_run_mcp_inner(options: AutoContainerizeOptions) -> Result<()> {
     
     // Initialize cache and content hasher
     let mut cache_manager = CacheManager::new()?;
@@ -200,37 +449,46 @@ pub async fn auto_containerize_and_run_mcp(options: AutoContainerizeOptions) ->
     
     // Generate content hash for the command
     let content_hash = content_hasher.hash_command(&options.command, &options.args)?;
-    let build_options_hash = hash_build_options(options.host_network, options.forward_registry, &options.env_vars);
-    let command_key = format!("{} {}", options.command, options.args.join(" "));
+    let build_options_hash = options.build_options_hash();
+    let command_key = options.command_key();
     
     // Check if we have a cached image
     if let Some(cached_image) = cache_manager.get_cached_image(&command_key, &content_hash, &build_options_hash).await {
-        // Run the cached container directly in MCP mode (MCP env vars are added by finch client)
-        let mut env_vars = options.env_vars;
-        if !options.args.is_empty() {
-            let extra_args = options.args.join(" ");
-            env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-        }
+        // Run the cached container directly in MCP mode; its arguments are baked into the image
+        let env_vars = options.env_vars;
         
         let finch_client = FinchClient::new();
         let run_options = StdioRunOptions {
             image_name: cached_image,
             env_vars,
             volumes: options.volumes,
-            host_network: options.host_network,
+            network: offline::network(options.offline, &options.network),
+            publish: options.publish.clone(),
+            args: Vec::new(),
+            user_map: options.user_map,
+            health_check: options.health_check,
+            startup_timeout: options.startup_timeout,
+            trace_mcp: options.trace_mcp.clone(),
+            forward_proxy: options.forward_proxy,
+            no_forward_timezone: options.no_forward_timezone,
+            restart: options.restart,
+            events: options.events.clone(),
         };
         
-        return finch_client.run_stdio_container(&run_options, None).await;
+        return Ok(finch_client.run_stdio_container(&run_options, None).await?);
     }
     
+    offline::ensure_online(options.offline, &format!("Building {}", command_key))?;
+    
     // Build the image first (with suppressed output for MCP)
     let log_manager = LogManager::new()?;
     let log_filename = log_manager.log_build_start("auto-mcp", &command_key)?;
     let build_start = std::time::Instant::now();
     
     // Detect command type
-    let command_details = detect_command_type(&options.command, &options.args);
+    let mut command_details = detect_command_type(&options.command, &options.args);
     debug!("Detected command type: {:?}", command_details);
+    let pinned_package = blocking(|| package_version::pin(&mut command_details));
     
     // Generate smart, human-readable image name
     let identifier = CacheManager::extract_identifier(&command_key);
@@ -242,38 +500,47 @@ pub async fn auto_containerize_and_run_mcp(options: AutoContainerizeOptions) ->
     );
     
     // Create temp directory for Dockerfile
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+    let temp_dir = workspace::temp_dir("build")?;
     let dockerfile_path = temp_dir.path().join("Dockerfile");
     
     // Generate Dockerfile content
-    let dockerfile_content = generate_dockerfile_content(&command_details);
-    fs::write(&dockerfile_path, dockerfile_content).context("Failed to write Dockerfile")?;
+    let dockerfile_content = healthcheck::apply(user_map::apply(timezone::apply(ca_certs::apply(generate_dockerfile_content(&command_details), options.ca_bundle.as_ref()), !options.no_forward_timezone)), options.image_healthcheck.as_deref());
+    fs::write(&dockerfile_path, &dockerfile_content).context("Failed to write Dockerfile")?;
     
     // Build the container image (suppress output for MCP)
     let mut build_command = Command::new("finch");
     build_command
         .arg("build")
         .arg("-t")
-        .arg(&image_name);
+        .arg(&image_name)
+        .arg("--label")
+        .arg(dockerfile_label(&dockerfile_content))
+        .arg("--label")
+        .arg(run_id::label());
     
-    if options.host_network {
+    if options.network.is_host() {
         build_command.arg("--network").arg("host");
     }
+    build_command.args(proxy::build_args(options.forward_proxy));
+    build_command.args(ca_certs::build_args(temp_dir.path(), options.ca_bundle.as_ref())?);
+    build_command.args(no_cache_build_args(options.no_cache));
     
     build_command
         .arg("-f")
         .arg(&dockerfile_path)
-        .arg(temp_dir.path())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null());
+        .arg(temp_dir.path());
+    
+    // Log build command
+    log_manager.append_to_log(&log_filename, &format!("Build command: {:?}", build_command))?;
     
-    let build_status = build_command.status().context("Failed to execute finch build command")?;
+    let build_log = log_manager.open_sink(&log_filename)?;
+    let build_status = run_build_logged(&mut build_command, &build_log, false, &build_policy(None, options.build_timeout), &options.events)?;
     let build_duration = build_start.elapsed().as_secs();
     
     if !build_status.success() {
         log_manager.append_to_log(&log_filename, &format!("Build failed with status: {}", build_status))?;
         log_manager.finish_build_log(&log_filename, false, build_duration)?;
-        return Err(anyhow::anyhow!("Container build failed with status: {}", build_status));
+        return Err(FinchMcpError::build_failed(log_manager.log_path(&log_filename), format!("finch build exited with {}", build_status)).into());
     }
     
     log_manager.append_to_log(&log_filename, "Build completed successfully")?;
@@ -286,34 +553,45 @@ pub async fn auto_containerize_and_run_mcp(options: AutoContainerizeOptions) ->
         &build_options_hash,
         &image_name,
         &format!("{:?}", command_details.cmd_type),
+        build_duration,
     )?;
-    
-    // Run the container directly (MCP env vars are added by finch client)
-    let mut env_vars = options.env_vars;
-    
-    let should_add_extra_args = match command_details.cmd_type {
-        CommandType::NodeNpx => false,
-        _ => !options.args.is_empty(),
-    };
-    
-    if should_add_extra_args {
-        let extra_args = options.args.join(" ");
-        env_vars.push(format!("EXTRA_ARGS={}", extra_args));
+    if let Some(pinned_package) = &pinned_package {
+        cache_manager.set_pinned_package(&image_name, pinned_package)?;
     }
+    cache_manager.store_dockerfile(&image_name, &dockerfile_content)?;
+    tags::after_build(&image_name).await;
+    gc::after_build(&image_name).await;
+    
+    // Run the container directly; its arguments are baked into the image (MCP env vars are added by finch client)
+    let env_vars = options.env_vars;
     
     let finch_client = FinchClient::new();
     let run_options = StdioRunOptions {
         image_name,
         env_vars,
         volumes: options.volumes,
-        host_network: options.host_network,
+        network: offline::network(options.offline, &options.network),
+        publish: options.publish.clone(),
+        args: Vec::new(),
+        user_map: options.user_map,
+        health_check: options.health_check,
+        startup_timeout: options.startup_timeout,
+        trace_mcp: options.trace_mcp.clone(),
+        forward_proxy: options.forward_proxy,
+        no_forward_timezone: options.no_forward_timezone,
+        restart: options.restart,
+        events: options.events.clone(),
     };
     
-    finch_client.run_stdio_container(&run_options, None).await
+    Ok(finch_client.run_stdio_container(&run_options, None).await?)
 }
 
 /// Build a container from a command without running it
-pub async fn auto_build(options: AutoContainerizeOptions) -> Result<String> {
+pub async fn auto_build(options: AutoContainerizeOptions) -> crate::error::Result<String> {
+    auto_build_inner(options).await.map_err(FinchMcpError::from)
+}
+
+async fn auto_build_inner(options: AutoContainerizeOptions) -> Result<String> {
     use console::style;
     
     // Initialize cache and content hasher
@@ -322,8 +600,8 @@ pub async fn auto_build(options: AutoContainerizeOptions) -> Result<String> {
     
     // Generate content hash for the command
     let content_hash = content_hasher.hash_command(&options.command, &options.args)?;
-    let build_options_hash = hash_build_options(options.host_network, options.forward_registry, &options.env_vars);
-    let command_key = format!("{} {}", options.command, options.args.join(" "));
+    let build_options_hash = options.build_options_hash();
+    let command_key = options.command_key();
     
     // Check if we have a cached image
     if let Some(cached_image) = cache_manager.get_cached_image(&command_key, &content_hash, &build_options_hash).await {
@@ -334,31 +612,40 @@ pub async fn auto_build(options: AutoContainerizeOptions) -> Result<String> {
             status!("⚡ Image already built: {}", style(&cached_image).cyan());
             status!("💡 To rebuild, use: {}", style("finch-mcp build --force <target>").yellow());
             info!("Cache hit for command: {}", command_key);
+            options.events.emit(Event::CacheHit { image: cached_image.clone() });
             
             // Output MCP configuration
-            output_mcp_config(&command_key, &cached_image, &options.env_vars)?;
+            output_mcp_config(&command_key, &cached_image, &options)?;
             
             return Ok(cached_image);
         }
     }
     
     // Cache miss or force rebuild - need to build
+    options.events.emit(Event::CacheMiss { source: command_key.clone() });
     status!("🔨 Building container...");
     
+    offline::ensure_online(options.offline, &format!("Building {}", command_key))?;
+    
     // Initialize logging
     let log_manager = LogManager::new()?;
     let log_filename = log_manager.log_build_start("auto", &command_key)?;
     let build_start = std::time::Instant::now();
     
     // Detect command type
-    let command_details = detect_command_type(&options.command, &options.args);
+    let mut command_details = detect_command_type(&options.command, &options.args);
     info!("Detected command type: {:?}", command_details.cmd_type);
+    let pinned_package = blocking(|| package_version::pin(&mut command_details));
+    if let Some(pinned_package) = &pinned_package {
+        status!("📌 Pinned to {}", style(pinned_package).cyan());
+    }
+    options.events.emit(Event::DetectionResult { project_type: format!("{:?}", command_details.cmd_type) });
     
     // Generate Dockerfile content based on command type
-    let dockerfile_content = generate_dockerfile_content(&command_details);
+    let dockerfile_content = healthcheck::apply(user_map::apply(timezone::apply(ca_certs::apply(generate_dockerfile_content(&command_details), options.ca_bundle.as_ref()), !options.no_forward_timezone)), options.image_healthcheck.as_deref());
     
     // Create temporary directory for Dockerfile
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+    let temp_dir = workspace::temp_dir("build")?;
     let dockerfile_path = temp_dir.path().join("Dockerfile");
     
     // Write Dockerfile
@@ -381,12 +668,19 @@ pub async fn auto_build(options: AutoContainerizeOptions) -> Result<String> {
     build_command
         .arg("build")
         .arg("-t")
-        .arg(&image_name);
+        .arg(&image_name)
+        .arg("--label")
+        .arg(dockerfile_label(&dockerfile_content))
+        .arg("--label")
+        .arg(run_id::label());
     
     // Add host network option if enabled
-    if options.host_network {
+    if options.network.is_host() {
         build_command.arg("--network").arg("host");
     }
+    build_command.args(proxy::build_args(options.forward_proxy));
+    build_command.args(ca_certs::build_args(temp_dir.path(), options.ca_bundle.as_ref())?);
+    build_command.args(no_cache_build_args(options.no_cache));
     
     build_command
         .arg("-f")
@@ -396,18 +690,17 @@ pub async fn auto_build(options: AutoContainerizeOptions) -> Result<String> {
     // Log build command
     log_manager.append_to_log(&log_filename, &format!("Build command: {:?}", build_command))?;
     
-    let build_status = build_command
-        .stdout(Stdio::null())
-        .stderr(Stdio::inherit())
-        .status()
-        .context("Failed to execute finch build command")?;
+    options.events.emit(Event::BuildStarted { image: image_name.clone(), log_path: log_manager.log_path(&log_filename) });
+    let build_log = log_manager.open_sink(&log_filename)?;
+    let build_status = run_build_logged(&mut build_command, &build_log, true, &build_policy(None, options.build_timeout), &options.events)?;
     
     let build_duration = build_start.elapsed().as_secs();
+    options.events.emit(Event::BuildFinished { image: image_name.clone(), success: build_status.success(), duration_secs: build_duration });
     
     if !build_status.success() {
         log_manager.append_to_log(&log_filename, &format!("Build failed with status: {}", build_status))?;
         log_manager.finish_build_log(&log_filename, false, build_duration)?;
-        return Err(anyhow::anyhow!("Container build failed with status: {}", build_status));
+        return Err(FinchMcpError::build_failed(log_manager.log_path(&log_filename), format!("finch build exited with {}", build_status)).into());
     }
     
     log_manager.append_to_log(&log_filename, "Build completed successfully")?;
@@ -433,66 +726,65 @@ pub async fn auto_build(options: AutoContainerizeOptions) -> Result<String> {
         &build_options_hash,
         &image_name,
         &format!("{:?}", command_details.cmd_type),
+        build_duration,
     )?;
+    if let Some(pinned_package) = &pinned_package {
+        cache_manager.set_pinned_package(&image_name, pinned_package)?;
+    }
+    cache_manager.store_dockerfile(&image_name, &dockerfile_content)?;
+    tags::after_build(&image_name).await;
+    gc::after_build(&image_name).await;
     
     status!("💾 Image cached for future use");
     
     // Output MCP configuration
-    output_mcp_config(&command_key, &image_name, &options.env_vars)?;
+    output_mcp_config(&command_key, &image_name, &options)?;
     
     Ok(image_name)
 }
 
-/// Output MCP configuration for MCP clients
-fn output_mcp_config(command_key: &str, image_name: &str, env_vars: &[String]) -> Result<()> {
+/// Print the `mcpServers` entry for the containerized command (see `--config-mode`) unless output is quiet
+fn output_mcp_config(command_key: &str, image_name: &str, options: &AutoContainerizeOptions) -> Result<()> {
     use console::style;
     
+    // Also off while stdio carries an MCP session, as in `run --aggregate`
+    if crate::output::is_quiet_mode() {
+        return Ok(());
+    }
+    
     // Extract a clean server name from the command
     let server_name = command_key
         .split_whitespace()
         .last()
         .unwrap_or("mcp-server")
         .to_lowercase()
-        .replace('/', "-")
-        .replace('_', "-");
-    
-    // Parse environment variables into a map
-    let mut env_map = serde_json::Map::new();
-    for env_var in env_vars {
-        if let Some((key, value)) = env_var.split_once('=') {
-            env_map.insert(key.to_string(), json!(value));
-        }
-    }
-    
-    // Build the configuration object
-    let config = json!({
-        server_name: {
-            "command": "finch-mcp",
-            "args": [
-                "run",
-                image_name
-            ],
-            "env": env_map
-        }
-    });
-    
-    // Pretty print the configuration
+        .replace(['/', '_'], "-");
+    
+    // The command's arguments are baked into the image, so the source is the whole command line
+    let entry = ClientEntry {
+        source: Some(command_key.trim().to_string()),
+        image_name: image_name.to_string(),
+        env_vars: options.env_vars.clone(),
+        volumes: options.volumes.clone(),
+        options: client_config::source_options(&options.network, None, None, None),
+        server_args: Vec::new(),
+    };
+    let config = json!({ server_name: entry.to_json(options.config_mode) });
     let config_str = serde_json::to_string_pretty(&config)?;
     
-    println!("\n{} MCP Server Configuration:", style("📋").blue());
-    println!("{}", style("Add this to your MCP client configuration:").dim());
-    println!("{}", style("─".repeat(60)).dim());
-    println!("{}", config_str);
-    println!("{}", style("─".repeat(60)).dim());
+    eprintln!("\n{} MCP Server Configuration:", style("📋").blue());
+    eprintln!("{}", style("Add this to your MCP client configuration:").dim());
+    eprintln!("{}", style("─".repeat(60)).dim());
+    eprintln!("{}", config_str);
+    eprintln!("{}", style("─".repeat(60)).dim());
     
     // Add helpful notes about environment variables and arguments
-    println!("\n{} Configuration Notes:", style("💡").yellow());
-    println!("• Environment variables: Check the MCP server's documentation for supported env vars");
-    println!("• Server arguments: Pass additional args via EXTRA_ARGS environment variable");
-    println!("  Example: \"env\": {{ \"EXTRA_ARGS\": \"--port 8080 --verbose\" }}");
+    eprintln!("\n{} Configuration Notes:", style("💡").yellow());
+    eprintln!("• Environment variables: Check the MCP server's documentation for supported env vars");
+    eprintln!("• Server arguments: The command's arguments are part of the image; change them by running the new command");
     
-    println!("\n{} Container image: {}", style("🐳").cyan(), style(image_name).green());
-    println!("{} Latest tag: {}", style("🏷️").yellow(), style(format!("{}:latest", image_name.split(':').next().unwrap_or(image_name))).green());
+    eprintln!("\n{} Container image: {}", style("🐳").cyan(), style(image_name).green());
+    eprintln!("{} Latest tag: {}", style("🏷️").yellow(), style(client_config::latest_tag(image_name)).green());
     
     Ok(())
 }
@@ -501,6 +793,20 @@ fn output_mcp_config(command_key: &str, image_name: &str, env_vars: &[String]) -
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_options_builder() {
+        let options = AutoContainerizeOptions::new("uvx")
+            .with_args(vec!["mcp-server-time".to_string()])
+            .with_network(NetworkMode::Host)
+            .with_force_rebuild(true);
+        assert_eq!(options.command, "uvx");
+        assert_eq!(options.args, ["mcp-server-time"]);
+        assert!(options.network.is_host());
+        assert!(options.force_rebuild);
+        assert!(options.env_vars.is_empty() && options.volumes.is_empty() && options.publish.is_empty());
+        assert!(!options.forward_registry);
+    }
+    
     // These tests would require finch installed to run
     // so we'll mark them as ignore for automated testing
     
@@ -512,8 +818,24 @@ mod tests {
             args: vec!["mcp-server-time".to_string(), "--local-timezone".to_string(), "UTC".to_string()],
             env_vars: vec![],
             volumes: vec![],
-            host_network: false,
+            network: NetworkMode::default(),
+            publish: Vec::new(),
             forward_registry: false,
+            force_rebuild: false,
+            no_cache: false,
+            offline: false,
+            user_map: UserMap::Auto,
+            health_check: false,
+            startup_timeout: None,
+            trace_mcp: None,
+            forward_proxy: false,
+            no_forward_timezone: false,
+            restart: RestartPolicy::No,
+            ca_bundle: None,
+            events: Events::default(),
+            config_mode: ConfigMode::default(),
+            image_healthcheck: None,
+            build_timeout: None,
         };
         
         let result = auto_containerize_and_run(options).await;
@@ -0,0 +1,262 @@
+//! `finch-mcp bench`: how long a server takes to start, phase by phase
+//!
+//! A warm start is what an MCP client pays when the image is cached: hashing the target,
+//! looking the hash up in the image cache, and `finch run` until the server writes the
+//! first byte of its `initialize` response. A cold start rebuilds the image first. Each
+//! is repeated and reported as p50/p95, so changes to hashing, caching or the generated
+//! images can be compared.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use console::style;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::timeout;
+
+use crate::cache::{CacheManager, ContentHasher};
+use crate::core::auto_containerize::{auto_build, AutoContainerizeOptions};
+use crate::core::git_containerize::{git_build, local_build, GitContainerizeOptions, LocalContainerizeOptions};
+use crate::finch::client::StdioRunOptions;
+use crate::finch::shutdown;
+use crate::mcp::conformance::PROTOCOL_VERSION;
+use crate::status;
+use crate::utils::progress::blocking;
+
+/// What to benchmark, with the options a run of it would use
+#[derive(Debug, Clone)]
+pub enum BenchTarget {
+    Image(String),
+    Git(GitContainerizeOptions),
+    Local(LocalContainerizeOptions),
+    Command(AutoContainerizeOptions),
+}
+
+impl BenchTarget {
+    /// Build the image, or reuse the cached one unless `force_rebuild`
+    async fn build(&self, force_rebuild: bool) -> Result<String> {
+        Ok(match self {
+            BenchTarget::Image(image) => image.clone(),
+            BenchTarget::Git(options) => git_build(GitContainerizeOptions { force_rebuild, ..options.clone() }).await?,
+            BenchTarget::Local(options) => local_build(LocalContainerizeOptions { force_rebuild, ..options.clone() }).await?,
+            BenchTarget::Command(options) => auto_build(AutoContainerizeOptions { force_rebuild, ..options.clone() }).await?,
+        })
+    }
+
+    /// Hash the target as a run does, returning its cache source, content hash and
+    /// build options hash. `None` for images, which aren't hashed
+    fn hash(&self) -> Result<Option<(String, String, String)>> {
+        let hasher = ContentHasher::new();
+        Ok(Some(match self {
+            BenchTarget::Image(_) => return Ok(None),
+            BenchTarget::Git(options) => (options.repo_url.clone(), hasher.hash_git_repository(&options.repo_url, options.offline, options.refresh)?, options.build_options_hash()),
+            BenchTarget::Local(options) => (
+                options.local_path.clone(),
+                hasher.hash_directory(std::path::Path::new(&options.local_path))?,
+                options.build_options_hash(),
+            ),
+            BenchTarget::Command(options) => (
+                options.command_key(),
+                hasher.hash_command(&options.command, &options.args)?,
+                options.build_options_hash(),
+            ),
+        }))
+    }
+}
+
+/// Timings of each phase over the iterations
+#[derive(Debug, Default)]
+pub struct Samples {
+    pub hash: Vec<Duration>,
+    pub lookup: Vec<Duration>,
+    pub build: Vec<Duration>,
+    pub first_byte: Vec<Duration>,
+    pub total: Vec<Duration>,
+}
+
+impl Samples {
+    /// Each phase's label and JSON key, with its samples
+    fn phases(&self) -> [(&'static str, &'static str, &[Duration]); 5] {
+        [
+            ("hash", "hash", &self.hash),
+            ("cache lookup", "cacheLookup", &self.lookup),
+            ("build", "build", &self.build),
+            ("first byte", "firstByte", &self.first_byte),
+            ("total", "total", &self.total),
+        ]
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let phases: serde_json::Map<String, serde_json::Value> = self.phases()
+            .into_iter()
+            .filter(|(_, _, samples)| !samples.is_empty())
+            .map(|(_, key, samples)| (key.to_string(), json!({
+                "p50Ms": percentile(samples, 50.0).as_millis() as u64,
+                "p95Ms": percentile(samples, 95.0).as_millis() as u64,
+                "samplesMs": samples.iter().map(|sample| sample.as_millis() as u64).collect::<Vec<_>>(),
+            })))
+            .collect();
+        phases.into()
+    }
+}
+
+/// Warm (and with `--cold`, cold) start timings of one server
+#[derive(Debug)]
+pub struct BenchReport {
+    pub image: String,
+    pub warm: Samples,
+    pub cold: Samples,
+}
+
+impl BenchReport {
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut json = json!({ "image": self.image, "warm": self.warm.to_json() });
+        if !self.cold.total.is_empty() {
+            json["cold"] = self.cold.to_json();
+        }
+        json
+    }
+}
+
+/// The `p`th percentile of `samples` (nearest rank)
+pub fn percentile(samples: &[Duration], p: f64) -> Duration {
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.saturating_sub(1)).copied().unwrap_or_default()
+}
+
+/// Start the container, send `initialize` and time until the server writes anything
+/// to stdout. The container is removed afterwards
+async fn time_to_first_byte(options: &StdioRunOptions, wait: Duration) -> Result<Duration> {
+    let name = shutdown::session_container_name();
+    let started = Instant::now();
+    let mut child = options.named_command(&name)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to run finch")?;
+
+    let initialize = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {"name": "finch-mcp-bench", "version": env!("CARGO_PKG_VERSION")},
+        },
+    });
+    let mut stdin = child.stdin.take().context("finch run has no stdin")?;
+    let mut stdout = child.stdout.take().context("finch run has no stdout")?;
+    stdin.write_all(format!("{}\n", initialize).as_bytes()).await?;
+    stdin.flush().await?;
+
+    let mut byte = [0u8; 1];
+    let read = timeout(wait, stdout.read(&mut byte)).await;
+    let elapsed = started.elapsed();
+    let _ = child.kill().await;
+    shutdown::remove_container(&name).await;
+    match read {
+        Ok(Ok(1)) => Ok(elapsed),
+        Ok(Ok(_)) => anyhow::bail!("The server exited without writing to stdout"),
+        Ok(Err(e)) => Err(e).context("Failed to read from the server"),
+        Err(_) => anyhow::bail!("The server wrote nothing to stdout within {}s", wait.as_secs()),
+    }
+}
+
+/// Time `iterations` warm starts of the target and, with `cold`, as many cold ones.
+/// `run` holds the container options; its image is filled in here
+pub async fn bench(target: &BenchTarget, mut run: StdioRunOptions, iterations: u64, cold: bool, wait: Duration) -> Result<BenchReport> {
+    // Untimed: build the image if needed and start it once, so the warm runs are warm
+    run.image_name = target.build(false).await?;
+    status!("{} Warming up {}...", style("⏱").blue(), style(&run.image_name).cyan());
+    time_to_first_byte(&run, wait).await?;
+
+    let mut warm = Samples::default();
+    for iteration in 1..=iterations {
+        status!("{} Warm start {}/{}", style("⏱").blue(), iteration, iterations);
+        let started = Instant::now();
+        if let Some((source, content_hash, build_options_hash)) = blocking(|| target.hash())? {
+            warm.hash.push(started.elapsed());
+            let lookup_started = Instant::now();
+            let cached = CacheManager::new()?.get_cached_image(&source, &content_hash, &build_options_hash).await;
+            warm.lookup.push(lookup_started.elapsed());
+            if cached.is_none() {
+                anyhow::bail!("The image cache missed during a warm start; did {} change?", source);
+            }
+        }
+        warm.first_byte.push(time_to_first_byte(&run, wait).await?);
+        warm.total.push(started.elapsed());
+    }
+
+    let mut cold_samples = Samples::default();
+    if cold && !matches!(target, BenchTarget::Image(_)) {
+        for iteration in 1..=iterations {
+            status!("{} Cold start {}/{}", style("⏱").blue(), iteration, iterations);
+            let started = Instant::now();
+            run.image_name = target.build(true).await?;
+            cold_samples.build.push(started.elapsed());
+            cold_samples.first_byte.push(time_to_first_byte(&run, wait).await?);
+            cold_samples.total.push(started.elapsed());
+        }
+    }
+
+    Ok(BenchReport { image: run.image_name, warm, cold: cold_samples })
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{} ms", duration.as_millis())
+    } else {
+        format!("{:.2} s", duration.as_secs_f64())
+    }
+}
+
+/// Print p50/p95 for each phase
+pub fn print_report(report: &BenchReport) {
+    eprintln!("\n{} {}", style("⏱").blue(), style(&report.image).cyan().bold());
+    for (label, samples) in [("Warm start", &report.warm), ("Cold start", &report.cold)] {
+        if samples.total.is_empty() {
+            continue;
+        }
+        eprintln!("\n{}", style(format!("{} ({} runs)", label, samples.total.len())).bold());
+        eprintln!("  {:<14} {:>10} {:>10}", "PHASE", "P50", "P95");
+        for (phase, _, samples) in samples.phases().into_iter().filter(|(_, _, samples)| !samples.is_empty()) {
+            eprintln!("  {:<14} {:>10} {:>10}", phase, format_duration(percentile(samples, 50.0)), format_duration(percentile(samples, 95.0)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let samples: Vec<Duration> = [30, 10, 20, 50, 40].into_iter().map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50.0), Duration::from_millis(30));
+        assert_eq!(percentile(&samples, 95.0), Duration::from_millis(50));
+        assert_eq!(percentile(&samples[..1], 95.0), Duration::from_millis(30));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_report_json() {
+        let warm = Samples {
+            hash: vec![Duration::from_millis(4)],
+            lookup: vec![Duration::from_millis(60)],
+            first_byte: vec![Duration::from_millis(900)],
+            total: vec![Duration::from_millis(964)],
+            ..Default::default()
+        };
+        let report = BenchReport { image: "mcp-demo:abc".to_string(), warm, cold: Samples::default() };
+        let json = report.to_json();
+        assert_eq!(json["warm"]["cacheLookup"]["p50Ms"], 60);
+        assert_eq!(json["warm"]["total"]["samplesMs"], json!([964]));
+        assert!(json["warm"].get("build").is_none());
+        assert!(json.get("cold").is_none());
+    }
+}
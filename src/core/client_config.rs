@@ -0,0 +1,309 @@
+//! The `mcpServers` entries printed after a build and by `up` (`--config-mode`)
+//!
+//! An entry naming the content-hashed image goes stale as soon as the source changes. By
+//! default entries run the source itself, which the image cache makes as fast as running
+//! the image; `latest` runs the image's `:latest` tag and `image` pins the exact build.
+//!
+//! `config generate` wraps an entry in the layout each client's config file expects.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+use crate::finch::client::NetworkMode;
+
+/// What a client entry runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ConfigMode {
+    /// `finch-mcp run <source>`, rebuilding when the source changes
+    #[default]
+    Source,
+    /// The exact image that was built
+    Image,
+    /// The image's `:latest` tag, moved by each rebuild
+    Latest,
+}
+
+/// Everything a client entry needs to start the same server again
+#[derive(Debug, Clone, Default)]
+pub struct ClientEntry {
+    /// Git URL, absolute local directory or command line; None for a prebuilt image
+    pub source: Option<String>,
+    pub image_name: String,
+    /// `KEY=VALUE`; values go in the entry's env block and are forwarded with `-e KEY`
+    pub env_vars: Vec<String>,
+    pub volumes: Vec<String>,
+    /// Other `finch-mcp` options, e.g. `--network none`
+    pub options: Vec<String>,
+    /// Arguments for the server, passed after `--`
+    pub server_args: Vec<String>,
+}
+
+impl ClientEntry {
+    /// Arguments for `finch-mcp` in the entry
+    pub fn args(&self, mode: ConfigMode) -> Vec<String> {
+        let mut args = vec!["run".to_string()];
+        for (key, _) in self.env() {
+            args.push("-e".to_string());
+            args.push(key);
+        }
+        for volume in &self.volumes {
+            args.push("-v".to_string());
+            args.push(volume.clone());
+        }
+        args.extend(self.options.iter().cloned());
+
+        match (mode, &self.source) {
+            (ConfigMode::Source, Some(source)) => args.push(source.clone()),
+            (ConfigMode::Latest, _) => {
+                args.push("--direct".to_string());
+                args.push(latest_tag(&self.image_name));
+            }
+            _ => {
+                args.push("--direct".to_string());
+                args.push(self.image_name.clone());
+            }
+        }
+        if !self.server_args.is_empty() {
+            args.push("--".to_string());
+            args.extend(self.server_args.iter().cloned());
+        }
+        args
+    }
+
+    /// The entry as it goes under `mcpServers`
+    pub fn to_json(&self, mode: ConfigMode) -> Value {
+        let env: Map<String, Value> = self.env().into_iter().map(|(key, value)| (key, json!(value))).collect();
+        json!({
+            "command": "finch-mcp",
+            "args": self.args(mode),
+            "env": env,
+        })
+    }
+
+    fn env(&self) -> Vec<(String, String)> {
+        self.env_vars
+            .iter()
+            .filter_map(|env| env.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+}
+
+/// MCP clients `config generate` knows the config layout of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum McpClient {
+    /// `mcpServers` in `claude_desktop_config.json`
+    ClaudeDesktop,
+    /// `mcpServers` in `~/.cursor/mcp.json`
+    Cursor,
+    /// `context_servers` in Zed's `settings.json`
+    Zed,
+    /// `servers` in the workspace's `.vscode/mcp.json`
+    Vscode,
+    /// A bare `mcpServers` block, understood by most other clients
+    #[default]
+    Generic,
+}
+
+impl McpClient {
+    /// The top-level key servers are listed under
+    pub fn servers_key(self) -> &'static str {
+        match self {
+            McpClient::ClaudeDesktop | McpClient::Cursor | McpClient::Generic => "mcpServers",
+            McpClient::Zed => "context_servers",
+            McpClient::Vscode => "servers",
+        }
+    }
+
+    /// The config file `--write` edits when no path is given
+    pub fn config_path(self) -> Option<PathBuf> {
+        match self {
+            McpClient::ClaudeDesktop => dirs::config_dir().map(|dir| dir.join("Claude").join("claude_desktop_config.json")),
+            McpClient::Cursor => dirs::home_dir().map(|home| home.join(".cursor").join("mcp.json")),
+            McpClient::Zed if cfg!(windows) => dirs::config_dir().map(|dir| dir.join("Zed").join("settings.json")),
+            McpClient::Zed => dirs::home_dir().map(|home| home.join(".config").join("zed").join("settings.json")),
+            McpClient::Vscode => Some(Path::new(".vscode").join("mcp.json")),
+            McpClient::Generic => None,
+        }
+    }
+
+    /// `entry` (as from [`ClientEntry::to_json`]) in this client's shape
+    pub fn shape(self, entry: Value) -> Value {
+        let extra = match self {
+            McpClient::Zed => ("source", "custom"),
+            McpClient::Vscode => ("type", "stdio"),
+            _ => return entry,
+        };
+        let mut shaped = Map::new();
+        shaped.insert(extra.0.to_string(), json!(extra.1));
+        if let Value::Object(fields) = entry {
+            shaped.extend(fields);
+        }
+        Value::Object(shaped)
+    }
+
+    /// A config document holding just this server
+    pub fn document(self, name: &str, entry: Value) -> Value {
+        json!({ self.servers_key(): { name: self.shape(entry) } })
+    }
+
+    /// Add or replace the server in an existing config document, returning whether it replaced one
+    pub fn merge(self, document: &mut Value, name: &str, entry: Value) -> Result<bool> {
+        let key = self.servers_key();
+        let root = document.as_object_mut().context("the config file is not a JSON object")?;
+        let servers = root
+            .entry(key)
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .with_context(|| format!("\"{}\" in the config file is not an object", key))?;
+        Ok(servers.insert(name.to_string(), self.shape(entry)).is_some())
+    }
+}
+
+/// Add the server to the client config at `path`, keeping everything else in it
+pub fn write_client_config(path: &Path, client: McpClient, name: &str, entry: Value) -> Result<bool> {
+    let mut document = match std::fs::read_to_string(path) {
+        Ok(content) if content.trim().is_empty() => json!({}),
+        Ok(content) => serde_json::from_str(&content).with_context(|| {
+            format!("Failed to parse {} (comments aren't supported); add the entry by hand instead", path.display())
+        })?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => json!({}),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let replaced = client.merge(&mut document, name, entry)?;
+
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, serde_json::to_string_pretty(&document)? + "\n")
+        .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    std::fs::rename(&temp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(replaced)
+}
+
+/// A short server name for a target: the repository, directory, package or image name
+pub fn server_name(target: &str) -> String {
+    let name = target
+        .split_whitespace()
+        .rfind(|word| !word.starts_with('-'))
+        .unwrap_or(target)
+        .trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(target);
+    // Drop a `.git` suffix, an image tag or a package version
+    let name = name.trim_end_matches(".git");
+    let name = match name.rsplit_once(['@', ':']) {
+        Some((base, _)) if !base.is_empty() => base,
+        _ => name,
+    };
+    name.to_lowercase().replace(['_', ' '], "-")
+}
+
+/// `finch-mcp` options reproducing how a git or local source is built and run
+pub fn source_options(network: &NetworkMode, package: Option<&str>, command: Option<&str>, entry: Option<&str>) -> Vec<String> {
+    let mut options = Vec::new();
+    if *network != NetworkMode::default() {
+        options.extend(["--network".to_string(), network.to_string()]);
+    }
+    for (flag, value) in [("--package", package), ("--cmd", command), ("--entry", entry)] {
+        if let Some(value) = value {
+            options.extend([flag.to_string(), value.to_string()]);
+        }
+    }
+    options
+}
+
+/// `repo:latest` for `repo:tag`
+pub fn latest_tag(image_name: &str) -> String {
+    let repository = match image_name.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => image_name,
+    };
+    format!("{}:latest", repository)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_args_by_mode() {
+        let entry = ClientEntry {
+            source: Some("https://github.com/example/weather-mcp".to_string()),
+            image_name: "mcp-weather-mcp:1a2b3c4d".to_string(),
+            env_vars: vec!["API_KEY=abc".to_string()],
+            volumes: vec!["/srv/data:/data:ro".to_string()],
+            options: vec!["--network".to_string(), "none".to_string()],
+            server_args: vec!["--units".to_string(), "metric".to_string()],
+        };
+
+        assert_eq!(entry.args(ConfigMode::Source), [
+            "run", "-e", "API_KEY", "-v", "/srv/data:/data:ro", "--network", "none",
+            "https://github.com/example/weather-mcp", "--", "--units", "metric",
+        ]);
+        assert_eq!(entry.args(ConfigMode::Image)[7..9], ["--direct", "mcp-weather-mcp:1a2b3c4d"]);
+        assert_eq!(entry.args(ConfigMode::Latest)[7..9], ["--direct", "mcp-weather-mcp:latest"]);
+        assert_eq!(entry.to_json(ConfigMode::Source)["env"], json!({ "API_KEY": "abc" }));
+
+        let image_only = ClientEntry { source: None, ..entry };
+        assert_eq!(image_only.args(ConfigMode::Source)[7..9], ["--direct", "mcp-weather-mcp:1a2b3c4d"]);
+    }
+
+    #[test]
+    fn test_source_options() {
+        assert!(source_options(&NetworkMode::Bridge, None, None, None).is_empty());
+        assert_eq!(source_options(&NetworkMode::Host, Some("@acme/server"), None, Some("serve")), [
+            "--network", "host", "--package", "@acme/server", "--entry", "serve",
+        ]);
+    }
+
+    #[test]
+    fn test_client_shapes() {
+        let entry = json!({ "command": "finch-mcp", "args": ["run", "uvx mcp-server-time"], "env": {} });
+
+        let claude = McpClient::ClaudeDesktop.document("time", entry.clone());
+        assert_eq!(claude["mcpServers"]["time"], entry);
+
+        let vscode = McpClient::Vscode.document("time", entry.clone());
+        assert_eq!(vscode["servers"]["time"]["type"], "stdio");
+        assert_eq!(vscode["servers"]["time"]["command"], "finch-mcp");
+
+        let zed = McpClient::Zed.document("time", entry.clone());
+        assert_eq!(zed["context_servers"]["time"]["source"], "custom");
+        assert_eq!(zed["context_servers"]["time"]["args"], entry["args"]);
+    }
+
+    #[test]
+    fn test_merge_keeps_other_settings() {
+        let mut document = json!({ "theme": "One Dark", "context_servers": { "other": {} } });
+        let entry = json!({ "command": "finch-mcp", "args": [] });
+
+        assert!(!McpClient::Zed.merge(&mut document, "time", entry.clone()).unwrap());
+        assert!(McpClient::Zed.merge(&mut document, "time", entry).unwrap());
+        assert_eq!(document["theme"], "One Dark");
+        assert_eq!(document["context_servers"].as_object().unwrap().keys().collect::<Vec<_>>(), ["other", "time"]);
+
+        assert!(McpClient::Cursor.merge(&mut json!([]), "time", json!({})).is_err());
+        assert!(McpClient::Cursor.merge(&mut json!({ "mcpServers": [] }), "time", json!({})).is_err());
+    }
+
+    #[test]
+    fn test_server_name() {
+        assert_eq!(server_name("https://github.com/example/Weather_MCP.git"), "weather-mcp");
+        assert_eq!(server_name("/home/me/projects/notes-server/"), "notes-server");
+        assert_eq!(server_name("npx -y @modelcontextprotocol/server-time@1.2.0"), "server-time");
+        assert_eq!(server_name("uvx mcp-server-fetch"), "mcp-server-fetch");
+        assert_eq!(server_name("ghcr.io/acme/mcp-search:1.0"), "mcp-search");
+    }
+
+    #[test]
+    fn test_latest_tag() {
+        assert_eq!(latest_tag("mcp-time:abcd1234"), "mcp-time:latest");
+        assert_eq!(latest_tag("localhost:5000/mcp-time"), "localhost:5000/mcp-time:latest");
+    }
+}
@@ -0,0 +1,180 @@
+//! Build context size checks, run before a context is copied or sent to `finch build`
+//!
+//! The context is measured with the rules that keep it lean: hidden entries and the
+//! directories in `SKIPPED_DIRS` never reach it. Past `build.contextWarnSize` (500MB by
+//! default) the build goes ahead with a warning; past `build.maxContextSize` or
+//! `--max-context-size` (5GB by default) it is refused. Both list the largest entries,
+//! since a forgotten data folder is the usual culprit.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use console::style;
+
+use crate::core::finch_config::FinchConfig;
+use crate::core::git_containerize::SKIPPED_DIRS;
+use crate::status;
+use crate::utils::units::{format_size, parse_size};
+
+/// Contexts larger than this get a warning unless `build.contextWarnSize` says otherwise
+pub const DEFAULT_WARN_SIZE: u64 = 500_000_000;
+
+/// Contexts larger than this are refused unless `build.maxContextSize` says otherwise
+pub const DEFAULT_MAX_SIZE: u64 = 5_000_000_000;
+
+/// How many of the largest entries a warning or error lists
+const LISTED: usize = 5;
+
+/// When a context gets a warning, and when it is refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextLimits {
+    pub warn_bytes: u64,
+    pub max_bytes: u64,
+}
+
+/// Limits from the project's `build` settings, with `max_bytes_override`
+/// (`--max-context-size`) replacing its cap
+pub fn context_limits(config: Option<&FinchConfig>, max_bytes_override: Option<u64>) -> Result<ContextLimits> {
+    let build = config.map(|config| &config.build);
+    let setting = |value: Option<&String>, name: &str| {
+        value.map(|value| parse_size(value))
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("build.{}: {}", name, e))
+    };
+    let warn_bytes = setting(build.and_then(|build| build.context_warn_size.as_ref()), "contextWarnSize")?;
+    let max_bytes = setting(build.and_then(|build| build.max_context_size.as_ref()), "maxContextSize")?;
+    Ok(ContextLimits {
+        warn_bytes: warn_bytes.unwrap_or(DEFAULT_WARN_SIZE),
+        max_bytes: max_bytes_override.or(max_bytes).unwrap_or(DEFAULT_MAX_SIZE),
+    })
+}
+
+/// How big a context is, and where the bytes are
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ContextSize {
+    pub total: u64,
+    /// Top-level entries, largest first; directories end in `/`
+    pub largest: Vec<(String, u64)>,
+}
+
+impl ContextSize {
+    /// The largest entries, one per line, for messages
+    fn listing(&self) -> String {
+        self.largest.iter()
+            .take(LISTED)
+            .map(|(name, size)| format!("  {:>9}  {}", format_size(*size), name))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Measure what of `dir` would end up in the build context
+pub fn measure(dir: &Path) -> ContextSize {
+    let mut largest: Vec<(String, u64)> = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|entry| entry.ok())
+            .filter(|entry| included(&entry.file_name().to_string_lossy()))
+            .map(|entry| {
+                let path = entry.path();
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let name = if entry.file_type().is_ok_and(|kind| kind.is_dir()) { name + "/" } else { name };
+                (name, entry_size(&path))
+            })
+            .collect())
+        .unwrap_or_default();
+    largest.sort_by(|(a_name, a_size), (b_name, b_size)| b_size.cmp(a_size).then_with(|| a_name.cmp(b_name)));
+    ContextSize { total: largest.iter().map(|(_, size)| size).sum(), largest }
+}
+
+/// Whether an entry with this name is part of a build context
+fn included(name: &str) -> bool {
+    !name.starts_with('.') && !SKIPPED_DIRS.contains(&name)
+}
+
+/// Size of a file, or of everything a context would take from a directory.
+/// Symlinks are not followed, as the context recreates them
+fn entry_size(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::read_dir(path)
+            .map(|entries| entries.filter_map(|entry| entry.ok())
+                .filter(|entry| included(&entry.file_name().to_string_lossy()))
+                .map(|entry| entry_size(&entry.path()))
+                .sum())
+            .unwrap_or(0),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
+/// Measure the context taken from `dir`, warning past the warning size and failing past the
+/// cap, which `max_bytes` (`--max-context-size`) overrides
+pub fn check(dir: &Path, config: Option<&FinchConfig>, max_bytes: Option<u64>) -> Result<ContextSize> {
+    let limits = context_limits(config, max_bytes)?;
+    let size = measure(dir);
+    log::debug!("Build context {} is {}", dir.display(), format_size(size.total));
+    if size.total > limits.max_bytes {
+        anyhow::bail!(
+            "Build context {} is {}, over the {} limit. Largest entries:\n{}\n\
+             Move data the server doesn't need out of the project, or raise the limit with \
+             --max-context-size or `build.maxContextSize` in .finch-mcp",
+            dir.display(), format_size(size.total), format_size(limits.max_bytes), size.listing()
+        );
+    }
+    if size.total > limits.warn_bytes {
+        status!("{} Build context is {}; copying it and building may be slow. Largest entries:\n{}",
+            style("⚠️").yellow(), format_size(size.total), size.listing());
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("data/raw")).unwrap();
+        fs::write(dir.path().join("data/raw/dump.csv"), vec![b'x'; 3000]).unwrap();
+        fs::write(dir.path().join("data/.cache"), vec![b'x'; 5000]).unwrap();
+        fs::write(dir.path().join("index.js"), vec![b'x'; 100]).unwrap();
+        fs::create_dir_all(dir.path().join("node_modules/left-pad")).unwrap();
+        fs::write(dir.path().join("node_modules/left-pad/index.js"), vec![b'x'; 9000]).unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".git/pack"), vec![b'x'; 9000]).unwrap();
+
+        let size = measure(dir.path());
+        assert_eq!(size.total, 3100);
+        assert_eq!(size.largest, vec![("data/".to_string(), 3000), ("index.js".to_string(), 100)]);
+        assert!(size.listing().starts_with("     3.0 KB  data/\n"));
+    }
+
+    #[test]
+    fn test_context_limits() {
+        let limits = context_limits(None, None).unwrap();
+        assert_eq!(limits, ContextLimits { warn_bytes: DEFAULT_WARN_SIZE, max_bytes: DEFAULT_MAX_SIZE });
+
+        let config: FinchConfig = serde_yaml::from_str("build:\n  contextWarnSize: 2KB\n  maxContextSize: 1GiB\n").unwrap();
+        assert_eq!(context_limits(Some(&config), None).unwrap(), ContextLimits { warn_bytes: 2000, max_bytes: 1 << 30 });
+        assert_eq!(context_limits(Some(&config), Some(5000)).unwrap(), ContextLimits { warn_bytes: 2000, max_bytes: 5000 });
+
+        let config: FinchConfig = serde_yaml::from_str("build:\n  maxContextSize: lots\n").unwrap();
+        assert!(context_limits(Some(&config), None).unwrap_err().to_string().starts_with("build.maxContextSize: invalid size"));
+    }
+
+    #[test]
+    fn test_check() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("datasets")).unwrap();
+        fs::write(dir.path().join("datasets/big.bin"), vec![0u8; 4000]).unwrap();
+        fs::write(dir.path().join("server.py"), "print('hi')\n").unwrap();
+
+        let config: FinchConfig = serde_yaml::from_str("build:\n  contextWarnSize: 1KB\n  maxContextSize: 10KB\n").unwrap();
+        assert_eq!(check(dir.path(), Some(&config), None).unwrap().total, 4012);
+
+        let config: FinchConfig = serde_yaml::from_str("build:\n  maxContextSize: 2KB\n").unwrap();
+        let error = check(dir.path(), Some(&config), None).unwrap_err().to_string();
+        assert!(error.contains("is 4.0 KB, over the 2.0 KB limit"), "{}", error);
+        assert!(error.contains("4.0 KB  datasets/"), "{}", error);
+    }
+}
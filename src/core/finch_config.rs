@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use anyhow::Result;
 
+use crate::core::healthcheck::HealthcheckSetting;
+
 /// Configuration for finch-mcp containerization
 #[derive(Debug, Deserialize, Serialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -36,6 +38,34 @@ pub struct BuildConfig {
     /// Additional build arguments
     #[serde(default)]
     pub args: Vec<String>,
+    
+    /// Seconds before a stuck `finch build` is killed (`--build-timeout` overrides this)
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    
+    /// Times to retry a build that failed with a transient error (default: 2)
+    #[serde(default)]
+    pub retries: Option<u32>,
+    
+    /// Seconds before the first retry, doubling each time (default: 5)
+    #[serde(default)]
+    pub retry_backoff: Option<u64>,
+    
+    /// Workspace package to build in a Node.js monorepo (`--package` overrides this)
+    #[serde(default)]
+    pub package: Option<String>,
+    
+    /// Image `HEALTHCHECK`: `true` for a process-alive check, or a command (`--image-healthcheck` overrides this)
+    #[serde(default)]
+    pub healthcheck: Option<HealthcheckSetting>,
+    
+    /// Warn when the build context is larger than this, e.g. `200MB` (default: 500MB)
+    #[serde(default)]
+    pub context_warn_size: Option<String>,
+    
+    /// Refuse to build a context larger than this, e.g. `10GB` (default: 5GB; `--max-context-size` overrides this)
+    #[serde(default)]
+    pub max_context_size: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Default)]
@@ -98,9 +128,18 @@ pub struct McpConfig {
     #[serde(default = "default_readiness_pattern")]
     pub readiness_pattern: String,
     
+    /// Run through the buffering proxy, holding client messages until the server is ready (default: false)
+    #[serde(default)]
+    pub buffering: bool,
+    
     /// Enable message buffering (default: true)
+    /// Legacy setting; the proxy only runs when `buffering` is set
     #[serde(default = "default_true")]
     pub enable_buffering: bool,
+    
+    /// Verify the server answers `initialize` before handing over stdio
+    #[serde(default)]
+    pub health_check: bool,
 }
 
 impl Default for McpConfig {
@@ -109,7 +148,9 @@ impl Default for McpConfig {
             startup_timeout: default_startup_timeout(),
             buffer_size: default_buffer_size(),
             readiness_pattern: default_readiness_pattern(),
+            buffering: false,
             enable_buffering: true,
+            health_check: false,
         }
     }
 }
@@ -0,0 +1,310 @@
+//! Garbage collection of cached images under a retention policy
+//!
+//! The policy lives under `gc` in the user's finch-mcp config
+//! (`~/.config/finch-mcp/config.yaml`). `finch-mcp gc` applies it on demand;
+//! with `afterBuild: true` it is also applied after every successful build.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use console::style;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::cache::{CacheEntry, CacheManager, RetentionPolicy};
+use crate::finch::client::FinchClient;
+use crate::paths;
+use crate::status;
+use crate::utils::units::{format_size, parse_age, parse_size};
+
+/// The `gc` section of the user config
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GcPolicy {
+    /// Collect after every successful build
+    pub after_build: bool,
+    /// Keep at most this many images per source, newest first
+    pub max_images_per_source: Option<usize>,
+    /// Remove least recently used images until the cache fits, e.g. `10GiB`
+    pub max_total_size: Option<String>,
+    /// Remove images not used for this long, e.g. `30d`
+    pub max_age: Option<String>,
+    /// After a successful build, keep only this many earlier images of the same source
+    /// and build options (`0` removes them all)
+    pub keep_previous: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct UserConfig {
+    #[serde(default)]
+    gc: GcPolicy,
+}
+
+/// A retention policy with its ages and sizes parsed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcLimits {
+    pub retention: RetentionPolicy,
+    pub max_total_bytes: Option<u64>,
+}
+
+impl GcLimits {
+    /// No limit set, so nothing would be collected
+    pub fn is_empty(&self) -> bool {
+        self.retention.is_empty() && self.max_total_bytes.is_none()
+    }
+}
+
+/// What a collection removed (or would remove)
+#[derive(Debug, Default)]
+pub struct GcReport {
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+impl GcPolicy {
+    /// Load the policy from the user config; the default (collect nothing) if there is none
+    pub fn load() -> Result<Self> {
+        Ok(paths::load_user_config::<UserConfig>()?.map(|(config, _)| config.gc).unwrap_or_default())
+    }
+
+    pub fn limits(&self) -> Result<GcLimits> {
+        let max_age_secs = self.max_age.as_deref()
+            .map(parse_age)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("gc.maxAge: {}", e))?;
+        let max_total_bytes = self.max_total_size.as_deref()
+            .map(parse_size)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("gc.maxTotalSize: {}", e))?;
+        Ok(GcLimits {
+            retention: RetentionPolicy { max_age_secs, keep_latest: self.max_images_per_source },
+            max_total_bytes,
+        })
+    }
+}
+
+/// Entries to evict, least recently used first, until the total size fits the budget.
+/// `keep` is never evicted but still counts towards the total
+pub fn over_budget(entries: &[(CacheEntry, u64)], max_total_bytes: u64, keep: Option<&str>) -> Vec<CacheEntry> {
+    let mut total: u64 = entries.iter().map(|(_, size)| size).sum();
+    let mut by_use: Vec<&(CacheEntry, u64)> = entries.iter()
+        .filter(|(entry, _)| Some(entry.image_name.as_str()) != keep)
+        .collect();
+    by_use.sort_by(|(a, _), (b, _)| a.last_accessed.cmp(&b.last_accessed).then_with(|| a.image_name.cmp(&b.image_name)));
+
+    let mut evicted = Vec::new();
+    for (entry, size) in by_use {
+        if total <= max_total_bytes {
+            break;
+        }
+        total = total.saturating_sub(*size);
+        evicted.push(entry.clone());
+    }
+    evicted
+}
+
+/// Apply limits to the cache, removing each image and its cache entry together.
+/// `keep` (the image just built) is never removed
+pub async fn collect(limits: &GcLimits, keep: Option<&str>, dry_run: bool) -> Result<GcReport> {
+    let cache_manager = CacheManager::new()?;
+    let finch_client = FinchClient::new();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+    let mut victims: Vec<CacheEntry> = cache_manager.expired_entries(&limits.retention, now)
+        .into_iter()
+        .filter(|entry| Some(entry.image_name.as_str()) != keep)
+        .collect();
+
+    let mut sizes = HashMap::new();
+    for entry in cache_manager.entries() {
+        if limits.max_total_bytes.is_some() || victims.iter().any(|victim| victim.image_name == entry.image_name) {
+            let size = finch_client.image_size(&entry.image_name).await.unwrap_or(0);
+            sizes.insert(entry.image_name.clone(), size);
+        }
+    }
+
+    if let Some(max_total_bytes) = limits.max_total_bytes {
+        let remaining: Vec<(CacheEntry, u64)> = cache_manager.entries()
+            .filter(|entry| !victims.iter().any(|victim| victim.image_name == entry.image_name))
+            .map(|entry| (entry.clone(), sizes.get(&entry.image_name).copied().unwrap_or(0)))
+            .collect();
+        victims.extend(over_budget(&remaining, max_total_bytes, keep));
+    }
+
+    remove_entries(victims, &sizes, dry_run).await
+}
+
+/// Remove the images of some entries, then the entries themselves
+async fn remove_entries(victims: Vec<CacheEntry>, sizes: &HashMap<String, u64>, dry_run: bool) -> Result<GcReport> {
+    let finch_client = FinchClient::new();
+    let mut report = GcReport::default();
+    if victims.is_empty() {
+        return Ok(report);
+    }
+
+    if dry_run {
+        for victim in &victims {
+            report.freed_bytes += sizes.get(&victim.image_name).copied().unwrap_or(0);
+            report.removed.push(victim.image_name.clone());
+        }
+        return Ok(report);
+    }
+
+    // Only forget an entry once its image is gone, so the cache never points at a missing image
+    // and an image is never left behind without an entry
+    for victim in &victims {
+        let removed = remove_image(&victim.image_name).await || !finch_client.image_exists(&victim.image_name).await.unwrap_or(true);
+        if removed {
+            report.freed_bytes += sizes.get(&victim.image_name).copied().unwrap_or(0);
+            report.removed.push(victim.image_name.clone());
+        } else {
+            warn!("GC could not remove {}; keeping its cache entry", victim.image_name);
+        }
+    }
+    let removed_entries: Vec<CacheEntry> = victims.into_iter()
+        .filter(|victim| report.removed.contains(&victim.image_name))
+        .collect();
+    for reference in CacheManager::new()?.image_refs_to_remove(&removed_entries) {
+        if !report.removed.contains(&reference) {
+            remove_image(&reference).await;
+        }
+    }
+
+    // Reload so entries written by a concurrent build since we started aren't lost
+    CacheManager::new()?.remove_images(&report.removed)?;
+    Ok(report)
+}
+
+/// Remove the images a new build supersedes (`keepPrevious`), then run the configured
+/// policy (`afterBuild`); never fails the build
+pub async fn after_build(image_name: &str) {
+    let policy = match GcPolicy::load() {
+        Ok(policy) => policy,
+        Err(e) => {
+            warn!("Skipping garbage collection: {:#}", e);
+            return;
+        }
+    };
+    if let Some(keep_previous) = policy.keep_previous {
+        match remove_superseded(image_name, keep_previous).await {
+            Ok(report) if !report.removed.is_empty() => {
+                status!("🗑️  Removed {} superseded image(s), freeing {}", report.removed.len(), format_size(report.freed_bytes));
+                for image in &report.removed {
+                    debug!("Removed superseded image {}", image);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to remove superseded images: {:#}", e),
+        }
+    }
+
+    let limits = match policy.limits() {
+        Ok(limits) if policy.after_build && !limits.is_empty() => limits,
+        Ok(_) => return,
+        Err(e) => {
+            warn!("Skipping garbage collection: {:#}", e);
+            return;
+        }
+    };
+
+    match collect(&limits, Some(image_name), false).await {
+        Ok(report) if !report.removed.is_empty() => {
+            status!("🗑️  Garbage collected {} old image(s), freeing {}", report.removed.len(), format_size(report.freed_bytes));
+            for image in &report.removed {
+                debug!("GC removed {}", image);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Garbage collection failed: {:#}", e),
+    }
+}
+
+/// Remove earlier builds of the source `image_name` was built from, keeping the
+/// `keep_previous` most recent
+async fn remove_superseded(image_name: &str, keep_previous: usize) -> Result<GcReport> {
+    let victims = CacheManager::new()?.superseded_entries(image_name, keep_previous);
+    let finch_client = FinchClient::new();
+    let mut sizes = HashMap::new();
+    for victim in &victims {
+        sizes.insert(victim.image_name.clone(), finch_client.image_size(&victim.image_name).await.unwrap_or(0));
+    }
+    remove_entries(victims, &sizes, false).await
+}
+
+/// Print a report from `finch-mcp gc`
+pub fn print_report(report: &GcReport, dry_run: bool) {
+    if report.removed.is_empty() {
+        status!("{} Nothing to collect", style("ℹ").blue());
+        return;
+    }
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    for image in &report.removed {
+        status!("  {} {} {}", style("✓").green(), verb, image);
+    }
+    let summary = if dry_run { "Would free" } else { "Freed" };
+    status!("\n{} {} {} from {} image(s)", style("✨").green().bold(), summary, format_size(report.freed_bytes), report.removed.len());
+}
+
+async fn remove_image(reference: &str) -> bool {
+    Command::new("finch")
+        .args(["rmi", "-f", reference])
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(image: &str, last_accessed: u64) -> CacheEntry {
+        CacheEntry {
+            content_hash: image.to_string(),
+            image_name: image.to_string(),
+            created_at: last_accessed,
+            last_accessed,
+            project_type: "NodeJs".to_string(),
+            source_path: format!("./{}", image),
+            build_options_hash: "def".to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_over_budget_evicts_least_recently_used() {
+        let entries = vec![
+            (entry("mcp-a:1", 300), 400),
+            (entry("mcp-b:1", 100), 400),
+            (entry("mcp-c:1", 200), 400),
+        ];
+        let images = |max: u64, keep: Option<&str>| -> Vec<String> {
+            over_budget(&entries, max, keep).into_iter().map(|entry| entry.image_name).collect()
+        };
+        assert!(images(1200, None).is_empty());
+        assert_eq!(images(800, None), ["mcp-b:1"]);
+        assert_eq!(images(400, None), ["mcp-b:1", "mcp-c:1"]);
+        assert_eq!(images(0, Some("mcp-b:1")), ["mcp-c:1", "mcp-a:1"]);
+    }
+
+    #[test]
+    fn test_policy_limits() {
+        let policy: GcPolicy = serde_yaml::from_str("afterBuild: true\nmaxImagesPerSource: 3\nmaxTotalSize: 10GiB\nmaxAge: 30d\n").unwrap();
+        assert!(policy.after_build);
+        assert_eq!(policy.keep_previous, None);
+        assert_eq!(serde_yaml::from_str::<GcPolicy>("keepPrevious: 1\n").unwrap().keep_previous, Some(1));
+        let limits = policy.limits().unwrap();
+        assert_eq!(limits.retention, RetentionPolicy { max_age_secs: Some(30 * 24 * 60 * 60), keep_latest: Some(3) });
+        assert_eq!(limits.max_total_bytes, Some(10 << 30));
+
+        assert!(GcPolicy::default().limits().unwrap().is_empty());
+        let bad = GcPolicy { max_age: Some("soon".to_string()), ..Default::default() };
+        assert!(bad.limits().unwrap_err().to_string().contains("gc.maxAge"));
+    }
+}
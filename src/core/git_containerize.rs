@@ -1,41 +1,593 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
 use anyhow::{Context, Result};
-use log::{debug, info};
-use tempfile::TempDir;
+use log::{debug, info, warn};
 use serde_json::json;
 
 use crate::utils::git_repository::GitRepository;
-use crate::utils::project_detector::{detect_project_type, ProjectType, ProjectInfo};
-use crate::utils::progress::run_build_with_progress;
-use crate::finch::client::{FinchClient, StdioRunOptions};
-use crate::cache::{CacheManager, ContentHasher, hash_build_options};
+use crate::utils::offline;
+use crate::utils::project_detector::{detect_project_type, detect_exposed_ports, select_entry, select_workspace_package, workspace_package_choices, ProjectType, ProjectInfo};
+use crate::utils::env_vars;
+use crate::utils::prompt;
+use crate::utils::proxy;
+use crate::utils::timezone;
+use crate::mcp::supervisor::RestartPolicy;
+use crate::utils::user_map::{self, UserMap};
+use crate::utils::progress::{blocking, build_policy, run_build_logged, run_build_with_progress};
+use crate::error::FinchMcpError;
+use crate::events::{Event, EventSink, Events};
+use crate::finch::client::{FinchClient, NetworkMode, StdioRunOptions};
+use crate::templates::dockerfile::shell_entrypoint;
+use crate::cache::{dockerfile_label, no_cache_build_args, tags, CacheManager, ContentHasher, PackageBuild, hash_package_build_options};
 use crate::logging::LogManager;
+use crate::run_id;
+use crate::core::{ca_certs, context_size, gc, healthcheck, registry_secrets};
+use crate::core::ca_certs::CaBundle;
+use crate::core::client_config::{self, ClientEntry, ConfigMode};
 use crate::core::finch_config::FinchConfig;
 use crate::status;
+use crate::workspace;
 
+/// Options for containerizing a git repository
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct GitContainerizeOptions {
     pub repo_url: String,
     pub args: Vec<String>,
     pub env_vars: Vec<String>,
     pub volumes: Vec<String>,
-    pub host_network: bool,
+    pub network: NetworkMode,
+    pub publish: Vec<String>,
     pub forward_registry: bool,
     pub force_rebuild: bool,
+    /// Build without finch's layer cache as well (`--no-cache`)
+    pub no_cache: bool,
+    /// Refuse build contexts larger than this many bytes, over `build.maxContextSize` (`--max-context-size`)
+    pub max_context_size: Option<u64>,
+    /// Build from the project's dev container definition, if it has one (`--use-devcontainer`)
+    pub use_devcontainer: bool,
+    /// Print one line per build step instead of a progress bar (`--plain-progress`)
+    pub plain_progress: bool,
+    /// Never touch the network: only cached images run, with no network (`--offline`)
+    pub offline: bool,
+    /// Ask the remote which commit the repository points at, even if it was resolved
+    /// within the hour (`--refresh`)
+    pub refresh: bool,
+    /// When the server runs as the host user (`--user-map`, `--no-user-map`)
+    pub user_map: UserMap,
+    /// In MCP mode, check that the server answers `initialize` before connecting the client (`--health-check`)
+    pub health_check: bool,
+    /// Abort if the server writes nothing to stdout this long after the client's first message,
+    /// over `mcp.startupTimeout` (`--startup-timeout`)
+    pub startup_timeout: Option<Duration>,
+    /// In MCP mode, record every JSON-RPC frame to this NDJSON file (`--trace-mcp`)
+    pub trace_mcp: Option<PathBuf>,
+    /// Pass the host's proxy variables to the build and the server (`--forward-proxy`)
+    pub forward_proxy: bool,
+    /// Leave the host's timezone and locale out of the image and the server (`--no-forward-timezone`)
+    pub no_forward_timezone: bool,
+    /// Restart the server when it exits with an error (`--restart`)
+    pub restart: RestartPolicy,
+    /// CA certificates to trust in the build and the server (`--ca-cert`, `caCerts`)
+    pub ca_bundle: Option<CaBundle>,
+    /// Receives the flow's progress events (`with_event_sink`)
+    pub events: Events,
+    /// What the printed client entry runs (`--config-mode`)
+    pub config_mode: ConfigMode,
+    /// `HEALTHCHECK` command for the image, over `build.healthcheck` (`--image-healthcheck`)
+    pub image_healthcheck: Option<String>,
+    /// Kill a build running longer than this, over `build.timeout` (`--build-timeout`)
+    pub build_timeout: Option<Duration>,
+    /// Workspace package to build in a Node.js monorepo
+    pub package: Option<String>,
+    /// Start command replacing the detected one
+    pub command: Option<String>,
+    /// Bin or script to start, when the project has several
+    pub entry: Option<String>,
 }
 
+impl GitContainerizeOptions {
+    /// Defaults to the bridge network with no extra args, env vars, volumes or ports
+    pub fn new(repo_url: impl Into<String>) -> Self {
+        Self {
+            repo_url: repo_url.into(),
+            args: Vec::new(),
+            env_vars: Vec::new(),
+            volumes: Vec::new(),
+            network: NetworkMode::default(),
+            publish: Vec::new(),
+            forward_registry: false,
+            force_rebuild: false,
+            no_cache: false,
+            max_context_size: None,
+            use_devcontainer: false,
+            plain_progress: false,
+            offline: false,
+            refresh: false,
+            user_map: UserMap::Auto,
+            health_check: false,
+            startup_timeout: None,
+            trace_mcp: None,
+            forward_proxy: false,
+            no_forward_timezone: false,
+            restart: RestartPolicy::No,
+            ca_bundle: None,
+            events: Events::default(),
+            config_mode: ConfigMode::default(),
+            image_healthcheck: None,
+            build_timeout: None,
+            package: None,
+            command: None,
+            entry: None,
+        }
+    }
+    
+    /// Extra arguments passed to the server
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+    
+    /// `KEY=VALUE` environment variables for the container
+    pub fn with_env_vars(mut self, env_vars: Vec<String>) -> Self {
+        self.env_vars = env_vars;
+        self
+    }
+    
+    /// `host:container` volume mounts
+    pub fn with_volumes(mut self, volumes: Vec<String>) -> Self {
+        self.volumes = volumes;
+        self
+    }
+    
+    pub fn with_network(mut self, network: NetworkMode) -> Self {
+        self.network = network;
+        self
+    }
+    
+    /// `host:container` port mappings
+    pub fn with_publish(mut self, publish: Vec<String>) -> Self {
+        self.publish = publish;
+        self
+    }
+    
+    /// Make the host's npm/pip registry config available to the build
+    pub fn with_forward_registry(mut self, forward_registry: bool) -> Self {
+        self.forward_registry = forward_registry;
+        self
+    }
+    
+    /// Build even if a cached image exists
+    pub fn with_force_rebuild(mut self, force_rebuild: bool) -> Self {
+        self.force_rebuild = force_rebuild;
+        self
+    }
+    
+    /// Build without finch's layer cache, so every step runs again
+    pub fn with_no_cache(mut self, no_cache: bool) -> Self {
+        self.no_cache = no_cache;
+        self
+    }
+    
+    /// Refuse build contexts larger than `bytes`, whatever the project's `build.maxContextSize`
+    pub fn with_max_context_size(mut self, bytes: Option<u64>) -> Self {
+        self.max_context_size = bytes;
+        self
+    }
+    
+    /// Build from the project's dev container definition instead of the detected recipe
+    pub fn with_use_devcontainer(mut self, use_devcontainer: bool) -> Self {
+        self.use_devcontainer = use_devcontainer;
+        self
+    }
+    
+    /// Print one line per build step instead of drawing a progress bar
+    pub fn with_plain_progress(mut self, plain_progress: bool) -> Self {
+        self.plain_progress = plain_progress;
+        self
+    }
+    
+    /// Run only cached images, with no network, and fail whatever needs the network
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+    
+    /// Resolve the repository's commit against the remote rather than a recent resolution
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+    
+    /// When the server runs as the host user, so files it writes to mounts belong to them
+    pub fn with_user_map(mut self, user_map: UserMap) -> Self {
+        self.user_map = user_map;
+        self
+    }
+    
+    /// In MCP mode, check that the server answers `initialize` before connecting the client
+    pub fn with_health_check(mut self, health_check: bool) -> Self {
+        self.health_check = health_check;
+        self
+    }
+    
+    /// Abort if the server stays silent this long after the client's first message
+    pub fn with_startup_timeout(mut self, startup_timeout: Option<Duration>) -> Self {
+        self.startup_timeout = startup_timeout;
+        self
+    }
+    
+    /// In MCP mode, record every JSON-RPC frame of the session to `path`, secrets masked
+    pub fn with_trace_mcp(mut self, path: Option<PathBuf>) -> Self {
+        self.trace_mcp = path;
+        self
+    }
+    
+    /// Pass the host's `HTTP_PROXY` and friends to the build and the server, by name only
+    pub fn with_forward_proxy(mut self, forward_proxy: bool) -> Self {
+        self.forward_proxy = forward_proxy;
+        self
+    }
+    
+    /// Keep the container in UTC with no locale, and skip installing `tzdata`
+    pub fn with_no_forward_timezone(mut self, no_forward_timezone: bool) -> Self {
+        self.no_forward_timezone = no_forward_timezone;
+        self
+    }
+    
+    /// Restart the server after it crashes, as `policy` says
+    pub fn with_restart(mut self, policy: RestartPolicy) -> Self {
+        self.restart = policy;
+        self
+    }
+    
+    /// Trust the certificates in `bundle` in the build and the server
+    pub fn with_ca_bundle(mut self, bundle: Option<CaBundle>) -> Self {
+        self.ca_bundle = bundle;
+        self
+    }
+    
+    /// Report what the flow does to `sink`, which then owns the build's progress display
+    pub fn with_event_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.events = Events::new(sink);
+        self
+    }
+    
+    /// What the printed client entry runs: the source, the exact image or its `:latest` tag
+    pub fn with_config_mode(mut self, config_mode: ConfigMode) -> Self {
+        self.config_mode = config_mode;
+        self
+    }
+    
+    /// Give the image a `HEALTHCHECK` running `command`, whatever the project's `build.healthcheck`
+    pub fn with_image_healthcheck(mut self, command: Option<String>) -> Self {
+        self.image_healthcheck = command;
+        self
+    }
+    
+    /// Kill a build running longer than `timeout`, whatever the project's `build.timeout`
+    pub fn with_build_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.build_timeout = timeout;
+        self
+    }
+    
+    /// Build this workspace package of a Node.js monorepo
+    pub fn with_package(mut self, package: Option<String>) -> Self {
+        self.package = package;
+        self
+    }
+    
+    /// Start the server with this command instead of the detected one
+    pub fn with_command(mut self, command: Option<String>) -> Self {
+        self.command = command;
+        self
+    }
+    
+    /// Start the server with this bin or script when the project has several
+    pub fn with_entry(mut self, entry: Option<String>) -> Self {
+        self.entry = entry;
+        self
+    }
+    
+    /// Build options part of this repository's cache key
+    pub fn build_options_hash(&self) -> String {
+        let package_build = PackageBuild { package: self.package.as_deref(), command: self.command.as_deref(), entry: self.entry.as_deref() };
+        hash_package_build_options(self.network.is_host(), self.forward_registry, &self.env_vars, package_build, self.image_healthcheck.as_deref(), self.use_devcontainer, !self.no_forward_timezone, self.ca_bundle.as_ref())
+    }
+    
+    /// Client entry starting this repository's server again
+    fn client_entry(&self, image_name: &str) -> ClientEntry {
+        ClientEntry {
+            source: Some(self.repo_url.clone()),
+            image_name: image_name.to_string(),
+            env_vars: self.env_vars.clone(),
+            volumes: self.volumes.clone(),
+            options: client_config::source_options(&self.network, self.package.as_deref(), self.command.as_deref(), self.entry.as_deref()),
+            server_args: self.args.clone(),
+        }
+    }
+}
+
+/// Options for containerizing a local project directory
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct LocalContainerizeOptions {
     pub local_path: String,
     pub args: Vec<String>,
     pub env_vars: Vec<String>,
     pub volumes: Vec<String>,
-    pub host_network: bool,
+    pub network: NetworkMode,
+    pub publish: Vec<String>,
     pub forward_registry: bool,
     pub force_rebuild: bool,
+    /// Build without finch's layer cache as well (`--no-cache`)
+    pub no_cache: bool,
+    /// Refuse build contexts larger than this many bytes, over `build.maxContextSize` (`--max-context-size`)
+    pub max_context_size: Option<u64>,
+    /// Build from the project's dev container definition, if it has one (`--use-devcontainer`)
+    pub use_devcontainer: bool,
+    /// Print one line per build step instead of a progress bar (`--plain-progress`)
+    pub plain_progress: bool,
+    /// Never touch the network: only cached images run, with no network (`--offline`)
+    pub offline: bool,
+    /// When the server runs as the host user (`--user-map`, `--no-user-map`)
+    pub user_map: UserMap,
+    /// In MCP mode, check that the server answers `initialize` before connecting the client (`--health-check`)
+    pub health_check: bool,
+    /// Abort if the server writes nothing to stdout this long after the client's first message,
+    /// over `mcp.startupTimeout` (`--startup-timeout`)
+    pub startup_timeout: Option<Duration>,
+    /// In MCP mode, record every JSON-RPC frame to this NDJSON file (`--trace-mcp`)
+    pub trace_mcp: Option<PathBuf>,
+    /// Pass the host's proxy variables to the build and the server (`--forward-proxy`)
+    pub forward_proxy: bool,
+    /// Leave the host's timezone and locale out of the image and the server (`--no-forward-timezone`)
+    pub no_forward_timezone: bool,
+    /// Restart the server when it exits with an error (`--restart`)
+    pub restart: RestartPolicy,
+    /// CA certificates to trust in the build and the server (`--ca-cert`, `caCerts`)
+    pub ca_bundle: Option<CaBundle>,
+    /// Receives the flow's progress events (`with_event_sink`)
+    pub events: Events,
+    /// What the printed client entry runs (`--config-mode`)
+    pub config_mode: ConfigMode,
+    /// `HEALTHCHECK` command for the image, over `build.healthcheck` (`--image-healthcheck`)
+    pub image_healthcheck: Option<String>,
+    /// Kill a build running longer than this, over `build.timeout` (`--build-timeout`)
+    pub build_timeout: Option<Duration>,
+    /// Workspace package to build in a Node.js monorepo
+    pub package: Option<String>,
+    /// Start command replacing the detected one
+    pub command: Option<String>,
+    /// Bin or script to start, when the project has several
+    pub entry: Option<String>,
+}
+
+impl LocalContainerizeOptions {
+    /// Defaults to the bridge network with no extra args, env vars, volumes or ports
+    pub fn new(local_path: impl Into<String>) -> Self {
+        Self {
+            local_path: local_path.into(),
+            args: Vec::new(),
+            env_vars: Vec::new(),
+            volumes: Vec::new(),
+            network: NetworkMode::default(),
+            publish: Vec::new(),
+            forward_registry: false,
+            force_rebuild: false,
+            no_cache: false,
+            max_context_size: None,
+            use_devcontainer: false,
+            plain_progress: false,
+            offline: false,
+            user_map: UserMap::Auto,
+            health_check: false,
+            startup_timeout: None,
+            trace_mcp: None,
+            forward_proxy: false,
+            no_forward_timezone: false,
+            restart: RestartPolicy::No,
+            ca_bundle: None,
+            events: Events::default(),
+            config_mode: ConfigMode::default(),
+            image_healthcheck: None,
+            build_timeout: None,
+            package: None,
+            command: None,
+            entry: None,
+        }
+    }
+    
+    /// Extra arguments passed to the server
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+    
+    /// `KEY=VALUE` environment variables for the container
+    pub fn with_env_vars(mut self, env_vars: Vec<String>) -> Self {
+        self.env_vars = env_vars;
+        self
+    }
+    
+    /// `host:container` volume mounts
+    pub fn with_volumes(mut self, volumes: Vec<String>) -> Self {
+        self.volumes = volumes;
+        self
+    }
+    
+    pub fn with_network(mut self, network: NetworkMode) -> Self {
+        self.network = network;
+        self
+    }
+    
+    /// `host:container` port mappings
+    pub fn with_publish(mut self, publish: Vec<String>) -> Self {
+        self.publish = publish;
+        self
+    }
+    
+    /// Make the host's npm/pip registry config available to the build
+    pub fn with_forward_registry(mut self, forward_registry: bool) -> Self {
+        self.forward_registry = forward_registry;
+        self
+    }
+    
+    /// Build even if a cached image exists
+    pub fn with_force_rebuild(mut self, force_rebuild: bool) -> Self {
+        self.force_rebuild = force_rebuild;
+        self
+    }
+    
+    /// Build without finch's layer cache, so every step runs again
+    pub fn with_no_cache(mut self, no_cache: bool) -> Self {
+        self.no_cache = no_cache;
+        self
+    }
+    
+    /// Refuse build contexts larger than `bytes`, whatever the project's `build.maxContextSize`
+    pub fn with_max_context_size(mut self, bytes: Option<u64>) -> Self {
+        self.max_context_size = bytes;
+        self
+    }
+    
+    /// Build from the project's dev container definition instead of the detected recipe
+    pub fn with_use_devcontainer(mut self, use_devcontainer: bool) -> Self {
+        self.use_devcontainer = use_devcontainer;
+        self
+    }
+    
+    /// Print one line per build step instead of drawing a progress bar
+    pub fn with_plain_progress(mut self, plain_progress: bool) -> Self {
+        self.plain_progress = plain_progress;
+        self
+    }
+    
+    /// Run only cached images, with no network, and fail whatever needs the network
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+    
+    /// When the server runs as the host user, so files it writes to mounts belong to them
+    pub fn with_user_map(mut self, user_map: UserMap) -> Self {
+        self.user_map = user_map;
+        self
+    }
+    
+    /// In MCP mode, check that the server answers `initialize` before connecting the client
+    pub fn with_health_check(mut self, health_check: bool) -> Self {
+        self.health_check = health_check;
+        self
+    }
+    
+    /// Abort if the server stays silent this long after the client's first message
+    pub fn with_startup_timeout(mut self, startup_timeout: Option<Duration>) -> Self {
+        self.startup_timeout = startup_timeout;
+        self
+    }
+    
+    /// In MCP mode, record every JSON-RPC frame of the session to `path`, secrets masked
+    pub fn with_trace_mcp(mut self, path: Option<PathBuf>) -> Self {
+        self.trace_mcp = path;
+        self
+    }
+    
+    /// Pass the host's `HTTP_PROXY` and friends to the build and the server, by name only
+    pub fn with_forward_proxy(mut self, forward_proxy: bool) -> Self {
+        self.forward_proxy = forward_proxy;
+        self
+    }
+    
+    /// Keep the container in UTC with no locale, and skip installing `tzdata`
+    pub fn with_no_forward_timezone(mut self, no_forward_timezone: bool) -> Self {
+        self.no_forward_timezone = no_forward_timezone;
+        self
+    }
+    
+    /// Restart the server after it crashes, as `policy` says
+    pub fn with_restart(mut self, policy: RestartPolicy) -> Self {
+        self.restart = policy;
+        self
+    }
+    
+    /// Trust the certificates in `bundle` in the build and the server
+    pub fn with_ca_bundle(mut self, bundle: Option<CaBundle>) -> Self {
+        self.ca_bundle = bundle;
+        self
+    }
+    
+    /// Report what the flow does to `sink`, which then owns the build's progress display
+    pub fn with_event_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.events = Events::new(sink);
+        self
+    }
+    
+    /// What the printed client entry runs: the source, the exact image or its `:latest` tag
+    pub fn with_config_mode(mut self, config_mode: ConfigMode) -> Self {
+        self.config_mode = config_mode;
+        self
+    }
+    
+    /// Give the image a `HEALTHCHECK` running `command`, whatever the project's `build.healthcheck`
+    pub fn with_image_healthcheck(mut self, command: Option<String>) -> Self {
+        self.image_healthcheck = command;
+        self
+    }
+    
+    /// Kill a build running longer than `timeout`, whatever the project's `build.timeout`
+    pub fn with_build_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.build_timeout = timeout;
+        self
+    }
+    
+    /// Build this workspace package of a Node.js monorepo
+    pub fn with_package(mut self, package: Option<String>) -> Self {
+        self.package = package;
+        self
+    }
+    
+    /// Start the server with this command instead of the detected one
+    pub fn with_command(mut self, command: Option<String>) -> Self {
+        self.command = command;
+        self
+    }
+    
+    /// Start the server with this bin or script when the project has several
+    pub fn with_entry(mut self, entry: Option<String>) -> Self {
+        self.entry = entry;
+        self
+    }
+    
+    /// Build options part of this directory's cache key
+    pub fn build_options_hash(&self) -> String {
+        let package_build = PackageBuild { package: self.package.as_deref(), command: self.command.as_deref(), entry: self.entry.as_deref() };
+        hash_package_build_options(self.network.is_host(), self.forward_registry, &self.env_vars, package_build, self.image_healthcheck.as_deref(), self.use_devcontainer, !self.no_forward_timezone, self.ca_bundle.as_ref())
+    }
+    
+    /// Client entry starting this directory's server again, wherever the client runs it from
+    fn client_entry(&self, image_name: &str) -> ClientEntry {
+        let path = std::path::absolute(&self.local_path).unwrap_or_else(|_| PathBuf::from(&self.local_path));
+        ClientEntry {
+            source: Some(path.display().to_string()),
+            image_name: image_name.to_string(),
+            env_vars: self.env_vars.clone(),
+            volumes: self.volumes.clone(),
+            options: client_config::source_options(&self.network, self.package.as_deref(), self.command.as_deref(), self.entry.as_deref()),
+            server_args: self.args.clone(),
+        }
+    }
 }
 
-pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result<()> {
+/// Clone, build (or reuse) and run an MCP server from a git repository
+pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> crate::error::Result<()> {
+    git_containerize_and_run_inner(options).await.map_err(FinchMcpError::from)
+}
+
+async fn git_containerize_and_run_inner(mut options: GitContainerizeOptions) -> Result<()> {
     use console::style;
     
     // Initialize cache and content hasher
@@ -43,8 +595,8 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
     let content_hasher = ContentHasher::new();
     
     // Generate content hash for the git repository
-    let content_hash = content_hasher.hash_git_repository(&options.repo_url, None)?;
-    let build_options_hash = hash_build_options(options.host_network, options.forward_registry, &options.env_vars);
+    let content_hash = blocking(|| content_hasher.hash_git_repository(&options.repo_url, options.offline, options.refresh))?;
+    let build_options_hash = options.build_options_hash();
     
     // Check if we have a cached image
     if let Some(cached_image) = cache_manager.get_cached_image(&options.repo_url, &content_hash, &build_options_hash).await {
@@ -52,18 +604,13 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
             status!("🔨 Force rebuild requested, ignoring cached image: {}", style(&cached_image).cyan());
             info!("Force rebuild for git repository: {}", options.repo_url);
         } else {
+            options.events.emit(Event::CacheHit { image: cached_image.clone() });
             status!("⚡ Using cached image: {}", style(&cached_image).cyan());
             status!("💡 To rebuild, use: {}", style("finch-mcp run --force <target>").yellow());
             info!("Cache hit for git repository: {}", options.repo_url);
             
             // Prepare environment variables (MCP env vars are added by finch client)
-            let mut env_vars = options.env_vars;
-            
-            // Add extra arguments if provided
-            if !options.args.is_empty() {
-                let extra_args = options.args.join(" ");
-                env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-            }
+            let env_vars = options.env_vars;
             
             // Run the cached container
             status!("🚀 Starting server...\n");
@@ -73,16 +620,29 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
                 image_name: cached_image,
                 env_vars,
                 volumes: options.volumes,
-                host_network: options.host_network,
+                network: offline::network(options.offline, &options.network),
+                publish: options.publish.clone(),
+                args: options.args,
+                user_map: options.user_map,
+                health_check: options.health_check,
+                startup_timeout: options.startup_timeout,
+                trace_mcp: options.trace_mcp.clone(),
+                forward_proxy: options.forward_proxy,
+                no_forward_timezone: options.no_forward_timezone,
+                restart: options.restart,
+                events: options.events.clone(),
             };
             
-            return finch_client.run_stdio_container(&run_options, None).await;
+            return Ok(finch_client.run_stdio_container(&run_options, None).await?);
         }
     }
     
     // Cache miss - need to build
+    options.events.emit(Event::CacheMiss { source: options.repo_url.clone() });
     status!("🔨 Cache miss - building container...");
     
+    offline::ensure_online(options.offline, &format!("Cloning and building {}", options.repo_url))?;
+    
     // Initialize logging
     let log_manager = LogManager::new()?;
     let log_filename = log_manager.log_build_start("git", &options.repo_url)?;
@@ -90,18 +650,27 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
     
     // Parse and clone the repository
     let mut git_repo = GitRepository::new(&options.repo_url);
+    git_repo.commit = Some(content_hash.clone());
     
+    options.events.emit(Event::CloneStarted { repo_url: options.repo_url.clone() });
     status!("\n🔄 Cloning repository...");
     info!("Cloning repository: {}", git_repo.url);
     let repo_path = git_repo.clone_to_temp_quiet(crate::output::is_quiet_mode()).await?;
     
     // Detect the project type
-    let project_info = detect_project_type(&repo_path)?;
+    let project_info = detect_project(&repo_path, options.package.as_deref(), options.command.as_deref(), options.entry.as_deref())?;
     debug!("Detected project: {:?}", project_info);
     
     if project_info.project_type == ProjectType::Unknown {
-        return Err(anyhow::anyhow!("Could not detect project type in repository"));
+        return Err(FinchMcpError::UnsupportedProject { source: options.repo_url.clone() }.into());
+    }
+    report_start_command(&project_info, options.use_devcontainer);
+    if let Some(manifest) = &project_info.mcp_manifest {
+        env_vars::require(manifest, &mut options.env_vars)?;
     }
+    options.events.emit(Event::DetectionResult { project_type: format!("{:?}", project_info.project_type) });
+    
+    suggest_published_ports(&repo_path, &options.publish);
     
     // Load finch-mcp config if present
     let finch_config = FinchConfig::load_from_dir(&repo_path)?;
@@ -110,7 +679,7 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
     }
     
     // Generate smart, human-readable image name
-    let identifier = CacheManager::extract_identifier(&options.repo_url);
+    let identifier = image_identifier(&options.repo_url, &project_info);
     let image_name = cache_manager.generate_smart_image_name(
         "git",
         &format!("{:?}", project_info.project_type),
@@ -119,17 +688,19 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
     );
     
     // Create temp directory for Dockerfile
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+    let temp_dir = workspace::temp_dir("build")?;
     let dockerfile_path = temp_dir.path().join("Dockerfile");
     
     // Generate Dockerfile content based on project type
-    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, None)?;
+    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, None, options.image_healthcheck.as_deref(), options.use_devcontainer, !options.no_forward_timezone, options.ca_bundle.as_ref())?;
     debug!("Generated Dockerfile:\n{}", dockerfile_content);
     
     // Write Dockerfile
-    fs::write(&dockerfile_path, dockerfile_content).context("Failed to write Dockerfile")?;
+    fs::write(&dockerfile_path, &dockerfile_content).context("Failed to write Dockerfile")?;
     info!("Created Dockerfile at: {:?}", dockerfile_path);
     
+    context_size::check(&repo_path, finch_config.as_ref(), options.max_context_size)?;
+    
     // Copy repository contents to build context
     let build_context = temp_dir.path().join("context");
     fs::create_dir_all(&build_context).context("Failed to create build context directory")?;
@@ -145,6 +716,8 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
         ProjectType::NodeJs | ProjectType::NodeJsMonorepo => "Node.js",
         ProjectType::PythonPoetry => "Python (Poetry)",
         ProjectType::PythonUv => "Python (uv)",
+        ProjectType::PythonPipenv => "Python (Pipenv)",
+        ProjectType::PythonConda => "Python (Conda)",
         ProjectType::PythonSetupPy => "Python (setup.py)",
         ProjectType::PythonRequirements => "Python (requirements.txt)",
         ProjectType::Rust => "Rust",
@@ -155,21 +728,32 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
     build_command
         .arg("build")
         .arg("-t")
-        .arg(&image_name);
+        .arg(&image_name)
+        .arg("--label")
+        .arg(dockerfile_label(&dockerfile_content))
+        .arg("--label")
+        .arg(run_id::label());
     
     // Add host network option if enabled
-    if options.host_network {
+    if options.network.is_host() {
         build_command.arg("--network").arg("host");
     }
+    build_command.args(proxy::build_args(options.forward_proxy));
+    build_command.args(ca_certs::build_args(temp_dir.path(), options.ca_bundle.as_ref())?);
+    build_command.args(no_cache_build_args(options.no_cache));
+    build_command.args(registry_secrets::prepare_build(temp_dir.path(), options.forward_registry, &project_info)?);
     
     build_command.arg(&build_context);
     
     // Log build command
     log_manager.append_to_log(&log_filename, &format!("Build command: {:?}", build_command))?;
     
-    let build_result = run_build_with_progress(&mut build_command, &image_name, project_type_str);
+    options.events.emit(Event::BuildStarted { image: image_name.clone(), log_path: log_manager.log_path(&log_filename) });
+    let build_log = log_manager.open_sink(&log_filename)?;
+    let build_result = run_build_with_progress(&mut build_command, &image_name, project_type_str, &build_log, &build_policy(finch_config.as_ref(), options.build_timeout), options.plain_progress, &options.events);
     
     let build_duration = build_start.elapsed().as_secs();
+    options.events.emit(Event::BuildFinished { image: image_name.clone(), success: build_result.is_ok(), duration_secs: build_duration });
     
     match &build_result {
         Ok(_) => {
@@ -182,7 +766,7 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
         }
     }
     
-    build_result?;
+    build_result.map_err(|e| FinchMcpError::build_failed(log_manager.log_path(&log_filename), e.to_string()))?;
     
     // Tag the image with 'latest' as well
     let base_name = image_name.split(':').next().unwrap_or(&image_name);
@@ -204,24 +788,22 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
         &build_options_hash,
         &image_name,
         &format!("{:?}", project_info.project_type),
+        build_duration,
     )?;
+    cache_manager.store_dockerfile(&image_name, &dockerfile_content)?;
+    tags::after_build(&image_name).await;
+    gc::after_build(&image_name).await;
     
     status!("💾 Image cached for future use");
     
     // Output MCP configuration
-    output_mcp_config(&options.repo_url, &image_name, &options.env_vars)?;
+    output_mcp_config(&options.repo_url, &options.client_entry(&image_name), options.config_mode)?;
     
     // Prepare environment variables
     let mut env_vars = options.env_vars;
     env_vars.push("MCP_ENABLED=true".to_string());
     env_vars.push("MCP_STDIO=true".to_string());
     
-    // Add extra arguments if provided
-    if !options.args.is_empty() {
-        let extra_args = options.args.join(" ");
-        env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-    }
-    
     // Run the container
     status!("🚀 Starting server...\n");
     info!("Running containerized git repository");
@@ -230,13 +812,28 @@ pub async fn git_containerize_and_run(options: GitContainerizeOptions) -> Result
         image_name,
         env_vars,
         volumes: options.volumes,
-        host_network: options.host_network,
+        network: offline::network(options.offline, &options.network),
+        publish: options.publish.clone(),
+        args: options.args,
+        user_map: options.user_map,
+        health_check: options.health_check,
+        startup_timeout: options.startup_timeout,
+        trace_mcp: options.trace_mcp.clone(),
+        forward_proxy: options.forward_proxy,
+        no_forward_timezone: options.no_forward_timezone,
+        restart: options.restart,
+        events: options.events.clone(),
     };
     
-    finch_client.run_stdio_container(&run_options, Some(temp_dir.path())).await
+    Ok(finch_client.run_stdio_container(&run_options, Some(temp_dir.path())).await?)
+}
+
+/// Build (or reuse) and run an MCP server from a local directory
+pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> crate::error::Result<()> {
+    local_containerize_and_run_inner(options).await.map_err(FinchMcpError::from)
 }
 
-pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Result<()> {
+async fn local_containerize_and_run_inner(mut options: LocalContainerizeOptions) -> Result<()> {
     use console::style;
     
     // Debug: Log that we're using the regular function
@@ -259,7 +856,7 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
     
     // Generate content hash for the local directory
     let content_hash = content_hasher.hash_directory(&local_path)?;
-    let build_options_hash = hash_build_options(options.host_network, options.forward_registry, &options.env_vars);
+    let build_options_hash = options.build_options_hash();
     
     // Check if we have a cached image
     if let Some(cached_image) = cache_manager.get_cached_image(&options.local_path, &content_hash, &build_options_hash).await {
@@ -270,6 +867,7 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
             info!("Force rebuild for local directory: {}", options.local_path);
         } else {
             // In MCP mode, suppress all output to avoid corrupting the protocol
+            options.events.emit(Event::CacheHit { image: cached_image.clone() });
             if !crate::output::is_quiet_mode() {
                 status!("⚡ Using cached image: {}", style(&cached_image).cyan());
                 status!("💡 To rebuild, use: {}", style("finch-mcp run --force <target>").yellow());
@@ -277,13 +875,7 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
             info!("Cache hit for local directory: {}", options.local_path);
             
             // Prepare environment variables (MCP env vars are added by finch client)
-            let mut env_vars = options.env_vars;
-            
-            // Add extra arguments if provided
-            if !options.args.is_empty() {
-                let extra_args = options.args.join(" ");
-                env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-            }
+            let env_vars = options.env_vars;
             
             // Run the cached container
             if !crate::output::is_quiet_mode() {
@@ -295,16 +887,29 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
                 image_name: cached_image,
                 env_vars,
                 volumes: options.volumes,
-                host_network: options.host_network,
+                network: offline::network(options.offline, &options.network),
+                publish: options.publish.clone(),
+                args: options.args,
+                user_map: options.user_map,
+                health_check: options.health_check,
+                startup_timeout: options.startup_timeout,
+                trace_mcp: options.trace_mcp.clone(),
+                forward_proxy: options.forward_proxy,
+                no_forward_timezone: options.no_forward_timezone,
+                restart: options.restart,
+                events: options.events.clone(),
             };
             
-            return finch_client.run_stdio_container(&run_options, None).await;
+            return Ok(finch_client.run_stdio_container(&run_options, Some(&local_path)).await?);
         }
     }
     
     // Cache miss - need to build
+    options.events.emit(Event::CacheMiss { source: options.local_path.clone() });
     status!("🔨 Cache miss - building container...");
     
+    offline::ensure_online(options.offline, &format!("Building {}", options.local_path))?;
+    
     // Initialize logging
     let log_manager = LogManager::new()?;
     let log_filename = log_manager.log_build_start("local", &options.local_path)?;
@@ -314,15 +919,22 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
     info!("Containerizing local directory: {}", local_path.display());
     
     // Detect the project type
-    let project_info = detect_project_type(&local_path)?;
+    let project_info = detect_project(&local_path, options.package.as_deref(), options.command.as_deref(), options.entry.as_deref())?;
     debug!("Detected project: {:?}", project_info);
     
     if project_info.project_type == ProjectType::Unknown {
-        return Err(anyhow::anyhow!("Could not detect project type in directory"));
+        return Err(FinchMcpError::UnsupportedProject { source: options.local_path.clone() }.into());
+    }
+    report_start_command(&project_info, options.use_devcontainer);
+    if let Some(manifest) = &project_info.mcp_manifest {
+        env_vars::require(manifest, &mut options.env_vars)?;
     }
+    options.events.emit(Event::DetectionResult { project_type: format!("{:?}", project_info.project_type) });
+    
+    suggest_published_ports(&local_path, &options.publish);
     
     // Generate smart, human-readable image name
-    let identifier = CacheManager::extract_identifier(&options.local_path);
+    let identifier = image_identifier(&options.local_path, &project_info);
     let image_name = cache_manager.generate_smart_image_name(
         "local",
         &format!("{:?}", project_info.project_type),
@@ -331,7 +943,7 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
     );
     
     // Create temp directory for Dockerfile
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+    let temp_dir = workspace::temp_dir("build")?;
     let dockerfile_path = temp_dir.path().join("Dockerfile");
     
     // Load finch-mcp config if present
@@ -341,28 +953,25 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
     }
     
     // Generate Dockerfile content based on project type
-    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, finch_config.as_ref())?;
+    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, finch_config.as_ref(), options.image_healthcheck.as_deref(), options.use_devcontainer, !options.no_forward_timezone, options.ca_bundle.as_ref())?;
     debug!("Generated Dockerfile:\n{}", dockerfile_content);
     
     // Write Dockerfile
-    fs::write(&dockerfile_path, dockerfile_content).context("Failed to write Dockerfile")?;
+    fs::write(&dockerfile_path, &dockerfile_content).context("Failed to write Dockerfile")?;
     info!("Created Dockerfile at: {:?}", dockerfile_path);
     
-    // Create build context and copy local directory contents
-    let build_context = temp_dir.path().join("context");
-    fs::create_dir_all(&build_context).context("Failed to create build context directory")?;
-    
-    // Copy local directory files to build context
-    copy_dir_all(&local_path, &build_context).context("Failed to copy local directory to build context")?;
+    context_size::check(&local_path, finch_config.as_ref(), options.max_context_size)?;
     
-    // Copy Dockerfile to build context
-    fs::copy(&dockerfile_path, build_context.join("Dockerfile"))?;
+    // Build straight from the directory; the ignore file leaves out what a copy would
+    write_context_ignore(&dockerfile_path)?;
     
     // Build the container image with progress tracking
     let project_type_str = match project_info.project_type {
         ProjectType::NodeJs | ProjectType::NodeJsMonorepo => "Node.js",
         ProjectType::PythonPoetry => "Python (Poetry)",
         ProjectType::PythonUv => "Python (uv)",
+        ProjectType::PythonPipenv => "Python (Pipenv)",
+        ProjectType::PythonConda => "Python (Conda)",
         ProjectType::PythonSetupPy => "Python (setup.py)",
         ProjectType::PythonRequirements => "Python (requirements.txt)",
         ProjectType::Rust => "Rust",
@@ -373,21 +982,32 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
     build_command
         .arg("build")
         .arg("-t")
-        .arg(&image_name);
+        .arg(&image_name)
+        .arg("--label")
+        .arg(dockerfile_label(&dockerfile_content))
+        .arg("--label")
+        .arg(run_id::label());
     
     // Add host network option if enabled
-    if options.host_network {
+    if options.network.is_host() {
         build_command.arg("--network").arg("host");
     }
+    build_command.args(proxy::build_args(options.forward_proxy));
+    build_command.args(ca_certs::build_args(temp_dir.path(), options.ca_bundle.as_ref())?);
+    build_command.args(no_cache_build_args(options.no_cache));
+    build_command.args(registry_secrets::prepare_build(temp_dir.path(), options.forward_registry, &project_info)?);
     
-    build_command.arg(&build_context);
+    build_command.arg("-f").arg(&dockerfile_path).arg(&local_path);
     
     // Log build command
     log_manager.append_to_log(&log_filename, &format!("Build command: {:?}", build_command))?;
     
-    let build_result = run_build_with_progress(&mut build_command, &image_name, project_type_str);
+    options.events.emit(Event::BuildStarted { image: image_name.clone(), log_path: log_manager.log_path(&log_filename) });
+    let build_log = log_manager.open_sink(&log_filename)?;
+    let build_result = run_build_with_progress(&mut build_command, &image_name, project_type_str, &build_log, &build_policy(finch_config.as_ref(), options.build_timeout), options.plain_progress, &options.events);
     
     let build_duration = build_start.elapsed().as_secs();
+    options.events.emit(Event::BuildFinished { image: image_name.clone(), success: build_result.is_ok(), duration_secs: build_duration });
     
     match &build_result {
         Ok(_) => {
@@ -400,7 +1020,7 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
         }
     }
     
-    build_result?;
+    build_result.map_err(|e| FinchMcpError::build_failed(log_manager.log_path(&log_filename), e.to_string()))?;
     
     // Tag the image with 'latest' as well
     let base_name = image_name.split(':').next().unwrap_or(&image_name);
@@ -422,24 +1042,22 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
         &build_options_hash,
         &image_name,
         &format!("{:?}", project_info.project_type),
+        build_duration,
     )?;
+    cache_manager.store_dockerfile(&image_name, &dockerfile_content)?;
+    tags::after_build(&image_name).await;
+    gc::after_build(&image_name).await;
     
     status!("💾 Image cached for future use");
     
     // Output MCP configuration
-    output_mcp_config(&options.local_path, &image_name, &options.env_vars)?;
+    output_mcp_config(&options.local_path, &options.client_entry(&image_name), options.config_mode)?;
     
     // Prepare environment variables
     let mut env_vars = options.env_vars;
     env_vars.push("MCP_ENABLED=true".to_string());
     env_vars.push("MCP_STDIO=true".to_string());
     
-    // Add extra arguments if provided
-    if !options.args.is_empty() {
-        let extra_args = options.args.join(" ");
-        env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-    }
-    
     // Run the container
     status!("🚀 Starting server...\n");
     info!("Running containerized local directory");
@@ -448,45 +1066,64 @@ pub async fn local_containerize_and_run(options: LocalContainerizeOptions) -> Re
         image_name,
         env_vars,
         volumes: options.volumes,
-        host_network: options.host_network,
+        network: offline::network(options.offline, &options.network),
+        publish: options.publish.clone(),
+        args: options.args,
+        user_map: options.user_map,
+        health_check: options.health_check,
+        startup_timeout: options.startup_timeout,
+        trace_mcp: options.trace_mcp.clone(),
+        forward_proxy: options.forward_proxy,
+        no_forward_timezone: options.no_forward_timezone,
+        restart: options.restart,
+        events: options.events.clone(),
     };
     
-    finch_client.run_stdio_container(&run_options, Some(temp_dir.path())).await
+    Ok(finch_client.run_stdio_container(&run_options, Some(temp_dir.path())).await?)
 }
 
 /// Git containerize and run for MCP clients (build-then-run in one step)
-pub async fn git_containerize_and_run_mcp(options: GitContainerizeOptions) -> Result<()> {
-    use std::process::Stdio;
-    
+pub async fn git_containerize_and_run_mcp(options: GitContainerizeOptions) -> crate::error::Result<()> {
+    git_containerize_and_run_mcp_inner(options).await.map_err(FinchMcpError::from)
+}
+
+async fn git_containerize_and_run_mcp_inner(mut options: GitContainerizeOptions) -> Result<()> {
     // Initialize cache and content hasher
     let mut cache_manager = CacheManager::new()?;
     let content_hasher = ContentHasher::new();
     
     // Generate content hash for the git repository
-    let content_hash = content_hasher.hash_git_repository(&options.repo_url, None)?;
-    let build_options_hash = hash_build_options(options.host_network, options.forward_registry, &options.env_vars);
+    let content_hash = blocking(|| content_hasher.hash_git_repository(&options.repo_url, options.offline, options.refresh))?;
+    let build_options_hash = options.build_options_hash();
     
     // Check if we have a cached image
     if let Some(cached_image) = cache_manager.get_cached_image(&options.repo_url, &content_hash, &build_options_hash).await {
         // Run the cached container directly in MCP mode (MCP env vars are added by finch client)
-        let mut env_vars = options.env_vars;
-        
-        if !options.args.is_empty() {
-            let extra_args = options.args.join(" ");
-            env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-        }
+        let env_vars = options.env_vars;
         
         let finch_client = FinchClient::new();
         let run_options = StdioRunOptions {
             image_name: cached_image,
             env_vars,
             volumes: options.volumes,
-            host_network: options.host_network,
+            network: offline::network(options.offline, &options.network),
+            publish: options.publish.clone(),
+            args: options.args,
+            user_map: options.user_map,
+            health_check: options.health_check,
+            startup_timeout: options.startup_timeout,
+            trace_mcp: options.trace_mcp.clone(),
+            forward_proxy: options.forward_proxy,
+            no_forward_timezone: options.no_forward_timezone,
+            restart: options.restart,
+            events: options.events.clone(),
         };
         
-        return finch_client.run_stdio_container(&run_options, None).await;
+        return Ok(finch_client.run_stdio_container(&run_options, None).await?);
     }
     
+    offline::ensure_online(options.offline, &format!("Cloning and building {}", options.repo_url))?;
+    
     // Build the image first (with suppressed output for MCP)
     let log_manager = LogManager::new()?;
     let log_filename = log_manager.log_build_start("git-mcp", &options.repo_url)?;
@@ -494,17 +1131,22 @@ pub async fn git_containerize_and_run_mcp(options: GitContainerizeOptions) -> Re
     
     // Parse and clone the repository
     let mut git_repo = GitRepository::new(&options.repo_url);
+    git_repo.commit = Some(content_hash.clone());
     let repo_path = git_repo.clone_to_temp_quiet(true).await?; // Always quiet for MCP
     
     // Detect the project type
-    let project_info = detect_project_type(&repo_path)?;
+    let project_info = detect_project(&repo_path, options.package.as_deref(), options.command.as_deref(), options.entry.as_deref())?;
     
     if project_info.project_type == ProjectType::Unknown {
-        return Err(anyhow::anyhow!("Could not detect project type in repository"));
+        return Err(FinchMcpError::UnsupportedProject { source: options.repo_url.clone() }.into());
+    }
+    report_start_command(&project_info, options.use_devcontainer);
+    if let Some(manifest) = &project_info.mcp_manifest {
+        env_vars::require(manifest, &mut options.env_vars)?;
     }
     
     // Generate smart, human-readable image name
-    let identifier = CacheManager::extract_identifier(&options.repo_url);
+    let identifier = image_identifier(&options.repo_url, &project_info);
     let image_name = cache_manager.generate_smart_image_name(
         "git-mcp",
         &format!("{:?}", project_info.project_type),
@@ -512,13 +1154,18 @@ pub async fn git_containerize_and_run_mcp(options: GitContainerizeOptions) -> Re
         &content_hash
     );
     
+    // Load finch-mcp config if present
+    let finch_config = FinchConfig::load_from_dir(&repo_path)?;
+    
     // Create temp directory for Dockerfile
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+    let temp_dir = workspace::temp_dir("build")?;
     let dockerfile_path = temp_dir.path().join("Dockerfile");
     
     // Generate Dockerfile content based on project type
-    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, None)?;
-    fs::write(&dockerfile_path, dockerfile_content).context("Failed to write Dockerfile")?;
+    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, None, options.image_healthcheck.as_deref(), options.use_devcontainer, !options.no_forward_timezone, options.ca_bundle.as_ref())?;
+    fs::write(&dockerfile_path, &dockerfile_content).context("Failed to write Dockerfile")?;
+    
+    context_size::check(&repo_path, finch_config.as_ref(), options.max_context_size)?;
     
     // Copy repository contents to build context
     let build_context = temp_dir.path().join("context");
@@ -531,28 +1178,36 @@ pub async fn git_containerize_and_run_mcp(options: GitContainerizeOptions) -> Re
     build_command
         .arg("build")
         .arg("-t")
-        .arg(&image_name);
+        .arg(&image_name)
+        .arg("--label")
+        .arg(dockerfile_label(&dockerfile_content))
+        .arg("--label")
+        .arg(run_id::label());
     
-    if options.host_network {
+    if options.network.is_host() {
         build_command.arg("--network").arg("host");
     }
+    build_command.args(proxy::build_args(options.forward_proxy));
+    build_command.args(ca_certs::build_args(temp_dir.path(), options.ca_bundle.as_ref())?);
+    build_command.args(no_cache_build_args(options.no_cache));
+    build_command.args(registry_secrets::prepare_build(temp_dir.path(), options.forward_registry, &project_info)?);
     
     build_command
         .arg(&build_context);
     
+    // Log build command
+    log_manager.append_to_log(&log_filename, &format!("Build command: {:?}", build_command))?;
+    
     // Don't suppress output in MCP mode as it can interfere with stdio setup
     // Instead, let stderr show build progress while keeping stdout clean
-    let build_status = build_command
-        .stdout(Stdio::null())
-        .stderr(Stdio::inherit())
-        .status()
-        .context("Failed to execute finch build command")?;
+    let build_log = log_manager.open_sink(&log_filename)?;
+    let build_status = run_build_logged(&mut build_command, &build_log, true, &build_policy(finch_config.as_ref(), options.build_timeout), &options.events)?;
     let build_duration = build_start.elapsed().as_secs();
     
     if !build_status.success() {
         log_manager.append_to_log(&log_filename, &format!("Build failed with status: {}", build_status))?;
         log_manager.finish_build_log(&log_filename, false, build_duration)?;
-        return Err(anyhow::anyhow!("Container build failed with status: {}", build_status));
+        return Err(FinchMcpError::build_failed(log_manager.log_path(&log_filename), format!("finch build exited with {}", build_status)).into());
     }
     
     log_manager.append_to_log(&log_filename, "Build completed successfully")?;
@@ -565,33 +1220,44 @@ pub async fn git_containerize_and_run_mcp(options: GitContainerizeOptions) -> Re
         &build_options_hash,
         &image_name,
         &format!("{:?}", project_info.project_type),
+        build_duration,
     )?;
+    cache_manager.store_dockerfile(&image_name, &dockerfile_content)?;
+    tags::after_build(&image_name).await;
+    gc::after_build(&image_name).await;
     
     // Run the container directly
     let mut env_vars = options.env_vars;
     env_vars.push("MCP_ENABLED=true".to_string());
     env_vars.push("MCP_STDIO=true".to_string());
     
-    if !options.args.is_empty() {
-        let extra_args = options.args.join(" ");
-        env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-    }
-    
     let finch_client = FinchClient::new();
     let run_options = StdioRunOptions {
         image_name,
         env_vars,
         volumes: options.volumes,
-        host_network: options.host_network,
+        network: offline::network(options.offline, &options.network),
+        publish: options.publish.clone(),
+        args: options.args,
+        user_map: options.user_map,
+        health_check: options.health_check,
+        startup_timeout: options.startup_timeout,
+        trace_mcp: options.trace_mcp.clone(),
+        forward_proxy: options.forward_proxy,
+        no_forward_timezone: options.no_forward_timezone,
+        restart: options.restart,
+        events: options.events.clone(),
     };
     
-    finch_client.run_stdio_container(&run_options, Some(temp_dir.path())).await
+    Ok(finch_client.run_stdio_container(&run_options, Some(temp_dir.path())).await?)
 }
 
 /// Local containerize and run for MCP clients (build-then-run in one step)
-pub async fn local_containerize_and_run_mcp(options: LocalContainerizeOptions) -> Result<()> {
-    use std::process::Stdio;
-    
+pub async fn local_containerize_and_run_mcp(options: LocalContainerizeOptions) -> crate::error::Result<()> {
+    local_containerize_and_run_mcp_inner(options).await.map_err(FinchMcpError::from)
+}
+
+async fn local_containerize_and_run_mcp_inner(mut options: LocalContainerizeOptions) -> Result<()> {
     // Debug: Log that we're using the MCP function
     log::info!("🚨 DEBUG: Using local_containerize_and_run_MCP function");
     
@@ -612,43 +1278,54 @@ pub async fn local_containerize_and_run_mcp(options: LocalContainerizeOptions) -
     
     // Generate content hash for the local directory
     let content_hash = content_hasher.hash_directory(&local_path)?;
-    let build_options_hash = hash_build_options(options.host_network, options.forward_registry, &options.env_vars);
+    let build_options_hash = options.build_options_hash();
     
     // Check if we have a cached image
     if let Some(cached_image) = cache_manager.get_cached_image(&options.local_path, &content_hash, &build_options_hash).await {
         // Run the cached container directly in MCP mode (MCP env vars are added by finch client)
-        let mut env_vars = options.env_vars;
-        
-        if !options.args.is_empty() {
-            let extra_args = options.args.join(" ");
-            env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-        }
+        let env_vars = options.env_vars;
         
         let finch_client = FinchClient::new();
         let run_options = StdioRunOptions {
             image_name: cached_image,
             env_vars,
             volumes: options.volumes,
-            host_network: options.host_network,
+            network: offline::network(options.offline, &options.network),
+            publish: options.publish.clone(),
+            args: options.args,
+            user_map: options.user_map,
+            health_check: options.health_check,
+            startup_timeout: options.startup_timeout,
+            trace_mcp: options.trace_mcp.clone(),
+            forward_proxy: options.forward_proxy,
+            no_forward_timezone: options.no_forward_timezone,
+            restart: options.restart,
+            events: options.events.clone(),
         };
         
-        return finch_client.run_stdio_container(&run_options, None).await;
+        return Ok(finch_client.run_stdio_container(&run_options, Some(&local_path)).await?);
     }
     
+    offline::ensure_online(options.offline, &format!("Building {}", options.local_path))?;
+    
     // Build the image first (with suppressed output for MCP)
     let log_manager = LogManager::new()?;
     let log_filename = log_manager.log_build_start("local-mcp", &options.local_path)?;
     let build_start = std::time::Instant::now();
     
     // Detect the project type
-    let project_info = detect_project_type(&local_path)?;
+    let project_info = detect_project(&local_path, options.package.as_deref(), options.command.as_deref(), options.entry.as_deref())?;
     
     if project_info.project_type == ProjectType::Unknown {
-        return Err(anyhow::anyhow!("Could not detect project type in directory"));
+        return Err(FinchMcpError::UnsupportedProject { source: options.local_path.clone() }.into());
+    }
+    report_start_command(&project_info, options.use_devcontainer);
+    if let Some(manifest) = &project_info.mcp_manifest {
+        env_vars::require(manifest, &mut options.env_vars)?;
     }
     
     // Generate smart, human-readable image name
-    let identifier = CacheManager::extract_identifier(&options.local_path);
+    let identifier = image_identifier(&options.local_path, &project_info);
     let image_name = cache_manager.generate_smart_image_name(
         "local-mcp",
         &format!("{:?}", project_info.project_type),
@@ -657,7 +1334,7 @@ pub async fn local_containerize_and_run_mcp(options: LocalContainerizeOptions) -
     );
     
     // Create temp directory for Dockerfile
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+    let temp_dir = workspace::temp_dir("build")?;
     let dockerfile_path = temp_dir.path().join("Dockerfile");
     
     // Load finch-mcp config if present
@@ -667,42 +1344,51 @@ pub async fn local_containerize_and_run_mcp(options: LocalContainerizeOptions) -
     }
     
     // Generate Dockerfile content based on project type
-    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, finch_config.as_ref())?;
-    fs::write(&dockerfile_path, dockerfile_content).context("Failed to write Dockerfile")?;
+    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, finch_config.as_ref(), options.image_healthcheck.as_deref(), options.use_devcontainer, !options.no_forward_timezone, options.ca_bundle.as_ref())?;
+    fs::write(&dockerfile_path, &dockerfile_content).context("Failed to write Dockerfile")?;
     
-    // Create build context and copy local directory contents
-    let build_context = temp_dir.path().join("context");
-    fs::create_dir_all(&build_context).context("Failed to create build context directory")?;
-    copy_dir_all(&local_path, &build_context).context("Failed to copy local directory to build context")?;
-    fs::copy(&dockerfile_path, build_context.join("Dockerfile"))?;
+    context_size::check(&local_path, finch_config.as_ref(), options.max_context_size)?;
+    
+    // Build straight from the directory; the ignore file leaves out what a copy would
+    write_context_ignore(&dockerfile_path)?;
     
     // Build the container image (suppress output for MCP)
     let mut build_command = Command::new("finch");
     build_command
         .arg("build")
         .arg("-t")
-        .arg(&image_name);
+        .arg(&image_name)
+        .arg("--label")
+        .arg(dockerfile_label(&dockerfile_content))
+        .arg("--label")
+        .arg(run_id::label());
     
-    if options.host_network {
+    if options.network.is_host() {
         build_command.arg("--network").arg("host");
     }
+    build_command.args(proxy::build_args(options.forward_proxy));
+    build_command.args(ca_certs::build_args(temp_dir.path(), options.ca_bundle.as_ref())?);
+    build_command.args(no_cache_build_args(options.no_cache));
+    build_command.args(registry_secrets::prepare_build(temp_dir.path(), options.forward_registry, &project_info)?);
     
     build_command
-        .arg(&build_context);
+        .arg("-f")
+        .arg(&dockerfile_path)
+        .arg(&local_path);
+    
+    // Log build command
+    log_manager.append_to_log(&log_filename, &format!("Build command: {:?}", build_command))?;
     
     // Don't suppress output in MCP mode as it can interfere with stdio setup
     // Instead, let stderr show build progress while keeping stdout clean
-    let build_status = build_command
-        .stdout(Stdio::null())
-        .stderr(Stdio::inherit())
-        .status()
-        .context("Failed to execute finch build command")?;
+    let build_log = log_manager.open_sink(&log_filename)?;
+    let build_status = run_build_logged(&mut build_command, &build_log, true, &build_policy(finch_config.as_ref(), options.build_timeout), &options.events)?;
     let build_duration = build_start.elapsed().as_secs();
     
     if !build_status.success() {
         log_manager.append_to_log(&log_filename, &format!("Build failed with status: {}", build_status))?;
         log_manager.finish_build_log(&log_filename, false, build_duration)?;
-        return Err(anyhow::anyhow!("Container build failed with status: {}", build_status));
+        return Err(FinchMcpError::build_failed(log_manager.log_path(&log_filename), format!("finch build exited with {}", build_status)).into());
     }
     
     log_manager.append_to_log(&log_filename, "Build completed successfully")?;
@@ -715,81 +1401,36 @@ pub async fn local_containerize_and_run_mcp(options: LocalContainerizeOptions) -
         &build_options_hash,
         &image_name,
         &format!("{:?}", project_info.project_type),
+        build_duration,
     )?;
+    cache_manager.store_dockerfile(&image_name, &dockerfile_content)?;
+    tags::after_build(&image_name).await;
+    gc::after_build(&image_name).await;
     
     // Run the container directly
     let mut env_vars = options.env_vars;
     env_vars.push("MCP_ENABLED=true".to_string());
     env_vars.push("MCP_STDIO=true".to_string());
     
-    if !options.args.is_empty() {
-        let extra_args = options.args.join(" ");
-        env_vars.push(format!("EXTRA_ARGS={}", extra_args));
-    }
-    
     let finch_client = FinchClient::new();
     let run_options = StdioRunOptions {
         image_name,
         env_vars,
         volumes: options.volumes,
-        host_network: options.host_network,
+        network: offline::network(options.offline, &options.network),
+        publish: options.publish.clone(),
+        args: options.args,
+        user_map: options.user_map,
+        health_check: options.health_check,
+        startup_timeout: options.startup_timeout,
+        trace_mcp: options.trace_mcp.clone(),
+        forward_proxy: options.forward_proxy,
+        no_forward_timezone: options.no_forward_timezone,
+        restart: options.restart,
+        events: options.events.clone(),
     };
     
-    finch_client.run_stdio_container(&run_options, Some(temp_dir.path())).await
-}
-
-fn get_registry_config(forward_registry: bool, project_type: &ProjectType) -> Vec<String> {
-    if !forward_registry {
-        return Vec::new();
-    }
-    
-    let mut config_lines = Vec::new();
-    
-    match project_type {
-        ProjectType::NodeJs | ProjectType::NodeJsMonorepo => {
-            // Check for .npmrc in home directory
-            if let Ok(home) = std::env::var("HOME") {
-                let npmrc_path = format!("{}/.npmrc", home);
-                if std::path::Path::new(&npmrc_path).exists() {
-                    config_lines.push(format!("COPY --from=host {} /root/.npmrc", npmrc_path));
-                }
-            }
-            
-            // Forward common npm registry environment variables
-            if let Ok(registry) = std::env::var("NPM_CONFIG_REGISTRY") {
-                config_lines.push(format!("ENV NPM_CONFIG_REGISTRY={}", registry));
-            }
-            if let Ok(token) = std::env::var("NPM_TOKEN") {
-                config_lines.push(format!("ENV NPM_TOKEN={}", token));
-            }
-        }
-        
-        ProjectType::PythonPoetry | ProjectType::PythonUv | 
-        ProjectType::PythonSetupPy | ProjectType::PythonRequirements => {
-            // Check for pip.conf
-            if let Ok(home) = std::env::var("HOME") {
-                let pip_conf_path = format!("{}/.pip/pip.conf", home);
-                if std::path::Path::new(&pip_conf_path).exists() {
-                    config_lines.push(format!("COPY --from=host {} /root/.pip/pip.conf", pip_conf_path));
-                }
-            }
-            
-            // Forward common pip registry environment variables
-            if let Ok(index_url) = std::env::var("PIP_INDEX_URL") {
-                config_lines.push(format!("ENV PIP_INDEX_URL={}", index_url));
-            }
-            if let Ok(extra_index_url) = std::env::var("PIP_EXTRA_INDEX_URL") {
-                config_lines.push(format!("ENV PIP_EXTRA_INDEX_URL={}", extra_index_url));
-            }
-            if let Ok(trusted_host) = std::env::var("PIP_TRUSTED_HOST") {
-                config_lines.push(format!("ENV PIP_TRUSTED_HOST={}", trusted_host));
-            }
-        }
-        
-        _ => {}
-    }
-    
-    config_lines
+    Ok(finch_client.run_stdio_container(&run_options, Some(temp_dir.path())).await?)
 }
 
 /// Generate Dockerfile steps to modify package.json for selective dependency installation
@@ -876,13 +1517,24 @@ fs.writeFileSync('package.json', JSON.stringify(pkg, null, 2));
     Ok(steps.join("\n"))
 }
 
-fn generate_dockerfile_for_project(project_info: &ProjectInfo, args: &[String], forward_registry: bool, config: Option<&FinchConfig>) -> Result<String> {
-    let registry_config = get_registry_config(forward_registry, &project_info.project_type);
-    
-    match project_info.project_type {
+/// The Dockerfile for a detected project; `image_healthcheck` (`--image-healthcheck`) wins
+/// over the project's `build.healthcheck`, `use_devcontainer` (`--use-devcontainer`)
+/// builds from its dev container definition, and `forward_timezone` installs `tzdata`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn generate_dockerfile_for_project(project_info: &ProjectInfo, args: &[String], forward_registry: bool, config: Option<&FinchConfig>, image_healthcheck: Option<&str>, use_devcontainer: bool, forward_timezone: bool, ca_bundle: Option<&CaBundle>) -> Result<String> {
+    let secrets = registry_secrets::host_secrets(forward_registry, project_info);
+    // A command declared by the project's MCP manifest wins over the one guessed from its layout
+    let declared_command = project_info.start_command.clone()
+        .or_else(|| project_info.mcp_manifest.as_ref().and_then(|manifest| manifest.start_command()));
+    let typescript_build = project_info.typescript_build && !config.is_some_and(|cfg| cfg.build.skip);
+    let healthcheck = healthcheck::command(image_healthcheck, config.and_then(|cfg| cfg.build.healthcheck.as_ref()));
+    
+    let dockerfile = match project_info.project_type {
         ProjectType::PythonPoetry => {
             let python_version = project_info.python_version.as_deref().unwrap_or("3.11");
-            let entry_command = if let Some(ref entry_point) = project_info.entry_point {
+            let entry_command = if let Some(ref command) = declared_command {
+                command.clone()
+            } else if let Some(ref entry_point) = project_info.entry_point {
                 format!("poetry run {}", entry_point)
             } else if !args.is_empty() {
                 format!("poetry run python {}", args.join(" "))
@@ -890,17 +1542,11 @@ fn generate_dockerfile_for_project(project_info: &ProjectInfo, args: &[String],
                 "poetry run python -m src".to_string()
             };
             
-            let registry_section = if registry_config.is_empty() {
-                String::new()
-            } else {
-                format!("\n# Registry configuration\n{}\n", registry_config.join("\n"))
-            };
-            
             Ok(format!(
                 r#"FROM python:{}-slim
 
 WORKDIR /app
-{registry_section}
+
 # Install poetry
 RUN pip install poetry
 
@@ -918,17 +1564,19 @@ ENV MCP_ENABLED=true
 ENV MCP_STDIO=true
 
 # Run the application
-CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+{}
 "#,
                 python_version,
-                entry_command,
-                registry_section = registry_section
+                shell_entrypoint(&[], &entry_command)
             ))
         }
         
         ProjectType::PythonUv => {
             let python_version = project_info.python_version.as_deref().unwrap_or("3.11");
-            let entry_command = if let Some(ref entry_point) = project_info.entry_point {
+            let install_command = project_info.install_command.as_deref().unwrap_or("uv pip install --system -e .");
+            let entry_command = if let Some(ref command) = declared_command {
+                command.clone()
+            } else if let Some(ref entry_point) = project_info.entry_point {
                 entry_point.clone()
             } else if !args.is_empty() {
                 format!("python {}", args.join(" "))
@@ -936,17 +1584,11 @@ CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
                 "python -m src".to_string()
             };
             
-            let registry_section = if registry_config.is_empty() {
-                String::new()
-            } else {
-                format!("\n# Registry configuration\n{}\n", registry_config.join("\n"))
-            };
-            
             Ok(format!(
                 r#"FROM python:{}-slim
 
 WORKDIR /app
-{registry_section}
+
 # Install uv
 RUN pip install uv
 
@@ -954,106 +1596,182 @@ RUN pip install uv
 COPY . .
 
 # Install dependencies
-RUN uv pip install --system -e .
+RUN {install_command}
 
 # Set environment variables for MCP
 ENV MCP_ENABLED=true
 ENV MCP_STDIO=true
 
 # Run the application
-CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+{}
 "#,
                 python_version,
-                entry_command,
-                registry_section = registry_section
+                shell_entrypoint(&[], &entry_command),
+                install_command = install_command
             ))
         }
         
-        ProjectType::PythonSetupPy => {
+        ProjectType::PythonPipenv => {
             let python_version = project_info.python_version.as_deref().unwrap_or("3.11");
-            let entry_command = if !args.is_empty() {
+            let install_command = project_info.install_command.as_deref().unwrap_or("pipenv install --system --skip-lock");
+            let entry_command = if let Some(ref command) = declared_command {
+                command.clone()
+            } else if let Some(ref script) = project_info.entry_point {
+                format!("pipenv run {}", script)
+            } else if !args.is_empty() {
                 format!("python {}", args.join(" "))
             } else {
-                "python setup.py".to_string()
-            };
-            
-            let registry_section = if registry_config.is_empty() {
-                String::new()
-            } else {
-                format!("\n# Registry configuration\n{}\n", registry_config.join("\n"))
+                "python main.py".to_string()
             };
             
             Ok(format!(
                 r#"FROM python:{}-slim
 
 WORKDIR /app
-{registry_section}
+
+# Install pipenv
+RUN pip install pipenv
+
 # Copy project files
 COPY . .
 
-# Install dependencies
-RUN pip install -e .
+# Install dependencies into the system interpreter
+RUN {install_command}
 
 # Set environment variables for MCP
 ENV MCP_ENABLED=true
 ENV MCP_STDIO=true
 
 # Run the application
-CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+{}
 "#,
                 python_version,
-                entry_command,
-                registry_section = registry_section
+                shell_entrypoint(&[], &entry_command),
+                install_command = install_command
             ))
         }
         
-        ProjectType::PythonRequirements => {
-            let python_version = project_info.python_version.as_deref().unwrap_or("3.11");
-            let entry_command = if !args.is_empty() {
+        ProjectType::PythonConda => {
+            let install_command = project_info.install_command.as_deref()
+                .unwrap_or("micromamba install -y -n base -f environment.yml && micromamba clean --all --yes");
+            let entry_command = if let Some(ref command) = declared_command {
+                command.clone()
+            } else if !args.is_empty() {
                 format!("python {}", args.join(" "))
             } else {
                 "python main.py".to_string()
             };
             
-            let registry_section = if registry_config.is_empty() {
-                String::new()
-            } else {
-                format!("\n# Registry configuration\n{}\n", registry_config.join("\n"))
-            };
-            
+            // The micromamba image's entrypoint activates the base environment, so it runs first
             Ok(format!(
-                r#"FROM python:{}-slim
+                r#"FROM mambaorg/micromamba:bookworm-slim
 
 WORKDIR /app
-{registry_section}
+
 # Copy project files
-COPY . .
+COPY --chown=$MAMBA_USER:$MAMBA_USER . .
 
-# Install dependencies
-RUN pip install -r requirements.txt
+# Install the conda environment into the base environment
+RUN {install_command}
+
+# Activate the environment for any later RUN steps
+ARG MAMBA_DOCKERFILE_ACTIVATE=1
 
 # Set environment variables for MCP
 ENV MCP_ENABLED=true
 ENV MCP_STDIO=true
 
 # Run the application
-CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+{}
 "#,
-                python_version,
-                entry_command,
-                registry_section = registry_section
+                shell_entrypoint(&["/usr/local/bin/_entrypoint.sh"], &entry_command),
+                install_command = install_command
             ))
         }
         
-        ProjectType::NodeJs => {
-            let node_version = project_info.node_version.as_deref().unwrap_or("20");
-            
+        ProjectType::PythonSetupPy => {
+            let python_version = project_info.python_version.as_deref().unwrap_or("3.11");
+            let entry_command = if let Some(ref command) = declared_command {
+                command.clone()
+            } else if !args.is_empty() {
+                format!("python {}", args.join(" "))
+            } else {
+                "python setup.py".to_string()
+            };
+            
+            Ok(format!(
+                r#"FROM python:{}-slim
+
+WORKDIR /app
+
+# Copy project files
+COPY . .
+
+# Install dependencies
+RUN pip install -e .
+
+# Set environment variables for MCP
+ENV MCP_ENABLED=true
+ENV MCP_STDIO=true
+
+# Run the application
+{}
+"#,
+                python_version,
+                shell_entrypoint(&[], &entry_command)
+            ))
+        }
+        
+        ProjectType::PythonRequirements => {
+            let python_version = project_info.python_version.as_deref().unwrap_or("3.11");
+            let entry_command = if let Some(ref command) = declared_command {
+                command.clone()
+            } else if !args.is_empty() {
+                format!("python {}", args.join(" "))
+            } else {
+                "python main.py".to_string()
+            };
+            
+            Ok(format!(
+                r#"FROM python:{}-slim
+
+WORKDIR /app
+
+# Copy project files
+COPY . .
+
+# Install dependencies
+RUN pip install -r requirements.txt
+
+# Set environment variables for MCP
+ENV MCP_ENABLED=true
+ENV MCP_STDIO=true
+
+# Run the application
+{}
+"#,
+                python_version,
+                shell_entrypoint(&[], &entry_command)
+            ))
+        }
+        
+        ProjectType::NodeJs => {
+            let node_version = project_info.node_version.as_deref().unwrap_or("20");
+            
             // Determine if this package has bin entries that need global installation
             let has_bin_command = project_info.bin_command.is_some();
             
             // Determine install command based on config
             let package_manager = project_info.package_manager.as_deref().unwrap_or("npm");
-            let install_command = if let Some(cfg) = config {
+            let custom_install = config.and_then(|cfg| cfg.dependencies.install_command.clone());
+            let install_command = if typescript_build && custom_install.is_none() {
+                // The compiler and type packages are dev dependencies; they're pruned after the build
+                match package_manager {
+                    "pnpm" => "pnpm install",
+                    "yarn" => "yarn install",
+                    _ => "npm install",
+                }.to_string()
+            } else if let Some(cfg) = config {
                 cfg.get_install_command(package_manager)
             } else {
                 match package_manager {
@@ -1063,7 +1781,9 @@ CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
                 }.to_string()
             };
             
-            let entry_command = if let Some(ref run_cmd) = project_info.run_command {
+            let entry_command = if let Some(ref command) = declared_command {
+                command.clone()
+            } else if let Some(ref run_cmd) = project_info.run_command {
                 run_cmd.clone()
             } else if let Some(ref bin_cmd) = project_info.bin_command {
                 // Use the bin command name directly
@@ -1076,12 +1796,6 @@ CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
                 "npm start".to_string()
             };
             
-            let registry_section = if registry_config.is_empty() {
-                String::new()
-            } else {
-                format!("\n# Registry configuration\n{}\n", registry_config.join("\n"))
-            };
-            
             // Generate package.json modification steps if needed
             let package_json_steps = if let Some(cfg) = config {
                 if !cfg.dependencies.install_all && 
@@ -1096,10 +1810,23 @@ CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
             };
             
             // Generate appropriate build and install steps
-            let (build_steps, install_steps) = if has_bin_command {
+            let global_install = "# Install the package globally to create bin symlinks\nRUN npm install -g .\n\n".to_string();
+            let (build_steps, install_steps) = if typescript_build {
+                let build_command = config.and_then(|cfg| cfg.build.command.clone())
+                    .unwrap_or_else(|| format!("{} run build", package_manager));
+                let prune_command = match package_manager {
+                    "pnpm" => "pnpm prune --prod",
+                    "yarn" => "yarn install --production --ignore-scripts --prefer-offline",
+                    _ => "npm prune --omit=dev",
+                };
+                (
+                    format!("# Compile TypeScript, then drop dev dependencies\nRUN {}\nRUN {}\n\n", build_command, prune_command),
+                    if has_bin_command { global_install } else { String::new() },
+                )
+            } else if has_bin_command {
                 (
                     "# Build the package if needed\nRUN npm run build 2>/dev/null || echo \"No build script found, skipping...\"\n\n".to_string(),
-                    "# Install the package globally to create bin symlinks\nRUN npm install -g .\n\n".to_string()
+                    global_install,
                 )
             } else {
                 ("".to_string(), "".to_string())
@@ -1136,19 +1863,23 @@ ENV MCP_ENABLED=true
 ENV MCP_STDIO=true
 
 # Run the application
-CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+{}
 "#,
                 node_version,
-                registry_section,
+                corepack_section(project_info).map(|section| format!("\n{}", section)).unwrap_or_default(),
                 pre_install_section,
                 package_json_steps,
                 install_command,
                 build_steps,
                 install_steps,
-                entry_command
+                shell_entrypoint(&[], &entry_command)
             ))
         }
         
+        ProjectType::NodeJsMonorepo if project_info.workspace_package.is_some() => {
+            generate_workspace_dockerfile(project_info, declared_command, args)
+        }
+        
         ProjectType::NodeJsMonorepo => {
             let node_version = project_info.node_version.as_deref().unwrap_or("20");
             let package_manager = project_info.package_manager.as_deref().unwrap_or("npm");
@@ -1162,7 +1893,9 @@ CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
             // Determine if this package has bin entries that need global installation
             let has_bin_command = project_info.bin_command.is_some();
             
-            let entry_command = if let Some(ref run_cmd) = project_info.run_command {
+            let entry_command = if let Some(ref command) = declared_command {
+                command.clone()
+            } else if let Some(ref run_cmd) = project_info.run_command {
                 run_cmd.clone()
             } else if let Some(ref bin_cmd) = project_info.bin_command {
                 // Use the bin command name directly
@@ -1180,17 +1913,11 @@ CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
             };
             
             // For monorepo, we need to install the package manager first
-            let pm_install = match package_manager {
-                "pnpm" => "RUN npm install -g pnpm",
-                "yarn" => "RUN npm install -g yarn", 
-                _ => "",
-            };
-            
-            let registry_section = if registry_config.is_empty() {
-                String::new()
-            } else {
-                format!("\n# Registry configuration\n{}\n", registry_config.join("\n"))
-            };
+            let pm_install = corepack_section(project_info).unwrap_or_else(|| match package_manager {
+                "pnpm" => "RUN npm install -g pnpm".to_string(),
+                "yarn" => "RUN npm install -g yarn".to_string(),
+                _ => String::new(),
+            });
             
             // Generate appropriate build and install steps for monorepos
             let (build_steps, install_steps) = if has_bin_command {
@@ -1216,7 +1943,7 @@ CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
                 r#"FROM node:{}-slim
 
 WORKDIR /app
-{}
+
 # Install package manager if needed
 {}
 
@@ -1231,15 +1958,14 @@ ENV MCP_ENABLED=true
 ENV MCP_STDIO=true
 
 # Run the application
-CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+{}
 "#,
                 node_version,
-                registry_section,
                 pm_install,
                 install_command,
                 build_steps,
                 install_steps,
-                entry_command
+                shell_entrypoint(&[], &entry_command)
             ))
         }
         
@@ -1250,11 +1976,176 @@ CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
         ProjectType::Unknown => {
             Err(anyhow::anyhow!("Unknown project type cannot be containerized"))
         }
+    }?;
+
+    if let Some(devcontainer) = project_info.devcontainer.as_ref().filter(|_| use_devcontainer) {
+        // The start command from the generated Dockerfile, without the conda activation wrapper
+        let entrypoint = dockerfile.lines().rev().find(|line| line.starts_with("ENTRYPOINT ")).unwrap_or_default()
+            .replace("\"/usr/local/bin/_entrypoint.sh\", ", "");
+        let dockerfile = user_map::apply(timezone::apply(ca_certs::apply(registry_secrets::mount_secrets(devcontainer.dockerfile(&entrypoint), &secrets), ca_bundle), forward_timezone));
+        return Ok(healthcheck::apply(dockerfile, healthcheck.as_deref()));
+    }
+
+    let is_node = matches!(project_info.project_type, ProjectType::NodeJs | ProjectType::NodeJsMonorepo);
+    let native = is_node && !project_info.native_dependencies.is_empty();
+    let compiled = project_info.project_type == ProjectType::NodeJs && typescript_build;
+    if native || compiled {
+        let node_version = project_info.node_version.as_deref().unwrap_or("20");
+        let dockerfile = with_build_stage(&dockerfile, node_version, native);
+        return Ok(healthcheck::apply(user_map::apply(timezone::apply(ca_certs::apply(registry_secrets::mount_secrets(dockerfile, &secrets), ca_bundle), forward_timezone)), healthcheck.as_deref()));
     }
+    Ok(healthcheck::apply(user_map::apply(timezone::apply(ca_certs::apply(registry_secrets::mount_secrets(dockerfile, &secrets), ca_bundle), forward_timezone)), healthcheck.as_deref()))
 }
 
+/// Split a Node.js Dockerfile in two: everything up to the runtime settings becomes a build
+/// stage, and the slim runtime gets only `/app` and `/usr/local` (Node itself, global installs
+/// and corepack's package managers). With `toolchain`, for native dependencies, the build stage
+/// runs on the full `node` image, which has the python3, make and g++ node-gyp needs.
+/// `ENV` lines are repeated since stages don't share them
+fn with_build_stage(dockerfile: &str, node_version: &str, toolchain: bool) -> String {
+    const RUNTIME_MARKER: &str = "# Set environment variables for MCP";
+    let slim_image = format!("FROM node:{}-slim", node_version);
+    let Some((build, runtime)) = dockerfile.split_once(RUNTIME_MARKER) else {
+        return dockerfile.to_string();
+    };
+    if !build.starts_with(&slim_image) {
+        return dockerfile.to_string();
+    }
+    
+    let (build_image, comment) = if toolchain {
+        (format!("FROM node:{} AS build", node_version), "native dependencies are compiled with the full image's toolchain")
+    } else {
+        (format!("{} AS build", slim_image), "dev dependencies are only needed to compile")
+    };
+    let build = build.replacen(&slim_image, &build_image, 1);
+    let env_lines: Vec<&str> = build.lines().filter(|line| line.starts_with("ENV ")).collect();
+    let env_section = if env_lines.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n\n", env_lines.join("\n"))
+    };
+    
+    format!(
+        r#"# Build stage: {comment}
+{build}# Runtime stage
+{slim_image}
+
+WORKDIR /app
+COPY --from=build /usr/local /usr/local
+COPY --from=build /app /app
+
+{env_section}{RUNTIME_MARKER}{runtime}"#
+    )
+}
+
+/// Steps installing the package manager version the project pins, through corepack.
+/// Newer Node.js images no longer bundle corepack, so it is installed first if missing
+fn corepack_section(project_info: &ProjectInfo) -> Option<String> {
+    let version = project_info.package_manager_version.as_deref()?;
+    let package_manager = project_info.package_manager.as_deref().unwrap_or("npm");
+    Some(format!(
+        "# Install the {pm} version pinned by the project\n\
+         ENV COREPACK_ENABLE_DOWNLOAD_PROMPT=0 COREPACK_HOME=/usr/local/share/corepack\n\
+         RUN (command -v corepack >/dev/null || npm install -g corepack) && corepack enable {pm} && corepack prepare {pm}@{version} --activate\n",
+        pm = package_manager,
+    ))
+}
+
+/// Dockerfile for one package of a monorepo workspace: the whole workspace is installed so
+/// local dependencies resolve, then only the package (and, with pnpm, its dependencies) is built
+fn generate_workspace_dockerfile(project_info: &ProjectInfo, declared_command: Option<String>, args: &[String]) -> Result<String> {
+    let Some(package) = project_info.workspace_package.as_ref() else {
+        anyhow::bail!("No workspace package selected");
+    };
+    let node_version = project_info.node_version.as_deref().unwrap_or("20");
+    let package_manager = project_info.package_manager.as_deref().unwrap_or("npm");
+    
+    let (pm_install, install_command, build_command) = match package_manager {
+        "pnpm" => (
+            "RUN npm install -g pnpm\n".to_string(),
+            format!("pnpm install --filter {}...", package.name),
+            Some(format!("pnpm --filter {}... run build", package.name)),
+        ),
+        "yarn" => (
+            String::new(),
+            "yarn install".to_string(),
+            package.has_build_script.then(|| format!("yarn workspace {} run build", package.name)),
+        ),
+        _ => (
+            String::new(),
+            "npm install".to_string(),
+            Some("npm run build --workspaces --if-present".to_string()),
+        ),
+    };
+    let pm_install = corepack_section(project_info).unwrap_or(pm_install);
+    
+    let entry_command = if let Some(command) = declared_command {
+        // Declared by the package's own manifest, relative to its directory
+        format!("cd {} && {}", package.dir, command)
+    } else if let Some(ref run_cmd) = project_info.run_command {
+        run_cmd.clone()
+    } else if let Some(ref entry_point) = project_info.entry_point {
+        format!("node {}", entry_point)
+    } else if !args.is_empty() {
+        format!("node {}", args.join(" "))
+    } else {
+        anyhow::bail!("Workspace package {} has no bin, main or start script to run", package.name);
+    };
+    
+    let build_section = build_command
+        .map(|command| format!("# Build the package\nRUN {}\n\n", command))
+        .unwrap_or_default();
+    
+    Ok(format!(
+        r#"FROM node:{node_version}-slim
+
+WORKDIR /app
+
+# Install package manager if needed
+{pm_install}
+# Copy project files
+COPY . .
+
+# Install workspace dependencies for {name}
+RUN {install_command}
+
+{build_section}# Set environment variables for MCP
+ENV MCP_ENABLED=true
+ENV MCP_STDIO=true
+
+# Run the application
+{entrypoint}
+"#,
+        entrypoint = shell_entrypoint(&[], &entry_command),
+        name = package.name,
+    ))
+}
+
+/// Build and cache directories left out of build contexts, at any depth (as are hidden entries)
+pub(crate) const SKIPPED_DIRS: [&str; 5] = ["node_modules", "__pycache__", "target", "dist", "build"];
+
+/// Ignore rules matching what [`copy_dir_all`] leaves out
+fn context_ignore() -> String {
+    let mut rules = vec!["**/.*".to_string()];
+    rules.extend(SKIPPED_DIRS.iter().map(|name| format!("**/{}", name)));
+    rules.join("\n") + "\n"
+}
+
+/// Write the ignore file for building a local directory in place with `-f dockerfile_path`.
+/// BuildKit reads `<Dockerfile>.dockerignore` next to the Dockerfile in preference to the
+/// project's own `.dockerignore`, so the project directory is left untouched
+fn write_context_ignore(dockerfile_path: &Path) -> Result<()> {
+    let mut ignore_path = dockerfile_path.as_os_str().to_owned();
+    ignore_path.push(".dockerignore");
+    fs::write(&ignore_path, context_ignore()).context("Failed to write the build context ignore file")
+}
+
+/// Copy a project into a build context, leaving out hidden entries and [`SKIPPED_DIRS`].
+/// Symlinks are recreated rather than followed, so relative links such as a symlinked
+/// bin still resolve inside the context; file and directory permissions are kept.
+/// Sockets, FIFOs and devices can't be part of a context and are skipped with a warning
 fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
-    fs::create_dir_all(dst)?;
+    fs::create_dir_all(dst).with_context(|| format!("Failed to create {}", dst.display()))?;
     
     for entry in fs::read_dir(src)? {
         let entry = entry?;
@@ -1263,102 +2154,57 @@ fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
         
         // Skip hidden files and directories, and common build/cache directories
         if let Some(file_name) = name.to_str() {
-            if file_name.starts_with('.') 
-                || file_name == "node_modules" 
-                || file_name == "__pycache__" 
-                || file_name == "target" 
-                || file_name == "dist" 
-                || file_name == "build" {
+            if file_name.starts_with('.') || SKIPPED_DIRS.contains(&file_name) {
                 continue;
             }
         }
         
         let dst_path = dst.join(&name);
+        let file_type = entry.file_type()?;
         
-        if path.is_dir() {
+        if file_type.is_symlink() {
+            copy_symlink(&path, &dst_path)?;
+        } else if file_type.is_dir() {
             copy_dir_all(&path, &dst_path)?;
+            fs::set_permissions(&dst_path, entry.metadata()?.permissions())?;
+        } else if file_type.is_file() {
+            // fs::copy carries the permission bits over, executable ones included
+            fs::copy(&path, &dst_path).with_context(|| format!("Failed to copy {}", path.display()))?;
         } else {
-            fs::copy(&path, &dst_path)?;
+            warn!("Skipping {} in the build context: not a regular file, directory or symlink", path.display());
         }
     }
     
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::utils::project_detector::ProjectInfo;
-
-    #[test]
-    fn test_generate_dockerfile_python_poetry() {
-        let project_info = ProjectInfo {
-            project_type: ProjectType::PythonPoetry,
-            name: Some("test-server".to_string()),
-            entry_point: Some("test-server".to_string()),
-            bin_command: None,
-            install_command: Some("poetry install".to_string()),
-            run_command: None,
-            python_version: Some("3.11".to_string()),
-            node_version: None,
-            is_monorepo: false,
-            package_manager: None,
-        };
-        
-        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, None).unwrap();
-        assert!(dockerfile.contains("FROM python:3.11-slim"));
-        assert!(dockerfile.contains("RUN pip install poetry"));
-        assert!(dockerfile.contains("poetry run test-server"));
-    }
-
-    #[test]
-    fn test_generate_dockerfile_nodejs() {
-        let project_info = ProjectInfo {
-            project_type: ProjectType::NodeJs,
-            name: Some("test-server".to_string()),
-            entry_point: Some("index.js".to_string()),
-            bin_command: None,
-            install_command: Some("npm install".to_string()),
-            run_command: None,
-            python_version: None,
-            node_version: Some("20".to_string()),
-            is_monorepo: false,
-            package_manager: None,
-        };
-        
-        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, None).unwrap();
-        assert!(dockerfile.contains("FROM node:20-slim"));
-        assert!(dockerfile.contains("RUN npm install --production"));
-        assert!(dockerfile.contains("node index.js"));
-    }
+/// Recreate the symlink at `src` as `dst`, pointing at the same (possibly dangling) target
+#[cfg(unix)]
+fn copy_symlink(src: &Path, dst: &Path) -> Result<()> {
+    let target = fs::read_link(src)?;
+    std::os::unix::fs::symlink(&target, dst)
+        .with_context(|| format!("Failed to recreate symlink {} -> {}", dst.display(), target.display()))
+}
 
-    #[test]
-    fn test_generate_dockerfile_nodejs_with_bin_command() {
-        let project_info = ProjectInfo {
-            project_type: ProjectType::NodeJs,
-            name: Some("my-mcp-server".to_string()),
-            entry_point: Some("./bin/server.js".to_string()),
-            bin_command: Some("my-server".to_string()),
-            install_command: Some("npm install".to_string()),
-            run_command: None,
-            python_version: None,
-            node_version: Some("18".to_string()),
-            is_monorepo: false,
-            package_manager: None,
-        };
-        
-        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, None).unwrap();
-        assert!(dockerfile.contains("FROM node:18-slim"));
-        assert!(dockerfile.contains("RUN npm install --production"));
-        assert!(dockerfile.contains("npm run build"));
-        assert!(dockerfile.contains("npm install -g ."));
-        assert!(dockerfile.contains("my-server"));
-        assert!(!dockerfile.contains("node ./bin/server.js")); // Should use bin command, not direct file
+/// Creating symlinks may need privileges off Unix, so copy what the link points at
+#[cfg(not(unix))]
+fn copy_symlink(src: &Path, dst: &Path) -> Result<()> {
+    match fs::metadata(src) {
+        Ok(metadata) if metadata.is_dir() => copy_dir_all(src, dst),
+        Ok(_) => fs::copy(src, dst).map(|_| ()).with_context(|| format!("Failed to copy {}", src.display())),
+        Err(_) => {
+            warn!("Skipping dangling symlink {} in the build context", src.display());
+            Ok(())
+        }
     }
 }
 
 /// Build a container from a git repository without running it
-pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
+pub async fn git_build(options: GitContainerizeOptions) -> crate::error::Result<String> {
+    git_build_inner(options).await.map_err(FinchMcpError::from)
+}
+
+async fn git_build_inner(options: GitContainerizeOptions) -> Result<String> {
     use console::style;
     
     // Initialize cache and content hasher
@@ -1366,8 +2212,8 @@ pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
     let content_hasher = ContentHasher::new();
     
     // Generate content hash for the git repository
-    let content_hash = content_hasher.hash_git_repository(&options.repo_url, None)?;
-    let build_options_hash = hash_build_options(options.host_network, options.forward_registry, &options.env_vars);
+    let content_hash = blocking(|| content_hasher.hash_git_repository(&options.repo_url, options.offline, options.refresh))?;
+    let build_options_hash = options.build_options_hash();
     
     // Check if we have a cached image
     if let Some(cached_image) = cache_manager.get_cached_image(&options.repo_url, &content_hash, &build_options_hash).await {
@@ -1378,17 +2224,21 @@ pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
             status!("⚡ Image already built: {}", style(&cached_image).cyan());
             status!("💡 To rebuild, use: {}", style("finch-mcp build --force <target>").yellow());
             info!("Cache hit for git repository: {}", options.repo_url);
+            options.events.emit(Event::CacheHit { image: cached_image.clone() });
             
             // Output MCP configuration
-            output_mcp_config(&options.repo_url, &cached_image, &options.env_vars)?;
+            output_mcp_config(&options.repo_url, &options.client_entry(&cached_image), options.config_mode)?;
             
             return Ok(cached_image);
         }
     }
     
     // Cache miss or force rebuild - need to build
+    options.events.emit(Event::CacheMiss { source: options.repo_url.clone() });
     status!("🔨 Building container...");
     
+    offline::ensure_online(options.offline, &format!("Cloning and building {}", options.repo_url))?;
+    
     // Initialize logging
     let log_manager = LogManager::new()?;
     let log_filename = log_manager.log_build_start("git", &options.repo_url)?;
@@ -1396,18 +2246,23 @@ pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
     
     // Parse and clone the repository
     let mut git_repo = GitRepository::new(&options.repo_url);
+    git_repo.commit = Some(content_hash.clone());
     
+    options.events.emit(Event::CloneStarted { repo_url: options.repo_url.clone() });
     status!("\n🔄 Cloning repository...");
     info!("Cloning repository: {}", git_repo.url);
     let repo_path = git_repo.clone_to_temp_quiet(crate::output::is_quiet_mode()).await?;
     
     // Detect the project type
-    let project_info = detect_project_type(&repo_path)?;
+    let project_info = detect_project(&repo_path, options.package.as_deref(), options.command.as_deref(), options.entry.as_deref())?;
     debug!("Detected project: {:?}", project_info);
     
     if project_info.project_type == ProjectType::Unknown {
-        return Err(anyhow::anyhow!("Could not detect project type in repository"));
+        return Err(FinchMcpError::UnsupportedProject { source: options.repo_url.clone() }.into());
     }
+    report_start_command(&project_info, options.use_devcontainer);
+    warn_missing_env(&project_info, &options.env_vars);
+    options.events.emit(Event::DetectionResult { project_type: format!("{:?}", project_info.project_type) });
     
     // Load finch-mcp config if present
     let finch_config = FinchConfig::load_from_dir(&repo_path)?;
@@ -1416,7 +2271,7 @@ pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
     }
     
     // Generate smart, human-readable image name
-    let identifier = CacheManager::extract_identifier(&options.repo_url);
+    let identifier = image_identifier(&options.repo_url, &project_info);
     let image_name = cache_manager.generate_smart_image_name(
         "git",
         &format!("{:?}", project_info.project_type),
@@ -1425,17 +2280,19 @@ pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
     );
     
     // Create temp directory for Dockerfile
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+    let temp_dir = workspace::temp_dir("build")?;
     let dockerfile_path = temp_dir.path().join("Dockerfile");
     
     // Generate Dockerfile content based on project type
-    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, None)?;
+    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, None, options.image_healthcheck.as_deref(), options.use_devcontainer, !options.no_forward_timezone, options.ca_bundle.as_ref())?;
     debug!("Generated Dockerfile:\n{}", dockerfile_content);
     
     // Write Dockerfile
-    fs::write(&dockerfile_path, dockerfile_content).context("Failed to write Dockerfile")?;
+    fs::write(&dockerfile_path, &dockerfile_content).context("Failed to write Dockerfile")?;
     info!("Created Dockerfile at: {:?}", dockerfile_path);
     
+    context_size::check(&repo_path, finch_config.as_ref(), options.max_context_size)?;
+    
     // Copy repository contents to build context
     let build_context = temp_dir.path().join("context");
     fs::create_dir_all(&build_context).context("Failed to create build context directory")?;
@@ -1451,6 +2308,8 @@ pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
         ProjectType::NodeJs | ProjectType::NodeJsMonorepo => "Node.js",
         ProjectType::PythonPoetry => "Python (Poetry)",
         ProjectType::PythonUv => "Python (uv)",
+        ProjectType::PythonPipenv => "Python (Pipenv)",
+        ProjectType::PythonConda => "Python (Conda)",
         ProjectType::PythonSetupPy => "Python (setup.py)",
         ProjectType::PythonRequirements => "Python (requirements.txt)",
         ProjectType::Rust => "Rust",
@@ -1461,21 +2320,32 @@ pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
     build_command
         .arg("build")
         .arg("-t")
-        .arg(&image_name);
+        .arg(&image_name)
+        .arg("--label")
+        .arg(dockerfile_label(&dockerfile_content))
+        .arg("--label")
+        .arg(run_id::label());
     
     // Add host network option if enabled
-    if options.host_network {
+    if options.network.is_host() {
         build_command.arg("--network").arg("host");
     }
+    build_command.args(proxy::build_args(options.forward_proxy));
+    build_command.args(ca_certs::build_args(temp_dir.path(), options.ca_bundle.as_ref())?);
+    build_command.args(no_cache_build_args(options.no_cache));
+    build_command.args(registry_secrets::prepare_build(temp_dir.path(), options.forward_registry, &project_info)?);
     
     build_command.arg(&build_context);
     
     // Log build command
     log_manager.append_to_log(&log_filename, &format!("Build command: {:?}", build_command))?;
     
-    let build_result = run_build_with_progress(&mut build_command, &image_name, project_type_str);
+    options.events.emit(Event::BuildStarted { image: image_name.clone(), log_path: log_manager.log_path(&log_filename) });
+    let build_log = log_manager.open_sink(&log_filename)?;
+    let build_result = run_build_with_progress(&mut build_command, &image_name, project_type_str, &build_log, &build_policy(finch_config.as_ref(), options.build_timeout), options.plain_progress, &options.events);
     
     let build_duration = build_start.elapsed().as_secs();
+    options.events.emit(Event::BuildFinished { image: image_name.clone(), success: build_result.is_ok(), duration_secs: build_duration });
     
     match &build_result {
         Ok(_) => {
@@ -1488,7 +2358,7 @@ pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
         }
     }
     
-    build_result?;
+    build_result.map_err(|e| FinchMcpError::build_failed(log_manager.log_path(&log_filename), e.to_string()))?;
     
     // Tag the image with 'latest' as well
     let base_name = image_name.split(':').next().unwrap_or(&image_name);
@@ -1510,18 +2380,26 @@ pub async fn git_build(options: GitContainerizeOptions) -> Result<String> {
         &build_options_hash,
         &image_name,
         &format!("{:?}", project_info.project_type),
+        build_duration,
     )?;
+    cache_manager.store_dockerfile(&image_name, &dockerfile_content)?;
+    tags::after_build(&image_name).await;
+    gc::after_build(&image_name).await;
     
     status!("💾 Image cached for future use");
     
     // Output MCP configuration
-    output_mcp_config(&options.repo_url, &image_name, &options.env_vars)?;
+    output_mcp_config(&options.repo_url, &options.client_entry(&image_name), options.config_mode)?;
     
     Ok(image_name)
 }
 
 /// Build a container from a local directory without running it
-pub async fn local_build(options: LocalContainerizeOptions) -> Result<String> {
+pub async fn local_build(options: LocalContainerizeOptions) -> crate::error::Result<String> {
+    local_build_inner(options).await.map_err(FinchMcpError::from)
+}
+
+async fn local_build_inner(options: LocalContainerizeOptions) -> Result<String> {
     use console::style;
     
     let local_path = PathBuf::from(&options.local_path);
@@ -1540,8 +2418,8 @@ pub async fn local_build(options: LocalContainerizeOptions) -> Result<String> {
     let content_hasher = ContentHasher::new();
     
     // Generate content hash for the local directory
-    let content_hash = content_hasher.hash_directory(&local_path)?;
-    let build_options_hash = hash_build_options(options.host_network, options.forward_registry, &options.env_vars);
+    let content_hash = blocking(|| content_hasher.hash_directory(&local_path))?;
+    let build_options_hash = options.build_options_hash();
     
     // Check if we have a cached image
     if let Some(cached_image) = cache_manager.get_cached_image(&options.local_path, &content_hash, &build_options_hash).await {
@@ -1552,17 +2430,21 @@ pub async fn local_build(options: LocalContainerizeOptions) -> Result<String> {
             status!("⚡ Image already built: {}", style(&cached_image).cyan());
             status!("💡 To rebuild, use: {}", style("finch-mcp build --force <target>").yellow());
             info!("Cache hit for local directory: {}", options.local_path);
+            options.events.emit(Event::CacheHit { image: cached_image.clone() });
             
             // Output MCP configuration
-            output_mcp_config(&options.local_path, &cached_image, &options.env_vars)?;
+            output_mcp_config(&options.local_path, &options.client_entry(&cached_image), options.config_mode)?;
             
             return Ok(cached_image);
         }
     }
     
     // Cache miss or force rebuild - need to build
+    options.events.emit(Event::CacheMiss { source: options.local_path.clone() });
     status!("🔨 Building container...");
     
+    offline::ensure_online(options.offline, &format!("Building {}", options.local_path))?;
+    
     // Initialize logging
     let log_manager = LogManager::new()?;
     let log_filename = log_manager.log_build_start("local", &options.local_path)?;
@@ -1572,15 +2454,18 @@ pub async fn local_build(options: LocalContainerizeOptions) -> Result<String> {
     info!("Containerizing local directory: {}", local_path.display());
     
     // Detect the project type
-    let project_info = detect_project_type(&local_path)?;
+    let project_info = detect_project(&local_path, options.package.as_deref(), options.command.as_deref(), options.entry.as_deref())?;
     debug!("Detected project: {:?}", project_info);
     
     if project_info.project_type == ProjectType::Unknown {
-        return Err(anyhow::anyhow!("Could not detect project type in directory"));
+        return Err(FinchMcpError::UnsupportedProject { source: options.local_path.clone() }.into());
     }
+    report_start_command(&project_info, options.use_devcontainer);
+    warn_missing_env(&project_info, &options.env_vars);
+    options.events.emit(Event::DetectionResult { project_type: format!("{:?}", project_info.project_type) });
     
     // Generate smart, human-readable image name
-    let identifier = CacheManager::extract_identifier(&options.local_path);
+    let identifier = image_identifier(&options.local_path, &project_info);
     let image_name = cache_manager.generate_smart_image_name(
         "local",
         &format!("{:?}", project_info.project_type),
@@ -1589,7 +2474,7 @@ pub async fn local_build(options: LocalContainerizeOptions) -> Result<String> {
     );
     
     // Create temp directory for Dockerfile
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+    let temp_dir = workspace::temp_dir("build")?;
     let dockerfile_path = temp_dir.path().join("Dockerfile");
     
     // Load finch-mcp config if present
@@ -1599,28 +2484,25 @@ pub async fn local_build(options: LocalContainerizeOptions) -> Result<String> {
     }
     
     // Generate Dockerfile content based on project type
-    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, finch_config.as_ref())?;
+    let dockerfile_content = generate_dockerfile_for_project(&project_info, &options.args, options.forward_registry, finch_config.as_ref(), options.image_healthcheck.as_deref(), options.use_devcontainer, !options.no_forward_timezone, options.ca_bundle.as_ref())?;
     debug!("Generated Dockerfile:\n{}", dockerfile_content);
     
     // Write Dockerfile
-    fs::write(&dockerfile_path, dockerfile_content).context("Failed to write Dockerfile")?;
+    fs::write(&dockerfile_path, &dockerfile_content).context("Failed to write Dockerfile")?;
     info!("Created Dockerfile at: {:?}", dockerfile_path);
     
-    // Create build context and copy local directory contents
-    let build_context = temp_dir.path().join("context");
-    fs::create_dir_all(&build_context).context("Failed to create build context directory")?;
-    
-    // Copy local directory files to build context
-    copy_dir_all(&local_path, &build_context).context("Failed to copy local directory to build context")?;
+    context_size::check(&local_path, finch_config.as_ref(), options.max_context_size)?;
     
-    // Copy Dockerfile to build context
-    fs::copy(&dockerfile_path, build_context.join("Dockerfile"))?;
+    // Build straight from the directory; the ignore file leaves out what a copy would
+    write_context_ignore(&dockerfile_path)?;
     
     // Build the container image with progress tracking
     let project_type_str = match project_info.project_type {
         ProjectType::NodeJs | ProjectType::NodeJsMonorepo => "Node.js",
         ProjectType::PythonPoetry => "Python (Poetry)",
         ProjectType::PythonUv => "Python (uv)",
+        ProjectType::PythonPipenv => "Python (Pipenv)",
+        ProjectType::PythonConda => "Python (Conda)",
         ProjectType::PythonSetupPy => "Python (setup.py)",
         ProjectType::PythonRequirements => "Python (requirements.txt)",
         ProjectType::Rust => "Rust",
@@ -1631,21 +2513,32 @@ pub async fn local_build(options: LocalContainerizeOptions) -> Result<String> {
     build_command
         .arg("build")
         .arg("-t")
-        .arg(&image_name);
+        .arg(&image_name)
+        .arg("--label")
+        .arg(dockerfile_label(&dockerfile_content))
+        .arg("--label")
+        .arg(run_id::label());
     
     // Add host network option if enabled
-    if options.host_network {
+    if options.network.is_host() {
         build_command.arg("--network").arg("host");
     }
+    build_command.args(proxy::build_args(options.forward_proxy));
+    build_command.args(ca_certs::build_args(temp_dir.path(), options.ca_bundle.as_ref())?);
+    build_command.args(no_cache_build_args(options.no_cache));
+    build_command.args(registry_secrets::prepare_build(temp_dir.path(), options.forward_registry, &project_info)?);
     
-    build_command.arg(&build_context);
+    build_command.arg("-f").arg(&dockerfile_path).arg(&local_path);
     
     // Log build command
     log_manager.append_to_log(&log_filename, &format!("Build command: {:?}", build_command))?;
     
-    let build_result = run_build_with_progress(&mut build_command, &image_name, project_type_str);
+    options.events.emit(Event::BuildStarted { image: image_name.clone(), log_path: log_manager.log_path(&log_filename) });
+    let build_log = log_manager.open_sink(&log_filename)?;
+    let build_result = run_build_with_progress(&mut build_command, &image_name, project_type_str, &build_log, &build_policy(finch_config.as_ref(), options.build_timeout), options.plain_progress, &options.events);
     
     let build_duration = build_start.elapsed().as_secs();
+    options.events.emit(Event::BuildFinished { image: image_name.clone(), success: build_result.is_ok(), duration_secs: build_duration });
     
     match &build_result {
         Ok(_) => {
@@ -1658,7 +2551,7 @@ pub async fn local_build(options: LocalContainerizeOptions) -> Result<String> {
         }
     }
     
-    build_result?;
+    build_result.map_err(|e| FinchMcpError::build_failed(log_manager.log_path(&log_filename), e.to_string()))?;
     
     // Tag the image with 'latest' as well
     let base_name = image_name.split(':').next().unwrap_or(&image_name);
@@ -1680,62 +2573,435 @@ pub async fn local_build(options: LocalContainerizeOptions) -> Result<String> {
         &build_options_hash,
         &image_name,
         &format!("{:?}", project_info.project_type),
+        build_duration,
     )?;
+    cache_manager.store_dockerfile(&image_name, &dockerfile_content)?;
+    tags::after_build(&image_name).await;
+    gc::after_build(&image_name).await;
     
     status!("💾 Image cached for future use");
     
     // Output MCP configuration
-    output_mcp_config(&options.local_path, &image_name, &options.env_vars)?;
+    output_mcp_config(&options.local_path, &options.client_entry(&image_name), options.config_mode)?;
     
     Ok(image_name)
 }
 
-/// Output MCP configuration for MCP clients
-fn output_mcp_config(source_path: &str, image_name: &str, env_vars: &[String]) -> Result<()> {
+/// Print the `mcpServers` entry for the built server (see `--config-mode`) unless output is quiet
+fn output_mcp_config(source_path: &str, entry: &ClientEntry, mode: ConfigMode) -> Result<()> {
     use console::style;
     
+    // Also off while stdio carries an MCP session, as in `run --aggregate`
+    if crate::output::is_quiet_mode() {
+        return Ok(());
+    }
+    
     // Extract the server name from the path
     let server_name = CacheManager::extract_identifier(source_path)
         .to_lowercase()
         .replace('_', "-");
     
-    // Parse environment variables into a map
-    let mut env_map = serde_json::Map::new();
-    for env_var in env_vars {
-        if let Some((key, value)) = env_var.split_once('=') {
-            env_map.insert(key.to_string(), json!(value));
-        }
-    }
-    
-    // Build the configuration object
-    let config = json!({
-        server_name: {
-            "command": "finch-mcp",
-            "args": [
-                "run",
-                image_name
-            ],
-            "env": env_map
-        }
-    });
-    
-    // Pretty print the configuration
+    let config = json!({ server_name: entry.to_json(mode) });
     let config_str = serde_json::to_string_pretty(&config)?;
     
-    println!("\n{} MCP Server Configuration:", style("📋").blue());
-    println!("{}", style("Add this to your MCP client configuration:").dim());
-    println!("{}", style("─".repeat(60)).dim());
-    println!("{}", config_str);
-    println!("{}", style("─".repeat(60)).dim());
+    eprintln!("\n{} MCP Server Configuration:", style("📋").blue());
+    eprintln!("{}", style("Add this to your MCP client configuration:").dim());
+    eprintln!("{}", style("─".repeat(60)).dim());
+    eprintln!("{}", config_str);
+    eprintln!("{}", style("─".repeat(60)).dim());
     
     // Add helpful notes about environment variables and arguments
-    println!("\n{} Configuration Notes:", style("💡").yellow());
-    println!("• Environment variables: Check the MCP server's documentation for supported env vars");
-    println!("• Server arguments: Pass additional args via EXTRA_ARGS environment variable");
-    println!("  Example: \"env\": {{ \"EXTRA_ARGS\": \"--port 8080 --verbose\" }}");
+    eprintln!("\n{} Configuration Notes:", style("💡").yellow());
+    eprintln!("• Environment variables: Check the MCP server's documentation for supported env vars");
+    eprintln!("• Server arguments: Append them to \"args\" after \"--\"; each one is passed to the server as-is");
+    if mode == ConfigMode::Source {
+        eprintln!("• The entry runs the source, rebuilding when it changes; {} pins this build", style("--config-mode image").yellow());
+    }
     
-    println!("\n{} Container image: {}", style("🐳").cyan(), style(image_name).green());
-    println!("{} Latest tag: {}", style("🏷️").yellow(), style(format!("{}:latest", image_name.split(':').next().unwrap_or(image_name))).green());
+    eprintln!("\n{} Container image: {}", style("🐳").cyan(), style(&entry.image_name).green());
+    eprintln!("{} Latest tag: {}", style("🏷️").yellow(), style(client_config::latest_tag(&entry.image_name)).green());
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Point out ports the project's own Dockerfile exposes when none are published
+/// Detect the project and, in a Node.js monorepo, select the workspace package to build:
+/// `package` (from `--package`), else `build.package` in `.finch-mcp`
+pub(crate) fn detect_project(project_path: &Path, package: Option<&str>, command: Option<&str>, entry: Option<&str>) -> Result<ProjectInfo> {
+    let mut project_info = detect_project_type(project_path)?;
+    if project_info.project_type == ProjectType::Unknown {
+        return Ok(project_info);
+    }
+    let config = FinchConfig::load_from_dir(project_path)?.unwrap_or_default();
+    
+    let mut package = package.map(str::to_string).or(config.build.package);
+    if package.is_none() {
+        let choices = workspace_package_choices(&project_info, project_path)?;
+        if let Some(index) = prompt::choose("This monorepo has several MCP servers. Which package should run?", &choices)? {
+            package = Some(choices[index].clone());
+        }
+    }
+    select_workspace_package(&mut project_info, project_path, package.as_deref())?;
+    project_info.start_command = command.map(str::to_string).or(config.runtime.command);
+    
+    let declared = project_info.start_command.is_some()
+        || project_info.mcp_manifest.as_ref().is_some_and(|manifest| manifest.command.is_some());
+    match entry {
+        Some(entry) => select_entry(&mut project_info, entry)?,
+        None if !declared && project_info.entry_is_ambiguous() => {
+            let names: Vec<String> = project_info.entry_candidates.iter().map(|c| c.name.clone()).collect();
+            match prompt::choose("Several entry points found. Which one starts the MCP server?", &names)? {
+                Some(index) => select_entry(&mut project_info, &names[index])?,
+                None => anyhow::bail!("Several entry points found; pick one with --entry <NAME>: {}", names.join(", ")),
+            }
+        }
+        None => {}
+    }
+    Ok(project_info)
+}
+
+/// Image name identifier for a source; a workspace package gets its own
+pub(crate) fn image_identifier(source: &str, project_info: &ProjectInfo) -> String {
+    let identifier = CacheManager::extract_identifier(source);
+    match &project_info.workspace_package {
+        Some(package) => format!("{}-{}", identifier, package.name.rsplit('/').next().unwrap_or(&package.name)),
+        None => identifier,
+    }
+}
+
+/// Warn about required env vars the project's MCP manifest declares that aren't passed
+/// with `-e`; builds don't need them, the client entry does
+fn warn_missing_env(project_info: &ProjectInfo, env_vars: &[String]) {
+    let Some(manifest) = &project_info.mcp_manifest else {
+        return;
+    };
+    for var in manifest.missing_env(env_vars) {
+        match &var.description {
+            Some(description) => warn!("{} requires {} ({}), but it isn't set; pass it with -e {}=...", manifest.source, var.name, description, var.name),
+            None => warn!("{} requires {}, but it isn't set; pass it with -e {}=...", manifest.source, var.name, var.name),
+        }
+    }
+}
+
+/// Say where the start command comes from when it isn't detected
+fn report_start_command(project_info: &ProjectInfo, use_devcontainer: bool) {
+    use console::style;
+    
+    match (&project_info.devcontainer, use_devcontainer) {
+        (Some(devcontainer), true) => {
+            status!("🧰 Building from {}", style(&devcontainer.source).cyan());
+            if !devcontainer.features.is_empty() {
+                warn!("{} uses features, which aren't installed: {}", devcontainer.source, devcontainer.features.join(", "));
+            }
+        }
+        (Some(devcontainer), false) => status!("💡 Found {}; pass --use-devcontainer to build from it", devcontainer.source),
+        (None, true) => warn!("--use-devcontainer given, but there is no usable devcontainer.json; using the detected recipe"),
+        (None, false) => {}
+    }
+    if let Some(command) = &project_info.start_command {
+        status!("📋 Using start command {}", style(command).cyan());
+    }
+    let Some(manifest) = &project_info.mcp_manifest else {
+        return;
+    };
+    if let Some(command) = manifest.start_command().filter(|_| project_info.start_command.is_none()) {
+        status!("📋 Using start command from {}: {}", manifest.source, style(&command).cyan());
+    }
+}
+
+fn suggest_published_ports(project_path: &Path, publish: &[String]) {
+    use console::style;
+    
+    if !publish.is_empty() {
+        return;
+    }
+    
+    let exposed_ports = detect_exposed_ports(project_path);
+    if let Some(port) = exposed_ports.first() {
+        let (port_number, protocol) = match port.split_once('/') {
+            Some((number, protocol)) => (number, format!("/{}", protocol)),
+            None => (port.as_str(), String::new()),
+        };
+        status!("💡 Project Dockerfile exposes port(s) {}", exposed_ports.join(", "));
+        status!("   To reach them from the host, use: {}",
+            style(format!("--publish {0}:{0}{1}", port_number, protocol)).yellow());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::project_detector::ProjectInfo;
+
+    #[test]
+    fn test_generate_dockerfile_python_poetry() {
+        let project_info = ProjectInfo {
+            project_type: ProjectType::PythonPoetry,
+            name: Some("test-server".to_string()),
+            entry_point: Some("test-server".to_string()),
+            bin_command: None,
+            install_command: Some("poetry install".to_string()),
+            run_command: None,
+            python_version: Some("3.11".to_string()),
+            node_version: None,
+            is_monorepo: false,
+            package_manager: None,
+            package_manager_version: None,
+            native_dependencies: Vec::new(),
+            typescript_build: false,
+            start_command: None,
+            entry_candidates: Vec::new(),
+            mcp_manifest: None,
+            workspace_package: None,
+            devcontainer: None,
+        };
+        
+        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, None, None, false, true, None).unwrap();
+        assert!(dockerfile.contains("FROM python:3.11-slim"));
+        assert!(dockerfile.contains("RUN pip install poetry"));
+        assert!(dockerfile.contains("poetry run test-server"));
+    }
+
+    #[test]
+    fn test_generate_dockerfile_nodejs() {
+        let project_info = ProjectInfo {
+            project_type: ProjectType::NodeJs,
+            name: Some("test-server".to_string()),
+            entry_point: Some("index.js".to_string()),
+            bin_command: None,
+            install_command: Some("npm install".to_string()),
+            run_command: None,
+            python_version: None,
+            node_version: Some("20".to_string()),
+            is_monorepo: false,
+            package_manager: None,
+            package_manager_version: None,
+            native_dependencies: Vec::new(),
+            typescript_build: false,
+            start_command: None,
+            entry_candidates: Vec::new(),
+            mcp_manifest: None,
+            workspace_package: None,
+            devcontainer: None,
+        };
+        
+        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, None, None, false, true, None).unwrap();
+        assert!(dockerfile.contains("FROM node:20-slim"));
+        assert!(dockerfile.contains("RUN npm install --production"));
+        assert!(dockerfile.contains("node index.js"));
+        assert!(!dockerfile.contains("corepack"));
+        
+        let pinned = ProjectInfo { package_manager_version: Some("10.8.1".to_string()), ..project_info };
+        let dockerfile = generate_dockerfile_for_project(&pinned, &[], false, None, None, false, true, None).unwrap();
+        assert!(dockerfile.contains("corepack enable npm && corepack prepare npm@10.8.1 --activate"));
+        
+        let overridden = ProjectInfo { start_command: Some("node dist/server.js".to_string()), ..pinned.clone() };
+        let dockerfile = generate_dockerfile_for_project(&overridden, &[], false, None, None, false, true, None).unwrap();
+        assert!(dockerfile.contains(r#"ENTRYPOINT ["sh", "-c", "node dist/server.js \"$@\"", "mcp-server"]"#));
+
+        // The dev container is the recipe only with --use-devcontainer
+        let devcontainer = crate::utils::devcontainer::DevContainer {
+            source: ".devcontainer/devcontainer.json".to_string(),
+            base: crate::utils::devcontainer::DevContainerBase::Image("mcr.microsoft.com/devcontainers/typescript-node:20".to_string()),
+            setup_commands: Vec::new(),
+            container_env: Default::default(),
+            user: None,
+            features: Vec::new(),
+        };
+        let with_devcontainer = ProjectInfo { devcontainer: Some(devcontainer), ..overridden };
+        let dockerfile = generate_dockerfile_for_project(&with_devcontainer, &[], false, None, None, false, true, None).unwrap();
+        assert!(dockerfile.contains("FROM node:20-slim"));
+        let dockerfile = generate_dockerfile_for_project(&with_devcontainer, &[], false, None, None, true, true, None).unwrap();
+        assert!(dockerfile.contains("FROM mcr.microsoft.com/devcontainers/typescript-node:20"));
+        assert!(dockerfile.contains(r#"ENTRYPOINT ["sh", "-c", "node dist/server.js \"$@\"", "mcp-server"]"#));
+
+        // Quotes in --cmd stay inside a valid exec-form array
+        let quoted = ProjectInfo { start_command: Some(r#"node -e "require('./server')""#.to_string()), ..pinned };
+        let dockerfile = generate_dockerfile_for_project(&quoted, &[], false, None, None, false, true, None).unwrap();
+        assert!(dockerfile.contains(r#"ENTRYPOINT ["sh", "-c", "node -e \"require('./server')\" \"$@\"", "mcp-server"]"#), "{}", dockerfile);
+    }
+
+    #[test]
+    fn test_generate_dockerfile_typescript_build() {
+        let project_info = ProjectInfo {
+            project_type: ProjectType::NodeJs,
+            name: Some("ts-server".to_string()),
+            entry_point: Some("dist/index.js".to_string()),
+            bin_command: None,
+            install_command: Some("npm install".to_string()),
+            run_command: None,
+            python_version: None,
+            node_version: Some("20".to_string()),
+            is_monorepo: false,
+            package_manager: None,
+            package_manager_version: None,
+            native_dependencies: Vec::new(),
+            typescript_build: true,
+            start_command: None,
+            entry_candidates: Vec::new(),
+            mcp_manifest: None,
+            workspace_package: None,
+            devcontainer: None,
+        };
+        
+        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, None, None, false, true, None).unwrap();
+        let (build, runtime) = dockerfile.split_once("# Runtime stage").unwrap();
+        assert!(build.contains("FROM node:20-slim AS build"));
+        assert!(build.contains("RUN npm install\n"));
+        assert!(build.contains("RUN npm run build\nRUN npm prune --omit=dev"));
+        assert!(runtime.contains("COPY --from=build /app /app"));
+        assert!(runtime.contains("node dist/index.js"));
+        
+        let config: FinchConfig = serde_yaml::from_str("build:\n  skip: true\n").unwrap();
+        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, Some(&config), None, false, true, None).unwrap();
+        assert!(!dockerfile.contains("AS build"));
+        assert!(dockerfile.contains("RUN npm install --production"));
+    }
+
+    #[test]
+    fn test_generate_dockerfile_with_native_dependencies() {
+        let project_info = ProjectInfo {
+            project_type: ProjectType::NodeJs,
+            name: Some("db-server".to_string()),
+            entry_point: Some("index.js".to_string()),
+            bin_command: None,
+            install_command: Some("npm install".to_string()),
+            run_command: None,
+            python_version: None,
+            node_version: Some("22".to_string()),
+            is_monorepo: false,
+            package_manager: None,
+            package_manager_version: Some("10.8.1".to_string()),
+            native_dependencies: vec!["better-sqlite3".to_string()],
+            typescript_build: false,
+            start_command: None,
+            entry_candidates: Vec::new(),
+            mcp_manifest: None,
+            workspace_package: None,
+            devcontainer: None,
+        };
+        
+        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, None, None, false, true, None).unwrap();
+        let (build, runtime) = dockerfile.split_once("# Runtime stage").unwrap();
+        assert!(build.contains("FROM node:22 AS build"));
+        assert!(build.contains("RUN npm install --production"));
+        assert!(runtime.contains("FROM node:22-slim"));
+        assert!(runtime.contains("COPY --from=build /app /app"));
+        assert!(runtime.contains("ENV COREPACK_ENABLE_DOWNLOAD_PROMPT=0 COREPACK_HOME=/usr/local/share/corepack"));
+        assert!(runtime.contains("node index.js"));
+    }
+
+    #[test]
+    fn test_generate_dockerfile_for_workspace_package() {
+        use crate::utils::project_detector::WorkspacePackage;
+        
+        let project_info = ProjectInfo {
+            project_type: ProjectType::NodeJsMonorepo,
+            name: Some("@acme/weather".to_string()),
+            entry_point: Some("packages/weather/dist/index.js".to_string()),
+            bin_command: None,
+            install_command: Some("pnpm install".to_string()),
+            run_command: None,
+            python_version: None,
+            node_version: Some("20".to_string()),
+            is_monorepo: true,
+            package_manager: Some("pnpm".to_string()),
+            package_manager_version: None,
+            native_dependencies: Vec::new(),
+            typescript_build: false,
+            start_command: None,
+            entry_candidates: Vec::new(),
+            mcp_manifest: None,
+            workspace_package: Some(WorkspacePackage {
+                name: "@acme/weather".to_string(),
+                dir: "packages/weather".to_string(),
+                entry_point: Some("packages/weather/dist/index.js".to_string()),
+                has_start_script: false,
+                has_build_script: true,
+            }),
+            devcontainer: None,
+        };
+        
+        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, None, None, false, true, None).unwrap();
+        assert!(dockerfile.contains("RUN pnpm install --filter @acme/weather..."));
+        assert!(dockerfile.contains("RUN pnpm --filter @acme/weather... run build"));
+        assert!(dockerfile.contains("node packages/weather/dist/index.js"));
+        assert_eq!(image_identifier("https://github.com/acme/servers", &project_info), "servers-weather");
+    }
+    
+    #[test]
+    fn test_generate_dockerfile_nodejs_with_bin_command() {
+        let project_info = ProjectInfo {
+            project_type: ProjectType::NodeJs,
+            name: Some("my-mcp-server".to_string()),
+            entry_point: Some("./bin/server.js".to_string()),
+            bin_command: Some("my-server".to_string()),
+            install_command: Some("npm install".to_string()),
+            run_command: None,
+            python_version: None,
+            node_version: Some("18".to_string()),
+            is_monorepo: false,
+            package_manager: None,
+            package_manager_version: None,
+            native_dependencies: Vec::new(),
+            typescript_build: false,
+            start_command: None,
+            entry_candidates: Vec::new(),
+            mcp_manifest: None,
+            workspace_package: None,
+            devcontainer: None,
+        };
+        
+        let dockerfile = generate_dockerfile_for_project(&project_info, &[], false, None, None, false, true, None).unwrap();
+        assert!(dockerfile.contains("FROM node:18-slim"));
+        assert!(dockerfile.contains("RUN npm install --production"));
+        assert!(dockerfile.contains("npm run build"));
+        assert!(dockerfile.contains("npm install -g ."));
+        assert!(dockerfile.contains("my-server"));
+        assert!(!dockerfile.contains("node ./bin/server.js")); // Should use bin command, not direct file
+    }
+
+    #[test]
+    fn test_write_context_ignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let dockerfile_path = dir.path().join("Dockerfile");
+        write_context_ignore(&dockerfile_path).unwrap();
+
+        let ignore = fs::read_to_string(dir.path().join("Dockerfile.dockerignore")).unwrap();
+        let rules: Vec<&str> = ignore.lines().collect();
+        assert_eq!(rules[0], "**/.*");
+        for name in SKIPPED_DIRS {
+            assert!(rules.contains(&format!("**/{}", name).as_str()));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_all_keeps_symlinked_bins() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let src = tempfile::tempdir().unwrap();
+        fs::create_dir_all(src.path().join("bin")).unwrap();
+        fs::create_dir_all(src.path().join("lib")).unwrap();
+        fs::write(src.path().join("lib/server.js"), "#!/usr/bin/env node\n").unwrap();
+        fs::set_permissions(src.path().join("lib/server.js"), fs::Permissions::from_mode(0o755)).unwrap();
+        symlink("../lib/server.js", src.path().join("bin/server")).unwrap();
+        symlink("../missing.js", src.path().join("bin/dangling")).unwrap();
+        symlink("lib", src.path().join("lib-link")).unwrap();
+        fs::create_dir_all(src.path().join("node_modules/.bin")).unwrap();
+        let _socket = std::os::unix::net::UnixListener::bind(src.path().join("server.sock")).unwrap();
+
+        let dst = tempfile::tempdir().unwrap();
+        let context = dst.path().join("context");
+        copy_dir_all(src.path(), &context).unwrap();
+
+        assert_eq!(fs::read_link(context.join("bin/server")).unwrap(), Path::new("../lib/server.js"));
+        assert_eq!(fs::read_to_string(context.join("bin/server")).unwrap(), "#!/usr/bin/env node\n");
+        assert_eq!(fs::read_link(context.join("bin/dangling")).unwrap(), Path::new("../missing.js"));
+        assert_eq!(fs::read_link(context.join("lib-link")).unwrap(), Path::new("lib"));
+        let mode = fs::metadata(context.join("lib/server.js")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert!(!context.join("server.sock").exists());
+        assert!(!context.join("node_modules").exists());
+    }
+}
@@ -0,0 +1,84 @@
+//! `HEALTHCHECK` for generated images (`--image-healthcheck`, `build.healthcheck`)
+//!
+//! The default check only asks whether the server process (PID 1) is still running rather
+//! than stopped or a zombie, which works in any image without curl or an MCP client.
+//! Projects that can tell more, e.g. an HTTP endpoint, give their own command.
+
+use serde::{Deserialize, Serialize};
+
+/// Passes while PID 1 is neither stopped (`T`) nor a zombie (`Z`)
+pub const PROCESS_CHECK: &str = "! grep -Eq '^State:[[:space:]]+[ZT]' /proc/1/status";
+
+/// Timing of the check; a server is unhealthy after three failures in a row
+const OPTIONS: &str = "--interval=30s --timeout=5s --start-period=10s --retries=3";
+
+/// `build.healthcheck` in `.finch-mcp`: `true` for the process check, or a command
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum HealthcheckSetting {
+    Enabled(bool),
+    Command(String),
+}
+
+/// The command for `--image-healthcheck`; without one, the process check
+pub fn cli_command(command: &str) -> String {
+    if command.trim().is_empty() { PROCESS_CHECK } else { command.trim() }.to_string()
+}
+
+/// The healthcheck command for an image: `cli` (`--image-healthcheck`) wins over the project's setting
+pub fn command(cli: Option<&str>, setting: Option<&HealthcheckSetting>) -> Option<String> {
+    if let Some(command) = cli {
+        return Some(command.to_string());
+    }
+    match setting? {
+        HealthcheckSetting::Enabled(true) => Some(PROCESS_CHECK.to_string()),
+        HealthcheckSetting::Enabled(false) => None,
+        HealthcheckSetting::Command(command) if command.trim().is_empty() => Some(PROCESS_CHECK.to_string()),
+        HealthcheckSetting::Command(command) => Some(command.trim().to_string()),
+    }
+}
+
+/// Add a `HEALTHCHECK` running `command` just before the final `ENTRYPOINT`, so it lands in
+/// the runtime stage of multi-stage Dockerfiles
+pub fn apply(dockerfile: String, command: Option<&str>) -> String {
+    let Some(command) = command else {
+        return dockerfile;
+    };
+    let Some(entrypoint) = dockerfile.rfind("\nENTRYPOINT ") else {
+        return dockerfile;
+    };
+
+    let mut output = dockerfile[..=entrypoint].to_string();
+    output.push_str(&format!("# Report whether the server is still healthy\nHEALTHCHECK {} CMD {}\n\n", OPTIONS, command));
+    output.push_str(&dockerfile[entrypoint + 1..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let dockerfile = "FROM node:20 AS build\nRUN npm ci\n\nFROM node:20-slim\nCOPY --from=build /app /app\nENTRYPOINT [\"sh\", \"-c\", \"node index.js \\\"$@\\\"\", \"mcp-server\"]\n".to_string();
+        assert_eq!(apply(dockerfile.clone(), None), dockerfile);
+
+        let checked = apply(dockerfile, Some(PROCESS_CHECK));
+        let runtime = checked.split("FROM node:20-slim").nth(1).unwrap();
+        assert!(runtime.contains(&format!("HEALTHCHECK {} CMD {}\n", OPTIONS, PROCESS_CHECK)));
+        assert!(runtime.find("HEALTHCHECK").unwrap() < runtime.find("ENTRYPOINT").unwrap());
+        assert_eq!(checked.matches("HEALTHCHECK").count(), 1);
+    }
+
+    #[test]
+    fn test_command_from_setting() {
+        assert_eq!(command(None, None), None);
+        assert_eq!(command(None, Some(&HealthcheckSetting::Enabled(false))), None);
+        assert_eq!(command(None, Some(&HealthcheckSetting::Enabled(true))).as_deref(), Some(PROCESS_CHECK));
+        let setting: HealthcheckSetting = serde_yaml::from_str("curl -fs http://localhost:8080/health").unwrap();
+        assert_eq!(command(None, Some(&setting)).as_deref(), Some("curl -fs http://localhost:8080/health"));
+        assert_eq!(command(Some(PROCESS_CHECK), Some(&HealthcheckSetting::Enabled(false))).as_deref(), Some(PROCESS_CHECK));
+        assert_eq!(cli_command(" "), PROCESS_CHECK);
+        assert_eq!(cli_command(" curl -fs localhost "), "curl -fs localhost");
+    }
+}
@@ -0,0 +1,204 @@
+//! `finch-mcp inspect`: where a cached image came from and how it was built
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use console::style;
+use serde_json::{json, Value};
+use tokio::process::Command;
+
+use crate::cache::{CacheEntry, CacheManager, DOCKERFILE_LABEL};
+use crate::core::auto_containerize::AutoContainerizeOptions;
+use crate::core::git_containerize::LocalContainerizeOptions;
+use crate::core::plan::{plan_auto, plan_local};
+use crate::core::sbom::stored_sbom;
+use crate::finch::client::FinchClient;
+use crate::logging::LogManager;
+use crate::utils::git_repository::GitRepository;
+
+/// Everything known about one cached image
+#[derive(Debug, Clone)]
+pub struct ImageProvenance {
+    pub entry: CacheEntry,
+    pub cache_key: String,
+    /// Latest build log for the entry's source
+    pub build_log: Option<PathBuf>,
+    /// Image size in bytes, if finch still has the image
+    pub size_bytes: Option<u64>,
+    /// The Dockerfile the image was built from, or one regenerated from the current templates
+    pub dockerfile: Option<String>,
+    /// Whether `dockerfile` was recorded at build time rather than regenerated
+    pub dockerfile_recorded: bool,
+    /// SBOM stored by `build --sbom`
+    pub sbom: Option<PathBuf>,
+}
+
+impl ImageProvenance {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "image": self.entry.image_name,
+            "source": self.entry.source_path,
+            "projectType": self.entry.project_type,
+            "pinnedPackage": self.entry.pinned_package,
+            "runId": self.entry.run_id,
+            "contentHash": self.entry.content_hash,
+            "buildOptionsHash": self.entry.build_options_hash,
+            "cacheKey": self.cache_key,
+            "createdAt": timestamp(self.entry.created_at),
+            "lastAccessed": timestamp(self.entry.last_accessed),
+            "buildLog": self.build_log,
+            "sizeBytes": self.size_bytes,
+            "dockerfile": self.dockerfile,
+            "dockerfileRecorded": self.dockerfile_recorded,
+            "sbom": self.sbom,
+        })
+    }
+
+    /// Print the provenance for a human
+    pub fn print(&self) {
+        let entry = &self.entry;
+        eprintln!("{} {}", style("🔎").blue(), style(&entry.image_name).cyan().bold());
+        eprintln!("  Source:             {}", entry.source_path);
+        eprintln!("  Project type:       {}", entry.project_type);
+        if let Some(pinned_package) = &entry.pinned_package {
+            eprintln!("  Pinned package:     {}", pinned_package);
+        }
+        eprintln!("  Content hash:       {}", entry.content_hash);
+        eprintln!("  Build options hash: {}", entry.build_options_hash);
+        eprintln!("  Cache key:          {}", self.cache_key);
+        eprintln!("  Built:              {}", timestamp(entry.created_at));
+        if let Some(run_id) = &entry.run_id {
+            eprintln!("  Built by run:       {}", run_id);
+        }
+        eprintln!("  Last used:          {}", timestamp(entry.last_accessed));
+        match self.size_bytes {
+            Some(bytes) => eprintln!("  Size:               {:.1} MB", bytes as f64 / (1024.0 * 1024.0)),
+            None => eprintln!("  Size:               {}", style("image not found in finch").yellow()),
+        }
+        match &self.build_log {
+            Some(path) => eprintln!("  Build log:          {}", path.display()),
+            None => eprintln!("  Build log:          {}", style("none found").dim()),
+        }
+        if let Some(path) = &self.sbom {
+            eprintln!("  SBOM:               {}", path.display());
+        }
+
+        match &self.dockerfile {
+            Some(dockerfile) => {
+                let heading = if self.dockerfile_recorded {
+                    "Dockerfile (as built):"
+                } else {
+                    "Dockerfile (regenerated from the current templates, may differ from the build):"
+                };
+                eprintln!("\n{}", style(heading).bold());
+                for line in dockerfile.lines() {
+                    eprintln!("  {}", line);
+                }
+            }
+            None => eprintln!("\n{}", style("Dockerfile: not recorded for this build").dim()),
+        }
+    }
+}
+
+/// Provenance of every cache entry matching an image name or source
+pub async fn inspect(target: &str) -> Result<Vec<ImageProvenance>> {
+    let cache_manager = CacheManager::new()?;
+    let entries: Vec<CacheEntry> = cache_manager.find_entries(target).into_iter().cloned().collect();
+    if entries.is_empty() {
+        anyhow::bail!("No cached image matches '{}'. Run `finch-mcp cache stats` to see what is cached", target);
+    }
+
+    let log_manager = LogManager::new()?;
+    let finch_client = FinchClient::new();
+    let mut provenance = Vec::new();
+    for entry in entries {
+        let cache_key = cache_manager.generate_cache_key(&entry.source_path, &entry.content_hash, &entry.build_options_hash);
+        let build_log = log_manager.latest_build_log_for(&entry.source_path)?.map(|log| log.path);
+        let size_bytes = finch_client.image_size(&entry.image_name).await;
+        let recorded = match cache_manager.recorded_dockerfile(&entry.image_name) {
+            Some(dockerfile) => Some(dockerfile),
+            None => image_label(&entry.image_name, DOCKERFILE_LABEL).await,
+        };
+        let dockerfile_recorded = recorded.is_some();
+        let dockerfile = recorded.or_else(|| regenerate_dockerfile(&entry.source_path));
+        let sbom = stored_sbom(&entry.image_name);
+        provenance.push(ImageProvenance { entry, cache_key, build_log, size_bytes, dockerfile, dockerfile_recorded, sbom });
+    }
+    Ok(provenance)
+}
+
+/// Regenerate the Dockerfile for a local directory or command source, for images
+/// built before Dockerfiles were recorded
+pub(crate) fn regenerate_dockerfile(source: &str) -> Option<String> {
+    if GitRepository::is_git_url(source) {
+        return None;
+    }
+    if Path::new(source).is_dir() {
+        return plan_local(&LocalContainerizeOptions::new(source)).ok().map(|plan| plan.dockerfile);
+    }
+
+    let mut words = source.split_whitespace().map(str::to_string);
+    let command = words.next()?;
+    plan_auto(&AutoContainerizeOptions::new(command).with_args(words.collect())).ok().map(|plan| plan.dockerfile)
+}
+
+async fn image_label(image_name: &str, label: &str) -> Option<String> {
+    let format = format!("{{{{index .Config.Labels \"{}\"}}}}", label);
+    let output = Command::new("finch")
+        .args(["image", "inspect", "--format", &format, image_name])
+        .output()
+        .await
+        .ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim_end().to_string();
+    (output.status.success() && !value.is_empty() && value != "<no value>").then_some(value)
+}
+
+fn timestamp(secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(secs as i64, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regenerate_dockerfile() {
+        let dockerfile = regenerate_dockerfile("uvx mcp-server-time").unwrap();
+        assert!(dockerfile.contains("mcp-server-time"));
+        assert!(regenerate_dockerfile("https://github.com/user/repo").is_none());
+    }
+
+    #[test]
+    fn test_provenance_json() {
+        let provenance = ImageProvenance {
+            entry: CacheEntry {
+                content_hash: "abc123".to_string(),
+                image_name: "mcp-demo:abc12345".to_string(),
+                created_at: 0,
+                last_accessed: 0,
+                project_type: "NodeJs".to_string(),
+                source_path: "./demo".to_string(),
+                build_options_hash: "def456".to_string(),
+                run_count: 0,
+                last_run: None,
+                pinned_package: None,
+                run_id: None,
+                build_secs: None,
+            },
+            cache_key: "./demo:abc123:def456".to_string(),
+            build_log: None,
+            size_bytes: Some(1024),
+            dockerfile: None,
+            dockerfile_recorded: false,
+            sbom: None,
+        };
+        let json = provenance.to_json();
+        assert_eq!(json["image"], "mcp-demo:abc12345");
+        assert_eq!(json["createdAt"], "1970-01-01 00:00:00 UTC");
+        assert_eq!(json["sizeBytes"], 1024);
+        assert!(json["buildLog"].is_null());
+    }
+}
@@ -0,0 +1,195 @@
+//! `finch-mcp list`: finch-mcp's containers, and its images with their size and what
+//! the cache recorded about them (project type, source, build time, last run)
+
+use std::cmp::Reverse;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use console::style;
+
+use crate::cache::CacheManager;
+use crate::finch::client::FinchClient;
+use crate::status;
+use crate::utils::units::{format_age, format_size};
+
+/// Orders for the image table; without one, images are listed by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListSort {
+    /// Largest first
+    Size,
+    /// Most recently built first
+    Age,
+    /// Most recently run first, never-run images last
+    LastUsed,
+}
+
+/// An image and what is known about it; images finch-mcp has no cache entry for only
+/// have a name and size
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageRow {
+    pub image: String,
+    pub size: Option<u64>,
+    pub project_type: Option<String>,
+    pub source: Option<String>,
+    pub build_secs: Option<u64>,
+    pub built_at: Option<u64>,
+    pub runs: u64,
+    pub last_run: Option<u64>,
+}
+
+impl ImageRow {
+    fn new(image: &str, size: Option<u64>, cache_manager: Option<&CacheManager>) -> Self {
+        let entry = cache_manager.and_then(|cache_manager| cache_manager.entries().find(|entry| entry.image_name == image));
+        Self {
+            image: image.to_string(),
+            size,
+            project_type: entry.map(|entry| entry.project_type.clone()),
+            source: entry.map(|entry| entry.source_path.clone()),
+            build_secs: entry.and_then(|entry| entry.build_secs),
+            built_at: entry.map(|entry| entry.created_at),
+            runs: entry.map_or(0, |entry| entry.run_count),
+            last_run: entry.and_then(|entry| entry.last_run),
+        }
+    }
+}
+
+/// Print the containers and images
+pub async fn print_list(all: bool, sort: Option<ListSort>) -> Result<()> {
+    let finch_client = FinchClient::new();
+    status!("\n{} Finch-MCP Resources", style("📋").blue().bold());
+    status!("{}", "=".repeat(50));
+    status!("\n{} Containers:", style("🐳").cyan());
+    finch_client.print_mcp_containers(all).await?;
+
+    status!("\n{} Images:", style("💿").green());
+    let cache_manager = CacheManager::new().ok();
+    let mut rows = Vec::new();
+    for image in listed(&finch_client.mcp_images().await?) {
+        let size = finch_client.image_size(image).await;
+        rows.push(ImageRow::new(image, size, cache_manager.as_ref()));
+    }
+    if rows.is_empty() {
+        status!("{}", style("No finch-mcp images").dim());
+        return Ok(());
+    }
+
+    sort_rows(&mut rows, sort);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut lines = table(&rows, now).into_iter();
+    if let Some(header) = lines.next() {
+        eprintln!("{}", style(header).bold());
+    }
+    for line in lines {
+        eprintln!("{}", line);
+    }
+    status!();
+    Ok(())
+}
+
+/// The images worth a row: `:latest` tags point at another listed image, so they are
+/// left out unless their repository has no other tag
+fn listed(images: &[String]) -> Vec<&str> {
+    let repository = |image: &str| image.rsplit_once(':').map_or(image, |(repository, _)| repository).to_string();
+    images.iter()
+        .map(String::as_str)
+        .filter(|image| {
+            !image.ends_with(":latest")
+                || !images.iter().any(|other| other != image && repository(other) == repository(image))
+        })
+        .collect()
+}
+
+fn sort_rows(rows: &mut [ImageRow], sort: Option<ListSort>) {
+    rows.sort_by(|a, b| a.image.cmp(&b.image));
+    match sort {
+        Some(ListSort::Size) => rows.sort_by_key(|row| Reverse(row.size)),
+        Some(ListSort::Age) => rows.sort_by_key(|row| Reverse(row.built_at)),
+        Some(ListSort::LastUsed) => rows.sort_by_key(|row| Reverse(row.last_run)),
+        None => {}
+    }
+}
+
+/// The image table, header first
+fn table(rows: &[ImageRow], now: u64) -> Vec<String> {
+    let ago = |at: Option<u64>| at.map(|at| format!("{} ago", format_age(now.saturating_sub(at))));
+    let cells: Vec<[String; 8]> = rows.iter()
+        .map(|row| [
+            row.image.clone(),
+            row.project_type.clone().unwrap_or_else(|| "-".to_string()),
+            row.size.map(format_size).unwrap_or_else(|| "-".to_string()),
+            row.build_secs.map(|secs| format!("{}s", secs)).unwrap_or_else(|| "-".to_string()),
+            ago(row.built_at).unwrap_or_else(|| "-".to_string()),
+            row.runs.to_string(),
+            ago(row.last_run).unwrap_or_else(|| "never".to_string()),
+            row.source.clone().unwrap_or_else(|| "-".to_string()),
+        ])
+        .collect();
+    let header = ["IMAGE", "TYPE", "SIZE", "BUILD", "BUILT", "RUNS", "LAST USED", "SOURCE"].map(str::to_string);
+    let widths: Vec<usize> = (0..8)
+        .map(|column| cells.iter().chain([&header]).map(|row| row[column].chars().count()).max().unwrap_or(0))
+        .collect();
+
+    [header].iter().chain(&cells)
+        .map(|row| {
+            let line = format!(
+                "{:<w0$}  {:<w1$}  {:>w2$}  {:>w3$}  {:<w4$}  {:>w5$}  {:<w6$}  {}",
+                row[0], row[1], row[2], row[3], row[4], row[5], row[6], row[7],
+                w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3], w4 = widths[4], w5 = widths[5], w6 = widths[6],
+            );
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(image: &str, size: u64, built_at: u64, last_run: Option<u64>) -> ImageRow {
+        ImageRow { image: image.to_string(), size: Some(size), built_at: Some(built_at), last_run, ..Default::default() }
+    }
+
+    #[test]
+    fn test_listed() {
+        let images = ["mcp-demo:abc", "mcp-demo:latest", "mcp-pulled:latest", "mcp-time:def"].map(str::to_string);
+        assert_eq!(listed(&images), ["mcp-demo:abc", "mcp-pulled:latest", "mcp-time:def"]);
+    }
+
+    #[test]
+    fn test_sort_rows() {
+        let mut rows = vec![row("mcp-b:1", 300, 20, None), row("mcp-a:1", 100, 30, Some(40)), row("mcp-c:1", 200, 10, Some(50))];
+        let images = |rows: &[ImageRow]| rows.iter().map(|row| row.image.clone()).collect::<Vec<_>>();
+        sort_rows(&mut rows, None);
+        assert_eq!(images(&rows), ["mcp-a:1", "mcp-b:1", "mcp-c:1"]);
+        sort_rows(&mut rows, Some(ListSort::Size));
+        assert_eq!(images(&rows), ["mcp-b:1", "mcp-c:1", "mcp-a:1"]);
+        sort_rows(&mut rows, Some(ListSort::Age));
+        assert_eq!(images(&rows), ["mcp-a:1", "mcp-b:1", "mcp-c:1"]);
+        sort_rows(&mut rows, Some(ListSort::LastUsed));
+        assert_eq!(images(&rows), ["mcp-c:1", "mcp-a:1", "mcp-b:1"]);
+    }
+
+    #[test]
+    fn test_table() {
+        let day = 24 * 60 * 60;
+        let rows = vec![
+            ImageRow {
+                image: "mcp-local-notes:1a2b3c4d".to_string(),
+                size: Some(245_300_000),
+                project_type: Some("PythonUv".to_string()),
+                source: Some("/srv/notes".to_string()),
+                build_secs: Some(95),
+                built_at: Some(10 * day - 3 * day),
+                runs: 12,
+                last_run: Some(10 * day - 3600),
+            },
+            ImageRow { image: "mcp-pulled:latest".to_string(), ..Default::default() },
+        ];
+        assert_eq!(table(&rows, 10 * day), [
+            "IMAGE                     TYPE          SIZE  BUILD  BUILT   RUNS  LAST USED  SOURCE",
+            "mcp-local-notes:1a2b3c4d  PythonUv  245.3 MB    95s  3d ago    12  1h ago     /srv/notes",
+            "mcp-pulled:latest         -                -      -  -          0  never      -",
+        ]);
+    }
+}
@@ -0,0 +1,291 @@
+//! Dry-run plans: what `run` or `build` would do, without touching Finch
+//!
+//! A plan performs detection and Dockerfile generation, then reports the image
+//! name, cache key and the exact `finch` commands a real build would execute.
+//! Git targets are still cloned, since detection needs the source.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use console::style;
+
+use crate::cache::{dockerfile_label, no_cache_build_args, CacheManager, ContentHasher};
+use crate::core::{ca_certs, healthcheck, registry_secrets};
+use crate::core::ca_certs::CaBundle;
+use crate::utils::timezone;
+use crate::utils::user_map;
+use crate::core::auto_containerize::AutoContainerizeOptions;
+use crate::core::finch_config::FinchConfig;
+use crate::core::git_containerize::{detect_project, generate_dockerfile_for_project, image_identifier, GitContainerizeOptions, LocalContainerizeOptions};
+use crate::error::FinchMcpError;
+use crate::finch::client::{NetworkMode, StdioRunOptions};
+use crate::mcp::trace;
+use crate::utils::command_detector::{detect_command_type, generate_dockerfile_content};
+use crate::utils::git_repository::GitRepository;
+use crate::utils::project_detector::ProjectType;
+use crate::utils::proxy;
+
+/// Placeholder for the temporary build context a real build creates
+const BUILD_CONTEXT: &str = "<build-context>";
+
+/// Placeholder for the Dockerfile embedded as an image label
+const DOCKERFILE_PLACEHOLDER: &str = "<dockerfile>";
+
+/// What a build (and optionally a run) would do
+#[derive(Debug, Clone)]
+pub struct BuildPlan {
+    /// Repository URL, directory or command being containerized
+    pub source: String,
+    /// Detected project or command type, e.g. `NodeJs` or `PythonUvx`
+    pub project_type: String,
+    /// The Dockerfile that would be generated
+    pub dockerfile: String,
+    pub image_name: String,
+    pub cache_key: String,
+    /// Every `finch` command, in order, as argument lists
+    pub commands: Vec<Vec<String>>,
+}
+
+impl BuildPlan {
+    /// Add the `finch run` that would start the server, run as `run_options` says
+    pub fn with_run(mut self, mut run_options: StdioRunOptions) -> Self {
+        run_options.image_name = self.image_name.clone();
+        let mut command = vec!["finch".to_string()];
+        command.extend(run_options.finch_run_args());
+        self.commands.push(command);
+        self
+    }
+
+    /// Print the plan for a human
+    pub fn print(&self) {
+        eprintln!("{} Dry run for {}", style("📝").blue(), style(&self.source).cyan());
+        eprintln!("  Project type: {}", self.project_type);
+        eprintln!("  Image:        {}", self.image_name);
+        eprintln!("  Cache key:    {}", self.cache_key);
+        eprintln!("\n{}", style("Dockerfile:").bold());
+        for line in self.dockerfile.lines() {
+            eprintln!("  {}", line);
+        }
+        eprintln!("\n{}", style("Commands:").bold());
+        for command in &self.commands {
+            eprintln!("  {}", shell_join(command));
+        }
+        eprintln!("\n{}", style("Nothing was built or run.").dim());
+    }
+}
+
+/// Plan a build of a git repository (the repository is cloned for detection)
+pub async fn plan_git(options: &GitContainerizeOptions) -> Result<BuildPlan> {
+    let content_hash = ContentHasher::new().hash_git_repository(&options.repo_url, options.offline, options.refresh)?;
+    let mut git_repo = GitRepository::new(&options.repo_url);
+    git_repo.commit = Some(content_hash.clone());
+    let repo_path = git_repo.clone_to_temp_quiet(true).await?;
+    let source = ProjectSource {
+        source_type: "git",
+        path: &repo_path,
+        source: &options.repo_url,
+        content_hash,
+        package: options.package.as_deref(),
+        command: options.command.as_deref(),
+        entry: options.entry.as_deref(),
+        build_options_hash: options.build_options_hash(),
+        image_healthcheck: options.image_healthcheck.as_deref(),
+        use_devcontainer: options.use_devcontainer,
+        no_forward_timezone: options.no_forward_timezone,
+        ca_bundle: options.ca_bundle.as_ref(),
+    };
+    plan_project(source, &options.args, options.forward_registry, options.no_cache, options.forward_proxy, &options.network)
+}
+
+/// Plan a build of a local project directory
+pub fn plan_local(options: &LocalContainerizeOptions) -> Result<BuildPlan> {
+    let local_path = PathBuf::from(&options.local_path);
+    if !local_path.is_dir() {
+        return Err(anyhow::anyhow!("Path is not a directory: {}", options.local_path));
+    }
+    let source = ProjectSource {
+        source_type: "local",
+        path: &local_path,
+        source: &options.local_path,
+        content_hash: ContentHasher::new().hash_directory(&local_path)?,
+        package: options.package.as_deref(),
+        command: options.command.as_deref(),
+        entry: options.entry.as_deref(),
+        build_options_hash: options.build_options_hash(),
+        image_healthcheck: options.image_healthcheck.as_deref(),
+        use_devcontainer: options.use_devcontainer,
+        no_forward_timezone: options.no_forward_timezone,
+        ca_bundle: options.ca_bundle.as_ref(),
+    };
+    plan_project(source, &options.args, options.forward_registry, options.no_cache, options.forward_proxy, &options.network)
+}
+
+/// A cloned repository or local directory to plan a build for
+struct ProjectSource<'a> {
+    source_type: &'a str,
+    path: &'a Path,
+    source: &'a str,
+    content_hash: String,
+    /// Workspace package requested with `--package`
+    package: Option<&'a str>,
+    /// Start command given with `--cmd`
+    command: Option<&'a str>,
+    /// Entry point given with `--entry`
+    entry: Option<&'a str>,
+    build_options_hash: String,
+    /// `HEALTHCHECK` command given with `--image-healthcheck`
+    image_healthcheck: Option<&'a str>,
+    /// Build from the dev container definition (`--use-devcontainer`)
+    use_devcontainer: bool,
+    /// Leave `tzdata` out (`--no-forward-timezone`)
+    no_forward_timezone: bool,
+    /// CA certificates to install (`--ca-cert`)
+    ca_bundle: Option<&'a CaBundle>,
+}
+
+fn plan_project(project: ProjectSource, args: &[String], forward_registry: bool, no_cache: bool, forward_proxy: bool, network: &NetworkMode) -> Result<BuildPlan> {
+    let project_info = detect_project(project.path, project.package, project.command, project.entry)?;
+    if project_info.project_type == ProjectType::Unknown {
+        return Err(FinchMcpError::UnsupportedProject { source: project.source.to_string() }.into());
+    }
+    let project_type = format!("{:?}", project_info.project_type);
+
+    let finch_config = FinchConfig::load_from_dir(project.path)?;
+    let dockerfile = generate_dockerfile_for_project(&project_info, args, forward_registry, finch_config.as_ref(), project.image_healthcheck, project.use_devcontainer, !project.no_forward_timezone, project.ca_bundle)?;
+
+    let cache_manager = CacheManager::new()?;
+    let identifier = image_identifier(project.source, &project_info);
+    let image_name = cache_manager.generate_smart_image_name(project.source_type, &project_type, &identifier, &project.content_hash);
+    let cache_key = cache_manager.generate_cache_key(project.source, &project.content_hash, &project.build_options_hash);
+
+    let mut build = build_command(&image_name, network, no_cache, forward_proxy, project.ca_bundle);
+    for secret in registry_secrets::host_secrets(forward_registry, &project_info) {
+        build.extend(["--secret".to_string(), format!("id={},src=<{}>", secret.id, secret.id)]);
+    }
+    if project.source_type == "local" {
+        // Local directories are built in place, with only the Dockerfile generated
+        build.extend(["-f".to_string(), format!("{}/Dockerfile", BUILD_CONTEXT), project.source.to_string()]);
+    } else {
+        build.push(BUILD_CONTEXT.to_string());
+    }
+    let commands = vec![build, tag_command(&image_name)];
+
+    Ok(BuildPlan { source: project.source.to_string(), project_type, dockerfile, image_name, cache_key, commands })
+}
+
+/// Plan a build of a package-runner command such as `uvx mcp-server-time`
+pub fn plan_auto(options: &AutoContainerizeOptions) -> Result<BuildPlan> {
+    let content_hash = ContentHasher::new().hash_command(&options.command, &options.args)?;
+    let build_options_hash = options.build_options_hash();
+    let command_key = format!("{} {}", options.command, options.args.join(" "));
+
+    let command_details = detect_command_type(&options.command, &options.args);
+    let project_type = format!("{:?}", command_details.cmd_type);
+    let dockerfile = healthcheck::apply(user_map::apply(timezone::apply(ca_certs::apply(generate_dockerfile_content(&command_details), options.ca_bundle.as_ref()), !options.no_forward_timezone)), options.image_healthcheck.as_deref());
+
+    let cache_manager = CacheManager::new()?;
+    let identifier = CacheManager::extract_identifier(&command_key);
+    let image_name = cache_manager.generate_smart_image_name("auto", &project_type, &identifier, &content_hash);
+    let cache_key = cache_manager.generate_cache_key(&command_key, &content_hash, &build_options_hash);
+
+    let mut build = build_command(&image_name, &options.network, options.no_cache, options.forward_proxy, options.ca_bundle.as_ref());
+    build.extend(["-f".to_string(), format!("{}/Dockerfile", BUILD_CONTEXT), BUILD_CONTEXT.to_string()]);
+    let commands = vec![build, tag_command(&image_name)];
+
+    Ok(BuildPlan { source: command_key, project_type, dockerfile, image_name, cache_key, commands })
+}
+
+fn build_command(image_name: &str, network: &NetworkMode, no_cache: bool, forward_proxy: bool, ca_bundle: Option<&CaBundle>) -> Vec<String> {
+    let mut command: Vec<String> = ["finch", "build", "-t", image_name, "--label"].iter().map(|s| s.to_string()).collect();
+    command.push(dockerfile_label(DOCKERFILE_PLACEHOLDER));
+    if network.is_host() {
+        command.extend(["--network".to_string(), "host".to_string()]);
+    }
+    command.extend(proxy::build_args(forward_proxy));
+    if ca_bundle.is_some() {
+        command.extend(["--build-context".to_string(), format!("{0}={1}/{0}", ca_certs::CONTEXT_NAME, BUILD_CONTEXT)]);
+    }
+    command.extend(no_cache_build_args(no_cache));
+    command
+}
+
+fn tag_command(image_name: &str) -> Vec<String> {
+    let base_name = image_name.split(':').next().unwrap_or(image_name);
+    ["finch", "tag", image_name, &format!("{}:latest", base_name)].iter().map(|s| s.to_string()).collect()
+}
+
+/// Render a command for copy-pasting, quoting where needed and masking secret env values
+pub fn shell_join(command: &[String]) -> String {
+    command.iter()
+        .map(|arg| {
+            let arg = match arg.split_once('=') {
+                Some((key, _)) if trace::is_secret_key(key) => format!("{}=***", key),
+                _ => arg.clone(),
+            };
+            if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,<>".contains(c)) {
+                arg
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Events;
+    use crate::mcp::supervisor::RestartPolicy;
+    use crate::utils::user_map::UserMap;
+
+    #[test]
+    fn test_plan_auto() {
+        let options = AutoContainerizeOptions::new("uvx")
+            .with_args(vec!["mcp-server-time".to_string()])
+            .with_network(NetworkMode::Host);
+        let plan = plan_auto(&options).unwrap()
+            .with_run(StdioRunOptions {
+                image_name: String::new(),
+                env_vars: vec!["API_TOKEN=abc".to_string()],
+                volumes: Vec::new(),
+                network: NetworkMode::Host,
+                publish: Vec::new(),
+                args: Vec::new(),
+                user_map: UserMap::Auto,
+                health_check: false,
+                startup_timeout: None,
+                trace_mcp: None,
+                forward_proxy: false,
+                no_forward_timezone: false,
+                restart: RestartPolicy::No,
+                events: Events::default(),
+            });
+
+        assert_eq!(plan.project_type, "PythonUvx");
+        assert!(plan.dockerfile.contains("FROM"));
+        assert!(plan.image_name.starts_with("mcp-"));
+        assert!(plan.cache_key.starts_with("uvx mcp-server-time:"));
+
+        let commands: Vec<String> = plan.commands.iter().map(|command| shell_join(command)).collect();
+        assert_eq!(commands.len(), 3);
+        assert!(commands[0].starts_with(&format!("finch build -t {} --label dev.finch-mcp.dockerfile=<dockerfile> --network host -f", plan.image_name)));
+        assert!(commands[1].starts_with("finch tag"));
+        assert!(commands[2].contains("-e 'API_TOKEN=***'"));
+    }
+
+    #[test]
+    fn test_plan_local_reports_unsupported_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = LocalContainerizeOptions::new(dir.path().to_string_lossy());
+        let err = plan_local(&options).unwrap_err();
+        assert!(matches!(err.downcast_ref::<FinchMcpError>(), Some(FinchMcpError::UnsupportedProject { .. })));
+    }
+
+    #[test]
+    fn test_shell_join_quotes() {
+        let command: Vec<String> = ["finch", "run", "-e", "GREETING=hello world", "-e", "PASSWORD=x"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(shell_join(&command), "finch run -e 'GREETING=hello world' -e 'PASSWORD=***'");
+    }
+}
@@ -0,0 +1,341 @@
+//! Registry credentials for `--forward-registry`, passed to builds as BuildKit secrets
+//!
+//! The host's npm, pnpm, yarn and pip configuration (plus `NPM_TOKEN`, `PIP_INDEX_URL` and
+//! friends) is written to files outside the build context and handed to `finch build --secret`.
+//! Every `RUN` step mounts them where the package managers look for config, so installs can
+//! authenticate while the credentials never reach an image layer or the image history.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::utils::project_detector::{ProjectInfo, ProjectType};
+
+/// A registry config file handed to the build as a secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistrySecret {
+    /// Secret id for `--secret id=…` and `--mount=type=secret,id=…`
+    pub id: &'static str,
+    /// Where `RUN` steps see the file
+    pub targets: &'static [&'static str],
+    pub content: String,
+}
+
+/// npm and yarn classic read the global npmrc of the Node.js images' prefix; pnpm reads
+/// the user's, and installs run as root
+const NPMRC_TARGETS: &[&str] = &["/usr/local/etc/npmrc", "/root/.npmrc"];
+
+/// Yarn 2+ ignores npmrc files and reads the home directory's `.yarnrc.yml`
+const YARNRC_TARGETS: &[&str] = &["/root/.yarnrc.yml"];
+
+/// pip reads the site-wide config for every user
+const PIP_CONF_TARGETS: &[&str] = &["/etc/pip.conf"];
+
+/// The host's registry configuration for a project; nothing unless `forward_registry`
+pub fn host_secrets(forward_registry: bool, project_info: &ProjectInfo) -> Vec<RegistrySecret> {
+    if !forward_registry {
+        return Vec::new();
+    }
+    let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let read = |path: Option<PathBuf>| path.and_then(|path| std::fs::read_to_string(path).ok());
+    let home = dirs::home_dir();
+
+    let mut secrets = Vec::new();
+    match project_info.project_type {
+        ProjectType::NodeJs | ProjectType::NodeJsMonorepo => {
+            let mut npmrc = read(env("NPM_CONFIG_USERCONFIG").map(PathBuf::from).or_else(|| home.as_ref().map(|home| home.join(".npmrc"))));
+            // pnpm keeps its own user config in the same format
+            let pnpm_rc = env("XDG_CONFIG_HOME").map(PathBuf::from)
+                .or_else(|| home.as_ref().map(|home| home.join(".config")))
+                .map(|dir| dir.join("pnpm").join("rc"));
+            if let Some(pnpm_rc) = read(pnpm_rc) {
+                npmrc = Some(format!("{}\n{}", npmrc.unwrap_or_default(), pnpm_rc));
+            }
+            let npmrc = npm_config(npmrc, env, std::env::vars());
+
+            if project_info.package_manager.as_deref() == Some("yarn") {
+                let yarnrc = read(home.as_ref().map(|home| home.join(".yarnrc.yml")));
+                if let Some(content) = yarn_config(yarnrc, npmrc.as_deref().unwrap_or_default(), env) {
+                    secrets.push(RegistrySecret { id: "yarnrc", targets: YARNRC_TARGETS, content });
+                }
+            }
+            if let Some(content) = npmrc {
+                secrets.insert(0, RegistrySecret { id: "npmrc", targets: NPMRC_TARGETS, content });
+            }
+        }
+        ProjectType::PythonPoetry | ProjectType::PythonUv | ProjectType::PythonPipenv | ProjectType::PythonConda |
+        ProjectType::PythonSetupPy | ProjectType::PythonRequirements => {
+            let pip_conf = read(env("PIP_CONFIG_FILE").map(PathBuf::from).or_else(|| {
+                let home = home.as_ref()?;
+                [home.join(".config/pip/pip.conf"), home.join(".pip/pip.conf")].into_iter().find(|path| path.exists())
+            }));
+            if let Some(content) = pip_config(pip_conf, env) {
+                secrets.push(RegistrySecret { id: "pipconf", targets: PIP_CONF_TARGETS, content });
+            }
+        }
+        _ => {}
+    }
+    secrets
+}
+
+/// The npmrc to hand to the build: the user's, with `${VAR}` references resolved (the build
+/// has none of the host's variables), plus `NPM_CONFIG_REGISTRY`, scoped registries set as
+/// `NPM_CONFIG_@SCOPE:REGISTRY` and `NPM_TOKEN`
+fn npm_config(npmrc: Option<String>, env: impl Fn(&str) -> Option<String>, vars: impl IntoIterator<Item = (String, String)>) -> Option<String> {
+    let original = npmrc.unwrap_or_default();
+    let mut content = expand_env_refs(&original, &env);
+
+    let mut scoped: Vec<(String, String)> = vars.into_iter()
+        .filter_map(|(name, value)| {
+            let key = name.strip_prefix("NPM_CONFIG_").or_else(|| name.strip_prefix("npm_config_"))?.to_lowercase();
+            (key.starts_with('@') && key.ends_with(":registry") && !value.is_empty()).then_some((key, value))
+        })
+        .collect();
+    scoped.sort();
+    for (key, value) in scoped {
+        content.push_str(&format!("\n{}={}\n", key, value));
+    }
+
+    let registry = env("NPM_CONFIG_REGISTRY");
+    if let Some(registry) = &registry {
+        content.push_str(&format!("\nregistry={}\n", registry));
+    }
+    if let Some(token) = env("NPM_TOKEN").filter(|_| !original.contains("${NPM_TOKEN}")) {
+        let registry = registry.as_deref().unwrap_or("https://registry.npmjs.org/");
+        let scope = registry.split_once("://").map_or(registry, |(_, rest)| rest).trim_end_matches('/');
+        content.push_str(&format!("\n//{}/:_authToken={}\n", scope, token));
+    }
+
+    (!content.trim().is_empty()).then_some(content)
+}
+
+/// Registry settings of an npmrc: the default registry, per-scope registries and auth tokens
+/// by registry path (`//npm.acme.dev/`), later lines winning
+#[derive(Debug, Default, PartialEq, Eq)]
+struct NpmRegistries {
+    registry: Option<String>,
+    scopes: Vec<(String, String)>,
+    tokens: Vec<(String, String)>,
+}
+
+fn parse_npmrc(npmrc: &str) -> NpmRegistries {
+    let mut registries = NpmRegistries::default();
+    for line in npmrc.lines().map(str::trim).filter(|line| !line.starts_with('#') && !line.starts_with(';')) {
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let (key, value) = (key.trim(), value.trim().to_string());
+        if key == "registry" {
+            registries.registry = Some(value);
+        } else if let Some(scope) = key.strip_prefix('@').and_then(|key| key.strip_suffix(":registry")) {
+            registries.scopes.retain(|(existing, _)| existing != scope);
+            registries.scopes.push((scope.to_string(), value));
+        } else if let Some(path) = key.strip_suffix(":_authToken").filter(|path| path.starts_with("//")) {
+            registries.tokens.retain(|(existing, _)| existing != path);
+            registries.tokens.push((path.to_string(), value));
+        }
+    }
+    registries
+}
+
+/// The `.yarnrc.yml` to hand to a Yarn 2+ build: the user's, with the npmrc's registries,
+/// scopes and tokens translated to `npmRegistryServer`, `npmScopes` and `npmRegistries`
+/// where the user's file doesn't already set them
+fn yarn_config(yarnrc: Option<String>, npmrc: &str, env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    use serde_yaml::{Mapping, Value};
+
+    let yarnrc = yarnrc.map(|content| expand_env_refs(&content, &env)).unwrap_or_default();
+    let mut config: Mapping = serde_yaml::from_str(&yarnrc).unwrap_or_default();
+    let registries = parse_npmrc(npmrc);
+    let key = |name: &str| Value::String(name.to_string());
+
+    if let Some(registry) = registries.registry {
+        config.entry(key("npmRegistryServer")).or_insert(Value::String(registry));
+    }
+    if !registries.scopes.is_empty() {
+        let scopes = config.entry(key("npmScopes")).or_insert_with(|| Value::Mapping(Mapping::new()));
+        if let Value::Mapping(scopes) = scopes {
+            for (scope, registry) in registries.scopes {
+                let mut settings = Mapping::new();
+                settings.insert(key("npmRegistryServer"), Value::String(registry));
+                scopes.entry(Value::String(scope)).or_insert(Value::Mapping(settings));
+            }
+        }
+    }
+    if !registries.tokens.is_empty() {
+        let servers = config.entry(key("npmRegistries")).or_insert_with(|| Value::Mapping(Mapping::new()));
+        if let Value::Mapping(servers) = servers {
+            for (path, token) in registries.tokens {
+                let mut settings = Mapping::new();
+                settings.insert(key("npmAuthToken"), Value::String(token));
+                servers.entry(Value::String(path.trim_end_matches('/').to_string())).or_insert(Value::Mapping(settings));
+            }
+        }
+    }
+
+    if config.is_empty() {
+        return None;
+    }
+    serde_yaml::to_string(&config).ok()
+}
+
+/// The pip.conf to hand to the build: the user's, with `PIP_INDEX_URL`, `PIP_EXTRA_INDEX_URL`
+/// and `PIP_TRUSTED_HOST` added to its `[global]` section
+fn pip_config(pip_conf: Option<String>, env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let settings: Vec<String> = [("PIP_INDEX_URL", "index-url"), ("PIP_EXTRA_INDEX_URL", "extra-index-url"), ("PIP_TRUSTED_HOST", "trusted-host")]
+        .iter()
+        .filter_map(|(var, key)| env(var).map(|value| format!("{} = {}", key, value)))
+        .collect();
+
+    let mut content = pip_conf.unwrap_or_default();
+    if !settings.is_empty() {
+        // pip rejects a second [global] section, so extend the existing one
+        let settings = settings.join("\n");
+        content = match content.find("[global]") {
+            Some(index) => {
+                let (head, tail) = content.split_at(index + "[global]".len());
+                format!("{}\n{}{}", head, settings, tail)
+            }
+            None => format!("{}\n[global]\n{}\n", content, settings),
+        };
+    }
+
+    (!content.trim().is_empty()).then_some(content)
+}
+
+/// Resolve npm's `${VAR}` references; unknown variables are left as they are
+fn expand_env_refs(content: &str, env: &impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let reference = &rest[start..start + end + 1];
+        match env(&reference[2..reference.len() - 1]) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(reference),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Mount the secrets into every `RUN` step of a Dockerfile
+pub fn mount_secrets(dockerfile: String, secrets: &[RegistrySecret]) -> String {
+    if secrets.is_empty() {
+        return dockerfile;
+    }
+    let mounts: String = secrets.iter()
+        .flat_map(|secret| secret.targets.iter().map(move |target| format!("--mount=type=secret,id={},target={} ", secret.id, target)))
+        .collect();
+
+    let mut output = String::new();
+    for line in dockerfile.lines() {
+        match line.strip_prefix("RUN ") {
+            Some(command) => output.push_str(&format!("RUN {}{}", mounts, command)),
+            None => output.push_str(line),
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// Write the secrets to `dir` (outside the build context) and return the matching
+/// `finch build --secret` arguments
+pub fn write_secrets(dir: &Path, secrets: &[RegistrySecret]) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    if secrets.is_empty() {
+        return Ok(args);
+    }
+    let secrets_dir = dir.join("secrets");
+    std::fs::create_dir_all(&secrets_dir).context("Failed to create the build secrets directory")?;
+    for secret in secrets {
+        let path = secrets_dir.join(secret.id);
+        std::fs::write(&path, &secret.content).with_context(|| format!("Failed to write build secret {}", secret.id))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        args.push("--secret".to_string());
+        args.push(format!("id={},src={}", secret.id, path.display()));
+    }
+    Ok(args)
+}
+
+/// Write the host's registry secrets for a build into `dir`, returning the `finch build` arguments
+pub fn prepare_build(dir: &Path, forward_registry: bool, project_info: &ProjectInfo) -> Result<Vec<String>> {
+    write_secrets(dir, &host_secrets(forward_registry, project_info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+    }
+
+    #[test]
+    fn test_npm_config() {
+        let npmrc = "@acme:registry=https://npm.acme.dev/\n//npm.acme.dev/:_authToken=${ACME_TOKEN}\n".to_string();
+        let content = npm_config(Some(npmrc), env(&[("ACME_TOKEN", "s3cret"), ("NPM_TOKEN", "npm-tok")]), Vec::new()).unwrap();
+        assert!(content.contains("//npm.acme.dev/:_authToken=s3cret"));
+        assert!(content.contains("//registry.npmjs.org/:_authToken=npm-tok"));
+
+        let content = npm_config(None, env(&[("NPM_CONFIG_REGISTRY", "https://npm.corp/repo/"), ("NPM_TOKEN", "tok")]), Vec::new()).unwrap();
+        assert!(content.contains("registry=https://npm.corp/repo/"));
+        assert!(content.contains("//npm.corp/repo/:_authToken=tok"));
+
+        let vars = vec![("NPM_CONFIG_@MYORG:REGISTRY".to_string(), "https://npm.myorg.dev/".to_string()), ("NPM_CONFIG_CACHE".to_string(), "/tmp".to_string())];
+        let content = npm_config(None, env(&[]), vars).unwrap();
+        assert_eq!(content.trim(), "@myorg:registry=https://npm.myorg.dev/");
+
+        assert_eq!(npm_config(None, env(&[]), Vec::new()), None);
+        assert_eq!(expand_env_refs("a=${MISSING} b=${", &env(&[])), "a=${MISSING} b=${");
+    }
+
+    #[test]
+    fn test_yarn_config() {
+        let npmrc = "registry=https://npm.corp/\n@myorg:registry=https://npm.myorg.dev/\n//npm.myorg.dev/:_authToken=s3cret\n";
+        let yarnrc = "enableTelemetry: false\nnpmScopes:\n  acme:\n    npmRegistryServer: https://npm.acme.dev\n".to_string();
+        let config: serde_yaml::Value = serde_yaml::from_str(&yarn_config(Some(yarnrc), npmrc, env(&[])).unwrap()).unwrap();
+
+        assert_eq!(config["enableTelemetry"], false);
+        assert_eq!(config["npmRegistryServer"], "https://npm.corp/");
+        assert_eq!(config["npmScopes"]["acme"]["npmRegistryServer"], "https://npm.acme.dev");
+        assert_eq!(config["npmScopes"]["myorg"]["npmRegistryServer"], "https://npm.myorg.dev/");
+        assert_eq!(config["npmRegistries"]["//npm.myorg.dev"]["npmAuthToken"], "s3cret");
+
+        assert_eq!(yarn_config(None, "", env(&[])), None);
+    }
+
+    #[test]
+    fn test_pip_config() {
+        let pip_conf = "[global]\ntimeout = 60\n".to_string();
+        let content = pip_config(Some(pip_conf), env(&[("PIP_INDEX_URL", "https://user:pw@pypi.corp/simple")])).unwrap();
+        assert_eq!(content.matches("[global]").count(), 1);
+        assert!(content.contains("[global]\nindex-url = https://user:pw@pypi.corp/simple\ntimeout = 60"));
+
+        let content = pip_config(None, env(&[("PIP_TRUSTED_HOST", "pypi.corp")])).unwrap();
+        assert!(content.contains("[global]\ntrusted-host = pypi.corp"));
+    }
+
+    #[test]
+    fn test_secrets_stay_out_of_the_dockerfile() {
+        let secrets = vec![RegistrySecret { id: "npmrc", targets: NPMRC_TARGETS, content: "//registry.npmjs.org/:_authToken=s3cret\n".to_string() }];
+        let dockerfile = mount_secrets("FROM node:20-slim\nWORKDIR /app\nRUN npm ci\nCOPY . .\nRUN npm run build\n".to_string(), &secrets);
+
+        assert!(!dockerfile.contains("s3cret"));
+        assert!(!dockerfile.contains("ENV NPM_TOKEN") && !dockerfile.contains("COPY --from=host"));
+        assert_eq!(dockerfile.matches("RUN --mount=type=secret,id=npmrc,target=/usr/local/etc/npmrc --mount=type=secret,id=npmrc,target=/root/.npmrc ").count(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let args = write_secrets(dir.path(), &secrets).unwrap();
+        assert_eq!(args[0], "--secret");
+        assert!(args[1].starts_with("id=npmrc,src="));
+        assert_eq!(std::fs::read_to_string(dir.path().join("secrets/npmrc")).unwrap(), secrets[0].content);
+    }
+}
@@ -0,0 +1,270 @@
+//! SBOMs for built images (`finch-mcp build --sbom`)
+//!
+//! With Syft installed, the exported image is catalogued in full. Without it, the SBOM
+//! lists the packages pinned by the lockfiles in the image's `/app` (package-lock.json,
+//! pnpm-lock.yaml, uv.lock, poetry.lock and `==` pins in requirements.txt). SBOMs are kept
+//! in the logs directory, one per image, for `finch-mcp inspect --sbom`.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use tokio::process::Command;
+
+use crate::core::scan::tool_installed;
+use crate::finch::client::FinchClient;
+use crate::logging::LogManager;
+use crate::workspace;
+
+/// Lockfiles read from `/app` when Syft isn't available
+const LOCKFILES: [&str; 5] = ["package-lock.json", "pnpm-lock.yaml", "uv.lock", "poetry.lock", "requirements.txt"];
+
+/// Precedes each lockfile in the output of the container that reads them
+const LOCKFILE_MARKER: &str = "@@finch-mcp-lockfile@@ ";
+
+/// SBOM document formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    Cyclonedx,
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
+impl SbomFormat {
+    fn syft_output(self) -> &'static str {
+        match self {
+            Self::Cyclonedx => "cyclonedx-json",
+            Self::Spdx => "spdx-json",
+        }
+    }
+}
+
+impl fmt::Display for SbomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cyclonedx => "CycloneDX",
+            Self::Spdx => "SPDX",
+        })
+    }
+}
+
+/// A package pinned by a lockfile
+#[derive(Debug, Clone, PartialEq)]
+struct Component {
+    name: String,
+    version: String,
+    /// Package URL type: `npm` or `pypi`
+    ecosystem: &'static str,
+}
+
+impl Component {
+    fn purl(&self) -> String {
+        match self.ecosystem {
+            // Scoped npm names keep their scope as an escaped namespace
+            "npm" => format!("pkg:npm/{}@{}", self.name.replace('@', "%40"), self.version),
+            _ => format!("pkg:{}/{}@{}", self.ecosystem, self.name.to_lowercase().replace('_', "-"), self.version),
+        }
+    }
+}
+
+/// The stored SBOM for an image, if one was generated
+pub fn stored_sbom(image_name: &str) -> Option<PathBuf> {
+    LogManager::new().ok().map(|logs| logs.sbom_path(image_name)).filter(|path| path.exists())
+}
+
+/// Generate an SBOM for a built image and store it, returning its path
+pub async fn generate_sbom(image_name: &str, format: SbomFormat) -> Result<PathBuf> {
+    let document = if tool_installed("syft").await {
+        syft_sbom(image_name, format).await?
+    } else {
+        let components = lockfile_components(image_name).await?;
+        match format {
+            SbomFormat::Cyclonedx => cyclonedx(image_name, &components),
+            SbomFormat::Spdx => spdx(image_name, &components),
+        }
+    };
+
+    let path = LogManager::new()?.sbom_path(image_name);
+    std::fs::write(&path, serde_json::to_string_pretty(&document)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+async fn syft_sbom(image_name: &str, format: SbomFormat) -> Result<Value> {
+    let temp_dir = workspace::temp_dir("sbom")?;
+    let archive = temp_dir.path().join("image.tar");
+    FinchClient::new().save_image(image_name, &archive).await?;
+
+    let output = Command::new("syft")
+        .arg(format!("docker-archive:{}", archive.display()))
+        .args(["--quiet", "--output", format.syft_output()])
+        .output()
+        .await
+        .context("Failed to run syft")?;
+    if !output.status.success() {
+        anyhow::bail!("syft failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    serde_json::from_slice(&output.stdout).context("Unexpected output from syft")
+}
+
+/// Packages pinned by the lockfiles in the image's `/app`, read in one container run
+async fn lockfile_components(image_name: &str) -> Result<Vec<Component>> {
+    let script = format!(
+        "for f in {}; do [ -f \"/app/$f\" ] && echo \"{}$f\" && cat \"/app/$f\" && echo; done; true",
+        LOCKFILES.join(" "),
+        LOCKFILE_MARKER
+    );
+    let output = Command::new("finch")
+        .args(["run", "--rm", "--entrypoint", "sh", image_name, "-c", &script])
+        .output()
+        .await
+        .context("Failed to run finch")?;
+    if !output.status.success() {
+        anyhow::bail!("Failed to read lockfiles from {}: {}", image_name, String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut components = Vec::new();
+    for section in stdout.split(LOCKFILE_MARKER).skip(1) {
+        let (file, content) = section.split_once('\n').unwrap_or((section, ""));
+        components.extend(parse_lockfile(file.trim(), content));
+    }
+    components.sort_by(|a, b| (a.ecosystem, &a.name, &a.version).cmp(&(b.ecosystem, &b.name, &b.version)));
+    components.dedup();
+    Ok(components)
+}
+
+fn parse_lockfile(file: &str, content: &str) -> Vec<Component> {
+    let component = |ecosystem, name: &str, version: &str| Component { name: name.to_string(), version: version.to_string(), ecosystem };
+    match file {
+        "package-lock.json" => {
+            let lock: Value = serde_json::from_str(content).unwrap_or_default();
+            let packages = lock["packages"].as_object().cloned().unwrap_or_default();
+            packages
+                .iter()
+                .filter_map(|(path, package)| {
+                    let (_, name) = path.rsplit_once("node_modules/")?;
+                    Some(component("npm", name, package["version"].as_str()?))
+                })
+                .collect()
+        }
+        "pnpm-lock.yaml" => {
+            let lock: serde_yaml::Value = serde_yaml::from_str(content).unwrap_or_default();
+            let Some(packages) = lock.get("packages").and_then(|v| v.as_mapping()) else {
+                return Vec::new();
+            };
+            // Keys look like `/better-sqlite3@9.4.0` or `/@scope/name@1.0.0(peer@2)`
+            packages
+                .keys()
+                .filter_map(|key| {
+                    let key = key.as_str()?.trim_start_matches('/');
+                    let key = key.split('(').next()?;
+                    let split = key.get(1..)?.find('@')? + 1;
+                    Some(component("npm", &key[..split], &key[split + 1..]))
+                })
+                .collect()
+        }
+        "uv.lock" | "poetry.lock" => {
+            let lock: toml::Table = toml::from_str(content).unwrap_or_default();
+            let packages = lock.get("package").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            packages
+                .iter()
+                .filter_map(|package| Some(component("pypi", package.get("name")?.as_str()?, package.get("version")?.as_str()?)))
+                .collect()
+        }
+        "requirements.txt" => content
+            .lines()
+            .filter_map(|line| {
+                let line = line.split('#').next()?.trim();
+                let (name, version) = line.split_once("==")?;
+                let name = name.split('[').next()?.trim();
+                let version = version.split([';', ' ']).next()?.trim();
+                (!name.is_empty() && !version.is_empty()).then(|| component("pypi", name, version))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn tool_version() -> String {
+    format!("finch-mcp-{}", env!("CARGO_PKG_VERSION"))
+}
+
+fn cyclonedx(image_name: &str, components: &[Component]) -> Value {
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": Utc::now().to_rfc3339(),
+            "tools": { "components": [{ "type": "application", "name": "finch-mcp", "version": env!("CARGO_PKG_VERSION") }] },
+            "component": { "type": "container", "name": image_name, "bom-ref": image_name },
+        },
+        "components": components.iter().map(|component| json!({
+            "type": "library",
+            "bom-ref": component.purl(),
+            "name": component.name,
+            "version": component.version,
+            "purl": component.purl(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn spdx(image_name: &str, components: &[Component]) -> Value {
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": image_name,
+        "documentNamespace": format!("https://github.com/mikeyobrien/finch-mcp/spdx/{}-{}", image_name.replace(':', "-"), uuid::Uuid::new_v4()),
+        "creationInfo": { "created": Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(), "creators": [format!("Tool: {}", tool_version())] },
+        "packages": components.iter().enumerate().map(|(i, component)| json!({
+            "name": component.name,
+            "SPDXID": format!("SPDXRef-Package-{}", i + 1),
+            "versionInfo": component.version,
+            "downloadLocation": "NOASSERTION",
+            "externalRefs": [{ "referenceCategory": "PACKAGE-MANAGER", "referenceType": "purl", "referenceLocator": component.purl() }],
+        })).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lockfiles() {
+        let npm = parse_lockfile("package-lock.json", r#"{"packages": {"": {"version": "1.0.0"}, "node_modules/@scope/util": {"version": "2.1.0"}, "node_modules/a/node_modules/b": {"version": "0.3.0"}}}"#);
+        assert_eq!(npm.len(), 2);
+        assert!(npm.iter().any(|c| c.purl() == "pkg:npm/%40scope/util@2.1.0"));
+        assert!(npm.iter().any(|c| c.name == "b" && c.version == "0.3.0"));
+
+        let pnpm = parse_lockfile("pnpm-lock.yaml", "packages:\n  /@scope/name@1.0.0(peer@2):\n    resolution: {}\n  /zod@3.22.4:\n    resolution: {}\n");
+        assert_eq!(pnpm[0], Component { name: "@scope/name".to_string(), version: "1.0.0".to_string(), ecosystem: "npm" });
+        assert_eq!(pnpm[1].name, "zod");
+
+        let uv = parse_lockfile("uv.lock", "version = 1\n[[package]]\nname = \"Mcp_Server\"\nversion = \"1.2.0\"\n");
+        assert_eq!(uv[0].purl(), "pkg:pypi/mcp-server@1.2.0");
+
+        let pip = parse_lockfile("requirements.txt", "httpx==0.27.0  # client\nuvicorn[standard]==0.30.1 ; python_version >= '3.8'\nrequests>=2\n");
+        let pins: Vec<(&str, &str)> = pip.iter().map(|c| (c.name.as_str(), c.version.as_str())).collect();
+        assert_eq!(pins, [("httpx", "0.27.0"), ("uvicorn", "0.30.1")]);
+    }
+
+    #[test]
+    fn test_documents() {
+        let components = [Component { name: "zod".to_string(), version: "3.22.4".to_string(), ecosystem: "npm" }];
+        let bom = cyclonedx("mcp-demo:abc123", &components);
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["components"][0]["purl"], "pkg:npm/zod@3.22.4");
+
+        let doc = spdx("mcp-demo:abc123", &components);
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["packages"][0]["externalRefs"][0]["referenceLocator"], "pkg:npm/zod@3.22.4");
+    }
+}
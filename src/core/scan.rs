@@ -0,0 +1,296 @@
+//! `finch-mcp scan`: vulnerability scanning of built server images
+//!
+//! The image is exported with `finch save` and handed to Trivy or Grype, whichever is
+//! installed, so scanning works without a Docker daemon on the host. Their JSON reports
+//! are reduced to one list of findings with a common severity scale.
+
+use std::fmt;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use console::style;
+use serde_json::{json, Value};
+use tokio::process::Command;
+
+use crate::finch::client::FinchClient;
+use crate::workspace;
+
+/// Vulnerability scanners `finch-mcp scan` can drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scanner {
+    Trivy,
+    Grype,
+}
+
+impl Scanner {
+    fn binary(self) -> &'static str {
+        match self {
+            Self::Trivy => "trivy",
+            Self::Grype => "grype",
+        }
+    }
+
+    async fn is_installed(self) -> bool {
+        tool_installed(self.binary()).await
+    }
+}
+
+/// Severity of a finding, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum Severity {
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 5] = [Self::Critical, Self::High, Self::Medium, Self::Low, Self::Unknown];
+
+    /// Trivy's and Grype's severity names; Grype's "Negligible" counts as low
+    fn parse(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "critical" => Self::Critical,
+            "high" => Self::High,
+            "medium" => Self::Medium,
+            "low" | "negligible" => Self::Low,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Unknown => "unknown",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A vulnerable package in the image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// CVE or advisory ID
+    pub id: String,
+    pub package: String,
+    pub installed_version: String,
+    /// Version that fixes it, if there is one
+    pub fixed_version: Option<String>,
+    pub severity: Severity,
+}
+
+/// Findings for one image
+#[derive(Debug, Clone)]
+pub struct ScanReport {
+    pub image: String,
+    pub scanner: Scanner,
+    /// Most severe first
+    pub findings: Vec<Finding>,
+}
+
+impl ScanReport {
+    /// Number of findings with exactly this severity
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|finding| finding.severity == severity).count()
+    }
+
+    /// Number of findings at `threshold` or worse, for `--fail-on`
+    pub fn count_at_least(&self, threshold: Severity) -> usize {
+        self.findings.iter().filter(|finding| finding.severity >= threshold).count()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let summary: serde_json::Map<String, Value> = Severity::ALL
+            .iter()
+            .map(|severity| (severity.to_string(), json!(self.count(*severity))))
+            .collect();
+        json!({
+            "image": self.image,
+            "scanner": self.scanner.binary(),
+            "summary": summary,
+            "findings": self.findings.iter().map(|finding| json!({
+                "id": finding.id,
+                "package": finding.package,
+                "installedVersion": finding.installed_version,
+                "fixedVersion": finding.fixed_version,
+                "severity": finding.severity.to_string(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Print the severity summary and the high and critical findings
+    pub fn print(&self) {
+        eprintln!("{} {} (scanned with {})", style("🛡️").blue(), style(&self.image).cyan().bold(), self.scanner.binary());
+        if self.findings.is_empty() {
+            eprintln!("  {}", style("No known vulnerabilities").green());
+            return;
+        }
+
+        let summary: Vec<String> = Severity::ALL
+            .iter()
+            .map(|severity| format!("{}: {}", severity, self.count(*severity)))
+            .collect();
+        eprintln!("  {}", summary.join("  "));
+
+        let serious: Vec<&Finding> = self.findings.iter().filter(|finding| finding.severity >= Severity::High).collect();
+        if !serious.is_empty() {
+            eprintln!();
+        }
+        for finding in &serious {
+            let severity = format!("{:<8}", finding.severity.to_string().to_uppercase());
+            let severity = if finding.severity == Severity::Critical { style(severity).red().bold() } else { style(severity).red() };
+            let fix = match &finding.fixed_version {
+                Some(fixed) => format!(" → {}", style(fixed).green()),
+                None => format!(" {}", style("(no fix)").dim()),
+            };
+            eprintln!("  {} {} {} {}{}", severity, finding.id, finding.package, finding.installed_version, fix);
+        }
+        let rest = self.findings.len() - serious.len();
+        if rest > 0 {
+            eprintln!("  {}", style(format!("{} lower-severity finding(s) not shown; use --json for all", rest)).dim());
+        }
+    }
+}
+
+/// Scan an image with `scanner`, or with the first of Trivy and Grype that is installed
+pub async fn scan_image(image: &str, scanner: Option<Scanner>) -> Result<ScanReport> {
+    let scanner = match scanner {
+        Some(scanner) if scanner.is_installed().await => scanner,
+        Some(scanner) => anyhow::bail!("{} is not installed or not on PATH", scanner.binary()),
+        None => find_scanner().await.context(
+            "No vulnerability scanner found; install Trivy (https://trivy.dev) or Grype (https://github.com/anchore/grype)",
+        )?,
+    };
+
+    let temp_dir = workspace::temp_dir("scan")?;
+    let archive = temp_dir.path().join("image.tar");
+    FinchClient::new().save_image(image, &archive).await?;
+
+    let mut command = Command::new(scanner.binary());
+    match scanner {
+        Scanner::Trivy => command.args(["image", "--quiet", "--format", "json", "--input"]).arg(&archive),
+        Scanner::Grype => command.arg(format!("docker-archive:{}", archive.display())).args(["--quiet", "--output", "json"]),
+    };
+    let output = command.output().await.with_context(|| format!("Failed to run {}", scanner.binary()))?;
+    if !output.status.success() {
+        anyhow::bail!("{} failed: {}", scanner.binary(), String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let report: Value = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("Unexpected output from {}", scanner.binary()))?;
+    let mut findings = match scanner {
+        Scanner::Trivy => trivy_findings(&report),
+        Scanner::Grype => grype_findings(&report),
+    };
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.id.cmp(&b.id)));
+    Ok(ScanReport { image: image.to_string(), scanner, findings })
+}
+
+/// Whether `binary --version` runs, i.e. the tool is on PATH
+pub(crate) async fn tool_installed(binary: &str) -> bool {
+    Command::new(binary)
+        .arg("--version")
+        .output()
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+async fn find_scanner() -> Option<Scanner> {
+    for scanner in [Scanner::Trivy, Scanner::Grype] {
+        if scanner.is_installed().await {
+            return Some(scanner);
+        }
+    }
+    None
+}
+
+fn text(value: &Value, key: &str) -> String {
+    value[key].as_str().unwrap_or_default().to_string()
+}
+
+/// Findings from `trivy image --format json`
+fn trivy_findings(report: &Value) -> Vec<Finding> {
+    let results = report["Results"].as_array().map(Vec::as_slice).unwrap_or_default();
+    results
+        .iter()
+        .flat_map(|result| result["Vulnerabilities"].as_array().map(Vec::as_slice).unwrap_or_default())
+        .map(|vulnerability| Finding {
+            id: text(vulnerability, "VulnerabilityID"),
+            package: text(vulnerability, "PkgName"),
+            installed_version: text(vulnerability, "InstalledVersion"),
+            fixed_version: vulnerability["FixedVersion"].as_str().filter(|fixed| !fixed.is_empty()).map(str::to_string),
+            severity: Severity::parse(vulnerability["Severity"].as_str().unwrap_or_default()),
+        })
+        .collect()
+}
+
+/// Findings from `grype --output json`
+fn grype_findings(report: &Value) -> Vec<Finding> {
+    let matches = report["matches"].as_array().map(Vec::as_slice).unwrap_or_default();
+    matches
+        .iter()
+        .map(|found| {
+            let vulnerability = &found["vulnerability"];
+            let fixed_versions: Vec<&str> = vulnerability["fix"]["versions"]
+                .as_array()
+                .map(|versions| versions.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            Finding {
+                id: text(vulnerability, "id"),
+                package: text(&found["artifact"], "name"),
+                installed_version: text(&found["artifact"], "version"),
+                fixed_version: (!fixed_versions.is_empty()).then(|| fixed_versions.join(", ")),
+                severity: Severity::parse(vulnerability["severity"].as_str().unwrap_or_default()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trivy_findings() {
+        let report = json!({
+            "Results": [
+                { "Target": "alpine 3.19", "Vulnerabilities": [
+                    { "VulnerabilityID": "CVE-2024-0001", "PkgName": "openssl", "InstalledVersion": "3.1.4-r0", "FixedVersion": "3.1.4-r5", "Severity": "CRITICAL" },
+                    { "VulnerabilityID": "CVE-2024-0002", "PkgName": "busybox", "InstalledVersion": "1.36.1-r15", "FixedVersion": "", "Severity": "LOW" }
+                ] },
+                { "Target": "app/package-lock.json" }
+            ]
+        });
+        let findings = trivy_findings(&report);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].fixed_version.as_deref(), Some("3.1.4-r5"));
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert_eq!(findings[1].fixed_version, None);
+    }
+
+    #[test]
+    fn test_grype_findings_and_thresholds() {
+        let report = json!({
+            "matches": [
+                { "vulnerability": { "id": "GHSA-xxxx", "severity": "High", "fix": { "versions": ["1.2.3"] } },
+                  "artifact": { "name": "undici", "version": "1.2.0" } },
+                { "vulnerability": { "id": "CVE-2023-9999", "severity": "Negligible", "fix": { "versions": [] } },
+                  "artifact": { "name": "libc6", "version": "2.36" } }
+            ]
+        });
+        let findings = grype_findings(&report);
+        assert_eq!(findings[0].package, "undici");
+        assert_eq!(findings[1].severity, Severity::Low);
+
+        let report = ScanReport { image: "mcp-demo".to_string(), scanner: Scanner::Grype, findings };
+        assert_eq!(report.count_at_least(Severity::High), 1);
+        assert_eq!(report.count_at_least(Severity::Critical), 0);
+        assert_eq!(report.to_json()["summary"]["low"], 1);
+    }
+}
@@ -0,0 +1,672 @@
+use anyhow::{Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::core::auto_containerize::{auto_build, AutoContainerizeOptions};
+use crate::core::ca_certs::CaBundle;
+use crate::core::client_config::{self, ClientEntry, ConfigMode};
+use crate::core::git_containerize::{git_build, local_build, GitContainerizeOptions, LocalContainerizeOptions};
+use crate::finch::client::NetworkMode;
+use crate::mcp::supervisor::RestartPolicy;
+use crate::utils::allowed_paths::{self, AllowedPath};
+use crate::utils::command_parser::parse_command_string;
+use crate::utils::mcp_manifest::shell_quote;
+use crate::utils::git_repository::GitRepository;
+use crate::utils::target_kind;
+use crate::utils::volumes;
+use crate::cache::CacheManager;
+use crate::{output, status};
+
+/// File names searched for (in order) when no manifest path is given
+pub const MANIFEST_FILE_NAMES: [&str; 2] = ["finch-mcp.servers.yaml", "finch-mcp.servers.yml"];
+
+/// A set of MCP servers managed together with `up` / `down`
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct ServersManifest {
+    /// Servers keyed by the name used in the generated `mcpServers` block
+    #[serde(default)]
+    pub servers: BTreeMap<String, ServerSpec>,
+}
+
+/// One server entry in the manifest
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerSpec {
+    /// Image, command, git repository URL, or local directory (relative to the manifest)
+    pub target: String,
+
+    /// Arguments for the server command
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Environment variables for the container
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Volume mounts (`HOST_PATH:CONTAINER_PATH`)
+    #[serde(default)]
+    pub volumes: Vec<String>,
+
+    /// Host directories mounted at the same path and listed in `ALLOWED_PATHS` (`PATH[:ro]`)
+    #[serde(default)]
+    pub allow_paths: Vec<AllowedPath>,
+
+    /// Network mode: bridge, host, none, or a finch network name
+    pub network: Option<String>,
+
+    /// Profiles this server belongs to; servers without profiles are always enabled
+    #[serde(default)]
+    pub profiles: Vec<String>,
+
+    /// Treat the target as an existing container image
+    #[serde(default)]
+    pub direct: bool,
+
+    /// Forward registry configuration from the host
+    #[serde(default)]
+    pub forward_registry: bool,
+
+    /// Workspace package to build when the target is a Node.js monorepo
+    pub package: Option<String>,
+
+    /// Start command replacing the detected one for git and local targets
+    pub cmd: Option<String>,
+
+    /// Bin or script to start, when a git or local target has several
+    pub entry: Option<String>,
+
+    /// Restart policy for the client entry (`on-failure[:MAX]`); defaults to `--restart`
+    pub restart: Option<RestartPolicy>,
+}
+
+/// How a server's target is turned into an image
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerSource {
+    /// Existing container image, used as-is
+    Image(String),
+    /// Git repository URL
+    Git(String),
+    /// Local directory (resolved against the manifest directory)
+    Local(PathBuf),
+    /// Command to auto-containerize
+    Command { command: String, args: Vec<String> },
+}
+
+impl ServerSource {
+    /// Key the build flows use for this source in the image cache
+    pub fn cache_source(&self) -> Option<String> {
+        match self {
+            ServerSource::Image(_) => None,
+            ServerSource::Git(url) => Some(url.clone()),
+            ServerSource::Local(path) => Some(path.to_string_lossy().to_string()),
+            ServerSource::Command { command, args } => Some(format!("{} {}", command, args.join(" "))),
+        }
+    }
+}
+
+impl ServersManifest {
+    /// Load a manifest from an explicit path, or search the current directory
+    pub fn load(path: Option<&Path>) -> Result<(Self, PathBuf)> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => MANIFEST_FILE_NAMES
+                .iter()
+                .map(PathBuf::from)
+                .find(|candidate| candidate.exists())
+                .ok_or_else(|| anyhow::anyhow!(
+                    "No servers manifest found (looked for {})",
+                    MANIFEST_FILE_NAMES.join(", ")
+                ))?,
+        };
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read servers manifest: {}", path.display()))?;
+        let mut manifest = Self::parse(&content)
+            .with_context(|| format!("Failed to parse servers manifest: {}", path.display()))?;
+
+        // Relative host paths are relative to the manifest, not to where a client starts the server
+        let base_dir = std::path::absolute(path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")))?;
+        for (name, spec) in manifest.servers.iter_mut() {
+            spec.volumes = spec.volumes.iter()
+                .map(|volume| volumes::normalize(volume, &base_dir))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|e| anyhow::anyhow!("Server '{}' has an invalid volume: {}", name, e))?;
+        }
+
+        Ok((manifest, path))
+    }
+
+    /// Parse manifest content (YAML, which also accepts JSON)
+    pub fn parse(content: &str) -> Result<Self> {
+        let manifest: Self = serde_yaml::from_str(content)?;
+
+        for (name, spec) in &manifest.servers {
+            if spec.target.trim().is_empty() {
+                return Err(anyhow::anyhow!("Server '{}' has no target", name));
+            }
+            if let Some(network) = &spec.network {
+                network.parse::<NetworkMode>()
+                    .map_err(|e| anyhow::anyhow!("Server '{}' has an invalid network: {}", name, e))?;
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Servers enabled for the given profiles, optionally limited to specific names
+    pub fn select(&self, profiles: &[String], names: &[String]) -> Result<Vec<(&String, &ServerSpec)>> {
+        for name in names {
+            if !self.servers.contains_key(name) {
+                return Err(anyhow::anyhow!("Unknown server in manifest: {}", name));
+            }
+        }
+
+        Ok(self.servers
+            .iter()
+            .filter(|(name, _)| names.is_empty() || names.contains(name))
+            .filter(|(name, spec)| {
+                // Explicitly named servers are started regardless of profile
+                names.contains(name)
+                    || spec.profiles.is_empty()
+                    || spec.profiles.iter().any(|p| profiles.contains(p))
+            })
+            .collect())
+    }
+}
+
+impl ServerSpec {
+    /// Work out what kind of target this is, resolving local paths against `base_dir`
+    pub fn source(&self, base_dir: &Path) -> ServerSource {
+        let target = self.target.trim();
+
+        if self.direct {
+            return ServerSource::Image(target.to_string());
+        }
+
+        let local_path = base_dir.join(target);
+        if local_path.is_dir() {
+            return ServerSource::Local(local_path);
+        }
+
+        if GitRepository::is_git_url(target) {
+            return ServerSource::Git(target.to_string());
+        }
+
+        if target_kind::looks_like_image(target) {
+            return ServerSource::Image(target.to_string());
+        }
+
+        // Allow `target: "npx -y @scope/server"` as well as a separate args list
+        let (command, mut args) = parse_command_string(target);
+        args.extend(self.args.iter().cloned());
+        ServerSource::Command { command, args }
+    }
+
+    /// Environment variables as `KEY=VALUE` strings, with `ALLOWED_PATHS` for `allowPaths`
+    pub fn env_vars(&self) -> Vec<String> {
+        let mut env_vars = self.env.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        allowed_paths::apply(&self.allow_paths, &mut env_vars, &mut Vec::new());
+        env_vars
+    }
+
+    /// `volumes` plus the mounts for `allowPaths`
+    pub fn volume_mounts(&self) -> Vec<String> {
+        let mut volumes = self.volumes.clone();
+        allowed_paths::apply(&self.allow_paths, &mut Vec::new(), &mut volumes);
+        volumes
+    }
+
+    /// Arguments passed to the server after the image name; a command source has
+    /// them baked into its image
+    pub fn run_args(&self, source: &ServerSource) -> Vec<String> {
+        match source {
+            ServerSource::Command { .. } => Vec::new(),
+            _ => self.args.clone(),
+        }
+    }
+
+    /// Network mode (validated when the manifest is parsed)
+    pub fn network_mode(&self) -> NetworkMode {
+        self.network
+            .as_deref()
+            .and_then(|network| network.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Run `build` for every named item, at most `jobs` at once, returning the results in
+/// input order. With more than one job, each build's output is labelled with its name
+pub async fn build_concurrently<T, F, Fut>(items: Vec<(String, T)>, jobs: usize, build: F) -> Vec<(String, Result<String>)>
+where
+    T: Send + 'static,
+    F: Fn(T) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<String>> + Send + 'static,
+{
+    use console::style;
+
+    let total = items.len();
+    let jobs = jobs.max(1);
+    let permits = Arc::new(Semaphore::new(jobs));
+    let build = Arc::new(build);
+    let mut builds = JoinSet::new();
+
+    for (index, (name, item)) in items.into_iter().enumerate() {
+        let permits = Arc::clone(&permits);
+        let build = Arc::clone(&build);
+        builds.spawn(async move {
+            let _permit = permits.acquire_owned().await.expect("semaphore is never closed");
+            status!("\n{} [{}/{}] {}", style("📦").blue(), index + 1, total, style(&name).cyan().bold());
+            let result = if jobs > 1 {
+                output::labelled(&name, build(item)).await
+            } else {
+                build(item).await
+            };
+            (index, name, result)
+        });
+    }
+
+    let mut results = Vec::with_capacity(total);
+    while let Some(joined) = builds.join_next().await {
+        match joined {
+            Ok(result) => results.push(result),
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+    results.sort_by_key(|(index, _, _)| *index);
+    results.into_iter().map(|(_, name, result)| (name, result)).collect()
+}
+
+/// Command-line settings every server built from a manifest gets
+#[derive(Debug, Clone, Default)]
+pub struct BuildDefaults {
+    /// Build even if a cached image exists
+    pub force_rebuild: bool,
+    /// Build without finch's layer cache (`--no-cache`)
+    pub no_cache: bool,
+    /// Forward the host's npm/pip registry config to every build, not only the servers asking for it
+    pub forward_registry: bool,
+    /// Never touch the network (`--offline`)
+    pub offline: bool,
+    /// Resolve git servers' commits against the remote (`--refresh`)
+    pub refresh: bool,
+    /// What the `mcpServers` entries `up` prints run (`--config-mode`)
+    pub config_mode: ConfigMode,
+    /// Refuse build contexts larger than this many bytes (`--max-context-size`)
+    pub max_context_size: Option<u64>,
+    /// `HEALTHCHECK` command for every image built (`--image-healthcheck`)
+    pub image_healthcheck: Option<String>,
+    /// Kill builds running longer than this (`--build-timeout`)
+    pub build_timeout: Option<Duration>,
+    /// Build git and local servers from their dev container definitions (`--use-devcontainer`)
+    pub use_devcontainer: bool,
+    /// Print one line per build step instead of a progress bar (`--plain-progress`)
+    pub plain_progress: bool,
+    /// Pass the host's proxy variables to every build (`--forward-proxy`)
+    pub forward_proxy: bool,
+    /// Leave the host's timezone database out of every image (`--no-forward-timezone`)
+    pub no_forward_timezone: bool,
+    /// Restart policy for servers that set none (`--restart`)
+    pub restart: RestartPolicy,
+    /// CA certificates to trust in every build (`--ca-cert`, `caCerts`)
+    pub ca_bundle: Option<CaBundle>,
+}
+
+/// Build (or reuse) an image for one server
+pub async fn build_server(spec: &ServerSpec, source: &ServerSource, defaults: &BuildDefaults) -> Result<String> {
+    let forward_registry = defaults.forward_registry || spec.forward_registry;
+    Ok(match source {
+        ServerSource::Image(image) => image.clone(),
+        ServerSource::Git(url) => git_build(
+            GitContainerizeOptions::new(url.clone())
+                .with_args(spec.args.clone())
+                .with_env_vars(spec.env_vars())
+                .with_volumes(spec.volume_mounts())
+                .with_network(spec.network_mode())
+                .with_forward_registry(forward_registry)
+                .with_force_rebuild(defaults.force_rebuild)
+                .with_no_cache(defaults.no_cache)
+                .with_max_context_size(defaults.max_context_size)
+                .with_use_devcontainer(defaults.use_devcontainer)
+                .with_plain_progress(defaults.plain_progress)
+                .with_offline(defaults.offline)
+                .with_image_healthcheck(defaults.image_healthcheck.clone())
+                .with_build_timeout(defaults.build_timeout)
+                .with_forward_proxy(defaults.forward_proxy)
+                .with_no_forward_timezone(defaults.no_forward_timezone)
+                .with_ca_bundle(defaults.ca_bundle.clone())
+                .with_refresh(defaults.refresh)
+                .with_package(spec.package.clone())
+                .with_command(spec.cmd.clone())
+                .with_entry(spec.entry.clone()),
+        ).await?,
+        ServerSource::Local(path) => local_build(
+            LocalContainerizeOptions::new(path.to_string_lossy())
+                .with_args(spec.args.clone())
+                .with_env_vars(spec.env_vars())
+                .with_volumes(spec.volume_mounts())
+                .with_network(spec.network_mode())
+                .with_forward_registry(forward_registry)
+                .with_force_rebuild(defaults.force_rebuild)
+                .with_no_cache(defaults.no_cache)
+                .with_max_context_size(defaults.max_context_size)
+                .with_use_devcontainer(defaults.use_devcontainer)
+                .with_plain_progress(defaults.plain_progress)
+                .with_offline(defaults.offline)
+                .with_image_healthcheck(defaults.image_healthcheck.clone())
+                .with_build_timeout(defaults.build_timeout)
+                .with_forward_proxy(defaults.forward_proxy)
+                .with_no_forward_timezone(defaults.no_forward_timezone)
+                .with_ca_bundle(defaults.ca_bundle.clone())
+                .with_package(spec.package.clone())
+                .with_command(spec.cmd.clone())
+                .with_entry(spec.entry.clone()),
+        ).await?,
+        ServerSource::Command { command, args } => auto_build(
+            AutoContainerizeOptions::new(command.clone())
+                .with_args(args.clone())
+                .with_env_vars(spec.env_vars())
+                .with_volumes(spec.volume_mounts())
+                .with_network(spec.network_mode())
+                .with_forward_registry(forward_registry)
+                .with_force_rebuild(defaults.force_rebuild)
+                .with_no_cache(defaults.no_cache)
+                .with_offline(defaults.offline)
+                .with_image_healthcheck(defaults.image_healthcheck.clone())
+                .with_build_timeout(defaults.build_timeout)
+                .with_forward_proxy(defaults.forward_proxy)
+                .with_no_forward_timezone(defaults.no_forward_timezone)
+                .with_ca_bundle(defaults.ca_bundle.clone()),
+        ).await?,
+    })
+}
+
+/// MCP client entry that runs the server through `finch-mcp run`: its source or the built
+/// image, depending on `mode`. `restart` applies to servers that set no restart policy
+pub fn mcp_server_entry(spec: &ServerSpec, source: &ServerSource, image_name: &str, mode: ConfigMode, restart: RestartPolicy) -> serde_json::Value {
+    let mut options = Vec::new();
+    for allowed in &spec.allow_paths {
+        options.push("--allow-path".to_string());
+        options.push(allowed.to_string());
+    }
+    if let Some(network) = &spec.network {
+        options.push("--network".to_string());
+        options.push(network.clone());
+    }
+    if let Some(policy) = spec.restart.or(Some(restart)).filter(|policy| *policy != RestartPolicy::No) {
+        options.push("--restart".to_string());
+        options.push(policy.to_string());
+    }
+
+    let target = match source {
+        ServerSource::Image(_) => None,
+        ServerSource::Git(url) => Some(url.clone()),
+        ServerSource::Local(path) => Some(std::path::absolute(path).unwrap_or_else(|_| path.clone()).display().to_string()),
+        ServerSource::Command { command, args } => {
+            Some(std::iter::once(command).chain(args).map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" "))
+        }
+    };
+    if matches!(source, ServerSource::Git(_) | ServerSource::Local(_)) {
+        options.extend(client_config::source_options(&NetworkMode::default(), spec.package.as_deref(), spec.cmd.as_deref(), spec.entry.as_deref()));
+    }
+
+    // Values live in the client's env block; `-e KEY` forwards them into the container
+    ClientEntry {
+        source: target,
+        image_name: image_name.to_string(),
+        env_vars: spec.env.iter().map(|(key, value)| format!("{}={}", key, value)).collect(),
+        volumes: spec.volumes.clone(),
+        options,
+        server_args: spec.run_args(source),
+    }
+    .to_json(mode)
+}
+
+/// Build every selected server and print a combined `mcpServers` block
+pub async fn servers_up(
+    manifest_path: Option<&Path>,
+    profiles: &[String],
+    names: &[String],
+    defaults: &BuildDefaults,
+    jobs: usize,
+) -> Result<serde_json::Value> {
+    use console::style;
+
+    let (manifest, path) = ServersManifest::load(manifest_path)?;
+    let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let selected = manifest.select(profiles, names)?;
+
+    if selected.is_empty() {
+        status!("{} No servers selected in {}", style("ℹ️").blue(), path.display());
+        return Ok(json!({ "mcpServers": {} }));
+    }
+
+    info!("Bringing up {} server(s) from {}", selected.len(), path.display());
+
+    let servers: Vec<(String, (ServerSpec, ServerSource))> = selected.iter()
+        .map(|(name, spec)| {
+            let source = spec.source(&base_dir);
+            debug!("Server '{}' source: {:?}", name, source);
+            (name.to_string(), ((*spec).clone(), source))
+        })
+        .collect();
+    let sources: Vec<(ServerSpec, ServerSource)> = servers.iter().map(|(_, server)| server.clone()).collect();
+
+    let build_defaults = defaults.clone();
+    let results = build_concurrently(servers, jobs, move |(spec, source)| {
+        let defaults = build_defaults.clone();
+        async move { build_server(&spec, &source, &defaults).await }
+    }).await;
+
+    let mut mcp_servers = serde_json::Map::new();
+    for ((name, result), (spec, source)) in results.into_iter().zip(sources) {
+        let image_name = result.with_context(|| format!("Failed to build server '{}'", name))?;
+        status!("✅ {} → {}", name, style(&image_name).cyan());
+        mcp_servers.insert(name, mcp_server_entry(&spec, &source, &image_name, defaults.config_mode, defaults.restart));
+    }
+
+    Ok(json!({ "mcpServers": mcp_servers }))
+}
+
+/// Remove images and cache entries built for the selected servers
+pub async fn servers_down(manifest_path: Option<&Path>, profiles: &[String], names: &[String]) -> Result<()> {
+    use console::style;
+
+    let (manifest, path) = ServersManifest::load(manifest_path)?;
+    let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let selected = manifest.select(profiles, names)?;
+
+    let mut cache_manager = CacheManager::new()?;
+    let mut removed = 0;
+
+    for (name, spec) in selected {
+        let source = spec.source(&base_dir);
+        let Some(cache_source) = source.cache_source() else {
+            // Never remove images the user supplied directly
+            status!("⏭️  {} uses an existing image, skipping", name);
+            continue;
+        };
+
+        let images = cache_manager.remove_entries_for_source(&cache_source)?;
+        if images.is_empty() {
+            status!("{} {} has no built images", style("ℹ️").blue(), name);
+            continue;
+        }
+
+        for image in images {
+            for reference in image_references(&image) {
+                let output = tokio::process::Command::new("finch")
+                    .args(["rmi", "-f", &reference])
+                    .output()
+                    .await?;
+                if output.status.success() {
+                    debug!("Removed image {}", reference);
+                }
+            }
+            status!("🗑️  {} → removed {}", name, style(&image).dim());
+            removed += 1;
+        }
+    }
+
+    status!("\n{} Removed {} image(s)", style("✅").green(), removed);
+    Ok(())
+}
+
+/// The tagged image plus the `:latest` alias added after each build
+fn image_references(image_name: &str) -> Vec<String> {
+    let mut references = vec![image_name.to_string()];
+    if let Some((repository, tag)) = image_name.rsplit_once(':') {
+        if tag != "latest" && !tag.contains('/') {
+            references.push(format!("{}:latest", repository));
+        }
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const MANIFEST: &str = r#"
+servers:
+  time:
+    target: uvx mcp-server-time
+    env:
+      TZ: UTC
+  files:
+    target: ./files-server
+    args: ["--root", "/data"]
+    volumes: ["/tmp/data:/data"]
+    allowPaths: ["/srv/notes:ro"]
+    network: none
+    restart: on-failure:3
+  github:
+    target: ghcr.io/example/github-mcp:1.0
+    direct: true
+    profiles: [dev]
+"#;
+
+    #[test]
+    fn test_parse_and_select() {
+        let manifest = ServersManifest::parse(MANIFEST).unwrap();
+        assert_eq!(manifest.servers.len(), 3);
+
+        let names = |selected: Vec<(&String, &ServerSpec)>| -> Vec<String> {
+            selected.into_iter().map(|(name, _)| name.clone()).collect()
+        };
+
+        assert_eq!(names(manifest.select(&[], &[]).unwrap()), ["files", "time"]);
+        assert_eq!(names(manifest.select(&["dev".to_string()], &[]).unwrap()), ["files", "github", "time"]);
+        assert_eq!(names(manifest.select(&[], &["github".to_string()]).unwrap()), ["github"]);
+        assert!(manifest.select(&[], &["missing".to_string()]).is_err());
+
+        assert!(ServersManifest::parse("servers:\n  bad:\n    target: ''\n").is_err());
+    }
+
+    #[test]
+    fn test_load_resolves_volumes_against_manifest() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("finch-mcp.servers.yaml");
+        std::fs::write(&path, "servers:\n  files:\n    target: ./files-server\n    volumes: [\"./data:/data:ro\"]\n").unwrap();
+
+        let (manifest, _) = ServersManifest::load(Some(&path)).unwrap();
+        assert_eq!(manifest.servers["files"].volumes, [format!("{}:/data:ro", dir.path().join("data").display())]);
+
+        std::fs::write(&path, "servers:\n  files:\n    target: ./files-server\n    volumes: [\"./data:data\"]\n").unwrap();
+        assert!(ServersManifest::load(Some(&path)).is_err());
+    }
+
+    #[test]
+    fn test_server_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("files-server")).unwrap();
+        let manifest = ServersManifest::parse(MANIFEST).unwrap();
+
+        let time = manifest.servers["time"].source(temp_dir.path());
+        assert_eq!(time, ServerSource::Command {
+            command: "uvx".to_string(),
+            args: vec!["mcp-server-time".to_string()],
+        });
+        assert_eq!(time.cache_source(), Some("uvx mcp-server-time".to_string()));
+
+        let files = manifest.servers["files"].source(temp_dir.path());
+        assert_eq!(files, ServerSource::Local(temp_dir.path().join("./files-server")));
+        assert_eq!(manifest.servers["files"].network_mode(), NetworkMode::None);
+
+        let github = manifest.servers["github"].source(temp_dir.path());
+        assert_eq!(github, ServerSource::Image("ghcr.io/example/github-mcp:1.0".to_string()));
+        assert_eq!(github.cache_source(), None);
+    }
+
+    #[test]
+    fn test_mcp_server_entry() {
+        let manifest = ServersManifest::parse(MANIFEST).unwrap();
+        let spec = &manifest.servers["files"];
+        let entry = mcp_server_entry(spec, &ServerSource::Local(PathBuf::from("files-server")), "mcp-files-server:abcd1234", ConfigMode::Image, RestartPolicy::No);
+
+        assert_eq!(entry["command"], "finch-mcp");
+        assert_eq!(entry["args"], json!([
+            "run", "-v", "/tmp/data:/data", "--allow-path", "/srv/notes:ro", "--network", "none", "--restart", "on-failure:3",
+            "--direct", "mcp-files-server:abcd1234", "--", "--root", "/data"
+        ]));
+        assert!(entry["env"].get("EXTRA_ARGS").is_none());
+
+        let local = ServerSource::Local(PathBuf::from("/srv/files-server"));
+        assert_eq!(mcp_server_entry(spec, &local, "mcp-files-server:abcd1234", ConfigMode::Source, RestartPolicy::No)["args"], json!([
+            "run", "-v", "/tmp/data:/data", "--allow-path", "/srv/notes:ro", "--network", "none", "--restart", "on-failure:3",
+            "/srv/files-server", "--", "--root", "/data"
+        ]));
+        assert_eq!(mcp_server_entry(spec, &local, "mcp-files-server:abcd1234", ConfigMode::Latest, RestartPolicy::No)["args"][10], "mcp-files-server:latest");
+        assert_eq!(spec.volume_mounts(), ["/tmp/data:/data", "/srv/notes:/srv/notes:ro"]);
+        assert_eq!(spec.env_vars(), ["ALLOWED_PATHS=/srv/notes"]);
+        assert!(ServersManifest::parse("servers:\n  bad:\n    target: x\n    restart: always\n").is_err());
+
+        // `--restart` fills in for servers without a policy of their own
+        let time = &manifest.servers["time"];
+        let command = time.source(Path::new("."));
+        let args = mcp_server_entry(time, &command, "mcp-time:abcd1234", ConfigMode::Source, RestartPolicy::No)["args"].clone();
+        assert!(!args.as_array().unwrap().contains(&json!("--restart")));
+        let restart = RestartPolicy::OnFailure { max_restarts: None };
+        let args = mcp_server_entry(time, &command, "mcp-time:abcd1234", ConfigMode::Source, restart)["args"].as_array().unwrap().clone();
+        assert_eq!(args[3..5], [json!("--restart"), json!("on-failure")]);
+        assert_eq!(mcp_server_entry(spec, &local, "mcp-files-server:abcd1234", ConfigMode::Source, restart)["args"][8], "on-failure:3");
+
+        assert_eq!(image_references("mcp-files-server:abcd1234"), ["mcp-files-server:abcd1234", "mcp-files-server:latest"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_build_concurrently() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let items: Vec<(String, u64)> = (0..6).map(|i| (format!("server-{}", i), 30 - i * 5)).collect();
+
+        let (running_in, peak_in) = (Arc::clone(&running), Arc::clone(&peak));
+        let results = build_concurrently(items, 2, move |delay| {
+            let (running, peak) = (Arc::clone(&running_in), Arc::clone(&peak_in));
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                assert!(output::target_label().is_some());
+                tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                if delay == 10 { anyhow::bail!("boom") } else { Ok(format!("mcp-{}", delay)) }
+            }
+        }).await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["server-0", "server-1", "server-2", "server-3", "server-4", "server-5"]);
+        assert_eq!(results[0].1.as_ref().unwrap(), "mcp-30");
+        assert!(results[4].1.is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! `finch-mcp upgrade`: rebuild cached servers whose upstream has moved on
+//!
+//! Command servers are pinned to a package release when they're built, and git servers
+//! are cached by commit, so neither changes until asked. `upgrade` asks the package
+//! registry or the git remote what is current, rebuilds the images that are behind
+//! (moving their `:latest` tags) and reports what changed. Local directories are rebuilt
+//! whenever they change and images are pulled rather than built, so both are left alone.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use console::style;
+
+use crate::cache::{git_refs, CacheEntry, CacheManager};
+use crate::core::server_manifest::{build_server, BuildDefaults, ServerSpec};
+use crate::status;
+use crate::utils::command_detector::{detect_command_type, CommandDetails, CommandType};
+use crate::utils::git_repository::GitRepository;
+use crate::utils::offline;
+use crate::utils::package_version;
+use crate::utils::progress::blocking;
+
+/// What became of one cached server
+#[derive(Debug)]
+pub enum UpgradeStatus {
+    UpToDate,
+    /// Newer upstream, not rebuilt because of `--dry-run`
+    Outdated,
+    /// Rebuilt as this image
+    Upgraded(String),
+    /// Upstream couldn't be checked, or the rebuild failed
+    Failed(String),
+}
+
+/// One cached server and its upstream
+#[derive(Debug)]
+pub struct Upgrade {
+    pub source: String,
+    /// The `package@version` or commit the cached image was built from, if recorded
+    pub built: Option<String>,
+    /// What is current upstream
+    pub latest: Option<String>,
+    pub status: UpgradeStatus,
+}
+
+/// The newest cached image of each command and git source among `entries`
+pub fn upgradable(entries: &[&CacheEntry]) -> Vec<CacheEntry> {
+    let mut newest: HashMap<&str, &CacheEntry> = HashMap::new();
+    for entry in entries.iter().filter(|entry| is_upgradable(&entry.source_path)) {
+        let source = entry.source_path.trim();
+        if newest.get(source).is_none_or(|current| current.created_at < entry.created_at) {
+            newest.insert(source, entry);
+        }
+    }
+    let mut upgradable: Vec<CacheEntry> = newest.into_values().cloned().collect();
+    upgradable.sort_by(|a, b| a.source_path.cmp(&b.source_path));
+    upgradable
+}
+
+fn is_upgradable(source: &str) -> bool {
+    GitRepository::is_git_url(source.trim()) || command_details(source).is_some()
+}
+
+/// The `uvx`/`npx` command a source names
+fn command_details(source: &str) -> Option<CommandDetails> {
+    let mut words = source.split_whitespace().map(str::to_string);
+    let command = words.next()?;
+    let details = detect_command_type(&command, &words.collect::<Vec<_>>());
+    matches!(details.cmd_type, CommandType::PythonUvx | CommandType::NodeNpx).then_some(details)
+}
+
+/// What the entry was built from and what is current, as `package@version` or a commit
+fn check(entry: &CacheEntry) -> Result<(Option<String>, Option<String>)> {
+    let source = entry.source_path.trim();
+    if GitRepository::is_git_url(source) {
+        return Ok((Some(entry.content_hash.clone()), Some(git_refs::resolve_commit(source, false, true)?)));
+    }
+    let latest = match command_details(source) {
+        Some(details) => package_version::current(&details)?,
+        None => None,
+    };
+    Ok((entry.pinned_package.clone(), latest))
+}
+
+/// Check every cached command and git server (or those matching `target`) against
+/// upstream, rebuilding the ones that are behind unless `dry_run`
+pub async fn upgrade(target: Option<&str>, dry_run: bool, defaults: &BuildDefaults) -> Result<Vec<Upgrade>> {
+    offline::ensure_online(defaults.offline, "Checking for newer releases")?;
+    // Always ask the remote, never a remembered resolution
+    let defaults = BuildDefaults { force_rebuild: true, refresh: true, ..defaults.clone() };
+    let cache_manager = CacheManager::new()?;
+    let entries: Vec<&CacheEntry> = match target {
+        Some(target) => cache_manager.find_entries(target),
+        None => cache_manager.entries().collect(),
+    };
+    let candidates = upgradable(&entries);
+    if let (Some(target), true) = (target, candidates.is_empty()) {
+        anyhow::bail!("No cached command or git server matches '{}'. Local directories and images are never upgraded", target);
+    }
+
+    let mut upgrades = Vec::new();
+    for entry in candidates {
+        let source = entry.source_path.trim().to_string();
+        let (built, latest) = match blocking(|| check(&entry)) {
+            Ok(versions) => versions,
+            Err(e) => {
+                upgrades.push(Upgrade { source, built: None, latest: None, status: UpgradeStatus::Failed(format!("{:#}", e)) });
+                continue;
+            }
+        };
+        let status = if latest.is_none() || built == latest {
+            UpgradeStatus::UpToDate
+        } else if dry_run {
+            UpgradeStatus::Outdated
+        } else {
+            status!("⬆️  Upgrading {}...", style(&source).cyan());
+            let spec = ServerSpec { target: source.clone(), ..Default::default() };
+            match build_server(&spec, &spec.source(Path::new("")), &defaults).await {
+                Ok(image) => UpgradeStatus::Upgraded(image),
+                Err(e) => UpgradeStatus::Failed(format!("{:#}", e)),
+            }
+        };
+        upgrades.push(Upgrade { source, built, latest, status });
+    }
+    Ok(upgrades)
+}
+
+/// A commit shortened for display; package versions are shown whole
+fn short(version: &str) -> &str {
+    if version.len() == 40 && version.chars().all(|c| c.is_ascii_hexdigit()) {
+        &version[..12]
+    } else {
+        version
+    }
+}
+
+/// Print what `finch-mcp upgrade` found and did
+pub fn print_report(upgrades: &[Upgrade]) {
+    if upgrades.is_empty() {
+        status!("ℹ No cached command or git servers to upgrade");
+        return;
+    }
+    for upgrade in upgrades {
+        let change = format!(
+            "{} → {}",
+            upgrade.built.as_deref().map(short).unwrap_or("unknown"),
+            upgrade.latest.as_deref().map(short).unwrap_or("unknown")
+        );
+        match &upgrade.status {
+            UpgradeStatus::UpToDate => status!("  {} {} is up to date", style("✓").green(), upgrade.source),
+            UpgradeStatus::Outdated => status!("  {} {}: {}", style("⬆").yellow(), upgrade.source, change),
+            UpgradeStatus::Upgraded(image) => status!("  {} {}: {} ({})", style("✅").green(), upgrade.source, change, style(image).cyan()),
+            UpgradeStatus::Failed(e) => status!("  {} {}: {}", style("❌").red(), upgrade.source, e),
+        }
+    }
+
+    let count = |matches: fn(&UpgradeStatus) -> bool| upgrades.iter().filter(|upgrade| matches(&upgrade.status)).count();
+    let upgraded = count(|status| matches!(status, UpgradeStatus::Upgraded(_)));
+    let outdated = count(|status| matches!(status, UpgradeStatus::Outdated));
+    let failed = count(|status| matches!(status, UpgradeStatus::Failed(_)));
+    if outdated > 0 {
+        status!("\n{} {} of {} server(s) can be upgraded; run without --dry-run to rebuild them", style("⬆️").yellow(), outdated, upgrades.len());
+    } else if upgraded + failed == 0 {
+        status!("\n{} All {} server(s) are up to date", style("✅").green(), upgrades.len());
+    } else {
+        status!("\n{} Upgraded {} of {} server(s), {} failed", style("📦").blue(), upgraded, upgrades.len(), failed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(image: &str, source: &str, created_at: u64) -> CacheEntry {
+        CacheEntry {
+            content_hash: "abc".to_string(),
+            image_name: image.to_string(),
+            created_at,
+            last_accessed: created_at,
+            project_type: "PythonUvx".to_string(),
+            source_path: source.to_string(),
+            build_options_hash: "def".to_string(),
+            run_count: 0,
+            last_run: None,
+            pinned_package: None,
+            run_id: None,
+            build_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_upgradable() {
+        let entries = [
+            entry("mcp-auto-time:old", "uvx mcp-server-time ", 1),
+            entry("mcp-auto-time:new", "uvx mcp-server-time ", 2),
+            entry("mcp-repo:a1", "https://github.com/user/repo", 1),
+            entry("mcp-local:b2", "/srv/server", 1),
+            entry("mcp-auto-node:c3", "node server.js", 1),
+        ];
+        let upgradable = upgradable(&entries.iter().collect::<Vec<_>>());
+        let images: Vec<&str> = upgradable.iter().map(|entry| entry.image_name.as_str()).collect();
+        assert_eq!(images, ["mcp-repo:a1", "mcp-auto-time:new"]);
+        assert_eq!(short(&"a".repeat(40)), "aaaaaaaaaaaa");
+        assert_eq!(short("mcp-server-time@0.6.2"), "mcp-server-time@0.6.2");
+    }
+}
@@ -0,0 +1,106 @@
+//! `finch-mcp warm`: build and cache servers without running them
+//!
+//! Meant for CI jobs that pre-populate the image cache (see `cache export`), so
+//! the first start on a developer machine or by an agent is instant.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use console::style;
+
+use crate::core::server_manifest::{build_concurrently, build_server, BuildDefaults, ServerSource, ServerSpec, ServersManifest};
+use crate::finch::client::FinchClient;
+use crate::finch::pull::{self, PullPolicy};
+use crate::status;
+use crate::utils::progress::build_policy;
+
+/// One server to build
+#[derive(Debug, Clone)]
+pub struct WarmTarget {
+    /// Manifest server name, or the target itself
+    pub name: String,
+    pub spec: ServerSpec,
+    /// Directory local targets are resolved against
+    pub base_dir: PathBuf,
+}
+
+impl WarmTarget {
+    /// A command, git repository, local directory or image given on the command line
+    pub fn from_target(target: &str) -> Self {
+        Self {
+            name: target.to_string(),
+            spec: ServerSpec { target: target.to_string(), ..Default::default() },
+            base_dir: PathBuf::new(),
+        }
+    }
+}
+
+/// The result of warming one target
+#[derive(Debug)]
+pub struct WarmOutcome {
+    pub name: String,
+    /// The cached (or pulled) image
+    pub result: Result<String>,
+}
+
+/// The enabled servers of a servers manifest
+pub fn manifest_targets(path: Option<&Path>, profiles: &[String]) -> Result<Vec<WarmTarget>> {
+    let (manifest, path) = ServersManifest::load(path)?;
+    let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    Ok(manifest.select(profiles, &[])?
+        .into_iter()
+        .map(|(name, spec)| WarmTarget { name: name.clone(), spec: spec.clone(), base_dir: base_dir.clone() })
+        .collect())
+}
+
+/// Build every target, at most `jobs` at a time. A failure doesn't stop the other builds;
+/// outcomes are returned in the order of `targets`
+pub async fn warm(targets: Vec<WarmTarget>, jobs: usize, defaults: &BuildDefaults) -> Vec<WarmOutcome> {
+    let targets: Vec<(String, WarmTarget)> = targets.into_iter().map(|target| (target.name.clone(), target)).collect();
+    let defaults = defaults.clone();
+    let results = build_concurrently(targets, jobs, move |target| {
+        let defaults = defaults.clone();
+        async move {
+            let result = warm_one(&target, &defaults)
+                .await
+                .with_context(|| format!("Failed to build '{}'", target.name));
+            match &result {
+                Ok(image) => status!("{} {} → {}", style("✅").green(), target.name, image),
+                Err(e) => status!("{} {}: {:#}", style("❌").red(), target.name, e),
+            }
+            result
+        }
+    }).await;
+
+    results.into_iter().map(|(name, result)| WarmOutcome { name, result }).collect()
+}
+
+async fn warm_one(target: &WarmTarget, defaults: &BuildDefaults) -> Result<String> {
+    let source = target.spec.source(&target.base_dir);
+    if let ServerSource::Image(image) = &source {
+        // Nothing to build, but make sure the image is local
+        pull::ensure_image(&FinchClient::new(), image, PullPolicy::Missing, defaults.offline, &build_policy(None, None)).await?;
+        return Ok(image.clone());
+    }
+    build_server(&target.spec, &source, defaults).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("finch-mcp.servers.yaml");
+        std::fs::write(&path, "servers:\n  time:\n    target: uvx mcp-server-time\n  fs:\n    target: ./fs\n    profiles: [files]\n").unwrap();
+
+        let targets = manifest_targets(Some(&path), &[]).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].name, "time");
+        assert_eq!(targets[0].base_dir, dir.path());
+
+        let target = WarmTarget::from_target("uvx mcp-server-time");
+        assert!(matches!(target.spec.source(&target.base_dir), ServerSource::Command { command, .. } if command == "uvx"));
+    }
+}
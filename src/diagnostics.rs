@@ -0,0 +1,109 @@
+//! Actionable hints for common build and startup failures
+//!
+//! Raw `finch build` output or a server's last stderr lines rarely say what to do next.
+//! [`diagnose`] matches the output against known failures (a full VM disk, a private
+//! registry refusing access, a missing interpreter, an image without a build for this
+//! architecture, ...) and returns a hint for each, which build and container-exit
+//! errors append.
+
+use std::path::Path;
+
+/// A known failure: any of `patterns` (lowercase) in the output triggers `hint`
+struct Rule {
+    patterns: &'static [&'static str],
+    hint: &'static str,
+}
+
+const RULES: &[Rule] = &[
+    Rule {
+        patterns: &["no space left on device", "enospc"],
+        hint: "The Finch VM's disk is full. Free space with `finch-mcp gc` or `finch system prune -a`, \
+               or run `finch-mcp vm recreate` with a larger disk",
+    },
+    Rule {
+        patterns: &["npm err! code e403", "npm error code e403", "403 forbidden - get", "e403 forbidden"],
+        hint: "The npm registry refused access (403). For a private registry or scope, pass --forward-registry \
+               so the build can use the credentials in your ~/.npmrc",
+    },
+    Rule {
+        patterns: &["npm err! code e401", "npm error code e401", "http error 401", "http error 403"],
+        hint: "The package registry wants credentials. Pass --forward-registry to use the ones in your \
+               ~/.npmrc, pip.conf or Poetry config; check they haven't expired",
+    },
+    Rule {
+        patterns: &["python: command not found", "python: not found", "exec: \"python\": executable file not found"],
+        hint: "The image has `python3` but no `python`. Start the server with `--cmd \"python3 ...\"`, \
+               or set `runtime.command` in .finch-mcp",
+    },
+    Rule {
+        patterns: &["no matching manifest for", "exec format error", "does not match the specified platform"],
+        hint: "An image has no build for this machine's architecture (arm64 on Apple silicon). Use a multi-arch \
+               base image, or pin `FROM --platform=linux/amd64` to run it under emulation",
+    },
+    Rule {
+        patterns: &["x509: certificate signed by unknown authority", "self_signed_cert_in_chain", "certificate_verify_failed", "unable_to_get_issuer_cert_locally"],
+        hint: "A TLS certificate wasn't trusted, as happens behind a TLS-intercepting proxy. Pass the proxy's \
+               root CA with --ca-cert",
+    },
+    Rule {
+        patterns: &["toomanyrequests", "429 too many requests"],
+        hint: "The registry is rate limiting pulls. Log in with `finch-mcp login` to raise the limit, or retry later",
+    },
+    Rule {
+        patterns: &["exit code: 137", "exited with 137", "signal: killed", "javascript heap out of memory"],
+        hint: "The build ran out of memory. Give the Finch VM more with `finch-mcp vm recreate --memory 8GiB`",
+    },
+    Rule {
+        patterns: &["no matching distribution found", "could not find a version that satisfies"],
+        hint: "pip found no release of a dependency for this Python version and platform. Check the version pin, \
+               or set the Python version in .python-version or pyproject.toml",
+    },
+];
+
+/// Hints for the failures `output` shows, each at most once, in rule order
+pub fn diagnose(output: &str) -> Vec<String> {
+    let output = output.to_lowercase();
+    RULES.iter()
+        .filter(|rule| rule.patterns.iter().any(|pattern| output.contains(pattern)))
+        .map(|rule| rule.hint.to_string())
+        .collect()
+}
+
+/// Hints for a failed build, from its log. A missing or unreadable log gives none
+pub fn diagnose_log(log_path: &Path) -> Vec<String> {
+    std::fs::read_to_string(log_path).map(|log| diagnose(&log)).unwrap_or_default()
+}
+
+/// Hints as lines to append to an error message
+pub fn format_hints(hints: &[String]) -> String {
+    hints.iter().map(|hint| format!("\nHint: {}", hint)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        let hints = diagnose("#9 ERROR: failed to copy: write /var/lib/buildkit/...: no space left on device");
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].contains("disk is full"));
+
+        let hints = diagnose("npm ERR! code E403\nnpm ERR! 403 Forbidden - GET https://npm.acme.dev/@acme%2fmcp");
+        assert!(hints[0].contains("--forward-registry"));
+
+        assert!(diagnose("sh: 1: python: not found")[0].contains("python3"));
+        assert!(diagnose("no matching manifest for linux/arm64/v8 in the manifest list entries")[0].contains("architecture"));
+        assert!(diagnose("SSL: CERTIFICATE_VERIFY_FAILED")[0].contains("--ca-cert"));
+
+        // Several failures, each hinted once
+        assert_eq!(diagnose("ENOSPC\nno space left on device\nexec format error").len(), 2);
+        assert!(diagnose("npm ERR! 404 Not Found").is_empty());
+    }
+
+    #[test]
+    fn test_format_hints() {
+        assert_eq!(format_hints(&[]), "");
+        assert_eq!(format_hints(&["a".to_string(), "b".to_string()]), "\nHint: a\nHint: b");
+    }
+}
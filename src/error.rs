@@ -0,0 +1,130 @@
+//! Typed errors for the public API
+//!
+//! Internals use `anyhow`; the public entry points (the containerize, run and build
+//! functions and the `FinchClient` methods) convert to `FinchMcpError` so embedders can
+//! match on what went wrong. Failures without a dedicated variant end up in `Other`.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::diagnostics;
+use crate::finch::client::ContainerExit;
+use crate::finch::shutdown::{Interrupted, Signal};
+
+/// Result type of the public API
+pub type Result<T> = std::result::Result<T, FinchMcpError>;
+
+/// Everything the public API can fail with; new variants may be added
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FinchMcpError {
+    /// The `finch` CLI is missing or doesn't respond
+    FinchNotInstalled,
+    /// The Finch VM couldn't be initialized or started
+    VmNotRunning { reason: String },
+    /// A direct-container run named an image that doesn't exist locally
+    ImageNotFound { image: String },
+    /// No supported project type was found at `source`
+    UnsupportedProject { source: String },
+    /// `finch build` failed; details are in the build log, and `hints` say what may fix it
+    BuildFailed { log_path: PathBuf, reason: String, hints: Vec<String> },
+    /// The image cache index couldn't be read
+    CacheCorrupted { path: PathBuf, reason: String },
+    /// The MCP server container exited unsuccessfully
+    ContainerExited(ContainerExit),
+    /// The session was stopped by a signal
+    Interrupted(Signal),
+    /// Any other failure
+    Other(anyhow::Error),
+}
+
+impl FinchMcpError {
+    /// A build failure, with hints for whatever known problem its log shows
+    pub fn build_failed(log_path: PathBuf, reason: String) -> Self {
+        let hints = diagnostics::diagnose_log(&log_path);
+        Self::BuildFailed { log_path, reason, hints }
+    }
+}
+
+impl fmt::Display for FinchMcpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FinchNotInstalled => write!(f, "Finch is not installed or not available. Please install Finch from https://runfinch.com/"),
+            Self::VmNotRunning { reason } => write!(f, "Finch VM is not running: {}", reason),
+            Self::ImageNotFound { image } => write!(f, "Container image '{}' not found", image),
+            Self::UnsupportedProject { source } => write!(f, "Could not detect project type in {}", source),
+            Self::BuildFailed { log_path, reason, hints } => write!(
+                f,
+                "Container build failed (run {}): {}\nBuild log: {}{}",
+                crate::run_id::short(), reason, log_path.display(), diagnostics::format_hints(hints)
+            ),
+            Self::CacheCorrupted { path, reason } => write!(f, "Cache file {} is corrupted: {}", path.display(), reason),
+            Self::ContainerExited(exit) => write!(f, "{}", exit),
+            Self::Interrupted(signal) => write!(f, "{}", Interrupted(*signal)),
+            Self::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for FinchMcpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // `Other` shows the top of its chain, so the rest continues from there
+        match self {
+            Self::Other(err) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for FinchMcpError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Other(err.into())
+    }
+}
+
+impl From<anyhow::Error> for FinchMcpError {
+    /// Recover the typed error from an `anyhow` chain, falling back to `Other`
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<FinchMcpError>() {
+            Ok(typed) => return typed,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<ContainerExit>() {
+            Ok(exit) => return Self::ContainerExited(exit),
+            Err(err) => err,
+        };
+        match err.downcast_ref::<Interrupted>() {
+            Some(Interrupted(signal)) => Self::Interrupted(*signal),
+            None => Self::Other(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_anyhow_recovers_typed_errors() {
+        let err: anyhow::Error = FinchMcpError::ImageNotFound { image: "mcp-demo".to_string() }.into();
+        assert!(matches!(FinchMcpError::from(err), FinchMcpError::ImageNotFound { image } if image == "mcp-demo"));
+
+        let err: anyhow::Error = ContainerExit { code: 3, stderr_tail: Vec::new() }.into();
+        assert!(matches!(FinchMcpError::from(err), FinchMcpError::ContainerExited(ContainerExit { code: 3, .. })));
+
+        let err: anyhow::Error = Interrupted(Signal::Terminate).into();
+        assert!(matches!(FinchMcpError::from(err), FinchMcpError::Interrupted(Signal::Terminate)));
+
+        let err = FinchMcpError::from(anyhow::anyhow!("disk full"));
+        assert!(matches!(err, FinchMcpError::Other(_)));
+        assert_eq!(err.to_string(), "disk full");
+
+        let err = FinchMcpError::from(std::io::Error::other("disk full"));
+        assert!(matches!(err, FinchMcpError::Other(_)));
+        assert_eq!(err.to_string(), "disk full");
+
+        let err = FinchMcpError::from(anyhow::anyhow!("disk full").context("Failed to write cache file"));
+        let chain: Vec<String> = anyhow::Error::from(err).chain().map(ToString::to_string).collect();
+        assert_eq!(chain, ["Failed to write cache file", "disk full"]);
+    }
+}
@@ -0,0 +1,100 @@
+//! Structured progress events for library consumers
+//!
+//! Embedders (e.g. a GUI) give the containerize options an `EventSink`
+//! (`with_event_sink`) to receive what that flow is doing. While a flow has a sink,
+//! its build progress bar is hidden so the embedder owns the presentation; set the
+//! output mode to `Silent` to hide `status!` output as well.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::utils::progress::BuildStep;
+
+/// Something the containerize and run flows did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A usable image was found in the cache
+    CacheHit { image: String },
+    /// No cached image; `source` (URL, path or command) will be built
+    CacheMiss { source: String },
+    /// Cloning a git repository has started
+    CloneStarted { repo_url: String },
+    /// The project or command type was detected, e.g. `NodeJs` or `PythonUvx`
+    DetectionResult { project_type: String },
+    /// `finch build` has started; its full output goes to `log_path`
+    BuildStarted { image: String, log_path: PathBuf },
+    /// The build moved on to a new step
+    BuildStep(BuildStep),
+    /// The build failed with a transient error and will be retried
+    BuildRetry { retry: u32, reason: String },
+    /// The build finished
+    BuildFinished { image: String, success: bool, duration_secs: u64 },
+    /// The server container is about to be started
+    ContainerStarting { image: String },
+}
+
+/// Receives events as they happen; called from whichever thread produced them
+pub trait EventSink: Send + Sync {
+    fn event(&self, event: &Event);
+}
+
+impl<F> EventSink for F
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn event(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Where a flow reports its events: the sink its options were given, if any
+#[derive(Clone, Default)]
+pub struct Events {
+    sink: Option<Arc<dyn EventSink>>,
+}
+
+impl Events {
+    pub fn new(sink: Arc<dyn EventSink>) -> Self {
+        Self { sink: Some(sink) }
+    }
+    
+    /// Check if an embedder takes these events
+    pub fn is_set(&self) -> bool {
+        self.sink.is_some()
+    }
+    
+    /// Report an event to the sink, if any
+    pub fn emit(&self, event: Event) {
+        if let Some(sink) = &self.sink {
+            sink.event(&event);
+        }
+    }
+}
+
+impl std::fmt::Debug for Events {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Events").field("sink", &self.is_set()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_closure_sink_receives_events() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = {
+            let seen = seen.clone();
+            move |event: &Event| seen.lock().unwrap().push(event.clone())
+        };
+
+        sink.event(&Event::CacheHit { image: "mcp-local-demo".to_string() });
+        sink.event(&Event::BuildStep(BuildStep { current: 2, total: 5, instruction: "RUN npm ci".to_string() }));
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0], Event::CacheHit { image: "mcp-local-demo".to_string() });
+    }
+}
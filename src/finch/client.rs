@@ -1,18 +1,97 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::process::Stdio;
 use tokio::process::Command;
 use log::{info, warn, debug};
 use console::style;
 use crate::{status, output};
+use crate::cache::{self, CacheManager, RetentionPolicy, DOCKERFILE_LABEL};
 use crate::core::finch_config::FinchConfig;
+use crate::diagnostics;
+use crate::error::FinchMcpError;
+use crate::events::{Event, Events};
 use crate::mcp::buffer::MCPBuffer;
 use crate::mcp::async_proxy::AsyncStdioProxy;
+use crate::finch::{shutdown, vm};
+use crate::mcp::health_check;
+use crate::mcp::startup;
+use crate::mcp::trace::{self, McpTracer};
+use crate::mcp::supervisor::{self, RestartPolicy};
+use crate::utils::proxy;
+use crate::utils::timezone;
+use crate::utils::user_map::{self, UserMap};
 use std::sync::Arc;
-use std::time::Duration;
-use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::fmt;
+use std::str::FromStr;
 use tokio::io::{AsyncReadExt};
 use std::io::Write;
 
+/// Label set on every container finch-mcp runs
+pub const MANAGED_LABEL: &str = "finch-mcp.managed=true";
+
+/// Label key recording the image a container was started from
+pub const IMAGE_LABEL_KEY: &str = "finch-mcp.image";
+
+/// Network mode for running containers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum NetworkMode {
+    /// Finch's default bridge network
+    #[default]
+    Bridge,
+    
+    /// Share the host's network stack
+    Host,
+    
+    /// No network access at all (for fully offline servers)
+    None,
+    
+    /// A named finch network (see `finch network ls`)
+    Named(String),
+}
+
+impl NetworkMode {
+    /// Value for `finch run --network`, or `None` to use finch's default
+    pub fn as_finch_arg(&self) -> Option<&str> {
+        match self {
+            NetworkMode::Bridge => None,
+            NetworkMode::Host => Some("host"),
+            NetworkMode::None => Some("none"),
+            NetworkMode::Named(name) => Some(name),
+        }
+    }
+    
+    /// Whether this mode shares the host network (also used for builds)
+    pub fn is_host(&self) -> bool {
+        matches!(self, NetworkMode::Host)
+    }
+}
+
+impl FromStr for NetworkMode {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err("network mode cannot be empty".to_string()),
+            "bridge" | "default" => Ok(NetworkMode::Bridge),
+            "host" => Ok(NetworkMode::Host),
+            "none" => Ok(NetworkMode::None),
+            name => Ok(NetworkMode::Named(name.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for NetworkMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkMode::Bridge => write!(f, "bridge"),
+            NetworkMode::Host => write!(f, "host"),
+            NetworkMode::None => write!(f, "none"),
+            NetworkMode::Named(name) => write!(f, "{}", name),
+        }
+    }
+}
+
 /// Options for running a container in STDIO mode
 #[derive(Debug, Clone)]
 pub struct StdioRunOptions {
@@ -25,10 +104,266 @@ pub struct StdioRunOptions {
     /// Volume mounts for the container
     pub volumes: Vec<String>,
     
-    /// Use host network for the container
-    pub host_network: bool,
+    /// Network mode for the container
+    pub network: NetworkMode,
+    
+    /// Port mappings to publish (`[IP:]HOST:CONTAINER[/PROTO]`)
+    pub publish: Vec<String>,
+    
+    /// Arguments for the server, passed after the image name
+    pub args: Vec<String>,
+    
+    /// When to run as the host user; `Never` for images finch-mcp didn't build
+    pub user_map: UserMap,
+    
+    /// Check that the server answers `initialize` before connecting the client (`--health-check`)
+    pub health_check: bool,
+    
+    /// Abort if the server writes nothing to stdout this long after the client's first message,
+    /// over `mcp.startupTimeout` (`--startup-timeout`)
+    pub startup_timeout: Option<Duration>,
+    
+    /// Record every JSON-RPC frame of the session to this NDJSON file (`--trace-mcp`)
+    pub trace_mcp: Option<PathBuf>,
+    
+    /// Pass the host's proxy variables to the container by name (`--forward-proxy`)
+    pub forward_proxy: bool,
+    
+    /// Leave the host's timezone and locale out of the container (`--no-forward-timezone`)
+    pub no_forward_timezone: bool,
+    
+    /// Restart the server when it exits with an error (`--restart`)
+    pub restart: RestartPolicy,
+    
+    /// Receives `ContainerStarting`, for embedders
+    pub events: Events,
+}
+
+impl StdioRunOptions {
+    /// Restart the server after it crashes, as `policy` says
+    pub fn with_restart(mut self, policy: RestartPolicy) -> Self {
+        self.restart = policy;
+        self
+    }
+    
+    /// Arguments for `finch run`, ending with the image name and the server's arguments
+    pub fn finch_run_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = ["run", "--rm", "-i", "-e", "MCP_ENABLED=true", "-e", "MCP_STDIO=true"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        
+        // Label the container so `logs follow` can find it
+        args.push("--label".to_string());
+        args.push(MANAGED_LABEL.to_string());
+        args.push("--label".to_string());
+        args.push(format!("{}={}", IMAGE_LABEL_KEY, self.image_name));
+        
+        // Forward the host's proxy variables by name (`--forward-proxy`), unless set explicitly
+        for name in proxy::forwarded_vars(self.forward_proxy) {
+            if !self.env_vars.iter().any(|env| env.split('=').next() == Some(name)) {
+                args.push("-e".to_string());
+                args.push(name.to_string());
+            }
+        }
+        
+        // The host's timezone and locale, unless set explicitly or `--no-forward-timezone`
+        for env in timezone::env_vars(&self.env_vars, !self.no_forward_timezone) {
+            args.push("-e".to_string());
+            args.push(env);
+        }
+        
+        // Add custom environment variables
+        for env in &self.env_vars {
+            args.push("-e".to_string());
+            args.push(env.clone());
+        }
+        
+        // Add volume mounts
+        for volume in &self.volumes {
+            args.push("-v".to_string());
+            args.push(volume.clone());
+        }
+        
+        // Run as the host user, so files written to mounted directories aren't root-owned
+        args.extend(user_map::run_args(self.user_map, &self.volumes, &self.env_vars));
+        
+        // Add published ports
+        for port in &self.publish {
+            args.push("-p".to_string());
+            args.push(port.clone());
+        }
+        
+        // Add network mode if not the default
+        if let Some(network) = self.network.as_finch_arg() {
+            args.push("--network".to_string());
+            args.push(network.to_string());
+        }
+        
+        args.push(self.image_name.clone());
+        args.extend(self.args.iter().cloned());
+        args
+    }
+    
+    /// `finch run` command for a named container that finch-mcp supervises, plus that name
+    pub fn supervised_command(&self) -> (Command, String) {
+        let name = shutdown::session_container_name();
+        (self.named_command(&name), name)
+    }
+    
+    /// Arguments for `finch run` starting a named container in the background
+    ///
+    /// Without `--rm`, so a server that exits stays around for `finch-mcp ps -a` and its logs;
+    /// `finch-mcp stop` removes it. `-i` keeps its stdin open for `finch-mcp attach`.
+    pub fn detached_args(&self, name: &str) -> Vec<String> {
+        let mut args = self.finch_run_args();
+        args.splice(1..2, ["-d".to_string(), "--name".to_string(), name.to_string()]);
+        args
+    }
+    
+    /// Arguments for `finch run` starting `command` (default `sh`) in place of the server,
+    /// with the same env, volumes and network
+    pub fn exec_args(&self, command: &[String], tty: bool) -> Vec<String> {
+        let mut args = self.finch_run_args();
+        args.truncate(args.len() - 1 - self.args.len());
+        if tty {
+            args.insert(3, "-t".to_string());
+        }
+        
+        let (entrypoint, command_args) = command.split_first().map_or(("sh", &[][..]), |(first, rest)| (first.as_str(), rest));
+        args.push("--entrypoint".to_string());
+        args.push(entrypoint.to_string());
+        args.push(self.image_name.clone());
+        args.extend(command_args.iter().cloned());
+        args
+    }
+    
+    /// `finch run` command for a container with the given name, e.g. to restart a supervised one
+    pub fn named_command(&self, name: &str) -> Command {
+        let mut args = self.finch_run_args();
+        args.splice(3..3, ["--name".to_string(), name.to_string()]);
+        
+        let mut command = Command::new("finch");
+        command.args(args);
+        command
+    }
+}
+
+/// A container's health for `finch-mcp list`, colored by state
+fn health_label(health: &str) -> String {
+    match health {
+        "healthy" => style("(healthy)").green().to_string(),
+        "unhealthy" => style("(unhealthy)").red().bold().to_string(),
+        other => style(format!("({})", other)).yellow().to_string(),
+    }
+}
+
+/// Name for a detached server when `--name` isn't given: the image's name without
+/// registry or tag, so the same target always gets the same name
+pub fn detached_name(image_name: &str) -> String {
+    let repository = image_name.rsplit('/').next().unwrap_or(image_name);
+    let repository = repository.split([':', '@']).next().unwrap_or(repository);
+    let name: String = repository
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '-' })
+        .collect();
+    if name.starts_with("mcp-") { name } else { format!("mcp-{}", name) }
+}
+
+/// A container started by finch-mcp
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct McpContainer {
+    pub id: String,
+    pub name: String,
+    /// Image from the `finch-mcp.image` label
+    pub image: String,
+    /// finch's status, e.g. `Up 5 minutes` or `Exited (1) 2 minutes ago`
+    pub status: String,
+}
+
+impl McpContainer {
+    /// Parse `finch ps --format '{{json .}}'` output, one container per line
+    pub fn parse_ps_output(output: &str) -> Vec<Self> {
+        output.lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line.trim()).ok())
+            .map(|container| {
+                let field = |key: &str| container.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                let labels = field("Labels");
+                let image = labels.split(',')
+                    .find_map(|label| label.strip_prefix(IMAGE_LABEL_KEY).and_then(|rest| rest.strip_prefix('=')))
+                    .map(str::to_string)
+                    .unwrap_or_else(|| field("Image"));
+                Self { id: field("ID"), name: field("Names"), image, status: field("Status") }
+            })
+            .collect()
+    }
+    
+    /// Pick the container to follow: by name or ID prefix, by image, or the newest one
+    pub fn select<'a>(containers: &'a [Self], target: Option<&str>, source_images: &[String]) -> Result<&'a Self> {
+        let Some(target) = target else {
+            // finch ps lists the newest container first
+            return containers.first()
+                .ok_or_else(|| anyhow::anyhow!("No running finch-mcp containers"));
+        };
+        
+        let image_matches = |container: &Self| {
+            let image = container.image.split(':').next().unwrap_or_default();
+            image == target || container.image == target || source_images.iter().any(|source_image| source_image == image)
+        };
+        
+        containers.iter()
+            .find(|container| container.name == target || (!container.id.is_empty() && container.id.starts_with(target)))
+            .or_else(|| containers.iter().find(|container| image_matches(container)))
+            .ok_or_else(|| anyhow::anyhow!("No running finch-mcp container matches '{}'", target))
+    }
+}
+
+/// Returned when a container exits unsuccessfully
+#[derive(Debug)]
+pub struct ContainerExit {
+    /// The container's exit code (128 + signal number if it was killed by a signal)
+    pub code: i32,
+    /// Last lines the container wrote to stderr
+    pub stderr_tail: Vec<String>,
+}
+
+impl ContainerExit {
+    pub fn new(status: std::process::ExitStatus, stderr_tail: Vec<String>) -> Self {
+        #[cfg(unix)]
+        let signal_code = {
+            use std::os::unix::process::ExitStatusExt;
+            status.signal().map(|signal| 128 + signal)
+        };
+        #[cfg(not(unix))]
+        let signal_code = None;
+        
+        let code = status.code().or(signal_code).unwrap_or(1);
+        Self { code, stderr_tail }
+    }
+    
+    /// `Ok` for a successful exit, otherwise a `ContainerExit` error
+    pub fn check(status: std::process::ExitStatus, stderr_tail: Vec<String>) -> Result<()> {
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Self::new(status, stderr_tail).into())
+        }
+    }
+}
+
+impl fmt::Display for ContainerExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Container exited with code {}", self.code)?;
+        if !self.stderr_tail.is_empty() {
+            let stderr = self.stderr_tail.join("\n");
+            write!(f, "\nLast container stderr:\n{}{}", stderr, diagnostics::format_hints(&diagnostics::diagnose(&stderr)))?;
+        }
+        Ok(())
+    }
 }
 
+impl std::error::Error for ContainerExit {}
+
 /// Client for interacting with Finch container CLI
 #[derive(Default)]
 pub struct FinchClient {}
@@ -40,7 +375,7 @@ impl FinchClient {
     }
     
     /// Check if Finch CLI is available on the system
-    pub async fn is_finch_available(&self) -> Result<bool> {
+    pub async fn is_finch_available(&self) -> crate::error::Result<bool> {
         let output = Command::new("finch")
             .arg("version")
             .stdout(Stdio::null())
@@ -57,7 +392,7 @@ impl FinchClient {
     }
     
     /// Check if Finch VM is initialized (exists)
-    pub async fn is_vm_initialized(&self) -> Result<bool> {
+    pub async fn is_vm_initialized(&self) -> crate::error::Result<bool> {
         debug!("Checking if Finch VM is initialized");
         let output = Command::new("finch")
             .args(["vm", "status"])
@@ -96,7 +431,7 @@ impl FinchClient {
     }
     
     /// Initialize Finch VM for first-time users
-    pub async fn initialize_vm(&self) -> Result<()> {
+    pub async fn initialize_vm(&self) -> crate::error::Result<()> {
         if !output::is_quiet_mode() {
             info!("🚀 Initializing Finch VM for first-time use...");
             info!("This may take a few minutes to download and set up the VM.");
@@ -105,8 +440,8 @@ impl FinchClient {
         let mut child = Command::new("finch")
             .args(["vm", "init"])
             .stdin(Stdio::null())
-            .stdout(if output::is_quiet_mode() { Stdio::null() } else { Stdio::inherit() })
-            .stderr(if output::is_quiet_mode() { Stdio::null() } else { Stdio::inherit() })
+            .stdout(output::child_output())
+            .stderr(output::child_output())
             .spawn()?;
             
         let status = child.wait().await?;
@@ -117,13 +452,17 @@ impl FinchClient {
             }
             Ok(())
         } else {
-            Err(anyhow::anyhow!("Failed to initialize Finch VM: exit code {}", status))
+            Err(FinchMcpError::VmNotRunning { reason: format!("`finch vm init` exited with {}", status) })
         }
     }
     
     /// Fast VM check - assumes VM is likely already running
-    pub async fn ensure_vm_running_fast(&self) -> Result<bool> {
+    pub async fn ensure_vm_running_fast(&self) -> crate::error::Result<bool> {
         debug!("Fast VM check for direct container execution");
+        if vm::is_starting_in_background() {
+            vm::wait_until_ready()?;
+            return Ok(true);
+        }
         
         // Try a quick status check first
         let status = Command::new("finch")
@@ -145,7 +484,7 @@ impl FinchClient {
     }
     
     /// Ensure Finch VM is running (with automatic initialization if needed)
-    pub async fn ensure_vm_running(&self) -> Result<bool> {
+    pub async fn ensure_vm_running(&self) -> crate::error::Result<bool> {
         debug!("Ensuring Finch VM is running");
         
         // First check if VM is initialized
@@ -176,7 +515,7 @@ impl FinchClient {
         let mut start_child = Command::new("finch")
             .args(["vm", "start"])
             .stdout(Stdio::null())
-            .stderr(if output::is_quiet_mode() { Stdio::null() } else { Stdio::inherit() })
+            .stderr(output::child_output())
             .spawn()?;
             
         let start_status = start_child.wait().await?;
@@ -187,19 +526,19 @@ impl FinchClient {
             }
             Ok(true)
         } else {
-            Err(anyhow::anyhow!("Failed to start Finch VM: exit code {}", start_status))
+            Err(FinchMcpError::VmNotRunning { reason: format!("`finch vm start` exited with {}", start_status) })
         }
     }
     
     /// Run a container in STDIO mode  
-    pub async fn run_stdio_container(&self, options: &StdioRunOptions, project_dir: Option<&Path>) -> Result<()> {
+    pub async fn run_stdio_container(&self, options: &StdioRunOptions, project_dir: Option<&Path>) -> crate::error::Result<()> {
         self.run_stdio_container_with_flags(options, project_dir, false).await
     }
     
     /// Run a container with buffered stdin for MCP mode
-    pub async fn run_stdio_container_buffered(&self, options: &StdioRunOptions, project_dir: Option<&Path>) -> Result<()> {
+    pub async fn run_stdio_container_buffered(&self, options: &StdioRunOptions, project_dir: Option<&Path>) -> crate::error::Result<()> {
         // In MCP mode, buffer stdin while the container starts
-        if output::is_quiet_mode() {
+        if output::is_mcp_mode() {
             use tokio::sync::mpsc;
             use std::sync::Mutex;
             
@@ -229,32 +568,9 @@ impl FinchClient {
             });
             
             // Start the container with piped stdin
+            cache::record_run(&options.image_name);
             let mut cmd = std::process::Command::new("finch");
-            cmd.arg("run")
-               .arg("--rm")
-               .arg("-i")
-               .arg("-e")
-               .arg("MCP_ENABLED=true")
-               .arg("-e")
-               .arg("MCP_STDIO=true");
-            
-            // Add custom environment variables
-            for env in &options.env_vars {
-                cmd.arg("-e").arg(env);
-            }
-            
-            // Add volume mounts
-            for volume in &options.volumes {
-                cmd.arg("-v").arg(volume);
-            }
-            
-            // Add host network if enabled
-            if options.host_network {
-                cmd.arg("--network").arg("host");
-            }
-            
-            // Add image name
-            cmd.arg(&options.image_name);
+            cmd.args(options.finch_run_args());
             
             // Spawn with piped stdin
             let mut child = cmd
@@ -292,12 +608,7 @@ impl FinchClient {
             
             // Wait for the container
             let status = child.wait()?;
-            
-            if status.success() {
-                Ok(())
-            } else {
-                Err(anyhow::anyhow!("Container exited with non-zero status code: {}", status))
-            }
+            Ok(ContainerExit::check(status, Vec::new())?)
         } else {
             // Non-MCP mode, use regular execution
             self.run_stdio_container_with_flags(options, project_dir, false).await
@@ -305,75 +616,44 @@ impl FinchClient {
     }
     
     /// Run a container in STDIO mode with additional control flags
-    pub async fn run_stdio_container_with_flags(&self, options: &StdioRunOptions, project_dir: Option<&Path>, _disable_proxy: bool) -> Result<()> {
-        // In MCP mode, exec immediately without any checks
-        if output::is_quiet_mode() {
+    #[tracing::instrument(name = "run", skip_all, fields(image = %options.image_name), err)]
+    pub async fn run_stdio_container_with_flags(&self, options: &StdioRunOptions, project_dir: Option<&Path>, disable_proxy: bool) -> crate::error::Result<()> {
+        options.events.emit(Event::ContainerStarting { image: options.image_name.clone() });
+        cache::record_run(&options.image_name);
+        
+        // In MCP mode, exec immediately unless the session needs supervising
+        if output::is_mcp_mode() {
+            let finch_config = match project_dir {
+                Some(dir) => FinchConfig::load_from_dir(dir).ok().flatten().unwrap_or_default(),
+                None => FinchConfig::default(),
+            };
+            vm::wait_until_ready()?;
+            if let Some(result) = self.run_supervised(options, &finch_config, disable_proxy).await {
+                return Ok(result?);
+            }
+            
             #[cfg(unix)]
             {
                 use std::os::unix::process::CommandExt;
                 
                 // Build and exec immediately
                 let mut cmd = std::process::Command::new("finch");
-                cmd.arg("run")
-                   .arg("--rm")
-                   .arg("-i")
-                   .arg("-e")
-                   .arg("MCP_ENABLED=true")
-                   .arg("-e")
-                   .arg("MCP_STDIO=true");
-                
-                for env in &options.env_vars {
-                    cmd.arg("-e").arg(env);
-                }
-                
-                for volume in &options.volumes {
-                    cmd.arg("-v").arg(volume);
-                }
-                
-                if options.host_network {
-                    cmd.arg("--network").arg("host");
-                }
-                
-                cmd.arg(&options.image_name);
+                cmd.args(options.finch_run_args());
                 
                 // Replace the current process immediately
                 let err = cmd.exec();
-                return Err(anyhow::anyhow!("Failed to exec finch: {}", err));
+                return Err(anyhow::anyhow!("Failed to exec finch: {}", err).into());
             }
+            
+            // No exec on Windows: keep finch-mcp in between and pump the pipes
+            #[cfg(not(unix))]
+            return self.run_piped(options).await;
         }
         
         // For non-MCP mode, do VM check
         debug!("Ensuring Finch VM is ready");
         self.ensure_vm_running_fast().await?;
         
-        // Build command
-        let mut cmd = Command::new("finch");
-        cmd.arg("run")
-           .arg("--rm")
-           .arg("-i")
-           .arg("-e")
-           .arg("MCP_ENABLED=true")
-           .arg("-e")
-           .arg("MCP_STDIO=true");
-        
-        // Add custom environment variables
-        for env in &options.env_vars {
-            cmd.arg("-e").arg(env);
-        }
-        
-        // Add volume mounts
-        for volume in &options.volumes {
-            cmd.arg("-v").arg(volume);
-        }
-        
-        // Add host network if enabled
-        if options.host_network {
-            cmd.arg("--network").arg("host");
-        }
-        
-        // Add image name
-        cmd.arg(&options.image_name);
-        
         // Load finch config if available
         let finch_config = if let Some(dir) = project_dir {
             FinchConfig::load_from_dir(dir)?.unwrap_or_default()
@@ -381,55 +661,117 @@ impl FinchClient {
             FinchConfig::default()
         };
         
-        // Disable MCP buffering entirely to fix STDIO communication issues
-        // The MCP proxy was interfering with proper STDIO handling for MCP servers
-        let should_use_proxy = false;
+        if let Some(result) = self.run_supervised(options, &finch_config, disable_proxy).await {
+            return Ok(result?);
+        }
         
-        if should_use_proxy {
-            // Run with proxy for MCP mode
-            log::debug!("Running finch command with MCP proxy: {:?}", cmd);
-            
-            let child = cmd
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()?;
-            
-            // Create buffer and proxy
-            let buffer = Arc::new(MCPBuffer::new(
-                finch_config.mcp.buffer_size,
-                Duration::from_secs(finch_config.mcp.startup_timeout)
-            ));
-            
-            let proxy = AsyncStdioProxy::new(buffer.clone(), child)?;
-            
-            // Start the proxy
-            proxy.start().await?;
-            
-            Ok(())
-        } else {
-            // Run with direct stdio inheritance
-            log::debug!("Running finch command with direct stdio: {:?}", cmd);
-            
-            let mut child = cmd
-                .stdin(Stdio::inherit())
-                .stdout(Stdio::inherit())
-                .stderr(Stdio::inherit())
-                .spawn()?;
-            
-            // Wait for the process to complete
+        // Run with direct stdio inheritance
+        let (mut cmd, container_name) = options.supervised_command();
+        log::debug!("Running finch command with direct stdio: {:?}", cmd);
+        
+        let mut child = cmd
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        
+        // Stderr is passed through, keeping the tail for the error message
+        let (stderr_tail, stderr_task) = startup::capture_stderr_tail(child.stderr.take());
+        
+        // Wait for the process to complete, stopping the container if we're signalled
+        Ok(shutdown::run_until_signal(async {
             let status = child.wait().await?;
+            let tail = startup::collect_stderr_tail(&stderr_tail, stderr_task).await;
+            ContainerExit::check(status, tail)
+        }, &[container_name]).await?)
+    }
+    
+    /// Run the container as a child process with stdio pumped through finch-mcp
+    ///
+    /// This is the MCP-mode path where `exec` isn't available (Windows). Ctrl+C stops
+    /// the container rather than leaving it running without a client.
+    pub async fn run_piped(&self, options: &StdioRunOptions) -> crate::error::Result<()> {
+        let (mut cmd, container_name) = options.supervised_command();
+        log::debug!("Running finch command with piped stdio: {:?}", cmd);
+        
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to start finch")?;
+        
+        let mut server_in = child.stdin.take().context("Failed to capture server stdin")?;
+        let mut server_out = child.stdout.take().context("Failed to capture server stdout")?;
+        let (stderr_tail, stderr_task) = startup::capture_stderr_tail(child.stderr.take());
+        
+        // Closing the client's stdin drops `server_in`, which ends the server's input too
+        let to_server = tokio::spawn(async move { tokio::io::copy(&mut tokio::io::stdin(), &mut server_in).await });
+        let to_client = tokio::spawn(async move { tokio::io::copy(&mut server_out, &mut tokio::io::stdout()).await });
+        
+        Ok(shutdown::run_until_signal(async {
+            let status = child.wait().await?;
+            let _ = to_client.await;
+            to_server.abort();
             
-            if status.success() {
-                Ok(())
-            } else {
-                Err(anyhow::anyhow!("Container exited with non-zero status code: {}", status))
-            }
+            let tail = startup::collect_stderr_tail(&stderr_tail, stderr_task).await;
+            ContainerExit::check(status, tail)
+        }, &[container_name]).await?)
+    }
+    
+    /// Run the session in-process if restarts, a health check, buffering, startup timeout or trace was requested
+    async fn run_supervised(&self, options: &StdioRunOptions, finch_config: &FinchConfig, disable_proxy: bool) -> Option<Result<()>> {
+        // --startup-timeout overrides the project's mcp.startupTimeout
+        let startup_timeout = options.startup_timeout
+            .unwrap_or_else(|| Duration::from_secs(finch_config.mcp.startup_timeout));
+        
+        if options.restart != RestartPolicy::No {
+            return Some(supervisor::run_with_restarts(options, options.restart).await);
+        }
+        let tracer = match trace::open(options.trace_mcp.as_deref()) {
+            Ok(tracer) => tracer,
+            Err(e) => return Some(Err(e)),
+        };
+        if options.health_check || finch_config.mcp.health_check {
+            return Some(health_check::run_with_health_check(options, startup_timeout, tracer).await);
+        }
+        if finch_config.mcp.buffering && !disable_proxy {
+            return Some(self.run_with_buffering_proxy(options, finch_config, startup_timeout, tracer).await);
+        }
+        if let Some(startup_timeout) = options.startup_timeout {
+            return Some(startup::run_with_startup_timeout(options, startup_timeout, tracer).await);
         }
+        if let Some(tracer) = tracer {
+            return Some(trace::run_traced(options, tracer).await);
+        }
+        None
+    }
+    
+    /// Run through the buffering proxy, which holds early client messages until the server is ready
+    async fn run_with_buffering_proxy(&self, options: &StdioRunOptions, finch_config: &FinchConfig, startup_timeout: Duration, tracer: Option<Arc<McpTracer>>) -> Result<()> {
+        let (mut cmd, container_name) = options.supervised_command();
+        log::debug!("Running finch command with MCP proxy: {:?}", cmd);
+        
+        let child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        
+        let buffer = Arc::new(MCPBuffer::new(finch_config.mcp.buffer_size, startup_timeout));
+        
+        let proxy = AsyncStdioProxy::new(buffer, child)?
+            .with_readiness_pattern(finch_config.mcp.readiness_pattern.clone())
+            .with_tracer(tracer);
+        shutdown::run_until_signal(proxy.start(), &[container_name]).await
     }
     
     /// Check if a container image exists
-    pub async fn image_exists(&self, image_name: &str) -> Result<bool> {
+    pub async fn image_exists(&self, image_name: &str) -> crate::error::Result<bool> {
+        vm::wait_until_ready()?;
         let output = Command::new("finch")
             .args(["image", "inspect", image_name])
             .output()
@@ -438,59 +780,251 @@ impl FinchClient {
         Ok(output.status.success())
     }
     
-    /// List finch-mcp containers and images
-    pub async fn list_resources(&self, show_all: bool) -> Result<()> {
-        status!("\n{} Finch-MCP Resources", style("📋").blue().bold());
-        status!("{}", "=".repeat(50));
+    /// Size of an image in bytes, or None if finch doesn't have it
+    pub async fn image_size(&self, image_name: &str) -> Option<u64> {
+        let output = Command::new("finch")
+            .args(["image", "inspect", "--format", "{{.Size}}", image_name])
+            .output()
+            .await
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    }
+    
+    /// Image ID (config digest) of an image, or None if finch doesn't have it
+    pub async fn image_id(&self, image_name: &str) -> Option<String> {
+        let output = Command::new("finch")
+            .args(["image", "inspect", "--format", "{{.Id}}", image_name])
+            .output()
+            .await
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string()).filter(|id| !id.is_empty())
+    }
+    
+    /// Every finch-mcp image reference (`mcp-*`), `:latest` tags included
+    pub async fn mcp_images(&self) -> crate::error::Result<Vec<String>> {
+        let output = Command::new("finch")
+            .args(["images", "--filter", "reference=mcp-*", "--format", "{{.Repository}}:{{.Tag}}"])
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("Failed to list images: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|image| image.starts_with("mcp-"))
+            .map(str::to_string)
+            .collect())
+    }
+    
+    /// Health reported by a container's `HEALTHCHECK` (`starting`, `healthy` or `unhealthy`),
+    /// if its image has one
+    pub async fn container_health(&self, container: &str) -> Option<String> {
+        let output = Command::new("finch")
+            .args(["inspect", "--format", "{{if .State.Health}}{{.State.Health.Status}}{{end}}", container])
+            .output()
+            .await
+            .ok()?;
+        let health = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !health.is_empty() && health != "<no value>").then_some(health)
+    }
+    
+    /// Export an image to a tar archive with `finch save`, e.g. for scanners that read archives
+    pub async fn save_image(&self, image_name: &str, archive: &Path) -> crate::error::Result<()> {
+        let output = Command::new("finch")
+            .arg("save")
+            .arg("-o")
+            .arg(archive)
+            .arg(image_name)
+            .output()
+            .await
+            .context("Failed to run finch save")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("Failed to export {}: {}", image_name, String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+        Ok(())
+    }
+    
+    /// Running containers started by finch-mcp, newest first
+    pub async fn running_mcp_containers(&self) -> crate::error::Result<Vec<McpContainer>> {
+        self.mcp_containers(false).await
+    }
+    
+    /// Containers started by finch-mcp, newest first; with `all`, stopped ones too
+    pub async fn mcp_containers(&self, all: bool) -> crate::error::Result<Vec<McpContainer>> {
+        let filter = format!("label={}", MANAGED_LABEL);
+        let mut args = vec!["ps", "--filter", &filter, "--format", "{{json .}}"];
+        if all {
+            args.insert(1, "-a");
+        }
+        let output = Command::new("finch")
+            .args(args)
+            .output()
+            .await?;
         
-        // List containers
-        status!("\n{} Containers:", style("🐳").cyan());
-        let container_args = if show_all {
-            vec!["ps", "-a", "--filter", "name=mcp-", "--format", "{{.Names}}\\t{{.Image}}\\t{{.Status}}\\t{{.CreatedAt}}"]
-        } else {
-            vec!["ps", "--filter", "name=mcp-", "--format", "{{.Names}}\\t{{.Image}}\\t{{.Status}}\\t{{.CreatedAt}}"]
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("Failed to list containers: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+        Ok(McpContainer::parse_ps_output(&String::from_utf8_lossy(&output.stdout)))
+    }
+    
+    /// A container's state (`running`, `exited`, ...), or None if there is no such container
+    pub async fn container_status(&self, container: &str) -> Option<String> {
+        let output = Command::new("finch")
+            .args(["inspect", "--format", "{{.State.Status}}", container])
+            .output()
+            .await
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+    
+    /// Run `command` (default `sh`) interactively: inside `container` if it is running,
+    /// otherwise in a new container of the server's image (`finch-mcp exec`)
+    pub async fn exec_in_container(&self, options: &StdioRunOptions, container: Option<&str>, command: &[String]) -> crate::error::Result<()> {
+        use std::io::IsTerminal;
+        let tty = std::io::stdin().is_terminal();
+        
+        let args = match container {
+            Some(container) => {
+                let mut args = vec!["exec".to_string(), "-i".to_string()];
+                if tty {
+                    args.push("-t".to_string());
+                }
+                args.push(container.to_string());
+                if command.is_empty() {
+                    args.push("sh".to_string());
+                }
+                args.extend(command.iter().cloned());
+                args
+            }
+            None => options.exec_args(command, tty),
         };
         
-        let container_output = Command::new("finch")
-            .args(&container_args)
-            .output()
+        debug!("Running: finch {}", args.join(" "));
+        let status = Command::new("finch")
+            .args(&args)
+            .status()
             .await?;
-            
-        if container_output.status.success() {
-            let output = String::from_utf8_lossy(&container_output.stdout);
-            if output.trim().is_empty() || !output.contains("mcp-") {
-                status!("  {}", style("No finch-mcp containers found").dim());
-            } else {
-                print!("{}", output);
-            }
-        } else {
-            status!("  {}", style("Error listing containers").red());
+        Ok(ContainerExit::check(status, Vec::new())?)
+    }
+    
+    /// Start a named server container in the background (`run --detach`)
+    #[tracing::instrument(name = "run", skip_all, fields(image = %options.image_name, detached = true), err)]
+    pub async fn run_detached(&self, options: &StdioRunOptions, name: &str) -> crate::error::Result<()> {
+        match self.container_status(name).await.as_deref() {
+            Some("running") => return Err(anyhow::anyhow!(
+                "A container named {} is already running; connect with `finch-mcp attach {}` or stop it with `finch-mcp stop {}`",
+                name, name, name
+            ).into()),
+            // Left over from an earlier detached run that exited
+            Some(_) => shutdown::remove_container(name).await,
+            None => {}
         }
         
-        // List images
-        status!("\n{} Images:", style("💿").green());
-        let image_output = Command::new("finch")
-            .args(["images", "--filter", "reference=mcp-*", "--format", "{{.Repository}}\\t{{.Tag}}\\t{{.Size}}\\t{{.CreatedAt}}"])
+        cache::record_run(&options.image_name);
+        let args = options.detached_args(name);
+        debug!("Running detached: finch {}", args.join(" "));
+        let output = Command::new("finch")
+            .args(&args)
             .output()
             .await?;
-            
-        if image_output.status.success() {
-            let output = String::from_utf8_lossy(&image_output.stdout);
-            if output.trim().is_empty() || !output.contains("mcp-") {
-                status!("  {}", style("No finch-mcp images found").dim());
-            } else {
-                print!("{}", output);
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("Failed to start {}: {}", name, String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+        Ok(())
+    }
+    
+    /// Connect this process's stdio to a detached server until either side closes
+    pub async fn attach_container(&self, name: &str) -> crate::error::Result<()> {
+        if self.container_status(name).await.as_deref() != Some("running") {
+            return Err(anyhow::anyhow!("No running container named {}; start it with `finch-mcp run --detach --name {} <TARGET>`", name, name).into());
+        }
+        
+        let status = Command::new("finch")
+            .args(["attach", name])
+            .status()
+            .await?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("finch attach exited with status: {}", status).into())
+        }
+    }
+    
+    /// Stop containers gracefully and remove them
+    pub async fn stop_containers(&self, names: &[String]) -> crate::error::Result<()> {
+        let mut failed = Vec::new();
+        for name in names {
+            if self.container_status(name).await.is_none() {
+                status!("{} No container named {}", style("ℹ️").blue(), name);
+                continue;
+            }
+            let stopped = Command::new("finch")
+                .args(["stop", name])
+                .stdout(Stdio::null())
+                .output()
+                .await?;
+            if !stopped.status.success() {
+                failed.push(format!("{}: {}", name, String::from_utf8_lossy(&stopped.stderr).trim()));
+                continue;
             }
+            shutdown::remove_container(name).await;
+            status!("{} Stopped {}", style("✓").green(), name);
+        }
+        
+        if failed.is_empty() {
+            Ok(())
         } else {
-            status!("  {}", style("Error listing images").red());
+            Err(anyhow::anyhow!("Failed to stop {}", failed.join("; ")).into())
+        }
+    }
+    
+    /// Print finch-mcp containers with their status and health (`finch-mcp ps`)
+    pub async fn print_mcp_containers(&self, all: bool) -> crate::error::Result<()> {
+        let containers = self.mcp_containers(all).await?;
+        if containers.is_empty() {
+            status!("{}", style(if all { "No finch-mcp containers" } else { "No running finch-mcp containers" }).dim());
+            return Ok(());
         }
         
-        status!();
+        let width = containers.iter().map(|container| container.name.len()).max().unwrap_or_default().max(4);
+        eprintln!("{:<width$}  {:<40}  STATUS", "NAME", "IMAGE", width = width);
+        for container in &containers {
+            let health = match self.container_health(&container.name).await {
+                Some(health) => format!(" {}", health_label(&health)),
+                None => String::new(),
+            };
+            eprintln!("{:<width$}  {:<40}  {}{}", container.name, container.image, container.status, health, width = width);
+        }
         Ok(())
     }
     
-    /// Cleanup finch-mcp containers and images
-    pub async fn cleanup_resources(&self, cleanup_all: bool, cleanup_containers: bool, cleanup_images: bool, force: bool) -> Result<()> {
+    /// Stream a container's output until it exits or the user presses Ctrl+C
+    pub async fn follow_container_logs(&self, container: &str) -> crate::error::Result<()> {
+        let status = Command::new("finch")
+            .args(["logs", "-f", container])
+            .status()
+            .await?;
+        
+        if status.success() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("finch logs exited with status: {}", status).into())
+        }
+    }
+    
+    /// Cleanup finch-mcp containers and images. With a retention policy or `dangling`,
+    /// only the images they select are removed (along with their cache entries)
+    pub async fn cleanup_resources(&self, cleanup_all: bool, cleanup_containers: bool, cleanup_images: bool, policy: &RetentionPolicy, dangling: bool, force: bool) -> crate::error::Result<()> {
         status!("\n{} Cleaning up Finch-MCP resources...", style("🧹").yellow().bold());
         
         let mut cleaned_something = false;
@@ -517,9 +1051,9 @@ impl FinchClient {
                         for name in &container_names {
                             status!("    • {}", name);
                         }
-                        print!("  Continue? [y/N]: ");
+                        eprint!("  Continue? [y/N]: ");
                         use std::io::{self, Write};
-                        io::stdout().flush().unwrap();
+                        io::stderr().flush().unwrap();
                         
                         let mut input = String::new();
                         io::stdin().read_line(&mut input).unwrap();
@@ -548,54 +1082,59 @@ impl FinchClient {
         }
         
         // Cleanup images
-        if cleanup_all || cleanup_images {
+        let pruning = !policy.is_empty() || dangling;
+        if cleanup_all || cleanup_images || pruning {
             status!("\n{} Removing images...", style("💿").green());
             
-            // Get list of finch-mcp images
-            let image_list = Command::new("finch")
-                .args(["images", "--filter", "reference=mcp-*", "--format", "{{.Repository}}:{{.Tag}}"])
-                .output()
-                .await?;
-                
-            if image_list.status.success() {
-                let images = String::from_utf8_lossy(&image_list.stdout);
-                let image_names: Vec<&str> = images.lines().filter(|l| !l.trim().is_empty()).collect();
-                
-                if image_names.is_empty() {
-                    status!("  {}", style("No finch-mcp images to remove").dim());
-                } else {
-                    if !force {
-                        status!("  Found {} images to remove:", image_names.len());
-                        for name in &image_names {
-                            status!("    • {}", name);
-                        }
-                        print!("  Continue? [y/N]: ");
-                        use std::io::{self, Write};
-                        io::stdout().flush().unwrap();
-                        
-                        let mut input = String::new();
-                        io::stdin().read_line(&mut input).unwrap();
-                        
-                        if !input.trim().to_lowercase().starts_with('y') {
-                            status!("  Skipped image cleanup");
-                            return Ok(());
-                        }
+            let mut cache_manager = CacheManager::new()?;
+            let (image_names, pruned_entries) = if pruning {
+                self.images_to_prune(&cache_manager, policy, dangling).await?
+            } else {
+                (self.list_mcp_images().await?, Vec::new())
+            };
+            
+            if image_names.is_empty() {
+                status!("  {}", style("No finch-mcp images to remove").dim());
+            } else {
+                if !force {
+                    status!("  Found {} images to remove:", image_names.len());
+                    for name in &image_names {
+                        status!("    • {}", name);
                     }
+                    eprint!("  Continue? [y/N]: ");
+                    use std::io::{self, Write};
+                    io::stderr().flush().unwrap();
                     
-                    for image in &image_names {
-                        let remove_result = Command::new("finch")
-                            .args(["rmi", "-f", image])
-                            .output()
-                            .await?;
-                            
-                        if remove_result.status.success() {
-                            status!("  {} Removed image: {}", style("✓").green(), image);
-                            cleaned_something = true;
-                        } else {
-                            status!("  {} Failed to remove image: {}", style("✗").red(), image);
-                        }
+                    let mut input = String::new();
+                    io::stdin().read_line(&mut input).unwrap();
+                    
+                    if !input.trim().to_lowercase().starts_with('y') {
+                        status!("  Skipped image cleanup");
+                        return Ok(());
+                    }
+                }
+                
+                let mut removed = Vec::new();
+                for image in &image_names {
+                    let remove_result = Command::new("finch")
+                        .args(["rmi", "-f", image])
+                        .output()
+                        .await?;
+                        
+                    if remove_result.status.success() {
+                        status!("  {} Removed image: {}", style("✓").green(), image);
+                        removed.push(image.clone());
+                        cleaned_something = true;
+                    } else {
+                        status!("  {} Failed to remove image: {}", style("✗").red(), image);
                     }
                 }
+                
+                // Forget the pruned images, so the next run rebuilds instead of missing them
+                let forgotten: Vec<String> = pruned_entries.into_iter()
+                    .filter(|image| removed.contains(image))
+                    .collect();
+                cache_manager.remove_images(&forgotten)?;
             }
         }
         
@@ -607,12 +1146,195 @@ impl FinchClient {
         
         Ok(())
     }
+    
+    /// Every tagged finch-mcp image
+    async fn list_mcp_images(&self) -> Result<Vec<String>> {
+        let output = Command::new("finch")
+            .args(["images", "--filter", "reference=mcp-*", "--format", "{{.Repository}}:{{.Tag}}"])
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Ok(Vec::new());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+    
+    /// Image references a retention policy prunes, plus the IDs of dangling images
+    /// built by finch-mcp; also returns the cached image names among them
+    async fn images_to_prune(&self, cache_manager: &CacheManager, policy: &RetentionPolicy, dangling: bool) -> Result<(Vec<String>, Vec<String>)> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let expired = cache_manager.expired_entries(policy, now);
+        let mut images = cache_manager.image_refs_to_remove(&expired);
+        let cached: Vec<String> = expired.into_iter().map(|entry| entry.image_name).collect();
+        
+        if dangling {
+            // Untagged images can't be matched by name, but every build carries the Dockerfile label
+            let label_filter = format!("label={}", DOCKERFILE_LABEL);
+            let output = Command::new("finch")
+                .args(["images", "--filter", "dangling=true", "--filter", &label_filter, "--quiet"])
+                .output()
+                .await?;
+            if output.status.success() {
+                for id in String::from_utf8_lossy(&output.stdout).lines().map(str::trim).filter(|id| !id.is_empty()) {
+                    if !images.iter().any(|image| image == id) {
+                        images.push(id.to_string());
+                    }
+                }
+            }
+        }
+        Ok((images, cached))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     
+    #[cfg(unix)]
+    #[test]
+    fn test_container_exit_propagates_code_and_stderr() {
+        use std::os::unix::process::ExitStatusExt;
+        
+        assert!(ContainerExit::check(std::process::ExitStatus::from_raw(0), Vec::new()).is_ok());
+        
+        // Exit code 3 (wait status encodes it in the high byte)
+        let err = ContainerExit::check(std::process::ExitStatus::from_raw(3 << 8), vec!["Error: no API key".to_string()]).unwrap_err();
+        let exit = err.downcast_ref::<ContainerExit>().unwrap();
+        assert_eq!(exit.code, 3);
+        assert!(exit.to_string().contains("Error: no API key"));
+        
+        // Killed by SIGKILL
+        assert_eq!(ContainerExit::new(std::process::ExitStatus::from_raw(9), Vec::new()).code, 137);
+    }
+    
+    #[test]
+    fn test_network_mode_parsing() {
+        assert_eq!("bridge".parse::<NetworkMode>().unwrap(), NetworkMode::Bridge);
+        assert_eq!("host".parse::<NetworkMode>().unwrap(), NetworkMode::Host);
+        assert_eq!("none".parse::<NetworkMode>().unwrap(), NetworkMode::None);
+        assert_eq!("mcp-net".parse::<NetworkMode>().unwrap(), NetworkMode::Named("mcp-net".to_string()));
+        assert!("".parse::<NetworkMode>().is_err());
+        
+        assert_eq!(NetworkMode::Bridge.as_finch_arg(), None);
+        assert_eq!(NetworkMode::None.as_finch_arg(), Some("none"));
+        assert_eq!(NetworkMode::Named("mcp-net".to_string()).as_finch_arg(), Some("mcp-net"));
+    }
+
+    #[test]
+    fn test_finch_run_args() {
+        let options = StdioRunOptions {
+            image_name: "mcp-server:latest".to_string(),
+            env_vars: vec!["API_KEY=test".to_string()],
+            volumes: vec![],
+            network: NetworkMode::None,
+            publish: vec!["8080:8080".to_string(), "127.0.0.1:9090:90/udp".to_string()],
+            args: vec!["--port".to_string(), "8080".to_string(), "has space".to_string()],
+            user_map: UserMap::Auto,
+            health_check: false,
+            startup_timeout: None,
+            trace_mcp: None,
+            forward_proxy: false,
+            no_forward_timezone: false,
+            restart: RestartPolicy::No,
+            events: Events::default(),
+        };
+
+        let args = options.finch_run_args();
+        assert_eq!(&args[..3], ["run", "--rm", "-i"]);
+        
+        let (command, name) = options.supervised_command();
+        let supervised: Vec<_> = command.as_std().get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+        assert_eq!(&supervised[3..5], ["--name", name.as_str()]);
+        assert_eq!(supervised.len(), args.len() + 2);
+        assert!(args.windows(2).any(|w| w == ["-e", "API_KEY=test"]));
+        assert!(args.windows(2).any(|w| w == ["-p", "8080:8080"]));
+        assert!(args.windows(2).any(|w| w == ["-p", "127.0.0.1:9090:90/udp"]));
+        assert!(args.windows(2).any(|w| w == ["--network", "none"]));
+        assert!(args.windows(2).any(|w| w == ["--label", MANAGED_LABEL]));
+        assert!(args.windows(2).any(|w| w == ["--label", "finch-mcp.image=mcp-server:latest"]));
+        assert_eq!(&args[args.len() - 4..], ["mcp-server:latest", "--port", "8080", "has space"]);
+    }
+    
+    #[test]
+    fn test_exec_args() {
+        let options = StdioRunOptions {
+            image_name: "mcp-local-demo:abc123".to_string(),
+            env_vars: vec!["API_KEY=test".to_string()],
+            volumes: vec!["/data:/data".to_string()],
+            network: NetworkMode::None,
+            publish: vec![],
+            args: vec!["--root".to_string(), "/data".to_string()],
+            user_map: UserMap::Auto,
+            health_check: false,
+            startup_timeout: None,
+            trace_mcp: None,
+            forward_proxy: false,
+            no_forward_timezone: false,
+            restart: RestartPolicy::No,
+            events: Events::default(),
+        };
+        
+        let shell = options.exec_args(&[], true);
+        assert_eq!(&shell[..4], ["run", "--rm", "-i", "-t"]);
+        assert!(shell.windows(2).any(|w| w == ["-e", "API_KEY=test"]));
+        assert!(shell.windows(2).any(|w| w == ["-v", "/data:/data"]));
+        assert_eq!(&shell[shell.len() - 3..], ["--entrypoint", "sh", "mcp-local-demo:abc123"]);
+        
+        let command = options.exec_args(&["ls".to_string(), "/app".to_string()], false);
+        assert!(!command.contains(&"-t".to_string()));
+        assert_eq!(&command[command.len() - 4..], ["--entrypoint", "ls", "mcp-local-demo:abc123", "/app"]);
+    }
+    
+    #[test]
+    fn test_detached_run() {
+        let options = StdioRunOptions {
+            image_name: "mcp-cmd-uvx-time:abc123".to_string(),
+            env_vars: vec![],
+            volumes: vec![],
+            network: NetworkMode::Bridge,
+            publish: vec![],
+            args: vec![],
+            user_map: UserMap::Auto,
+            health_check: false,
+            startup_timeout: None,
+            trace_mcp: None,
+            forward_proxy: false,
+            no_forward_timezone: false,
+            restart: RestartPolicy::No,
+            events: Events::default(),
+        };
+        let args = options.detached_args("my-time-server");
+        assert_eq!(&args[..5], ["run", "-d", "--name", "my-time-server", "-i"]);
+        assert!(!args.contains(&"--rm".to_string()));
+        
+        assert_eq!(detached_name("mcp-cmd-uvx-time:abc123"), "mcp-cmd-uvx-time");
+        assert_eq!(detached_name("ghcr.io/example/github-mcp:1.0"), "mcp-github-mcp");
+    }
+    
+    #[test]
+    fn test_select_followed_container() {
+        let output = concat!(
+            r#"{"ID":"a1b2c3","Names":"mcp-session-111","Image":"docker.io/library/mcp-local-demo:latest","Status":"Up 2 minutes","Labels":"finch-mcp.image=mcp-local-demo,finch-mcp.managed=true"}"#, "\n",
+            r#"{"ID":"d4e5f6","Names":"mcp-session-222","Image":"mcp-cmd-uvx-time:latest","Labels":"finch-mcp.managed=true"}"#, "\n",
+        );
+        let containers = McpContainer::parse_ps_output(output);
+        assert_eq!(containers.len(), 2);
+        assert_eq!(containers[0].image, "mcp-local-demo");
+        assert_eq!(containers[0].status, "Up 2 minutes");
+        assert_eq!(containers[1].image, "mcp-cmd-uvx-time:latest");
+        
+        let pick = |target, images: &[String]| McpContainer::select(&containers, target, images).map(|c| c.name.clone());
+        assert_eq!(pick(None, &[]).unwrap(), "mcp-session-111");
+        assert_eq!(pick(Some("d4e5"), &[]).unwrap(), "mcp-session-222");
+        assert_eq!(pick(Some("mcp-cmd-uvx-time"), &[]).unwrap(), "mcp-session-222");
+        assert_eq!(pick(Some("./demo"), &["mcp-local-demo".to_string()]).unwrap(), "mcp-session-111");
+        assert!(pick(Some("./other"), &[]).is_err());
+        assert!(McpContainer::select(&[], None, &[]).is_err());
+    }
+
     #[tokio::test]
     async fn test_is_finch_available() {
         // This is a basic test - it will only pass if finch is actually installed,
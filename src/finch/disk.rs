@@ -0,0 +1,172 @@
+//! Free disk space checks before builds
+//!
+//! A build that fills the disk holding images dies halfway with an opaque I/O error.
+//! Before building, finch-mcp asks how much space is left (on Linux, where Finch runs
+//! natively, on the filesystem of its data directory; elsewhere with `df` in a throwaway
+//! container, whose root is on the VM's disk) and refuses to start with less than
+//! [`MIN_FREE`], saying how to make room. The VM's measurement is reused for a while
+//! when it had room to spare, so most builds don't start a container for it. When the
+//! space can't be found out, the build goes ahead with a warning.
+
+#[cfg(not(target_os = "linux"))]
+use std::fs;
+use std::process::{Command, Stdio};
+#[cfg(not(target_os = "linux"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use log::warn;
+#[cfg(not(target_os = "linux"))]
+use serde::{Deserialize, Serialize};
+
+use crate::logging::LogSink;
+use crate::utils::units::format_size;
+
+/// Free space a build needs to get started: base image layers, dependency installs and
+/// the build cache quickly add up to this
+pub const MIN_FREE: u64 = 2_000_000_000;
+
+/// Where Finch keeps images and build cache on Linux
+#[cfg(target_os = "linux")]
+const DATA_DIRS: [&str; 2] = ["/var/lib/finch", "/var/lib/containerd"];
+
+/// Image the VM's free space is read with; tiny, and shipped with `df`
+#[cfg(not(target_os = "linux"))]
+const HELPER_IMAGE: &str = "public.ecr.aws/docker/library/busybox:stable";
+
+/// The VM's last measured free space, in the cache directory
+#[cfg(not(target_os = "linux"))]
+const CHECK_FILE: &str = "vm-disk-check.json";
+
+/// How long a measurement of the VM's free space is reused; each one runs a container,
+/// and the first pulls [`HELPER_IMAGE`]
+#[cfg(not(target_os = "linux"))]
+const CHECK_EVERY_SECS: u64 = 10 * 60;
+
+#[cfg(not(target_os = "linux"))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiskCheck {
+    checked_at: u64,
+    free: u64,
+}
+
+/// Refuse to build when the disk holding images has less than [`MIN_FREE`] left.
+/// The free space is recorded in the build log
+pub fn preflight(log: &LogSink) -> Result<()> {
+    let free = match free_bytes() {
+        Ok(Some(free)) => free,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            let message = format!("Skipping the disk space check: {:#}", e);
+            warn!("{}", message);
+            log.write_line(&message);
+            return Ok(());
+        }
+    };
+    log.write_line(&format!("Free disk space for images: {}", format_size(free)));
+    ensure_free(free, MIN_FREE)
+}
+
+fn ensure_free(free: u64, needed: u64) -> Result<()> {
+    if free < needed {
+        anyhow::bail!(
+            "Only {} of disk space is left for images, and a build needs at least {}. \
+             Make room with `finch-mcp gc` or `finch-mcp cleanup --all`, or `finch system prune -a` \
+             for images finch-mcp didn't build; `finch system df` shows what is using it",
+            format_size(free), format_size(needed)
+        );
+    }
+    Ok(())
+}
+
+/// Free bytes on the filesystem of Finch's data directory; none if Finch has none yet
+#[cfg(target_os = "linux")]
+fn free_bytes() -> Result<Option<u64>> {
+    let Some(dir) = DATA_DIRS.iter().find(|dir| std::path::Path::new(dir).exists()) else {
+        return Ok(None);
+    };
+    df(Command::new("df").args(["-Pk", dir]))
+}
+
+/// Free bytes on the VM's disk: the last measurement while it can be reused, else `df`
+/// in a throwaway container
+#[cfg(not(target_os = "linux"))]
+fn free_bytes() -> Result<Option<u64>> {
+    let dir = crate::paths::cache_dir()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let last: Option<DiskCheck> = fs::read_to_string(dir.join(CHECK_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    if let Some(free) = reusable(last, now) {
+        return Ok(Some(free));
+    }
+
+    let free = df(Command::new("finch").args(["run", "--rm", "--network", "none", "--entrypoint", "df", HELPER_IMAGE, "-Pk", "/"]))?;
+    if let Some(free) = free {
+        let saved = (|| -> Result<()> {
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join(CHECK_FILE), serde_json::to_string(&DiskCheck { checked_at: now, free })?)?;
+            Ok(())
+        })();
+        if let Err(e) = saved {
+            warn!("Failed to remember the VM's free disk space: {}", e);
+        }
+    }
+    Ok(free)
+}
+
+/// The free space of `last` if it is recent and had room to spare; a build is only
+/// ever refused on a fresh measurement
+#[cfg(not(target_os = "linux"))]
+fn reusable(last: Option<DiskCheck>, now: u64) -> Option<u64> {
+    last.filter(|last| now.saturating_sub(last.checked_at) < CHECK_EVERY_SECS && last.free >= MIN_FREE)
+        .map(|last| last.free)
+}
+
+fn df(command: &mut Command) -> Result<Option<u64>> {
+    let output = command.stdin(Stdio::null()).output().context("Failed to run df")?;
+    if !output.status.success() {
+        anyhow::bail!("df failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_df(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Available bytes from POSIX `df -Pk` output
+fn parse_df(output: &str) -> Option<u64> {
+    let line = output.lines().nth(1)?;
+    let available: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df() {
+        let output = "Filesystem           1024-blocks    Used Available Capacity Mounted on\n\
+                      overlay               102687672 98342108   4345564  96% /\n";
+        assert_eq!(parse_df(output), Some(4_449_857_536));
+        assert_eq!(parse_df("Filesystem 1024-blocks Used Available Capacity Mounted on\n"), None);
+        assert_eq!(parse_df(""), None);
+    }
+
+    #[test]
+    fn test_ensure_free() {
+        assert!(ensure_free(MIN_FREE, MIN_FREE).is_ok());
+        let error = ensure_free(750_000_000, MIN_FREE).unwrap_err().to_string();
+        assert!(error.starts_with("Only 750.0 MB of disk space is left for images, and a build needs at least 2.0 GB"), "{}", error);
+        assert!(error.contains("finch-mcp gc"));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[test]
+    fn test_reusable() {
+        let roomy = DiskCheck { checked_at: 1_000, free: MIN_FREE * 2 };
+        assert_eq!(reusable(Some(roomy), 1_000 + CHECK_EVERY_SECS - 1), Some(MIN_FREE * 2));
+        assert_eq!(reusable(Some(roomy), 1_000 + CHECK_EVERY_SECS), None);
+        assert_eq!(reusable(Some(DiskCheck { checked_at: 1_000, free: MIN_FREE - 1 }), 1_000), None);
+        assert_eq!(reusable(None, 1_000), None);
+    }
+}
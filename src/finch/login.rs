@@ -0,0 +1,157 @@
+//! Registry logins for image targets (`finch-mcp login ghcr.io`)
+//!
+//! `finch login` keeps the credentials; finch-mcp only remembers, in `registries.json`
+//! in the data directory, which registries were logged in to and when. A pull refused
+//! with 401 or 403 then says what to do: log in to that registry, or log in again when
+//! finch-mcp already has and the credentials may have expired or lack access.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::utils::offline;
+
+const REGISTRIES_FILE: &str = "registries.json";
+
+/// Docker Hub, the registry of references without a registry host
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// Output of a pull the registry refused for lack of (valid) credentials
+const AUTH_FAILURES: &[&str] = &[
+    "401 unauthorized",
+    "403 forbidden",
+    "unauthorized: ",
+    "denied: ",
+    "authentication required",
+    "insufficient_scope",
+    "pull access denied",
+];
+
+/// A registry logged in to through finch-mcp
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Login {
+    pub username: Option<String>,
+    pub logged_in_at: u64,
+}
+
+/// Every registry logged in to, by host
+pub fn load() -> Result<BTreeMap<String, Login>> {
+    let path = crate::paths::data_dir()?.join(REGISTRIES_FILE);
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).with_context(|| format!("Invalid registry file {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn save(registries: &BTreeMap<String, Login>) -> Result<()> {
+    let dir = crate::paths::data_dir()?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(REGISTRIES_FILE), serde_json::to_string_pretty(registries)?).context("Failed to save registry logins")
+}
+
+/// The registry host as finch-mcp records it: no scheme or path, lowercase, and
+/// Docker Hub's aliases as `docker.io`
+pub fn normalize_registry(registry: &str) -> String {
+    let host = registry.trim().trim_start_matches("https://").trim_start_matches("http://");
+    let host = host.split('/').next().unwrap_or_default().to_lowercase();
+    match host.as_str() {
+        "" | "index.docker.io" | "registry-1.docker.io" | "registry.hub.docker.com" => DEFAULT_REGISTRY.to_string(),
+        _ => host,
+    }
+}
+
+/// The registry an image reference pulls from
+pub fn registry_of(image: &str) -> String {
+    match image.split_once('/') {
+        Some((first, _)) if first.contains(['.', ':']) || first == "localhost" => normalize_registry(first),
+        _ => DEFAULT_REGISTRY.to_string(),
+    }
+}
+
+/// Log in to `registry` with `finch login`, which prompts for what isn't given, and
+/// remember it
+pub async fn login(registry: &str, username: Option<&str>, password_stdin: bool, offline: bool) -> Result<String> {
+    let registry = normalize_registry(registry);
+    offline::ensure_online(offline, &format!("Logging in to {}", registry))?;
+
+    let mut command = Command::new("finch");
+    command.args(["login", &registry]);
+    if let Some(username) = username {
+        command.args(["--username", username]);
+    }
+    if password_stdin {
+        command.arg("--password-stdin");
+    }
+    let status = command
+        .stdin(Stdio::inherit())
+        .stdout(std::io::stderr())
+        .stderr(Stdio::inherit())
+        .status()
+        .await
+        .context("Failed to run finch login")?;
+    if !status.success() {
+        anyhow::bail!("finch login {} failed", registry);
+    }
+
+    let mut registries = load()?;
+    let logged_in_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    registries.insert(registry.clone(), Login { username: username.map(str::to_string), logged_in_at });
+    save(&registries)?;
+    Ok(registry)
+}
+
+/// What to do about a pull of `image` refused with `output`, if the registry wanted
+/// credentials; `login` is finch-mcp's record of logging in to its registry
+pub fn auth_hint(image: &str, output: &str, login: Option<&Login>) -> Option<String> {
+    let output = output.to_lowercase();
+    if !AUTH_FAILURES.iter().any(|pattern| output.contains(pattern)) {
+        return None;
+    }
+    let registry = registry_of(image);
+    Some(match login {
+        None => format!("not logged in to {}, run `finch-mcp login {}`", registry, registry),
+        Some(_) => format!(
+            "{} refused the credentials from `finch-mcp login`; they may have expired or lack access to {}. \
+             Run `finch-mcp login {}` again",
+            registry, image, registry
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_of() {
+        assert_eq!(registry_of("ghcr.io/acme/notes:1.0"), "ghcr.io");
+        assert_eq!(registry_of("localhost:5000/notes"), "localhost:5000");
+        assert_eq!(registry_of("localhost/notes"), "localhost");
+        assert_eq!(registry_of("acme/notes"), "docker.io");
+        assert_eq!(registry_of("redis:7"), "docker.io");
+        assert_eq!(registry_of("index.docker.io/library/redis"), "docker.io");
+        assert_eq!(normalize_registry("https://GHCR.io/"), "ghcr.io");
+        assert_eq!(normalize_registry("registry-1.docker.io"), "docker.io");
+    }
+
+    #[test]
+    fn test_auth_hint() {
+        let refused = "FATA[0001] failed to resolve reference \"ghcr.io/acme/notes:1.0\": unexpected status from HEAD request: 401 Unauthorized";
+        assert_eq!(
+            auth_hint("ghcr.io/acme/notes:1.0", refused, None).as_deref(),
+            Some("not logged in to ghcr.io, run `finch-mcp login ghcr.io`")
+        );
+        let login = Login { username: Some("octocat".to_string()), logged_in_at: 1 };
+        let hint = auth_hint("ghcr.io/acme/notes:1.0", refused, Some(&login)).unwrap();
+        assert!(hint.starts_with("ghcr.io refused the credentials"), "{}", hint);
+        assert!(auth_hint("redis:7", "pull access denied, repository does not exist or may require authorization", None).unwrap().contains("finch-mcp login docker.io"));
+        assert_eq!(auth_hint("redis:7", "toomanyrequests: You have reached your pull rate limit", None), None);
+    }
+}
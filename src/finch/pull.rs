@@ -0,0 +1,205 @@
+//! Pull policy for direct image targets (`--pull always|missing|never`)
+//!
+//! An image run directly is pulled before the server starts rather than by `finch run`
+//! in the middle of the client's handshake. `missing` (the default) pulls it only when
+//! finch doesn't have it, `always` also refreshes a stale local tag, and `never` runs what
+//! is local or fails. Pull progress goes to stderr, so it is safe while stdout carries an
+//! MCP session. A pull that fails with a transient error (network, registry 5xx/429) is
+//! retried with the same backoff as builds.
+
+use std::collections::VecDeque;
+use std::process::Stdio;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+use crate::diagnostics::{diagnose, format_hints};
+use crate::error::FinchMcpError;
+use crate::finch::client::FinchClient;
+use crate::finch::login::{self, Login};
+use crate::output::{self, OutputMode};
+use crate::status;
+use crate::utils::offline;
+use crate::utils::progress::{transient_failure, BuildPolicy};
+
+/// Lines of `finch pull` output kept for the error when it fails
+const ERROR_TAIL_LINES: usize = 10;
+
+/// When to pull a direct image target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PullPolicy {
+    /// Pull before every run, so a moved tag runs its newest image
+    Always,
+    /// Pull only when finch doesn't have the image
+    #[default]
+    Missing,
+    /// Never pull; fail when finch doesn't have the image
+    Never,
+}
+
+/// Whether `policy` pulls an image finch has (`exists`) or hasn't
+fn should_pull(policy: PullPolicy, exists: bool) -> bool {
+    match policy {
+        PullPolicy::Always => true,
+        PullPolicy::Missing => !exists,
+        PullPolicy::Never => false,
+    }
+}
+
+/// Make sure finch has `image` as `policy` asks, pulling it if need be; `offline` fails
+/// instead of pulling. `retry` says how often a transient pull failure is retried
+pub async fn ensure_image(finch_client: &FinchClient, image: &str, policy: PullPolicy, offline: bool, retry: &BuildPolicy) -> Result<()> {
+    let exists = finch_client.image_exists(image).await?;
+    if !should_pull(policy, exists) {
+        if !exists {
+            return Err(FinchMcpError::ImageNotFound { image: image.to_string() }.into());
+        }
+        return Ok(());
+    }
+    offline::ensure_online(offline, &format!("Pulling {}", image))?;
+    pull(image, retry).await
+}
+
+/// `finch pull`, with its output on stderr, retrying transient failures as `policy` says
+pub async fn pull(image: &str, policy: &BuildPolicy) -> Result<()> {
+    pull_with("finch", image, policy).await
+}
+
+async fn pull_with(program: &str, image: &str, policy: &BuildPolicy) -> Result<()> {
+    status!("⬇️  Pulling {}", image);
+    let mut retry = 0;
+    loop {
+        let (success, output) = pull_once(program, image).await?;
+        if success {
+            return Ok(());
+        }
+        if retry < policy.retries {
+            if let Some(reason) = transient_failure(&output) {
+                retry += 1;
+                let delay = policy.backoff(retry);
+                let message = format!("Pull of {} failed with a transient error ({}); retrying in {}s (retry {}/{})",
+                    image, reason, delay.as_secs(), retry, policy.retries);
+                log::warn!("{}", message);
+                status!("🔁 {}", message);
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+        }
+        let login = login::load().ok().and_then(|mut registries| registries.remove(&login::registry_of(image)));
+        return Err(pull_error(image, &output, login.as_ref()));
+    }
+}
+
+/// One pull attempt, returning whether it succeeded and the tail of its output
+async fn pull_once(program: &str, image: &str) -> Result<(bool, String)> {
+    let silent = output::mode() == OutputMode::Silent;
+    let mut child = Command::new(program)
+        .args(["pull", image])
+        .stdin(Stdio::null())
+        .stdout(if silent { Stdio::null() } else { std::io::stderr().into() })
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run finch pull")?;
+
+    let mut tail = VecDeque::with_capacity(ERROR_TAIL_LINES);
+    if let Some(stderr) = child.stderr.take() {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Some(line) = lines.next_line().await.context("Failed to read finch pull output")? {
+            if !silent {
+                output::print_labelled(output::target_label().as_deref(), &line);
+            }
+            if tail.len() == ERROR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+    }
+
+    let exit = child.wait().await.context("Failed to wait for finch pull")?;
+    Ok((exit.success(), Vec::from(tail).join("\n")))
+}
+
+/// The error for a failed pull of `image`, with hints from its output. A registry
+/// refusing access gets a login hint instead; `login` is finch-mcp's record of logging in to it
+fn pull_error(image: &str, output: &str, login: Option<&Login>) -> anyhow::Error {
+    let reason = output.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("finch pull failed").trim();
+    match login::auth_hint(image, output, login) {
+        Some(hint) => anyhow::anyhow!("Failed to pull {}: {}\n{}", image, hint, reason),
+        None => anyhow::anyhow!("Failed to pull {}: {}{}", image, reason, format_hints(&diagnose(output))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_pull() {
+        assert!(should_pull(PullPolicy::Always, true));
+        assert!(should_pull(PullPolicy::Always, false));
+        assert!(!should_pull(PullPolicy::Missing, true));
+        assert!(should_pull(PullPolicy::Missing, false));
+        assert!(!should_pull(PullPolicy::Never, true));
+        assert!(!should_pull(PullPolicy::Never, false));
+        assert_eq!(PullPolicy::default(), PullPolicy::Missing);
+    }
+
+    #[test]
+    fn test_pull_error() {
+        let output = "docker.io/library/redis:7: resolving\n\
+                      FATA[0002] failed to resolve reference: toomanyrequests: You have reached your pull rate limit\n";
+        let error = pull_error("redis:7", output, None).to_string();
+        assert!(error.starts_with("Failed to pull redis:7: FATA[0002] failed to resolve reference: toomanyrequests"), "{}", error);
+        assert!(error.contains("\nHint: The registry is rate limiting pulls"), "{}", error);
+        assert_eq!(pull_error("redis:7", "", None).to_string(), "Failed to pull redis:7: finch pull failed");
+
+        let output = "FATA[0001] failed to resolve reference \"ghcr.io/acme/notes:1.0\": unexpected status from HEAD request: 403 Forbidden";
+        let error = pull_error("ghcr.io/acme/notes:1.0", output, None).to_string();
+        assert!(error.starts_with("Failed to pull ghcr.io/acme/notes:1.0: not logged in to ghcr.io, run `finch-mcp login ghcr.io`\nFATA"), "{}", error);
+    }
+
+    /// A stand-in for finch that logs each call to `calls` and fails with `first` the first time
+    /// and `rest` after that (an empty `rest` succeeds)
+    #[cfg(unix)]
+    fn fake_finch(dir: &std::path::Path, first: &str, rest: &str) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let calls = dir.join("calls");
+        let script = dir.join("finch");
+        let fail = |message: &str| if message.is_empty() { "exit 0".to_string() } else { format!("echo '{}' >&2; exit 1", message) };
+        std::fs::write(&script, format!(
+            "#!/bin/sh\nif [ -e {calls} ]; then echo x >> {calls}; {rest}; else echo x > {calls}; {first}; fi\n",
+            calls = calls.display(), first = fail(first), rest = fail(rest),
+        )).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pull_retries_transient_failures() {
+        let policy = BuildPolicy { timeout: None, retries: 2, retry_backoff: std::time::Duration::from_millis(10) };
+        let calls = |dir: &tempfile::TempDir| std::fs::read_to_string(dir.path().join("calls")).unwrap().lines().count();
+
+        let dir = tempfile::tempdir().unwrap();
+        let finch = fake_finch(dir.path(), "FATA[0001] failed to fetch: 503 Service Unavailable", "");
+        pull_with(finch.to_str().unwrap(), "redis:7", &policy).await.unwrap();
+        assert_eq!(calls(&dir), 2);
+
+        // Refused or missing images fail on the first try
+        for error in ["unexpected status from HEAD request: 401 Unauthorized", "failed to resolve reference: 404 Not Found"] {
+            let dir = tempfile::tempdir().unwrap();
+            let finch = fake_finch(dir.path(), error, error);
+            assert!(pull_with(finch.to_str().unwrap(), "ghcr.io/acme/notes:1.0", &policy).await.is_err());
+            assert_eq!(calls(&dir), 1);
+        }
+
+        // Transient failures give up after the configured retries
+        let dir = tempfile::tempdir().unwrap();
+        let finch = fake_finch(dir.path(), "connection reset by peer", "connection reset by peer");
+        assert!(pull_with(finch.to_str().unwrap(), "redis:7", &policy).await.is_err());
+        assert_eq!(calls(&dir), 3);
+    }
+}
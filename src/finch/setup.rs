@@ -0,0 +1,286 @@
+//! `finch-mcp setup finch`: install Finch when it's missing
+//!
+//! On macOS Homebrew is used when available. Otherwise the installer for this
+//! platform is downloaded from the latest GitHub release of runfinch/finch, its
+//! SHA-256 checked against the checksum published with the release, and run.
+//! Finally the Finch VM is initialized (Linux runs containers natively).
+
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::process::Stdio;
+
+use anyhow::{Context, Result};
+use log::debug;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
+
+use crate::finch::client::FinchClient;
+use crate::status;
+use crate::utils::offline;
+
+/// Latest Finch release, as reported by the GitHub API
+const RELEASES_URL: &str = "https://api.github.com/repos/runfinch/finch/releases/latest";
+
+/// Manual installation instructions, for platforms without a release installer
+const INSTALL_DOCS: &str = "https://runfinch.com/docs/getting-started/installation/";
+
+/// A Finch release on GitHub
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// One downloadable file of a release
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    /// Checksum computed by GitHub, e.g. `sha256:3b1f...`
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+/// Installer file name endings for a platform, most preferred first
+pub fn installer_suffixes(os: &str, arch: &str) -> Vec<&'static str> {
+    match (os, arch) {
+        ("macos", "aarch64") => vec!["aarch64.pkg"],
+        ("macos", "x86_64") => vec!["x86_64.pkg"],
+        ("windows", "x86_64") => vec!["x86_64.msi"],
+        ("linux", "x86_64") => linux_packages(&["amd64.deb"], &["x86_64.rpm"]),
+        ("linux", "aarch64") => linux_packages(&["arm64.deb"], &["aarch64.rpm"]),
+        _ => Vec::new(),
+    }
+}
+
+/// Package formats the local package manager can install
+fn linux_packages(deb: &[&'static str], rpm: &[&'static str]) -> Vec<&'static str> {
+    let mut suffixes = Vec::new();
+    if which("dpkg") {
+        suffixes.extend(deb);
+    }
+    if which("rpm") {
+        suffixes.extend(rpm);
+    }
+    suffixes
+}
+
+/// The installer asset for the first suffix the release provides
+pub fn select_asset<'a>(assets: &'a [ReleaseAsset], suffixes: &[&str]) -> Option<&'a ReleaseAsset> {
+    suffixes.iter().find_map(|suffix| assets.iter().find(|asset| asset.name.ends_with(suffix)))
+}
+
+/// Hex SHA-256 of a file
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let content = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(&content)))
+}
+
+/// Check a download against its published checksum
+pub fn verify_checksum(path: &Path, expected: &str) -> Result<()> {
+    let actual = sha256_file(path)?;
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        anyhow::bail!(
+            "Checksum mismatch for {}: expected {}, got {}. The download may be corrupted or tampered with",
+            path.display(), expected.trim(), actual
+        );
+    }
+    Ok(())
+}
+
+/// Install Finch (if needed) and initialize its VM
+pub async fn setup_finch(assume_yes: bool, offline: bool) -> Result<()> {
+    offline::ensure_online(offline, "Installing Finch")?;
+    let finch_client = FinchClient::new();
+    if finch_client.is_finch_available().await? {
+        status!("✅ Finch is already installed");
+    } else {
+        install_finch(assume_yes).await?;
+        if !finch_client.is_finch_available().await? {
+            anyhow::bail!("Finch was installed but `finch` is not on PATH yet. Open a new terminal and run `finch-mcp setup finch` again");
+        }
+        status!("✅ Finch installed");
+    }
+
+    if std::env::consts::OS != "linux" && !finch_client.is_vm_initialized().await? {
+        finch_client.initialize_vm().await?;
+    }
+    status!("🎉 Finch is ready. Try: finch-mcp run uvx mcp-server-time");
+    Ok(())
+}
+
+/// Ask whether to install Finch right now; false without a terminal to ask on, or `offline`
+pub async fn offer_install(offline: bool) -> Result<bool> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() || offline {
+        return Ok(false);
+    }
+    if !confirm("Finch is not installed. Install it now?")? {
+        return Ok(false);
+    }
+    setup_finch(true, false).await?;
+    Ok(true)
+}
+
+async fn install_finch(assume_yes: bool) -> Result<()> {
+    let os = std::env::consts::OS;
+    if os == "macos" && which("brew") {
+        confirm_or_bail("Install Finch with `brew install --cask finch`?", assume_yes)?;
+        return run_installer(Command::new("brew").args(["install", "--cask", "finch"])).await;
+    }
+
+    let suffixes = installer_suffixes(os, std::env::consts::ARCH);
+    if suffixes.is_empty() {
+        anyhow::bail!("No Finch installer for {} ({}). See {}", os, std::env::consts::ARCH, INSTALL_DOCS);
+    }
+
+    status!("🔎 Looking up the latest Finch release...");
+    let release: Release = serde_json::from_slice(&curl(&[RELEASES_URL]).await?)
+        .context("Failed to parse the Finch release information")?;
+    let asset = select_asset(&release.assets, &suffixes).with_context(|| format!(
+        "Finch {} has no installer for {} ({}). See {}", release.tag_name, os, std::env::consts::ARCH, INSTALL_DOCS
+    ))?;
+    let expected = expected_checksum(&release, asset).await?;
+
+    confirm_or_bail(&format!("Download and install {} ({})?", asset.name, release.tag_name), assume_yes)?;
+
+    let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let installer = temp_dir.path().join(&asset.name);
+    status!("📥 Downloading {}...", asset.name);
+    let installer_arg = installer.to_string_lossy();
+    curl(&["--output", &installer_arg, &asset.browser_download_url]).await?;
+    verify_checksum(&installer, &expected)?;
+    status!("🔒 Checksum verified");
+
+    status!("📦 Installing {} (you may be asked for your password)...", asset.name);
+    run_installer(&mut install_command(&installer)).await
+}
+
+/// The release's checksum for an asset: GitHub's digest, or a published `.sha256sum` file
+pub(crate) async fn expected_checksum(release: &Release, asset: &ReleaseAsset) -> Result<String> {
+    if let Some(digest) = asset.digest.as_deref().and_then(|digest| digest.strip_prefix("sha256:")) {
+        return Ok(digest.to_string());
+    }
+
+    let checksum_names = [format!("{}.sha256sum", asset.name), format!("{}.sha256", asset.name)];
+    let checksum_asset = release.assets.iter()
+        .find(|candidate| checksum_names.contains(&candidate.name))
+        .with_context(|| format!("Release {} publishes no checksum for {}; refusing to install it unverified", release.tag_name, asset.name))?;
+    let content = String::from_utf8_lossy(&curl(&[&checksum_asset.browser_download_url]).await?).to_string();
+    content.split_whitespace().next()
+        .map(str::to_string)
+        .with_context(|| format!("{} is empty", checksum_asset.name))
+}
+
+/// Command that installs a downloaded package
+fn install_command(installer: &Path) -> Command {
+    let path = installer.to_string_lossy().to_string();
+    let mut command;
+    if path.ends_with(".pkg") {
+        command = Command::new("sudo");
+        command.args(["installer", "-pkg", &path, "-target", "/"]);
+    } else if path.ends_with(".msi") {
+        command = Command::new("msiexec");
+        command.args(["/i", &path, "/passive"]);
+    } else if path.ends_with(".deb") {
+        command = Command::new("sudo");
+        command.args(["apt-get", "install", "-y", &path]);
+    } else {
+        command = Command::new("sudo");
+        command.args([if which("dnf") { "dnf" } else { "yum" }, "install", "-y", &path]);
+    }
+    command
+}
+
+async fn run_installer(command: &mut Command) -> Result<()> {
+    debug!("Running installer: {:?}", command);
+    let status = command.status().await.context("Failed to start the Finch installer")?;
+    if !status.success() {
+        anyhow::bail!("Finch installer exited with {}. See {} to install manually", status, INSTALL_DOCS);
+    }
+    Ok(())
+}
+
+/// Fetch a URL over HTTPS with curl, returning the body (empty when `--output` is given)
+pub(crate) async fn curl(args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--proto", "=https"])
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("Failed to run curl; it is needed for downloads")?;
+    if !output.status.success() {
+        anyhow::bail!("Download failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+fn confirm_or_bail(question: &str, assume_yes: bool) -> Result<()> {
+    if assume_yes || confirm(question)? {
+        Ok(())
+    } else {
+        anyhow::bail!("Finch installation cancelled")
+    }
+}
+
+/// Ask a yes/no question on stderr; anything but "y"/"yes" is no
+fn confirm(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("Not asking \"{}\" without a terminal; pass --yes to proceed", question);
+    }
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Check if a program is on PATH
+fn which(program: &str) -> bool {
+    std::process::Command::new(program)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://github.com/runfinch/finch/releases/download/v1.8.0/{}", name),
+            digest: None,
+        }
+    }
+
+    #[test]
+    fn test_select_asset() {
+        let assets = vec![
+            asset("Finch-v1.8.0-aarch64.pkg"),
+            asset("Finch-v1.8.0-x86_64.pkg"),
+            asset("Finch-v1.8.0-x86_64.msi"),
+            asset("Finch-v1.8.0-x86_64.msi.sha256sum"),
+        ];
+        let name = |os, arch| select_asset(&assets, &installer_suffixes(os, arch)).map(|asset| asset.name.clone());
+        assert_eq!(name("macos", "aarch64").unwrap(), "Finch-v1.8.0-aarch64.pkg");
+        assert_eq!(name("windows", "x86_64").unwrap(), "Finch-v1.8.0-x86_64.msi");
+        assert!(name("freebsd", "x86_64").is_none());
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("installer.pkg");
+        std::fs::write(&path, "finch").unwrap();
+        let expected = format!("{:x}", Sha256::digest(b"finch"));
+
+        verify_checksum(&path, &expected.to_uppercase()).unwrap();
+        let err = verify_checksum(&path, &"0".repeat(64)).unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"));
+    }
+}
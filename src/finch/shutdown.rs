@@ -0,0 +1,164 @@
+use std::fmt;
+use std::future::Future;
+use std::process::Stdio;
+
+use anyhow::Result;
+use log::{debug, info};
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+/// How long a container gets to exit after a forwarded signal before it is force-removed
+pub const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// A termination signal received by finch-mcp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Interrupt,
+    Terminate,
+}
+
+impl Signal {
+    /// Name understood by `finch kill --signal`
+    pub fn finch_name(self) -> &'static str {
+        match self {
+            Signal::Interrupt => "INT",
+            Signal::Terminate => "TERM",
+        }
+    }
+
+    /// Conventional exit code for a process ended by this signal
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Signal::Interrupt => 130,
+            Signal::Terminate => 143,
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SIG{}", self.finch_name())
+    }
+}
+
+/// Returned when a session was cut short by a signal
+#[derive(Debug)]
+pub struct Interrupted(pub Signal);
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Interrupted by {}", self.0)
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Unique name for a container finch-mcp supervises, so it can be stopped by name
+pub fn session_container_name() -> String {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    format!("mcp-session-{}", &id[..12])
+}
+
+/// Wait for SIGINT or SIGTERM (Ctrl+C only on non-Unix platforms)
+pub async fn wait_for_signal() -> Signal {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => Signal::Interrupt,
+                _ = terminate.recv() => Signal::Terminate,
+            },
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+                Signal::Interrupt
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        Signal::Interrupt
+    }
+}
+
+/// Force-remove a container, ignoring failures (it may already be gone)
+pub async fn remove_container(name: &str) {
+    let removed = Command::new("finch")
+        .args(["rm", "-f", name])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+    debug!("Force-removed container {}: {:?}", name, removed);
+}
+
+/// Send a signal to a running container
+async fn signal_container(name: &str, signal: Signal) {
+    let sent = Command::new("finch")
+        .args(["kill", "--signal", signal.finch_name(), name])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+    debug!("Forwarded {} to container {}: {:?}", signal, name, sent);
+}
+
+/// Run a session until it finishes or finch-mcp is told to stop
+///
+/// On SIGINT/SIGTERM the signal is forwarded to each container, the session gets
+/// [`SHUTDOWN_GRACE_PERIOD`] to wind down, and any container still around is force-removed.
+pub async fn run_until_signal<T, F>(session: F, container_names: &[String]) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    tokio::pin!(session);
+
+    let signal = tokio::select! {
+        result = &mut session => return result,
+        signal = wait_for_signal() => signal,
+    };
+
+    info!("Received {}, stopping {} container(s)", signal, container_names.len());
+    for name in container_names {
+        signal_container(name, signal).await;
+    }
+
+    if timeout(SHUTDOWN_GRACE_PERIOD, &mut session).await.is_err() {
+        debug!("Containers did not exit within {:?}", SHUTDOWN_GRACE_PERIOD);
+    }
+    // Cheap when `--rm` already removed them, and catches the ones that ignored the signal
+    for name in container_names {
+        remove_container(name).await;
+    }
+
+    Err(Interrupted(signal).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_container_name() {
+        let name = session_container_name();
+        assert!(name.starts_with("mcp-session-"));
+        assert_eq!(name.len(), "mcp-session-".len() + 12);
+        assert_ne!(name, session_container_name());
+    }
+
+    #[test]
+    fn test_signal_exit_codes() {
+        assert_eq!(Signal::Interrupt.exit_code(), 130);
+        assert_eq!(Signal::Terminate.exit_code(), 143);
+        assert_eq!(Interrupted(Signal::Terminate).to_string(), "Interrupted by SIGTERM");
+    }
+
+    #[tokio::test]
+    async fn test_session_result_passes_through() {
+        let result = run_until_signal(async { Ok(42) }, &[]).await;
+        assert_eq!(result.unwrap(), 42);
+    }
+}
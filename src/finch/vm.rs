@@ -0,0 +1,375 @@
+//! `finch-mcp vm`: manage the Finch VM without dropping down to the finch CLI
+//!
+//! CPU and memory are set in Finch's own `finch.yaml`, which Finch reads when the
+//! VM is created, so they take effect on `init` or `recreate`.
+//!
+//! With `--lazy-vm` the VM boots on a background thread while finch-mcp hashes and
+//! detects the project; the first step that needs Finch waits for it.
+
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::debug;
+use serde_json::{json, Value as JsonValue};
+use serde_yaml::{Mapping, Value};
+use tokio::process::Command;
+
+use crate::error::FinchMcpError;
+use crate::output;
+
+/// Suggested wait before retrying when the VM outlasts the timeout
+const RETRY_AFTER_SECS: u64 = 30;
+
+/// JSON-RPC error code for implementation-defined server errors
+const SERVER_ERROR: i64 = -32000;
+
+/// A VM boot running in the background
+struct Boot {
+    started: Instant,
+    timeout: Duration,
+    /// Set once the boot finishes: `Ok` or the reason it failed
+    result: Mutex<Option<std::result::Result<(), String>>>,
+    done: Condvar,
+}
+
+/// Boot started by `--lazy-vm`, if any
+static BOOT: OnceLock<Arc<Boot>> = OnceLock::new();
+
+/// State reported by `finch vm status`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmState {
+    /// `finch vm init` hasn't been run (or the VM was removed)
+    Nonexistent,
+    Stopped,
+    Running,
+    /// Anything else, e.g. `Starting` or an error
+    Other(String),
+}
+
+impl VmState {
+    /// Parse the output of `finch vm status`
+    pub fn parse(stdout: &str, stderr: &str) -> Self {
+        let status = stdout.trim().to_lowercase();
+        let error = stderr.to_lowercase();
+        if status.contains("nonexistent") || ["does not exist", "not found", "no such"].iter().any(|text| error.contains(text)) {
+            Self::Nonexistent
+        } else if status.contains("running") {
+            Self::Running
+        } else if status.contains("stopped") {
+            Self::Stopped
+        } else {
+            let detail = if status.is_empty() { stderr.trim() } else { stdout.trim() };
+            Self::Other(detail.to_string())
+        }
+    }
+}
+
+impl fmt::Display for VmState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nonexistent => write!(f, "not initialized"),
+            Self::Stopped => write!(f, "stopped"),
+            Self::Running => write!(f, "running"),
+            Self::Other(detail) => write!(f, "unknown ({})", detail),
+        }
+    }
+}
+
+/// Resources for the VM, applied when it is created
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VmResources {
+    pub cpus: Option<u32>,
+    /// Memory in Finch's format, e.g. `4GiB`
+    pub memory: Option<String>,
+}
+
+impl VmResources {
+    pub fn is_empty(&self) -> bool {
+        self.cpus.is_none() && self.memory.is_none()
+    }
+}
+
+/// Current VM state
+pub async fn vm_state() -> Result<VmState> {
+    let output = Command::new("finch")
+        .args(["vm", "status"])
+        .output()
+        .await
+        .context("Failed to run `finch vm status`")?;
+    Ok(VmState::parse(&String::from_utf8_lossy(&output.stdout), &String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Stop the VM
+pub async fn stop_vm(force: bool) -> Result<()> {
+    let mut args = vec!["vm", "stop"];
+    if force {
+        args.push("--force");
+    }
+    run_finch(&args).await
+}
+
+/// Delete the VM, including all of its containers and images
+pub async fn remove_vm() -> Result<()> {
+    run_finch(&["vm", "remove", "--force"]).await
+}
+
+async fn run_finch(args: &[&str]) -> Result<()> {
+    let status = Command::new("finch")
+        .args(args)
+        .stdin(Stdio::null())
+        .status()
+        .await
+        .with_context(|| format!("Failed to run `finch {}`", args.join(" ")))?;
+    if !status.success() {
+        anyhow::bail!("`finch {}` exited with {}", args.join(" "), status);
+    }
+    Ok(())
+}
+
+/// Start the VM on a background thread unless it is already running
+///
+/// Does nothing on Linux, where Finch has no VM, or if a boot was already started.
+pub fn start_in_background(timeout: Duration) {
+    if std::env::consts::OS == "linux" {
+        return;
+    }
+    let boot = Arc::new(Boot { started: Instant::now(), timeout, result: Mutex::new(None), done: Condvar::new() });
+    if BOOT.set(boot.clone()).is_err() {
+        return;
+    }
+    
+    std::thread::spawn(move || {
+        let result = boot_vm();
+        debug!("Background VM boot finished after {:?}: {:?}", boot.started.elapsed(), result);
+        *boot.result.lock().unwrap() = Some(result);
+        boot.done.notify_all();
+    });
+}
+
+/// Check if a background boot was started
+pub fn is_starting_in_background() -> bool {
+    BOOT.get().is_some()
+}
+
+/// Block until the background boot finishes, with a spinner outside MCP mode
+///
+/// Returns at once if no boot was started. Fails with `VmNotRunning` if the boot
+/// failed or is still going after its timeout.
+pub fn wait_until_ready() -> Result<()> {
+    let Some(boot) = BOOT.get() else {
+        return Ok(());
+    };
+    let deadline = boot.started + boot.timeout;
+    
+    let mut result = boot.result.lock().unwrap();
+    let spinner = (result.is_none()).then(waiting_spinner);
+    while result.is_none() {
+        let now = Instant::now();
+        if now >= deadline {
+            if let Some(spinner) = &spinner {
+                spinner.finish_and_clear();
+            }
+            return Err(FinchMcpError::VmNotRunning {
+                reason: format!("it is still starting after {}s; retry in {}s", boot.timeout.as_secs(), RETRY_AFTER_SECS),
+            }.into());
+        }
+        result = boot.done.wait_timeout(result, (deadline - now).min(Duration::from_millis(250))).unwrap().0;
+        if let Some(spinner) = &spinner {
+            spinner.set_message(format!("Waiting for the Finch VM to start ({}s)", boot.started.elapsed().as_secs()));
+        }
+    }
+    if let Some(spinner) = &spinner {
+        spinner.finish_and_clear();
+    }
+    
+    match result.as_ref() {
+        Some(Err(reason)) => Err(FinchMcpError::VmNotRunning { reason: reason.clone() }.into()),
+        _ => Ok(()),
+    }
+}
+
+fn waiting_spinner() -> ProgressBar {
+    let spinner = ProgressBar::new_spinner();
+    if output::is_quiet_mode() {
+        spinner.set_draw_target(ProgressDrawTarget::hidden());
+    } else {
+        spinner.set_style(ProgressStyle::default_spinner().tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏").template("{spinner} {msg}").unwrap());
+        spinner.enable_steady_tick(Duration::from_millis(80));
+    }
+    spinner.set_message("Waiting for the Finch VM to start");
+    spinner
+}
+
+/// Bring the VM up with the blocking finch CLI: init if it doesn't exist, else start
+fn boot_vm() -> std::result::Result<(), String> {
+    let output = std::process::Command::new("finch")
+        .args(["vm", "status"])
+        .output()
+        .map_err(|e| format!("failed to run `finch vm status`: {}", e))?;
+    let action = match VmState::parse(&String::from_utf8_lossy(&output.stdout), &String::from_utf8_lossy(&output.stderr)) {
+        VmState::Running => return Ok(()),
+        VmState::Nonexistent => "init",
+        _ => "start",
+    };
+    
+    let status = std::process::Command::new("finch")
+        .args(["vm", action])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("failed to run `finch vm {}`: {}", action, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("`finch vm {}` exited with {}", action, status))
+    }
+}
+
+/// Answer the MCP client's first request with an error, so it shows the reason
+/// instead of a closed connection
+///
+/// The request is still unread in stdin; it's given a moment to arrive.
+pub fn reply_to_client(message: &str) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line).is_ok() {
+            let _ = tx.send(line);
+        }
+    });
+    let Ok(line) = rx.recv_timeout(Duration::from_secs(2)) else {
+        return;
+    };
+    if let Some(error) = error_response(&line, message) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", error);
+        let _ = stdout.flush();
+    }
+}
+
+/// JSON-RPC error answering `request`, if it is a request (has an id)
+fn error_response(request: &str, message: &str) -> Option<JsonValue> {
+    let request: JsonValue = serde_json::from_str(request.trim()).ok()?;
+    let id = request.get("id")?.clone();
+    Some(json!({"jsonrpc": "2.0", "id": id, "error": {"code": SERVER_ERROR, "message": message}}))
+}
+
+/// Where Finch reads its VM settings
+pub fn finch_config_path() -> Result<PathBuf> {
+    #[cfg(windows)]
+    let base = dirs::data_local_dir();
+    #[cfg(not(windows))]
+    let base = dirs::home_dir();
+    Ok(base.context("Could not determine the home directory")?.join(".finch").join("finch.yaml"))
+}
+
+/// Write CPU and memory into `finch.yaml`, keeping every other setting
+pub fn apply_resources(config_path: &Path, resources: &VmResources) -> Result<()> {
+    let mut config = match fs::read_to_string(config_path) {
+        Ok(content) if !content.trim().is_empty() => serde_yaml::from_str::<Mapping>(&content)
+            .with_context(|| format!("Failed to parse {}", config_path.display()))?,
+        _ => Mapping::new(),
+    };
+    if let Some(cpus) = resources.cpus {
+        config.insert("cpus".into(), cpus.into());
+    }
+    if let Some(memory) = &resources.memory {
+        config.insert("memory".into(), Value::String(memory.clone()));
+    }
+
+    if let Some(dir) = config_path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(config_path, serde_yaml::to_string(&config)?)
+        .with_context(|| format!("Failed to write {}", config_path.display()))
+}
+
+/// Host directories the Finch VM can see: the home directory, plus
+/// `additional_directories` from `finch.yaml`
+pub fn shared_directories() -> Vec<PathBuf> {
+    let mut directories: Vec<PathBuf> = dirs::home_dir().into_iter().collect();
+    if let Ok(config_path) = finch_config_path() {
+        directories.extend(additional_directories(&config_path));
+    }
+    directories
+}
+
+fn additional_directories(config_path: &Path) -> Vec<PathBuf> {
+    let config: Mapping = fs::read_to_string(config_path).ok()
+        .and_then(|content| serde_yaml::from_str(&content).ok())
+        .unwrap_or_default();
+    let Some(Value::Sequence(entries)) = config.get("additional_directories") else {
+        return Vec::new();
+    };
+    entries.iter()
+        .filter_map(|entry| entry.get("path").and_then(Value::as_str))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// CPU and memory currently configured in `finch.yaml`
+pub fn configured_resources(config_path: &Path) -> VmResources {
+    let config: Mapping = fs::read_to_string(config_path).ok()
+        .and_then(|content| serde_yaml::from_str(&content).ok())
+        .unwrap_or_default();
+    VmResources {
+        cpus: config.get("cpus").and_then(Value::as_u64).map(|cpus| cpus as u32),
+        memory: config.get("memory").and_then(Value::as_str).map(str::to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vm_state() {
+        assert_eq!(VmState::parse("Running\n", ""), VmState::Running);
+        assert_eq!(VmState::parse("Stopped\n", ""), VmState::Stopped);
+        assert_eq!(VmState::parse("Nonexistent\n", ""), VmState::Nonexistent);
+        assert_eq!(VmState::parse("", "FATA[0000] the instance \"finch\" does not exist"), VmState::Nonexistent);
+        assert_eq!(VmState::parse("Starting\n", ""), VmState::Other("Starting".to_string()));
+    }
+
+    #[test]
+    fn test_error_response() {
+        let request = r#"{"jsonrpc":"2.0","id":7,"method":"initialize","params":{}}"#;
+        let error = error_response(request, "Finch VM is not running").unwrap();
+        assert_eq!(error["id"], 7);
+        assert_eq!(error["error"]["message"], "Finch VM is not running");
+        assert!(error_response(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#, "x").is_none());
+    }
+
+    #[test]
+    fn test_apply_resources_keeps_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".finch").join("finch.yaml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "cpus: 2\nmemory: 2GiB\nvmType: vz\n").unwrap();
+
+        apply_resources(&path, &VmResources { cpus: Some(6), memory: None }).unwrap();
+        assert_eq!(configured_resources(&path), VmResources { cpus: Some(6), memory: Some("2GiB".to_string()) });
+        assert!(fs::read_to_string(&path).unwrap().contains("vmType: vz"));
+
+        let fresh = dir.path().join("new").join("finch.yaml");
+        apply_resources(&fresh, &VmResources { cpus: None, memory: Some("8GiB".to_string()) }).unwrap();
+        assert_eq!(configured_resources(&fresh).memory.as_deref(), Some("8GiB"));
+    }
+
+    #[test]
+    fn test_additional_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("finch.yaml");
+        fs::write(&path, "cpus: 2\nadditional_directories:\n  - path: /Volumes/data\n  - path: /opt/work\n").unwrap();
+        assert_eq!(additional_directories(&path), [PathBuf::from("/Volumes/data"), PathBuf::from("/opt/work")]);
+        assert!(additional_directories(&dir.path().join("missing.yaml")).is_empty());
+    }
+}
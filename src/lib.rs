@@ -1,33 +1,87 @@
+// Human-readable output goes to stderr; stdout carries MCP traffic and `output::print_payload`
+#![warn(clippy::print_stdout)]
+
 // Export modules
 pub mod cli;
+pub mod completions;
+pub mod diagnostics;
+pub mod error;
+pub mod events;
 pub mod run;
+pub mod run_id;
 pub mod finch {
     pub mod client;
+    pub mod disk;
+    pub mod login;
+    pub mod pull;
+    pub mod setup;
+    pub mod shutdown;
+    pub mod vm;
 }
 pub mod templates {
     pub mod dockerfile;
 }
 pub mod utils {
+    pub mod allowed_paths;
+    pub mod archive_source;
     pub mod command_detector;
     pub mod command_parser;
+    pub mod devcontainer;
+    pub mod env_vars;
     pub mod git_repository;
+    pub mod mcp_manifest;
+    pub mod offline;
+    pub mod package_version;
     pub mod progress;
     pub mod project_detector;
+    pub mod prompt;
+    pub mod proxy;
+    pub mod target_kind;
+    pub mod target_scheme;
+    pub mod timezone;
     pub mod build_deps;
+    pub mod units;
+    pub mod user_map;
+    pub mod volumes;
 }
 pub mod core {
     pub mod auto_containerize;
+    pub mod bench;
+    pub mod context_size;
+    pub mod ca_certs;
+    pub mod client_config;
     pub mod git_containerize;
     pub mod finch_config;
+    pub mod server_manifest;
+    pub mod upgrade;
+    pub mod plan;
+    pub mod registry_secrets;
+    pub mod report;
+    pub mod sbom;
+    pub mod scan;
+    pub mod inspect;
+    pub mod list;
+    pub mod gc;
+    pub mod healthcheck;
+    pub mod warm;
 }
 pub mod cache;
 pub mod logging;
 pub mod output;
+pub mod paths;
 pub mod mcp;
+pub mod self_update;
+pub mod stats;
+pub mod telemetry;
+pub mod workspace;
 
 // Re-export main types for easier access
+pub use error::FinchMcpError;
+pub use events::{Event, EventSink, Events};
 pub use run::{RunOptions, run_stdio_container};
-pub use finch::client::{FinchClient, StdioRunOptions};
+pub use finch::client::{FinchClient, NetworkMode, StdioRunOptions};
+pub use finch::pull::PullPolicy;
+pub use mcp::supervisor::RestartPolicy;
 pub use templates::dockerfile::{DockerfileOptions, generate_stdio_dockerfile};
 pub use core::auto_containerize::{AutoContainerizeOptions, auto_containerize_and_run};
 pub use core::git_containerize::{GitContainerizeOptions, git_containerize_and_run, LocalContainerizeOptions, local_containerize_and_run};
\ No newline at end of file
@@ -1,9 +1,69 @@
 use std::path::{Path, PathBuf};
 use std::fs;
-use std::env;
 use anyhow::{Result, Context};
-use chrono::{DateTime, Utc};
-use std::io::Write;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::paths;
+use crate::utils::units::{parse_age, parse_size};
+
+pub mod session;
+
+/// Timestamp format embedded in every log filename
+const TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// The `logs` section of the user config (`~/.config/finch-mcp/config.yaml`)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogPolicy {
+    /// Remove the oldest logs until the logs directory fits, e.g. `100MiB`
+    pub max_total_size: String,
+    /// Gzip logs older than this, e.g. `7d`
+    pub compress_after: Option<String>,
+}
+
+impl Default for LogPolicy {
+    fn default() -> Self {
+        Self { max_total_size: "100MiB".to_string(), compress_after: None }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct UserConfig {
+    #[serde(default)]
+    logs: LogPolicy,
+}
+
+impl LogPolicy {
+    /// Load the policy from the user config; the default (a 100 MiB cap) if there is none
+    pub fn load() -> Result<Self> {
+        Ok(paths::load_user_config::<UserConfig>()?.map(|(config, _)| config.logs).unwrap_or_default())
+    }
+
+    /// The size cap in bytes and the compression age in seconds
+    fn limits(&self) -> Result<(u64, Option<u64>)> {
+        let max_total_bytes = parse_size(&self.max_total_size)
+            .map_err(|e| anyhow::anyhow!("logs.maxTotalSize: {}", e))?;
+        let compress_after_secs = self.compress_after.as_deref()
+            .map(parse_age)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("logs.compressAfter: {}", e))?;
+        Ok((max_total_bytes, compress_after_secs))
+    }
+}
+
+/// What a rotation compressed and removed
+#[derive(Debug, Default)]
+pub struct RotationReport {
+    pub compressed: usize,
+    pub removed: usize,
+    pub freed_bytes: u64,
+}
 
 pub struct LogManager {
     log_dir: PathBuf,
@@ -19,24 +79,17 @@ impl LogManager {
     }
 
     fn get_logs_directory() -> Result<PathBuf> {
-        // Use XDG_STATE_HOME if available, otherwise fall back to ~/.local/state
-        let state_home = if let Ok(xdg_state) = env::var("XDG_STATE_HOME") {
-            PathBuf::from(xdg_state)
-        } else {
-            let home = env::var("HOME")
-                .with_context(|| "HOME environment variable not set")?;
-            PathBuf::from(home).join(".local").join("state")
-        };
-
-        Ok(state_home.join("finch-mcp").join("logs"))
+        Ok(crate::paths::data_dir()?.join("logs"))
     }
 
     pub fn log_build_start(&self, operation_type: &str, identifier: &str) -> Result<String> {
+        self.rotate_quietly();
         let timestamp = Utc::now();
-        let log_filename = format!("{}_{}_build_{}.log", 
+        let log_filename = format!("{}_{}_build_{}_{}.log", 
             operation_type,
             Self::sanitize_identifier(identifier),
-            timestamp.format("%Y%m%d_%H%M%S")
+            crate::run_id::short(),
+            timestamp.format(TIMESTAMP_FORMAT)
         );
         
         let log_path = self.log_dir.join(&log_filename);
@@ -45,25 +98,43 @@ impl LogManager {
 
         writeln!(file, "=== Build Log for {} ===", operation_type)?;
         writeln!(file, "Identifier: {}", identifier)?;
+        writeln!(file, "Run ID: {}", crate::run_id::get())?;
         writeln!(file, "Started: {}", timestamp.format("%Y-%m-%d %H:%M:%S UTC"))?;
         writeln!(file, "=")?;
         writeln!(file)?;
 
+        let mut start = structured_record("start", FINCH_MCP_STREAM, &format!("Build of {} started", identifier));
+        start["operation"] = json!(operation_type);
+        start["source"] = json!(identifier);
+        start["runId"] = json!(crate::run_id::get());
+        fs::write(structured_path(&log_path), format!("{}\n", start))
+            .with_context(|| format!("Failed to create log file: {}", structured_path(&log_path).display()))?;
+
         Ok(log_filename)
     }
 
     pub fn append_to_log(&self, log_filename: &str, content: &str) -> Result<()> {
         let log_path = self.log_dir.join(log_filename);
-        let mut file = fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_path)
-            .with_context(|| format!("Failed to open log file: {}", log_path.display()))?;
-
-        writeln!(file, "{}", content)?;
+        append_text(&log_path, content)?;
+        append_structured(&log_path, &structured_record("build", FINCH_MCP_STREAM, content));
         Ok(())
     }
 
+    /// Full path of a log in the logs directory
+    pub fn log_path(&self, log_filename: &str) -> PathBuf {
+        self.log_dir.join(log_filename)
+    }
+
+    /// Where the SBOM for an image is kept, next to its build logs
+    pub fn sbom_path(&self, image_name: &str) -> PathBuf {
+        self.log_dir.join(format!("sbom_{}.json", Self::sanitize_identifier(image_name)))
+    }
+
+    /// Open a log for streaming command output into it, e.g. from reader threads
+    pub fn open_sink(&self, log_filename: &str) -> Result<LogSink> {
+        LogSink::open(&self.log_dir.join(log_filename))
+    }
+
     pub fn finish_build_log(&self, log_filename: &str, success: bool, duration_secs: u64) -> Result<()> {
         let timestamp = Utc::now();
         let status = if success { "SUCCESS" } else { "FAILED" };
@@ -75,7 +146,64 @@ impl LogManager {
             duration_secs
         );
 
-        self.append_to_log(log_filename, &content)
+        let log_path = self.log_dir.join(log_filename);
+        append_text(&log_path, &content)?;
+        let mut finish = structured_record("finish", FINCH_MCP_STREAM, &format!("Build {}", status));
+        finish["success"] = json!(success);
+        finish["durationSecs"] = json!(duration_secs);
+        append_structured(&log_path, &finish);
+        Ok(())
+    }
+
+    /// Record why a server failed to start, returning the log path
+    pub fn log_run_failure(&self, identifier: &str, content: &str) -> Result<PathBuf> {
+        let timestamp = Utc::now();
+        let log_filename = format!("run_{}_startup_{}.log",
+            Self::sanitize_identifier(identifier),
+            timestamp.format(TIMESTAMP_FORMAT)
+        );
+        
+        let log_path = self.log_dir.join(&log_filename);
+        let mut file = fs::File::create(&log_path)
+            .with_context(|| format!("Failed to create log file: {}", log_path.display()))?;
+
+        writeln!(file, "=== Startup Failure ===")?;
+        writeln!(file, "Identifier: {}", identifier)?;
+        writeln!(file, "Time: {}", timestamp.format("%Y-%m-%d %H:%M:%S UTC"))?;
+        writeln!(file, "=")?;
+        writeln!(file)?;
+        writeln!(file, "{}", content)?;
+
+        Ok(log_path)
+    }
+
+    /// Create the JSON-lines log file for an MCP session
+    pub fn create_session_log(&self) -> Result<(PathBuf, fs::File)> {
+        self.rotate_quietly();
+        let log_filename = format!("session_{}_{}.log",
+            std::process::id(),
+            Utc::now().format(TIMESTAMP_FORMAT)
+        );
+        
+        let log_path = self.log_dir.join(&log_filename);
+        let file = fs::File::create(&log_path)
+            .with_context(|| format!("Failed to create log file: {}", log_path.display()))?;
+        Ok((log_path, file))
+    }
+
+    /// Most recent build log, if any
+    pub fn latest_build_log(&self) -> Result<Option<LogEntry>> {
+        Ok(self.list_recent_logs(usize::MAX)?
+            .into_iter()
+            .find(|entry| entry.filename.contains("_build_")))
+    }
+
+    /// Most recent build log for a source (URL, path or command), if any
+    pub fn latest_build_log_for(&self, identifier: &str) -> Result<Option<LogEntry>> {
+        let marker = format!("_{}_build_", Self::sanitize_identifier(identifier));
+        Ok(self.list_recent_logs(usize::MAX)?
+            .into_iter()
+            .find(|entry| entry.filename.contains(&marker)))
     }
 
     pub fn list_recent_logs(&self, limit: usize) -> Result<Vec<LogEntry>> {
@@ -89,45 +217,92 @@ impl LogManager {
             let entry = entry?;
             let path = entry.path();
             
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "log") {
+            if path.is_file() {
                 if let Some(log_entry) = LogEntry::from_path(&path)? {
                     entries.push(log_entry);
                 }
             }
         }
 
-        // Sort by creation time, most recent first
-        entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        // Sort by the time in the filename, most recent first
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
         entries.truncate(limit);
 
         Ok(entries)
     }
 
+    /// Remove logs older than `keep_days`, going by the time in their filenames
     pub fn cleanup_old_logs(&self, keep_days: u32) -> Result<usize> {
         let cutoff = Utc::now() - chrono::Duration::days(keep_days as i64);
         let mut removed_count = 0;
 
-        if !self.log_dir.exists() {
-            return Ok(0);
+        for entry in self.list_recent_logs(usize::MAX)? {
+            if entry.created_at < cutoff {
+                entry.remove()?;
+                removed_count += 1;
+            }
         }
 
-        for entry in fs::read_dir(&self.log_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "log") {
-                let metadata = fs::metadata(&path)?;
-                if let Ok(created) = metadata.created() {
-                    let created_datetime: DateTime<Utc> = created.into();
-                    if created_datetime < cutoff {
-                        fs::remove_file(&path)?;
-                        removed_count += 1;
-                    }
-                }
+        Ok(removed_count)
+    }
+
+    /// Gzip logs older than `compressAfter`, then remove the oldest logs until the
+    /// directory fits `maxTotalSize`. The newest log is always kept
+    pub fn rotate(&self, policy: &LogPolicy) -> Result<RotationReport> {
+        let (max_total_bytes, compress_after_secs) = policy.limits()?;
+        let mut report = RotationReport::default();
+        let mut logs = self.list_recent_logs(usize::MAX)?;
+
+        if let Some(compress_after_secs) = compress_after_secs {
+            let cutoff = Utc::now() - chrono::Duration::seconds(compress_after_secs as i64);
+            for entry in logs.iter_mut().filter(|entry| !entry.is_compressed() && entry.created_at < cutoff) {
+                entry.path = compress_log(&entry.path)?;
+                entry.filename.push_str(".gz");
+                report.compressed += 1;
             }
         }
 
-        Ok(removed_count)
+        let sizes: Vec<u64> = logs.iter().map(LogEntry::size).collect();
+        let mut total: u64 = sizes.iter().sum();
+        for (entry, size) in logs.iter().zip(sizes).skip(1).rev() {
+            if total <= max_total_bytes {
+                break;
+            }
+            entry.remove()?;
+            total = total.saturating_sub(size);
+            report.removed += 1;
+            report.freed_bytes += size;
+        }
+
+        Ok(report)
+    }
+
+    /// Build logs matching the query, most recent first
+    pub fn search(&self, query: &LogQuery) -> Result<Vec<(LogEntry, BuildSummary)>> {
+        Ok(self.list_recent_logs(usize::MAX)?
+            .into_iter()
+            .filter(|entry| entry.filename.contains("_build_"))
+            .map(|entry| {
+                let summary = entry.build_summary();
+                (entry, summary)
+            })
+            .filter(|(entry, summary)| query.matches(entry, summary))
+            .collect())
+    }
+
+    /// Rotate under the configured policy before a new log is created; a failure only costs disk space
+    fn rotate_quietly(&self) {
+        if let Err(e) = LogPolicy::load().and_then(|policy| self.rotate(&policy)) {
+            log::debug!("Failed to rotate logs: {:#}", e);
+        }
+    }
+
+    /// Read a log, decompressing it if it was gzipped. `name.log` finds `name.log.gz`
+    pub fn read_log(&self, log_filename: &str) -> Result<Option<String>> {
+        let path = self.log_dir.join(log_filename);
+        let compressed = self.log_dir.join(format!("{}.gz", log_filename));
+        let path = if path.is_file() { path } else if compressed.is_file() { compressed } else { return Ok(None) };
+        read_log_file(&path).map(Some)
     }
 
     pub fn get_logs_directory_path(&self) -> &Path {
@@ -142,6 +317,77 @@ impl LogManager {
     }
 }
 
+/// Shared append handle on a log file
+#[derive(Clone)]
+pub struct LogSink {
+    file: Arc<Mutex<fs::File>>,
+    /// The structured twin, for build logs
+    structured: Option<Arc<Mutex<fs::File>>>,
+}
+
+impl LogSink {
+    pub fn open(log_path: &Path) -> Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path)
+            .with_context(|| format!("Failed to open log file: {}", log_path.display()))?;
+        let structured = fs::OpenOptions::new().append(true).open(structured_path(log_path)).ok();
+        Ok(Self { file: Arc::new(Mutex::new(file)), structured: structured.map(|file| Arc::new(Mutex::new(file))) })
+    }
+
+    /// Append one line of finch-mcp's own; write errors are ignored so logging never fails a build
+    pub fn write_line(&self, line: &str) {
+        self.write_output(FINCH_MCP_STREAM, line);
+    }
+
+    /// Append one line a command wrote to `stream` (`stdout` or `stderr`)
+    pub fn write_output(&self, stream: &str, line: &str) {
+        let _ = writeln!(self.file.lock().unwrap(), "{}", line);
+        if let Some(structured) = &self.structured {
+            let _ = writeln!(structured.lock().unwrap(), "{}", structured_record("build", stream, line));
+        }
+    }
+}
+
+/// Stream of the lines finch-mcp writes itself, as opposed to a command's output
+const FINCH_MCP_STREAM: &str = "finch-mcp";
+
+/// Where a build log's NDJSON twin is kept: `x.log` (or `x.log.gz`) has `x.ndjson`
+pub fn structured_path(log_path: &Path) -> PathBuf {
+    let name = log_path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let stem = name.strip_suffix(".gz").unwrap_or(name);
+    let stem = stem.strip_suffix(".log").unwrap_or(stem);
+    log_path.with_file_name(format!("{}.ndjson", stem))
+}
+
+/// One line of a structured build log
+fn structured_record(phase: &str, stream: &str, message: &str) -> Value {
+    json!({
+        "timestamp": Utc::now().to_rfc3339(),
+        "phase": phase,
+        "stream": stream,
+        "message": message,
+    })
+}
+
+fn append_text(log_path: &Path, content: &str) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .with_context(|| format!("Failed to open log file: {}", log_path.display()))?;
+    writeln!(file, "{}", content)?;
+    Ok(())
+}
+
+/// Append to a build log's twin, if it has one
+fn append_structured(log_path: &Path, record: &Value) {
+    if let Ok(mut file) = fs::OpenOptions::new().append(true).open(structured_path(log_path)) {
+        let _ = writeln!(file, "{}", record);
+    }
+}
+
 #[derive(Debug)]
 pub struct LogEntry {
     pub filename: String,
@@ -149,6 +395,8 @@ pub struct LogEntry {
     pub created_at: DateTime<Utc>,
     pub operation_type: String,
     pub identifier: String,
+    /// Short ID of the run that wrote the log, for logs written since runs had IDs
+    pub run_id: Option<String>,
 }
 
 impl LogEntry {
@@ -157,27 +405,37 @@ impl LogEntry {
             .and_then(|n| n.to_str())
             .ok_or_else(|| anyhow::anyhow!("Invalid filename"))?;
 
-        // Parse filename: {operation_type}_{identifier}_build_{timestamp}.log
-        let parts: Vec<&str> = filename.split('_').collect();
-        if parts.len() < 4 || !filename.ends_with(".log") {
+        // Parse filename: {operation_type}_{identifier}_build[_{run ID}]_{YYYYmmdd}_{HHMMSS}.log[.gz]
+        let Some(stem) = filename.strip_suffix(".log").or_else(|| filename.strip_suffix(".log.gz")) else {
+            return Ok(None);
+        };
+        let parts: Vec<&str> = stem.split('_').collect();
+        if parts.len() < 4 {
             return Ok(None);
         }
 
         let operation_type = parts[0].to_string();
-        
-        // Find the timestamp part (last part before .log)
-        let _timestamp_part = parts.iter()
-            .rev()
-            .find(|&&part| part.contains(".log"))
-            .map(|&part| part.trim_end_matches(".log"))
-            .ok_or_else(|| anyhow::anyhow!("No timestamp found"))?;
 
-        // Identifier is everything between operation_type and build_{timestamp}
-        let identifier_parts = &parts[1..parts.len()-2]; // Skip operation_type and build_{timestamp}
-        let identifier = identifier_parts.join("_");
+        // The time is in the name, as creation times aren't recorded on every filesystem
+        let timestamp = parts[parts.len() - 2..].join("_");
+        let created_at = match NaiveDateTime::parse_from_str(&timestamp, TIMESTAMP_FORMAT) {
+            Ok(time) => time.and_utc(),
+            Err(_) => fs::metadata(path)?.modified()?.into(),
+        };
 
-        let metadata = fs::metadata(path)?;
-        let created_at: DateTime<Utc> = metadata.created()?.into();
+        // Identifier is everything between operation_type and the kind of log
+        let mut rest = &parts[1..parts.len() - 2];
+        let mut run_id = None;
+        if let [identifier @ .., "build", id] = rest {
+            if id.len() == 8 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+                rest = identifier;
+                run_id = Some(id.to_string());
+            }
+        }
+        if let [identifier @ .., "build" | "startup"] = rest {
+            rest = identifier;
+        }
+        let identifier = rest.join("_");
 
         Ok(Some(Self {
             filename: filename.to_string(),
@@ -185,6 +443,226 @@ impl LogEntry {
             created_at,
             operation_type,
             identifier,
+            run_id,
         }))
     }
-}
\ No newline at end of file
+
+    pub fn is_compressed(&self) -> bool {
+        self.filename.ends_with(".gz")
+    }
+
+    /// The log's text, decompressed if it was gzipped
+    pub fn read(&self) -> Result<String> {
+        read_log_file(&self.path)
+    }
+
+    /// Bytes on disk, the structured twin included
+    fn size(&self) -> u64 {
+        [self.path.clone(), structured_path(&self.path)].iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// Delete the log and its structured twin
+    fn remove(&self) -> Result<()> {
+        fs::remove_file(&self.path)?;
+        let _ = fs::remove_file(structured_path(&self.path));
+        Ok(())
+    }
+
+    /// What the log says about its build: from the structured twin, or for logs
+    /// written before there was one, the text
+    pub fn build_summary(&self) -> BuildSummary {
+        let mut summary = BuildSummary::default();
+        if let Ok(content) = fs::read_to_string(structured_path(&self.path)) {
+            for record in content.lines().filter_map(|line| serde_json::from_str::<Value>(line).ok()) {
+                match record["phase"].as_str() {
+                    Some("start") => {
+                        summary.source = record["source"].as_str().map(str::to_string);
+                        summary.run_id = record["runId"].as_str().map(str::to_string);
+                    }
+                    Some("finish") => {
+                        summary.success = record["success"].as_bool();
+                        summary.duration_secs = record["durationSecs"].as_u64();
+                    }
+                    _ => {}
+                }
+            }
+            return summary;
+        }
+
+        if let Ok(content) = read_log_file(&self.path) {
+            summary.source = content.lines().find_map(|line| line.strip_prefix("Identifier: ")).map(str::to_string);
+            summary.run_id = content.lines().find_map(|line| line.strip_prefix("Run ID: ")).map(str::to_string);
+            if content.contains("=== Build SUCCESS ===") {
+                summary.success = Some(true);
+            } else if content.contains("=== Build FAILED ===") {
+                summary.success = Some(false);
+            }
+        }
+        summary
+    }
+}
+
+/// A build's source and outcome, as recorded in its log
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BuildSummary {
+    pub source: Option<String>,
+    pub run_id: Option<String>,
+    /// `None` while the build runs, or if it was interrupted
+    pub success: Option<bool>,
+    pub duration_secs: Option<u64>,
+}
+
+/// Which build logs `logs search` returns
+#[derive(Debug, Default, Clone)]
+pub struct LogQuery {
+    /// Part of the source (URL, path or command) the build was for
+    pub source: Option<String>,
+    /// Only failed builds
+    pub failed: bool,
+    /// Only logs from after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only logs of this run (its ID or a prefix of it)
+    pub run: Option<String>,
+}
+
+impl LogQuery {
+    fn matches(&self, entry: &LogEntry, summary: &BuildSummary) -> bool {
+        if self.since.is_some_and(|since| entry.created_at < since) {
+            return false;
+        }
+        if self.failed && summary.success != Some(false) {
+            return false;
+        }
+        if let Some(run) = &self.run {
+            let run_id = summary.run_id.as_ref().or(entry.run_id.as_ref());
+            if !run_id.is_some_and(|run_id| crate::run_id::matches(run_id, run)) {
+                return false;
+            }
+        }
+        match &self.source {
+            Some(source) => summary.source.as_deref().unwrap_or(&entry.identifier).contains(source.as_str())
+                || entry.identifier.contains(&LogManager::sanitize_identifier(source)),
+            None => true,
+        }
+    }
+}
+
+/// A log's text, decompressed if it was gzipped
+fn read_log_file(path: &Path) -> Result<String> {
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut content = String::new();
+        GzDecoder::new(fs::File::open(path)?).read_to_string(&mut content)
+            .with_context(|| format!("Failed to decompress {}", path.display()))?;
+        Ok(content)
+    } else {
+        Ok(fs::read_to_string(path)?)
+    }
+}
+
+/// Replace a log with a gzipped copy, returning the new path
+fn compress_log(path: &Path) -> Result<PathBuf> {
+    let mut compressed_path = path.as_os_str().to_owned();
+    compressed_path.push(".gz");
+    let compressed_path = PathBuf::from(compressed_path);
+
+    let mut encoder = GzEncoder::new(fs::File::create(&compressed_path)?, flate2::Compression::default());
+    std::io::copy(&mut fs::File::open(path)?, &mut encoder)?;
+    encoder.finish().with_context(|| format!("Failed to compress {}", path.display()))?;
+    fs::remove_file(path)?;
+    Ok(compressed_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_log(dir: &Path, name: &str, bytes: usize) {
+        fs::write(dir.join(name), "x".repeat(bytes)).unwrap();
+    }
+
+    #[test]
+    fn test_log_entry_time_from_filename() {
+        let dir = tempfile::tempdir().unwrap();
+        write_log(dir.path(), "local_my_server_build_20240115_103045.log", 1);
+        let entry = LogEntry::from_path(&dir.path().join("local_my_server_build_20240115_103045.log")).unwrap().unwrap();
+        assert_eq!(entry.created_at.format("%Y-%m-%d %H:%M:%S").to_string(), "2024-01-15 10:30:45");
+        assert_eq!(entry.operation_type, "local");
+        assert_eq!(entry.identifier, "my_server");
+
+        assert_eq!(entry.run_id, None);
+
+        write_log(dir.path(), "git_acme_repo_build_9f3c1a2b_20240115_103045.log.gz", 1);
+        let entry = LogEntry::from_path(&dir.path().join("git_acme_repo_build_9f3c1a2b_20240115_103045.log.gz")).unwrap().unwrap();
+        assert_eq!((entry.identifier.as_str(), entry.run_id.as_deref()), ("acme_repo", Some("9f3c1a2b")));
+
+        write_log(dir.path(), "notes.txt", 1);
+        assert!(LogEntry::from_path(&dir.path().join("notes.txt")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = LogManager { log_dir: dir.path().to_path_buf() };
+        let recent = Utc::now().format(TIMESTAMP_FORMAT);
+        write_log(dir.path(), "git_repo_build_20240101_000000.log", 400);
+        write_log(dir.path(), "git_repo_build_20240102_000000.log", 400);
+        write_log(dir.path(), &format!("git_repo_build_{}.log", recent), 400);
+
+        // Too big: the oldest goes first, and the newest always stays
+        let policy = LogPolicy { max_total_size: "1KB".to_string(), compress_after: None };
+        let report = logs.rotate(&policy).unwrap();
+        assert_eq!((report.removed, report.freed_bytes), (1, 400));
+        assert!(!dir.path().join("git_repo_build_20240101_000000.log").exists());
+
+        // Old logs are gzipped and still readable
+        let policy = LogPolicy { max_total_size: "1KB".to_string(), compress_after: Some("7d".to_string()) };
+        assert_eq!(logs.rotate(&policy).unwrap().compressed, 1);
+        assert!(dir.path().join("git_repo_build_20240102_000000.log.gz").exists());
+        assert_eq!(logs.read_log("git_repo_build_20240102_000000.log").unwrap().unwrap(), "x".repeat(400));
+        assert_eq!(logs.list_recent_logs(usize::MAX).unwrap().len(), 2);
+
+        let policy = LogPolicy { max_total_size: "1B".to_string(), compress_after: None };
+        logs.rotate(&policy).unwrap();
+        assert_eq!(logs.list_recent_logs(usize::MAX).unwrap()[0].filename, format!("git_repo_build_{}.log", recent));
+    }
+
+    #[test]
+    fn test_structured_log_and_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = LogManager { log_dir: dir.path().to_path_buf() };
+
+        let failed = logs.log_build_start("local", "./weather-server").unwrap();
+        logs.append_to_log(&failed, "Build command: finch build").unwrap();
+        logs.open_sink(&failed).unwrap().write_output("stderr", "ERROR: npm ci exited with 1");
+        logs.finish_build_log(&failed, false, 12).unwrap();
+        let passed = logs.log_build_start("git", "https://github.com/acme/time-server").unwrap();
+        logs.finish_build_log(&passed, true, 30).unwrap();
+
+        let records: Vec<Value> = fs::read_to_string(structured_path(&logs.log_path(&failed))).unwrap()
+            .lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let phases: Vec<&str> = records.iter().map(|record| record["phase"].as_str().unwrap()).collect();
+        assert_eq!(phases, ["start", "build", "build", "finish"]);
+        assert_eq!(records[2]["stream"], "stderr");
+        assert_eq!(records[2]["message"], "ERROR: npm ci exited with 1");
+        assert!(fs::read_to_string(logs.log_path(&failed)).unwrap().contains("ERROR: npm ci exited with 1"));
+
+        let found = logs.search(&LogQuery { failed: true, ..Default::default() }).unwrap();
+        assert_eq!(found.len(), 1);
+        let run_id = Some(crate::run_id::get().to_string());
+        assert_eq!(found[0].1, BuildSummary { source: Some("./weather-server".to_string()), run_id, success: Some(false), duration_secs: Some(12) });
+        assert_eq!(found[0].0.run_id.as_deref(), Some(crate::run_id::short()));
+        assert_eq!(logs.search(&LogQuery { run: Some(crate::run_id::short().to_string()), ..Default::default() }).unwrap().len(), 2);
+        assert!(logs.search(&LogQuery { run: Some("zzzz".to_string()), ..Default::default() }).unwrap().is_empty());
+        let found = logs.search(&LogQuery { source: Some("acme/time".to_string()), ..Default::default() }).unwrap();
+        assert_eq!(found[0].0.filename, passed);
+        let since = Some(Utc::now() + chrono::Duration::hours(1));
+        assert!(logs.search(&LogQuery { since, ..Default::default() }).unwrap().is_empty());
+
+        // Removing a log takes its twin along
+        logs.cleanup_old_logs(0).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
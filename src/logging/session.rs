@@ -0,0 +1,105 @@
+//! Session logger for MCP mode
+//!
+//! While finch-mcp owns stdio for an MCP client, log records go to a JSON-lines
+//! file under the logs directory instead of the terminal. Errors are still echoed
+//! to stderr so clients that surface server stderr show them.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::Utc;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::{json, Value};
+
+use super::LogManager;
+
+/// Writes every record as one JSON object per line
+pub struct SessionLogger {
+    file: Mutex<File>,
+    level: LevelFilter,
+}
+
+impl SessionLogger {
+    pub fn new(file: File, level: LevelFilter) -> Self {
+        Self { file: Mutex::new(file), level }
+    }
+}
+
+impl Log for SessionLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if record.level() == Level::Error {
+            eprintln!("[ERROR {}] {}", record.target(), record.args());
+        }
+
+        // Logging must never break the session, so write errors are ignored
+        let mut file = self.file.lock().unwrap();
+        let _ = writeln!(file, "{}", format_record(record));
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().unwrap().flush();
+    }
+}
+
+/// JSON form of a log record
+pub fn format_record(record: &Record) -> Value {
+    json!({
+        "timestamp": Utc::now().to_rfc3339(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+}
+
+/// Install the session logger, returning the path of the log file
+pub fn init(level: LevelFilter) -> Result<PathBuf> {
+    let (path, file) = LogManager::new()?.create_session_log()?;
+    let logger: &'static SessionLogger = Box::leak(Box::new(SessionLogger::new(file, level)));
+    log::set_logger(logger).map_err(|e| anyhow::anyhow!("Failed to install session logger: {}", e))?;
+    log::set_max_level(level);
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_record() {
+        let line = format_record(&Record::builder()
+            .args(format_args!("Using cached image {}", "mcp-local-demo"))
+            .level(Level::Debug)
+            .target("finch_mcp::core")
+            .build());
+        assert_eq!(line["level"], "DEBUG");
+        assert_eq!(line["target"], "finch_mcp::core");
+        assert_eq!(line["message"], "Using cached image mcp-local-demo");
+        assert!(line["timestamp"].is_string());
+    }
+
+    #[test]
+    fn test_session_logger_filters_by_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.log");
+        let logger = SessionLogger::new(File::create(&path).unwrap(), LevelFilter::Info);
+
+        logger.log(&Record::builder().args(format_args!("kept")).level(Level::Info).build());
+        logger.log(&Record::builder().args(format_args!("dropped")).level(Level::Trace).build());
+        logger.flush();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["message"], "kept");
+    }
+}
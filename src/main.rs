@@ -1,13 +1,45 @@
-use finch_mcp::cli::{Cli, Commands, CacheCommands, LogCommands};
+#![warn(clippy::print_stdout)]
+
+use clap::CommandFactory;
+use clap_complete::CompleteEnv;
+use finch_mcp::cli::{Cli, Commands, CacheCommands, ConfigCommands, LogCommands, SelfCommands, SetupCommands, VmCommands};
+use finch_mcp::utils::offline;
+use finch_mcp::utils::progress::build_policy;
+use finch_mcp::utils::target_kind::TargetKind;
+use finch_mcp::utils::user_map::UserMap;
+use finch_mcp::completions::{write_script, COMPLETE_VAR};
 use finch_mcp::run::run_stdio_container;
+use finch_mcp::mcp::gateway::serve_stdio_container;
+use finch_mcp::mcp::conformance::test_stdio_container;
+use finch_mcp::mcp::tools::{list_container_tools, print_tools};
 use finch_mcp::core::auto_containerize::{auto_containerize_and_run, auto_build};
+use finch_mcp::core::client_config::{self, ConfigMode};
+use finch_mcp::core::gc::{collect, print_report, GcPolicy};
+use finch_mcp::core::inspect::inspect;
+use finch_mcp::core::report::{self, write_report};
+use finch_mcp::core::bench::{bench, print_report as print_bench_report, BenchTarget};
+use finch_mcp::core::sbom::generate_sbom;
+use finch_mcp::core::scan::{scan_image, Scanner, Severity};
+use finch_mcp::core::warm::{manifest_targets, warm, WarmTarget};
+use finch_mcp::core::upgrade::{upgrade, print_report as print_upgrade_report, UpgradeStatus};
+use finch_mcp::core::plan::{plan_auto, plan_git, plan_local};
+use finch_mcp::core::server_manifest::{servers_up, servers_down, build_server, ServerSource, ServerSpec};
+use finch_mcp::mcp::aggregator::{run_aggregator, parse_aggregate_target, dedupe_names, AggregatedServer};
 use finch_mcp::core::git_containerize::{git_containerize_and_run, local_containerize_and_run, git_build, local_build};
-use finch_mcp::finch::client::FinchClient;
-use finch_mcp::cache::CacheManager;
-use finch_mcp::logging::LogManager;
-use finch_mcp::status;
+use finch_mcp::finch::client::{detached_name, ContainerExit, FinchClient, McpContainer, StdioRunOptions};
+use finch_mcp::finch::setup::{offer_install, setup_finch};
+use finch_mcp::output::OutputMode;
+use finch_mcp::self_update::{notify_if_outdated, self_update};
+use finch_mcp::finch::shutdown::Interrupted;
+use finch_mcp::cache::{CacheManager, RetentionPolicy};
+use finch_mcp::cache::archive::{export_cache, import_cache};
+use finch_mcp::cache::verify::{print_report as print_report_verify, verify_cache};
+use finch_mcp::logging::{LogManager, LogPolicy, LogQuery};
+use finch_mcp::{status, output, Events, FinchMcpError};
+use std::collections::BTreeMap;
 use log::{info, error};
 
+#[cfg(unix)]
 fn check_cached_image_sync(cli: &Cli) -> Option<String> {
     use std::path::PathBuf;
     use finch_mcp::cache::ContentHasher;
@@ -15,69 +47,102 @@ fn check_cached_image_sync(cli: &Cli) -> Option<String> {
     let target = cli.get_target();
     let local_path = PathBuf::from(target);
     
-    // This is a simplified check - just look for the expected image name pattern
-    let content_hasher = ContentHasher::new();
-    if let Ok(content_hash) = content_hasher.hash_directory(&local_path) {
-        // Extract directory name for the image
-        let dir_name = local_path.file_name()?.to_str()?;
-        let image_name = format!("mcp-local-nodejs-{}-{}", 
-            dir_name.to_lowercase().replace(" ", ""),
-            &content_hash[0..8]
-        );
-        
-        // Check if this image exists by trying to run a quick finch command
-        let output = std::process::Command::new("finch")
-            .args(&["images", "-q", &image_name])
-            .output()
-            .ok()?;
-            
-        if output.status.success() && !output.stdout.is_empty() {
-            Some(image_name)
-        } else {
-            None
-        }
+    // A configured health check or buffering proxy needs the async session, so skip the exec shortcut
+    let finch_config = finch_mcp::core::finch_config::FinchConfig::load_from_dir(&local_path).ok().flatten();
+    if finch_config.is_some_and(|config| config.mcp.health_check || config.mcp.buffering) {
+        return None;
+    }
+    
+    // Only an image recorded for this content and these build options (which include the
+    // finch-mcp version) may be reused
+    let options = cli.to_local_containerize_options();
+    let cache_manager = CacheManager::new().ok()?;
+    let content_hash = ContentHasher::new().hash_directory(&local_path).ok()?;
+    let cache_key = cache_manager.generate_cache_key(&options.local_path, &content_hash, &options.build_options_hash());
+    let image_name = cache_manager.entries()
+        .find(|entry| cache_manager.generate_cache_key(&entry.source_path, &entry.content_hash, &entry.build_options_hash) == cache_key)?
+        .image_name
+        .clone();
+    
+    // Check if this image exists by trying to run a quick finch command
+    let output = std::process::Command::new("finch")
+        .args(["images", "-q", &image_name])
+        .output()
+        .ok()?;
+        
+    if output.status.success() && !output.stdout.is_empty() {
+        finch_mcp::stats::record_lookup(&options.local_path, Some(&image_name));
+        finch_mcp::cache::record_run(&image_name);
+        Some(image_name)
     } else {
         None
     }
 }
 
 fn main() -> anyhow::Result<()> {
+    finch_mcp::stats::mark_process_start();
+    
+    // Answer shell completion requests before anything touches stdout
+    CompleteEnv::with_factory(Cli::command).var(COMPLETE_VAR).complete();
+    
     // Parse CLI args and initialize logging
-    let cli = Cli::parse_and_init();
+    let mut cli = Cli::parse_and_init();
+    
+    // Set up before the runtime: the exporter's HTTP client blocks
+    let telemetry = match finch_mcp::telemetry::init(cli.otlp_endpoint.as_deref()) {
+        Ok(telemetry) => telemetry,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = cli.load_ca_certs() {
+        eprintln!("❌ {:#}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = finch_mcp::utils::volumes::check(&cli.volumes()) {
+        eprintln!("❌ {:#}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = cli.apply_target_scheme() {
+        eprintln!("❌ {:#}", e);
+        std::process::exit(1);
+    }
+    // An archive is unpacked here and built like a local directory from then on;
+    // generated client entries keep pointing at the archive itself
+    let (offline, refresh) = (cli.offline, cli.refresh());
+    if cli.target_mut().is_some() && !matches!(cli.command, Commands::Config { .. }) {
+        match cli.target_kind() {
+            TargetKind::Archive => {
+                let target = cli.target_mut().expect("checked above");
+                match finch_mcp::utils::archive_source::prepare(target, offline, refresh) {
+                    Ok(dir) => *target = dir.display().to_string(),
+                    Err(e) => {
+                        eprintln!("❌ {:#}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            TargetKind::Path if !std::path::Path::new(cli.get_target()).is_dir() => {
+                eprintln!("❌ {} is not a directory. Use --kind to run it as something else", cli.get_target());
+                std::process::exit(1);
+            }
+            _ => {}
+        }
+    }
     
     // Special handling for MCP mode - exec immediately before async runtime
-    if cli.is_mcp_client_context() && cli.is_local_directory() {
-        if let Commands::Run { .. } = &cli.command {
+    #[cfg(unix)]
+    if cli.is_mcp_client_context() && !cli.is_aggregate() && !cli.dry_run && !cli.force_rebuild() && !cli.needs_supervision() && cli.is_local_directory() {
+        if let Commands::Run { detach: false, .. } = &cli.command {
             // Try to check for cached image synchronously
             if let Some(image_name) = check_cached_image_sync(&cli) {
                 use std::os::unix::process::CommandExt;
                 
-                let mut cmd = std::process::Command::new("finch");
-                cmd.arg("run")
-                   .arg("--rm")
-                   .arg("-i")
-                   .arg("-e")
-                   .arg("MCP_ENABLED=true")
-                   .arg("-e")
-                   .arg("MCP_STDIO=true");
-                
-                if let Some(env_vars) = &cli.env {
-                    for env in env_vars {
-                        cmd.arg("-e").arg(env);
-                    }
-                }
+                let run_options = cli.stdio_run_options(image_name);
                 
-                if let Some(volumes) = &cli.volume {
-                    for volume in volumes {
-                        cmd.arg("-v").arg(volume);
-                    }
-                }
-                
-                if cli.host_network {
-                    cmd.arg("--network").arg("host");
-                }
-                
-                cmd.arg(&image_name);
+                let mut cmd = std::process::Command::new("finch");
+                cmd.args(run_options.finch_run_args());
                 
                 // Exec immediately before any async runtime
                 let _ = cmd.exec();
@@ -88,40 +153,168 @@ fn main() -> anyhow::Result<()> {
         }
     }
     
+    // Mention a newer release to people at a terminal, never to MCP clients or scripts
+    if matches!(cli.output_mode(), OutputMode::Normal | OutputMode::Verbose) && !matches!(cli.command, Commands::SelfCommand { .. } | Commands::Completions { .. }) {
+        notify_if_outdated(cli.offline);
+    }
+    
     // Run the async main
-    tokio::runtime::Runtime::new()?.block_on(async_main(cli))
+    let mcp_client = cli.is_mcp_client_context();
+    // One trace per invocation; gateway requests get their own
+    let span = tracing::info_span!("finch-mcp", version = env!("CARGO_PKG_VERSION"));
+    let result = tokio::runtime::Runtime::new()?.block_on(tracing::Instrument::instrument(async_main(cli), span));
+    // Flush spans before any exit below
+    drop(telemetry);
+    if let Err(e) = &result {
+        exit_with_container_status(e);
+        if mcp_client && finch_mcp::finch::vm::is_starting_in_background() {
+            finch_mcp::finch::vm::reply_to_client(&e.to_string());
+        }
+    }
+    result
+}
+
+/// Fail with `FinchNotInstalled` unless the `finch` CLI responds
+async fn ensure_finch_available(finch_client: &FinchClient) -> Result<(), FinchMcpError> {
+    if finch_client.is_finch_available().await? {
+        return Ok(());
+    }
+    error!("Finch is not installed or not available");
+    eprintln!("💡 finch-mcp can install it: finch-mcp setup finch");
+    Err(FinchMcpError::FinchNotInstalled)
+}
+
+/// Exit with the container's own code (or the signal's) so callers can tell failures apart
+fn exit_with_container_status(err: &anyhow::Error) {
+    match err.downcast_ref::<FinchMcpError>() {
+        Some(FinchMcpError::ContainerExited(exit)) => {
+            eprintln!("❌ {}", exit);
+            std::process::exit(exit.code);
+        }
+        Some(FinchMcpError::Interrupted(signal)) => std::process::exit(signal.exit_code()),
+        _ => {}
+    }
+    if let Some(exit) = err.downcast_ref::<ContainerExit>() {
+        eprintln!("❌ {}", exit);
+        std::process::exit(exit.code);
+    }
+    if let Some(Interrupted(signal)) = err.downcast_ref::<Interrupted>() {
+        std::process::exit(signal.exit_code());
+    }
 }
 
 async fn async_main(cli: Cli) -> anyhow::Result<()> {
+    if cli.dry_run && !matches!(cli.command, Commands::Gc { .. } | Commands::Upgrade { .. }) {
+        return plan_target(&cli).await;
+    }
+    if let Some(secs) = cli.lazy_vm {
+        if matches!(cli.command, Commands::Run { .. } | Commands::Serve { .. } | Commands::Test { .. } | Commands::Tools { .. } | Commands::Scan { .. } | Commands::Bench { .. } | Commands::Exec { .. } | Commands::Build { .. } | Commands::Up { .. } | Commands::Warm { .. }) {
+            finch_mcp::finch::vm::start_in_background(std::time::Duration::from_secs(secs));
+        }
+    }
     
     // Handle subcommands
     match &cli.command {
-        Commands::List { all } => {
+        Commands::List { all, sort } => {
             let finch_client = FinchClient::new();
-            if !finch_client.is_finch_available().await? {
-                error!("Finch is not installed or not available");
-                eprintln!("\n❌ Error: Finch is required but not found");
-                eprintln!("📥 Please install Finch from: https://runfinch.com/");
-                std::process::exit(1);
-            }
+            ensure_finch_available(&finch_client).await?;
             
-            finch_client.list_resources(*all).await?;
+            finch_mcp::core::list::print_list(*all, *sort).await
+        }
+        
+        Commands::Tag { target, name, remove } => {
+            use console::style;
+            use finch_mcp::cache::tags;
+            if let Some(name) = remove {
+                let tag = tags::remove_tag(name).await?;
+                status!("🗑️  Removed {} (from {}); {} is kept", name, tag.source, tag.image);
+            } else if let (Some(target), Some(name)) = (target, name) {
+                let (name, tag) = tags::add_tag(target, name).await?;
+                status!("🏷️  {} → {}", style(&name).cyan(), tag.image);
+                status!("   Moves to every new build of {}", tag.source);
+            } else {
+                let tags = tags::load()?;
+                if tags.is_empty() {
+                    status!("No friendly tags; add one with `finch-mcp tag <target> <name>`");
+                }
+                for (name, tag) in &tags {
+                    eprintln!("{}  {}  {}", style(name).cyan(), tag.image, style(&tag.source).dim());
+                }
+            }
+            Ok(())
+        }
+        
+        Commands::Login { registry, username, password_stdin, list } => {
+            use console::style;
+            use finch_mcp::finch::login;
+            if *list {
+                let registries = login::load()?;
+                if registries.is_empty() {
+                    status!("Not logged in to any registry; log in with `finch-mcp login <registry>`");
+                }
+                let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+                for (registry, record) in &registries {
+                    let age = finch_mcp::utils::units::format_age(now.saturating_sub(record.logged_in_at));
+                    let user = record.username.as_deref().map(|user| format!(" as {}", user)).unwrap_or_default();
+                    eprintln!("{}{}  {}", style(registry).cyan(), user, style(format!("{} ago", age)).dim());
+                }
+                return Ok(());
+            }
+            let registry = login::login(registry.as_deref().unwrap_or(login::DEFAULT_REGISTRY), username.as_deref(), *password_stdin, cli.offline).await?;
+            status!("🔑 Logged in to {}; image targets from it can be pulled now", style(registry).cyan());
             Ok(())
         }
         
-        Commands::Cleanup { all, containers, images, force } => {
+        Commands::Ps { all } => {
             let finch_client = FinchClient::new();
-            if !finch_client.is_finch_available().await? {
-                error!("Finch is not installed or not available");
-                eprintln!("\n❌ Error: Finch is required but not found");
-                eprintln!("📥 Please install Finch from: https://runfinch.com/");
-                std::process::exit(1);
+            ensure_finch_available(&finch_client).await?;
+            
+            Ok(finch_client.print_mcp_containers(*all).await?)
+        }
+        
+        Commands::Attach { name } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            
+            Ok(finch_client.attach_container(name).await?)
+        }
+        
+        Commands::Stop { names } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            
+            Ok(finch_client.stop_containers(names).await?)
+        }
+        
+        Commands::Cleanup { all, containers, images, older_than, keep_latest, dangling, workspace, force } => {
+            if *workspace || *all {
+                use console::style;
+                let report = finch_mcp::workspace::reap(std::time::Duration::ZERO)?;
+                if report.removed > 0 {
+                    eprintln!("{} Removed {} scratch director{} left by interrupted runs ({:.1} MB)",
+                        style("🧹").green(), report.removed, if report.removed == 1 { "y" } else { "ies" }, report.freed_bytes as f64 / (1024.0 * 1024.0));
+                } else {
+                    eprintln!("{} No scratch directories to clean up", style("✅").green());
+                }
+                let others = *containers || *images || older_than.is_some() || keep_latest.is_some() || *dangling;
+                if !*all && !others {
+                    return Ok(());
+                }
             }
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
             
-            finch_client.cleanup_resources(*all, *containers, *images, *force).await?;
+            let policy = RetentionPolicy { max_age_secs: *older_than, keep_latest: *keep_latest };
+            finch_client.cleanup_resources(*all, *containers, *images, &policy, *dangling, *force).await?;
             Ok(())
         }
         
+        Commands::Gc { max_age, max_images_per_source, max_total_size } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            handle_gc_command(*max_age, *max_images_per_source, *max_total_size, cli.dry_run).await
+        }
+        
         Commands::Cache { action } => {
             handle_cache_command(action).await?;
             Ok(())
@@ -132,20 +325,201 @@ async fn async_main(cli: Cli) -> anyhow::Result<()> {
             Ok(())
         }
         
+        Commands::Inspect { target, json, sbom } => {
+            let provenance = inspect(target).await?;
+            if *sbom {
+                // Most recently built match first
+                let latest = provenance.iter().max_by_key(|image| image.entry.created_at).expect("inspect returns at least one image");
+                let path = latest.sbom.as_ref().ok_or_else(|| anyhow::anyhow!(
+                    "No SBOM stored for {}; generate one with `finch-mcp build --sbom {}`", latest.entry.image_name, latest.entry.source_path
+                ))?;
+                output::print_payload(std::fs::read_to_string(path)?.trim_end());
+            } else if *json {
+                let details: Vec<_> = provenance.iter().map(|image| image.to_json()).collect();
+                output::print_payload(serde_json::to_string_pretty(&details)?);
+            } else {
+                for image in &provenance {
+                    image.print();
+                    eprintln!();
+                }
+            }
+            Ok(())
+        }
+        
+        Commands::Report { target, logs, output } => {
+            use console::style;
+            let path = output.clone().unwrap_or_else(report::default_path);
+            let report = write_report(target.as_deref(), *logs, &path).await?;
+            eprintln!("{} Wrote {} with:", style("📦").green(), style(report.path.display()).cyan());
+            for file in &report.files {
+                eprintln!("  • {}", file);
+            }
+            eprintln!("Secrets, URL credentials and your home directory are masked; review it before attaching it to an issue");
+            Ok(())
+        }
+        
+        Commands::Stats { json, reset } => {
+            if *reset {
+                finch_mcp::stats::reset()?;
+                status!("🗑️  Usage statistics deleted");
+                return Ok(());
+            }
+            let (total, servers) = finch_mcp::stats::collect()?;
+            if *json {
+                output::print_payload(serde_json::to_string_pretty(&finch_mcp::stats::to_json(&total, &servers))?);
+            } else {
+                finch_mcp::stats::print(&total, &servers);
+            }
+            Ok(())
+        }
+        
+        Commands::Vm { action } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            handle_vm_command(&finch_client, action).await
+        }
+        
+        Commands::Setup { action: SetupCommands::Finch { yes } } => {
+            setup_finch(*yes, cli.offline).await
+        }
+        
+        Commands::SelfCommand { action: SelfCommands::Update { check } } => {
+            self_update(*check, cli.offline).await
+        }
+        
+        Commands::Completions { shell } => {
+            write_script(*shell, &mut std::io::stdout())?;
+            Ok(())
+        }
+        
         Commands::Build { .. } => {
             build_target(&cli).await
         }
         
+        Commands::Config { action: ConfigCommands::Generate { .. } } => {
+            generate_client_config(&cli)
+        }
+        
+        Commands::Warm { targets, file, profile, jobs } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            handle_warm_command(&cli, targets, file.as_deref(), profile, *jobs).await
+        }
+        
+        Commands::Up { servers, file, profile, jobs } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            
+            let config = servers_up(file.as_deref(), profile, servers, &cli.build_defaults(), *jobs).await?;
+            
+            eprintln!("\n{} MCP Server Configuration:", console::style("📋").blue());
+            eprintln!("{}", console::style("Add this to your MCP client configuration:").dim());
+            eprintln!("{}", serde_json::to_string_pretty(&config)?);
+            Ok(())
+        }
+        
+        Commands::Upgrade { target, .. } => {
+            let finch_client = FinchClient::new();
+            if !cli.dry_run {
+                ensure_finch_available(&finch_client).await?;
+            }
+            let upgrades = upgrade(target.as_deref(), cli.dry_run, &cli.build_defaults()).await?;
+            print_upgrade_report(&upgrades);
+            if upgrades.iter().any(|upgrade| matches!(upgrade.status, UpgradeStatus::Failed(_))) {
+                anyhow::bail!("Some servers could not be upgraded");
+            }
+            Ok(())
+        }
+        
+        Commands::Down { servers, file, profile } => {
+            servers_down(file.as_deref(), profile, servers).await
+        }
+        
+        Commands::Serve { port, bind, .. } => {
+            status!("Finch-MCP v{}", env!("CARGO_PKG_VERSION"));
+            status!("-------------------------------");
+            
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            serve_target(&cli, bind, *port).await
+        }
+        
+        Commands::Test { timeout, json, .. } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            test_target(&cli, *timeout, *json).await
+        }
+        
+        Commands::Tools { timeout, json, .. } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            
+            let run_options = stdio_run_options(&cli).await?;
+            finch_client.ensure_vm_running_fast().await?;
+            let tools = list_container_tools(&run_options, std::time::Duration::from_secs(*timeout)).await?;
+            
+            if *json {
+                output::print_payload(serde_json::to_string_pretty(&tools)?);
+            } else {
+                print_tools(&tools);
+            }
+            Ok(())
+        }
+        
+        Commands::Bench { iterations, cold, timeout, json, .. } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            
+            let target = if cli.is_direct_container() {
+                BenchTarget::Image(cli.get_target().to_string())
+            } else if cli.is_git_repository() {
+                BenchTarget::Git(cli.to_git_containerize_options())
+            } else if cli.is_local_directory() {
+                BenchTarget::Local(cli.to_local_containerize_options())
+            } else {
+                BenchTarget::Command(cli.to_auto_containerize_options())
+            };
+            let run_options = cli.stdio_run_options(String::new());
+            finch_client.ensure_vm_running_fast().await?;
+            let report = bench(&target, run_options, *iterations, *cold, std::time::Duration::from_secs(*timeout)).await?;
+            
+            if *json {
+                output::print_payload(serde_json::to_string_pretty(&report.to_json())?);
+            } else {
+                print_bench_report(&report);
+            }
+            Ok(())
+        }
+        
+        Commands::Scan { scanner, fail_on, json, .. } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            scan_target(&cli, *scanner, *fail_on, *json).await
+        }
+        
+        Commands::Exec { command, .. } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            exec_target(&cli, command).await
+        }
+        
+        Commands::Run { detach: true, name, .. } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            detach_target(&cli, name.as_deref()).await
+        }
+        
+        Commands::Run { aggregate: true, .. } => {
+            let finch_client = FinchClient::new();
+            ensure_finch_available(&finch_client).await?;
+            aggregate_targets(&cli).await
+        }
+        
         Commands::Run { .. } => {
             // For direct container mode or MCP STDIO mode, skip banner and do minimal setup
             if cli.is_direct_container() || cli.is_mcp_client_context() {
                 let finch_client = FinchClient::new();
-                if !finch_client.is_finch_available().await? {
-                    error!("Finch is not installed or not available");
-                    eprintln!("\n❌ Error: Finch is required but not found");
-                    eprintln!("📥 Please install Finch from: https://runfinch.com/");
-                    std::process::exit(1);
-                }
+                ensure_finch_available(&finch_client).await?;
                 run_target(&cli).await
             } else {
                 // Non-direct, non-MCP mode - show banner and full setup
@@ -153,10 +527,11 @@ async fn async_main(cli: Cli) -> anyhow::Result<()> {
                 status!("-------------------------------");
                 
                 let finch_client = FinchClient::new();
-                if !finch_client.is_finch_available().await? {
+                if !finch_client.is_finch_available().await? && !offer_install(cli.offline).await? {
                     error!("Finch is not installed or not available");
                     eprintln!("\n❌ Error: Finch is required but not found");
                     eprintln!("📥 Please install Finch from: https://runfinch.com/");
+                    eprintln!("💡 Or let finch-mcp install it: finch-mcp setup finch");
                     eprintln!("💡 Finch is a container runtime that enables finch-mcp to run MCP servers");
                     std::process::exit(1);
                 }
@@ -169,23 +544,23 @@ async fn async_main(cli: Cli) -> anyhow::Result<()> {
 
 async fn build_target(cli: &Cli) -> anyhow::Result<()> {
     match &cli.command {
-        Commands::Build { target: _, args: _ } => {
+        Commands::Build { sbom, .. } => {
             // Determine the type of target
-            if cli.is_git_repository() {
+            let image_name = if cli.is_git_repository() {
                 // Git repository - clone and build
-                let git_options = cli.to_git_containerize_options();
-                let image_name = git_build(git_options).await?;
-                status!("\n✅ Build complete: {}", image_name);
+                git_build(cli.to_git_containerize_options()).await?
             } else if cli.is_local_directory() {
                 // Local directory - build from local source
-                let local_options = cli.to_local_containerize_options();
-                let image_name = local_build(local_options).await?;
-                status!("\n✅ Build complete: {}", image_name);
+                local_build(cli.to_local_containerize_options()).await?
             } else {
                 // Command - auto-containerize
-                let auto_options = cli.to_auto_containerize_options();
-                let image_name = auto_build(auto_options).await?;
-                status!("\n✅ Build complete: {}", image_name);
+                auto_build(cli.to_auto_containerize_options()).await?
+            };
+            status!("\n✅ Build complete: {}", image_name);
+            
+            if let Some(format) = sbom {
+                let path = generate_sbom(&image_name, *format).await?;
+                status!("📄 {} SBOM: {}", format, path.display());
             }
             Ok(())
         }
@@ -193,6 +568,316 @@ async fn build_target(cli: &Cli) -> anyhow::Result<()> {
     }
 }
 
+/// Print the target's client entry in the layout `--client` expects, or add it to the client's config file
+fn generate_client_config(cli: &Cli) -> anyhow::Result<()> {
+    use anyhow::Context;
+    
+    let Commands::Config { action: ConfigCommands::Generate { target, client, name, write, .. } } = &cli.command else {
+        unreachable!()
+    };
+    let mut entry = cli.client_entry();
+    let mut mode = cli.config_mode.unwrap_or_default();
+    if entry.source.is_none() {
+        // A prebuilt image is run as named
+        mode = ConfigMode::Image;
+    } else if mode != ConfigMode::Source {
+        let cache_key = if cli.is_git_repository() || cli.is_local_directory() {
+            target.clone()
+        } else {
+            let options = cli.to_auto_containerize_options();
+            format!("{} {}", options.command, options.args.join(" "))
+        };
+        let cache_manager = CacheManager::new()?;
+        entry.image_name = cache_manager.find_entries(&cache_key)
+            .into_iter()
+            .max_by_key(|cached| cached.created_at)
+            .map(|cached| cached.image_name.clone())
+            .with_context(|| format!("No image has been built for {} yet; build it with `finch-mcp build` or use --config-mode source", target))?;
+    }
+    
+    let name = name.clone().unwrap_or_else(|| client_config::server_name(target));
+    let server = entry.to_json(mode);
+    let Some(write) = write else {
+        output::print_payload(serde_json::to_string_pretty(&client.document(&name, server))?);
+        return Ok(());
+    };
+    
+    let path = write.clone().or_else(|| client.config_path())
+        .context("--client generic has no config file of its own; use --write=PATH")?;
+    let replaced = client_config::write_client_config(&path, *client, &name, server)?;
+    status!("✅ {} \"{}\" in {}", if replaced { "Updated" } else { "Added" }, name, path.display());
+    Ok(())
+}
+
+async fn handle_warm_command(cli: &Cli, targets: &[String], file: Option<&std::path::Path>, profiles: &[String], jobs: usize) -> anyhow::Result<()> {
+    let mut warm_targets: Vec<WarmTarget> = targets.iter().map(|target| WarmTarget::from_target(target)).collect();
+    if targets.is_empty() || file.is_some() {
+        warm_targets.extend(manifest_targets(file, profiles)?);
+    }
+    if warm_targets.is_empty() {
+        status!("ℹ No servers to warm");
+        return Ok(());
+    }
+    let total = warm_targets.len();
+    let outcomes = warm(warm_targets, jobs, &cli.build_defaults()).await;
+    let failed: Vec<_> = outcomes.iter().filter(|outcome| outcome.result.is_err()).collect();
+    status!("\n🔥 Warmed {} of {} target(s)", total - failed.len(), total);
+    if !failed.is_empty() {
+        let names: Vec<&str> = failed.iter().map(|outcome| outcome.name.as_str()).collect();
+        anyhow::bail!("Failed to build: {}", names.join(", "));
+    }
+    Ok(())
+}
+
+async fn handle_gc_command(max_age: Option<u64>, max_images_per_source: Option<usize>, max_total_size: Option<u64>, dry_run: bool) -> anyhow::Result<()> {
+    let mut limits = GcPolicy::load()?.limits()?;
+    if max_age.is_some() {
+        limits.retention.max_age_secs = max_age;
+    }
+    if max_images_per_source.is_some() {
+        limits.retention.keep_latest = max_images_per_source;
+    }
+    if max_total_size.is_some() {
+        limits.max_total_bytes = max_total_size;
+    }
+    
+    if limits.is_empty() {
+        let config = finch_mcp::paths::user_config().map(|path| path.display().to_string()).unwrap_or_else(|| "the finch-mcp config".to_string());
+        status!("ℹ No retention policy set. Add a `gc` section to {} or pass --max-age, --max-images-per-source or --max-total-size", config);
+        return Ok(());
+    }
+    
+    status!("🧹 Collecting cached images...");
+    let report = collect(&limits, None, dry_run).await?;
+    print_report(&report, dry_run);
+    Ok(())
+}
+
+/// Print what `run` or `build` would do for the target, without touching Finch
+async fn plan_target(cli: &Cli) -> anyhow::Result<()> {
+    let is_run = match &cli.command {
+        Commands::Run { aggregate: false, .. } => true,
+        Commands::Build { .. } => false,
+        _ => anyhow::bail!("--dry-run is only supported by `run` and `build`"),
+    };
+    if cli.is_direct_container() {
+        anyhow::bail!("Nothing to plan: {} is an existing image", cli.get_target());
+    }
+    
+    let plan = if cli.is_git_repository() {
+        plan_git(&cli.to_git_containerize_options()).await?
+    } else if cli.is_local_directory() {
+        plan_local(&cli.to_local_containerize_options())?
+    } else {
+        plan_auto(&cli.to_auto_containerize_options())?
+    };
+    
+    let plan = if is_run {
+        plan.with_run(cli.stdio_run_options(String::new()))
+    } else {
+        plan
+    };
+    plan.print();
+    Ok(())
+}
+
+/// Build (or reuse) the image for a run/serve/test target and describe how to run it
+async fn stdio_run_options(cli: &Cli) -> anyhow::Result<StdioRunOptions> {
+    let image_name = if cli.is_direct_container() {
+        let image = cli.get_target();
+        let finch_client = FinchClient::new();
+        finch_client.ensure_vm_running_fast().await?;
+        finch_mcp::finch::pull::ensure_image(&finch_client, image, cli.pull.unwrap_or_default(), cli.offline, &build_policy(None, None)).await?;
+        image.to_string()
+    } else if cli.is_git_repository() {
+        git_build(cli.to_git_containerize_options()).await?
+    } else if cli.is_local_directory() {
+        local_build(cli.to_local_containerize_options()).await?
+    } else {
+        auto_build(cli.to_auto_containerize_options()).await?
+    };
+    
+    Ok(cli.stdio_run_options(image_name))
+}
+
+async fn serve_target(cli: &Cli, bind: &str, port: u16) -> anyhow::Result<()> {
+    use console::style;
+    
+    let ip: std::net::IpAddr = bind.parse()
+        .map_err(|_| anyhow::anyhow!("Invalid bind address: {}", bind))?;
+    let addr = std::net::SocketAddr::new(ip, port);
+    if !ip.is_loopback() {
+        eprintln!("{} Binding to {}: anyone who can reach this address can use the server and whatever it can access",
+            style("⚠️").yellow(), ip);
+    }
+    
+    let run_options = stdio_run_options(cli).await?;
+    
+    FinchClient::new().ensure_vm_running_fast().await?;
+    
+    status!("\n🌐 Serving {} over HTTP", style(&run_options.image_name).cyan());
+    status!("   Streamable HTTP: {}", style(format!("http://{}/mcp", addr)).yellow());
+    status!("   SSE:             {}", style(format!("http://{}/sse", addr)).yellow());
+    status!("   Press Ctrl+C to stop\n");
+    
+    serve_stdio_container(&run_options, addr).await
+}
+
+/// Build or reuse the image, start it in the background and print how to connect
+async fn detach_target(cli: &Cli, name: Option<&str>) -> anyhow::Result<()> {
+    use console::style;
+    
+    let run_options = stdio_run_options(cli).await?;
+    let name = name.map(str::to_string).unwrap_or_else(|| detached_name(&run_options.image_name));
+    
+    let finch_client = FinchClient::new();
+    finch_client.ensure_vm_running_fast().await?;
+    finch_client.run_detached(&run_options, &name).await?;
+    
+    let entry = serde_json::json!({ "mcpServers": { &name: { "command": "finch-mcp", "args": ["attach", &name] } } });
+    eprintln!("{} Started {} in the background ({})", style("✅").green(), style(&name).cyan().bold(), run_options.image_name);
+    eprintln!("\nConnect an MCP client with:\n{}", serde_json::to_string_pretty(&entry)?);
+    eprintln!("\nOne client at a time; the server keeps its state between clients.");
+    eprintln!("Status: finch-mcp ps    Logs: finch-mcp logs follow {}    Stop: finch-mcp stop {}", name, name);
+    Ok(())
+}
+
+/// Run a shell or command in the target's running container, or in a new one from its image
+async fn exec_target(cli: &Cli, command: &[String]) -> anyhow::Result<()> {
+    use console::style;
+    
+    let run_options = stdio_run_options(cli).await?;
+    
+    let finch_client = FinchClient::new();
+    finch_client.ensure_vm_running_fast().await?;
+    
+    let containers = finch_client.running_mcp_containers().await?;
+    let running = containers.iter().find(|container| container.image == run_options.image_name);
+    match running {
+        Some(container) => status!("{} Running in container {}", style("🐚").blue(), style(&container.name).cyan()),
+        None => status!("{} Running in a new container of {}", style("🐚").blue(), style(&run_options.image_name).cyan()),
+    }
+    
+    Ok(finch_client.exec_in_container(&run_options, running.map(|container| container.name.as_str()), command).await?)
+}
+
+async fn test_target(cli: &Cli, timeout_secs: u64, json: bool) -> anyhow::Result<()> {
+    use console::style;
+    
+    let run_options = stdio_run_options(cli).await?;
+    
+    FinchClient::new().ensure_vm_running_fast().await?;
+    
+    status!("\n🧪 Testing {}\n", style(&run_options.image_name).cyan());
+    let report = test_stdio_container(&run_options, std::time::Duration::from_secs(timeout_secs)).await?;
+    
+    if json {
+        output::print_payload(serde_json::to_string_pretty(&report.to_json())?);
+    } else {
+        report.print();
+    }
+    
+    if report.passed() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("{} conformance check(s) failed", report.failures()))
+    }
+}
+
+async fn scan_target(cli: &Cli, scanner: Option<Scanner>, fail_on: Option<Severity>, json: bool) -> anyhow::Result<()> {
+    use console::style;
+    
+    let finch_client = FinchClient::new();
+    finch_client.ensure_vm_running_fast().await?;
+    
+    // An image finch already has (e.g. one built by finch-mcp) is scanned as-is
+    let target = cli.get_target();
+    let image = if finch_client.image_exists(target).await? {
+        target.to_string()
+    } else if cli.is_direct_container() {
+        finch_mcp::finch::pull::ensure_image(&finch_client, target, cli.pull.unwrap_or_default(), cli.offline, &build_policy(None, None)).await?;
+        target.to_string()
+    } else {
+        stdio_run_options(cli).await?.image_name
+    };
+    
+    status!("\n🛡️  Scanning {}\n", style(&image).cyan());
+    let report = scan_image(&image, scanner).await?;
+    
+    if json {
+        output::print_payload(serde_json::to_string_pretty(&report.to_json())?);
+    } else {
+        report.print();
+    }
+    
+    match fail_on.map(|threshold| (threshold, report.count_at_least(threshold))) {
+        Some((threshold, count)) if count > 0 => Err(anyhow::anyhow!("{} finding(s) at {} severity or above", count, threshold)),
+        _ => Ok(()),
+    }
+}
+
+async fn aggregate_targets(cli: &Cli) -> anyhow::Result<()> {
+    let specs: Vec<&str> = std::iter::once(cli.get_target())
+        .chain(cli.get_args().iter().map(String::as_str))
+        .collect();
+    
+    let (mut names, targets): (Vec<String>, Vec<String>) = specs.iter()
+        .map(|spec| parse_aggregate_target(spec))
+        .unzip();
+    dedupe_names(&mut names);
+    
+    // Global -e/-v/--allow-path/--network apply to every server
+    let env: BTreeMap<String, String> = cli.env_vars().iter()
+        .map(|var| match var.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (var.clone(), std::env::var(var).unwrap_or_default()),
+        })
+        .collect();
+    let network = cli.network_mode();
+    
+    let base_dir = std::env::current_dir()?;
+    let mut servers = Vec::with_capacity(targets.len());
+    for (name, target) in names.into_iter().zip(targets) {
+        let spec = ServerSpec {
+            target,
+            env: env.clone(),
+            volumes: cli.volumes(),
+            network: Some(network.to_string()),
+            direct: cli.is_direct_container(),
+            forward_registry: cli.forward_registry,
+            ..Default::default()
+        };
+        let source = spec.source(&base_dir);
+        let image_name = build_server(&spec, &source, &cli.build_defaults())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to prepare server '{}': {}", name, e))?;
+        
+        servers.push(AggregatedServer {
+            name,
+            run_options: StdioRunOptions {
+                image_name,
+                env_vars: spec.env_vars(),
+                volumes: spec.volume_mounts(),
+                network: offline::network(cli.offline, &network),
+                publish: Vec::new(),
+                args: spec.run_args(&source),
+                // Images run directly weren't built to run as the host user
+                user_map: if matches!(source, ServerSource::Image(_)) { UserMap::Never } else { cli.user_map_mode() },
+                health_check: cli.health_check,
+                startup_timeout: cli.startup_timeout(),
+                trace_mcp: None,
+                forward_proxy: cli.forward_proxy,
+                no_forward_timezone: cli.no_forward_timezone,
+                restart: cli.restart.unwrap_or_default(),
+                events: Events::default(),
+            },
+        });
+    }
+    
+    info!("Aggregating {} MCP servers", servers.len());
+    run_aggregator(servers, cli.trace_mcp.as_deref()).await
+}
+
 async fn run_target(cli: &Cli) -> anyhow::Result<()> {
     let is_mcp_context = cli.is_mcp_client_context();
     
@@ -247,6 +932,82 @@ async fn run_target(cli: &Cli) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Handle VM-related commands
+async fn handle_vm_command(finch_client: &FinchClient, action: &VmCommands) -> anyhow::Result<()> {
+    use console::style;
+    use finch_mcp::finch::vm::{apply_resources, configured_resources, finch_config_path, remove_vm, stop_vm, vm_state, VmState};
+    
+    if std::env::consts::OS == "linux" {
+        eprintln!("{} Finch runs containers natively on Linux; there is no VM to manage", style("ℹ️").blue());
+        return Ok(());
+    }
+    
+    let config_path = finch_config_path()?;
+    match action {
+        VmCommands::Status => {
+            let state = vm_state().await?;
+            let resources = configured_resources(&config_path);
+            eprintln!("{} Finch VM: {}", style("🖥️").blue(), style(&state).cyan());
+            eprintln!("  CPUs:   {}", resources.cpus.map(|cpus| cpus.to_string()).unwrap_or_else(|| "Finch default".to_string()));
+            eprintln!("  Memory: {}", resources.memory.unwrap_or_else(|| "Finch default".to_string()));
+            eprintln!("  Config: {}", config_path.display());
+            if state == VmState::Nonexistent {
+                eprintln!("Run {} to create it", style("finch-mcp vm init").cyan());
+            }
+        }
+        
+        VmCommands::Start => {
+            finch_client.ensure_vm_running().await?;
+            eprintln!("{} Finch VM is running", style("✅").green());
+        }
+        
+        VmCommands::Stop { force } => {
+            if vm_state().await? != VmState::Running {
+                eprintln!("{} Finch VM is not running", style("ℹ️").blue());
+                return Ok(());
+            }
+            stop_vm(*force).await?;
+            eprintln!("{} Finch VM stopped", style("✅").green());
+        }
+        
+        VmCommands::Init { resources } => {
+            if vm_state().await? != VmState::Nonexistent {
+                eprintln!("{} Finch VM already exists", style("ℹ️").blue());
+                if !resources.to_resources().is_empty() {
+                    eprintln!("Use {} to apply new CPUs or memory", style("finch-mcp vm recreate").cyan());
+                }
+                return Ok(());
+            }
+            if !resources.to_resources().is_empty() {
+                apply_resources(&config_path, &resources.to_resources())?;
+            }
+            finch_client.initialize_vm().await?;
+        }
+        
+        VmCommands::Recreate { resources, force } => {
+            if !force {
+                eprintln!("{} This deletes the Finch VM with all of its containers and images", style("⚠️").yellow());
+                eprintln!("Run with {} to proceed", style("--force").cyan());
+                return Ok(());
+            }
+            
+            let state = vm_state().await?;
+            if state == VmState::Running {
+                stop_vm(true).await?;
+            }
+            if state != VmState::Nonexistent {
+                remove_vm().await?;
+            }
+            if !resources.to_resources().is_empty() {
+                apply_resources(&config_path, &resources.to_resources())?;
+            }
+            finch_client.initialize_vm().await?;
+            eprintln!("{} Finch VM recreated. Cached images must be rebuilt; run {}", style("✅").green(), style("finch-mcp cache clear --force").cyan());
+        }
+    }
+    Ok(())
+}
+
 /// Handle cache-related commands
 async fn handle_cache_command(action: &CacheCommands) -> anyhow::Result<()> {
     use console::style;
@@ -256,20 +1017,34 @@ async fn handle_cache_command(action: &CacheCommands) -> anyhow::Result<()> {
             let cache_manager = CacheManager::new()?;
             let stats = cache_manager.get_stats();
             
-            println!("\n{} Cache Statistics", style("📊").blue());
-            println!("Total cached images: {}", style(stats.total_entries).cyan());
-            println!("Estimated disk usage: {:.1} MB", style(stats.estimated_size_bytes as f64 / 1024.0 / 1024.0).yellow());
+            eprintln!("\n{} Cache Statistics", style("📊").blue());
+            eprintln!("Total cached images: {}", style(stats.total_entries).cyan());
+            eprintln!("Estimated disk usage: {:.1} MB", style(stats.estimated_size_bytes as f64 / 1024.0 / 1024.0).yellow());
             
             if !stats.project_types.is_empty() {
-                println!("\nCached images by type:");
+                eprintln!("\nCached images by type:");
                 for (project_type, count) in stats.project_types {
-                    println!("  {}: {}", style(&project_type).green(), style(count).cyan());
+                    eprintln!("  {}: {}", style(&project_type).green(), style(count).cyan());
+                }
+            }
+            
+            // Most recently run first; images never run sort last, ready to prune
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let mut entries: Vec<_> = cache_manager.entries().collect();
+            entries.sort_by_key(|entry| std::cmp::Reverse((entry.last_run, entry.run_count)));
+            if !entries.is_empty() {
+                eprintln!("\nUsage:");
+                for entry in entries {
+                    eprintln!("  {}  {}  {}", style(&entry.image_name).cyan(), entry.usage_label(now), style(&entry.source_path).dim());
                 }
             }
             
             if stats.total_entries == 0 {
-                println!("{} No cached images found", style("ℹ️").blue());
-                println!("Run some projects to build up the cache!");
+                eprintln!("{} No cached images found", style("ℹ️").blue());
+                eprintln!("Run some projects to build up the cache!");
             }
         }
         
@@ -278,19 +1053,19 @@ async fn handle_cache_command(action: &CacheCommands) -> anyhow::Result<()> {
             let stats = cache_manager.get_stats();
             
             if stats.total_entries == 0 {
-                println!("{} Cache is already empty", style("✅").green());
+                eprintln!("{} Cache is already empty", style("✅").green());
                 return Ok(());
             }
             
             if !force {
-                println!("{} This will remove {} cached images", style("⚠️").yellow(), stats.total_entries);
-                println!("Run with {} to proceed", style("--force").cyan());
+                eprintln!("{} This will remove {} cached images", style("⚠️").yellow(), stats.total_entries);
+                eprintln!("Run with {} to proceed", style("--force").cyan());
                 return Ok(());
             }
             
             cache_manager.clear_cache()?;
-            println!("{} Cleared all {} cached images", style("🗑️").green(), stats.total_entries);
-            println!("Note: Container images may still exist in Finch. Use {} to remove them.", style("finch-mcp cleanup").cyan());
+            eprintln!("{} Cleared all {} cached images", style("🗑️").green(), stats.total_entries);
+            eprintln!("Note: Container images may still exist in Finch. Use {} to remove them.", style("finch-mcp cleanup").cyan());
         }
         
         CacheCommands::Cleanup { max_age } => {
@@ -298,9 +1073,31 @@ async fn handle_cache_command(action: &CacheCommands) -> anyhow::Result<()> {
             let removed_count = cache_manager.cleanup_old_entries(*max_age).await?;
             
             if removed_count > 0 {
-                println!("{} Cleaned up {} old cache entries", style("🧹").green(), removed_count);
+                eprintln!("{} Cleaned up {} old cache entries", style("🧹").green(), removed_count);
             } else {
-                println!("{} No old cache entries to clean up", style("✅").green());
+                eprintln!("{} No old cache entries to clean up", style("✅").green());
+            }
+        }
+        
+        CacheCommands::Export { output, targets } => {
+            let manifest = export_cache(output, targets).await?;
+            eprintln!("{} Exported {} cached image(s) to {}", style("✅").green(), manifest.entries.len(), style(output.display()).cyan());
+            eprintln!("Restore them elsewhere with {}", style(format!("finch-mcp cache import {}", output.display())).cyan());
+        }
+        
+        CacheCommands::Verify { fix } => {
+            let report = verify_cache(*fix).await?;
+            print_report_verify(&report, *fix);
+            if report.problems() > report.fixed {
+                std::process::exit(1);
+            }
+        }
+        
+        CacheCommands::Import { archive } => {
+            let manifest = import_cache(archive).await?;
+            eprintln!("{} Imported {} cached image(s):", style("✅").green(), manifest.entries.len());
+            for entry in &manifest.entries {
+                eprintln!("  • {} ({})", style(&entry.image_name).cyan(), entry.source_path);
             }
         }
     }
@@ -318,58 +1115,119 @@ async fn handle_log_command(action: &LogCommands) -> anyhow::Result<()> {
             let logs = log_manager.list_recent_logs(*limit)?;
             
             if logs.is_empty() {
-                println!("{} No build logs found", style("ℹ️").blue());
-                println!("Build logs will appear here after container builds");
+                eprintln!("{} No build logs found", style("ℹ️").blue());
+                eprintln!("Build logs will appear here after container builds");
                 return Ok(());
             }
             
-            println!("\n{} Recent Build Logs", style("📄").blue());
-            println!();
+            eprintln!("\n{} Recent Build Logs", style("📄").blue());
+            eprintln!();
             
             for log_entry in logs {
                 let time_str = log_entry.created_at.format("%Y-%m-%d %H:%M:%S UTC");
-                println!("{} {} {} ({})", 
+                eprintln!("{} {} {} ({})", 
                     style("📁").blue(),
                     style(&log_entry.filename).cyan(),
                     style(&log_entry.operation_type).green(),
                     style(time_str).dim()
                 );
-                println!("   {}", style(&log_entry.identifier).dim());
+                eprintln!("   {}", style(&log_entry.identifier).dim());
             }
             
-            println!();
-            println!("Use {} to view a specific log", style("finch-mcp logs show <filename>").cyan());
+            eprintln!();
+            eprintln!("Use {} to view a specific log", style("finch-mcp logs show <filename>").cyan());
         }
         
         LogCommands::Show { filename } => {
             let log_manager = LogManager::new()?;
-            let log_path = log_manager.get_logs_directory_path().join(filename);
-            
-            if !log_path.exists() {
+            let Some(content) = log_manager.read_log(filename)? else {
                 eprintln!("{} Log file not found: {}", style("❌").red(), filename);
                 eprintln!("Use {} to see available logs", style("finch-mcp logs list").cyan());
                 return Ok(());
-            }
+            };
             
-            let content = std::fs::read_to_string(&log_path)?;
-            println!("{}", content);
+            output::print_payload(content.trim_end());
         }
         
         LogCommands::Cleanup { max_age } => {
             let log_manager = LogManager::new()?;
             let removed_count = log_manager.cleanup_old_logs(*max_age)?;
+            let rotation = log_manager.rotate(&LogPolicy::load()?)?;
             
-            if removed_count > 0 {
-                println!("{} Cleaned up {} old log files", style("🧹").green(), removed_count);
+            if removed_count + rotation.removed > 0 {
+                eprintln!("{} Cleaned up {} old log files", style("🧹").green(), removed_count + rotation.removed);
             } else {
-                println!("{} No old log files to clean up", style("✅").green());
+                eprintln!("{} No old log files to clean up", style("✅").green());
+            }
+            if rotation.compressed > 0 {
+                eprintln!("{} Compressed {} log files", style("🗜").green(), rotation.compressed);
+            }
+        }
+        
+        LogCommands::Search { source, failed, since, run, json } => {
+            let query = LogQuery {
+                source: source.clone(),
+                failed: *failed,
+                since: since.map(|secs| chrono::Utc::now() - chrono::Duration::seconds(secs as i64)),
+                run: run.clone(),
+            };
+            let matches = LogManager::new()?.search(&query)?;
+            
+            if *json {
+                let results: Vec<serde_json::Value> = matches.iter().map(|(entry, summary)| serde_json::json!({
+                    "filename": entry.filename,
+                    "path": entry.path,
+                    "startedAt": entry.created_at.to_rfc3339(),
+                    "source": summary.source.as_deref().unwrap_or(&entry.identifier),
+                    "runId": summary.run_id.as_ref().or(entry.run_id.as_ref()),
+                    "success": summary.success,
+                    "durationSecs": summary.duration_secs,
+                })).collect();
+                output::print_payload(serde_json::to_string_pretty(&results)?);
+                return Ok(());
             }
+            
+            if matches.is_empty() {
+                eprintln!("{} No build logs match", style("ℹ️").blue());
+                return Ok(());
+            }
+            for (entry, summary) in &matches {
+                let outcome = match summary.success {
+                    Some(true) => style("✅").green(),
+                    Some(false) => style("❌").red(),
+                    None => style("…").dim(),
+                };
+                eprintln!("{} {} ({})", outcome, style(&entry.filename).cyan(), style(entry.created_at.format("%Y-%m-%d %H:%M:%S UTC")).dim());
+                eprintln!("   {}", style(summary.source.as_deref().unwrap_or(&entry.identifier)).dim());
+            }
+            eprintln!();
+            eprintln!("Use {} to view a log", style("finch-mcp logs show <filename>").cyan());
         }
         
         LogCommands::Path => {
             let log_manager = LogManager::new()?;
             let log_dir = log_manager.get_logs_directory_path();
-            println!("{}", log_dir.display());
+            output::print_payload(log_dir.display());
+        }
+        
+        LogCommands::Follow { target } => {
+            let finch_client = FinchClient::new();
+            let containers = finch_client.running_mcp_containers().await?;
+            let source_images = match target {
+                Some(target) => CacheManager::new()?.images_for_source(target),
+                None => Vec::new(),
+            };
+            let container = McpContainer::select(&containers, target.as_deref(), &source_images)?;
+            
+            if target.is_none() && containers.len() > 1 {
+                eprintln!("{} {} finch-mcp containers are running; following the newest. Pass a name to pick another:", 
+                    style("ℹ️").blue(), containers.len());
+                for other in &containers {
+                    eprintln!("  {} ({})", other.name, other.image);
+                }
+            }
+            eprintln!("{} Following {} ({}), Ctrl+C to stop\n", style("📡").blue(), container.name, container.image);
+            finch_client.follow_container_logs(&container.name).await?;
         }
     }
     
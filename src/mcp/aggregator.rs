@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde_json::{json, Map, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Child;
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+
+use crate::cache;
+use crate::finch::client::StdioRunOptions;
+use crate::finch::shutdown;
+use crate::mcp::gateway::StdioBackend;
+use crate::mcp::trace::{self, Direction, McpTracer};
+
+/// Separator between server name and tool/prompt name in aggregated listings
+pub const NAMESPACE_SEPARATOR: &str = "__";
+
+/// JSON-RPC error code for unknown methods
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// JSON-RPC error code for bad parameters (e.g. unknown tool)
+const INVALID_PARAMS: i64 = -32602;
+
+/// JSON-RPC error code for failures inside the aggregator or a backend
+const INTERNAL_ERROR: i64 = -32603;
+
+/// One server taking part in an aggregated session
+#[derive(Debug, Clone)]
+pub struct AggregatedServer {
+    /// Name used to namespace the server's tools and prompts
+    pub name: String,
+
+    /// How to run the server's container
+    pub run_options: StdioRunOptions,
+}
+
+struct Backend {
+    name: String,
+    server: Arc<StdioBackend>,
+}
+
+/// Which backend owns each namespaced tool, prompt and resource
+#[derive(Default)]
+struct Routes {
+    tools: HashMap<String, (usize, String)>,
+    prompts: HashMap<String, (usize, String)>,
+    resources: HashMap<String, usize>,
+}
+
+/// Multiplexes several stdio MCP servers into one stdio MCP session
+struct Aggregator {
+    backends: Vec<Backend>,
+    routes: Mutex<Routes>,
+    /// Server-initiated requests relayed to the client: our id -> (backend, original id)
+    relayed: Mutex<HashMap<u64, (usize, Value)>>,
+    next_relay_id: AtomicU64,
+    stdout: Mutex<tokio::io::Stdout>,
+    /// Records the client's side of the session (`--trace-mcp`)
+    tracer: Option<Arc<McpTracer>>,
+}
+
+/// Pick a namespace for a target, honouring an explicit `name=target` prefix
+pub fn parse_aggregate_target(spec: &str) -> (String, String) {
+    if let Some((name, target)) = spec.split_once('=') {
+        if is_valid_name(name) && !target.trim().is_empty() {
+            return (name.to_string(), target.trim().to_string());
+        }
+    }
+
+    // Derive a name from the last meaningful word, e.g. "uvx mcp-server-time" -> "mcp-server-time"
+    let word = spec
+        .split_whitespace()
+        .rfind(|word| !word.starts_with('-'))
+        .unwrap_or(spec);
+    let word = word.trim_end_matches('/').rsplit('/').next().unwrap_or(word);
+    let word = word.split(['@', ':']).find(|part| !part.is_empty()).unwrap_or("server");
+    let name: String = word
+        .trim_end_matches(".git")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+
+    let name = if name.is_empty() { "server".to_string() } else { name };
+    (name, spec.trim().to_string())
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Make names unique by appending -2, -3, ... to repeats
+pub fn dedupe_names(names: &mut [String]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for name in names.iter_mut() {
+        let count = seen.entry(name.clone()).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            *name = format!("{}-{}", name, count);
+        }
+    }
+}
+
+/// Advertise a capability if any backend offers it
+fn merge_capabilities(results: &[Value]) -> Value {
+    let mut capabilities = Map::new();
+    for result in results {
+        if let Some(caps) = result.get("capabilities").and_then(Value::as_object) {
+            for key in ["tools", "prompts", "resources", "logging", "completions"] {
+                if caps.contains_key(key) {
+                    capabilities.entry(key).or_insert_with(|| json!({}));
+                }
+            }
+        }
+    }
+    Value::Object(capabilities)
+}
+
+fn namespaced(server: &str, name: &str) -> String {
+    format!("{}{}{}", server, NAMESPACE_SEPARATOR, name)
+}
+
+/// Build a JSON-RPC response carrying a backend's result or error under the client's id
+fn relay_response(client_id: &Value, backend_response: &Value) -> Value {
+    let mut response = json!({"jsonrpc": "2.0", "id": client_id});
+    if let Some(error) = backend_response.get("error") {
+        response["error"] = error.clone();
+    } else {
+        response["result"] = backend_response.get("result").cloned().unwrap_or_else(|| json!({}));
+    }
+    response
+}
+
+fn error_response(client_id: &Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": client_id, "error": {"code": code, "message": message}})
+}
+
+impl Aggregator {
+    async fn write_client(&self, message: &Value) {
+        let mut line = message.to_string();
+        if let Some(tracer) = &self.tracer {
+            tracer.record(Direction::ServerToClient, &line);
+        }
+        line.push('\n');
+
+        let mut stdout = self.stdout.lock().await;
+        if stdout.write_all(line.as_bytes()).await.is_ok() {
+            let _ = stdout.flush().await;
+        }
+    }
+
+    /// Send the same request to every backend, returning successful results in backend order
+    async fn fan_out(&self, method: &str, params: Option<Value>) -> Vec<(usize, Value)> {
+        let mut tasks = JoinSet::new();
+        for (index, backend) in self.backends.iter().enumerate() {
+            let server = backend.server.clone();
+            let method = method.to_string();
+            let params = params.clone();
+            tasks.spawn(async move { (index, server.request(&method, params).await) });
+        }
+
+        let mut results = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((index, Ok(response))) => match response.get("result") {
+                    Some(result) => results.push((index, result.clone())),
+                    None => debug!("{} failed {}: {}", self.backends[index].name, method, response),
+                },
+                Ok((index, Err(e))) => warn!("{} failed {}: {}", self.backends[index].name, method, e),
+                Err(e) => warn!("Aggregator task failed: {}", e),
+            }
+        }
+        results.sort_by_key(|(index, _)| *index);
+        results
+    }
+
+    /// Collect every page of a list method from every backend
+    async fn list_all(&self, method: &str, field: &str) -> Vec<(usize, Value)> {
+        let mut items = Vec::new();
+        for (index, result) in self.fan_out(method, None).await {
+            let mut result = result;
+            loop {
+                if let Some(page) = result.get(field).and_then(Value::as_array) {
+                    items.extend(page.iter().cloned().map(|item| (index, item)));
+                }
+                let Some(cursor) = result.get("nextCursor").and_then(Value::as_str) else {
+                    break;
+                };
+                match self.backends[index].server.request(method, Some(json!({"cursor": cursor}))).await {
+                    Ok(response) => match response.get("result") {
+                        Some(next) => result = next.clone(),
+                        None => break,
+                    },
+                    Err(_) => break,
+                }
+            }
+        }
+        items
+    }
+
+    async fn forward_to(&self, index: usize, client_id: &Value, method: &str, params: Value) -> Value {
+        match self.backends[index].server.request(method, Some(params)).await {
+            Ok(response) => relay_response(client_id, &response),
+            Err(e) => error_response(client_id, INTERNAL_ERROR, &e.to_string()),
+        }
+    }
+
+    async fn handle_request(&self, id: Value, method: &str, params: Option<Value>) -> Value {
+        match method {
+            "initialize" => {
+                let results = self.fan_out(method, params.clone()).await;
+                if results.is_empty() {
+                    return error_response(&id, INTERNAL_ERROR, "No aggregated server could be initialized");
+                }
+                let protocol_version = results[0].1.get("protocolVersion").cloned()
+                    .or_else(|| params.as_ref().and_then(|p| p.get("protocolVersion").cloned()))
+                    .unwrap_or_else(|| json!("2024-11-05"));
+                let values: Vec<Value> = results.into_iter().map(|(_, result)| result).collect();
+                json!({"jsonrpc": "2.0", "id": id, "result": {
+                    "protocolVersion": protocol_version,
+                    "capabilities": merge_capabilities(&values),
+                    "serverInfo": {"name": "finch-mcp-aggregate", "version": env!("CARGO_PKG_VERSION")},
+                }})
+            }
+
+            "ping" => json!({"jsonrpc": "2.0", "id": id, "result": {}}),
+
+            "logging/setLevel" => {
+                self.fan_out(method, params).await;
+                json!({"jsonrpc": "2.0", "id": id, "result": {}})
+            }
+
+            "tools/list" | "prompts/list" => {
+                let field = if method == "tools/list" { "tools" } else { "prompts" };
+                let items = self.list_all(method, field).await;
+
+                let mut routes = self.routes.lock().await;
+                let table = if field == "tools" { &mut routes.tools } else { &mut routes.prompts };
+                table.clear();
+
+                let mut listed = Vec::new();
+                for (index, mut item) in items {
+                    let Some(name) = item.get("name").and_then(Value::as_str).map(str::to_string) else {
+                        continue;
+                    };
+                    let full_name = namespaced(&self.backends[index].name, &name);
+                    table.insert(full_name.clone(), (index, name));
+                    item["name"] = json!(full_name);
+                    listed.push(item);
+                }
+                json!({"jsonrpc": "2.0", "id": id, "result": {field: listed}})
+            }
+
+            "resources/list" | "resources/templates/list" => {
+                let field = if method == "resources/list" { "resources" } else { "resourceTemplates" };
+                let items = self.list_all(method, field).await;
+
+                let mut routes = self.routes.lock().await;
+                let mut listed = Vec::new();
+                for (index, item) in items {
+                    if let Some(uri) = item.get("uri").and_then(Value::as_str) {
+                        routes.resources.insert(uri.to_string(), index);
+                    }
+                    listed.push(item);
+                }
+                json!({"jsonrpc": "2.0", "id": id, "result": {field: listed}})
+            }
+
+            "tools/call" | "prompts/get" => {
+                let mut params = params.unwrap_or_else(|| json!({}));
+                let requested = params.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+                let route = {
+                    let routes = self.routes.lock().await;
+                    let table = if method == "tools/call" { &routes.tools } else { &routes.prompts };
+                    table.get(&requested).cloned()
+                };
+                // Fall back to splitting the name in case the client skipped the list call
+                let route = route.or_else(|| {
+                    let (server, name) = requested.split_once(NAMESPACE_SEPARATOR)?;
+                    let index = self.backends.iter().position(|b| b.name == server)?;
+                    Some((index, name.to_string()))
+                });
+
+                match route {
+                    Some((index, name)) => {
+                        params["name"] = json!(name);
+                        self.forward_to(index, &id, method, params).await
+                    }
+                    None => error_response(&id, INVALID_PARAMS, &format!("Unknown name: {}", requested)),
+                }
+            }
+
+            "resources/read" | "resources/subscribe" | "resources/unsubscribe" => {
+                let params = params.unwrap_or_else(|| json!({}));
+                let uri = params.get("uri").and_then(Value::as_str).unwrap_or_default();
+                let index = self.routes.lock().await.resources.get(uri).copied();
+                match index {
+                    Some(index) => self.forward_to(index, &id, method, params).await,
+                    None => error_response(&id, INVALID_PARAMS, &format!("Unknown resource: {}", uri)),
+                }
+            }
+
+            _ => error_response(&id, METHOD_NOT_FOUND, &format!("Method not supported by aggregator: {}", method)),
+        }
+    }
+
+    /// Relay notifications and requests that backends send on their own
+    async fn relay_backend_messages(self: Arc<Self>, index: usize) {
+        let mut messages = self.backends[index].server.messages();
+        while let Ok(routed) = messages.recv().await {
+            let mut message = routed.message;
+            if message.get("method").is_none() {
+                // A response nobody is waiting for (e.g. after a timeout)
+                continue;
+            }
+            if let Some(original_id) = message.get("id").cloned() {
+                let relay_id = self.next_relay_id.fetch_add(1, Ordering::Relaxed);
+                self.relayed.lock().await.insert(relay_id, (index, original_id));
+                message["id"] = json!(relay_id);
+            }
+            self.write_client(&message).await;
+        }
+    }
+
+    async fn handle_client_message(self: Arc<Self>, message: Value) {
+        let method = message.get("method").and_then(Value::as_str).map(str::to_string);
+        let id = message.get("id").cloned();
+
+        match (method, id) {
+            // Request: answer it
+            (Some(method), Some(id)) => {
+                let response = self.handle_request(id, &method, message.get("params").cloned()).await;
+                self.write_client(&response).await;
+            }
+            // Notification: every backend gets it
+            (Some(_), None) => {
+                for backend in &self.backends {
+                    let _ = backend.server.send(&message).await;
+                }
+            }
+            // Response to a relayed server request: route it back with the original id
+            (None, Some(id)) => {
+                let Some(relay_id) = id.as_u64() else { return };
+                let Some((index, original_id)) = self.relayed.lock().await.remove(&relay_id) else { return };
+                let mut message = message;
+                message["id"] = original_id;
+                let _ = self.backends[index].server.send(&message).await;
+            }
+            (None, None) => debug!("Ignoring malformed client message: {}", message),
+        }
+    }
+}
+
+/// Run several MCP server containers behind a single stdio MCP session, tracing the
+/// client's side of it to `trace_mcp`
+pub async fn run_aggregator(servers: Vec<AggregatedServer>, trace_mcp: Option<&Path>) -> Result<()> {
+    let tracer = trace::open(trace_mcp)?;
+    let mut backends = Vec::with_capacity(servers.len());
+    let mut children: Vec<Child> = Vec::with_capacity(servers.len());
+    let mut container_names = Vec::with_capacity(servers.len());
+
+    for server in servers {
+        cache::record_run(&server.run_options.image_name);
+        let (command, container_name) = server.run_options.supervised_command();
+        let (backend, child) = StdioBackend::spawn(command)?;
+        backends.push(Backend { name: server.name, server: backend });
+        children.push(child);
+        container_names.push(container_name);
+    }
+
+    let aggregator = Arc::new(Aggregator {
+        backends,
+        routes: Mutex::new(Routes::default()),
+        relayed: Mutex::new(HashMap::new()),
+        next_relay_id: AtomicU64::new(1),
+        stdout: Mutex::new(tokio::io::stdout()),
+        tracer,
+    });
+
+    for index in 0..aggregator.backends.len() {
+        tokio::spawn(aggregator.clone().relay_backend_messages(index));
+    }
+
+    let session = async {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await? {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(tracer) = &aggregator.tracer {
+                tracer.record(Direction::ClientToServer, line);
+            }
+            match serde_json::from_str::<Value>(line) {
+                // Handle concurrently so a slow tool call doesn't block other requests
+                Ok(message) => {
+                    tokio::spawn(aggregator.clone().handle_client_message(message));
+                }
+                Err(e) => debug!("Ignoring non-JSON client input: {}", e),
+            }
+        }
+        Ok(())
+    };
+    let result = shutdown::run_until_signal(session, &container_names).await;
+
+    // Client disconnected (or we were signalled); stop every container
+    for mut child in children {
+        let _ = child.kill().await;
+    }
+    for name in &container_names {
+        shutdown::remove_container(name).await;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aggregate_target() {
+        assert_eq!(parse_aggregate_target("time=uvx mcp-server-time"), ("time".to_string(), "uvx mcp-server-time".to_string()));
+        assert_eq!(parse_aggregate_target("uvx mcp-server-time").0, "mcp-server-time");
+        assert_eq!(parse_aggregate_target("npx -y @modelcontextprotocol/server-memory").0, "server-memory");
+        assert_eq!(parse_aggregate_target("https://github.com/user/weather-mcp.git").0, "weather-mcp");
+        assert_eq!(parse_aggregate_target("./servers/files/").0, "files");
+        assert_eq!(parse_aggregate_target("ghcr.io/example/github-mcp:1.0").0, "github-mcp");
+
+        // Not a name prefix: contains characters a name can't have
+        assert_eq!(parse_aggregate_target("uvx server --opt=1").1, "uvx server --opt=1");
+    }
+
+    #[test]
+    fn test_dedupe_names() {
+        let mut names = vec!["files".to_string(), "time".to_string(), "files".to_string()];
+        dedupe_names(&mut names);
+        assert_eq!(names, ["files", "time", "files-2"]);
+    }
+
+    #[test]
+    fn test_merge_capabilities() {
+        let merged = merge_capabilities(&[
+            json!({"capabilities": {"tools": {"listChanged": true}}}),
+            json!({"capabilities": {"tools": {}, "prompts": {}}}),
+        ]);
+        assert_eq!(merged, json!({"tools": {}, "prompts": {}}));
+    }
+
+    #[test]
+    fn test_relay_response() {
+        let id = json!("client-1");
+        assert_eq!(
+            relay_response(&id, &json!({"jsonrpc": "2.0", "id": 9, "result": {"ok": true}})),
+            json!({"jsonrpc": "2.0", "id": "client-1", "result": {"ok": true}})
+        );
+        assert_eq!(
+            relay_response(&id, &json!({"jsonrpc": "2.0", "id": 9, "error": {"code": -1, "message": "x"}}))["error"]["code"],
+            -1
+        );
+        assert_eq!(namespaced("files", "read_file"), "files__read_file");
+    }
+}
@@ -1,247 +1,213 @@
 use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::process::{Child, ChildStdin, ChildStdout, ChildStderr};
-use tokio::sync::mpsc;
-use tokio::time::{Duration, interval};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin};
+use tokio::sync::watch;
+use tokio::time::timeout;
 use anyhow::{Result, Context};
+use serde_json::Value;
 
+use crate::finch::client::ContainerExit;
 use crate::mcp::buffer::MCPBuffer;
-use crate::output::is_quiet_mode;
+use crate::mcp::startup::{push_stderr_line, StderrTail};
+use crate::mcp::trace::{Direction, McpTracer};
 
+/// Stdio proxy that holds early client messages until the server is ready
+///
+/// The client's `initialize` is forwarded straight away; anything sent after it
+/// is buffered until the server answers (or prints the readiness pattern on
+/// stderr), then flushed in order.
 pub struct AsyncStdioProxy {
     buffer: Arc<MCPBuffer>,
     container: Child,
-    shutdown_tx: mpsc::Sender<()>,
+    readiness_pattern: Option<String>,
+    tracer: Option<Arc<McpTracer>>,
 }
 
 impl AsyncStdioProxy {
     pub fn new(buffer: Arc<MCPBuffer>, container: Child) -> Result<Self> {
-        let (shutdown_tx, _) = mpsc::channel(1);
-        
         Ok(Self {
             buffer,
             container,
-            shutdown_tx,
+            readiness_pattern: None,
+            tracer: None,
         })
     }
 
-    pub async fn start(mut self) -> Result<()> {
-        let buffer = self.buffer.clone();
-        let _shutdown_tx = self.shutdown_tx.clone();
-        
+    /// Treat a server stderr line containing `pattern` as a readiness signal
+    pub fn with_readiness_pattern(mut self, pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        self.readiness_pattern = (!pattern.is_empty()).then_some(pattern);
+        self
+    }
+
+    /// Record every frame passing through to `tracer` (`--trace-mcp`)
+    pub fn with_tracer(mut self, tracer: Option<Arc<McpTracer>>) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    /// Proxy between this process's stdio and the container until it exits
+    pub async fn start(self) -> Result<()> {
+        self.run(tokio::io::stdin(), tokio::io::stdout()).await
+    }
+
+    /// Proxy between the given client streams and the container until it exits
+    pub async fn run<R, W>(mut self, client_in: R, client_out: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
         let container_stdin = self.container.stdin.take()
             .context("Failed to capture container stdin")?;
         let container_stdout = self.container.stdout.take()
             .context("Failed to capture container stdout")?;
         let container_stderr = self.container.stderr.take()
             .context("Failed to capture container stderr")?;
-        
-        // Create shutdown receiver for each task
-        let (shutdown_tx_stdin, mut shutdown_rx_stdin) = mpsc::channel::<()>(1);
-        let (shutdown_tx_stdout, mut shutdown_rx_stdout) = mpsc::channel::<()>(1);
-        let (shutdown_tx_stderr, mut shutdown_rx_stderr) = mpsc::channel::<()>(1);
-        let (shutdown_tx_timeout, mut shutdown_rx_timeout) = mpsc::channel::<()>(1);
-        
-        // Spawn stdin handler (client -> server)
-        let stdin_task = {
-            let buffer = buffer.clone();
-            tokio::spawn(async move {
-                handle_stdin(buffer, container_stdin, &mut shutdown_rx_stdin).await
-            })
-        };
 
-        // Spawn stdout handler (server -> client)
+        let (ready_tx, mut ready_rx) = watch::channel(false);
+        let ready_tx = Arc::new(ready_tx);
+
+        // Client -> server
+        let stdin_task = tokio::spawn(handle_stdin(self.buffer.clone(), client_in, container_stdin, ready_rx.clone(), self.tracer.clone()));
+
+        // Server -> client
         let stdout_task = {
-            let buffer = buffer.clone();
+            let buffer = self.buffer.clone();
+            let ready_tx = ready_tx.clone();
+            let tracer = self.tracer.clone();
             tokio::spawn(async move {
-                handle_stdout(buffer, container_stdout, &mut shutdown_rx_stdout).await
+                let mut lines = BufReader::new(container_stdout).lines();
+                let mut client_out = client_out;
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if buffer.observe_server_message(line.as_bytes()) {
+                        ready_tx.send_replace(true);
+                    }
+                    if let Some(tracer) = &tracer {
+                        tracer.record(Direction::ServerToClient, &line);
+                    }
+                    if client_out.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                        break;
+                    }
+                    let _ = client_out.flush().await;
+                }
             })
         };
 
-        // Spawn stderr handler
+        // Server stderr, watched for the readiness pattern
+        let stderr_tail = StderrTail::default();
         let stderr_task = {
+            let stderr_tail = stderr_tail.clone();
+            let buffer = self.buffer.clone();
+            let ready_tx = ready_tx.clone();
+            let readiness_pattern = self.readiness_pattern.clone();
             tokio::spawn(async move {
-                handle_stderr(container_stderr, &mut shutdown_rx_stderr).await
+                let mut lines = BufReader::new(container_stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    eprintln!("{}", line);
+                    if !buffer.is_server_ready() && readiness_pattern.as_ref().is_some_and(|p| line.contains(p.as_str())) {
+                        buffer.mark_ready();
+                        ready_tx.send_replace(true);
+                    }
+                    push_stderr_line(&stderr_tail, line);
+                }
             })
         };
 
-        // Spawn timeout checker
-        let timeout_task = {
-            let buffer = buffer.clone();
-            tokio::spawn(async move {
-                handle_timeout(buffer, &mut shutdown_rx_timeout).await
-            })
+        // Only the output readers can signal readiness from here on
+        drop(ready_tx);
+
+        let startup_timeout = self.buffer.startup_timeout();
+        let became_ready = async {
+            // Map to a plain bool so the watch guard isn't held across the await below
+            timeout(startup_timeout, ready_rx.wait_for(|ready| *ready)).await.map(|ready| ready.is_ok())
         };
 
-        // Wait for container to finish
-        let exit_status = self.container.wait().await?;
-        
-        // Signal all tasks to shutdown
-        let _ = shutdown_tx_stdin.send(()).await;
-        let _ = shutdown_tx_stdout.send(()).await;
-        let _ = shutdown_tx_stderr.send(()).await;
-        let _ = shutdown_tx_timeout.send(()).await;
-        
-        // Wait for all tasks to complete
-        let _ = tokio::join!(stdin_task, stdout_task, stderr_task, timeout_task);
-        
-        if exit_status.success() {
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Container exited with non-zero status: {}", exit_status))
-        }
+        let exit_status = tokio::select! {
+            status = self.container.wait() => status?,
+            result = became_ready => {
+                if result.is_err() {
+                    let _ = self.container.kill().await;
+                    stdin_task.abort();
+                    let (client_bytes, _) = self.buffer.get_buffer_stats();
+                    eprintln!("❌ MCP server was not ready within {}s ({} bytes of client input buffered)",
+                        startup_timeout.as_secs(), client_bytes);
+                    return Err(anyhow::anyhow!("Server startup timeout after {:?}", startup_timeout));
+                }
+                self.container.wait().await?
+            }
+        };
+
+        // Let the server's last output reach the client before tearing down
+        let _ = stdout_task.await;
+        let _ = stderr_task.await;
+        stdin_task.abort();
+
+        let tail = stderr_tail.lock().unwrap().iter().cloned().collect();
+        ContainerExit::check(exit_status, tail)
     }
 }
 
-async fn handle_stdin(
+async fn handle_stdin<R: AsyncRead + Unpin>(
     buffer: Arc<MCPBuffer>,
+    client_in: R,
     mut container_stdin: ChildStdin,
-    shutdown_rx: &mut mpsc::Receiver<()>,
+    mut ready_rx: watch::Receiver<bool>,
+    tracer: Option<Arc<McpTracer>>,
 ) -> Result<()> {
-    let mut stdin = tokio::io::stdin();
-    let mut read_buffer = vec![0u8; 8192];
-    
+    let mut lines = BufReader::new(client_in).lines();
+    let mut initialize_forwarded = false;
+    let mut waiting_for_ready = true;
+
     loop {
         tokio::select! {
-            _ = shutdown_rx.recv() => break,
-            result = stdin.read(&mut read_buffer) => {
-                match result {
-                    Ok(0) => break, // EOF
-                    Ok(n) => {
-                        let data = read_buffer[..n].to_vec();
-                        
-                        if buffer.is_server_ready() {
-                            // Server is ready, write directly
-                            if container_stdin.write_all(&data).await.is_err() {
-                                break;
-                            }
-                        } else {
-                            // Buffer the message
-                            if buffer.buffer_client_message(data).is_err() {
-                                if !is_quiet_mode() {
-                                    eprintln!("Warning: Client message buffer overflow");
-                                }
-                                break;
-                            }
-                        }
-                    }
-                    Err(_) => break,
+            line = lines.next_line() => {
+                let Some(line) = line? else { break };
+                if let Some(tracer) = &tracer {
+                    tracer.record(Direction::ClientToServer, &line);
                 }
-                
-                // If server just became ready, flush buffered messages
+                let data = format!("{}\n", line).into_bytes();
+
                 if buffer.is_server_ready() {
-                    for message in buffer.drain_client_buffer() {
-                        if container_stdin.write_all(&message).await.is_err() {
-                            break;
-                        }
-                    }
+                    flush_buffered(&buffer, &mut container_stdin).await?;
+                    container_stdin.write_all(&data).await?;
+                    container_stdin.flush().await?;
+                } else if !initialize_forwarded && is_initialize_request(&line) {
+                    // The server can't become ready without seeing initialize
+                    initialize_forwarded = true;
+                    container_stdin.write_all(&data).await?;
+                    container_stdin.flush().await?;
+                } else if let Err(e) = buffer.buffer_client_message(data) {
+                    eprintln!("Warning: {}", e);
+                    break;
                 }
             }
-        }
-    }
-    
-    Ok(())
-}
-
-async fn handle_stdout(
-    buffer: Arc<MCPBuffer>,
-    mut container_stdout: ChildStdout,
-    shutdown_rx: &mut mpsc::Receiver<()>,
-) -> Result<()> {
-    let mut stdout = tokio::io::stdout();
-    let mut read_buffer = vec![0u8; 8192];
-    
-    loop {
-        tokio::select! {
-            _ = shutdown_rx.recv() => break,
-            result = container_stdout.read(&mut read_buffer) => {
-                match result {
-                    Ok(0) => break, // EOF
-                    Ok(n) => {
-                        let data = read_buffer[..n].to_vec();
-                        
-                        // Always check server messages for readiness
-                        if let Err(e) = buffer.buffer_server_message(data.clone()) {
-                            if !is_quiet_mode() {
-                                eprintln!("Warning: Server message buffer error: {}", e);
-                            }
-                        }
-                        
-                        // Write to client immediately
-                        if stdout.write_all(&data).await.is_err() {
-                            break;
-                        }
-                        let _ = stdout.flush().await;
-                    }
-                    Err(_) => break,
+            changed = ready_rx.changed(), if waiting_for_ready => {
+                if changed.is_err() {
+                    // Nothing left to signal readiness; keep forwarding input as it comes
+                    buffer.mark_ready();
+                }
+                if buffer.is_server_ready() {
+                    waiting_for_ready = false;
+                    flush_buffered(&buffer, &mut container_stdin).await?;
                 }
             }
         }
     }
-    
-    // Flush any remaining server messages
-    for message in buffer.drain_server_buffer() {
-        let _ = stdout.write_all(&message).await;
-    }
-    let _ = stdout.flush().await;
-    
+
     Ok(())
 }
 
-async fn handle_stderr(
-    mut container_stderr: ChildStderr,
-    shutdown_rx: &mut mpsc::Receiver<()>,
-) -> Result<()> {
-    let mut stderr = tokio::io::stderr();
-    let mut read_buffer = vec![0u8; 8192];
-    
-    loop {
-        tokio::select! {
-            _ = shutdown_rx.recv() => break,
-            result = container_stderr.read(&mut read_buffer) => {
-                match result {
-                    Ok(0) => break, // EOF
-                    Ok(n) => {
-                        // Write to client stderr immediately
-                        if stderr.write_all(&read_buffer[..n]).await.is_err() {
-                            break;
-                        }
-                        let _ = stderr.flush().await;
-                    }
-                    Err(_) => break,
-                }
-            }
-        }
+/// Write any buffered client messages to the server, oldest first
+async fn flush_buffered(buffer: &MCPBuffer, container_stdin: &mut ChildStdin) -> Result<()> {
+    for message in buffer.drain_client_buffer() {
+        container_stdin.write_all(&message).await?;
     }
-    
+    container_stdin.flush().await?;
     Ok(())
 }
 
-async fn handle_timeout(
-    buffer: Arc<MCPBuffer>,
-    shutdown_rx: &mut mpsc::Receiver<()>,
-) -> Result<()> {
-    let mut check_interval = interval(Duration::from_millis(100));
-    
-    loop {
-        tokio::select! {
-            _ = shutdown_rx.recv() => break,
-            _ = check_interval.tick() => {
-                // Check timeout
-                if let Err(e) = buffer.check_timeout() {
-                    if !is_quiet_mode() {
-                        eprintln!("Error: {}", e);
-                    }
-                    break;
-                }
-                
-                // If server is ready, no need to check timeout anymore
-                if buffer.is_server_ready() {
-                    break;
-                }
-            }
-        }
-    }
-    
-    Ok(())
-}
\ No newline at end of file
+fn is_initialize_request(line: &str) -> bool {
+    serde_json::from_str::<Value>(line)
+        .map(|message| message.get("method").and_then(Value::as_str) == Some("initialize"))
+        .unwrap_or(false)
+}
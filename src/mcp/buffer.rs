@@ -40,22 +40,42 @@ impl MCPBuffer {
     }
 
     pub fn buffer_server_message(&self, data: Vec<u8>) -> Result<()> {
-        // Check if this is the initialization response
-        if !self.server_ready.load(Ordering::SeqCst) {
-            if let Ok(text) = std::str::from_utf8(&data) {
-                if text.contains(r#""method":"initialize""#) || 
-                   text.contains(r#""result":{"capabilities""#) {
-                    self.server_ready.store(true, Ordering::SeqCst);
-                    *self.startup_time.lock().unwrap() = None;
-                }
-            }
-        }
+        self.observe_server_message(&data);
         
         let mut buffer = self.server_to_client.lock().unwrap();
         buffer.push_back(data);
         Ok(())
     }
 
+    /// Check a server message for the initialization response without buffering it
+    /// Returns true if this message made the server ready
+    pub fn observe_server_message(&self, data: &[u8]) -> bool {
+        if self.server_ready.load(Ordering::SeqCst) {
+            return false;
+        }
+        
+        let Ok(text) = std::str::from_utf8(data) else {
+            return false;
+        };
+        let is_ready_signal = text.contains(r#""method":"initialize""#) ||
+            text.contains(r#""result":{"capabilities""#) ||
+            // Before initialize completes, the only response a server sends is to initialize
+            serde_json::from_str::<serde_json::Value>(text.trim())
+                .map(|message| message.get("result").is_some() && message.get("method").is_none())
+                .unwrap_or(false);
+        
+        if is_ready_signal {
+            self.mark_ready();
+        }
+        is_ready_signal
+    }
+    
+    /// Mark the server ready (e.g. it printed the readiness pattern) and stop the startup clock
+    pub fn mark_ready(&self) {
+        self.server_ready.store(true, Ordering::SeqCst);
+        *self.startup_time.lock().unwrap() = None;
+    }
+
     pub fn drain_client_buffer(&self) -> Vec<Vec<u8>> {
         let mut buffer = self.client_to_server.lock().unwrap();
         buffer.drain(..).collect()
@@ -79,6 +99,10 @@ impl MCPBuffer {
         Ok(())
     }
 
+    pub fn startup_timeout(&self) -> Duration {
+        self.startup_timeout
+    }
+
     pub fn get_buffer_stats(&self) -> (usize, usize) {
         let client_size: usize = self.client_to_server.lock().unwrap().iter().map(|v| v.len()).sum();
         let server_size: usize = self.server_to_client.lock().unwrap().iter().map(|v| v.len()).sum();
@@ -0,0 +1,321 @@
+use anyhow::Result;
+use console::style;
+use serde_json::{json, Value};
+use tokio::time::{timeout, Duration};
+
+use crate::finch::client::StdioRunOptions;
+use crate::finch::shutdown;
+use crate::mcp::gateway::StdioBackend;
+
+/// Protocol version offered in the test client's `initialize`
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Outcome of a single conformance check
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckStatus {
+    Passed(String),
+    Failed(String),
+    Skipped(String),
+}
+
+/// A named check and its outcome
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+}
+
+/// Results of running the conformance checks against one server
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    fn record(&mut self, name: &str, status: CheckStatus) {
+        self.checks.push(CheckResult { name: name.to_string(), status });
+    }
+
+    /// Number of failed checks
+    pub fn failures(&self) -> usize {
+        self.checks.iter().filter(|check| matches!(check.status, CheckStatus::Failed(_))).count()
+    }
+
+    /// Whether every check passed or was skipped
+    pub fn passed(&self) -> bool {
+        self.failures() == 0
+    }
+
+    /// Print a pass/fail line per check followed by a summary
+    pub fn print(&self) {
+        for check in &self.checks {
+            let (icon, detail) = match &check.status {
+                CheckStatus::Passed(detail) => (style("✅").green(), detail),
+                CheckStatus::Failed(detail) => (style("❌").red(), detail),
+                CheckStatus::Skipped(detail) => (style("⏭️").dim(), detail),
+            };
+            eprintln!("{} {:<16} {}", icon, check.name, style(detail).dim());
+        }
+
+        let failures = self.failures();
+        if failures == 0 {
+            eprintln!("\n{} All {} checks passed", style("✅").green(), self.checks.len());
+        } else {
+            eprintln!("\n{} {} of {} checks failed", style("❌").red(), failures, self.checks.len());
+        }
+    }
+
+    /// Machine-readable form of the report
+    pub fn to_json(&self) -> Value {
+        let checks: Vec<Value> = self.checks.iter().map(|check| {
+            let (status, detail) = match &check.status {
+                CheckStatus::Passed(detail) => ("passed", detail),
+                CheckStatus::Failed(detail) => ("failed", detail),
+                CheckStatus::Skipped(detail) => ("skipped", detail),
+            };
+            json!({"name": check.name, "status": status, "detail": detail})
+        }).collect();
+
+        json!({"passed": self.passed(), "checks": checks})
+    }
+}
+
+/// Check the JSON-RPC envelope of a response and return its `result`
+pub fn validate_envelope(response: &Value) -> Result<&Value, String> {
+    if response.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err("response is not JSON-RPC 2.0".to_string());
+    }
+    match (response.get("result"), response.get("error")) {
+        (Some(result), None) => Ok(result),
+        (None, Some(error)) => {
+            let message = error.get("message").and_then(Value::as_str).unwrap_or("unknown error");
+            Err(format!("server returned an error: {}", message))
+        }
+        (Some(_), Some(_)) => Err("response has both result and error".to_string()),
+        (None, None) => Err("response has neither result nor error".to_string()),
+    }
+}
+
+/// Validate an `initialize` result
+pub fn validate_initialize(result: &Value) -> Result<String, String> {
+    let version = result.get("protocolVersion").and_then(Value::as_str)
+        .ok_or("missing protocolVersion")?;
+    if !result.get("capabilities").is_some_and(Value::is_object) {
+        return Err("missing capabilities object".to_string());
+    }
+    let server_info = result.get("serverInfo").ok_or("missing serverInfo")?;
+    let name = server_info.get("name").and_then(Value::as_str)
+        .ok_or("serverInfo is missing name")?;
+    let version_info = server_info.get("version").and_then(Value::as_str).unwrap_or("unknown version");
+
+    Ok(format!("{} {} (protocol {})", name, version_info, version))
+}
+
+/// Validate a `tools/list` result
+pub fn validate_tools_list(result: &Value) -> Result<String, String> {
+    let tools = list_field(result, "tools")?;
+    for (index, tool) in tools.iter().enumerate() {
+        let name = string_field(tool, "name").map_err(|e| format!("tool {}: {}", index, e))?;
+        let schema = tool.get("inputSchema").ok_or_else(|| format!("tool '{}' is missing inputSchema", name))?;
+        if schema.get("type").and_then(Value::as_str) != Some("object") {
+            return Err(format!("tool '{}' inputSchema must have type \"object\"", name));
+        }
+    }
+    Ok(format!("{} tools", tools.len()))
+}
+
+/// Validate a `resources/list` result
+pub fn validate_resources_list(result: &Value) -> Result<String, String> {
+    let resources = list_field(result, "resources")?;
+    for (index, resource) in resources.iter().enumerate() {
+        string_field(resource, "uri").map_err(|e| format!("resource {}: {}", index, e))?;
+        string_field(resource, "name").map_err(|e| format!("resource {}: {}", index, e))?;
+    }
+    Ok(format!("{} resources", resources.len()))
+}
+
+/// Validate a `prompts/list` result
+pub fn validate_prompts_list(result: &Value) -> Result<String, String> {
+    let prompts = list_field(result, "prompts")?;
+    for (index, prompt) in prompts.iter().enumerate() {
+        let name = string_field(prompt, "name").map_err(|e| format!("prompt {}: {}", index, e))?;
+        if let Some(arguments) = prompt.get("arguments") {
+            let arguments = arguments.as_array()
+                .ok_or_else(|| format!("prompt '{}' arguments must be an array", name))?;
+            for argument in arguments {
+                string_field(argument, "name").map_err(|e| format!("prompt '{}' argument: {}", name, e))?;
+            }
+        }
+    }
+    Ok(format!("{} prompts", prompts.len()))
+}
+
+fn list_field<'a>(result: &'a Value, field: &str) -> Result<&'a Vec<Value>, String> {
+    result.get(field).and_then(Value::as_array)
+        .ok_or_else(|| format!("result is missing the '{}' array", field))
+}
+
+fn string_field<'a>(item: &'a Value, field: &str) -> Result<&'a str, String> {
+    item.get(field).and_then(Value::as_str)
+        .ok_or_else(|| format!("missing string field '{}'", field))
+}
+
+/// Send a request, giving up after `request_timeout`
+async fn request_with_timeout(
+    backend: &StdioBackend,
+    method: &str,
+    params: Option<Value>,
+    request_timeout: Duration,
+) -> Result<Value> {
+    timeout(request_timeout, backend.request(method, params))
+        .await
+        .map_err(|_| anyhow::anyhow!("no response to {} within {}s", method, request_timeout.as_secs()))?
+}
+
+/// Perform the MCP handshake and return the full `initialize` response
+pub async fn handshake(backend: &StdioBackend, request_timeout: Duration) -> Result<Value> {
+    let params = json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": {"name": "finch-mcp", "version": env!("CARGO_PKG_VERSION")},
+    });
+    let response = request_with_timeout(backend, "initialize", Some(params), request_timeout).await?;
+
+    backend.send(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"})).await?;
+    Ok(response)
+}
+
+/// Run the handshake and list calls against a server, recording a check for each
+pub async fn run_conformance(backend: &StdioBackend, request_timeout: Duration) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+
+    let capabilities = match handshake(backend, request_timeout).await {
+        Ok(response) => match validate_envelope(&response).and_then(|result| {
+            validate_initialize(result).map(|detail| (detail, result["capabilities"].clone()))
+        }) {
+            Ok((detail, capabilities)) => {
+                report.record("initialize", CheckStatus::Passed(detail));
+                capabilities
+            }
+            Err(reason) => {
+                report.record("initialize", CheckStatus::Failed(reason));
+                return report;
+            }
+        },
+        Err(e) => {
+            report.record("initialize", CheckStatus::Failed(e.to_string()));
+            return report;
+        }
+    };
+
+    let status = match request_with_timeout(backend, "ping", None, request_timeout).await {
+        Ok(response) => match validate_envelope(&response) {
+            Ok(result) if result.is_object() => CheckStatus::Passed("server responded".to_string()),
+            Ok(_) => CheckStatus::Failed("ping result must be an empty object".to_string()),
+            Err(reason) => CheckStatus::Failed(reason),
+        },
+        Err(e) => CheckStatus::Failed(e.to_string()),
+    };
+    report.record("ping", status);
+
+    type Validator = fn(&Value) -> Result<String, String>;
+    let listings: [(&str, &str, Validator); 3] = [
+        ("tools", "tools/list", validate_tools_list),
+        ("resources", "resources/list", validate_resources_list),
+        ("prompts", "prompts/list", validate_prompts_list),
+    ];
+
+    for (capability, method, validate) in listings {
+        // Servers only have to answer list calls for capabilities they advertise
+        if capabilities.get(capability).is_none() {
+            report.record(method, CheckStatus::Skipped(format!("{} capability not advertised", capability)));
+            continue;
+        }
+
+        let status = match request_with_timeout(backend, method, None, request_timeout).await {
+            Ok(response) => match validate_envelope(&response).and_then(validate) {
+                Ok(detail) => CheckStatus::Passed(detail),
+                Err(reason) => CheckStatus::Failed(reason),
+            },
+            Err(e) => CheckStatus::Failed(e.to_string()),
+        };
+        report.record(method, status);
+    }
+
+    report
+}
+
+/// Start a container, run the conformance checks against it, then stop it
+pub async fn test_stdio_container(options: &StdioRunOptions, request_timeout: Duration) -> Result<ConformanceReport> {
+    let (command, container_name) = options.supervised_command();
+    let (backend, mut child) = StdioBackend::spawn(command)?;
+
+    let names = [container_name];
+    let report = shutdown::run_until_signal(async { Ok(run_conformance(&backend, request_timeout).await) }, &names).await;
+
+    let _ = child.kill().await;
+    shutdown::remove_container(&names[0]).await;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::process::Command;
+
+    #[test]
+    fn test_validate_envelope() {
+        let ok = json!({"jsonrpc": "2.0", "id": 1, "result": {}});
+        assert!(validate_envelope(&ok).is_ok());
+
+        let error = json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32601, "message": "Method not found"}});
+        assert!(validate_envelope(&error).unwrap_err().contains("Method not found"));
+
+        let old = json!({"jsonrpc": "1.0", "id": 1, "result": {}});
+        assert!(validate_envelope(&old).is_err());
+    }
+
+    #[test]
+    fn test_validate_listings() {
+        let initialize = json!({"protocolVersion": "2024-11-05", "capabilities": {}, "serverInfo": {"name": "demo", "version": "1.0"}});
+        assert_eq!(validate_initialize(&initialize).unwrap(), "demo 1.0 (protocol 2024-11-05)");
+        assert!(validate_initialize(&json!({"protocolVersion": "2024-11-05", "capabilities": {}})).is_err());
+
+        let tools = json!({"tools": [{"name": "echo", "inputSchema": {"type": "object"}}]});
+        assert_eq!(validate_tools_list(&tools).unwrap(), "1 tools");
+        let bad_schema = json!({"tools": [{"name": "echo", "inputSchema": {"type": "string"}}]});
+        assert!(validate_tools_list(&bad_schema).unwrap_err().contains("echo"));
+
+        let resources = json!({"resources": [{"uri": "file:///a", "name": "a"}]});
+        assert!(validate_resources_list(&resources).is_ok());
+        assert!(validate_resources_list(&json!({"resources": [{"name": "a"}]})).is_err());
+
+        let prompts = json!({"prompts": [{"name": "greet", "arguments": [{"name": "who"}]}]});
+        assert!(validate_prompts_list(&prompts).is_ok());
+        assert!(validate_prompts_list(&json!({})).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_conformance_against_scripted_server() {
+        // Rewrite each request line into a canned response with the same id
+        let mut command = Command::new("sed");
+        command.args([
+            "-u",
+            "-e", r#"s/"method":"initialize".*/"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"sed","version":"1"}}}/"#,
+            "-e", r#"s/"method":"ping".*/"result":{}}/"#,
+            "-e", r#"s/"method":"tools\/list".*/"result":{"tools":[{"name":"echo","inputSchema":{"type":"object"}}]}}/"#,
+        ]);
+        let (backend, mut child) = StdioBackend::spawn(command).unwrap();
+
+        let report = run_conformance(&backend, Duration::from_secs(5)).await;
+        let _ = child.kill().await;
+
+        assert!(report.passed(), "{:?}", report);
+        let statuses: Vec<_> = report.checks.iter().map(|c| (c.name.as_str(), &c.status)).collect();
+        assert_eq!(statuses[0].0, "initialize");
+        assert_eq!(*statuses[2].1, CheckStatus::Passed("1 tools".to_string()));
+        assert!(matches!(statuses[3].1, CheckStatus::Skipped(_)));
+        assert_eq!(report.to_json()["passed"], true);
+    }
+}
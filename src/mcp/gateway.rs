@@ -0,0 +1,757 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::extract::{Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{broadcast, oneshot, Mutex};
+use tokio::time::{timeout, Duration};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::cache;
+use crate::finch::client::{ContainerExit, StdioRunOptions};
+use crate::finch::shutdown;
+use crate::mcp::supervisor::{self, RestartPolicy};
+
+/// How long a POST waits for the server to answer a request
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Path clients are told to POST to on the legacy SSE transport
+const LEGACY_MESSAGES_PATH: &str = "/messages";
+
+/// Header carrying the streamable HTTP session
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// A client session of the HTTP gateway
+pub type SessionId = String;
+
+/// A message from the server and the session it is for; `None` means every session
+#[derive(Debug, Clone)]
+pub struct Routed {
+    pub session: Option<SessionId>,
+    pub message: Value,
+}
+
+/// A forwarded request waiting for the server's response
+struct Pending {
+    session: Option<SessionId>,
+    /// The id the client gave the request, restored on the response
+    client_id: Value,
+    /// The gateway's stand-in for the request's progress token, if it had one
+    progress_token: Option<u64>,
+    reply: oneshot::Sender<Value>,
+}
+
+/// A stdio MCP server process shared by several clients (HTTP gateway, aggregator)
+///
+/// Every request reaches the server under an id the backend assigns, so clients that
+/// pick the same ids can't receive each other's responses; the client's id is put back
+/// on the response. Progress tokens are replaced the same way, so progress goes only to
+/// the session whose request it is about. The server is initialized once: later clients'
+/// `initialize` is answered with the result of the first.
+pub struct StdioBackend {
+    /// None while the server is down, e.g. between a crash and its restart
+    stdin: Mutex<Option<ChildStdin>>,
+    pending: Mutex<HashMap<u64, Pending>>,
+    /// The session and token of each replaced progress token
+    progress: Mutex<HashMap<u64, (Option<SessionId>, Value)>>,
+    /// Sessions sent each request the server made, by its id, for a `notifications/cancelled` about it
+    server_requests: Mutex<HashMap<String, Option<SessionId>>>,
+    events: broadcast::Sender<Routed>,
+    next_id: AtomicU64,
+    /// Params of the client's `initialize`, replayed to a restarted server
+    handshake: Mutex<Option<Value>>,
+    /// The server's result for the first `initialize`, given to later clients instead of
+    /// initializing the shared server again
+    initialized: Mutex<Option<Value>>,
+    /// Set once a client's `notifications/initialized` has reached the server
+    initialized_sent: AtomicBool,
+    /// Set once the clients are gone, so the server isn't restarted
+    closed: AtomicBool,
+}
+
+impl StdioBackend {
+    /// Spawn the server process with piped stdio and start reading its output
+    pub fn spawn(command: Command) -> Result<(Arc<Self>, Child)> {
+        let (child, stdin, stdout) = start_server(command)?;
+
+        let (events, _) = broadcast::channel(256);
+        let backend = Arc::new(Self {
+            stdin: Mutex::new(Some(stdin)),
+            pending: Mutex::new(HashMap::new()),
+            progress: Mutex::new(HashMap::new()),
+            server_requests: Mutex::new(HashMap::new()),
+            events,
+            next_id: AtomicU64::new(1),
+            handshake: Mutex::new(None),
+            initialized: Mutex::new(None),
+            initialized_sent: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+        });
+
+        tokio::spawn(backend.clone().read_server_output(stdout));
+
+        Ok((backend, child))
+    }
+
+    /// Forget a server that exited: requests still waiting on it fail now rather than at their timeout
+    pub async fn detach(&self) {
+        self.stdin.lock().await.take();
+        self.pending.lock().await.clear();
+        self.progress.lock().await.clear();
+        self.server_requests.lock().await.clear();
+    }
+
+    /// Attach a restarted server in place of the one that exited
+    pub async fn reattach(self: &Arc<Self>, command: Command) -> Result<Child> {
+        let (child, stdin, stdout) = start_server(command)?;
+        *self.stdin.lock().await = Some(stdin);
+        tokio::spawn(self.clone().read_server_output(stdout));
+        Ok(child)
+    }
+
+    /// Initialize a restarted server the way the client initialized the original one
+    pub async fn replay_handshake(&self) -> Result<()> {
+        let Some(params) = self.handshake.lock().await.clone() else {
+            return Ok(());
+        };
+        // The restarted server answers this one itself
+        self.initialized.lock().await.take();
+        self.request("initialize", Some(params)).await.context("Restarted server did not initialize")?;
+        self.send(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"})).await
+    }
+
+    /// Close the server's stdin for good; it is not restarted after this
+    pub async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.stdin.lock().await.take();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Route server messages: responses go to the waiting request, everything else to the
+    /// session it belongs to, or every session
+    async fn read_server_output(self: Arc<Self>, stdout: ChildStdout) {
+        let mut lines = BufReader::new(stdout).lines();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let message: Value = match serde_json::from_str(line) {
+                Ok(message) => message,
+                Err(_) => {
+                    debug!("Ignoring non-JSON server output: {}", line);
+                    continue;
+                }
+            };
+
+            let mut message = message;
+            if let Some(id) = response_key(&message).and_then(|key| key.parse::<u64>().ok()) {
+                if let Some(pending) = self.pending.lock().await.remove(&id) {
+                    if let Some(token) = pending.progress_token {
+                        self.progress.lock().await.remove(&token);
+                    }
+                    message["id"] = pending.client_id;
+                    let _ = pending.reply.send(message);
+                    continue;
+                }
+            }
+
+            let session = self.route(&mut message).await;
+            // No subscribers is fine; the message is simply dropped
+            let _ = self.events.send(Routed { session, message });
+        }
+
+        debug!("MCP server closed its stdout");
+    }
+
+    /// The session a server-initiated message is for, restoring a replaced progress token:
+    /// progress goes to the session of its request, a request from the server to the session
+    /// with the newest request in flight, and anything else to every session
+    async fn route(&self, message: &mut Value) -> Option<SessionId> {
+        let method = message.get("method").and_then(Value::as_str).unwrap_or_default().to_string();
+        if method == "notifications/progress" {
+            let token = message["params"]["progressToken"].as_u64()?;
+            let (session, client_token) = self.progress.lock().await.get(&token).cloned()?;
+            message["params"]["progressToken"] = client_token;
+            return session;
+        }
+        if method == "notifications/cancelled" {
+            let request_id = message["params"]["requestId"].to_string();
+            return self.server_requests.lock().await.remove(&request_id).flatten();
+        }
+        let id = request_key(message)?;
+        let session = self.pending.lock().await.iter()
+            .max_by_key(|(id, _)| **id)
+            .and_then(|(_, pending)| pending.session.clone());
+        self.server_requests.lock().await.insert(id, session.clone());
+        session
+    }
+
+    /// Write one JSON-RPC message to the server as a single line
+    pub async fn send(&self, message: &Value) -> Result<()> {
+        if message.get("method").and_then(Value::as_str) == Some("initialize") {
+            *self.handshake.lock().await = message.get("params").cloned();
+        }
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+
+        let mut guard = self.stdin.lock().await;
+        let stdin = guard.as_mut().context("MCP server is not running (restarting?)")?;
+        stdin.write_all(&line).await.context("Failed to write to MCP server")?;
+        stdin.flush().await?;
+        Ok(())
+    }
+
+    /// Forward client messages and wait for the responses to any requests among them
+    #[tracing::instrument(
+        name = "mcp.request",
+        skip_all,
+        fields(method = messages.first().and_then(|message| message.get("method")).and_then(serde_json::Value::as_str).unwrap_or("")),
+        err
+    )]
+    pub(crate) async fn forward(&self, session: Option<&str>, messages: &[Value]) -> Result<Vec<Value>> {
+        let mut waiters = Vec::new();
+
+        for message in messages {
+            let method = message.get("method").and_then(Value::as_str);
+            let Some(client_id) = request_key(message).and(message.get("id")).cloned() else {
+                if response_key(message).is_some() {
+                    // The client's answer to a request from the server
+                    self.server_requests.lock().await.remove(&message["id"].to_string());
+                }
+                if method == Some("notifications/initialized") && self.initialized_sent.swap(true, Ordering::SeqCst) {
+                    continue;
+                }
+                self.send(message).await?;
+                continue;
+            };
+
+            let initializes = method == Some("initialize");
+            if initializes {
+                if let Some(result) = self.initialized.lock().await.clone() {
+                    // The server is already initialized: answer from its first result
+                    let (reply, rx) = oneshot::channel();
+                    let _ = reply.send(json!({"jsonrpc": "2.0", "id": client_id, "result": result}));
+                    waiters.push((0, client_id, rx, false));
+                    continue;
+                }
+            }
+
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            let mut message = message.clone();
+            message["id"] = json!(id);
+            let progress_token = match message.pointer_mut("/params/_meta/progressToken") {
+                Some(token) => {
+                    let stand_in = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    let client_token = std::mem::replace(token, json!(stand_in));
+                    self.progress.lock().await.insert(stand_in, (session.map(str::to_string), client_token));
+                    Some(stand_in)
+                }
+                None => None,
+            };
+
+            let (reply, rx) = oneshot::channel();
+            let pending = Pending { session: session.map(str::to_string), client_id: client_id.clone(), progress_token, reply };
+            self.pending.lock().await.insert(id, pending);
+            waiters.push((id, client_id, rx, initializes));
+            self.send(&message).await?;
+        }
+
+        let mut responses = Vec::with_capacity(waiters.len());
+        for (id, client_id, rx, initializes) in waiters {
+            match timeout(RESPONSE_TIMEOUT, rx).await {
+                Ok(Ok(response)) => {
+                    if let Some(result) = response.get("result").filter(|_| initializes) {
+                        self.initialized.lock().await.get_or_insert_with(|| result.clone());
+                    }
+                    responses.push(response);
+                }
+                _ => {
+                    if let Some(token) = self.pending.lock().await.remove(&id).and_then(|pending| pending.progress_token) {
+                        self.progress.lock().await.remove(&token);
+                    }
+                    return Err(anyhow::anyhow!("MCP server did not respond to request {}", client_id));
+                }
+            }
+        }
+
+        Ok(responses)
+    }
+
+    /// Send a request of the gateway's own and wait for the full response message
+    pub async fn request(&self, method: &str, params: Option<Value>) -> Result<Value> {
+        let mut message = json!({"jsonrpc": "2.0", "id": 0, "method": method});
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+
+        self.forward(None, &[message])
+            .await?
+            .pop()
+            .context("MCP server returned no response")
+    }
+
+    /// Server-initiated messages (notifications and requests) plus unmatched responses,
+    /// with the session each is for
+    pub fn messages(&self) -> broadcast::Receiver<Routed> {
+        self.events.subscribe()
+    }
+
+    /// Deliver a message to `session`'s event stream
+    fn publish(&self, session: Option<SessionId>, message: Value) {
+        let _ = self.events.send(Routed { session, message });
+    }
+
+    /// Event stream of one session, or of clients without one
+    fn subscribe(&self, session: Option<SessionId>) -> impl Stream<Item = Result<Event, Infallible>> {
+        BroadcastStream::new(self.events.subscribe()).filter_map(move |routed| {
+            // Lagging subscribers skip what they missed rather than disconnecting
+            let routed = routed.ok()?;
+            if routed.session.is_some() && routed.session != session {
+                return None;
+            }
+            Some(Ok(Event::default().event("message").data(routed.message.to_string())))
+        })
+    }
+}
+
+/// Start a server process with piped stdin and stdout
+fn start_server(mut command: Command) -> Result<(Child, ChildStdin, ChildStdout)> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start MCP server")?;
+
+    let stdin = child.stdin.take().context("Failed to capture server stdin")?;
+    let stdout = child.stdout.take().context("Failed to capture server stdout")?;
+    Ok((child, stdin, stdout))
+}
+
+/// Key identifying a request that expects a response (has both `id` and `method`)
+pub(crate) fn request_key(message: &Value) -> Option<String> {
+    if message.get("method").is_some() {
+        message.get("id").map(|id| id.to_string())
+    } else {
+        None
+    }
+}
+
+/// Key identifying a response to an earlier request (has `id` but no `method`)
+fn response_key(message: &Value) -> Option<String> {
+    if message.get("method").is_none() {
+        message.get("id").map(|id| id.to_string())
+    } else {
+        None
+    }
+}
+
+/// The HTTP side of the gateway: the backend and its client sessions
+struct Gateway {
+    backend: Arc<StdioBackend>,
+    sessions: std::sync::Mutex<HashSet<SessionId>>,
+}
+
+impl Gateway {
+    fn open_session(&self) -> SessionId {
+        let session = uuid::Uuid::new_v4().to_string();
+        self.sessions.lock().unwrap().insert(session.clone());
+        session
+    }
+
+    fn end_session(&self, session: &str) -> bool {
+        self.sessions.lock().unwrap().remove(session)
+    }
+
+    /// The session a request names, `Ok(None)` for none, or 404 for one that doesn't exist
+    fn session(&self, session: Option<&str>) -> Result<Option<SessionId>, StatusCode> {
+        match session {
+            Some(session) if self.sessions.lock().unwrap().contains(session) => Ok(Some(session.to_string())),
+            Some(_) => Err(StatusCode::NOT_FOUND),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Ends a legacy SSE session when its stream is dropped, i.e. the client disconnected
+struct SessionGuard {
+    gateway: Arc<Gateway>,
+    session: SessionId,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.gateway.end_session(&self.session);
+    }
+}
+
+/// Whether a browser page from `origin` may use the gateway: only pages served from this
+/// machine, so a website can't reach the server through the user's browser (DNS rebinding).
+/// Clients that aren't browsers send no `Origin` and are let through.
+fn allowed_origin(origin: &str) -> bool {
+    let Some((_, rest)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match rest.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => rest.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Reject requests whose `Origin` isn't local with 403
+async fn check_origin(request: Request, next: Next) -> Response {
+    let origin = request.headers().get(header::ORIGIN).map(|value| value.to_str().unwrap_or_default());
+    match origin {
+        Some(origin) if !allowed_origin(origin) => {
+            warn!("Rejected gateway request from origin {}", origin);
+            StatusCode::FORBIDDEN.into_response()
+        }
+        _ => next.run(request).await,
+    }
+}
+
+/// Build the HTTP routes for a backend
+///
+/// - `POST /mcp`, `GET /mcp` and `DELETE /mcp`: streamable HTTP transport. An `initialize`
+///   starts a session, named by the `Mcp-Session-Id` header of the response
+/// - `GET /sse` and `POST /messages?sessionId=...`: legacy HTTP+SSE transport, a session per stream
+///
+/// Requests from browser pages that aren't served from this machine are refused with 403.
+pub fn router(backend: Arc<StdioBackend>) -> Router {
+    let gateway = Arc::new(Gateway { backend, sessions: std::sync::Mutex::new(HashSet::new()) });
+    Router::new()
+        .route("/mcp", post(handle_streamable_post).get(handle_event_stream).delete(handle_session_end))
+        .route("/sse", get(handle_legacy_sse))
+        .route(LEGACY_MESSAGES_PATH, post(handle_legacy_post))
+        .layer(middleware::from_fn(check_origin))
+        .with_state(gateway)
+}
+
+fn session_header(headers: &HeaderMap) -> Option<&str> {
+    headers.get(SESSION_HEADER).and_then(|value| value.to_str().ok())
+}
+
+async fn handle_streamable_post(
+    State(gateway): State<Arc<Gateway>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let (messages, is_batch) = match body {
+        Value::Array(messages) => (messages, true),
+        message => (vec![message], false),
+    };
+    let initializes = messages.iter().any(|message| message.get("method").and_then(Value::as_str) == Some("initialize"));
+    let session = match gateway.session(session_header(&headers)) {
+        Ok(None) if initializes => Some(gateway.open_session()),
+        Ok(session) => session,
+        Err(status) => return status.into_response(),
+    };
+
+    let mut response = match gateway.backend.forward(session.as_deref(), &messages).await {
+        // Only notifications or responses were sent
+        Ok(responses) if responses.is_empty() => StatusCode::ACCEPTED.into_response(),
+        Ok(mut responses) if !is_batch => Json(responses.remove(0)).into_response(),
+        Ok(responses) => Json(Value::Array(responses)).into_response(),
+        Err(e) => {
+            warn!("Gateway request failed: {}", e);
+            return (StatusCode::BAD_GATEWAY, e.to_string()).into_response();
+        }
+    };
+    if let Some(value) = session.filter(|_| initializes).and_then(|session| session.parse().ok()) {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+async fn handle_event_stream(State(gateway): State<Arc<Gateway>>, headers: HeaderMap) -> Response {
+    match gateway.session(session_header(&headers)) {
+        Ok(session) => Sse::new(gateway.backend.subscribe(session)).keep_alive(KeepAlive::default()).into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+async fn handle_session_end(State(gateway): State<Arc<Gateway>>, headers: HeaderMap) -> StatusCode {
+    match session_header(&headers) {
+        Some(session) if gateway.end_session(session) => StatusCode::OK,
+        Some(_) => StatusCode::NOT_FOUND,
+        None => StatusCode::BAD_REQUEST,
+    }
+}
+
+async fn handle_legacy_sse(State(gateway): State<Arc<Gateway>>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let session = gateway.open_session();
+    let endpoint = format!("{}?sessionId={}", LEGACY_MESSAGES_PATH, session);
+    let endpoint = tokio_stream::once(Ok(Event::default().event("endpoint").data(endpoint)));
+    let events = gateway.backend.subscribe(Some(session.clone()));
+    // The stream owns the guard, so the session ends when the client disconnects
+    let guard = SessionGuard { gateway, session };
+    let stream = endpoint.chain(events).map(move |event| {
+        let _ = &guard;
+        event
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn handle_legacy_post(
+    State(gateway): State<Arc<Gateway>>,
+    Query(query): Query<HashMap<String, String>>,
+    Json(message): Json<Value>,
+) -> Response {
+    let session = match gateway.session(query.get("sessionId").map(String::as_str)) {
+        Ok(session) => session,
+        Err(status) => return status.into_response(),
+    };
+    // Responses are delivered over the client's SSE stream, so don't wait here
+    tokio::spawn(async move {
+        match gateway.backend.forward(session.as_deref(), std::slice::from_ref(&message)).await {
+            Ok(responses) => {
+                for response in responses {
+                    gateway.backend.publish(session.clone(), response);
+                }
+            }
+            Err(e) => {
+                warn!("Gateway request failed: {}", e);
+                if let Some(id) = message.get("id").filter(|_| request_key(&message).is_some()) {
+                    let error = json!({"jsonrpc": "2.0", "id": id, "error": {"code": -32603, "message": e.to_string()}});
+                    gateway.backend.publish(session, error);
+                }
+            }
+        }
+    });
+    StatusCode::ACCEPTED.into_response()
+}
+
+/// Run a container and serve it to MCP clients over HTTP until it exits or Ctrl+C is pressed
+pub async fn serve_stdio_container(options: &StdioRunOptions, addr: SocketAddr) -> Result<()> {
+    cache::record_run(&options.image_name);
+    let (command, container_name) = options.supervised_command();
+    let (backend, mut child) = StdioBackend::spawn(command)?;
+    let restart = Some(options.restart).filter(|policy| *policy != RestartPolicy::No);
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind gateway to {}", addr))?;
+    info!("MCP gateway listening on {}", addr);
+
+    let server = axum::serve(listener, router(backend.clone()));
+
+    // Ctrl+C or SIGTERM stops the container; the gateway ends when it exits for good
+    let session = async {
+        tokio::select! {
+            result = server => {
+                result.context("Gateway server failed")?;
+                Ok(())
+            }
+            result = async {
+                match restart {
+                    Some(policy) => supervisor::supervise(&backend, child, options, &container_name, policy).await,
+                    None => ContainerExit::check(child.wait().await?, Vec::new()),
+                }
+            } => result,
+        }
+    };
+
+    match shutdown::run_until_signal(session, std::slice::from_ref(&container_name)).await {
+        Err(e) if e.is::<shutdown::Interrupted>() => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_message_keys() {
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"});
+        let notification = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        let response = json!({"jsonrpc": "2.0", "id": "abc", "result": {}});
+
+        assert_eq!(request_key(&request), Some("1".to_string()));
+        assert_eq!(request_key(&notification), None);
+        assert_eq!(request_key(&response), None);
+
+        assert_eq!(response_key(&response), Some("\"abc\"".to_string()));
+        assert_eq!(response_key(&request), None);
+        assert_eq!(response_key(&notification), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_streamable_post_round_trip() {
+        // Turn each request into a response with the same id
+        let mut command = Command::new("sed");
+        command.args(["-u", "s/\"method\":\"ping\"/\"result\":{}/"]);
+        let (backend, _child) = StdioBackend::spawn(command).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router(backend)).await.unwrap();
+        });
+
+        let body = r#"{"jsonrpc":"2.0","id":7,"method":"ping"}"#;
+        let request = format!(
+            "POST /mcp HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            addr, body.len(), body
+        );
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"), "unexpected response: {}", response);
+        assert!(response.contains(r#""id":7"#));
+        assert!(response.contains(r#""result":{}"#));
+    }
+
+    #[test]
+    fn test_allowed_origin() {
+        assert!(allowed_origin("http://localhost:3000"));
+        assert!(allowed_origin("http://127.0.0.1"));
+        assert!(allowed_origin("http://[::1]:3333"));
+        assert!(!allowed_origin("https://example.com"));
+        assert!(!allowed_origin("http://localhost.example.com"));
+        assert!(!allowed_origin("null"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_foreign_origin_rejected() {
+        let (backend, _child) = StdioBackend::spawn(Command::new("cat")).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router(backend)).await.unwrap();
+        });
+
+        let body = r#"{"jsonrpc":"2.0","id":7,"method":"ping"}"#;
+        let request = format!(
+            "POST /mcp HTTP/1.1\r\nHost: {}\r\nOrigin: https://example.com\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            addr, body.len(), body
+        );
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 403"), "unexpected response: {}", response);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_legacy_session_ends_with_stream() {
+        let (backend, _child) = StdioBackend::spawn(Command::new("cat")).unwrap();
+        let gateway = Arc::new(Gateway { backend, sessions: std::sync::Mutex::new(HashSet::new()) });
+
+        let sse = handle_legacy_sse(State(gateway.clone())).await;
+        assert_eq!(gateway.sessions.lock().unwrap().len(), 1);
+        drop(sse);
+        assert!(gateway.sessions.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_server_initialized_once() {
+        // Answers the first initialize, then treats the next line as a ping
+        let mut command = Command::new("sh");
+        command.args(["-c", r#"read a; printf '%s\n' "$a" | sed 's/"method":"initialize"/"result":{"first":true}/'; read b; printf '%s\n' "$b" | sed 's/"method":"[a-z]*"/"result":{}/'; sleep 1"#]);
+        let (backend, _child) = StdioBackend::spawn(command).unwrap();
+
+        let initialize = |id: u64| json!({"jsonrpc": "2.0", "id": id, "method": "initialize", "params": {}});
+        let first = backend.forward(Some("session-a"), &[initialize(1)]).await.unwrap();
+        let second = backend.forward(Some("session-b"), &[initialize(2)]).await.unwrap();
+        assert_eq!(second[0]["id"], json!(2));
+        assert_eq!(second[0]["result"], first[0]["result"]);
+
+        // The second initialize never reached the server, so it answers this ping
+        let ping = json!({"jsonrpc": "2.0", "id": 3, "method": "ping"});
+        let pong = timeout(Duration::from_secs(5), backend.forward(Some("session-b"), &[ping])).await.unwrap().unwrap();
+        assert_eq!(pong[0]["id"], json!(3));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_clients_reusing_ids() {
+        // Answers only once both requests are in, in reverse order
+        let mut command = Command::new("sh");
+        command.args(["-c", r#"read a; read b; printf '%s\n%s\n' "$b" "$a" | sed 's/"method":"ping"/"result":{}/'"#]);
+        let (backend, _child) = StdioBackend::spawn(command).unwrap();
+
+        let ping = |client: &str| json!({"jsonrpc": "2.0", "id": 1, "method": "ping", "params": {"client": client}});
+        let (ping_a, ping_b) = (ping("a"), ping("b"));
+        let (a, b) = tokio::join!(
+            backend.forward(Some("session-a"), std::slice::from_ref(&ping_a)),
+            backend.forward(Some("session-b"), std::slice::from_ref(&ping_b)),
+        );
+        for (responses, client) in [(a.unwrap(), "a"), (b.unwrap(), "b")] {
+            assert_eq!(responses.len(), 1);
+            assert_eq!(responses[0]["id"], json!(1));
+            assert_eq!(responses[0]["params"]["client"], json!(client));
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_progress_goes_to_its_session() {
+        // Reports progress on the request's token, then answers it
+        let mut command = Command::new("sh");
+        command.args(["-c", r#"read a; token=$(printf '%s' "$a" | sed 's/.*"progressToken":\([0-9]*\).*/\1/'); printf '{"jsonrpc":"2.0","method":"notifications/progress","params":{"progressToken":%s,"progress":1}}\n' "$token"; printf '%s\n' "$a" | sed 's/"method":"tools\/call"/"result":{}/'; sleep 1"#]);
+        let (backend, _child) = StdioBackend::spawn(command).unwrap();
+        let mut messages = backend.messages();
+
+        let call = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"_meta": {"progressToken": "mine"}}});
+        backend.forward(Some("session-a"), &[call]).await.unwrap();
+        let routed = messages.recv().await.unwrap();
+        assert_eq!(routed.session.as_deref(), Some("session-a"));
+        assert_eq!(routed.message["params"]["progressToken"], json!("mine"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reattach_replays_handshake() {
+        let echo = || {
+            let mut command = Command::new("sed");
+            command.args(["-u", "s/\"method\":\"initialize\"/\"result\":{}/"]);
+            command
+        };
+        let (backend, mut child) = StdioBackend::spawn(echo()).unwrap();
+        let initialize = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2025-06-18"}});
+        backend.forward(None, &[initialize]).await.unwrap();
+
+        // The server goes away: sends fail until a new one is attached
+        child.kill().await.unwrap();
+        backend.detach().await;
+        assert!(backend.send(&json!({"jsonrpc": "2.0", "method": "ping"})).await.is_err());
+
+        let _child = backend.reattach(echo()).await.unwrap();
+        backend.replay_handshake().await.unwrap();
+    }
+}
@@ -0,0 +1,262 @@
+use std::process::Stdio;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use log::debug;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::Child;
+use tokio::time::{timeout, Duration};
+
+use crate::finch::client::{ContainerExit, StdioRunOptions};
+use crate::finch::shutdown;
+use crate::mcp::startup::{capture_stderr_tail, collect_stderr_tail};
+use crate::mcp::trace::{self, Direction, McpTracer};
+
+/// JSON-RPC error code reported to the client when the server fails the check
+const SERVER_ERROR: i64 = -32000;
+
+/// Check that a message is a successful `initialize` response to `expected_id`
+pub fn validate_initialize_response(message: &Value, expected_id: &Value) -> Result<(), String> {
+    if message.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err("response is not JSON-RPC 2.0".to_string());
+    }
+    if message.get("id") != Some(expected_id) {
+        return Err(format!("response id {} does not match request id {}",
+            message.get("id").unwrap_or(&Value::Null), expected_id));
+    }
+    if let Some(error) = message.get("error") {
+        let reason = error.get("message").and_then(Value::as_str).unwrap_or("unknown error");
+        return Err(format!("server rejected initialize: {}", reason));
+    }
+
+    let result = message.get("result").ok_or("response has neither result nor error")?;
+    if result.get("protocolVersion").and_then(Value::as_str).is_none() {
+        return Err("initialize result is missing protocolVersion".to_string());
+    }
+    Ok(())
+}
+
+/// Run a container in MCP mode, only splicing the client through once it answers `initialize`
+pub async fn run_with_health_check(options: &StdioRunOptions, startup_timeout: Duration, tracer: Option<Arc<McpTracer>>) -> Result<()> {
+    let (mut command, container_name) = options.supervised_command();
+    let child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start finch")?;
+
+    let session = health_checked_session(tokio::io::stdin(), tokio::io::stdout(), child, startup_timeout, tracer);
+    shutdown::run_until_signal(session, &[container_name]).await
+}
+
+/// Forward the client's `initialize`, validate the reply, then copy bytes both ways until the
+/// server exits, recording every frame to `tracer`
+pub async fn health_checked_session<R, W>(
+    client_in: R,
+    mut client_out: W,
+    mut child: Child,
+    startup_timeout: Duration,
+    tracer: Option<Arc<McpTracer>>,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let mut server_in = child.stdin.take().context("Failed to capture server stdin")?;
+    let mut server_out = BufReader::new(child.stdout.take().context("Failed to capture server stdout")?);
+
+    // Pass server stderr through, keeping the tail for diagnostics
+    let (stderr_tail, stderr_task) = capture_stderr_tail(child.stderr.take());
+
+    let mut client_in = BufReader::new(client_in);
+    let mut first_line = String::new();
+    if client_in.read_line(&mut first_line).await? == 0 {
+        // Client went away before saying anything
+        let _ = child.kill().await;
+        return Ok(());
+    }
+
+    let initialize_id = serde_json::from_str::<Value>(first_line.trim())
+        .ok()
+        .filter(|message| message.get("method").and_then(Value::as_str) == Some("initialize"))
+        .and_then(|message| message.get("id").cloned());
+
+    if let Some(tracer) = &tracer {
+        tracer.record(Direction::ClientToServer, &first_line);
+    }
+    
+    let forwarded = async {
+        server_in.write_all(first_line.as_bytes()).await?;
+        server_in.flush().await
+    }.await;
+
+    if let Some(id) = initialize_id {
+        let failure = if let Err(e) = forwarded {
+            Some(format!("server stopped reading input: {}", e))
+        } else {
+            let check = timeout(startup_timeout, wait_for_response(&mut server_out, &mut client_out, &id, tracer.as_deref())).await;
+            match check {
+                Ok(Ok(response)) => match validate_initialize_response(&response, &id) {
+                    Ok(()) => {
+                        debug!("MCP server passed the initialize health check");
+                        write_line(&mut client_out, &response, tracer.as_deref()).await?;
+                        None
+                    }
+                    Err(reason) => Some(reason),
+                },
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(_) => Some(format!("no initialize response within {}s", startup_timeout.as_secs())),
+            }
+        };
+
+        if let Some(reason) = failure {
+            let _ = child.kill().await;
+            let tail = collect_stderr_tail(&stderr_tail, stderr_task).await;
+
+            let mut diagnostic = format!("MCP server failed health check: {}", reason);
+            if !tail.is_empty() {
+                diagnostic.push_str(&format!("\nLast server output:\n{}", tail.join("\n")));
+            }
+            eprintln!("❌ {}", diagnostic);
+
+            let error = json!({"jsonrpc": "2.0", "id": id, "error": {"code": SERVER_ERROR, "message": diagnostic}});
+            let _ = write_line(&mut client_out, &error, tracer.as_deref()).await;
+            return Err(anyhow::anyhow!("MCP server failed health check: {}", reason));
+        }
+    } else {
+        forwarded?;
+        debug!("First client message was not initialize; skipping health check");
+    }
+
+    // Splice the rest of the session through
+    let to_server = tokio::spawn(trace::splice_lines(client_in, server_in, Direction::ClientToServer, tracer.clone()));
+    let to_client = tokio::spawn(trace::splice_lines(server_out, client_out, Direction::ServerToClient, tracer));
+
+    let status = child.wait().await?;
+    let _ = to_client.await;
+    // The client may still hold its end open; nothing left to forward to
+    to_server.abort();
+
+    let tail = collect_stderr_tail(&stderr_tail, stderr_task).await;
+    ContainerExit::check(status, tail)
+}
+
+/// Read server lines until the response to `id`, passing anything else (e.g. log notifications) to the client
+async fn wait_for_response<S, W>(server_out: &mut BufReader<S>, client_out: &mut W, id: &Value, tracer: Option<&McpTracer>) -> Result<Value>
+where
+    S: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        let mut line = String::new();
+        if server_out.read_line(&mut line).await? == 0 {
+            return Err(anyhow::anyhow!("server exited before answering initialize"));
+        }
+
+        match serde_json::from_str::<Value>(line.trim()) {
+            Ok(message) if message.get("method").is_none() && message.get("id") == Some(id) => {
+                return Ok(message);
+            }
+            Ok(message) => write_line(client_out, &message, tracer).await?,
+            Err(_) => {
+                return Err(anyhow::anyhow!("server wrote non-JSON output to stdout: {}", line.trim()));
+            }
+        }
+    }
+}
+
+/// Write a message to the client, tracing it if `--trace-mcp` is on
+async fn write_line<W: AsyncWrite + Unpin>(out: &mut W, message: &Value, tracer: Option<&McpTracer>) -> Result<()> {
+    let mut line = message.to_string();
+    if let Some(tracer) = tracer {
+        tracer.record(Direction::ServerToClient, &line);
+    }
+    line.push('\n');
+    out.write_all(line.as_bytes()).await?;
+    out.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::process::Command;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_validate_initialize_response() {
+        let id = json!(1);
+        let ok = json!({"jsonrpc": "2.0", "id": 1, "result": {"protocolVersion": "2024-11-05", "capabilities": {}}});
+        assert!(validate_initialize_response(&ok, &id).is_ok());
+
+        let wrong_id = json!({"jsonrpc": "2.0", "id": 2, "result": {"protocolVersion": "2024-11-05"}});
+        assert!(validate_initialize_response(&wrong_id, &id).is_err());
+
+        let rejected = json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32600, "message": "bad version"}});
+        assert!(validate_initialize_response(&rejected, &id).unwrap_err().contains("bad version"));
+
+        let incomplete = json!({"jsonrpc": "2.0", "id": 1, "result": {}});
+        assert!(validate_initialize_response(&incomplete, &id).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failing_server_reports_error_to_client() {
+        let child = Command::new("sh")
+            .args(["-c", "echo 'ModuleNotFoundError: no module named mcp' >&2; exit 1"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let (mut client, session_side) = tokio::io::duplex(4096);
+        let (session_in, session_out) = tokio::io::split(session_side);
+
+        client.write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\",\"params\":{}}\n").await.unwrap();
+        let result = health_checked_session(session_in, session_out, child, Duration::from_secs(5), None).await;
+        assert!(result.is_err());
+
+        let mut reply = vec![0u8; 4096];
+        let n = client.read(&mut reply).await.unwrap();
+        let reply: Value = serde_json::from_slice(&reply[..n]).unwrap();
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["error"]["code"], SERVER_ERROR);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_healthy_server_is_spliced_through() {
+        // Answer initialize, then echo everything else back
+        let child = Command::new("sed")
+            .args(["-u", r#"s/"method":"initialize"/"result":{"protocolVersion":"2024-11-05"}/"#])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let (client, session_side) = tokio::io::duplex(4096);
+        let (session_in, session_out) = tokio::io::split(session_side);
+        let (client_read, mut client_write) = tokio::io::split(client);
+        let session = tokio::spawn(health_checked_session(session_in, session_out, child, Duration::from_secs(5), None));
+
+        client_write.write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\"}\n").await.unwrap();
+        let mut client_read = BufReader::new(client_read);
+        let mut reply = String::new();
+        client_read.read_line(&mut reply).await.unwrap();
+        let reply: Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["result"]["protocolVersion"], "2024-11-05");
+
+        client_write.write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"notifications/initialized\"}\n").await.unwrap();
+        let mut echoed = String::new();
+        client_read.read_line(&mut echoed).await.unwrap();
+        assert!(echoed.contains("notifications/initialized"));
+
+        client_write.shutdown().await.unwrap();
+        assert!(session.await.unwrap().is_ok());
+    }
+}
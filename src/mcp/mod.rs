@@ -1,3 +1,10 @@
 pub mod buffer;
 pub mod proxy;
-pub mod async_proxy;
\ No newline at end of file
+pub mod async_proxy;
+pub mod gateway;
+pub mod aggregator;
+pub mod health_check;
+pub mod conformance;
+pub mod tools;
+pub mod trace;
+pub mod startup;pub mod supervisor;
@@ -117,12 +117,8 @@ impl StdioProxy {
                         Ok(n) => {
                             let data = read_buffer[..n].to_vec();
                             
-                            // Always check server messages for readiness
-                            if let Err(e) = buffer.buffer_server_message(data.clone()) {
-                                if !is_quiet_mode() {
-                                    eprintln!("Warning: Server message buffer error: {}", e);
-                                }
-                            }
+                            // Check server messages for readiness (they are written straight through)
+                            buffer.observe_server_message(&data);
                             
                             // Write to client immediately
                             if client_stdout.write_all(&data).is_err() {
@@ -133,11 +129,6 @@ impl StdioProxy {
                         Err(_) => break,
                     }
                 }
-                
-                // Flush any remaining server messages
-                for message in buffer.drain_server_buffer() {
-                    let _ = client_stdout.write_all(&message);
-                }
                 let _ = client_stdout.flush();
             })
         };
@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use log::debug;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Duration};
+
+use crate::finch::client::{ContainerExit, StdioRunOptions};
+use crate::finch::shutdown;
+use crate::logging::LogManager;
+use crate::mcp::trace::{Direction, McpTracer};
+
+/// Number of server stderr lines kept for failure diagnostics
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Last lines a server wrote to stderr
+pub type StderrTail = Arc<Mutex<VecDeque<String>>>;
+
+/// Returned when a server stays silent past the startup timeout
+#[derive(Debug)]
+pub struct StartupTimeout {
+    /// Human-readable explanation, including the stderr tail
+    pub report: String,
+}
+
+impl fmt::Display for StartupTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.report)
+    }
+}
+
+impl std::error::Error for StartupTimeout {}
+
+/// Pass server stderr through to ours, keeping the tail for diagnostics
+pub fn capture_stderr_tail(stderr: Option<ChildStderr>) -> (StderrTail, Option<JoinHandle<()>>) {
+    let tail: StderrTail = Arc::new(Mutex::new(VecDeque::new()));
+    let task = stderr.map(|stderr| {
+        let tail = tail.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                eprintln!("{}", line);
+                push_stderr_line(&tail, line);
+            }
+        })
+    });
+    (tail, task)
+}
+
+/// Keep `line` in the tail, dropping the oldest line once it is full
+pub fn push_stderr_line(tail: &StderrTail, line: String) {
+    let mut tail = tail.lock().unwrap();
+    if tail.len() == STDERR_TAIL_LINES {
+        tail.pop_front();
+    }
+    tail.push_back(line);
+}
+
+/// Wait briefly for the stderr reader to drain, then return the captured tail
+pub async fn collect_stderr_tail(tail: &StderrTail, task: Option<JoinHandle<()>>) -> Vec<String> {
+    if let Some(task) = task {
+        let _ = timeout(Duration::from_secs(1), task).await;
+    }
+    let lines = tail.lock().unwrap();
+    lines.iter().cloned().collect()
+}
+
+/// Explain a startup timeout, including the server's stderr tail and where to find the build log
+pub fn startup_failure_report(image_name: &str, startup_timeout: Duration, stderr_tail: &[String], build_log: Option<&PathBuf>) -> String {
+    let mut report = format!(
+        "MCP server in {} produced no output within {}s of the client's first message",
+        image_name,
+        startup_timeout.as_secs()
+    );
+
+    if stderr_tail.is_empty() {
+        report.push_str("\nThe container wrote nothing to stderr either.");
+    } else {
+        report.push_str(&format!("\nLast container stderr:\n{}", stderr_tail.join("\n")));
+    }
+
+    match build_log {
+        Some(path) => report.push_str(&format!("\nBuild log: {}", path.display())),
+        None => report.push_str("\nRun `finch-mcp logs list` to find the build log."),
+    }
+    report
+}
+
+/// Run a container, aborting if it doesn't write to stdout within `startup_timeout` of the client's first message
+pub async fn run_with_startup_timeout(options: &StdioRunOptions, startup_timeout: Duration, tracer: Option<Arc<McpTracer>>) -> Result<()> {
+    let (mut command, container_name) = options.supervised_command();
+    let child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start finch")?;
+
+    let build_log = LogManager::new().ok()
+        .and_then(|logs| logs.latest_build_log().ok().flatten())
+        .map(|entry| entry.path);
+
+    let session = watched_session(tokio::io::stdin(), tokio::io::stdout(), child, startup_timeout, &options.image_name, build_log, tracer);
+    let result = shutdown::run_until_signal(session, &[container_name]).await;
+
+    if let Some(failure) = result.as_ref().err().and_then(|e| e.downcast_ref::<StartupTimeout>()) {
+        eprintln!("❌ {}", failure.report);
+        match LogManager::new().and_then(|logs| logs.log_run_failure(&options.image_name, &failure.report)) {
+            Ok(path) => eprintln!("📄 Details saved to {}", path.display()),
+            Err(e) => debug!("Failed to write startup failure log: {}", e),
+        }
+    }
+    result
+}
+
+/// Proxy a session, killing the server if it stays silent after the client starts talking
+pub async fn watched_session<R, W>(
+    client_in: R,
+    client_out: W,
+    mut child: Child,
+    startup_timeout: Duration,
+    image_name: &str,
+    build_log: Option<PathBuf>,
+    tracer: Option<Arc<McpTracer>>,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let server_in = child.stdin.take().context("Failed to capture server stdin")?;
+    let server_out = child.stdout.take().context("Failed to capture server stdout")?;
+    let (stderr_tail, stderr_task) = capture_stderr_tail(child.stderr.take());
+
+    let (client_started_tx, client_started) = oneshot::channel();
+    let (server_output_tx, server_output) = oneshot::channel();
+    let to_server = tokio::spawn(forward_lines(client_in, server_in, Direction::ClientToServer, client_started_tx, tracer.clone()));
+    let to_client = tokio::spawn(forward_lines(server_out, client_out, Direction::ServerToClient, server_output_tx, tracer));
+
+    // The clock starts with the client's first message; idle servers are silent until asked
+    let watchdog = async {
+        if client_started.await.is_err() {
+            return true;
+        }
+        timeout(startup_timeout, server_output).await.is_ok()
+    };
+
+    let status = tokio::select! {
+        status = child.wait() => status?,
+        responded = watchdog => {
+            if !responded {
+                let _ = child.kill().await;
+                to_server.abort();
+                let tail = collect_stderr_tail(&stderr_tail, stderr_task).await;
+                let report = startup_failure_report(image_name, startup_timeout, &tail, build_log.as_ref());
+                return Err(StartupTimeout { report }.into());
+            }
+            child.wait().await?
+        }
+    };
+
+    let _ = to_client.await;
+    to_server.abort();
+
+    let tail = collect_stderr_tail(&stderr_tail, stderr_task).await;
+    ContainerExit::check(status, tail)
+}
+
+/// Copy lines from `reader` to `writer`, tracing each one and signalling when the first arrives
+async fn forward_lines<R, W>(reader: R, mut writer: W, direction: Direction, first_line: oneshot::Sender<()>, tracer: Option<Arc<McpTracer>>) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut first_line = Some(first_line);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            break;
+        }
+        if let Some(first_line) = first_line.take() {
+            let _ = first_line.send(());
+        }
+        if let Some(tracer) = &tracer {
+            tracer.record(direction, &line);
+        }
+        writer.write_all(line.as_bytes()).await?;
+        writer.flush().await?;
+    }
+    writer.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::process::Command;
+
+    #[test]
+    fn test_startup_failure_report() {
+        let tail = vec!["Error: Cannot find module 'dist/index.js'".to_string()];
+        let log = PathBuf::from("/tmp/local_demo_build_20240101_000000.log");
+        let report = startup_failure_report("mcp-local-demo", Duration::from_secs(10), &tail, Some(&log));
+        assert!(report.contains("within 10s"));
+        assert!(report.contains("Cannot find module"));
+        assert!(report.contains("local_demo_build"));
+
+        let report = startup_failure_report("mcp-local-demo", Duration::from_secs(10), &[], None);
+        assert!(report.contains("nothing to stderr"));
+        assert!(report.contains("finch-mcp logs list"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_silent_server_times_out() {
+        let child = Command::new("sh")
+            .args(["-c", "echo 'loading...' >&2; sleep 10"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+
+        let (mut client, session_side) = tokio::io::duplex(4096);
+        let (session_in, session_out) = tokio::io::split(session_side);
+        client.write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\"}\n").await.unwrap();
+
+        let result = timeout(
+            Duration::from_secs(5),
+            watched_session(session_in, session_out, child, Duration::from_millis(300), "mcp-test", None, None),
+        ).await;
+
+        let error = result.expect("watchdog should fire").unwrap_err();
+        let failure = error.downcast_ref::<StartupTimeout>().expect("startup timeout error");
+        assert!(failure.report.contains("loading..."));
+    }
+}
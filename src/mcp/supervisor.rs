@@ -0,0 +1,334 @@
+//! Restarting crashed servers (`--restart on-failure[:MAX]`)
+//!
+//! The container runs behind a [`StdioBackend`], so when it crashes the client's transport
+//! stays open: requests in flight fail with a JSON-RPC error, the same named container is
+//! started again after a backoff, and the client's `initialize` is replayed to it. A server
+//! that keeps crashing right after start is given up on instead of restarted forever.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use console::style;
+use log::debug;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Child;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+use crate::finch::client::{ContainerExit, StdioRunOptions};
+use crate::finch::shutdown;
+use crate::mcp::gateway::{request_key, StdioBackend};
+
+/// Delay before the first restart; doubled for each crash in a row
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest delay between restarts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A server that stays up this long is considered recovered and the backoff starts over
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+/// A crash this soon after starting counts towards the crash-loop breaker
+const CRASH_LOOP_WINDOW: Duration = Duration::from_secs(10);
+
+/// Crashes in a row within [`CRASH_LOOP_WINDOW`] of starting after which restarting stops
+const CRASH_LOOP_LIMIT: u32 = 5;
+
+/// JSON-RPC error code for requests lost while the server was down
+const SERVER_RESTARTING: i64 = -32000;
+
+/// When a server that exited is started again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    #[default]
+    No,
+    /// Restart after a non-zero exit, at most `max_restarts` times if given
+    OnFailure { max_restarts: Option<u32> },
+}
+
+impl FromStr for RestartPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "no" => Ok(Self::No),
+            None if s == "on-failure" => Ok(Self::OnFailure { max_restarts: None }),
+            Some(("on-failure", max)) => max
+                .parse()
+                .map(|max| Self::OnFailure { max_restarts: Some(max) })
+                .map_err(|_| format!("invalid restart count '{}'", max)),
+            _ => Err(format!("invalid restart policy '{}' (expected no, on-failure or on-failure:MAX)", s)),
+        }
+    }
+}
+
+impl fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::No => f.write_str("no"),
+            Self::OnFailure { max_restarts: None } => f.write_str("on-failure"),
+            Self::OnFailure { max_restarts: Some(max) } => write!(f, "on-failure:{}", max),
+        }
+    }
+}
+
+impl Serialize for RestartPolicy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RestartPolicy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// What to do after the server exited
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// Clean exit, or restarting isn't wanted
+    Exit,
+    /// Start it again after `delay`
+    Restart { attempt: u32, delay: Duration },
+    /// Stop restarting, with the reason
+    GiveUp(String),
+}
+
+/// Keeps count of restarts and crashes for a [`RestartPolicy`]
+#[derive(Debug)]
+pub struct Supervisor {
+    policy: RestartPolicy,
+    restarts: u32,
+    /// Crashes since the server last stayed up for [`STABLE_UPTIME`]
+    consecutive_crashes: u32,
+    /// Crashes in a row within [`CRASH_LOOP_WINDOW`] of starting
+    quick_crashes: u32,
+}
+
+impl Supervisor {
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self { policy, restarts: 0, consecutive_crashes: 0, quick_crashes: 0 }
+    }
+
+    /// Decide what follows an exit, given whether it succeeded and how long the server ran
+    pub fn next(&mut self, success: bool, uptime: Duration) -> Decision {
+        let RestartPolicy::OnFailure { max_restarts } = self.policy else {
+            return Decision::Exit;
+        };
+        if success {
+            return Decision::Exit;
+        }
+
+        if uptime >= STABLE_UPTIME {
+            self.consecutive_crashes = 0;
+        }
+        self.consecutive_crashes += 1;
+        if uptime < CRASH_LOOP_WINDOW {
+            self.quick_crashes += 1;
+        } else {
+            self.quick_crashes = 0;
+        }
+
+        if self.quick_crashes >= CRASH_LOOP_LIMIT {
+            return Decision::GiveUp(format!(
+                "crashed {} times in a row within {}s of starting",
+                self.quick_crashes,
+                CRASH_LOOP_WINDOW.as_secs()
+            ));
+        }
+        if max_restarts.is_some_and(|max| self.restarts >= max) {
+            return Decision::GiveUp(format!("reached the limit of {} restart(s)", self.restarts));
+        }
+
+        self.restarts += 1;
+        let delay = INITIAL_BACKOFF
+            .saturating_mul(1 << (self.consecutive_crashes - 1).min(16))
+            .min(MAX_BACKOFF);
+        Decision::Restart { attempt: self.restarts, delay }
+    }
+}
+
+/// Wait on the server, restarting it under `policy` until it exits for good or the
+/// backend is closed
+pub async fn supervise(
+    backend: &Arc<StdioBackend>,
+    mut child: Child,
+    options: &StdioRunOptions,
+    container_name: &str,
+    policy: RestartPolicy,
+) -> Result<()> {
+    let mut supervisor = Supervisor::new(policy);
+    let mut started = Instant::now();
+
+    loop {
+        let status = child.wait().await?;
+        if backend.is_closed() {
+            return ContainerExit::check(status, Vec::new());
+        }
+        backend.detach().await;
+
+        match supervisor.next(status.success(), started.elapsed()) {
+            Decision::Exit => return ContainerExit::check(status, Vec::new()),
+            Decision::GiveUp(reason) => {
+                // stdout carries the MCP session, so restarts are reported on stderr
+                eprintln!("{} Not restarting the MCP server: it {}", style("⚠️").yellow(), reason);
+                return ContainerExit::check(status, Vec::new());
+            }
+            Decision::Restart { attempt, delay } => {
+                eprintln!(
+                    "{} MCP server exited with code {}; restarting in {}s (restart {})",
+                    style("⚠️").yellow(),
+                    ContainerExit::new(status, Vec::new()).code,
+                    delay.as_secs(),
+                    attempt
+                );
+                tokio::time::sleep(delay).await;
+                // `--rm` may not have finished removing the old container under this name
+                shutdown::remove_container(container_name).await;
+
+                child = backend.reattach(options.named_command(container_name)).await?;
+                started = Instant::now();
+                if let Err(e) = backend.replay_handshake().await {
+                    // The next exit is handled like any other crash
+                    debug!("Handshake replay failed: {:#}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Run a stdio session through a backend that restarts the server under `policy`
+pub async fn run_with_restarts(options: &StdioRunOptions, policy: RestartPolicy) -> Result<()> {
+    let container_name = shutdown::session_container_name();
+    let (backend, child) = StdioBackend::spawn(options.named_command(&container_name))?;
+
+    // One writer keeps lines from the server and from failed requests whole
+    let (output, mut lines) = mpsc::unbounded_channel::<Value>();
+    tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = lines.recv().await {
+            let mut line = message.to_string().into_bytes();
+            line.push(b'\n');
+            if stdout.write_all(&line).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut messages = backend.messages();
+    let server_output = output.clone();
+    tokio::spawn(async move {
+        while let Ok(routed) = messages.recv().await {
+            if server_output.send(routed.message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let session = async {
+        let supervised = supervise(&backend, child, options, &container_name, policy);
+        tokio::pin!(supervised);
+        tokio::select! {
+            result = &mut supervised => result,
+            result = pump_client_messages(&backend, output) => {
+                // The client is gone: let the server see EOF and exit without a restart
+                backend.close().await;
+                result?;
+                supervised.await
+            }
+        }
+    };
+    shutdown::run_until_signal(session, std::slice::from_ref(&container_name)).await
+}
+
+/// Send the client's messages to the backend until stdin closes
+///
+/// Responses to requests are written by the forwarding task, so a request lost to a crash
+/// gets an error response instead of leaving the client waiting.
+async fn pump_client_messages(backend: &Arc<StdioBackend>, output: mpsc::UnboundedSender<Value>) -> Result<()> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await.context("Failed to read from stdin")? {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            debug!("Ignoring non-JSON client line: {}", line);
+            continue;
+        };
+
+        if request_key(&message).is_none() {
+            if let Err(e) = backend.send(&message).await {
+                debug!("Dropped client message while the server is down: {:#}", e);
+            }
+            continue;
+        }
+
+        let backend = backend.clone();
+        let output = output.clone();
+        tokio::spawn(async move {
+            let reply = match backend.forward(None, std::slice::from_ref(&message)).await {
+                Ok(mut responses) if !responses.is_empty() => responses.remove(0),
+                Ok(_) => return,
+                Err(e) => restarting_error(&message, &e),
+            };
+            let _ = output.send(reply);
+        });
+    }
+    Ok(())
+}
+
+/// Error response for a request the server did not answer
+fn restarting_error(request: &Value, error: &anyhow::Error) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": request["id"],
+        "error": {"code": SERVER_RESTARTING, "message": format!("MCP server unavailable: {}", error)},
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRASH: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!("no".parse(), Ok(RestartPolicy::No));
+        assert_eq!("on-failure".parse(), Ok(RestartPolicy::OnFailure { max_restarts: None }));
+        assert_eq!("on-failure:3".parse(), Ok(RestartPolicy::OnFailure { max_restarts: Some(3) }));
+        assert!("always".parse::<RestartPolicy>().is_err());
+        assert!("on-failure:x".parse::<RestartPolicy>().is_err());
+        assert_eq!(RestartPolicy::OnFailure { max_restarts: Some(3) }.to_string(), "on-failure:3");
+    }
+
+    #[test]
+    fn test_backoff_and_limits() {
+        let mut supervisor = Supervisor::new(RestartPolicy::OnFailure { max_restarts: Some(2) });
+        assert_eq!(supervisor.next(true, CRASH), Decision::Exit);
+        assert_eq!(supervisor.next(false, Duration::from_secs(20)), Decision::Restart { attempt: 1, delay: INITIAL_BACKOFF });
+        assert_eq!(supervisor.next(false, Duration::from_secs(20)), Decision::Restart { attempt: 2, delay: INITIAL_BACKOFF * 2 });
+        assert!(matches!(supervisor.next(false, Duration::from_secs(20)), Decision::GiveUp(_)));
+
+        let mut supervisor = Supervisor::new(RestartPolicy::No);
+        assert_eq!(supervisor.next(false, CRASH), Decision::Exit);
+    }
+
+    #[test]
+    fn test_crash_loop_breaker() {
+        let mut supervisor = Supervisor::new(RestartPolicy::OnFailure { max_restarts: None });
+        for attempt in 1..CRASH_LOOP_LIMIT {
+            assert!(matches!(supervisor.next(false, CRASH), Decision::Restart { attempt: a, .. } if a == attempt));
+        }
+        assert!(matches!(supervisor.next(false, CRASH), Decision::GiveUp(_)));
+
+        // Staying up resets both the breaker and the backoff
+        let mut supervisor = Supervisor::new(RestartPolicy::OnFailure { max_restarts: None });
+        for _ in 0..10 {
+            supervisor.next(false, Duration::from_secs(20));
+        }
+        assert_eq!(supervisor.next(false, STABLE_UPTIME), Decision::Restart { attempt: 11, delay: INITIAL_BACKOFF });
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use console::style;
+use serde_json::{json, Value};
+use tokio::time::{timeout, Duration};
+
+use crate::finch::client::StdioRunOptions;
+use crate::finch::shutdown;
+use crate::mcp::conformance::{handshake, validate_envelope};
+use crate::mcp::gateway::StdioBackend;
+
+/// Width the description column is cut to in the table
+const DESCRIPTION_WIDTH: usize = 60;
+
+/// Fetch every tool the server advertises, following `nextCursor` pagination
+pub async fn fetch_tools(backend: &StdioBackend, request_timeout: Duration) -> Result<Vec<Value>> {
+    let response = handshake(backend, request_timeout).await?;
+    let result = validate_envelope(&response).map_err(|e| anyhow::anyhow!("initialize failed: {}", e))?;
+    if result.get("capabilities").and_then(|c| c.get("tools")).is_none() {
+        return Ok(Vec::new());
+    }
+
+    let mut tools = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let params = cursor.as_ref().map(|cursor| json!({"cursor": cursor}));
+        let response = timeout(request_timeout, backend.request("tools/list", params))
+            .await
+            .map_err(|_| anyhow::anyhow!("no response to tools/list within {}s", request_timeout.as_secs()))??;
+        let result = validate_envelope(&response).map_err(|e| anyhow::anyhow!("tools/list failed: {}", e))?;
+
+        let page = result.get("tools").and_then(Value::as_array)
+            .context("tools/list result is missing the 'tools' array")?;
+        tools.extend(page.iter().cloned());
+
+        match result.get("nextCursor").and_then(Value::as_str) {
+            Some(next) if !next.is_empty() => cursor = Some(next.to_string()),
+            _ => break,
+        }
+    }
+
+    Ok(tools)
+}
+
+/// Start a container, list its tools, then stop it
+pub async fn list_container_tools(options: &StdioRunOptions, request_timeout: Duration) -> Result<Vec<Value>> {
+    let (command, container_name) = options.supervised_command();
+    let (backend, mut child) = StdioBackend::spawn(command)?;
+
+    let names = [container_name];
+    let tools = shutdown::run_until_signal(fetch_tools(&backend, request_timeout), &names).await;
+
+    let _ = child.kill().await;
+    shutdown::remove_container(&names[0]).await;
+    tools
+}
+
+/// One-line summary of a tool's input schema, e.g. `path*: string, limit: integer`
+pub fn parameter_summary(schema: &Value) -> String {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return String::new();
+    };
+    let required: Vec<&str> = schema.get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    properties.iter()
+        .map(|(name, property)| {
+            let marker = if required.contains(&name.as_str()) { "*" } else { "" };
+            let kind = property.get("type")
+                .map(|kind| match kind {
+                    Value::String(kind) => kind.clone(),
+                    other => other.to_string(),
+                })
+                .unwrap_or_else(|| "any".to_string());
+            format!("{}{}: {}", name, marker, kind)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Print the tools as a human-readable list
+pub fn print_tools(tools: &[Value]) {
+    if tools.is_empty() {
+        eprintln!("{}", style("The server does not advertise any tools").dim());
+        return;
+    }
+
+    eprintln!("{} {} tools\n", style("🔧").blue(), tools.len());
+    for tool in tools {
+        let name = tool.get("name").and_then(Value::as_str).unwrap_or("<unnamed>");
+        eprintln!("  {}", style(name).cyan().bold());
+
+        if let Some(description) = tool.get("description").and_then(Value::as_str) {
+            // Only the first line, trimmed to keep the listing scannable
+            let first_line = description.lines().next().unwrap_or_default().trim();
+            let shortened: String = first_line.chars().take(DESCRIPTION_WIDTH).collect();
+            let ellipsis = if first_line.chars().count() > DESCRIPTION_WIDTH { "…" } else { "" };
+            eprintln!("    {}{}", shortened, ellipsis);
+        }
+
+        let parameters = tool.get("inputSchema").map(parameter_summary).unwrap_or_default();
+        if !parameters.is_empty() {
+            eprintln!("    {} {}", style("params:").dim(), parameters);
+        }
+        eprintln!();
+    }
+    eprintln!("{}", style("* required parameter").dim());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::process::Command;
+
+    #[test]
+    fn test_parameter_summary() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "limit": {"type": "integer"},
+                "path": {"type": "string"},
+                "value": {},
+            },
+            "required": ["path"],
+        });
+        assert_eq!(parameter_summary(&schema), "limit: integer, path*: string, value: any");
+        assert_eq!(parameter_summary(&json!({"type": "object"})), "");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fetch_tools_follows_pagination() {
+        // First page points at a cursor; the request carrying it gets the last page
+        let mut command = Command::new("sed");
+        command.args([
+            "-u",
+            "-e", r#"s/"method":"initialize".*/"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"sed"}}}/"#,
+            "-e", r#"s/"method":"tools\/list","params":{"cursor":"2"}}/"result":{"tools":[{"name":"second","inputSchema":{"type":"object"}}]}}/"#,
+            "-e", r#"s/"method":"tools\/list"}/"result":{"tools":[{"name":"first","inputSchema":{"type":"object"}}],"nextCursor":"2"}}/"#,
+        ]);
+        let (backend, mut child) = StdioBackend::spawn(command).unwrap();
+
+        let tools = fetch_tools(&backend, Duration::from_secs(5)).await.unwrap();
+        let _ = child.kill().await;
+
+        let names: Vec<_> = tools.iter().map(|tool| tool["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["first", "second"]);
+    }
+}
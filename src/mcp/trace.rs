@@ -0,0 +1,204 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::finch::client::{ContainerExit, StdioRunOptions};
+use crate::finch::shutdown;
+use crate::mcp::startup;
+
+/// Replacement written in place of masked values
+const MASK: &str = "***";
+
+/// Key fragments whose values are masked in traces
+const SECRET_KEY_PATTERNS: &[&str] = &[
+    "token", "secret", "password", "passwd", "apikey", "api_key", "api-key",
+    "authorization", "credential", "private_key", "cookie",
+];
+
+/// Direction a frame travelled through the proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::ClientToServer => "client->server",
+            Direction::ServerToClient => "server->client",
+        }
+    }
+}
+
+/// Appends JSON-RPC frames to an NDJSON file
+#[derive(Debug)]
+pub struct McpTracer {
+    file: Mutex<File>,
+}
+
+impl McpTracer {
+    /// Open (or append to) a trace file
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open MCP trace file {}", path.display()))?;
+        Ok(Self { file: Mutex::new(file) })
+    }
+
+    /// Record one frame; non-JSON lines are kept as raw strings
+    pub fn record(&self, direction: Direction, line: &str) {
+        let line = line.trim_end();
+        if line.is_empty() {
+            return;
+        }
+
+        let message = match serde_json::from_str::<Value>(line) {
+            Ok(mut message) => {
+                mask_secrets(&mut message);
+                json!({"timestamp": Utc::now().to_rfc3339(), "direction": direction.as_str(), "message": message})
+            }
+            Err(_) => json!({"timestamp": Utc::now().to_rfc3339(), "direction": direction.as_str(), "raw": line}),
+        };
+
+        // Tracing must never break the session, so write errors are ignored
+        let mut file = self.file.lock().unwrap();
+        let _ = writeln!(file, "{}", message);
+        let _ = file.flush();
+    }
+}
+
+/// The tracer for a session traced to `path` (`--trace-mcp`), if there is one
+pub fn open(path: Option<&Path>) -> Result<Option<Arc<McpTracer>>> {
+    Ok(path.map(McpTracer::open).transpose()?.map(Arc::new))
+}
+
+/// Replace the values of secret-looking keys, at any depth
+pub fn mask_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) && !value.is_object() && !value.is_array() {
+                    *value = Value::String(MASK.to_string());
+                } else {
+                    mask_secrets(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(mask_secrets),
+        _ => {}
+    }
+}
+
+pub(crate) fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRET_KEY_PATTERNS.iter().any(|pattern| key.contains(pattern))
+}
+
+/// Copy newline-delimited frames from `reader` to `writer`, tracing each one
+pub async fn splice_lines<R, W>(
+    reader: R,
+    mut writer: W,
+    direction: Direction,
+    tracer: Option<Arc<McpTracer>>,
+) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            break;
+        }
+        if let Some(tracer) = &tracer {
+            tracer.record(direction, &line);
+        }
+        writer.write_all(line.as_bytes()).await?;
+        writer.flush().await?;
+    }
+    writer.shutdown().await
+}
+
+/// Run a container in MCP mode, proxying stdio so every frame is traced
+pub async fn run_traced(options: &StdioRunOptions, tracer: Arc<McpTracer>) -> Result<()> {
+    let (mut command, container_name) = options.supervised_command();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start finch")?;
+
+    let server_in = child.stdin.take().context("Failed to capture server stdin")?;
+    let server_out = child.stdout.take().context("Failed to capture server stdout")?;
+    let (stderr_tail, stderr_task) = startup::capture_stderr_tail(child.stderr.take());
+
+    let to_server = tokio::spawn(splice_lines(tokio::io::stdin(), server_in, Direction::ClientToServer, Some(tracer.clone())));
+    let to_client = tokio::spawn(splice_lines(server_out, tokio::io::stdout(), Direction::ServerToClient, Some(tracer)));
+
+    shutdown::run_until_signal(async {
+        let status = child.wait().await?;
+        let _ = to_client.await;
+        to_server.abort();
+
+        let tail = startup::collect_stderr_tail(&stderr_tail, stderr_task).await;
+        ContainerExit::check(status, tail)
+    }, &[container_name]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_mask_secrets() {
+        let mut message = json!({
+            "method": "tools/call",
+            "params": {
+                "name": "fetch",
+                "arguments": {"url": "https://example.com", "apiKey": "sk-123", "headers": [{"Authorization": "Bearer x"}]},
+            },
+        });
+        mask_secrets(&mut message);
+
+        let arguments = &message["params"]["arguments"];
+        assert_eq!(arguments["url"], "https://example.com");
+        assert_eq!(arguments["apiKey"], MASK);
+        assert_eq!(arguments["headers"][0]["Authorization"], MASK);
+        assert_eq!(message["params"]["name"], "fetch");
+    }
+
+    #[tokio::test]
+    async fn test_splice_lines_records_frames() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("trace.ndjson");
+        let tracer = Arc::new(McpTracer::open(&path).unwrap());
+
+        let input: &[u8] = b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\",\"params\":{\"token\":\"abc\"}}\nnot json\n";
+        let mut output = Vec::new();
+        splice_lines(input, &mut output, Direction::ClientToServer, Some(tracer)).await.unwrap();
+        assert_eq!(output, input);
+
+        let trace = std::fs::read_to_string(&path).unwrap();
+        let frames: Vec<Value> = trace.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0]["direction"], "client->server");
+        assert_eq!(frames[0]["message"]["params"]["token"], MASK);
+        assert_eq!(frames[1]["raw"], "not json");
+        assert!(frames[0]["timestamp"].is_string());
+    }
+}
@@ -1,27 +1,155 @@
-/// Smart output macro that respects quiet mode
-/// 
-/// This macro automatically checks for MCP_STDIO environment variable
-/// and suppresses output when in STDIO mode for clean MCP communication.
+//! Output control: how much human-readable output to print, and where
+//!
+//! One `OutputMode` is decided from the command line when the CLI starts and respected
+//! by `status!`, progress bars, spinners and the output of the `finch` commands we run.
+//! While stdout carries an MCP session or a JSON report, nothing human-readable goes there.
 
+use std::future::Future;
+use std::process::Stdio;
 use std::sync::OnceLock;
 
-/// Cache the MCP_STDIO environment variable check
-static IS_QUIET_MODE: OnceLock<bool> = OnceLock::new();
+use console::style;
+use indicatif::MultiProgress;
 
-/// Check if we're in quiet mode (MCP_STDIO is set)
-pub fn is_quiet_mode() -> bool {
-    *IS_QUIET_MODE.get_or_init(|| {
-        std::env::var("MCP_STDIO").is_ok()
+/// How much to print, decided once per process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Only errors (`--quiet`)
+    Silent,
+    /// stdout carries an MCP session: no status lines, `finch` output on stderr
+    McpSafe,
+    /// Status lines and progress bars
+    Normal,
+    /// Normal output plus debug logs (`-V`)
+    Verbose,
+    /// stdout carries a `--json` report: no status lines, `finch` output on stderr
+    Json,
+}
+
+impl OutputMode {
+    /// Whether status lines, spinners and progress bars are drawn
+    pub fn shows_status(self) -> bool {
+        matches!(self, OutputMode::Normal | OutputMode::Verbose)
+    }
+
+    /// Log level for the terminal logger
+    pub fn log_level(self, verbose: u8) -> log::LevelFilter {
+        match self {
+            OutputMode::Silent | OutputMode::McpSafe => log::LevelFilter::Error,
+            OutputMode::Json => log::LevelFilter::Warn,
+            OutputMode::Normal => log::LevelFilter::Info,
+            OutputMode::Verbose if verbose >= 2 => log::LevelFilter::Trace,
+            OutputMode::Verbose => log::LevelFilter::Debug,
+        }
+    }
+}
+
+static OUTPUT_MODE: OnceLock<OutputMode> = OnceLock::new();
+
+/// Set the output mode for this process; returns false if it was already decided
+pub fn set_output_mode(mode: OutputMode) -> bool {
+    OUTPUT_MODE.set(mode).is_ok()
+}
+
+/// The output mode; without `set_output_mode`, MCP-safe when `MCP_STDIO` is set
+pub fn mode() -> OutputMode {
+    *OUTPUT_MODE.get_or_init(|| {
+        if std::env::var("MCP_STDIO").is_ok() {
+            OutputMode::McpSafe
+        } else {
+            OutputMode::Normal
+        }
     })
 }
 
-/// Print status message only if not in quiet mode
+/// Whether status lines and progress bars are suppressed
+pub fn is_quiet_mode() -> bool {
+    !mode().shows_status()
+}
+
+/// Whether stdout carries an MCP session
+pub fn is_mcp_mode() -> bool {
+    mode() == OutputMode::McpSafe
+}
+
+/// Where a `finch` command's own output goes: the terminal normally, stderr while stdout
+/// is taken, and nowhere with `--quiet`
+pub fn child_output() -> Stdio {
+    match mode() {
+        OutputMode::Normal | OutputMode::Verbose => Stdio::inherit(),
+        OutputMode::McpSafe | OutputMode::Json => std::io::stderr().into(),
+        OutputMode::Silent => Stdio::null(),
+    }
+}
+
+tokio::task_local! {
+    /// Name of the target being built, while several build at once
+    static TARGET_LABEL: String;
+}
+
+/// Progress bars of concurrent builds, drawn one per line
+static MULTI_PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+
+/// Run one of several concurrent builds: its status lines are prefixed with
+/// `label` and its progress bar is drawn alongside the others
+pub async fn labelled<F: Future>(label: &str, future: F) -> F::Output {
+    TARGET_LABEL.scope(label.to_string(), future).await
+}
+
+/// Label of the concurrent build running on this task, if any
+pub fn target_label() -> Option<String> {
+    TARGET_LABEL.try_with(String::clone).ok()
+}
+
+/// Where concurrent builds draw their progress bars
+pub fn multi_progress() -> &'static MultiProgress {
+    MULTI_PROGRESS.get_or_init(MultiProgress::new)
+}
+
+/// Print a machine-readable payload on stdout: a `--json` report, an SBOM, a log file or
+/// its path. Apart from MCP traffic, nothing else reaches stdout: `print!` and `println!`
+/// are denied elsewhere, so human-readable output goes to stderr
+#[allow(clippy::print_stdout)]
+pub fn print_payload(payload: impl std::fmt::Display) {
+    println!("{}", payload);
+}
+
+/// Print a status line, prefixed with the build's label and kept clear of
+/// progress bars when several targets build at once
+#[doc(hidden)]
+pub fn print_status(args: std::fmt::Arguments) {
+    print_labelled(target_label().as_deref(), &args.to_string());
+}
+
+/// Print a status line for a build, e.g. from a thread following its output
+pub fn print_labelled(label: Option<&str>, line: &str) {
+    let Some(label) = label else {
+        match MULTI_PROGRESS.get() {
+            Some(multi_progress) => multi_progress.suspend(|| eprintln!("{}", line)),
+            None => eprintln!("{}", line),
+        }
+        return;
+    };
+    // Blank separator lines only make interleaved output harder to follow
+    let prefixed: Vec<String> = line.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| format!("{} {}", style(format!("[{}]", label)).dim(), line))
+        .collect();
+    if !prefixed.is_empty() {
+        multi_progress().suspend(|| eprintln!("{}", prefixed.join("\n")));
+    }
+}
+
+/// Print status message only when the output mode shows status
 /// Usage: status!("Starting server...")
 #[macro_export]
 macro_rules! status {
+    () => {
+        $crate::status!("")
+    };
     ($($arg:tt)*) => {
         if !$crate::output::is_quiet_mode() {
-            println!($($arg)*);
+            $crate::output::print_status(format_args!($($arg)*));
         }
     };
 }
@@ -30,6 +158,15 @@ macro_rules! status {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_output_modes() {
+        assert!(OutputMode::Normal.shows_status() && OutputMode::Verbose.shows_status());
+        assert!(!OutputMode::McpSafe.shows_status() && !OutputMode::Json.shows_status() && !OutputMode::Silent.shows_status());
+        assert_eq!(OutputMode::Verbose.log_level(1), log::LevelFilter::Debug);
+        assert_eq!(OutputMode::Verbose.log_level(2), log::LevelFilter::Trace);
+        assert_eq!(OutputMode::McpSafe.log_level(2), log::LevelFilter::Error);
+    }
+
     #[test]
     fn test_quiet_mode_detection() {
         // Test with environment variable unset
@@ -0,0 +1,180 @@
+//! Where finch-mcp keeps its image cache, its logs and its user config
+//!
+//! The cache directory (`entries/`, `git/`, `stats.jsonl`, ...) and the data directory
+//! (build and session logs) follow platform conventions: `~/.cache` and `~/.local/state`
+//! on Linux, `~/Library/Caches` and `~/Library/Application Support` on macOS and
+//! `%LOCALAPPDATA%` on Windows. `XDG_CACHE_HOME` and `XDG_STATE_HOME` are honored
+//! everywhere, and `--cache-dir`/`--data-dir` or `FINCH_MCP_CACHE_DIR`/`FINCH_MCP_DATA_DIR`
+//! override both. The user config (`gc`, `logs`, `caCerts`, ...) is
+//! `~/.config/finch-mcp/config.yaml`, or under `XDG_CONFIG_HOME` when it is set.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+
+const APP_DIR: &str = "finch-mcp";
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// One of the two directories and how it is located
+struct Location {
+    flag: Option<PathBuf>,
+    env_var: &'static str,
+    xdg_var: &'static str,
+    platform: fn() -> Option<PathBuf>,
+    /// Where versions before platform conventions were followed kept it, under the home directory
+    legacy: &'static [&'static str],
+}
+
+impl Location {
+    /// The explicitly chosen directory: the flag, else the variable
+    fn chosen(&self) -> Option<PathBuf> {
+        self.flag.clone().or_else(|| env_dir(self.env_var))
+    }
+
+    fn default(&self) -> Option<PathBuf> {
+        match env_dir(self.xdg_var) {
+            Some(xdg) => Some(xdg.join(APP_DIR)),
+            None => self.platform_default(),
+        }
+    }
+
+    fn platform_default(&self) -> Option<PathBuf> {
+        (self.platform)().map(|dir| dir.join(APP_DIR))
+    }
+
+    fn legacy(&self) -> Option<PathBuf> {
+        if cfg!(not(unix)) {
+            return None;
+        }
+        dirs::home_dir().map(|home| self.legacy.iter().fold(home, |dir, part| dir.join(part)).join(APP_DIR))
+    }
+}
+
+fn env_dir(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var).filter(|value| !value.is_empty()).map(PathBuf::from)
+}
+
+fn cache_location(flag: Option<PathBuf>) -> Location {
+    Location { flag, env_var: "FINCH_MCP_CACHE_DIR", xdg_var: "XDG_CACHE_HOME", platform: dirs::cache_dir, legacy: &[".cache"] }
+}
+
+fn data_location(flag: Option<PathBuf>) -> Location {
+    let platform = || dirs::state_dir().or_else(dirs::data_local_dir);
+    Location { flag, env_var: "FINCH_MCP_DATA_DIR", xdg_var: "XDG_STATE_HOME", platform, legacy: &[".local", "state"] }
+}
+
+/// Apply `--cache-dir`/`--data-dir` for this process, then move data left in the
+/// pre-platform-convention locations (macOS used `~/.cache` and `~/.local/state`) into
+/// the default ones. Returns what was moved, from where to where
+pub fn configure(cache_dir: Option<PathBuf>, data_dir: Option<PathBuf>) -> Vec<(PathBuf, PathBuf)> {
+    let mut migrated = Vec::new();
+    for (location, cell) in [(cache_location(cache_dir), &CACHE_DIR), (data_location(data_dir), &DATA_DIR)] {
+        if let Some(dir) = location.chosen() {
+            let _ = cell.set(dir);
+        } else if env_dir(location.xdg_var).is_none() {
+            // With XDG variables set, earlier versions already used them
+            if let (Some(legacy), Some(default)) = (location.legacy(), location.platform_default()) {
+                if migrate(&legacy, &default) {
+                    migrated.push((legacy, default));
+                }
+            }
+        }
+    }
+    migrated
+}
+
+/// Move `legacy` to `target` when only the former exists. A failed move leaves both
+/// alone; the data is rebuilt or simply not carried over
+fn migrate(legacy: &Path, target: &Path) -> bool {
+    if legacy == target || !legacy.is_dir() || target.exists() {
+        return false;
+    }
+    if let Some(parent) = target.parent() {
+        if std::fs::create_dir_all(parent).is_err() {
+            return false;
+        }
+    }
+    std::fs::rename(legacy, target).is_ok()
+}
+
+fn resolve(cell: &OnceLock<PathBuf>, location: Location, what: &str) -> Result<PathBuf> {
+    if let Some(dir) = cell.get() {
+        return Ok(dir.clone());
+    }
+    location.chosen().or_else(|| location.default())
+        .with_context(|| format!("Could not determine the {} directory; set it with --{}-dir", what, what))
+}
+
+/// The image cache directory
+pub fn cache_dir() -> Result<PathBuf> {
+    resolve(&CACHE_DIR, cache_location(None), "cache")
+}
+
+/// The directory holding build and session logs
+pub fn data_dir() -> Result<PathBuf> {
+    resolve(&DATA_DIR, data_location(None), "data")
+}
+
+/// Path of the user config
+pub fn user_config() -> Option<PathBuf> {
+    if let Some(xdg_config) = env_dir("XDG_CONFIG_HOME") {
+        return Some(xdg_config.join(APP_DIR).join("config.yaml"));
+    }
+
+    #[cfg(unix)]
+    let base = dirs::home_dir().map(|home| home.join(".config"));
+    #[cfg(not(unix))]
+    let base = dirs::config_dir();
+
+    base.map(|dir| dir.join(APP_DIR).join("config.yaml"))
+}
+
+/// Read the sections of the user config that `T` describes, with the file's path.
+/// `None` if there is no user config
+pub fn load_user_config<T: DeserializeOwned>() -> Result<Option<(T, PathBuf)>> {
+    let Some(path) = user_config().filter(|path| path.exists()) else {
+        return Ok(None);
+    };
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let config = serde_yaml::from_str(&content)
+        .with_context(|| format!("Invalid finch-mcp config {}", path.display()))?;
+    Ok(Some((config, path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        let home = tempfile::tempdir().unwrap();
+        let legacy = home.path().join(".cache").join(APP_DIR);
+        let target = home.path().join("Library").join("Caches").join(APP_DIR);
+        std::fs::create_dir_all(legacy.join("entries")).unwrap();
+        std::fs::write(legacy.join("git-refs.json"), "{}").unwrap();
+
+        assert!(migrate(&legacy, &target));
+        assert!(!legacy.exists());
+        assert_eq!(std::fs::read_to_string(target.join("git-refs.json")).unwrap(), "{}");
+        assert!(target.join("entries").is_dir());
+
+        // Nothing left to move, or already in place: no-ops
+        assert!(!migrate(&legacy, &target));
+        assert!(!migrate(&target, &target));
+        std::fs::create_dir_all(&legacy).unwrap();
+        assert!(!migrate(&legacy, &target));
+        assert!(legacy.exists());
+    }
+
+    #[test]
+    fn test_flag_wins() {
+        let location = cache_location(Some(PathBuf::from("/srv/finch-mcp-cache")));
+        assert_eq!(location.chosen(), Some(PathBuf::from("/srv/finch-mcp-cache")));
+        assert!(location.legacy().is_none_or(|legacy| legacy.ends_with(".cache/finch-mcp")));
+    }
+}
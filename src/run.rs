@@ -4,11 +4,18 @@ use log::{info, error};
 use std::path::Path;
 use tokio::signal::ctrl_c;
 
-use crate::finch::client::{FinchClient, StdioRunOptions};
+use crate::error::FinchMcpError;
+use crate::events::Events;
+use crate::finch::client::{FinchClient, NetworkMode, StdioRunOptions};
+use crate::finch::pull::{self, PullPolicy};
+use crate::mcp::supervisor::RestartPolicy;
 use crate::output;
+use crate::utils::offline;
+use crate::utils::progress::build_policy;
+use crate::utils::user_map::UserMap;
 
 /// Options for running an MCP server container in STDIO mode
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct RunOptions {
     /// Name of the image to run
     pub image_name: String,
@@ -18,6 +25,39 @@ pub struct RunOptions {
     
     /// Volume mounts for the container
     pub volumes: Option<Vec<String>>,
+    
+    /// Network mode for the container
+    pub network: NetworkMode,
+    
+    /// Port mappings to publish (`[IP:]HOST:CONTAINER[/PROTO]`)
+    pub publish: Option<Vec<String>>,
+    
+    /// Arguments for the server, passed after the image name
+    pub args: Vec<String>,
+    
+    /// When to pull the image before running it
+    pub pull: PullPolicy,
+    
+    /// Never touch the network: fail instead of pulling, and run with no network (`--offline`)
+    pub offline: bool,
+    
+    /// In MCP mode, check that the server answers `initialize` before connecting the client (`--health-check`)
+    pub health_check: bool,
+    
+    /// Abort if the server writes nothing to stdout this long after the client's first message (`--startup-timeout`)
+    pub startup_timeout: Option<std::time::Duration>,
+    
+    /// In MCP mode, record every JSON-RPC frame to this NDJSON file (`--trace-mcp`)
+    pub trace_mcp: Option<std::path::PathBuf>,
+    
+    /// Pass the host's proxy variables to builds and the container (`--forward-proxy`)
+    pub forward_proxy: bool,
+    
+    /// Leave the host's timezone and locale out of images and the container (`--no-forward-timezone`)
+    pub no_forward_timezone: bool,
+    
+    /// Restart the server when it exits with an error (`--restart`)
+    pub restart: RestartPolicy,
 }
 
 /// Spinner helper for console output
@@ -57,7 +97,11 @@ impl Spinner {
 }
 
 /// Run an MCP server container in STDIO mode
-pub async fn run_stdio_container(options: RunOptions) -> Result<()> {
+pub async fn run_stdio_container(options: RunOptions) -> crate::error::Result<()> {
+    run_stdio_container_inner(options).await.map_err(FinchMcpError::from)
+}
+
+async fn run_stdio_container_inner(options: RunOptions) -> Result<()> {
     let spinner = Spinner::new("Preparing to run MCP server container in STDIO mode...");
     
     // Create Finch client
@@ -66,19 +110,24 @@ pub async fn run_stdio_container(options: RunOptions) -> Result<()> {
     // Check if Finch is available
     if !finch_client.is_finch_available().await? {
         spinner.fail("Finch is not installed or not available");
-        return Err(anyhow::anyhow!("Finch is not installed or not available. Please install Finch from https://runfinch.com/"));
+        return Err(FinchMcpError::FinchNotInstalled.into());
     }
     
-    // Check if the image exists
-    if !finch_client.image_exists(&options.image_name).await? {
+    // Pull progress is printed line by line, which the spinner would draw over
+    spinner.progress.finish_and_clear();
+    if let Err(e) = pull::ensure_image(&finch_client, &options.image_name, options.pull, options.offline, &build_policy(None, None)).await {
+        if !matches!(e.downcast_ref(), Some(FinchMcpError::ImageNotFound { .. })) {
+            spinner.fail("Failed to pull the container image");
+            return Err(e);
+        }
         spinner.fail("Container image not found");
         use console::style;
         eprintln!("{} Container image not found: {}", style("❌").red(), style(&options.image_name).yellow());
         eprintln!("\n{} Available options:", style("💡").yellow());
         eprintln!("  1. Build it first: {}", style("finch-mcp build <target>").cyan());
-        eprintln!("  2. Pull from registry: {}", style(format!("finch pull {}", &options.image_name)).cyan());
+        eprintln!("  2. Pull from registry: {}", style("--pull missing").cyan());
         eprintln!("  3. List available images: {}", style("finch-mcp list").cyan());
-        return Err(anyhow::anyhow!("Container image '{}' not found", options.image_name));
+        return Err(e);
     }
     
     // Log the MCP server we're about to run
@@ -94,7 +143,17 @@ pub async fn run_stdio_container(options: RunOptions) -> Result<()> {
         image_name: options.image_name,
         env_vars: options.env_vars.unwrap_or_default(),
         volumes: options.volumes.unwrap_or_default(),
-        host_network: false, // Default to false for run command
+        network: offline::network(options.offline, &options.network),
+        publish: options.publish.unwrap_or_default(),
+        args: options.args,
+        user_map: UserMap::Never,
+        health_check: options.health_check,
+        startup_timeout: options.startup_timeout,
+        trace_mcp: options.trace_mcp,
+        forward_proxy: options.forward_proxy,
+        no_forward_timezone: options.no_forward_timezone,
+        restart: options.restart,
+        events: Events::default(),
     };
 
     // Setup signal handler for ctrl+c
@@ -122,7 +181,7 @@ pub async fn run_stdio_container(options: RunOptions) -> Result<()> {
     tokio::select! {
         result = container_task => {
             match result {
-                Ok(container_result) => container_result,
+                Ok(container_result) => Ok(container_result?),
                 Err(e) => Err(anyhow::anyhow!("Container task failed: {}", e))
             }
         }
@@ -162,7 +221,7 @@ mod tests {
         // Only run this test if Finch is installed
         let finch_client = FinchClient::new();
         if !finch_client.is_finch_available().await.unwrap_or(false) {
-            println!("Finch not available, skipping test");
+            eprintln!("Finch not available, skipping test");
             return;
         }
         
@@ -170,6 +229,17 @@ mod tests {
             image_name: "hello-world".to_string(), // Use a simple public image
             env_vars: None,
             volumes: None,
+            network: NetworkMode::default(),
+            publish: None,
+            args: Vec::new(),
+            pull: PullPolicy::Missing,
+            offline: false,
+            health_check: false,
+            startup_timeout: None,
+            trace_mcp: None,
+            forward_proxy: false,
+            no_forward_timezone: false,
+            restart: RestartPolicy::No,
         };
         
         let result = run_stdio_container(run_options).await;
@@ -0,0 +1,50 @@
+//! One ID per invocation, tying together what a run produced
+//!
+//! The ID is in build log filenames and records, the cache entries and image labels of
+//! images the run built, and build errors, so a failing run leads straight to its logs
+//! and images: `finch-mcp logs search --run 9f3c1a2b`, or
+//! `finch images --filter label=dev.finch-mcp.run-id=...`.
+
+use std::sync::OnceLock;
+
+/// Image label holding the ID of the run that built the image
+pub const LABEL: &str = "dev.finch-mcp.run-id";
+
+static RUN_ID: OnceLock<String> = OnceLock::new();
+
+/// This invocation's run ID, a UUID
+pub fn get() -> &'static str {
+    RUN_ID.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// The first eight characters of the run ID, as used in filenames and messages
+pub fn short() -> &'static str {
+    &get()[..8]
+}
+
+/// `--label` value that records this run on a built image
+pub fn label() -> String {
+    format!("{}={}", LABEL, get())
+}
+
+/// Whether `id` (a whole run ID or a prefix of one) names the run `run_id`
+pub fn matches(run_id: &str, id: &str) -> bool {
+    !id.is_empty() && (run_id.starts_with(id) || id.starts_with(run_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_id() {
+        assert_eq!(get().len(), 36);
+        assert_eq!(get(), get());
+        assert!(get().starts_with(short()));
+        assert!(label().ends_with(get()));
+        assert!(matches(get(), short()));
+        assert!(matches(short(), get()));
+        assert!(!matches(get(), ""));
+        assert!(!matches("9f3c1a2b", "0000"));
+    }
+}
@@ -0,0 +1,255 @@
+//! `finch-mcp self update`: replace this binary with the latest GitHub release
+//!
+//! The release archive for this platform is downloaded, checked against the SHA-256
+//! published with the release, unpacked next to the running binary and renamed over it,
+//! so an interrupted update never leaves a half-written binary behind.
+//!
+//! Interactive runs also print a one-line notice when a newer release exists. The
+//! release is looked up at most once a day, in the background, and never in an MCP
+//! session; `FINCH_MCP_NO_UPDATE_CHECK=1` (or `CI`) turns the lookup off.
+
+use std::fs::{self, File};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use console::style;
+use flate2::read::GzDecoder;
+use log::debug;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::CacheManager;
+use crate::finch::setup::{curl, expected_checksum, verify_checksum, Release};
+use crate::status;
+use crate::utils::offline;
+
+/// Latest finch-mcp release, as reported by the GitHub API
+const RELEASES_URL: &str = "https://api.github.com/repos/mikeyobrien/finch-mcp/releases/latest";
+
+/// The last release lookup, in the cache directory
+const CHECK_FILE: &str = "update-check.json";
+
+/// How often the notice looks up the latest release
+const CHECK_EVERY_SECS: u64 = 24 * 60 * 60;
+
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateCheck {
+    checked_at: u64,
+    latest: String,
+}
+
+/// The release archive for a platform, as the release workflow names it
+pub fn asset_name(os: &str, arch: &str) -> Option<String> {
+    match (os, arch) {
+        ("macos" | "linux", "x86_64" | "aarch64") => Some(format!("finch-mcp-{}-{}.tar.gz", os, arch)),
+        ("windows", "x86_64") => Some("finch-mcp-windows-x86_64.exe.zip".to_string()),
+        _ => None,
+    }
+}
+
+/// Whether release `latest` (`v1.2.3` or `1.2.3`) is newer than version `current`
+/// by semver precedence, so `1.0.0-rc.2` is newer than `1.0.0-rc.1` and older than `1.0.0`
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// A version or release tag as semver, with left-out minor and patch numbers as 0 (`v1.2`)
+fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches('v');
+    let split = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, rest) = version.split_at(split);
+    let missing = 2usize.saturating_sub(core.matches('.').count());
+    Version::parse(&format!("{}{}{}", core, ".0".repeat(missing), rest)).ok()
+}
+
+/// Update to the latest release, or with `check_only` just say whether there is one
+pub async fn self_update(check_only: bool, offline: bool) -> Result<()> {
+    offline::ensure_online(offline, "Looking up the latest finch-mcp release")?;
+    status!("🔎 Looking up the latest finch-mcp release...");
+    let release: Release = serde_json::from_slice(&curl(&[RELEASES_URL]).await?)
+        .context("Failed to parse the finch-mcp release information")?;
+    remember(&release.tag_name);
+
+    if !is_newer(&release.tag_name, CURRENT_VERSION) {
+        status!("✅ finch-mcp {} is up to date", CURRENT_VERSION);
+        return Ok(());
+    }
+    if check_only {
+        status!("⬆️  finch-mcp {} is available (you have {}). Update with {}", release.tag_name, CURRENT_VERSION, style("finch-mcp self update").cyan());
+        return Ok(());
+    }
+
+    let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+    let name = asset_name(os, arch)
+        .with_context(|| format!("No finch-mcp release binary for {} ({}); build it with `cargo install --git https://github.com/mikeyobrien/finch-mcp.git`", os, arch))?;
+    let asset = release.assets.iter().find(|asset| asset.name == name)
+        .with_context(|| format!("Release {} has no {}", release.tag_name, name))?;
+    let expected = expected_checksum(&release, asset).await?;
+
+    let exe = std::env::current_exe().and_then(|exe| exe.canonicalize()).context("Failed to find the running finch-mcp binary")?;
+    let install_dir = exe.parent().context("The finch-mcp binary has no parent directory")?;
+    // Next to the binary, so the final rename stays on one filesystem
+    let temp_dir = tempfile::Builder::new()
+        .prefix(".finch-mcp-update")
+        .tempdir_in(install_dir)
+        .with_context(|| format!("Can't write to {}; run the update with permission to replace {}", install_dir.display(), exe.display()))?;
+
+    status!("📥 Downloading {}...", asset.name);
+    let archive = temp_dir.path().join(&asset.name);
+    curl(&["--output", &archive.to_string_lossy(), &asset.browser_download_url]).await?;
+    verify_checksum(&archive, &expected)?;
+    status!("🔒 Checksum verified");
+
+    let binary = temp_dir.path().join(exe.file_name().unwrap_or_default());
+    extract_binary(&archive, &binary)?;
+    replace_binary(&binary, &exe)?;
+    status!("✅ Updated finch-mcp {} → {}", CURRENT_VERSION, release.tag_name);
+    Ok(())
+}
+
+/// Copy the `finch-mcp` (or `finch-mcp.exe`) binary out of a release archive
+fn extract_binary(archive: &Path, binary: &Path) -> Result<()> {
+    let is_binary = |path: &Path| path.file_name().is_some_and(|name| name == "finch-mcp" || name == "finch-mcp.exe");
+    let file = File::open(archive)?;
+    let mut out = File::create(binary)?;
+    if archive.to_string_lossy().ends_with(".zip") {
+        let mut zip = zip::ZipArchive::new(file)?;
+        let index = (0..zip.len())
+            .find(|index| zip.by_index(*index).ok().and_then(|entry| entry.enclosed_name()).is_some_and(|path| is_binary(&path)))
+            .context("The release archive has no finch-mcp binary")?;
+        std::io::copy(&mut zip.by_index(index)?, &mut out)?;
+    } else {
+        let mut tar = tar::Archive::new(GzDecoder::new(file));
+        let mut entry = tar.entries()?
+            .filter_map(|entry| entry.ok())
+            .find(|entry| entry.path().is_ok_and(|path| is_binary(&path)))
+            .context("The release archive has no finch-mcp binary")?;
+        std::io::copy(&mut entry, &mut out)?;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(binary, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+/// Rename the new binary over the running one. Windows won't replace a running
+/// executable, but it will rename one out of the way
+fn replace_binary(binary: &Path, exe: &Path) -> Result<()> {
+    if cfg!(windows) {
+        let old = exe.with_extension("old.exe");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).with_context(|| format!("Failed to move {} aside", exe.display()))?;
+    }
+    fs::rename(binary, exe).with_context(|| format!("Failed to replace {}", exe.display()))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn remember(latest: &str) {
+    let check = UpdateCheck { checked_at: now(), latest: latest.to_string() };
+    let saved = CacheManager::get_cache_dir().and_then(|dir| {
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(CHECK_FILE), serde_json::to_string(&check)?)?;
+        Ok(())
+    });
+    if let Err(e) = saved {
+        debug!("Failed to remember the latest release: {}", e);
+    }
+}
+
+/// Print a notice if the last lookup found a newer release, and look again in the
+/// background once a day. Only on a terminal, never for MCP sessions, and not `offline`
+pub fn notify_if_outdated(offline: bool) {
+    if !std::io::stderr().is_terminal() || offline || std::env::var_os("FINCH_MCP_NO_UPDATE_CHECK").is_some() || std::env::var_os("CI").is_some() {
+        return;
+    }
+    let last: Option<UpdateCheck> = CacheManager::get_cache_dir()
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(CHECK_FILE)).ok())
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    if let Some(last) = &last {
+        if is_newer(&last.latest, CURRENT_VERSION) {
+            status!("{} finch-mcp {} is available (you have {}). Update with {}", style("💡").yellow(), last.latest, CURRENT_VERSION, style("finch-mcp self update").cyan());
+        }
+    }
+    if last.is_none_or(|last| now().saturating_sub(last.checked_at) >= CHECK_EVERY_SECS) {
+        // Finishes while the command runs, or is dropped when it exits; the next run
+        // looks again
+        std::thread::spawn(|| {
+            let output = std::process::Command::new("curl")
+                .args(["--fail", "--silent", "--location", "--proto", "=https", "--max-time", "5", RELEASES_URL])
+                .stdin(std::process::Stdio::null())
+                .output();
+            let release = output.ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| serde_json::from_slice::<Release>(&output.stdout).ok());
+            if let Some(release) = release {
+                remember(&release.tag_name);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_asset_name() {
+        assert_eq!(asset_name("macos", "aarch64").unwrap(), "finch-mcp-macos-aarch64.tar.gz");
+        assert_eq!(asset_name("linux", "x86_64").unwrap(), "finch-mcp-linux-x86_64.tar.gz");
+        assert_eq!(asset_name("windows", "x86_64").unwrap(), "finch-mcp-windows-x86_64.exe.zip");
+        assert_eq!(asset_name("windows", "aarch64"), None);
+        assert_eq!(asset_name("freebsd", "x86_64"), None);
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("v0.10.0", "0.9.3"));
+        assert!(is_newer("1.0.0", "0.9"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("v0.1.0-rc.1", "0.1.0"));
+        assert!(!is_newer("v0.0.9", "0.1.0"));
+        assert!(is_newer("v0.2.0-rc.1", "0.1.0"));
+        assert!(is_newer("v0.2.0", "0.2.0-rc.1"));
+        assert!(is_newer("v0.2.0-rc.10", "0.2.0-rc.9"));
+        assert!(!is_newer("v0.2.0-rc.1", "0.2.0-rc.1+build.5"));
+        assert!(!is_newer("nightly", "0.1.0"));
+    }
+
+    #[test]
+    fn test_extract_and_replace_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("finch-mcp-linux-x86_64.tar.gz");
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(File::create(&archive).unwrap(), flate2::Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(3);
+        header.set_mode(0o755);
+        header.set_cksum();
+        tar.append_data(&mut header, "finch-mcp", &b"new"[..]).unwrap();
+        tar.into_inner().unwrap().finish().unwrap().flush().unwrap();
+
+        let exe = dir.path().join("installed");
+        fs::write(&exe, "old").unwrap();
+        let binary = dir.path().join("finch-mcp");
+        extract_binary(&archive, &binary).unwrap();
+        replace_binary(&binary, &exe).unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "new");
+        assert!(!binary.exists());
+    }
+}
@@ -0,0 +1,311 @@
+//! Local usage statistics behind `finch-mcp stats`
+//!
+//! Cache lookups, builds and server starts are appended to `stats.jsonl` in the cache
+//! directory. Nothing leaves the machine: the log exists so cache settings can be tuned
+//! from real hit rates and build times. It is trimmed to its newest records once it
+//! outgrows [`MAX_LOG_BYTES`], and `finch-mcp stats --reset` deletes it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use console::style;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::CacheManager;
+
+/// The usage log, in the cache directory
+const STATS_FILE: &str = "stats.jsonl";
+
+/// Past this size the oldest half of the log is dropped
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+
+static PROCESS_START: OnceLock<Instant> = OnceLock::new();
+static BUILT: AtomicBool = AtomicBool::new(false);
+
+/// One thing worth counting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum Record {
+    /// A cache lookup for a source, and the image it found
+    Lookup { at: u64, source: String, image: Option<String> },
+    /// An image build, successful or not
+    Build { at: u64, image: String, success: bool, duration_ms: u64 },
+    /// A server container starting; cold if this process had to build its image first
+    Start { at: u64, image: String, cold: bool, latency_ms: Option<u64> },
+}
+
+/// Note when the process started, so server starts can report how long they took
+pub fn mark_process_start() {
+    let _ = PROCESS_START.set(Instant::now());
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// A cache lookup for `source`, with the image if it was a hit
+pub fn record_lookup(source: &str, image: Option<&str>) {
+    record(&Record::Lookup { at: now(), source: source.trim().to_string(), image: image.map(str::to_string) });
+}
+
+/// A finished build of `image`
+pub fn record_build(image: &str, success: bool, duration: Duration) {
+    if success {
+        BUILT.store(true, Ordering::Relaxed);
+    }
+    record(&Record::Build { at: now(), image: image.to_string(), success, duration_ms: duration.as_millis() as u64 });
+}
+
+/// A server container starting from `image`
+pub fn record_start(image: &str) {
+    let latency_ms = PROCESS_START.get().map(|start| start.elapsed().as_millis() as u64);
+    record(&Record::Start { at: now(), image: image.to_string(), cold: BUILT.load(Ordering::Relaxed), latency_ms });
+}
+
+fn record(record: &Record) {
+    if let Err(e) = append(record) {
+        debug!("Failed to record usage statistics: {}", e);
+    }
+}
+
+fn append(record: &Record) -> Result<()> {
+    let dir = CacheManager::get_cache_dir()?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(STATS_FILE);
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_LOG_BYTES) {
+        let content = fs::read_to_string(&path)?;
+        let lines: Vec<&str> = content.lines().collect();
+        let kept = lines[lines.len() / 2..].join("\n");
+        fs::write(&path, kept + "\n")?;
+    }
+    // One write per record, so concurrent runs append whole lines
+    let line = serde_json::to_string(record)? + "\n";
+    OpenOptions::new().create(true).append(true).open(&path)?.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Every record in the log, skipping lines that don't parse
+pub fn load() -> Result<Vec<Record>> {
+    let path = CacheManager::get_cache_dir()?.join(STATS_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+/// Delete the log
+pub fn reset() -> Result<()> {
+    match fs::remove_file(CacheManager::get_cache_dir()?.join(STATS_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Usage of one server (an image repository), or of all of them
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    pub runs: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub builds: u64,
+    pub failed_builds: u64,
+    #[serde(skip)]
+    build_ms: u64,
+    #[serde(skip)]
+    cold_ms: Vec<u64>,
+    #[serde(skip)]
+    warm_ms: Vec<u64>,
+    pub cold_starts: u64,
+    pub warm_starts: u64,
+}
+
+impl Usage {
+    /// Hits as a share of lookups, if there were any
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.cache_hits + self.cache_misses;
+        (lookups > 0).then(|| self.cache_hits as f64 / lookups as f64)
+    }
+
+    /// Mean duration of the successful builds
+    pub fn average_build_ms(&self) -> Option<u64> {
+        let succeeded = self.builds - self.failed_builds;
+        (succeeded > 0).then(|| self.build_ms / succeeded)
+    }
+
+    pub fn average_cold_start_ms(&self) -> Option<u64> {
+        average(&self.cold_ms)
+    }
+
+    pub fn average_warm_start_ms(&self) -> Option<u64> {
+        average(&self.warm_ms)
+    }
+
+    fn add(&mut self, record: &Record) {
+        match record {
+            Record::Lookup { image: Some(_), .. } => self.cache_hits += 1,
+            Record::Lookup { image: None, .. } => self.cache_misses += 1,
+            Record::Build { success, duration_ms, .. } => {
+                self.builds += 1;
+                if *success {
+                    self.build_ms += duration_ms;
+                } else {
+                    self.failed_builds += 1;
+                }
+            }
+            Record::Start { cold, latency_ms, .. } => {
+                self.runs += 1;
+                let (starts, latencies) = if *cold {
+                    (&mut self.cold_starts, &mut self.cold_ms)
+                } else {
+                    (&mut self.warm_starts, &mut self.warm_ms)
+                };
+                *starts += 1;
+                latencies.extend(latency_ms);
+            }
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut json = serde_json::to_value(self).unwrap_or_default();
+        json["cacheHitRate"] = self.hit_rate().into();
+        json["averageBuildMs"] = self.average_build_ms().into();
+        json["averageColdStartMs"] = self.average_cold_start_ms().into();
+        json["averageWarmStartMs"] = self.average_warm_start_ms().into();
+        json
+    }
+}
+
+fn average(values: &[u64]) -> Option<u64> {
+    (!values.is_empty()).then(|| values.iter().sum::<u64>() / values.len() as u64)
+}
+
+/// The repository part of an image name, e.g. `mcp-auto-time` for `mcp-auto-time:abc123`
+fn repository(image: &str) -> &str {
+    match image.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => image,
+    }
+}
+
+/// Totals and per-server usage. `sources` maps cache lookup sources to their images,
+/// so misses are counted against the server they went on to build
+pub fn summarize(records: &[Record], sources: &HashMap<String, String>) -> (Usage, BTreeMap<String, Usage>) {
+    let mut sources: HashMap<&str, &str> = sources.iter().map(|(source, image)| (source.trim(), repository(image))).collect();
+    for record in records {
+        if let Record::Lookup { source, image: Some(image), .. } = record {
+            sources.insert(source, repository(image));
+        }
+    }
+
+    let mut total = Usage::default();
+    let mut servers: BTreeMap<String, Usage> = BTreeMap::new();
+    for record in records {
+        let server = match record {
+            Record::Lookup { source, .. } => sources.get(source.as_str()).copied().unwrap_or(source),
+            Record::Build { image, .. } | Record::Start { image, .. } => repository(image),
+        };
+        total.add(record);
+        servers.entry(server.to_string()).or_default().add(record);
+    }
+    (total, servers)
+}
+
+/// Summarize the log against the sources the image cache knows
+pub fn collect() -> Result<(Usage, BTreeMap<String, Usage>)> {
+    let records = load()?;
+    let sources = CacheManager::new()
+        .map(|cache_manager| cache_manager.entries().map(|entry| (entry.source_path.trim().to_string(), entry.image_name.clone())).collect())
+        .unwrap_or_default();
+    Ok(summarize(&records, &sources))
+}
+
+/// `finch-mcp stats --json`
+pub fn to_json(total: &Usage, servers: &BTreeMap<String, Usage>) -> serde_json::Value {
+    serde_json::json!({
+        "total": total.to_json(),
+        "servers": servers.iter().map(|(server, usage)| (server.clone(), usage.to_json())).collect::<serde_json::Map<_, _>>(),
+    })
+}
+
+fn seconds(ms: Option<u64>) -> String {
+    ms.map(|ms| format!("{:.1}s", ms as f64 / 1000.0)).unwrap_or_else(|| "-".to_string())
+}
+
+fn percent(rate: Option<f64>) -> String {
+    rate.map(|rate| format!("{:.0}%", rate * 100.0)).unwrap_or_else(|| "-".to_string())
+}
+
+/// Print `finch-mcp stats`
+pub fn print(total: &Usage, servers: &BTreeMap<String, Usage>) {
+    if servers.is_empty() {
+        eprintln!("ℹ No usage recorded yet. Statistics are kept locally as servers are built and run");
+        return;
+    }
+    eprintln!("{}", style("📊 Usage (recorded locally, never sent anywhere)").bold());
+    eprintln!("  Runs:               {} ({} cold, {} warm)", total.runs, total.cold_starts, total.warm_starts);
+    eprintln!("  Cache hit rate:     {} ({} hits, {} misses)", percent(total.hit_rate()), total.cache_hits, total.cache_misses);
+    eprintln!("  Builds:             {} ({} failed), {} on average", total.builds, total.failed_builds, seconds(total.average_build_ms()));
+    eprintln!("  Startup:            {} cold, {} warm on average", seconds(total.average_cold_start_ms()), seconds(total.average_warm_start_ms()));
+
+    let width = servers.keys().map(|server| server.len()).max().unwrap_or(0).max("SERVER".len());
+    eprintln!("\n{}", style(format!("{:<width$}  {:>5}  {:>9}  {:>9}  {:>10}  {:>10}", "SERVER", "RUNS", "HIT RATE", "AVG BUILD", "COLD START", "WARM START")).bold());
+    for (server, usage) in servers {
+        eprintln!(
+            "{:<width$}  {:>5}  {:>9}  {:>9}  {:>10}  {:>10}",
+            server,
+            usage.runs,
+            percent(usage.hit_rate()),
+            seconds(usage.average_build_ms()),
+            seconds(usage.average_cold_start_ms()),
+            seconds(usage.average_warm_start_ms()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let records = vec![
+            Record::Lookup { at: 1, source: "uvx mcp-server-time".to_string(), image: None },
+            Record::Build { at: 2, image: "mcp-auto-time:abc".to_string(), success: false, duration_ms: 1_000 },
+            Record::Build { at: 3, image: "mcp-auto-time:abc".to_string(), success: true, duration_ms: 30_000 },
+            Record::Start { at: 4, image: "mcp-auto-time:abc".to_string(), cold: true, latency_ms: Some(32_000) },
+            Record::Lookup { at: 5, source: "uvx mcp-server-time".to_string(), image: Some("mcp-auto-time:abc".to_string()) },
+            Record::Start { at: 6, image: "mcp-auto-time:abc".to_string(), cold: false, latency_ms: Some(400) },
+            Record::Lookup { at: 7, source: "/srv/notes".to_string(), image: None },
+            Record::Start { at: 8, image: "ghcr.io:5000/acme/search".to_string(), cold: false, latency_ms: None },
+        ];
+        let sources = HashMap::from([("/srv/notes".to_string(), "mcp-local-notes:def".to_string())]);
+        let (total, servers) = summarize(&records, &sources);
+
+        assert_eq!(total.runs, 3);
+        assert_eq!(total.hit_rate(), Some(1.0 / 3.0));
+        let time = &servers["mcp-auto-time"];
+        assert_eq!((time.runs, time.cold_starts, time.warm_starts), (2, 1, 1));
+        assert_eq!(time.hit_rate(), Some(0.5));
+        assert_eq!((time.builds, time.failed_builds, time.average_build_ms()), (2, 1, Some(30_000)));
+        assert_eq!((time.average_cold_start_ms(), time.average_warm_start_ms()), (Some(32_000), Some(400)));
+        assert_eq!(servers["mcp-local-notes"].cache_misses, 1);
+        assert_eq!(servers["ghcr.io:5000/acme/search"].average_warm_start_ms(), None);
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        let record = Record::Build { at: 1, image: "mcp-demo:abc".to_string(), success: true, duration_ms: 1_500 };
+        let line = serde_json::to_string(&record).unwrap();
+        assert_eq!(line, r#"{"kind":"build","at":1,"image":"mcp-demo:abc","success":true,"durationMs":1500}"#);
+        assert_eq!(serde_json::from_str::<Record>(&line).unwrap(), record);
+    }
+}
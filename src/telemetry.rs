@@ -0,0 +1,113 @@
+//! Exporting the clone, hash, detect, build and run spans over OTLP
+//!
+//! finch-mcp is instrumented with `tracing` spans; without a subscriber they cost next
+//! to nothing. Built with `--features otlp` (as release binaries are), `--otlp-endpoint`
+//! or the standard `OTEL_EXPORTER_OTLP_ENDPOINT` variables send them to a collector over
+//! OTLP/HTTP, so a gateway's per-phase and per-request latency shows up next to the rest
+//! of a service's traces. Logs keep going where they always did.
+
+use anyhow::Result;
+
+/// Set when the standard OpenTelemetry variables name a collector
+const ENDPOINT_VARS: [&str; 2] = ["OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "OTEL_EXPORTER_OTLP_ENDPOINT"];
+
+/// The OTLP/HTTP traces URL for a collector address: `http://collector:4318` becomes
+/// `http://collector:4318/v1/traces`
+pub fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    }
+}
+
+fn configured_by_env() -> bool {
+    ENDPOINT_VARS.iter().any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()))
+}
+
+/// Exports spans until dropped, then flushes what is left
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    provider: opentelemetry_sdk::trace::SdkTracerProvider,
+}
+
+/// Start exporting spans to `endpoint`, or to the collector the OpenTelemetry variables
+/// name. `None` when neither is set. Call before the async runtime starts
+#[cfg(feature = "otlp")]
+pub fn init(endpoint: Option<&str>) -> Result<Option<Telemetry>> {
+    use anyhow::Context;
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::WithExportConfig;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    if endpoint.is_none() && !configured_by_env() {
+        return Ok(None);
+    }
+    let mut exporter = opentelemetry_otlp::SpanExporter::builder().with_http();
+    if let Some(endpoint) = endpoint {
+        exporter = exporter.with_endpoint(traces_url(endpoint));
+    }
+    let exporter = exporter.build().context("Failed to set up the OTLP exporter")?;
+    let resource = opentelemetry_sdk::Resource::builder()
+        .with_service_name("finch-mcp")
+        .with_attribute(opentelemetry::KeyValue::new("service.version", env!("CARGO_PKG_VERSION")))
+        .build();
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build();
+
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("finch-mcp"));
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+        .context("A tracing subscriber is already installed")?;
+    Ok(Some(Telemetry { provider }))
+}
+
+/// Without the `otlp` feature spans can't be exported; say so if asked to
+#[cfg(not(feature = "otlp"))]
+pub fn init(endpoint: Option<&str>) -> Result<Option<Telemetry>> {
+    if endpoint.is_some() {
+        anyhow::bail!("This finch-mcp was built without OTLP support; rebuild it with `--features otlp` to use --otlp-endpoint");
+    }
+    if configured_by_env() {
+        log::debug!("OTEL_EXPORTER_OTLP_ENDPOINT is set, but this build has no OTLP exporter");
+    }
+    Ok(None)
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Err(e) = self.provider.shutdown() {
+            log::debug!("Failed to flush spans: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traces_url() {
+        assert_eq!(traces_url("http://localhost:4318"), "http://localhost:4318/v1/traces");
+        assert_eq!(traces_url("https://otel.example.com/"), "https://otel.example.com/v1/traces");
+        assert_eq!(traces_url("http://collector:4318/v1/traces"), "http://collector:4318/v1/traces");
+    }
+
+    #[cfg(feature = "otlp")]
+    #[test]
+    fn test_init() {
+        if !configured_by_env() {
+            assert!(init(None).unwrap().is_none());
+        }
+        // Nothing listens there; spans are only sent once there are some
+        let telemetry = init(Some("http://127.0.0.1:9")).unwrap();
+        assert!(telemetry.is_some());
+        let Err(e) = init(Some("http://127.0.0.1:9")) else {
+            panic!("a second subscriber was installed");
+        };
+        assert!(e.to_string().contains("already installed"), "{}", e);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::utils::timezone;
+
 /// Options for generating a Dockerfile for STDIO mode
 #[derive(Debug, Clone)]
 pub struct DockerfileOptions {
@@ -7,7 +9,7 @@ pub struct DockerfileOptions {
     /// Whether to include Python and MCP time server dependencies
     pub python_dependencies: bool,
     
-    /// Local timezone for the MCP time server
+    /// Local timezone for the MCP time server (defaults to the host's)
     pub timezone: Option<String>,
 }
 
@@ -16,7 +18,7 @@ impl Default for DockerfileOptions {
         Self {
             base_image: "node:20-alpine".to_string(),
             python_dependencies: true,
-            timezone: Some("America/Chicago".to_string()),
+            timezone: timezone::host_timezone().or_else(|| Some("America/Chicago".to_string())),
         }
     }
 }
@@ -48,7 +50,7 @@ COPY . .
 FROM {} AS runtime
 
 # Install dumb-init and runtime dependencies
-RUN apk add --no-cache dumb-init{}
+RUN apk add --no-cache dumb-init tzdata{}
 
 # Create non-root user
 RUN addgroup -g 1000 -S mcp && \
@@ -64,7 +66,8 @@ ENV NODE_ENV=production \
     NPM_CONFIG_CACHE=/tmp/.npm \
     NPM_CONFIG_LOGLEVEL=warn \
     MCP_ENABLED=true \
-    MCP_STDIO=true
+    MCP_STDIO=true \
+    TZ={}
 
 # Set non-root user for security
 USER mcp
@@ -87,10 +90,24 @@ CMD ["python", "-m", "mcp_server_time", "--local-timezone", "{}"]
         } else {
             ""
         },
+        timezone,
         timezone
     )
 }
 
+/// An exec-form `ENTRYPOINT` running `command` through `sh -c`, with the container's
+/// arguments appended and `wrapper` (e.g. `dumb-init --`) in front. Every element is
+/// JSON-escaped: a command with quotes or backslashes would otherwise make invalid JSON,
+/// which Docker silently runs as a shell-form command instead
+pub fn shell_entrypoint(wrapper: &[&str], command: &str) -> String {
+    let script = format!("{} \"$@\"", command);
+    let elements: Vec<String> = wrapper.iter().copied()
+        .chain(["sh", "-c", script.as_str(), "mcp-server"])
+        .map(|element| serde_json::Value::from(element).to_string())
+        .collect();
+    format!("ENTRYPOINT [{}]", elements.join(", "))
+}
+
 /// Write Dockerfile to a specified path
 pub async fn write_dockerfile_to_file(
     path: &str, 
@@ -105,6 +122,20 @@ pub async fn write_dockerfile_to_file(
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_shell_entrypoint() {
+        assert_eq!(shell_entrypoint(&[], "node dist/index.js"), r#"ENTRYPOINT ["sh", "-c", "node dist/index.js \"$@\"", "mcp-server"]"#);
+        assert_eq!(
+            shell_entrypoint(&["dumb-init", "--"], "npx server"),
+            r#"ENTRYPOINT ["dumb-init", "--", "sh", "-c", "npx server \"$@\"", "mcp-server"]"#
+        );
+
+        let command = r#"node -e "require('./server')" --root C:\data"#;
+        let entrypoint = shell_entrypoint(&[], command);
+        let array: Vec<String> = serde_json::from_str(entrypoint.strip_prefix("ENTRYPOINT ").unwrap()).unwrap();
+        assert_eq!(array, ["sh", "-c", &format!("{} \"$@\"", command), "mcp-server"]);
+    }
+
     #[test]
     fn test_generate_stdio_dockerfile_with_python() {
         let options = DockerfileOptions {
@@ -115,13 +146,15 @@ mod tests {
         
         let dockerfile = generate_stdio_dockerfile(&options);
         
-        println!("Generated Dockerfile:\n{}", dockerfile);
+        eprintln!("Generated Dockerfile:\n{}", dockerfile);
         
         assert!(dockerfile.contains("FROM node:20-alpine"));
         assert!(dockerfile.contains("Multi-stage build"));
         assert!(dockerfile.contains("dumb-init"));
         assert!(dockerfile.contains("--local-timezone"));
         assert!(dockerfile.contains("Europe/London"));
+        assert!(dockerfile.contains("TZ=Europe/London"));
+        assert!(dockerfile.contains("dumb-init tzdata"));
     }
     
     #[test]
@@ -134,7 +167,7 @@ mod tests {
         
         let dockerfile = generate_stdio_dockerfile(&options);
         
-        println!("Generated Dockerfile:\n{}", dockerfile);
+        eprintln!("Generated Dockerfile:\n{}", dockerfile);
         
         assert!(dockerfile.contains("FROM node:20-alpine"));
         assert!(dockerfile.contains("Multi-stage build"));
@@ -0,0 +1,146 @@
+//! Host directories shared with a server at the same path (`--allow-path PATH[:ro]`)
+//!
+//! Filesystem servers are usually told which paths they may touch, and those paths have
+//! to mean the same thing on the host and in the container. Each allowed path is mounted
+//! at its own location and appended to `ALLOWED_PATHS`, the variable several servers read.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Variable listing the allowed paths, `:`-separated
+pub const ENV_VAR: &str = "ALLOWED_PATHS";
+
+/// A host path mounted at the same location in the container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowedPath {
+    /// Absolute path, with `~` expanded
+    pub path: PathBuf,
+    pub read_only: bool,
+}
+
+impl AllowedPath {
+    /// The `-v` spec mounting the path at itself
+    pub fn volume(&self) -> String {
+        let path = self.path.display();
+        if self.read_only {
+            format!("{}:{}:ro", path, path)
+        } else {
+            format!("{}:{}", path, path)
+        }
+    }
+}
+
+impl FromStr for AllowedPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, read_only) = match s.rsplit_once(':') {
+            Some((path, "ro")) => (path, true),
+            Some((path, "rw")) => (path, false),
+            _ => (s, false),
+        };
+        if path.is_empty() {
+            return Err("empty path".to_string());
+        }
+
+        let path = match path.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+                let home = dirs::home_dir().ok_or("cannot expand ~: no home directory")?;
+                home.join(rest.trim_start_matches('/'))
+            }
+            _ => PathBuf::from(path),
+        };
+        let path = std::path::absolute(&path).map_err(|e| format!("cannot resolve {}: {}", path.display(), e))?;
+        Ok(Self { path, read_only })
+    }
+}
+
+impl fmt::Display for AllowedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if self.read_only {
+            f.write_str(":ro")?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for AllowedPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for AllowedPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Add the mounts for `allowed` to `volumes` and append the paths to `ALLOWED_PATHS`,
+/// keeping a value given with `-e`
+pub fn apply(allowed: &[AllowedPath], env_vars: &mut Vec<String>, volumes: &mut Vec<String>) {
+    if allowed.is_empty() {
+        return;
+    }
+    volumes.extend(allowed.iter().map(AllowedPath::volume));
+
+    let paths: Vec<String> = allowed.iter().map(|allowed| allowed.path.display().to_string()).collect();
+    let paths = paths.join(":");
+    let existing = env_vars.iter().position(|env| env.split('=').next() == Some(ENV_VAR));
+    match existing {
+        Some(index) => {
+            // A bare `-e ALLOWED_PATHS` forwards the host's value
+            let current = match env_vars[index].split_once('=') {
+                Some((_, value)) => value.to_string(),
+                None => std::env::var(ENV_VAR).unwrap_or_default(),
+            };
+            env_vars[index] = if current.is_empty() {
+                format!("{}={}", ENV_VAR, paths)
+            } else {
+                format!("{}={}:{}", ENV_VAR, current, paths)
+            };
+        }
+        None => env_vars.push(format!("{}={}", ENV_VAR, paths)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let notes: AllowedPath = "/Users/me/notes:ro".parse().unwrap();
+        assert_eq!(notes, AllowedPath { path: PathBuf::from("/Users/me/notes"), read_only: true });
+        assert_eq!(notes.volume(), "/Users/me/notes:/Users/me/notes:ro");
+        assert_eq!(notes.to_string(), "/Users/me/notes:ro");
+
+        let relative: AllowedPath = "data".parse().unwrap();
+        assert_eq!(relative.path, std::env::current_dir().unwrap().join("data"));
+        assert!(!relative.read_only);
+
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!("~/notes:rw".parse::<AllowedPath>().unwrap().path, home.join("notes"));
+        }
+        assert!("".parse::<AllowedPath>().is_err());
+        assert!(":ro".parse::<AllowedPath>().is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let allowed: Vec<AllowedPath> = vec!["/srv/a".parse().unwrap(), "/srv/b:ro".parse().unwrap()];
+
+        let (mut env, mut volumes) = (vec!["DEBUG=1".to_string()], vec!["/tmp:/tmp".to_string()]);
+        apply(&allowed, &mut env, &mut volumes);
+        assert_eq!(env, ["DEBUG=1", "ALLOWED_PATHS=/srv/a:/srv/b"]);
+        assert_eq!(volumes, ["/tmp:/tmp", "/srv/a:/srv/a", "/srv/b:/srv/b:ro"]);
+
+        let mut env = vec!["ALLOWED_PATHS=/data".to_string()];
+        apply(&allowed, &mut env, &mut Vec::new());
+        assert_eq!(env, ["ALLOWED_PATHS=/data:/srv/a:/srv/b"]);
+    }
+}
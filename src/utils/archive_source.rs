@@ -0,0 +1,190 @@
+//! Release archives as targets: `https://.../server-v1.2.0.tar.gz` or a local `.zip`/`.tgz`
+//!
+//! The archive is unpacked under `archives/` in the cache directory and built like a
+//! local directory. The directory is named after the archive, so the cache finds the
+//! image again on the next run. A downloaded archive is kept until `--refresh` or
+//! `--force`; a local one is unpacked again each time, since it may have been replaced.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use log::{debug, info};
+use sha2::{Digest, Sha256};
+
+use crate::cache::CacheManager;
+use crate::status;
+use crate::utils::offline;
+
+/// Written once an archive is fully unpacked, so an interrupted unpack is never built
+const COMPLETE_MARKER: &str = ".finch-mcp-unpacked";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    TarGz,
+    Tar,
+    Zip,
+}
+
+/// Whether `target` is an archive: an `http(s)` URL or an existing file ending in
+/// `.tar.gz`, `.tgz`, `.tar` or `.zip`
+pub fn is_archive(target: &str) -> bool {
+    let is_url = target.starts_with("https://") || target.starts_with("http://");
+    format(target).is_some() && (is_url || Path::new(target).is_file())
+}
+
+fn format(target: &str) -> Option<Format> {
+    // Ignore the query string and fragment of a URL
+    let path = target.split(['?', '#']).next().unwrap_or(target).to_lowercase();
+    if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if path.ends_with(".tar") {
+        Some(Format::Tar)
+    } else if path.ends_with(".zip") {
+        Some(Format::Zip)
+    } else {
+        None
+    }
+}
+
+/// Download (for a URL) and unpack the archive, returning the project directory in it;
+/// `offline` only uses what was unpacked before, `refresh` downloads it again
+pub fn prepare(target: &str, offline: bool, refresh: bool) -> Result<PathBuf> {
+    let format = format(target).with_context(|| format!("{} is not a .tar.gz, .tgz, .tar or .zip archive", target))?;
+    let is_url = !Path::new(target).is_file();
+    let source = if is_url {
+        target.to_string()
+    } else {
+        std::path::absolute(target)?.display().to_string()
+    };
+    let dir = unpack_dir(&CacheManager::get_cache_dir()?, &source);
+
+    if is_url && dir.join(COMPLETE_MARKER).exists() && !refresh {
+        debug!("Using {} unpacked earlier at {:?}", target, dir);
+        return Ok(project_root(&dir));
+    }
+    if is_url {
+        offline::ensure_online(offline, &format!("Downloading {}", target))?;
+    }
+
+    let partial = PathBuf::from(format!("{}.{}.tmp", dir.display(), uuid::Uuid::new_v4()));
+    fs::create_dir_all(&partial).context("Failed to create archive directory")?;
+    let unpacked = (|| {
+        let archive = if is_url {
+            status!("📦 Downloading {}...", target);
+            let file = partial.join(".download");
+            download(target, &file)?;
+            file
+        } else {
+            PathBuf::from(&source)
+        };
+        info!("Unpacking {} to {:?}", target, partial);
+        unpack(&archive, format, &partial)?;
+        let _ = fs::remove_file(partial.join(".download"));
+        fs::write(partial.join(COMPLETE_MARKER), &source)?;
+        Ok::<_, anyhow::Error>(())
+    })();
+    if let Err(e) = unpacked {
+        let _ = fs::remove_dir_all(&partial);
+        return Err(e.context(format!("Failed to unpack {}", target)));
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    if fs::rename(&partial, &dir).is_err() {
+        // Another run unpacked it at the same time
+        let _ = fs::remove_dir_all(&partial);
+    }
+    Ok(project_root(&dir))
+}
+
+/// `archives/<name>-<hash of source>` under the cache directory
+fn unpack_dir(cache_dir: &Path, source: &str) -> PathBuf {
+    let file_name = source.split(['?', '#']).next().unwrap_or(source).rsplit(['/', '\\']).next().unwrap_or(source);
+    let name = [".tar.gz", ".tgz", ".tar", ".zip"]
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(extension).or_else(|| file_name.strip_suffix(&extension.to_uppercase())))
+        .unwrap_or(file_name);
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '-' }).collect();
+    let hash = format!("{:x}", Sha256::digest(source.as_bytes()));
+    cache_dir.join("archives").join(format!("{}-{}", name, &hash[..12]))
+}
+
+/// Release archives usually wrap everything in one `name-version/` directory
+fn project_root(dir: &Path) -> PathBuf {
+    let entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| !path.ends_with(COMPLETE_MARKER)).collect())
+        .unwrap_or_default();
+    match entries.as_slice() {
+        [only] if only.is_dir() => only.clone(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+fn download(url: &str, file: &Path) -> Result<()> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--output"])
+        .arg(file)
+        .arg(url)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run curl; it is needed to download archive targets")?;
+    if !output.status.success() {
+        anyhow::bail!("Download failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Unpack into `dir`. Both unpackers refuse entries that would land outside it
+fn unpack(archive: &Path, format: Format, dir: &Path) -> Result<()> {
+    let file = File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    match format {
+        Format::TarGz => tar::Archive::new(GzDecoder::new(file)).unpack(dir)?,
+        Format::Tar => tar::Archive::new(file).unpack(dir)?,
+        Format::Zip => zip::ZipArchive::new(file)?.extract(dir)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_is_archive() {
+        assert!(is_archive("https://github.com/acme/server/releases/download/v1.2.0/server-v1.2.0.tar.gz"));
+        assert!(is_archive("https://example.com/server.zip?token=abc"));
+        assert!(!is_archive("https://github.com/acme/server"));
+        assert!(!is_archive("./missing-server.tgz"));
+        assert!(!is_archive("uvx mcp-server-time"));
+        assert_eq!(format("./Server.TGZ"), Some(Format::TarGz));
+    }
+
+    #[test]
+    fn test_unpack_dir_is_stable() {
+        let cache = Path::new("/cache");
+        let dir = unpack_dir(cache, "https://example.com/dl/server-v1.2.0.tar.gz?x=1");
+        assert!(dir.starts_with("/cache/archives"));
+        assert!(dir.file_name().unwrap().to_string_lossy().starts_with("server-v1.2.0-"));
+        assert_eq!(dir, unpack_dir(cache, "https://example.com/dl/server-v1.2.0.tar.gz?x=1"));
+        assert_ne!(dir, unpack_dir(cache, "https://example.com/dl/server-v1.3.0.tar.gz"));
+    }
+
+    #[test]
+    fn test_unpack_finds_project_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("server.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.start_file("server-v1.2.0/package.json", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.finish().unwrap();
+
+        let dir = temp_dir.path().join("unpacked");
+        unpack(&archive, Format::Zip, &dir).unwrap();
+        let root = project_root(&dir);
+        assert!(root.ends_with("server-v1.2.0"));
+        assert!(root.join("package.json").exists());
+    }
+}
@@ -1,3 +1,6 @@
+use crate::templates::dockerfile::shell_entrypoint;
+use crate::utils::target_scheme::pip_requirement;
+
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum CommandType {
@@ -16,6 +19,7 @@ pub struct CommandDetails {
     pub package_name: Option<String>,
 }
 
+#[tracing::instrument(name = "detect", skip(args))]
 pub fn detect_command_type(command: &str, args: &[String]) -> CommandDetails {
     let command = command.to_lowercase();
     
@@ -83,7 +87,8 @@ pub fn detect_command_type(command: &str, args: &[String]) -> CommandDetails {
 pub fn generate_dockerfile_content(details: &CommandDetails) -> String {
     match details.cmd_type {
         CommandType::PythonUvx => {
-            let package_name = details.package_name.clone().unwrap_or_default();
+            // `uvx` takes `package@1.2.3`; pip wants `package==1.2.3`
+            let package_name = pip_requirement(details.package_name.as_deref().unwrap_or_default());
             let command_with_args = format!("{} {}", details.command, details.args.join(" "));
             format!(
                 r#"# Multi-stage build for smaller final image
@@ -118,10 +123,10 @@ RUN addgroup -g 1000 -S mcp && \
 USER mcp
 
 # Run the command with arguments
-CMD ["sh", "-c", "{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+{}
 "#,
                 package_name,
-                command_with_args
+                shell_entrypoint(&[], &command_with_args)
             )
         }
         CommandType::PythonPip => {
@@ -152,10 +157,9 @@ RUN addgroup -g 1001 -S mcp && \
 USER mcp
 
 # Install and run the command
-CMD ["sh", "-c", "{} {} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+{}
 "#,
-                details.command,
-                details.args.join(" ")
+                shell_entrypoint(&[], &format!("{} {}", details.command, details.args.join(" ")))
             )
         }
         CommandType::NodeNpm => {
@@ -178,14 +182,10 @@ RUN addgroup -g 1001 -S mcp && \
 
 USER mcp
 
-# Use dumb-init for proper signal handling
-ENTRYPOINT ["dumb-init", "--"]
-
-# Run the command with arguments
-CMD ["sh", "-c", "{} {} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+# Run the command with arguments under dumb-init for proper signal handling
+{}
 "#,
-                details.command,
-                details.args.join(" ")
+                shell_entrypoint(&["dumb-init", "--"], &format!("{} {}", details.command, details.args.join(" ")))
             )
         }
         CommandType::NodeNpx => {
@@ -249,14 +249,10 @@ RUN addgroup -g 1001 -S mcp && \
 
 USER mcp
 
-# Use dumb-init for proper signal handling
-ENTRYPOINT ["dumb-init", "--"]
-
-# Run the npx command
-CMD ["sh", "-c", "npx {}{} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+# Run the npx command under dumb-init for proper signal handling
+{}
 "#,
-                package_and_flags,
-                cmd_args
+                shell_entrypoint(&["dumb-init", "--"], &format!("npx {}{}", package_and_flags, cmd_args))
             )
         }
         CommandType::Generic => {
@@ -282,14 +278,10 @@ RUN addgroup -g 1001 -S mcp && \
 
 USER mcp
 
-# Use dumb-init for proper signal handling
-ENTRYPOINT ["dumb-init", "--"]
-
-# Run the command with arguments
-CMD ["sh", "-c", "{} {} ${{EXTRA_ARGS:+$EXTRA_ARGS}}"]
+# Run the command with arguments under dumb-init for proper signal handling
+{}
 "#,
-                details.command,
-                details.args.join(" ")
+                shell_entrypoint(&["dumb-init", "--"], &format!("{} {}", details.command, details.args.join(" ")))
             )
         }
     }
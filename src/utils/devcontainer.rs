@@ -0,0 +1,368 @@
+//! Dev container definitions (`.devcontainer/devcontainer.json`) as a containerization recipe
+//!
+//! With `--use-devcontainer`, the image (or Dockerfile) a repo already maintains for its dev
+//! container becomes the base of the generated Dockerfile, and its `onCreateCommand`,
+//! `updateContentCommand` and `postCreateCommand` run after the source is copied in. The
+//! start command is still the detected (or declared) one.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::utils::mcp_manifest::shell_quote;
+
+/// Where dev container definitions live, in the order the Dev Containers spec looks
+const DEVCONTAINER_FILES: [&str; 2] = [".devcontainer/devcontainer.json", ".devcontainer.json"];
+
+/// A dev container definition, resolved against the repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevContainer {
+    /// File the definition was read from, relative to the repository
+    pub source: String,
+    pub base: DevContainerBase,
+    /// Lifecycle commands run once the source is in place, in order
+    pub setup_commands: Vec<String>,
+    pub container_env: BTreeMap<String, String>,
+    /// User the lifecycle commands and the server run as
+    pub user: Option<String>,
+    /// Dev container features, which finch-mcp can't install
+    pub features: Vec<String>,
+}
+
+/// What the dev container is built from
+#[derive(Debug, Clone, PartialEq)]
+pub enum DevContainerBase {
+    Image(String),
+    /// The definition's Dockerfile, with `COPY`/`ADD` sources made relative to the repository
+    /// and `build.args` applied, plus the stage named by `build.target`
+    Dockerfile { content: String, target: Option<String> },
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevContainerJson {
+    image: Option<String>,
+    build: Option<BuildJson>,
+    /// Pre-`build` spelling of `build.dockerfile`
+    #[serde(rename = "dockerFile")]
+    docker_file: Option<String>,
+    context: Option<String>,
+    docker_compose_file: Option<Value>,
+    on_create_command: Option<Value>,
+    update_content_command: Option<Value>,
+    post_create_command: Option<Value>,
+    #[serde(default)]
+    container_env: BTreeMap<String, String>,
+    container_user: Option<String>,
+    remote_user: Option<String>,
+    #[serde(default)]
+    features: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BuildJson {
+    dockerfile: Option<String>,
+    context: Option<String>,
+    #[serde(default)]
+    args: BTreeMap<String, String>,
+    target: Option<String>,
+}
+
+/// Find and resolve the repository's dev container definition. One that can't be used
+/// (Docker Compose based, unreadable, or pointing outside the repository) is reported and ignored
+pub fn detect_devcontainer(repo_path: &Path) -> Option<DevContainer> {
+    let source = DEVCONTAINER_FILES.iter().find(|file| repo_path.join(file).is_file())?;
+    match load_devcontainer(repo_path, source) {
+        Ok(devcontainer) => devcontainer,
+        Err(e) => {
+            warn!("Ignoring {}: {:#}", source, e);
+            None
+        }
+    }
+}
+
+fn load_devcontainer(repo_path: &Path, source: &str) -> Result<Option<DevContainer>> {
+    let path = repo_path.join(source);
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", source))?;
+    let json: DevContainerJson = serde_json::from_str(&strip_jsonc(&content)).context("Invalid devcontainer.json")?;
+
+    if json.docker_compose_file.is_some() {
+        debug!("{} uses Docker Compose, which isn't supported", source);
+        return Ok(None);
+    }
+
+    let definition_dir = path.parent().unwrap_or(repo_path);
+    let build = json.build.unwrap_or_default();
+    let base = match (build.dockerfile.or(json.docker_file), json.image) {
+        (Some(dockerfile), _) => {
+            let dockerfile_path = definition_dir.join(&dockerfile);
+            let dockerfile_content = fs::read_to_string(&dockerfile_path)
+                .with_context(|| format!("Failed to read its Dockerfile {}", dockerfile))?;
+            let context = definition_dir.join(build.context.or(json.context).as_deref().unwrap_or("."));
+            let context = relative_to(repo_path, &context)
+                .with_context(|| format!("its build context {} is outside the repository", context.display()))?;
+            DevContainerBase::Dockerfile {
+                content: adapt_dockerfile(&dockerfile_content, &context, &build.args),
+                target: build.target,
+            }
+        }
+        (None, Some(image)) => DevContainerBase::Image(image),
+        (None, None) => anyhow::bail!("it names neither an image nor a Dockerfile"),
+    };
+
+    let setup_commands = [json.on_create_command, json.update_content_command, json.post_create_command]
+        .into_iter()
+        .flatten()
+        .flat_map(|command| lifecycle_commands(&command))
+        .collect();
+
+    Ok(Some(DevContainer {
+        source: source.to_string(),
+        base,
+        setup_commands,
+        container_env: json.container_env,
+        user: json.remote_user.or(json.container_user),
+        features: json.features.into_keys().collect(),
+    }))
+}
+
+impl DevContainer {
+    /// The Dockerfile for the server: the dev container's base, the source in `/app`,
+    /// its setup commands, then `entrypoint` (an `ENTRYPOINT` line)
+    pub fn dockerfile(&self, entrypoint: &str) -> String {
+        let mut lines = vec![format!("# Based on {}", self.source)];
+        match &self.base {
+            DevContainerBase::Image(image) => lines.push(format!("FROM {}", image)),
+            DevContainerBase::Dockerfile { content, target } => {
+                lines.push(content.trim_end().to_string());
+                if let Some(target) = target {
+                    lines.push(String::new());
+                    lines.push(format!("FROM {}", target));
+                }
+            }
+        }
+
+        lines.push(String::new());
+        lines.push("# Copy project files".to_string());
+        match &self.user {
+            // Copying creates /app owned by the user, so setup commands can write to it
+            Some(user) => {
+                lines.push("USER root".to_string());
+                lines.push(format!("COPY --chown={} . /app", user));
+                lines.push(format!("USER {}", user));
+            }
+            None => lines.push("COPY . /app".to_string()),
+        }
+        lines.push("WORKDIR /app".to_string());
+
+        if !self.container_env.is_empty() {
+            lines.push(String::new());
+            for (key, value) in &self.container_env {
+                lines.push(format!("ENV {}={}", key, shell_quote(value)));
+            }
+        }
+        if !self.setup_commands.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("# Setup commands from {}", self.source));
+            lines.extend(self.setup_commands.iter().map(|command| format!("RUN {}", command)));
+        }
+
+        lines.push(String::new());
+        lines.push("# Set environment variables for MCP".to_string());
+        lines.push("ENV MCP_ENABLED=true".to_string());
+        lines.push("ENV MCP_STDIO=true".to_string());
+        lines.push(String::new());
+        lines.push("# Run the application".to_string());
+        lines.push(entrypoint.to_string());
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+/// A lifecycle command as shell commands: a string runs in a shell, an array is an exec
+/// form, and an object holds several named commands of either kind
+fn lifecycle_commands(command: &Value) -> Vec<String> {
+    match command {
+        Value::String(command) => {
+            let lines: Vec<&str> = command.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+            if lines.is_empty() { Vec::new() } else { vec![lines.join(" && ")] }
+        }
+        Value::Array(parts) => {
+            let parts: Vec<String> = parts.iter().filter_map(Value::as_str).map(shell_quote).collect();
+            if parts.is_empty() { Vec::new() } else { vec![parts.join(" ")] }
+        }
+        Value::Object(commands) => commands.values().flat_map(lifecycle_commands).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Make a dev container Dockerfile build from the repository root: `COPY`/`ADD` sources
+/// get the build context's path as a prefix, and `ARG` defaults come from `build.args`
+fn adapt_dockerfile(content: &str, context: &Path, args: &BTreeMap<String, String>) -> String {
+    let prefix = context.to_string_lossy().replace('\\', "/");
+    let mut output = Vec::new();
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let instruction = trimmed.split_whitespace().next().unwrap_or("").to_uppercase();
+        let adapted = match instruction.as_str() {
+            "COPY" | "ADD" if !prefix.is_empty() && !trimmed.contains("--from=") && !trimmed.contains('[') => {
+                let words: Vec<&str> = trimmed.split_whitespace().collect();
+                let last = words.len() - 1;
+                words.iter().enumerate()
+                    .map(|(i, word)| if i == 0 || i == last || word.starts_with("--") || word.contains("://") {
+                        word.to_string()
+                    } else {
+                        format!("{}/{}", prefix, word)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+            "ARG" => {
+                let name = trimmed[3..].trim().split('=').next().unwrap_or("").to_string();
+                match args.get(&name) {
+                    Some(value) => format!("ARG {}={}", name, shell_quote(value)),
+                    None => line.to_string(),
+                }
+            }
+            _ => line.to_string(),
+        };
+        output.push(adapted);
+    }
+    output.join("\n")
+}
+
+/// `path` relative to `root`, if it is inside it
+fn relative_to(root: &Path, path: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.strip_prefix(root).ok()?.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !relative.pop() {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(relative)
+}
+
+/// Remove the comments and trailing commas devcontainer.json allows (it is JSONC)
+fn strip_jsonc(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            match c {
+                '\\' => output.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => {
+                while chars.peek().is_some_and(|&next| next != '\n') {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            (',', _) => {
+                let rest: String = chars.clone().collect();
+                if !rest.trim_start().starts_with(['}', ']']) {
+                    output.push(c);
+                }
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_devcontainer() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".devcontainer")).unwrap();
+        fs::write(dir.path().join(".devcontainer/devcontainer.json"), r#"{
+            // Node 20 with the usual tools
+            "image": "mcr.microsoft.com/devcontainers/typescript-node:20",
+            "features": { "ghcr.io/devcontainers/features/github-cli:1": {} },
+            "containerEnv": { "LOG_LEVEL": "debug" },
+            "postCreateCommand": "npm ci && npm run build", /* installs deps */
+            "remoteUser": "node",
+        }"#).unwrap();
+
+        let devcontainer = detect_devcontainer(dir.path()).unwrap();
+        assert_eq!(devcontainer.base, DevContainerBase::Image("mcr.microsoft.com/devcontainers/typescript-node:20".to_string()));
+        assert_eq!(devcontainer.setup_commands, ["npm ci && npm run build"]);
+        assert_eq!(devcontainer.features, ["ghcr.io/devcontainers/features/github-cli:1"]);
+
+        let dockerfile = devcontainer.dockerfile(r#"ENTRYPOINT ["sh", "-c", "node dist/index.js \"$@\"", "mcp-server"]"#);
+        assert!(dockerfile.contains("FROM mcr.microsoft.com/devcontainers/typescript-node:20\n"));
+        assert!(dockerfile.contains("COPY --chown=node . /app\nUSER node\nWORKDIR /app"));
+        assert!(dockerfile.contains("ENV LOG_LEVEL=debug"));
+        assert!(dockerfile.contains("RUN npm ci && npm run build"));
+        assert!(dockerfile.trim_end().ends_with("\"mcp-server\"]"));
+    }
+
+    #[test]
+    fn test_dockerfile_devcontainer() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".devcontainer")).unwrap();
+        fs::write(dir.path().join(".devcontainer/Dockerfile"), "ARG VARIANT=3.10\nFROM python:${VARIANT}\nCOPY requirements.txt /tmp/\nCOPY --from=ghcr.io/astral-sh/uv /uv /bin/uv\n").unwrap();
+        fs::write(dir.path().join(".devcontainer/devcontainer.json"), r#"{
+            "build": { "dockerfile": "Dockerfile", "args": { "VARIANT": "3.12" } },
+            "onCreateCommand": ["pip", "install", "-r", "/tmp/requirements.txt"],
+            "postCreateCommand": { "deps": "pip install -e .", "hooks": "pre-commit install" }
+        }"#).unwrap();
+
+        let devcontainer = detect_devcontainer(dir.path()).unwrap();
+        let DevContainerBase::Dockerfile { content, target } = &devcontainer.base else { panic!("expected a Dockerfile base") };
+        assert!(content.contains("ARG VARIANT=3.12\n"));
+        assert!(content.contains("COPY .devcontainer/requirements.txt /tmp/"));
+        assert!(content.contains("COPY --from=ghcr.io/astral-sh/uv /uv /bin/uv"));
+        assert_eq!(*target, None);
+        assert_eq!(devcontainer.setup_commands, ["pip install -r /tmp/requirements.txt", "pip install -e .", "pre-commit install"]);
+    }
+
+    #[test]
+    fn test_unusable_devcontainers() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(detect_devcontainer(dir.path()).is_none());
+
+        fs::write(dir.path().join(".devcontainer.json"), r#"{ "dockerComposeFile": "compose.yml", "service": "app" }"#).unwrap();
+        assert!(detect_devcontainer(dir.path()).is_none());
+
+        fs::write(dir.path().join(".devcontainer.json"), r#"{ "build": { "dockerfile": "Dockerfile", "context": "../.." } }"#).unwrap();
+        fs::write(dir.path().join("Dockerfile"), "FROM alpine\n").unwrap();
+        assert!(detect_devcontainer(dir.path()).is_none());
+
+        assert_eq!(strip_jsonc(r#"{"url": "http://x//y", "a": [1, 2,],}"#), r#"{"url": "http://x//y", "a": [1, 2]}"#);
+    }
+}
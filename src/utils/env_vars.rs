@@ -0,0 +1,151 @@
+//! Checking `-e` values and filling in env vars a server requires
+//!
+//! `-e KEY=VALUE` sets a variable and a bare `-e KEY` copies it from the host, as with
+//! `docker run`. finch may run the container runtime inside a VM that doesn't share the
+//! host's environment, so bare names are resolved here rather than left to finch.
+
+use anyhow::Result;
+use console::style;
+use log::debug;
+
+use crate::utils::mcp_manifest::McpManifest;
+use crate::utils::prompt;
+
+/// Validate a `-e` value: `KEY=VALUE`, or a bare `KEY` to copy from the host
+pub fn parse_env(spec: &str) -> Result<String, String> {
+    match spec.split_once('=') {
+        Some(("", _)) => Err(format!("'{}' has no variable name before '='", spec)),
+        Some((key, _)) if key.chars().any(char::is_whitespace) => {
+            Err(format!("variable name '{}' contains whitespace", key))
+        }
+        Some(_) => Ok(spec.to_string()),
+        None if is_name(spec) => Ok(spec.to_string()),
+        None => Err(format!("'{}' is not KEY=VALUE, or a bare KEY to copy from the host", spec)),
+    }
+}
+
+/// Whether `name` can be forwarded by name: letters, digits and `_`, not starting with a digit
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replace each bare `KEY` with `KEY=<host value>`, dropping names the host doesn't set
+pub fn resolve(env_vars: Vec<String>) -> Vec<String> {
+    env_vars
+        .into_iter()
+        .filter_map(|env| {
+            if env.contains('=') {
+                return Some(env);
+            }
+            match std::env::var(&env) {
+                Ok(value) => Some(format!("{}={}", env, value)),
+                Err(_) => {
+                    debug!("-e {} is not set on the host; not passing it", env);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Ask for the variables `manifest` requires that `env_vars` doesn't set, adding the answers.
+/// Without a terminal, fail listing every missing variable
+pub fn require(manifest: &McpManifest, env_vars: &mut Vec<String>) -> Result<()> {
+    let missing: Vec<_> = manifest.missing_env(env_vars).into_iter().cloned().collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    if !prompt::is_interactive() {
+        let list: Vec<String> = missing
+            .iter()
+            .map(|var| match &var.description {
+                Some(description) => format!("  {} - {}", var.name, description),
+                None => format!("  {}", var.name),
+            })
+            .collect();
+        anyhow::bail!(
+            "{} requires environment variables that aren't set:\n{}\nPass them with -e KEY=VALUE, or -e KEY to copy them from the host",
+            manifest.source,
+            list.join("\n")
+        );
+    }
+
+    eprintln!("{} {} requires environment variables that aren't set", style("🔑").yellow(), manifest.source);
+    for var in missing {
+        let question = match &var.description {
+            Some(description) => format!("{} ({})", var.name, description),
+            None => var.name.clone(),
+        };
+        let value = prompt::ask(&question, is_secret(&var.name))?;
+        env_vars.push(format!("{}={}", var.name, value));
+    }
+    Ok(())
+}
+
+/// Names whose values shouldn't be echoed while typing
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    ["KEY", "TOKEN", "SECRET", "PASSWORD", "PASS", "CREDENTIAL"].iter().any(|word| name.contains(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mcp_manifest::ManifestEnvVar;
+
+    #[test]
+    fn test_parse_env() {
+        assert_eq!(parse_env("KEY=VALUE").unwrap(), "KEY=VALUE");
+        assert_eq!(parse_env("URL=http://x?a=b").unwrap(), "URL=http://x?a=b");
+        assert_eq!(parse_env("EMPTY=").unwrap(), "EMPTY=");
+        assert_eq!(parse_env("HOME").unwrap(), "HOME");
+        assert_eq!(parse_env("_PRIVATE_1").unwrap(), "_PRIVATE_1");
+
+        assert!(parse_env("=value").is_err());
+        assert!(parse_env("API KEY=value").is_err());
+        assert!(parse_env("API_KEY:value").is_err());
+        assert!(parse_env("1KEY").is_err());
+        assert!(parse_env("").is_err());
+    }
+
+    #[test]
+    fn test_resolve_copies_host_values() {
+        let home = std::env::var("HOME").ok();
+        let resolved = resolve(vec![
+            "DEBUG=1".to_string(),
+            "HOME".to_string(),
+            "FINCH_MCP_TEST_UNSET_VAR".to_string(),
+        ]);
+        let mut expected = vec!["DEBUG=1".to_string()];
+        expected.extend(home.map(|home| format!("HOME={}", home)));
+        assert_eq!(resolved, expected);
+    }
+
+    #[test]
+    fn test_require_without_terminal_lists_missing() {
+        let manifest = McpManifest {
+            source: "smithery.yaml".to_string(),
+            env: vec![
+                ManifestEnvVar { name: "API_KEY".to_string(), required: true, description: Some("Service key".to_string()), default: None },
+                ManifestEnvVar { name: "REGION".to_string(), required: true, description: None, default: None },
+                ManifestEnvVar { name: "LOG_LEVEL".to_string(), required: false, description: None, default: None },
+            ],
+            ..Default::default()
+        };
+
+        let mut env_vars = vec!["API_KEY=abc".to_string(), "REGION=eu".to_string()];
+        assert!(require(&manifest, &mut env_vars).is_ok());
+        assert_eq!(env_vars.len(), 2);
+
+        // At a terminal this would prompt instead
+        if !prompt::is_interactive() {
+            let error = require(&manifest, &mut Vec::new()).unwrap_err().to_string();
+            assert!(error.contains("API_KEY - Service key"));
+            assert!(error.contains("  REGION"));
+            assert!(!error.contains("LOG_LEVEL"));
+        }
+    }
+}
@@ -1,14 +1,20 @@
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use anyhow::{Context, Result};
-use log::{debug, info};
+use log::{debug, info, warn};
+use sha2::{Digest, Sha256};
 use tempfile::TempDir;
 
+use crate::cache::CacheManager;
+use crate::workspace;
+
 /// Represents a Git repository URL and its metadata  
 #[derive(Debug)]
 pub struct GitRepository {
     pub url: String,
     pub branch: Option<String>,
+    /// Commit to check out instead of the tip of `branch`, e.g. the one the cache key uses
+    pub commit: Option<String>,
     pub local_path: Option<PathBuf>,
     pub _temp_dir: Option<TempDir>, // Keep temp dir alive
 }
@@ -16,6 +22,9 @@ pub struct GitRepository {
 impl GitRepository {
     /// Create a new GitRepository from a URL
     pub fn new(url: &str) -> Self {
+        // `git+https://...` says the target is a repository; git itself wants the plain URL
+        let url = url.strip_prefix("git+").unwrap_or(url);
+        
         // Parse URL for branch information (e.g., url#branch)
         let (clean_url, branch) = if let Some((url_part, branch_part)) = url.split_once('#') {
             (url_part.to_string(), Some(branch_part.to_string()))
@@ -26,6 +35,7 @@ impl GitRepository {
         Self {
             url: clean_url,
             branch,
+            commit: None,
             local_path: None,
             _temp_dir: None,
         }
@@ -33,7 +43,8 @@ impl GitRepository {
 
     /// Check if the given string looks like a Git repository URL
     pub fn is_git_url(input: &str) -> bool {
-        input.starts_with("http://") 
+        input.starts_with("git+")
+            || input.starts_with("http://") 
             || input.starts_with("https://")
             || input.starts_with("git@")
             || input.starts_with("ssh://")
@@ -45,32 +56,43 @@ impl GitRepository {
 
     /// Clone the repository to a temporary directory
     pub async fn clone_to_temp(&mut self) -> Result<PathBuf> {
-        let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
+        self.clone_to_temp_quiet(false).await
+    }
+
+    /// Check the repository out into a temporary directory with optional quiet mode
+    ///
+    /// The checkout comes from a bare mirror under `git/` in the cache directory, which
+    /// is created on first use and fetched incrementally after that. When the remote
+    /// can't be reached the mirror is used as it is, so repositories built before can be
+    /// rebuilt offline.
+    #[tracing::instrument(name = "clone", skip_all, fields(url = %self.url), err)]
+    pub async fn clone_to_temp_quiet(&mut self, quiet: bool) -> Result<PathBuf> {
+        let mirror = self.mirror_path()?;
+        self.checkout_from_mirror(&mirror, quiet)
+    }
+
+    fn checkout_from_mirror(&mut self, mirror: &Path, quiet: bool) -> Result<PathBuf> {
+        let temp_dir = workspace::temp_dir("clone")?;
         let clone_path = temp_dir.path().join("repo");
         
-        info!("Cloning repository {} to {:?}", self.url, clone_path);
-        
-        let mut cmd = Command::new("git");
-        cmd.arg("clone")
-           .arg("--depth").arg("1"); // Shallow clone for faster downloads
-        
-        // Add branch specification if provided
-        if let Some(ref branch) = self.branch {
-            cmd.arg("--branch").arg(branch);
-        }
-        
-        cmd.arg(&self.url)
-           .arg(&clone_path)
-           .stdout(Stdio::inherit())
-           .stderr(Stdio::inherit());
-        
-        debug!("Running git command: {:?}", cmd);
+        self.update_mirror(mirror, quiet)?;
+        let commit = self.resolve_in_mirror(mirror)?;
+        info!("Checking out {} at {} to {:?}", self.url, commit, clone_path);
         
-        let status = cmd.status().context("Failed to execute git clone command")?;
-        
-        if !status.success() {
-            return Err(anyhow::anyhow!("Git clone failed with status: {}", status));
-        }
+        // `--shared` borrows the mirror's objects instead of copying them
+        run(git(quiet)
+            .args(["clone", "--shared", "--no-checkout", "--quiet"])
+            .arg(mirror)
+            .arg(&clone_path), "Failed to check out repository")?;
+        run(git(quiet)
+            .arg("-C").arg(&clone_path)
+            .args(["checkout", "--quiet", "--detach", &commit]), "Failed to check out repository")?;
+        // Builds that read the remote should see the real one, not the mirror
+        run(git(true)
+            .arg("-C").arg(&clone_path)
+            .args(["remote", "set-url", "origin", &self.url]), "Failed to check out repository")?;
+        checkout_submodules(&clone_path, quiet)?;
+        pull_lfs_files(&clone_path, quiet)?;
         
         // Keep the temp directory alive by storing it
         self.local_path = Some(clone_path.clone());
@@ -79,47 +101,72 @@ impl GitRepository {
         Ok(clone_path)
     }
 
-    /// Clone the repository to a temporary directory with optional quiet mode
-    pub async fn clone_to_temp_quiet(&mut self, quiet: bool) -> Result<PathBuf> {
-        let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
-        let clone_path = temp_dir.path().join("repo");
-        
-        info!("Cloning repository {} to {:?}", self.url, clone_path);
-        
-        let mut cmd = Command::new("git");
-        cmd.arg("clone")
-           .arg("--depth").arg("1"); // Shallow clone for faster downloads
-        
-        // Add branch specification if provided
-        if let Some(ref branch) = self.branch {
-            cmd.arg("--branch").arg(branch);
-        }
-        
-        cmd.arg(&self.url)
-           .arg(&clone_path);
-        
-        // Redirect output based on quiet mode
-        if quiet {
-            cmd.stdout(Stdio::null())
-               .stderr(Stdio::null());
+    /// Where the bare mirror of this repository lives
+    pub fn mirror_path(&self) -> Result<PathBuf> {
+        let hash = format!("{:x}", Sha256::digest(self.url.as_bytes()));
+        Ok(mirrors_dir()?.join(format!("{}.git", &hash[..16])))
+    }
+
+    /// Create the mirror, or fetch what changed since it was last used
+    fn update_mirror(&self, mirror: &Path, quiet: bool) -> Result<()> {
+        if !mirror.exists() {
+            info!("Creating mirror of {} at {:?}", self.url, mirror);
+            if let Some(dir) = mirror.parent() {
+                std::fs::create_dir_all(dir).context("Failed to create git mirror directory")?;
+            }
+            // Cloned beside the final path and renamed, so an interrupted clone is never used
+            let partial = mirror.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+            let cloned = run(git(quiet)
+                .args(["clone", "--bare"])
+                .arg("--").arg(&self.url).arg(&partial), "Git clone failed")
+                .and_then(|_| {
+                    run(git(true)
+                        .arg("--git-dir").arg(&partial)
+                        .args(["config", "remote.origin.fetch", "+refs/heads/*:refs/heads/*"]), "Failed to configure git mirror")
+                });
+            if let Err(e) = cloned {
+                let _ = std::fs::remove_dir_all(&partial);
+                return Err(e);
+            }
+            if std::fs::rename(&partial, mirror).is_err() {
+                // Another build created it first
+                let _ = std::fs::remove_dir_all(&partial);
+            }
+        } else if self.commit.as_deref().is_some_and(|commit| has_commit(mirror, commit)) {
+            debug!("Mirror of {} already has {:?}", self.url, self.commit);
         } else {
-            cmd.stdout(Stdio::inherit())
-               .stderr(Stdio::inherit());
+            debug!("Updating mirror of {} at {:?}", self.url, mirror);
+            let fetched = run(git(quiet)
+                .arg("--git-dir").arg(mirror)
+                .args(["fetch", "--prune", "--tags", "origin"]), "Git fetch failed");
+            if let Err(e) = fetched {
+                warn!("Could not update {} ({}); using the copy fetched earlier", self.url, e);
+            }
         }
         
-        debug!("Running git command: {:?}", cmd);
-        
-        let status = cmd.status().context("Failed to execute git clone command")?;
-        
-        if !status.success() {
-            return Err(anyhow::anyhow!("Git clone failed with status: {}", status));
+        // A commit no branch or tag points at any more has to be asked for by name
+        if let Some(commit) = &self.commit {
+            if !has_commit(mirror, commit) {
+                run(git(quiet)
+                    .arg("--git-dir").arg(mirror)
+                    .args(["fetch", "origin", commit]), &format!("Failed to fetch commit {}", commit))?;
+            }
         }
-        
-        // Keep the temp directory alive by storing it
-        self.local_path = Some(clone_path.clone());
-        self._temp_dir = Some(temp_dir);
-        
-        Ok(clone_path)
+        Ok(())
+    }
+
+    /// The commit to check out: the pinned commit, else the branch or tag, else `HEAD`
+    fn resolve_in_mirror(&self, mirror: &Path) -> Result<String> {
+        let git_ref = self.commit.as_deref().or(self.branch.as_deref()).unwrap_or("HEAD");
+        let output = Command::new("git")
+            .arg("--git-dir").arg(mirror)
+            .args(["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", git_ref)])
+            .output()
+            .context("Failed to execute git rev-parse")?;
+        if !output.status.success() {
+            anyhow::bail!("{} has no branch, tag or commit named '{}'", self.url, git_ref);
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
     }
 
     /// Get the local path of the cloned repository
@@ -128,6 +175,90 @@ impl GitRepository {
     }
 }
 
+/// The directory holding every repository mirror
+pub fn mirrors_dir() -> Result<PathBuf> {
+    Ok(CacheManager::get_cache_dir()?.join("git"))
+}
+
+/// Check out the repository's submodules, recursively, like `git clone --recurse-submodules`
+fn checkout_submodules(repo_path: &Path, quiet: bool) -> Result<()> {
+    if !repo_path.join(".gitmodules").exists() {
+        return Ok(());
+    }
+    info!("Checking out submodules in {:?}", repo_path);
+    run(git(quiet)
+        .arg("-C").arg(repo_path)
+        .args(["submodule", "update", "--init", "--recursive", "--jobs", "4"]), "Failed to check out submodules")
+}
+
+/// Replace Git LFS pointer files with their content. Without git-lfs on the host the
+/// build goes ahead with the pointers, after a warning
+fn pull_lfs_files(repo_path: &Path, quiet: bool) -> Result<()> {
+    if !uses_lfs(repo_path) {
+        return Ok(());
+    }
+    let lfs_installed = Command::new("git")
+        .args(["lfs", "version"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !lfs_installed {
+        crate::status!(
+            "{} This repository stores files in Git LFS, but git-lfs isn't installed, so the image will contain \
+             pointer files instead of their content. Install git-lfs (https://git-lfs.com) and rebuild with --force",
+            console::style("⚠️").yellow()
+        );
+        return Ok(());
+    }
+    info!("Pulling Git LFS files in {:?}", repo_path);
+    run(git(quiet)
+        .arg("-C").arg(repo_path)
+        .args(["lfs", "pull"]), "Failed to pull Git LFS files")?;
+    if repo_path.join(".gitmodules").exists() {
+        run(git(quiet)
+            .arg("-C").arg(repo_path)
+            .args(["submodule", "foreach", "--recursive", "git lfs pull"]), "Failed to pull Git LFS files")?;
+    }
+    Ok(())
+}
+
+/// Whether `.gitattributes` routes any files through the LFS filter
+fn uses_lfs(repo_path: &Path) -> bool {
+    std::fs::read_to_string(repo_path.join(".gitattributes"))
+        .is_ok_and(|attributes| attributes.lines().any(|line| !line.trim_start().starts_with('#') && line.contains("filter=lfs")))
+}
+
+fn has_commit(mirror: &Path, commit: &str) -> bool {
+    Command::new("git")
+        .arg("--git-dir").arg(mirror)
+        .args(["cat-file", "-e", &format!("{}^{{commit}}", commit)])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// A git command whose output goes to stderr, or nowhere when `quiet`
+fn git(quiet: bool) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.env("GIT_TERMINAL_PROMPT", "0");
+    if quiet {
+        cmd.stdout(Stdio::null()).stderr(Stdio::null());
+    } else {
+        cmd.stdout(std::io::stderr()).stderr(Stdio::inherit());
+    }
+    cmd
+}
+
+fn run(cmd: &mut Command, error: &str) -> Result<()> {
+    debug!("Running git command: {:?}", cmd);
+    let status = cmd.status().context("Failed to execute git command")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} with status: {}", error, status));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,6 +271,7 @@ mod tests {
         assert!(GitRepository::is_git_url("ssh://git@github.com/user/repo.git"));
         assert!(GitRepository::is_git_url("https://gitlab.com/user/repo"));
         assert!(GitRepository::is_git_url("https://example.com/repo.git"));
+        assert!(GitRepository::is_git_url("git+https://git.example.com/team/server"));
         
         assert!(!GitRepository::is_git_url("uvx mcp-server-time"));
         assert!(!GitRepository::is_git_url("npx @package/name"));
@@ -153,10 +285,67 @@ mod tests {
         assert_eq!(repo.branch, Some("main".to_string()));
     }
 
+    #[test]
+    fn test_new_strips_git_scheme() {
+        let repo = GitRepository::new("git+https://git.example.com/team/server#v1.0");
+        assert_eq!(repo.url, "https://git.example.com/team/server");
+        assert_eq!(repo.branch, Some("v1.0".to_string()));
+    }
+
     #[test]
     fn test_new_without_branch() {
         let repo = GitRepository::new("https://github.com/user/repo");
         assert_eq!(repo.url, "https://github.com/user/repo");
         assert_eq!(repo.branch, None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_uses_lfs() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(!uses_lfs(temp_dir.path()));
+        
+        std::fs::write(temp_dir.path().join(".gitattributes"), "# *.bin filter=lfs\n*.sh text eol=lf\n").unwrap();
+        assert!(!uses_lfs(temp_dir.path()));
+        
+        std::fs::write(temp_dir.path().join(".gitattributes"), "*.onnx filter=lfs diff=lfs merge=lfs -text\n").unwrap();
+        assert!(uses_lfs(temp_dir.path()));
+    }
+
+    #[test]
+    fn test_checkout_from_mirror() {
+        let temp_dir = TempDir::new().unwrap();
+        let upstream = temp_dir.path().join("upstream");
+        let commit = |file: &str| {
+            std::fs::write(upstream.join(file), file).unwrap();
+            for args in [vec!["add", "."], vec!["commit", "-q", "-m", file]] {
+                let status = Command::new("git").arg("-C").arg(&upstream).args(args)
+                    .env("GIT_AUTHOR_NAME", "test").env("GIT_AUTHOR_EMAIL", "test@example.com")
+                    .env("GIT_COMMITTER_NAME", "test").env("GIT_COMMITTER_EMAIL", "test@example.com")
+                    .status().unwrap();
+                assert!(status.success());
+            }
+            let output = Command::new("git").arg("-C").arg(&upstream).args(["rev-parse", "HEAD"]).output().unwrap();
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        std::fs::create_dir(&upstream).unwrap();
+        assert!(Command::new("git").args(["init", "-q"]).arg(&upstream).status().unwrap().success());
+        let first = commit("one.txt");
+        
+        let mirror = temp_dir.path().join("mirrors/upstream.git");
+        let url = upstream.display().to_string();
+        let mut repo = GitRepository::new(&url);
+        let path = repo.checkout_from_mirror(&mirror, true).unwrap();
+        assert!(path.join("one.txt").exists());
+        
+        // Later checkouts fetch what was pushed since
+        commit("two.txt");
+        let mut repo = GitRepository::new(&url);
+        let path = repo.checkout_from_mirror(&mirror, true).unwrap();
+        assert!(path.join("two.txt").exists());
+        
+        let mut pinned = GitRepository::new(&url);
+        pinned.commit = Some(first);
+        let path = pinned.checkout_from_mirror(&mirror, true).unwrap();
+        assert!(path.join("one.txt").exists() && !path.join("two.txt").exists());
+    }
+}
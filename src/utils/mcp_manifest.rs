@@ -0,0 +1,352 @@
+//! MCP manifests shipped by servers: `mcp.json`, `smithery.yaml` or an `"mcp"` field in package.json
+//!
+//! A manifest declares how the server is launched and which env vars it needs.
+//! When one is present its command wins over the start command guessed from the project type.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde_json::{Map, Value};
+
+/// Launch details declared by a server's MCP manifest
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct McpManifest {
+    /// File the manifest was read from, e.g. `smithery.yaml`
+    pub source: String,
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    pub env: Vec<ManifestEnvVar>,
+}
+
+/// An env var the server reads
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ManifestEnvVar {
+    pub name: String,
+    pub required: bool,
+    pub description: Option<String>,
+    pub default: Option<String>,
+}
+
+impl McpManifest {
+    /// The declared command and args as a shell command line; the generated `ENTRYPOINT`
+    /// JSON-escapes it with [`shell_entrypoint`](crate::templates::dockerfile::shell_entrypoint)
+    pub fn start_command(&self) -> Option<String> {
+        let command = self.command.as_deref().filter(|command| !command.trim().is_empty())?;
+        let mut parts = vec![command.to_string()];
+        parts.extend(self.args.iter().map(|arg| shell_quote(arg)));
+        Some(parts.join(" "))
+    }
+
+    /// Required env vars without a default that `env_vars` (`KEY=VALUE` or `KEY`) doesn't set
+    pub fn missing_env(&self, env_vars: &[String]) -> Vec<&ManifestEnvVar> {
+        self.env.iter()
+            .filter(|var| var.required && var.default.is_none())
+            .filter(|var| !env_vars.iter().any(|env| env.split('=').next() == Some(var.name.as_str())))
+            .collect()
+    }
+}
+
+type ManifestParser = fn(&str) -> Result<Option<McpManifest>>;
+
+/// Find and parse the project's MCP manifest. A manifest that can't be parsed is
+/// reported and ignored, so detection falls back to the project type
+pub fn detect_mcp_manifest(project_path: &Path) -> Option<McpManifest> {
+    let parsers: [(&str, ManifestParser); 3] = [
+        ("mcp.json", parse_mcp_json),
+        ("smithery.yaml", parse_smithery_yaml),
+        ("package.json", parse_package_json),
+    ];
+
+    for (file_name, parse) in parsers {
+        let path = project_path.join(file_name);
+        if !path.exists() {
+            continue;
+        }
+        let parsed = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", file_name))
+            .and_then(|content| parse(&content));
+        match parsed {
+            Ok(Some(mut manifest)) => {
+                debug!("Found MCP manifest in {}: {:?}", file_name, manifest);
+                manifest.source = file_name.to_string();
+                return Some(manifest);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Ignoring MCP manifest {}: {:#}", file_name, e),
+        }
+    }
+    None
+}
+
+/// Either a single server (`command`, `args`, `env`) or an `mcpServers` map as MCP clients use
+fn parse_mcp_json(content: &str) -> Result<Option<McpManifest>> {
+    let value: Value = serde_json::from_str(content).context("Invalid JSON")?;
+    let server = match value.get("mcpServers").and_then(Value::as_object) {
+        Some(servers) => {
+            if servers.len() > 1 {
+                debug!("mcp.json declares {} servers; using the first", servers.len());
+            }
+            servers.values().next().cloned().unwrap_or(Value::Null)
+        }
+        None => value,
+    };
+    Ok(parse_server(&server))
+}
+
+fn parse_package_json(content: &str) -> Result<Option<McpManifest>> {
+    let value: Value = serde_json::from_str(content).context("Invalid JSON")?;
+    Ok(value.get("mcp").and_then(parse_server))
+}
+
+fn parse_server(server: &Value) -> Option<McpManifest> {
+    let server = server.as_object()?;
+    let command = server.get("command").and_then(Value::as_str).map(str::to_string);
+    let args = server.get("args")
+        .and_then(Value::as_array)
+        .map(|args| args.iter().filter_map(|arg| arg.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    let env = match server.get("env") {
+        Some(Value::Object(env)) => env.iter().map(|(name, value)| env_var_from_value(name, value)).collect(),
+        Some(Value::Array(env)) => env.iter()
+            .filter_map(|value| {
+                let name = value.get("name").and_then(Value::as_str)?;
+                Some(env_var_from_value(name, value))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    if command.is_none() && env.is_empty() {
+        return None;
+    }
+    Some(McpManifest { source: String::new(), command, args, env })
+}
+
+/// `"KEY": "value"` sets a default, unless the value is empty or a placeholder like
+/// `<your-api-key>` or `${API_KEY}`; `"KEY": {"required": .., "description": .., "default": ..}`
+/// spells it out
+fn env_var_from_value(name: &str, value: &Value) -> ManifestEnvVar {
+    match value {
+        Value::String(value) => {
+            let placeholder = value.is_empty()
+                || (value.starts_with('<') && value.ends_with('>'))
+                || value.starts_with("${");
+            ManifestEnvVar {
+                name: name.to_string(),
+                required: placeholder,
+                description: None,
+                default: (!placeholder).then(|| value.clone()),
+            }
+        }
+        Value::Object(spec) => {
+            let default = spec.get("default").and_then(scalar_string);
+            ManifestEnvVar {
+                name: name.to_string(),
+                required: spec.get("required").and_then(Value::as_bool).unwrap_or(default.is_none()),
+                description: spec.get("description").and_then(Value::as_str).map(str::to_string),
+                default,
+            }
+        }
+        _ => ManifestEnvVar { name: name.to_string(), required: true, ..Default::default() },
+    }
+}
+
+/// `startCommand.commandFunction` is a JavaScript arrow function such as
+/// `(config) => ({ command: 'node', args: ['dist/index.js'], env: { API_KEY: config.apiKey } })`.
+/// It isn't run; its literal command, args and env mapping are read from the source,
+/// and an env var is required if the config property it maps from is
+fn parse_smithery_yaml(content: &str) -> Result<Option<McpManifest>> {
+    let value: Value = serde_yaml::from_str(content).context("Invalid YAML")?;
+    let Some(start) = value.get("startCommand") else {
+        return Ok(None);
+    };
+    if start.get("type").and_then(Value::as_str).is_some_and(|kind| kind != "stdio") {
+        debug!("smithery.yaml declares a non-stdio server; ignoring its start command");
+        return Ok(None);
+    }
+    let Some(function) = start.get("commandFunction").and_then(Value::as_str) else {
+        return Ok(None);
+    };
+
+    let empty = Map::new();
+    let schema = start.get("configSchema");
+    let properties = schema.and_then(|schema| schema.get("properties")).and_then(Value::as_object).unwrap_or(&empty);
+    let required: Vec<&str> = schema
+        .and_then(|schema| schema.get("required"))
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let env = js_object_entries(function, "env")
+        .into_iter()
+        .map(|(name, value)| match value.strip_prefix("config.") {
+            Some(property) => {
+                let spec = properties.get(property);
+                ManifestEnvVar {
+                    name,
+                    required: required.contains(&property),
+                    description: spec.and_then(|spec| spec.get("description")).and_then(Value::as_str).map(str::to_string),
+                    default: spec.and_then(|spec| spec.get("default")).and_then(scalar_string),
+                }
+            }
+            None => ManifestEnvVar { name, required: false, description: None, default: js_string(&value) },
+        })
+        .collect();
+
+    Ok(Some(McpManifest {
+        source: String::new(),
+        command: js_value_after(function, "command").and_then(js_string),
+        args: js_value_after(function, "args").map(js_string_array).unwrap_or_default(),
+        env,
+    }))
+}
+
+fn scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// The source text following `key:` where `key` is a whole identifier
+fn js_value_after<'a>(source: &'a str, key: &str) -> Option<&'a str> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+    let mut offset = 0;
+    while let Some(found) = source[offset..].find(key) {
+        let start = offset + found;
+        let end = start + key.len();
+        let whole = !source[..start].ends_with(is_ident) && !source[end..].starts_with(is_ident);
+        if whole {
+            if let Some(rest) = source[end..].trim_start().strip_prefix(':') {
+                return Some(rest.trim_start());
+            }
+        }
+        offset = end;
+    }
+    None
+}
+
+/// A leading quoted string literal
+fn js_string(source: &str) -> Option<String> {
+    let quote = source.chars().next().filter(|c| matches!(c, '\'' | '"' | '`'))?;
+    let body = &source[1..];
+    let end = body.find(quote)?;
+    Some(body[..end].to_string())
+}
+
+/// The string literals of a leading `[...]`
+fn js_string_array(source: &str) -> Vec<String> {
+    let Some(body) = source.strip_prefix('[').and_then(|body| body.split_once(']')) else {
+        return Vec::new();
+    };
+    body.0.split(',').filter_map(|item| js_string(item.trim())).collect()
+}
+
+/// The `KEY: value` entries of the object literal following `key:`
+fn js_object_entries(source: &str, key: &str) -> Vec<(String, String)> {
+    let Some(body) = js_value_after(source, key).and_then(|value| value.strip_prefix('{')) else {
+        return Vec::new();
+    };
+    let Some((body, _)) = body.split_once('}') else {
+        return Vec::new();
+    };
+    body.split(',')
+        .filter_map(|entry| {
+            let (name, value) = entry.split_once(':')?;
+            let name = name.trim().trim_matches(|c| c == '\'' || c == '"');
+            (!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+pub(crate) fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mcp_json() {
+        let manifest = parse_mcp_json(r#"{
+            "command": "node",
+            "args": ["dist/index.js", "--root", "/data dir"],
+            "env": {
+                "API_KEY": {"description": "Service API key", "required": true},
+                "LOG_LEVEL": "info",
+                "TOKEN": "<your-token>"
+            }
+        }"#).unwrap().unwrap();
+        assert_eq!(manifest.start_command().as_deref(), Some("node dist/index.js --root '/data dir'"));
+
+        let missing: Vec<&str> = manifest.missing_env(&["TOKEN=abc".to_string()]).iter().map(|var| var.name.as_str()).collect();
+        assert_eq!(missing, ["API_KEY"]);
+        assert!(manifest.missing_env(&["API_KEY".to_string(), "TOKEN=abc".to_string()]).is_empty());
+
+        let clients = parse_mcp_json(r#"{"mcpServers": {"weather": {"command": "python", "args": ["-m", "weather"]}}}"#).unwrap().unwrap();
+        assert_eq!(clients.start_command().as_deref(), Some("python -m weather"));
+
+        // Args holding JSON, quotes or backslashes still make a valid exec-form ENTRYPOINT
+        let json_args = parse_mcp_json(r#"{"command": "node", "args": ["index.js", "--config", "{\"root\": \"C:\\data\"}"]}"#).unwrap().unwrap();
+        let command = json_args.start_command().unwrap();
+        assert_eq!(command, r#"node index.js --config '{"root": "C:\data"}'"#);
+        let entrypoint = crate::templates::dockerfile::shell_entrypoint(&[], &command);
+        let array: Vec<String> = serde_json::from_str(entrypoint.strip_prefix("ENTRYPOINT ").unwrap()).unwrap();
+        assert_eq!(array[2], format!("{} \"$@\"", command));
+
+        assert!(parse_package_json(r#"{"name": "x", "mcp": {"command": "x-server"}}"#).unwrap().is_some());
+        assert!(parse_package_json(r#"{"name": "x"}"#).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_smithery_yaml() {
+        let manifest = parse_smithery_yaml(r#"
+startCommand:
+  type: stdio
+  configSchema:
+    type: object
+    required: [braveApiKey]
+    properties:
+      braveApiKey:
+        type: string
+        description: The API key for the Brave Search server.
+      region:
+        type: string
+        default: us
+  commandFunction: |-
+    (config) => ({ command: 'node', args: ['dist/index.js'], env: { BRAVE_API_KEY: config.braveApiKey, REGION: config.region, MODE: "stdio" } })
+"#).unwrap().unwrap();
+
+        assert_eq!(manifest.start_command().as_deref(), Some("node dist/index.js"));
+        assert_eq!(manifest.env.len(), 3);
+        let missing = manifest.missing_env(&[]);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].name, "BRAVE_API_KEY");
+        assert_eq!(missing[0].description.as_deref(), Some("The API key for the Brave Search server."));
+
+        assert!(parse_smithery_yaml("startCommand:\n  type: http\n").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_detect_mcp_manifest_prefers_mcp_json() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("package.json"), r#"{"name": "x", "mcp": {"command": "from-package"}}"#).unwrap();
+        assert_eq!(detect_mcp_manifest(dir.path()).unwrap().source, "package.json");
+
+        fs::write(dir.path().join("mcp.json"), r#"{"command": "from-mcp-json"}"#).unwrap();
+        let manifest = detect_mcp_manifest(dir.path()).unwrap();
+        assert_eq!(manifest.source, "mcp.json");
+        assert_eq!(manifest.command.as_deref(), Some("from-mcp-json"));
+
+        fs::write(dir.path().join("mcp.json"), "not json").unwrap();
+        assert_eq!(detect_mcp_manifest(dir.path()).unwrap().source, "package.json");
+    }
+}
@@ -0,0 +1,32 @@
+//! Offline mode (`--offline`): never touch the network
+//!
+//! Only already-cached images run, with `--network none`. Anything that would need the
+//! network (resolving or cloning a git repository, a build with its package installs,
+//! update checks) fails straight away instead of hanging on an unreachable host. The
+//! setting travels with the options of each run (`with_offline`) rather than living in
+//! the process.
+
+use anyhow::Result;
+
+use crate::finch::client::NetworkMode;
+
+/// Fail when `offline` forbids `action`, which needs the network
+pub fn ensure_online(offline: bool, action: &str) -> Result<()> {
+    if offline {
+        anyhow::bail!(
+            "{} needs network access, which --offline rules out. Only cached images can run offline; \
+             build it once while online (e.g. with `finch-mcp warm`), then retry",
+            action
+        );
+    }
+    Ok(())
+}
+
+/// Network a server runs on: none at all when `offline`
+pub fn network(offline: bool, network: &NetworkMode) -> NetworkMode {
+    if offline {
+        NetworkMode::None
+    } else {
+        network.clone()
+    }
+}
@@ -0,0 +1,192 @@
+//! Pinning the package behind a `uvx`/`npx` command to the version it was built with
+//!
+//! `uvx mcp-server-time` names no version, so the image holds whatever was current when
+//! it was built and a `--force` rebuild silently moves to a newer release. Before a build
+//! the registry is asked for the current release and the command is rewritten to
+//! `mcp-server-time@0.6.2`: the Dockerfile installs exactly that and the cache entry
+//! records it. The cache key is still the command as typed.
+
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde_json::Value;
+
+use crate::utils::command_detector::{CommandDetails, CommandType};
+
+const PYPI_URL: &str = "https://pypi.org/pypi";
+const NPM_REGISTRY: &str = "https://registry.npmjs.org";
+
+/// Pin the package of a `uvx`/`npx` command to a concrete version, returning it as
+/// `package@version`. `None` for other commands, ranges uvx can't pin, or when the
+/// registry can't be reached (the build then goes ahead unpinned)
+pub fn pin(details: &mut CommandDetails) -> Option<String> {
+    let index = package_index(details)?;
+    let spec = details.args[index].clone();
+    match current(details) {
+        Ok(Some(pinned)) => {
+            if pinned != spec {
+                info!("Pinned {} to {}", spec, pinned);
+            }
+            details.args[index] = pinned.clone();
+            if details.cmd_type == CommandType::PythonUvx {
+                details.package_name = Some(pinned.clone());
+            }
+            Some(pinned)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Could not resolve the current version of {} ({}); building it unpinned", spec, e);
+            None
+        }
+    }
+}
+
+/// The `package@version` a build of the command would pin to now: the version given if
+/// it is exact, else the registry's current release
+pub fn current(details: &CommandDetails) -> Result<Option<String>> {
+    let Some(index) = package_index(details) else {
+        return Ok(None);
+    };
+    let spec = &details.args[index];
+    match details.cmd_type {
+        CommandType::PythonUvx => pin_python(spec),
+        CommandType::NodeNpx => pin_node(spec),
+        _ => Ok(None),
+    }
+}
+
+/// Where the package is in the arguments: the first one for `uvx` (options such as
+/// `--from` change what it means), the first non-flag one for `npx`
+fn package_index(details: &CommandDetails) -> Option<usize> {
+    match details.cmd_type {
+        CommandType::PythonUvx => details.args.first().filter(|arg| !arg.starts_with('-')).map(|_| 0),
+        CommandType::NodeNpx => details.args.iter().position(|arg| !arg.starts_with('-')),
+        _ => None,
+    }
+}
+
+/// `name[extras]@1.2.3` for `uvx`, asking PyPI unless the version is already exact
+fn pin_python(spec: &str) -> Result<Option<String>> {
+    let (requirement, version) = match spec.split_once("==").or_else(|| spec.split_once('@')) {
+        Some((requirement, version)) => (requirement.trim(), Some(version.trim())),
+        None => (spec, None),
+    };
+    if requirement.contains(['<', '>', '~', '!', '=']) || version.is_some_and(|version| version.contains("://")) {
+        return Ok(None);
+    }
+    if let Some(version) = version.filter(|version| !version.is_empty() && *version != "latest") {
+        return Ok(Some(format!("{}@{}", requirement, version)));
+    }
+    let name = requirement.split('[').next().unwrap_or(requirement);
+    let metadata = fetch_json(&format!("{}/{}/json", PYPI_URL, name))?;
+    let version = metadata["info"]["version"].as_str().context("PyPI returned no version")?;
+    Ok(Some(format!("{}@{}", requirement, version)))
+}
+
+/// `name@1.2.3` for `npx`, asking the npm registry to resolve a tag or range
+fn pin_node(spec: &str) -> Result<Option<String>> {
+    // A scope's `@` comes first, the version's after the name
+    let (name, version) = match spec.rfind('@').filter(|at| *at > 0) {
+        Some(at) => (&spec[..at], Some(&spec[at + 1..])),
+        None => (spec, None),
+    };
+    // Local paths, git URLs and tarballs aren't registry packages
+    if name.contains(':') || name.starts_with('.') || name.starts_with('/') {
+        return Ok(None);
+    }
+    if let Some(version) = version.filter(|version| is_exact_version(version)) {
+        return Ok(Some(format!("{}@{}", name, version)));
+    }
+
+    let registry = std::env::var("NPM_CONFIG_REGISTRY")
+        .or_else(|_| std::env::var("npm_config_registry"))
+        .unwrap_or_else(|_| NPM_REGISTRY.to_string());
+    let url = format!(
+        "{}/{}/{}",
+        registry.trim_end_matches('/'),
+        encode(name),
+        encode(version.filter(|version| !version.is_empty()).unwrap_or("latest"))
+    );
+    let metadata = fetch_json(&url)?;
+    let version = metadata["version"].as_str().context("the npm registry returned no version")?;
+    Ok(Some(format!("{}@{}", name, version)))
+}
+
+/// `1.2.3`, optionally with a `-prerelease` or `+build` suffix
+fn is_exact_version(version: &str) -> bool {
+    let core = version.split(['-', '+']).next().unwrap_or(version);
+    let parts: Vec<&str> = core.split('.').collect();
+    parts.len() == 3 && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Percent-encode a URL path segment, so `@scope/name` stays one segment
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'@' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn fetch_json(url: &str) -> Result<Value> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--max-time", "10"])
+        .arg(url)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run curl")?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    serde_json::from_slice(&output.stdout).with_context(|| format!("{} did not return JSON", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command_detector::detect_command_type;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_exact_versions_need_no_registry() {
+        let mut details = detect_command_type("uvx", &args(&["mcp-server-time==0.6.2", "--local-timezone", "UTC"]));
+        assert_eq!(pin(&mut details).as_deref(), Some("mcp-server-time@0.6.2"));
+        assert_eq!(details.args, ["mcp-server-time@0.6.2", "--local-timezone", "UTC"]);
+        assert_eq!(details.package_name.as_deref(), Some("mcp-server-time@0.6.2"));
+
+        let mut details = detect_command_type("npx", &args(&["-y", "@modelcontextprotocol/server-filesystem@0.6.2", "/data"]));
+        assert_eq!(pin(&mut details).as_deref(), Some("@modelcontextprotocol/server-filesystem@0.6.2"));
+        assert_eq!(details.args, ["-y", "@modelcontextprotocol/server-filesystem@0.6.2", "/data"]);
+    }
+
+    #[test]
+    fn test_unpinnable_commands_are_left_alone() {
+        for (command, arguments) in [
+            ("uvx", &["--from", "git+https://example.com/repo", "server"][..]),
+            ("uvx", &["mcp-server-time>=0.6"][..]),
+            ("npx", &["-y", "./local-server"][..]),
+            ("npx", &["github:acme/server"][..]),
+            ("node", &["server.js"][..]),
+        ] {
+            let mut details = detect_command_type(command, &args(arguments));
+            assert_eq!(pin(&mut details), None, "{} {:?}", command, arguments);
+            assert_eq!(details.args, args(arguments));
+        }
+    }
+
+    #[test]
+    fn test_is_exact_version() {
+        assert!(is_exact_version("1.2.3"));
+        assert!(is_exact_version("1.0.0-beta.1"));
+        assert!(!is_exact_version("latest"));
+        assert!(!is_exact_version("^1.2.3"));
+        assert!(!is_exact_version("1.2"));
+        assert_eq!(encode("@scope/name"), "@scope%2Fname");
+    }
+}
@@ -1,34 +1,168 @@
 use std::io;
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use anyhow::{Context, Result};
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
+use crate::core::finch_config::FinchConfig;
+use crate::events::{Event, Events};
+use crate::logging::LogSink;
+use crate::output;
+use crate::stats;
 use crate::status;
 
+/// How often a running build is checked against its deadline
+const BUILD_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Retries after a transient build failure unless `build.retries` says otherwise
+pub const DEFAULT_BUILD_RETRIES: u32 = 2;
+
+/// Delay before the first retry unless `build.retryBackoff` says otherwise; doubles each time
+pub const DEFAULT_RETRY_BACKOFF_SECS: u64 = 5;
+
+/// Output fragments of failures worth retrying: network errors, registry 5xx/429, and a VM that is still starting
+const TRANSIENT_BUILD_ERRORS: &[&str] = &[
+    "connection reset by peer",
+    "connection refused",
+    "i/o timeout",
+    "tls handshake timeout",
+    "temporary failure in name resolution",
+    "no such host",
+    "unexpected eof",
+    "500 internal server error",
+    "502 bad gateway",
+    "503 service unavailable",
+    "504 gateway timeout",
+    "toomanyrequests",
+    "429 too many requests",
+    "vm is not running",
+    "instance is not running",
+    "buildkitd.sock",
+];
+
+/// How a build is bounded and retried
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildPolicy {
+    pub timeout: Option<Duration>,
+    pub retries: u32,
+    pub retry_backoff: Duration,
+}
+
+impl BuildPolicy {
+    /// Delay before retry number `retry` (starting at 1)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.retry_backoff * 2u32.saturating_pow(retry.saturating_sub(1))
+    }
+}
+
+/// Build policy from the project's `build` settings; `timeout` (`--build-timeout`)
+/// overrides `build.timeout`
+pub fn build_policy(config: Option<&FinchConfig>, timeout: Option<Duration>) -> BuildPolicy {
+    let build = config.map(|config| &config.build);
+    BuildPolicy {
+        timeout: timeout
+            .or_else(|| build.and_then(|build| build.timeout).map(Duration::from_secs)),
+        retries: build.and_then(|build| build.retries).unwrap_or(DEFAULT_BUILD_RETRIES),
+        retry_backoff: Duration::from_secs(build.and_then(|build| build.retry_backoff).unwrap_or(DEFAULT_RETRY_BACKOFF_SECS)),
+    }
+}
+
+/// The transient error a failed build's output shows, if any
+pub fn transient_failure(output: &str) -> Option<&'static str> {
+    let output = output.to_lowercase();
+    TRANSIENT_BUILD_ERRORS.iter().copied().find(|pattern| output.contains(pattern))
+}
+
+/// Record a retry in the build log and tell the user about it
+fn note_retry(log: &LogSink, reason: &str, retry: u32, policy: &BuildPolicy, events: &Events) {
+    let delay = policy.backoff(retry);
+    let message = format!("Build failed with a transient error ({}); retrying in {}s (retry {}/{})",
+        reason, delay.as_secs(), retry, policy.retries);
+    log.write_line(&message);
+    log::warn!("{}", message);
+    events.emit(Event::BuildRetry { retry, reason: reason.to_string() });
+    status!("{} {}", style("🔁").yellow(), message);
+}
+
+/// Returned when a build is killed for running past its timeout
+#[derive(Debug)]
+pub struct BuildTimeout {
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for BuildTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Build timed out after {}s and was stopped", self.timeout.as_secs())
+    }
+}
+
+impl std::error::Error for BuildTimeout {}
+
+/// Wait for a build, killing it once `timeout` has passed
+fn wait_for_build(child: &mut Child, timeout: Option<Duration>, log: &LogSink) -> Result<ExitStatus> {
+    let Some(timeout) = timeout else {
+        return child.wait().context("Failed to wait for build process");
+    };
+    
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for build process")? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            log.write_line(&format!("Build timed out after {}s; finch build was killed", timeout.as_secs()));
+            return Err(BuildTimeout { timeout }.into());
+        }
+        thread::sleep(BUILD_POLL_INTERVAL);
+    }
+}
+
 pub struct BuildProgress {
     pb: ProgressBar,
     start_time: Instant,
     current_step: Arc<Mutex<String>>,
+    plain: bool,
+    /// Nothing is drawn or printed: quiet mode, or an embedder takes the build's events
+    hidden: bool,
+    /// Target this build belongs to when several build at once
+    label: Option<String>,
 }
 
 impl BuildProgress {
-    pub fn new(total_steps: u64) -> Self {
+    /// Progress of a build of `total_steps` steps; `plain` prints one line per step
+    /// instead of drawing a bar (`--plain-progress`)
+    pub fn new(total_steps: u64, plain: bool, events: &Events) -> Self {
         let pb = ProgressBar::new(total_steps);
+        let label = output::target_label();
+        let hidden = output::is_quiet_mode() || events.is_set();
         
-        // Disable progress bar in quiet mode (MCP_STDIO)
-        if crate::output::is_quiet_mode() {
+        // Disable progress bar in quiet mode (MCP_STDIO), for plain output, and when an embedder takes events
+        let pb = if hidden || plain {
             pb.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+            pb
         } else {
+            // Concurrent builds each get a labelled line of their own
+            let (pb, template) = match &label {
+                Some(label) => {
+                    let pb = output::multi_progress().add(pb);
+                    pb.set_prefix(label.clone());
+                    (pb, "{spinner:.green} {prefix:.cyan.bold} [{elapsed_precise}] {bar:30.cyan/blue} {pos:>3}/{len:3} {msg}")
+                }
+                None => (pb, "{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>3}/{len:3} {msg}"),
+            };
             pb.set_style(
                 ProgressStyle::default_bar()
-                    .template("{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>3}/{len:3} {msg}")
+                    .template(template)
                     .unwrap()
                     .progress_chars("##-")
             );
-        }
+            pb.enable_steady_tick(Duration::from_millis(120));
+            pb
+        };
         
         let start_time = Instant::now();
         let current_step = Arc::new(Mutex::new(String::new()));
@@ -37,6 +171,9 @@ impl BuildProgress {
             pb,
             start_time,
             current_step,
+            plain,
+            hidden,
+            label,
         }
     }
     
@@ -51,8 +188,24 @@ impl BuildProgress {
         self.pb.inc(1);
     }
     
+    /// Move the bar to step `current` of `total`
+    pub fn set_step(&self, current: u64, total: u64) {
+        self.pb.set_length(total);
+        self.pb.set_position(current);
+    }
+    
     pub fn finish_with_message(&self, msg: &str) {
         self.pb.finish_with_message(msg.to_string());
+        if self.plain {
+            self.print(msg);
+        }
+    }
+    
+    /// Print a line about this build, labelled if it runs alongside others
+    fn print(&self, line: &str) {
+        if !self.hidden {
+            output::print_labelled(self.label.as_deref(), line);
+        }
     }
     
     pub fn elapsed(&self) -> Duration {
@@ -60,53 +213,95 @@ impl BuildProgress {
     }
 }
 
-pub struct DockerBuildProgress {
-    progress: BuildProgress,
-    build_phases: Vec<&'static str>,
-    current_phase: usize,
+/// A build step header from finch output
+///
+/// Understands BuildKit (`#8 [3/7] RUN npm install`, `#9 [builder 2/5] COPY . .`)
+/// and the classic builder (`Step 3/7 : RUN npm install`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildStep {
+    pub current: u64,
+    pub total: u64,
+    pub instruction: String,
 }
 
-impl Default for DockerBuildProgress {
-    fn default() -> Self {
-        Self::new()
+impl BuildStep {
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        
+        if let Some(rest) = line.strip_prefix("Step ") {
+            let (counts, instruction) = rest.split_once(" : ")?;
+            return Self::from_parts(counts, instruction);
+        }
+        
+        // Only `#N [...]` headers, not bracketed text inside a step's own output
+        let rest = match line.strip_prefix('#') {
+            Some(rest) => {
+                let (id, rest) = rest.split_once(' ')?;
+                if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                rest
+            }
+            None => line,
+        };
+        let (label, instruction) = rest.strip_prefix('[')?.split_once(']')?;
+        // Multi-stage builds prefix the counts with the stage name
+        Self::from_parts(label.rsplit(' ').next()?, instruction)
+    }
+    
+    fn from_parts(counts: &str, instruction: &str) -> Option<Self> {
+        let (current, total) = counts.split_once('/')?;
+        let current: u64 = current.parse().ok()?;
+        let total: u64 = total.parse().ok()?;
+        if current == 0 || current > total {
+            return None;
+        }
+        Some(Self { current, total, instruction: instruction.trim().to_string() })
     }
 }
 
+/// Width the running instruction is cut to in the progress message
+const INSTRUCTION_WIDTH: usize = 60;
+
+pub struct DockerBuildProgress {
+    progress: BuildProgress,
+    last_step: Option<BuildStep>,
+    events: Events,
+}
+
 impl DockerBuildProgress {
-    pub fn new() -> Self {
-        let build_phases = vec![
-            "Setting up build context",
-            "Pulling base image", 
-            "Installing dependencies",
-            "Copying application files",
-            "Finalizing image",
-        ];
-        
-        let progress = BuildProgress::new(build_phases.len() as u64);
+    pub fn new(plain: bool, events: Events) -> Self {
+        let progress = BuildProgress::new(0, plain, &events);
+        progress.set_message("🔨 Setting up build context");
         
         Self {
             progress,
-            build_phases,
-            current_phase: 0,
+            last_step: None,
+            events,
         }
     }
     
-    pub fn start_phase(&mut self, phase_index: usize) {
-        if phase_index < self.build_phases.len() {
-            self.current_phase = phase_index;
-            let phase_name = self.build_phases[phase_index];
-            self.progress.set_message(&format!("🔨 {}", phase_name));
-            self.progress.inc();
+    /// Advance the progress from a line of build output; lines that aren't step headers are ignored
+    pub fn observe_line(&mut self, line: &str) {
+        let Some(step) = BuildStep::parse(line) else {
+            return;
+        };
+        // BuildKit repeats a step's header when it finishes
+        if self.last_step.as_ref() == Some(&step) {
+            return;
         }
-    }
-    
-    pub fn update_message(&self, msg: &str) {
-        let phase_name = if self.current_phase < self.build_phases.len() {
-            self.build_phases[self.current_phase]
+        self.events.emit(Event::BuildStep(step.clone()));
+        
+        if self.progress.plain {
+            // Called from the threads following the build's output, where the task's label isn't visible
+            self.progress.print(&format!("  [{}/{}] {}", step.current, step.total, step.instruction));
         } else {
-            "Building"
-        };
-        self.progress.set_message(&format!("🔨 {}: {}", phase_name, msg));
+            let instruction: String = step.instruction.chars().take(INSTRUCTION_WIDTH).collect();
+            let ellipsis = if step.instruction.chars().count() > INSTRUCTION_WIDTH { "…" } else { "" };
+            self.progress.set_step(step.current, step.total);
+            self.progress.set_message(&format!("🔨 {}{}", instruction, ellipsis));
+        }
+        self.last_step = Some(step);
     }
     
     pub fn finish_success(&self, image_name: &str) {
@@ -124,21 +319,83 @@ impl DockerBuildProgress {
     }
 }
 
+/// Run blocking work (a build, hashing a tree) without stalling the other builds
+/// sharing the runtime
+pub fn blocking<T>(work: impl FnOnce() -> T) -> T {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => tokio::task::block_in_place(work),
+        _ => work(),
+    }
+}
+
+#[tracing::instrument(name = "build", skip_all, fields(image = image_name), err)]
 pub fn run_build_with_progress(
     build_command: &mut Command,
     image_name: &str,
     project_type: &str,
+    log: &LogSink,
+    policy: &BuildPolicy,
+    plain: bool,
+    events: &Events,
 ) -> Result<()> {
-    let mut progress = DockerBuildProgress::new();
-    
+    let started = Instant::now();
+    let result = blocking(|| build_with_progress(build_command, image_name, project_type, log, policy, plain, events));
+    stats::record_build(image_name, result.is_ok(), started.elapsed());
+    result
+}
+
+fn build_with_progress(
+    build_command: &mut Command,
+    image_name: &str,
+    project_type: &str,
+    log: &LogSink,
+    policy: &BuildPolicy,
+    plain: bool,
+    events: &Events,
+) -> Result<()> {
+    crate::finch::vm::wait_until_ready()?;
+    crate::finch::disk::preflight(log)?;
     // Start the build process
     status!("\n{} Containerizing {} project...", 
         style("🚀").blue(), 
         style(project_type).cyan().bold()
     );
     
-    progress.start_phase(0); // Setting up build context
-    
+    let mut retry = 0;
+    loop {
+        let progress = Arc::new(Mutex::new(DockerBuildProgress::new(plain, events.clone())));
+        let (exit_status, output) = build_once_with_progress(build_command, &progress, log, policy.timeout)?;
+        
+        // Finish progress based on result
+        let progress = progress.lock().unwrap();
+        if exit_status.success() {
+            progress.finish_success(image_name);
+            status!("{} Container ready! Starting server...\n", style("✨").green());
+            return Ok(());
+        }
+        
+        if retry < policy.retries {
+            if let Some(reason) = transient_failure(&output) {
+                retry += 1;
+                progress.finish_error(reason);
+                note_retry(log, reason, retry, policy, events);
+                thread::sleep(policy.backoff(retry));
+                continue;
+            }
+        }
+        
+        progress.finish_error(&build_error_summary(&output));
+        return Err(anyhow::anyhow!("Build failed with status: {}", exit_status));
+    }
+}
+
+/// One build attempt, returning its status and combined output
+fn build_once_with_progress(
+    build_command: &mut Command,
+    progress: &Arc<Mutex<DockerBuildProgress>>,
+    log: &LogSink,
+    timeout: Option<Duration>,
+) -> Result<(ExitStatus, String)> {
     // Configure command to capture output
     let mut child = build_command
         .stdout(Stdio::piped())
@@ -146,103 +403,295 @@ pub fn run_build_with_progress(
         .spawn()
         .context("Failed to start build process")?;
     
-    // Handle stdout in a separate thread to parse Docker build output
     let stdout = child.stdout.take().unwrap();
     let stderr = child.stderr.take().unwrap();
     
-    // Parse Docker build output to track progress
-    let progress_clone = Arc::new(Mutex::new(progress));
-    let progress_thread = {
-        let progress = progress_clone.clone();
-        thread::spawn(move || {
-            parse_docker_output(stdout, progress);
-        })
+    // BuildKit reports steps on stderr, the classic builder on stdout, so watch both
+    let stdout_thread = {
+        let progress = progress.clone();
+        let log = log.clone();
+        thread::spawn(move || follow_build_output(stdout, "stdout", &progress, &log))
+    };
+    let stderr_thread = {
+        let progress = progress.clone();
+        let log = log.clone();
+        thread::spawn(move || follow_build_output(stderr, "stderr", &progress, &log))
     };
-    
-    // Handle stderr
-    let error_thread = thread::spawn(move || {
-        use std::io::Read;
-        let mut error_output = String::new();
-        io::BufReader::new(stderr).read_to_string(&mut error_output).ok();
-        error_output
-    });
     
     // Wait for the process to complete
-    let exit_status = child.wait().context("Failed to wait for build process")?;
+    let exit_status = match wait_for_build(&mut child, timeout, log) {
+        Ok(status) => status,
+        Err(e) => {
+            if let Ok(progress) = progress.lock() {
+                progress.finish_error(&e.to_string());
+            }
+            return Err(e);
+        }
+    };
     
     // Wait for output parsing to complete
-    progress_thread.join().unwrap();
-    let error_output = error_thread.join().unwrap();
-    
-    // Finish progress based on result
-    let progress = progress_clone.lock().unwrap();
-    if exit_status.success() {
-        progress.finish_success(image_name);
-        status!("{} Container ready! Starting server...\n", style("✨").green());
-    } else {
-        let error_msg = if !error_output.trim().is_empty() {
-            error_output.trim()
-        } else {
-            "Unknown build error"
+    let mut output = stdout_thread.join().unwrap();
+    output.push_str(&stderr_thread.join().unwrap());
+    Ok((exit_status, output))
+}
+
+/// The line of build output that best explains a failure
+fn build_error_summary(output: &str) -> String {
+    let lines: Vec<&str> = output.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    lines.iter()
+        .rev()
+        .find(|line| line.contains("ERROR") || line.starts_with("error:"))
+        .or(lines.last())
+        .map(|line| line.to_string())
+        .unwrap_or_else(|| "Unknown build error".to_string())
+}
+
+/// Run a build without a progress bar, copying all of its output into the build log
+///
+/// Stderr is also echoed to ours when `echo_stderr` is set; stdout never is, so it
+/// stays clean for MCP traffic. Transient failures are retried per `policy`.
+#[tracing::instrument(name = "build", skip_all, fields(image = tracing::field::Empty), err)]
+pub fn run_build_logged(build_command: &mut Command, log: &LogSink, echo_stderr: bool, policy: &BuildPolicy, events: &Events) -> Result<ExitStatus> {
+    let image = image_tag(build_command);
+    if let Some(image) = &image {
+        tracing::Span::current().record("image", image.as_str());
+    }
+    let started = Instant::now();
+    let result = blocking(|| build_logged(build_command, log, echo_stderr, policy, events));
+    if let Some(image) = &image {
+        stats::record_build(image, result.as_ref().is_ok_and(|status| status.success()), started.elapsed());
+    }
+    result
+}
+
+/// The image a `finch build` command tags, from its `-t`
+fn image_tag(build_command: &Command) -> Option<String> {
+    let mut args = build_command.get_args().map(|arg| arg.to_string_lossy());
+    args.by_ref().find(|arg| arg == "-t" || arg == "--tag")?;
+    args.next().map(|image| image.into_owned())
+}
+
+fn build_logged(build_command: &mut Command, log: &LogSink, echo_stderr: bool, policy: &BuildPolicy, events: &Events) -> Result<ExitStatus> {
+    crate::finch::vm::wait_until_ready()?;
+    crate::finch::disk::preflight(log)?;
+    let mut retry = 0;
+    loop {
+        let (exit_status, output) = build_once_logged(build_command, log, echo_stderr, policy.timeout, events)?;
+        if exit_status.success() || retry >= policy.retries {
+            return Ok(exit_status);
+        }
+        let Some(reason) = transient_failure(&output) else {
+            return Ok(exit_status);
         };
-        progress.finish_error(error_msg);
-        return Err(anyhow::anyhow!("Build failed with status: {}", exit_status));
+        
+        retry += 1;
+        note_retry(log, reason, retry, policy, events);
+        thread::sleep(policy.backoff(retry));
     }
-    
-    Ok(())
 }
 
-fn parse_docker_output(
-    stdout: std::process::ChildStdout,
-    progress: Arc<Mutex<DockerBuildProgress>>,
-) {
-    use std::io::{BufRead, BufReader};
+/// Report a step header in `line` as an event, skipping BuildKit's repeated headers.
+/// Returns the step if it is a new one
+fn emit_build_step<'a>(line: &str, last_step: &'a mut Option<BuildStep>, events: &Events) -> Option<&'a BuildStep> {
+    let step = BuildStep::parse(line)?;
+    if last_step.as_ref() == Some(&step) {
+        return None;
+    }
+    events.emit(Event::BuildStep(step.clone()));
+    *last_step = Some(step);
+    last_step.as_ref()
+}
+
+/// One build attempt, returning its status and combined output
+fn build_once_logged(build_command: &mut Command, log: &LogSink, echo_stderr: bool, timeout: Option<Duration>, events: &Events) -> Result<(ExitStatus, String)> {
+    use std::io::BufRead;
+    
+    let mut child = build_command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute finch build command")?;
     
-    let reader = BufReader::new(stdout);
-    let mut current_phase = 0;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
     
-    for line in reader.lines().map_while(Result::ok) {
-        // Parse Docker build steps to track progress
-        if line.contains("FROM ") && current_phase == 0 {
-            if let Ok(mut p) = progress.lock() {
-                p.start_phase(1); // Pulling base image
-                current_phase = 1;
-            }
-        } else if (line.contains("RUN pip install") || 
-                  line.contains("RUN npm install") || 
-                  line.contains("RUN poetry install") ||
-                  line.contains("RUN uv pip install")) && current_phase <= 1 {
-            if let Ok(mut p) = progress.lock() {
-                p.start_phase(2); // Installing dependencies
-                current_phase = 2;
-            }
-        } else if line.contains("COPY . .") && current_phase <= 2 {
-            if let Ok(mut p) = progress.lock() {
-                p.start_phase(3); // Copying application files
-                current_phase = 3;
-            }
-        } else if line.contains("exporting to image") && current_phase <= 3 {
-            if let Ok(mut p) = progress.lock() {
-                p.start_phase(4); // Finalizing image
-                current_phase = 4;
-            }
-        }
-        
-        // Update with specific progress messages
-        if line.contains("downloading") {
-            if let Ok(p) = progress.lock() {
-                p.update_message("downloading base image");
-            }
-        } else if line.contains("extracting") {
-            if let Ok(p) = progress.lock() {
-                p.update_message("extracting layers");
+    let stdout_thread = {
+        let log = log.clone();
+        let events = events.clone();
+        thread::spawn(move || {
+            let mut output = String::new();
+            let mut last_step = None;
+            for line in io::BufReader::new(stdout).lines().map_while(Result::ok) {
+                emit_build_step(&line, &mut last_step, &events);
+                log.write_output("stdout", &line);
+                output.push_str(&line);
+                output.push('\n');
             }
-        } else if line.contains("npm WARN") || line.contains("warning") {
-            // Don't update for warnings, just continue
-        } else if line.contains("found ") && line.contains("vulnerabilities") {
-            if let Ok(p) = progress.lock() {
-                p.update_message("security scan complete");
+            output
+        })
+    };
+    // Alongside other builds, the raw output of each would be unreadable; show just the steps.
+    // Like `output::child_output()`, raw output is echoed in every mode but `--quiet`
+    let label = output::target_label();
+    let echo_raw = echo_stderr && output::mode() != output::OutputMode::Silent;
+    let echo_steps = echo_stderr && !output::is_quiet_mode() && !events.is_set();
+    let stderr_thread = {
+        let log = log.clone();
+        let events = events.clone();
+        thread::spawn(move || {
+            let mut output = String::new();
+            let mut last_step = None;
+            for line in io::BufReader::new(stderr).lines().map_while(Result::ok) {
+                let new_step = emit_build_step(&line, &mut last_step, &events);
+                match (&label, new_step) {
+                    (None, _) if echo_raw => eprintln!("{}", line),
+                    (Some(label), Some(step)) if echo_steps => {
+                        output::print_labelled(Some(label), &format!("  [{}/{}] {}", step.current, step.total, step.instruction));
+                    }
+                    _ => {}
+                }
+                log.write_output("stderr", &line);
+                output.push_str(&line);
+                output.push('\n');
             }
+            output
+        })
+    };
+    
+    let exit_status = wait_for_build(&mut child, timeout, log)?;
+    let mut output = stdout_thread.join().unwrap_or_default();
+    output.push_str(&stderr_thread.join().unwrap_or_default());
+    Ok((exit_status, output))
+}
+
+/// Log every line of build output and feed it to the progress display, returning the output
+fn follow_build_output<R: io::Read>(
+    reader: R,
+    stream: &str,
+    progress: &Mutex<DockerBuildProgress>,
+    log: &LogSink,
+) -> String {
+    use std::io::BufRead;
+    
+    let mut output = String::new();
+    for line in io::BufReader::new(reader).lines().map_while(Result::ok) {
+        log.write_output(stream, &line);
+        if let Ok(mut progress) = progress.lock() {
+            progress.observe_line(&line);
         }
+        output.push_str(&line);
+        output.push('\n');
     }
-}
\ No newline at end of file
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_retries(timeout: Option<Duration>) -> BuildPolicy {
+        BuildPolicy { timeout, retries: 0, retry_backoff: Duration::ZERO }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_build_logged_captures_both_streams() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("build.log");
+        let log = LogSink::open(&path).unwrap();
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo 'Step 1/3 : FROM node:20'; echo 'npm ERR! missing script: build' >&2; exit 1"]);
+        let status = run_build_logged(&mut command, &log, false, &no_retries(None), &Events::default()).unwrap();
+        assert!(!status.success());
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("Step 1/3 : FROM node:20"));
+        assert!(contents.contains("npm ERR! missing script: build"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_build_logged_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("build.log");
+        let log = LogSink::open(&path).unwrap();
+
+        let mut command = Command::new("sleep");
+        command.arg("10");
+        let started = Instant::now();
+        let err = run_build_logged(&mut command, &log, false, &no_retries(Some(Duration::from_millis(300))), &Events::default()).unwrap_err();
+
+        assert!(err.downcast_ref::<BuildTimeout>().is_some());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(std::fs::read_to_string(&path).unwrap().contains("timed out after"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_build_logged_retries_transient_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("build.log");
+        let log = LogSink::open(&path).unwrap();
+        let marker = dir.path().join("attempted");
+
+        // Fails with a registry 503 the first time, succeeds the second
+        let script = format!(
+            "if [ -e {0} ]; then echo built; else touch {0}; echo 'failed to fetch: 503 Service Unavailable' >&2; exit 1; fi",
+            marker.display()
+        );
+        let mut command = Command::new("sh");
+        command.args(["-c", &script]);
+        let policy = BuildPolicy { timeout: None, retries: 2, retry_backoff: Duration::from_millis(10) };
+        assert!(run_build_logged(&mut command, &log, false, &policy, &Events::default()).unwrap().success());
+        assert!(std::fs::read_to_string(&path).unwrap().contains("retrying in"));
+
+        // Real errors fail straight away
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo 'npm ERR! missing script: build' >&2; exit 1"]);
+        assert!(!run_build_logged(&mut command, &log, false, &policy, &Events::default()).unwrap().success());
+    }
+
+    #[test]
+    fn test_build_policy() {
+        let policy = build_policy(None, None);
+        assert_eq!(policy.retries, DEFAULT_BUILD_RETRIES);
+        assert_eq!(policy.backoff(1), Duration::from_secs(DEFAULT_RETRY_BACKOFF_SECS));
+        assert_eq!(policy.backoff(3), Duration::from_secs(DEFAULT_RETRY_BACKOFF_SECS * 4));
+
+        let config: FinchConfig = serde_yaml::from_str("build:\n  retries: 0\n  retryBackoff: 1\n").unwrap();
+        let policy = build_policy(Some(&config), None);
+        assert_eq!((policy.retries, policy.retry_backoff), (0, Duration::from_secs(1)));
+
+        let config: FinchConfig = serde_yaml::from_str("build:\n  timeout: 300\n").unwrap();
+        assert_eq!(build_policy(Some(&config), None).timeout, Some(Duration::from_secs(300)));
+        assert_eq!(build_policy(Some(&config), Some(Duration::from_secs(60))).timeout, Some(Duration::from_secs(60)));
+
+        assert_eq!(transient_failure("ERROR: dial tcp: lookup registry-1.docker.io: no such host"), Some("no such host"));
+        assert_eq!(transient_failure("npm ERR! 404 Not Found"), None);
+    }
+
+    #[test]
+    fn test_parse_build_step() {
+        let step = BuildStep::parse("#8 [3/7] RUN npm install").unwrap();
+        assert_eq!((step.current, step.total, step.instruction.as_str()), (3, 7, "RUN npm install"));
+
+        let step = BuildStep::parse("#12 [builder 2/5] COPY package*.json ./").unwrap();
+        assert_eq!((step.current, step.total), (2, 5));
+        assert_eq!(step.instruction, "COPY package*.json ./");
+
+        let step = BuildStep::parse("Step 4/9 : WORKDIR /app").unwrap();
+        assert_eq!((step.current, step.total, step.instruction.as_str()), (4, 9, "WORKDIR /app"));
+
+        assert!(BuildStep::parse("#1 [internal] load build definition from Dockerfile").is_none());
+        assert!(BuildStep::parse("#8 1.204 [1/4] Resolving packages...").is_none());
+        assert!(BuildStep::parse("#8 DONE 12.3s").is_none());
+    }
+
+    #[test]
+    fn test_build_error_summary() {
+        let output = "#8 [3/7] RUN npm install\n#8 4.1 npm ERR! 404 Not Found\n#8 ERROR: process \"/bin/sh -c npm install\" did not complete successfully\n------\n";
+        assert!(build_error_summary(output).starts_with("#8 ERROR: process"));
+        assert_eq!(build_error_summary("\n"), "Unknown build error");
+    }
+}
@@ -4,12 +4,17 @@ use anyhow::{Context, Result};
 use log::debug;
 use serde_json::Value;
 
+use crate::utils::devcontainer::{detect_devcontainer, DevContainer};
+use crate::utils::mcp_manifest::{detect_mcp_manifest, McpManifest};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ProjectType {
     PythonPoetry,     // pyproject.toml with poetry
     PythonSetupPy,    // setup.py
     PythonRequirements, // requirements.txt
     PythonUv,         // pyproject.toml with uv
+    PythonPipenv,     // Pipfile
+    PythonConda,      // environment.yml
     NodeJs,           // package.json
     NodeJsMonorepo,   // package.json with workspaces (pnpm/npm)
     Rust,             // Cargo.toml
@@ -28,11 +33,65 @@ pub struct ProjectInfo {
     pub node_version: Option<String>,
     pub is_monorepo: bool,
     pub package_manager: Option<String>,
+    /// Version of the package manager pinned by `packageManager` or `engines`, installed with corepack
+    pub package_manager_version: Option<String>,
+    /// Node.js dependencies compiled at install time (e.g. `better-sqlite3`), which need a toolchain
+    pub native_dependencies: Vec<String>,
+    /// A TypeScript project (`tsconfig.json`) with a `build` script to run before it can start
+    pub typescript_build: bool,
+    /// Start command set with `--cmd` or `runtime.command`; replaces any detected one
+    pub start_command: Option<String>,
+    /// Bins or scripts the server could be started with, when there are several
+    pub entry_candidates: Vec<EntryCandidate>,
+    /// Launch command and env declared by the project's MCP manifest
+    pub mcp_manifest: Option<McpManifest>,
+    /// The monorepo workspace package selected with `--package`
+    pub workspace_package: Option<WorkspacePackage>,
+    /// The repository's dev container definition, used with `--use-devcontainer`
+    pub devcontainer: Option<DevContainer>,
+}
+
+/// One of several bins or scripts a project could be started with
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryCandidate {
+    /// Bin or script name, as passed to `--entry`
+    pub name: String,
+    /// Path of the bin script, for Node.js projects
+    pub path: Option<String>,
+}
+
+impl ProjectInfo {
+    /// Several candidates and nothing to tell which one starts the server
+    pub fn entry_is_ambiguous(&self) -> bool {
+        !self.entry_candidates.is_empty() && self.entry_point.is_none() && self.run_command.is_none()
+    }
 }
 
+/// A package in a Node.js monorepo workspace
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspacePackage {
+    pub name: String,
+    /// Directory relative to the repository root, with `/` separators
+    pub dir: String,
+    /// Path of the package's bin (or main) script relative to the repository root
+    pub entry_point: Option<String>,
+    pub has_start_script: bool,
+    pub has_build_script: bool,
+}
+
+#[tracing::instrument(name = "detect", skip_all, fields(path = %repo_path.display()), err)]
 pub fn detect_project_type(repo_path: &Path) -> Result<ProjectInfo> {
     debug!("Detecting project type in: {:?}", repo_path);
     
+    let mut info = detect_project_layout(repo_path)?;
+    if info.project_type != ProjectType::Unknown {
+        info.mcp_manifest = detect_mcp_manifest(repo_path);
+        info.devcontainer = detect_devcontainer(repo_path);
+    }
+    Ok(info)
+}
+
+fn detect_project_layout(repo_path: &Path) -> Result<ProjectInfo> {
     // Check for Python projects first
     if let Some(info) = detect_python_project(repo_path)? {
         return Ok(info);
@@ -60,6 +119,14 @@ pub fn detect_project_type(repo_path: &Path) -> Result<ProjectInfo> {
         node_version: None,
         is_monorepo: false,
         package_manager: None,
+        package_manager_version: None,
+        native_dependencies: Vec::new(),
+        typescript_build: false,
+        start_command: None,
+        entry_candidates: Vec::new(),
+        mcp_manifest: None,
+        workspace_package: None,
+        devcontainer: None,
     })
 }
 
@@ -74,7 +141,28 @@ fn detect_python_project(repo_path: &Path) -> Result<Option<ProjectInfo>> {
         let content = fs::read_to_string(&pyproject_path)
             .context("Failed to read pyproject.toml")?;
         
-        let info = parse_pyproject_toml(&content)?;
+        let info = parse_pyproject_toml(repo_path, &content)?;
+        return Ok(Some(info));
+    }
+    
+    // Check for Pipfile (pipenv)
+    let pipfile_path = repo_path.join("Pipfile");
+    if pipfile_path.exists() {
+        debug!("Found Pipfile");
+        let content = fs::read_to_string(&pipfile_path)
+            .context("Failed to read Pipfile")?;
+        
+        let info = parse_pipfile(repo_path, &content)?;
+        return Ok(Some(info));
+    }
+    
+    // Check for environment.yml (conda)
+    if let Some(environment_file) = ["environment.yml", "environment.yaml"].into_iter().find(|file| repo_path.join(file).exists()) {
+        debug!("Found {}", environment_file);
+        let content = fs::read_to_string(repo_path.join(environment_file))
+            .with_context(|| format!("Failed to read {}", environment_file))?;
+        
+        let info = parse_conda_environment(environment_file, &content)?;
         return Ok(Some(info));
     }
     
@@ -88,10 +176,18 @@ fn detect_python_project(repo_path: &Path) -> Result<Option<ProjectInfo>> {
             bin_command: None,
             install_command: Some("pip install -e .".to_string()),
             run_command: None,
-            python_version: Some("3.11".to_string()),
+            python_version: Some(resolve_python_version(repo_path, None)),
             node_version: None,
             is_monorepo: false,
             package_manager: None,
+            package_manager_version: None,
+            native_dependencies: Vec::new(),
+            typescript_build: false,
+            start_command: None,
+            entry_candidates: Vec::new(),
+            mcp_manifest: None,
+            workspace_package: None,
+            devcontainer: None,
         }));
     }
     
@@ -105,10 +201,18 @@ fn detect_python_project(repo_path: &Path) -> Result<Option<ProjectInfo>> {
             bin_command: None,
             install_command: Some("pip install -r requirements.txt".to_string()),
             run_command: None,
-            python_version: Some("3.11".to_string()),
+            python_version: Some(resolve_python_version(repo_path, None)),
             node_version: None,
             is_monorepo: false,
             package_manager: None,
+            package_manager_version: None,
+            native_dependencies: Vec::new(),
+            typescript_build: false,
+            start_command: None,
+            entry_candidates: Vec::new(),
+            mcp_manifest: None,
+            workspace_package: None,
+            devcontainer: None,
         }));
     }
     
@@ -144,57 +248,70 @@ fn detect_nodejs_project(repo_path: &Path) -> Result<Option<ProjectInfo>> {
             (ProjectType::NodeJs, None, "npm install".to_string())
         };
         
-        // Look for MCP server entry point and bin command
-        let (entry_point, bin_command) = package_json.get("bin")
-            .and_then(|bin| {
-                if let Some(bin_str) = bin.as_str() {
-                    // Single bin entry: use package name as command
-                    let cmd_name = name.clone().unwrap_or_else(|| "server".to_string());
-                    Some((bin_str.to_string(), cmd_name))
-                } else if let Some(bin_obj) = bin.as_object() {
-                    // Multiple bin entries: get the first one
-                    bin_obj.iter().next()
-                        .and_then(|(key, value)| {
-                            value.as_str().map(|path| (path.to_string(), key.clone()))
-                        })
-                } else {
-                    None
-                }
-            })
-            .map(|(path, cmd)| (Some(path), Some(cmd)))
-            .unwrap_or_else(|| {
-                // Fall back to main entry point
-                let main_entry = package_json.get("main")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
-                (main_entry, None)
-            });
-        
         // Check for start script
-        let run_command = package_json.get("scripts")
+        let has_start_script = package_json.get("scripts")
             .and_then(|scripts| scripts.get("start"))
-            .and_then(|v| v.as_str())
-            .map(|_s| {
-                if is_monorepo {
-                    match package_manager.as_deref() {
-                        Some("pnpm") => "pnpm run start".to_string(),
-                        Some("yarn") => "yarn start".to_string(),
-                        _ => "npm run start".to_string(),
-                    }
-                } else {
-                    "npm run start".to_string()
+            .is_some();
+        
+        // Look for MCP server entry point and bin command
+        let bins: Vec<EntryCandidate> = match package_json.get("bin") {
+            // Single bin entry: use package name as command
+            Some(Value::String(path)) => vec![EntryCandidate {
+                name: name.clone().unwrap_or_else(|| "server".to_string()),
+                path: Some(path.clone()),
+            }],
+            Some(Value::Object(bins)) => bins.iter()
+                .filter_map(|(key, value)| Some(EntryCandidate { name: key.clone(), path: Some(value.as_str()?.to_string()) }))
+                .collect(),
+            _ => Vec::new(),
+        };
+        // With several bins, the one named after the package; with a start script the bin
+        // doesn't decide what runs, so the first will do
+        let unscoped_name = name.as_deref().map(|name| name.rsplit('/').next().unwrap_or(name));
+        let default_bin = match bins.as_slice() {
+            [] => None,
+            [only] => Some(only),
+            _ => bins.iter().find(|bin| Some(bin.name.as_str()) == unscoped_name)
+                .or_else(|| bins.first().filter(|_| has_start_script)),
+        };
+        let (entry_point, bin_command) = match default_bin {
+            Some(bin) => (bin.path.clone(), Some(bin.name.clone())),
+            // Fall back to main entry point, unless it has to be picked from several bins
+            None if bins.is_empty() => (package_json.get("main").and_then(|v| v.as_str()).map(|s| s.to_string()), None),
+            None => (None, None),
+        };
+        let entry_candidates = if bins.len() > 1 { bins } else { Vec::new() };
+        
+        let run_command = has_start_script.then(|| {
+            if is_monorepo {
+                match package_manager.as_deref() {
+                    Some("pnpm") => "pnpm run start".to_string(),
+                    Some("yarn") => "yarn start".to_string(),
+                    _ => "npm run start".to_string(),
                 }
-            });
+            } else {
+                "npm run start".to_string()
+            }
+        });
+        
+        let has_build_script = package_json.get("scripts")
+            .and_then(|scripts| scripts.get("build"))
+            .is_some();
         
-        // Check for Node.js version requirement
+        // A pinned .nvmrc or .node-version wins over the engines.node range
         let raw_node_version = package_json.get("engines")
             .and_then(|engines| engines.get("node"))
             .and_then(|v| v.as_str());
         
-        let node_version = raw_node_version
-            .map(normalize_node_version)
+        let node_version = parse_node_version_file(repo_path)
+            .or_else(|| raw_node_version.map(normalize_node_version))
             .or_else(|| Some("20".to_string())); // Default to Node 20
         
+        let package_manager_version = pinned_package_manager_version(
+            &package_json,
+            package_manager.as_deref().unwrap_or("npm"),
+        );
+        
         return Ok(Some(ProjectInfo {
             project_type,
             name,
@@ -206,6 +323,14 @@ fn detect_nodejs_project(repo_path: &Path) -> Result<Option<ProjectInfo>> {
             node_version,
             is_monorepo,
             package_manager,
+            package_manager_version,
+            native_dependencies: detect_native_dependencies(repo_path, &package_json),
+            typescript_build: has_build_script && repo_path.join("tsconfig.json").exists(),
+            start_command: None,
+            entry_candidates,
+            mcp_manifest: None,
+            workspace_package: None,
+            devcontainer: None,
         }));
     }
     
@@ -229,74 +354,75 @@ fn detect_rust_project(repo_path: &Path) -> Result<Option<ProjectInfo>> {
             node_version: None,
             is_monorepo: false,
             package_manager: None,
+            package_manager_version: None,
+            native_dependencies: Vec::new(),
+            typescript_build: false,
+            start_command: None,
+            entry_candidates: Vec::new(),
+            mcp_manifest: None,
+            workspace_package: None,
+            devcontainer: None,
         }));
     }
     
     Ok(None)
 }
 
-fn parse_pyproject_toml(content: &str) -> Result<ProjectInfo> {
-    // Simple TOML parsing for key information
-    // For a full implementation, we'd use a proper TOML parser
+fn parse_pyproject_toml(repo_path: &Path, content: &str) -> Result<ProjectInfo> {
+    let pyproject: toml::Table = toml::from_str(content)
+        .context("Failed to parse pyproject.toml")?;
+    let project = pyproject.get("project").and_then(|v| v.as_table());
+    let tool = pyproject.get("tool").and_then(|v| v.as_table());
+    let poetry = tool.and_then(|tool| tool.get("poetry")).and_then(|v| v.as_table());
+    let has_tool = |name: &str| tool.is_some_and(|tool| tool.contains_key(name));
     
-    let mut project_type = ProjectType::PythonUv; // Default to uv
-    let mut name = None;
-    let mut entry_point = None;
-    let mut python_version = Some("3.11".to_string());
+    let project_type = if poetry.is_some() {
+        ProjectType::PythonPoetry
+    } else {
+        ProjectType::PythonUv // PEP 621 projects (uv, PDM, Hatch, Rye, setuptools) install with uv
+    };
     
-    // Check for Poetry
-    if content.contains("[tool.poetry]") {
-        project_type = ProjectType::PythonPoetry;
-    }
+    let name = project.and_then(|p| p.get("name"))
+        .or_else(|| poetry.and_then(|p| p.get("name")))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
     
-    // Extract project name
-    for line in content.lines() {
-        let line = line.trim();
-        if line.starts_with("name = ") {
-            if let Some(name_str) = line.strip_prefix("name = ") {
-                name = Some(name_str.trim_matches('"').to_string());
-            }
-        }
-        
-        // Look for Python version requirement
-        if line.contains("python = ") || line.contains("requires-python = ") {
-            if let Some(version_part) = line.split('=').nth(1) {
-                let version = version_part.trim().trim_matches('"');
-                if let Some(stripped) = version.strip_prefix(">=") {
-                    python_version = Some(stripped.to_string());
-                } else if let Some(stripped) = version.strip_prefix("^") {
-                    python_version = Some(stripped.to_string());
-                }
-            }
-        }
-    }
+    // The only script, or the one named after the project; several others have to be picked from
+    let scripts = project.and_then(|p| p.get("scripts"))
+        .or_else(|| poetry.and_then(|p| p.get("scripts")))
+        .and_then(|v| v.as_table());
+    let script_names: Vec<String> = scripts.map(|scripts| scripts.keys().cloned().collect()).unwrap_or_default();
+    let entry_point = match script_names.as_slice() {
+        [only] => Some(only.clone()),
+        _ => name.as_ref().filter(|name| script_names.contains(name)).cloned(),
+    };
+    let entry_candidates = script_candidates(&script_names);
     
-    // Try to find entry points
-    if content.contains("[project.scripts]") || content.contains("[tool.poetry.scripts]") {
-        // Look for script definitions
-        let mut in_scripts = false;
-        for line in content.lines() {
-            let line = line.trim();
-            if line.starts_with("[project.scripts]") || line.starts_with("[tool.poetry.scripts]") {
-                in_scripts = true;
-                continue;
-            }
-            if in_scripts && line.starts_with('[') {
-                break; // End of scripts section
-            }
-            if in_scripts && line.contains('=') {
-                if let Some(script_name) = line.split('=').next() {
-                    entry_point = Some(script_name.trim().to_string());
-                    break; // Use the first script as entry point
-                }
-            }
-        }
-    }
+    let requires_python = project.and_then(|p| p.get("requires-python"))
+        .or_else(|| poetry.and_then(|p| p.get("dependencies")).and_then(|d| d.get("python")))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .or_else(|| uv_lock_requires_python(repo_path));
+    let python_version = Some(resolve_python_version(repo_path, requires_python.as_deref()));
     
-    let install_command = match project_type {
-        ProjectType::PythonPoetry => Some("poetry install".to_string()),
-        ProjectType::PythonUv => Some("uv pip install -e .".to_string()),
-        _ => Some("pip install -e .".to_string()),
+    // The tool that manages the project, and how its locked dependencies are installed
+    let (package_manager, install_command) = if poetry.is_some() {
+        (Some("poetry"), "poetry install".to_string())
+    } else if repo_path.join("uv.lock").exists() {
+        (Some("uv"), "UV_PROJECT_ENVIRONMENT=/usr/local uv sync --frozen --no-dev".to_string())
+    } else if has_tool("rye") || repo_path.join("requirements.lock").exists() {
+        let install = if repo_path.join("requirements.lock").exists() {
+            "uv pip install --system -r requirements.lock"
+        } else {
+            "uv pip install --system -e ."
+        };
+        (Some("rye"), install.to_string())
+    } else if has_tool("pdm") || repo_path.join("pdm.lock").exists() {
+        (Some("pdm"), "uv pip install --system -e .".to_string())
+    } else if has_tool("hatch") {
+        (Some("hatch"), "uv pip install --system -e .".to_string())
+    } else {
+        (has_tool("uv").then_some("uv"), "uv pip install --system -e .".to_string())
     };
     
     Ok(ProjectInfo {
@@ -304,15 +430,132 @@ fn parse_pyproject_toml(content: &str) -> Result<ProjectInfo> {
         name,
         entry_point,
         bin_command: None,
-        install_command,
+        install_command: Some(install_command),
         run_command: None,
         python_version,
         node_version: None,
         is_monorepo: false,
-        package_manager: None,
+        package_manager: package_manager.map(|s| s.to_string()),
+        package_manager_version: None,
+        native_dependencies: Vec::new(),
+        typescript_build: false,
+        start_command: None,
+        entry_candidates,
+        mcp_manifest: None,
+        workspace_package: None,
+        devcontainer: None,
     })
 }
 
+/// Candidates for `--entry` when a project has several scripts
+fn script_candidates(names: &[String]) -> Vec<EntryCandidate> {
+    if names.len() < 2 {
+        return Vec::new();
+    }
+    names.iter().map(|name| EntryCandidate { name: name.clone(), path: None }).collect()
+}
+
+fn parse_pipfile(repo_path: &Path, content: &str) -> Result<ProjectInfo> {
+    let pipfile: toml::Table = toml::from_str(content)
+        .context("Failed to parse Pipfile")?;
+    
+    let requires = pipfile.get("requires").and_then(|v| v.as_table());
+    let requires_python = requires
+        .and_then(|r| r.get("python_version").or_else(|| r.get("python_full_version")))
+        .and_then(|v| v.as_str());
+    
+    // `pipenv run start` if there is a start script, else the only script
+    let scripts = pipfile.get("scripts").and_then(|v| v.as_table());
+    let script_names: Vec<String> = scripts.map(|scripts| scripts.keys().cloned().collect()).unwrap_or_default();
+    let entry_point = match script_names.as_slice() {
+        [only] => Some(only.clone()),
+        _ => script_names.iter().find(|name| *name == "start").cloned(),
+    };
+    let entry_candidates = script_candidates(&script_names);
+    
+    let install_command = if repo_path.join("Pipfile.lock").exists() {
+        "pipenv install --system --deploy"
+    } else {
+        "pipenv install --system --skip-lock"
+    };
+    
+    Ok(ProjectInfo {
+        project_type: ProjectType::PythonPipenv,
+        name: None,
+        entry_point,
+        bin_command: None,
+        install_command: Some(install_command.to_string()),
+        run_command: None,
+        python_version: Some(resolve_python_version(repo_path, requires_python)),
+        node_version: None,
+        is_monorepo: false,
+        package_manager: Some("pipenv".to_string()),
+        package_manager_version: None,
+        native_dependencies: Vec::new(),
+        typescript_build: false,
+        start_command: None,
+        entry_candidates,
+        mcp_manifest: None,
+        workspace_package: None,
+        devcontainer: None,
+    })
+}
+
+fn parse_conda_environment(environment_file: &str, content: &str) -> Result<ProjectInfo> {
+    let environment: serde_yaml::Value = serde_yaml::from_str(content)
+        .with_context(|| format!("Failed to parse {}", environment_file))?;
+    
+    let name = environment.get("name")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    
+    // `python=3.11` (a conda prefix match) or `python>=3.10`; the interpreter itself comes from conda
+    let python_version = environment.get("dependencies")
+        .and_then(|v| v.as_sequence())
+        .into_iter()
+        .flatten()
+        .filter_map(|dependency| dependency.as_str())
+        .find_map(|dependency| {
+            let spec = dependency.trim().strip_prefix("python")?.trim();
+            if spec.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return None; // e.g. python-dotenv
+            }
+            let spec = match spec.strip_prefix('=') {
+                Some(version) if !version.starts_with('=') => format!("=={}.*", version.trim_end_matches(".*")),
+                _ => spec.to_string(),
+            };
+            Some(if spec.is_empty() { format!("3.{}", DEFAULT_PYTHON_MINOR) } else { normalize_python_version(&spec) })
+        });
+    
+    Ok(ProjectInfo {
+        project_type: ProjectType::PythonConda,
+        name,
+        entry_point: None,
+        bin_command: None,
+        install_command: Some(format!("micromamba install -y -n base -f {} && micromamba clean --all --yes", environment_file)),
+        run_command: None,
+        python_version,
+        node_version: None,
+        is_monorepo: false,
+        package_manager: Some("micromamba".to_string()),
+        package_manager_version: None,
+        native_dependencies: Vec::new(),
+        typescript_build: false,
+        start_command: None,
+        entry_candidates: Vec::new(),
+        mcp_manifest: None,
+        workspace_package: None,
+        devcontainer: None,
+    })
+}
+
+/// The top-level `requires-python` recorded in uv.lock
+fn uv_lock_requires_python(repo_path: &Path) -> Option<String> {
+    let content = fs::read_to_string(repo_path.join("uv.lock")).ok()?;
+    let lock: toml::Table = toml::from_str(&content).ok()?;
+    lock.get("requires-python").and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
 fn extract_setup_py_name(repo_path: &Path) -> Result<Option<String>> {
     // Try to extract name from setup.py
     // This is a simplified approach - a full parser would be more robust
@@ -367,6 +610,210 @@ fn detect_nodejs_monorepo(repo_path: &Path, package_json: &Value) -> Result<bool
     Ok(false)
 }
 
+/// The packages matched by the workspace globs in package.json or pnpm-workspace.yaml
+pub fn list_workspace_packages(repo_path: &Path) -> Result<Vec<WorkspacePackage>> {
+    let mut patterns: Vec<String> = Vec::new();
+    
+    if let Ok(content) = fs::read_to_string(repo_path.join("package.json")) {
+        let package_json: Value = serde_json::from_str(&content)
+            .context("Failed to parse package.json")?;
+        // `"workspaces": [...]` or, with yarn, `"workspaces": {"packages": [...]}`
+        let workspaces = package_json.get("workspaces")
+            .map(|w| w.get("packages").unwrap_or(w))
+            .and_then(|w| w.as_array());
+        patterns.extend(workspaces.into_iter().flatten().filter_map(|p| p.as_str()).map(|p| p.to_string()));
+    }
+    
+    if let Ok(content) = fs::read_to_string(repo_path.join("pnpm-workspace.yaml")) {
+        let workspace: serde_yaml::Value = serde_yaml::from_str(&content)
+            .context("Failed to parse pnpm-workspace.yaml")?;
+        let globs = workspace.get("packages").and_then(|p| p.as_sequence());
+        patterns.extend(globs.into_iter().flatten().filter_map(|p| p.as_str()).map(|p| p.to_string()));
+    }
+    
+    let expand = |pattern: &str| expand_workspace_glob(repo_path, pattern.trim_start_matches("./").trim_end_matches('/'));
+    let excluded: Vec<String> = patterns.iter()
+        .filter_map(|p| p.strip_prefix('!'))
+        .flat_map(expand)
+        .collect();
+    let mut dirs: Vec<String> = Vec::new();
+    for pattern in patterns.iter().filter(|p| !p.starts_with('!')) {
+        for dir in expand(pattern) {
+            if !dirs.contains(&dir) && !excluded.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    
+    let mut packages = Vec::new();
+    for dir in dirs {
+        let Ok(content) = fs::read_to_string(repo_path.join(&dir).join("package.json")) else {
+            continue;
+        };
+        let Ok(package_json) = serde_json::from_str::<Value>(&content) else {
+            debug!("Skipping workspace package {} with an invalid package.json", dir);
+            continue;
+        };
+        let Some(name) = package_json.get("name").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let bin = package_json.get("bin").and_then(|bin| {
+            bin.as_str().or_else(|| bin.as_object().and_then(|bins| bins.values().next()).and_then(|v| v.as_str()))
+        });
+        let entry_point = bin
+            .or_else(|| package_json.get("main").and_then(|v| v.as_str()))
+            .map(|path| format!("{}/{}", dir, path.trim_start_matches("./")));
+        let has_script = |script: &str| package_json.get("scripts").and_then(|s| s.get(script)).is_some();
+        packages.push(WorkspacePackage {
+            name: name.to_string(),
+            dir: dir.clone(),
+            entry_point,
+            has_start_script: has_script("start"),
+            has_build_script: has_script("build"),
+        });
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
+/// Directories matching a workspace glob such as `packages/*`, `servers/mcp-*`, `apps/**` or `apps/web`
+fn expand_workspace_glob(repo_path: &Path, pattern: &str) -> Vec<String> {
+    let Some(wildcard) = pattern.find('*') else {
+        return vec![pattern.to_string()];
+    };
+    let (base, name_prefix) = pattern[..wildcard].rsplit_once('/').unwrap_or(("", &pattern[..wildcard]));
+    let rest = &pattern[wildcard..];
+    let recursive = rest.starts_with("**");
+    let name_suffix = rest.trim_start_matches('*').split('/').next().unwrap_or("");
+    
+    let mut dirs = Vec::new();
+    let mut pending = vec![base.to_string()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(repo_path.join(&dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !entry.path().is_dir() || file_name == "node_modules" || file_name.starts_with('.') {
+                continue;
+            }
+            let child = if dir.is_empty() { file_name.clone() } else { format!("{}/{}", dir, file_name) };
+            if recursive {
+                pending.push(child.clone());
+                dirs.push(child);
+            } else if file_name.starts_with(name_prefix) && file_name.ends_with(name_suffix) {
+                dirs.push(child);
+            }
+        }
+    }
+    dirs.sort();
+    dirs
+}
+
+/// Start the server with the bin or script named `name` (`--entry`)
+pub fn select_entry(project_info: &mut ProjectInfo, name: &str) -> Result<()> {
+    let Some(candidate) = project_info.entry_candidates.iter().find(|c| c.name == name).cloned() else {
+        if project_info.entry_candidates.is_empty() {
+            anyhow::bail!("Entry point '{}' requested, but this project has a single entry point", name);
+        }
+        let names: Vec<&str> = project_info.entry_candidates.iter().map(|c| c.name.as_str()).collect();
+        anyhow::bail!("Entry point '{}' not found; available entry points: {}", name, names.join(", "));
+    };
+    
+    debug!("Using entry point {}", candidate.name);
+    // An explicit choice wins over the start script
+    project_info.run_command = None;
+    match candidate.path {
+        Some(path) => {
+            project_info.entry_point = Some(path);
+            project_info.bin_command = Some(candidate.name);
+        }
+        None => project_info.entry_point = Some(candidate.name),
+    }
+    Ok(())
+}
+
+/// Runnable workspace packages to choose from when no package is named and the
+/// monorepo root has no entry point of its own; empty when there's nothing to choose
+pub fn workspace_package_choices(project_info: &ProjectInfo, repo_path: &Path) -> Result<Vec<String>> {
+    let root_has_entry = project_info.run_command.is_some() || project_info.bin_command.is_some() || project_info.entry_point.is_some();
+    if project_info.project_type != ProjectType::NodeJsMonorepo || root_has_entry {
+        return Ok(Vec::new());
+    }
+    let runnable: Vec<String> = list_workspace_packages(repo_path)?
+        .into_iter()
+        .filter(|p| p.entry_point.is_some() || p.has_start_script)
+        .map(|p| p.name)
+        .collect();
+    Ok(if runnable.len() > 1 { runnable } else { Vec::new() })
+}
+
+/// Build `package` from a monorepo's workspace instead of the repository root. Without one,
+/// a root that has no entry point of its own is an error when several packages could be meant
+pub fn select_workspace_package(project_info: &mut ProjectInfo, repo_path: &Path, package: Option<&str>) -> Result<()> {
+    if project_info.project_type != ProjectType::NodeJsMonorepo {
+        if let Some(package) = package {
+            anyhow::bail!("Workspace package '{}' requested, but this is a {:?} project, not a Node.js monorepo", package, project_info.project_type);
+        }
+        return Ok(());
+    }
+    
+    let packages = list_workspace_packages(repo_path)?;
+    let names = || packages.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", ");
+    let selected = match package {
+        Some(wanted) => {
+            // The full name, the name without its scope, or the directory
+            let found = packages.iter().find(|p| p.name == wanted)
+                .or_else(|| packages.iter().find(|p| p.name.rsplit('/').next() == Some(wanted)))
+                .or_else(|| packages.iter().find(|p| p.dir == wanted.trim_start_matches("./").trim_end_matches('/')));
+            match found {
+                Some(found) => found.clone(),
+                None => anyhow::bail!("Workspace package '{}' not found; available packages: {}", wanted, names()),
+            }
+        }
+        None => {
+            let root_has_entry = project_info.run_command.is_some() || project_info.bin_command.is_some() || project_info.entry_point.is_some();
+            if root_has_entry {
+                return Ok(());
+            }
+            let runnable: Vec<&WorkspacePackage> = packages.iter().filter(|p| p.entry_point.is_some() || p.has_start_script).collect();
+            match runnable.as_slice() {
+                [] => return Ok(()),
+                [only] => (*only).clone(),
+                _ => anyhow::bail!("This monorepo has several packages; pick one with --package <NAME>: {}", names()),
+            }
+        }
+    };
+    
+    debug!("Building workspace package {} in {}", selected.name, selected.dir);
+    let package_dir = repo_path.join(&selected.dir);
+    let engines_node = fs::read_to_string(package_dir.join("package.json")).ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|package_json| package_json.get("engines")?.get("node")?.as_str().map(normalize_node_version));
+    if let Some(node) = parse_node_version_file(&package_dir)
+        .or_else(|| parse_node_version_file(repo_path))
+        .or(engines_node)
+    {
+        project_info.node_version = Some(node);
+    }
+    project_info.name = Some(selected.name.clone());
+    project_info.bin_command = None;
+    project_info.entry_candidates.clear();
+    project_info.entry_point = selected.entry_point.clone();
+    project_info.run_command = selected.has_start_script.then(|| match project_info.package_manager.as_deref() {
+        Some("pnpm") => format!("pnpm --filter {} start", selected.name),
+        Some("yarn") => format!("yarn workspace {} start", selected.name),
+        _ => format!("npm start --workspace {}", selected.name),
+    });
+    // A root manifest can't describe one of several servers; the package's own one can
+    project_info.mcp_manifest = detect_mcp_manifest(&repo_path.join(&selected.dir)).map(|mut manifest| {
+        manifest.source = format!("{}/{}", selected.dir, manifest.source);
+        manifest
+    });
+    project_info.workspace_package = Some(selected);
+    Ok(())
+}
+
 fn detect_package_manager(repo_path: &Path) -> Result<Option<String>> {
     // Check for lock files to determine package manager
     if repo_path.join("pnpm-lock.yaml").exists() {
@@ -403,6 +850,183 @@ fn detect_package_manager(repo_path: &Path) -> Result<Option<String>> {
     Ok(None)
 }
 
+/// Detect ports declared with `EXPOSE` in a project's own Dockerfile
+/// Returns port specs such as "8080" or "53/udp"; variable references are skipped
+pub fn detect_exposed_ports(repo_path: &Path) -> Vec<String> {
+    let dockerfile_path = repo_path.join("Dockerfile");
+    let content = match fs::read_to_string(&dockerfile_path) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut ports: Vec<String> = Vec::new();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some(instruction) if instruction.eq_ignore_ascii_case("EXPOSE") => {}
+            _ => continue,
+        }
+
+        for port in parts {
+            let port = port.trim_end_matches('\\');
+            if port.is_empty() || port.starts_with('$') || ports.iter().any(|p| p == port) {
+                continue;
+            }
+            ports.push(port.to_string());
+        }
+    }
+
+    ports
+}
+
+/// Well-known packages that compile a native addon when installed
+const KNOWN_NATIVE_PACKAGES: &[&str] = &[
+    "better-sqlite3", "sqlite3", "sharp", "canvas", "bcrypt", "argon2", "node-pty", "keytar",
+    "bufferutil", "utf-8-validate", "cpu-features", "re2", "leveldown", "classic-level",
+    "zeromq", "usb", "serialport", "@serialport/bindings-cpp", "isolated-vm", "duckdb",
+];
+
+/// Packages that exist to build native addons; depending on one means compiling something
+const NATIVE_BUILD_TOOLS: &[&str] = &["node-gyp", "node-gyp-build", "prebuild-install", "node-addon-api", "nan", "cmake-js"];
+
+/// Dependencies that compile native code during install: well-known native packages and
+/// node-gyp helpers in package.json, packages flagged with install scripts in
+/// package-lock.json or pnpm-lock.yaml, and the project itself if it has a binding.gyp
+fn detect_native_dependencies(repo_path: &Path, package_json: &Value) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    let mut add = |name: &str| {
+        if !found.iter().any(|existing| existing == name) {
+            found.push(name.to_string());
+        }
+    };
+    
+    for section in ["dependencies", "optionalDependencies", "devDependencies"] {
+        let Some(deps) = package_json.get(section).and_then(|v| v.as_object()) else {
+            continue;
+        };
+        for name in deps.keys() {
+            if KNOWN_NATIVE_PACKAGES.contains(&name.as_str()) || NATIVE_BUILD_TOOLS.contains(&name.as_str()) {
+                add(name);
+            }
+        }
+    }
+    
+    if repo_path.join("binding.gyp").exists() {
+        add("binding.gyp");
+    }
+    
+    if let Some(lock) = fs::read_to_string(repo_path.join("package-lock.json")).ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+    {
+        if let Some(packages) = lock.get("packages").and_then(|v| v.as_object()) {
+            for (path, package) in packages {
+                if package.get("hasInstallScript").and_then(|v| v.as_bool()) == Some(true) {
+                    if let Some((_, name)) = path.rsplit_once("node_modules/") {
+                        add(name);
+                    }
+                }
+            }
+        }
+    }
+    
+    if let Ok(content) = fs::read_to_string(repo_path.join("pnpm-lock.yaml")) {
+        if let Ok(lock) = serde_yaml::from_str::<serde_yaml::Value>(&content) {
+            if let Some(packages) = lock.get("packages").and_then(|v| v.as_mapping()) {
+                for (key, package) in packages {
+                    if package.get("requiresBuild").and_then(|v| v.as_bool()) != Some(true) {
+                        continue;
+                    }
+                    // Keys look like `/better-sqlite3@9.4.0` or `/@scope/name@1.0.0(peer@2)`
+                    if let Some(key) = key.as_str() {
+                        let key = key.trim_start_matches('/');
+                        let split = key.get(1..).and_then(|rest| rest.find('@')).map(|i| i + 1).unwrap_or(key.len());
+                        add(&key[..split]);
+                    }
+                }
+            }
+        }
+    }
+    
+    if !found.is_empty() {
+        debug!("Native dependencies: {}", found.join(", "));
+    }
+    found
+}
+
+/// Node.js version pinned by `.nvmrc` or `.node-version`, as a `node` image tag.
+/// Exact versions are kept (`v20.11.1` → `20.11.1`), `lts/<codename>` maps to its major
+/// version, and `lts/*` and `node` map to the `lts` and `current` tags
+fn parse_node_version_file(repo_path: &Path) -> Option<String> {
+    let content = [".nvmrc", ".node-version"].iter()
+        .find_map(|file| fs::read_to_string(repo_path.join(file)).ok())?;
+    let version = content.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .find(|line| !line.is_empty())?
+        .to_ascii_lowercase();
+    
+    let tag = match version.as_str() {
+        "lts/*" | "lts" => "lts".to_string(),
+        "node" | "stable" | "latest" | "current" => "current".to_string(),
+        alias if alias.starts_with("lts/") => {
+            let major = match &alias[4..] {
+                "fermium" => "14",
+                "gallium" => "16",
+                "hydrogen" => "18",
+                "iron" => "20",
+                "jod" => "22",
+                "krypton" => "24",
+                _ => return None,
+            };
+            major.to_string()
+        }
+        exact => {
+            let exact = exact.trim_start_matches('v');
+            let major: u32 = exact.split('.').next()?.parse().ok()?;
+            let valid = exact.split('.').count() <= 3 && exact.split('.').all(|part| part.parse::<u32>().is_ok());
+            if !valid || !(14..=30).contains(&major) {
+                debug!("Ignoring unsupported Node.js version '{}' in version file", version);
+                return None;
+            }
+            exact.to_string()
+        }
+    };
+    debug!("Node.js version {} pinned by version file", tag);
+    Some(tag)
+}
+
+/// Version of `package_manager` pinned by the `packageManager` field (`pnpm@9.1.0`) or,
+/// failing that, by `engines.<manager>`. Ranges keep only their major version
+fn pinned_package_manager_version(package_json: &Value, package_manager: &str) -> Option<String> {
+    let declared = package_json.get("packageManager")
+        .and_then(|v| v.as_str())
+        .and_then(|field| field.split_once('@'))
+        .filter(|(name, _)| *name == package_manager)
+        // Drop the integrity hash corepack appends, e.g. `+sha512.abc`
+        .map(|(_, version)| version.split('+').next().unwrap_or(version).to_string());
+    if declared.is_some() {
+        return declared;
+    }
+    
+    let range = package_json.get("engines")?.get(package_manager)?.as_str()?;
+    let range = range.split("||").next().unwrap_or(range).trim();
+    if range.starts_with('<') {
+        // Only an upper bound; nothing to pin to
+        return None;
+    }
+    let version = range.trim_start_matches(['>', '<', '=', '^', '~', 'v', ' ']);
+    let version = version.split_whitespace().next().unwrap_or(version);
+    if version.split('.').next()?.parse::<u32>().is_err() {
+        return None;
+    }
+    let exact = range.trim_start_matches('=').trim_start_matches('v') == version
+        && version.split('.').all(|part| part.parse::<u32>().is_ok());
+    if exact {
+        Some(version.to_string())
+    } else {
+        version.split('.').next().map(str::to_string)
+    }
+}
+
 /// Normalize Node.js version strings to valid Docker tag format
 /// Converts version ranges like ">=20", "^18.0.0", "~16.14" to specific versions
 fn normalize_node_version(version_str: &str) -> String {
@@ -483,6 +1107,86 @@ fn extract_major_version(version: &str) -> String {
     }
 }
 
+/// Python interpreters with official slim images, newest first
+const PYTHON_VERSIONS: [u32; 6] = [13, 12, 11, 10, 9, 8];
+const DEFAULT_PYTHON_MINOR: u32 = 11;
+
+/// Pick the Python interpreter to build with: `.python-version` if present, else
+/// the supported `3.x` closest to 3.11 that satisfies `requires` (a PEP 440 or Poetry
+/// specifier such as `>=3.9,<3.13` or `^3.10`)
+fn resolve_python_version(repo_path: &Path, requires: Option<&str>) -> String {
+    if let Some(pinned) = fs::read_to_string(repo_path.join(".python-version")).ok()
+        .and_then(|content| parse_python_version_file(&content))
+    {
+        return pinned;
+    }
+    requires.map(normalize_python_version)
+        .unwrap_or_else(|| format!("3.{}", DEFAULT_PYTHON_MINOR))
+}
+
+/// `3.12`, `3.12.1` or `cpython-3.12.1` from the first line of a `.python-version` file
+fn parse_python_version_file(content: &str) -> Option<String> {
+    let line = content.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('#'))?;
+    let version = line.rsplit('-').next().unwrap_or(line);
+    let mut parts = version.split('.');
+    let major: u32 = parts.next()?.parse().ok()?;
+    let minor: u32 = parts.next()?.trim_end_matches(|c: char| !c.is_ascii_digit()).parse().ok()?;
+    Some(format!("{}.{}", major, minor))
+}
+
+/// Map a Python version specifier to a concrete `3.x` image tag
+fn normalize_python_version(spec: &str) -> String {
+    let mut candidates: Vec<u32> = PYTHON_VERSIONS.iter()
+        .copied()
+        .filter(|minor| python_satisfies(*minor, spec))
+        .collect();
+    candidates.sort_by_key(|minor| (minor.abs_diff(DEFAULT_PYTHON_MINOR), std::cmp::Reverse(*minor)));
+    format!("3.{}", candidates.first().copied().unwrap_or(DEFAULT_PYTHON_MINOR))
+}
+
+/// Whether some release of Python `3.minor` satisfies the specifier
+fn python_satisfies(minor: u32, spec: &str) -> bool {
+    spec.split("||").any(|alternative| {
+        // Clauses are separated by commas (PEP 440) or spaces (Poetry); `>= 3.9` is one clause
+        let mut clauses: Vec<String> = Vec::new();
+        for token in alternative.replace(',', " ").split_whitespace() {
+            match clauses.last_mut() {
+                Some(last) if last.chars().all(|c| "<>=!~^".contains(c)) => last.push_str(token),
+                _ => clauses.push(token.to_string()),
+            }
+        }
+        clauses.iter().all(|clause| python_clause_satisfied(minor, clause))
+    })
+}
+
+fn python_clause_satisfied(minor: u32, clause: &str) -> bool {
+    let operator_len = clause.find(|c: char| !"<>=!~^".contains(c)).unwrap_or(clause.len());
+    let (operator, version) = clause.split_at(operator_len);
+    let version = version.trim_end_matches(".*");
+    let parts: Vec<u32> = version.split('.').map_while(|part| part.parse().ok()).collect();
+    let (Some(&major), bound_minor, patch) = (parts.first(), parts.get(1).copied(), parts.get(2).copied()) else {
+        return true; // Unparseable, e.g. `*`
+    };
+    // Compare (major, minor, patch) with the newest and oldest patch release of 3.minor
+    let bound = (major, bound_minor.unwrap_or(0), patch.unwrap_or(0));
+    let newest = (3, minor, u32::MAX);
+    let oldest = (3, minor, 0);
+    let same_release = major == 3 && bound_minor.is_none_or(|bound_minor| bound_minor == minor);
+    match operator {
+        ">=" => newest >= bound,
+        ">" => newest > bound,
+        "<=" => oldest <= bound,
+        "<" => oldest < bound,
+        "!=" => !(same_release && patch.is_none() && bound_minor.is_some()),
+        // `^3.10` allows 3.10 up to 4, `~3.10` only 3.10; `~=3.10` and `~=3.10.1` follow PEP 440
+        "^" => newest >= bound && major == 3,
+        "~" => newest >= bound && same_release,
+        "~=" if patch.is_some() => newest >= bound && same_release,
+        "~=" => newest >= bound && major == 3,
+        _ => same_release, // `==`, `===` or a bare version
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -512,6 +1216,139 @@ test-server = "test_mcp_server:main"
         assert_eq!(project_info.entry_point, Some("test-server".to_string()));
     }
 
+    #[test]
+    fn test_normalize_python_version() {
+        assert_eq!(normalize_python_version(">=3.9,<3.13"), "3.11");
+        assert_eq!(normalize_python_version(">=3.12"), "3.12");
+        assert_eq!(normalize_python_version(">= 3.8, < 3.11"), "3.10");
+        assert_eq!(normalize_python_version("^3.10"), "3.11");
+        assert_eq!(normalize_python_version("~3.9"), "3.9");
+        assert_eq!(normalize_python_version("~=3.12.1"), "3.12");
+        assert_eq!(normalize_python_version("==3.10.*"), "3.10");
+        assert_eq!(normalize_python_version(">=3.10,!=3.11"), "3.12");
+        assert_eq!(normalize_python_version(">=3.8 <3.10 || >=3.13"), "3.13");
+        assert_eq!(normalize_python_version("*"), "3.11");
+        assert_eq!(normalize_python_version("<3"), "3.11");
+
+        assert_eq!(parse_python_version_file("3.12.4\n"), Some("3.12".to_string()));
+        assert_eq!(parse_python_version_file("# pinned\ncpython-3.10\n"), Some("3.10".to_string()));
+        assert_eq!(parse_python_version_file("system"), None);
+    }
+
+    #[test]
+    fn test_detect_python_uv_lock_and_version_file() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("pyproject.toml"), r#"
+[project]
+name = "weather"
+requires-python = ">=3.10,<3.13"
+
+[project.scripts]
+weather-cli = "weather:cli"
+weather = "weather:main"
+"#).unwrap();
+
+        let project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert_eq!(project_info.project_type, ProjectType::PythonUv);
+        assert_eq!(project_info.entry_point.as_deref(), Some("weather"));
+        assert_eq!(project_info.python_version.as_deref(), Some("3.11"));
+        assert_eq!(project_info.install_command.as_deref(), Some("uv pip install --system -e ."));
+
+        fs::write(temp_dir.path().join("uv.lock"), "version = 1\nrequires-python = \">=3.10\"\n").unwrap();
+        fs::write(temp_dir.path().join(".python-version"), "3.12\n").unwrap();
+        let project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert_eq!(project_info.package_manager.as_deref(), Some("uv"));
+        assert!(project_info.install_command.unwrap().contains("uv sync --frozen"));
+        assert_eq!(project_info.python_version.as_deref(), Some("3.12"));
+
+        fs::remove_file(temp_dir.path().join("uv.lock")).unwrap();
+        fs::write(temp_dir.path().join("requirements.lock"), "-e file:.\n").unwrap();
+        let project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert_eq!(project_info.package_manager.as_deref(), Some("rye"));
+        assert_eq!(project_info.install_command.as_deref(), Some("uv pip install --system -r requirements.lock"));
+    }
+
+    #[test]
+    fn test_detect_pipenv_and_conda_projects() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Pipfile"), r#"
+[packages]
+mcp = "*"
+
+[requires]
+python_version = "3.10"
+
+[scripts]
+serve = "python -m weather"
+"#).unwrap();
+
+        let project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert_eq!(project_info.project_type, ProjectType::PythonPipenv);
+        assert_eq!(project_info.python_version.as_deref(), Some("3.10"));
+        assert_eq!(project_info.entry_point.as_deref(), Some("serve"));
+        assert_eq!(project_info.install_command.as_deref(), Some("pipenv install --system --skip-lock"));
+
+        fs::write(temp_dir.path().join("Pipfile.lock"), "{}").unwrap();
+        let project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert_eq!(project_info.install_command.as_deref(), Some("pipenv install --system --deploy"));
+
+        let conda_dir = TempDir::new().unwrap();
+        fs::write(conda_dir.path().join("environment.yml"), r#"
+name: weather
+channels: [conda-forge]
+dependencies:
+  - python-dotenv
+  - python=3.12
+  - pip:
+      - mcp
+"#).unwrap();
+
+        let project_info = detect_project_type(conda_dir.path()).unwrap();
+        assert_eq!(project_info.project_type, ProjectType::PythonConda);
+        assert_eq!(project_info.name.as_deref(), Some("weather"));
+        assert_eq!(project_info.python_version.as_deref(), Some("3.12"));
+        assert!(project_info.install_command.unwrap().starts_with("micromamba install -y -n base -f environment.yml"));
+    }
+
+    #[test]
+    fn test_select_workspace_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("package.json"), r#"{"name": "servers", "private": true}"#).unwrap();
+        fs::write(root.join("pnpm-lock.yaml"), "").unwrap();
+        fs::write(root.join("pnpm-workspace.yaml"), "packages:\n  - 'packages/*'\n  - '!packages/ignored'\n").unwrap();
+        for (dir, package_json) in [
+            ("packages/weather", r#"{"name": "@acme/weather", "bin": {"weather": "./dist/index.js"}, "scripts": {"build": "tsc"}}"#),
+            ("packages/search", r#"{"name": "@acme/search", "main": "index.js", "scripts": {"start": "node index.js"}}"#),
+            ("packages/shared", r#"{"name": "@acme/shared"}"#),
+            ("packages/ignored", r#"{"name": "@acme/ignored", "main": "index.js"}"#),
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("package.json"), package_json).unwrap();
+        }
+
+        let packages = list_workspace_packages(root).unwrap();
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["@acme/search", "@acme/shared", "@acme/weather"]);
+
+        let mut project_info = detect_project_type(root).unwrap();
+        assert_eq!(project_info.project_type, ProjectType::NodeJsMonorepo);
+        let err = select_workspace_package(&mut project_info.clone(), root, None).unwrap_err();
+        assert!(err.to_string().contains("--package"));
+        assert!(select_workspace_package(&mut project_info.clone(), root, Some("nope")).unwrap_err().to_string().contains("@acme/weather"));
+
+        select_workspace_package(&mut project_info, root, Some("weather")).unwrap();
+        let package = project_info.workspace_package.as_ref().unwrap();
+        assert_eq!(package.dir, "packages/weather");
+        assert!(package.has_build_script);
+        assert_eq!(project_info.entry_point.as_deref(), Some("packages/weather/dist/index.js"));
+        assert_eq!(project_info.run_command, None);
+
+        let mut project_info = detect_project_type(root).unwrap();
+        select_workspace_package(&mut project_info, root, Some("packages/search")).unwrap();
+        assert_eq!(project_info.run_command.as_deref(), Some("pnpm --filter @acme/search start"));
+    }
+
     #[test]
     fn test_detect_nodejs_project() {
         let temp_dir = TempDir::new().unwrap();
@@ -535,6 +1372,39 @@ test-server = "test_mcp_server:main"
         assert_eq!(project_info.project_type, ProjectType::NodeJs);
         assert_eq!(project_info.name, Some("test-mcp-server".to_string()));
         assert_eq!(project_info.entry_point, Some("./bin/server.js".to_string()));
+        assert!(!project_info.typescript_build);
+        
+        fs::write(temp_dir.path().join("package.json"), r#"{"name": "ts-server", "main": "dist/index.js", "scripts": {"build": "tsc"}}"#).unwrap();
+        fs::write(temp_dir.path().join("tsconfig.json"), "{}").unwrap();
+        assert!(detect_project_type(temp_dir.path()).unwrap().typescript_build);
+    }
+    
+    #[test]
+    fn test_entry_candidates() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("package.json"), r#"{"name": "tools", "main": "index.js", "bin": {"tools-cli": "./cli.js", "tools-mcp": "./mcp.js"}}"#).unwrap();
+        
+        let mut project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert!(project_info.entry_is_ambiguous());
+        assert_eq!(project_info.entry_point, None);
+        assert!(select_entry(&mut project_info, "tools-web").unwrap_err().to_string().contains("tools-cli, tools-mcp"));
+        select_entry(&mut project_info, "tools-mcp").unwrap();
+        assert_eq!(project_info.entry_point.as_deref(), Some("./mcp.js"));
+        assert_eq!(project_info.bin_command.as_deref(), Some("tools-mcp"));
+        
+        // The bin named after the package is the obvious choice
+        fs::write(temp_dir.path().join("package.json"), r#"{"name": "@acme/tools", "bin": {"admin": "./admin.js", "tools": "./mcp.js"}}"#).unwrap();
+        let project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert!(!project_info.entry_is_ambiguous());
+        assert_eq!(project_info.bin_command.as_deref(), Some("tools"));
+        assert_eq!(project_info.entry_candidates.len(), 2);
+        
+        fs::remove_file(temp_dir.path().join("package.json")).unwrap();
+        fs::write(temp_dir.path().join("pyproject.toml"), "[project]\nname = \"svc\"\n\n[project.scripts]\nsvc-admin = \"svc:admin\"\nsvc-mcp = \"svc:mcp\"\n").unwrap();
+        let mut project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert!(project_info.entry_is_ambiguous());
+        select_entry(&mut project_info, "svc-mcp").unwrap();
+        assert_eq!(project_info.entry_point.as_deref(), Some("svc-mcp"));
     }
     
     #[test]
@@ -606,4 +1476,63 @@ test-server = "test_mcp_server:main"
         assert_eq!(project_info.project_type, ProjectType::NodeJs);
         assert_eq!(project_info.node_version, Some("18".to_string()));
     }
+
+    #[test]
+    fn test_node_version_file_and_pinned_package_manager() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("package.json"), r#"{"name": "pinned", "engines": {"node": ">=18", "npm": ">=10.2"}}"#).unwrap();
+        fs::write(temp_dir.path().join(".nvmrc"), "v20.11.1\n").unwrap();
+        
+        let project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert_eq!(project_info.node_version.as_deref(), Some("20.11.1"));
+        assert_eq!(project_info.package_manager_version.as_deref(), Some("10"));
+        
+        fs::write(temp_dir.path().join(".nvmrc"), "lts/iron\n").unwrap();
+        assert_eq!(parse_node_version_file(temp_dir.path()).as_deref(), Some("20"));
+        fs::write(temp_dir.path().join(".nvmrc"), "lts/*").unwrap();
+        assert_eq!(parse_node_version_file(temp_dir.path()).as_deref(), Some("lts"));
+        fs::write(temp_dir.path().join(".nvmrc"), "system").unwrap();
+        assert_eq!(parse_node_version_file(temp_dir.path()), None);
+        
+        let package_json: Value = serde_json::from_str(r#"{"packageManager": "pnpm@9.1.0+sha512.abc", "engines": {"pnpm": ">=8", "yarn": "1.22.19"}}"#).unwrap();
+        assert_eq!(pinned_package_manager_version(&package_json, "pnpm").as_deref(), Some("9.1.0"));
+        assert_eq!(pinned_package_manager_version(&package_json, "yarn").as_deref(), Some("1.22.19"));
+        assert_eq!(pinned_package_manager_version(&package_json, "npm"), None);
+    }
+
+    #[test]
+    fn test_detect_native_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("package.json"), r#"{"name": "db-server", "main": "index.js", "dependencies": {"better-sqlite3": "^9.4.0", "zod": "^3.0.0"}}"#).unwrap();
+        fs::write(temp_dir.path().join("package-lock.json"), r#"{"packages": {"": {}, "node_modules/zod": {}, "node_modules/better-sqlite3": {"hasInstallScript": true}, "node_modules/@img/sharp/node_modules/detect-libc": {"hasInstallScript": true}}}"#).unwrap();
+        
+        let project_info = detect_project_type(temp_dir.path()).unwrap();
+        assert_eq!(project_info.native_dependencies, ["better-sqlite3", "detect-libc"]);
+        
+        fs::remove_file(temp_dir.path().join("package-lock.json")).unwrap();
+        fs::write(temp_dir.path().join("package.json"), r#"{"name": "plain", "main": "index.js"}"#).unwrap();
+        fs::write(temp_dir.path().join("pnpm-lock.yaml"), "packages:\n  /@scope/addon@1.0.0:\n    requiresBuild: true\n  /zod@3.0.0:\n    resolution: {}\n").unwrap();
+        assert_eq!(detect_project_type(temp_dir.path()).unwrap().native_dependencies, ["@scope/addon"]);
+    }
+
+    #[test]
+    fn test_detect_exposed_ports() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(detect_exposed_ports(temp_dir.path()).is_empty());
+
+        let dockerfile_content = r#"
+FROM node:20-slim
+EXPOSE 8080
+expose 9090/tcp 53/udp
+EXPOSE $PORT 8080
+CMD ["node", "index.js"]
+"#;
+
+        fs::write(temp_dir.path().join("Dockerfile"), dockerfile_content).unwrap();
+
+        assert_eq!(
+            detect_exposed_ports(temp_dir.path()),
+            vec!["8080".to_string(), "9090/tcp".to_string(), "53/udp".to_string()]
+        );
+    }
 }
\ No newline at end of file
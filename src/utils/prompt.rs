@@ -0,0 +1,52 @@
+//! Questions asked on the terminal when detection needs a human to decide
+
+use std::io::{BufRead, IsTerminal, Write};
+
+use anyhow::Result;
+use console::style;
+
+/// Whether someone is at a terminal to answer; never when stdin carries an MCP stream
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Ask on stderr to pick one of `options`; the index of the answer, or None without a terminal
+pub fn choose(question: &str, options: &[String]) -> Result<Option<usize>> {
+    if !is_interactive() || options.is_empty() {
+        return Ok(None);
+    }
+    
+    eprintln!("{}", style(question).bold());
+    for (index, option) in options.iter().enumerate() {
+        eprintln!("  {}) {}", index + 1, option);
+    }
+    loop {
+        eprint!("Choose 1-{}: ", options.len());
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer)? == 0 {
+            anyhow::bail!("No answer given");
+        }
+        let answer = answer.trim();
+        let picked = answer.parse::<usize>().ok()
+            .filter(|n| (1..=options.len()).contains(n))
+            .map(|n| n - 1)
+            .or_else(|| options.iter().position(|option| option == answer));
+        if let Some(index) = picked {
+            return Ok(Some(index));
+        }
+    }
+}
+
+/// Ask on stderr for a value, hiding the answer when `secret`; an empty answer asks again
+pub fn ask(question: &str, secret: bool) -> Result<String> {
+    let term = console::Term::stderr();
+    loop {
+        term.write_str(&format!("{}: ", style(question).bold()))?;
+        let answer = if secret { term.read_secure_line()? } else { term.read_line()? };
+        let answer = answer.trim();
+        if !answer.is_empty() {
+            return Ok(answer.to_string());
+        }
+    }
+}
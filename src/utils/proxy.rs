@@ -0,0 +1,54 @@
+//! Forwarding the host's HTTP proxy settings into builds and containers (`--forward-proxy`)
+//!
+//! Only the variable names are passed to `finch` (`--build-arg HTTP_PROXY`, `-e HTTP_PROXY`);
+//! `finch` inherits our environment and reads the values from it, so proxy credentials
+//! never show up in build logs, plans or process listings.
+
+/// Proxy variables forwarded when set on the host. Build tools disagree on the case
+/// they read, so both spellings are passed through as-is
+pub const PROXY_VARS: [&str; 6] = ["HTTP_PROXY", "HTTPS_PROXY", "NO_PROXY", "http_proxy", "https_proxy", "no_proxy"];
+
+/// Names of the proxy variables to forward: none unless `forward` (`--forward-proxy`)
+pub fn forwarded_vars(forward: bool) -> Vec<&'static str> {
+    if !forward {
+        return Vec::new();
+    }
+    set_vars(|name| std::env::var(name).ok())
+}
+
+/// Arguments for `finch build` that pass the proxy variables as build args.
+/// `HTTP_PROXY` and friends are predefined build args, so Dockerfiles need no `ARG`
+/// and the values aren't kept in the image history
+pub fn build_args(forward: bool) -> Vec<String> {
+    forwarded_vars(forward)
+        .into_iter()
+        .flat_map(|name| ["--build-arg".to_string(), name.to_string()])
+        .collect()
+}
+
+/// The proxy variables set (and not empty) according to `lookup`
+fn set_vars(lookup: impl Fn(&str) -> Option<String>) -> Vec<&'static str> {
+    PROXY_VARS
+        .iter()
+        .copied()
+        .filter(|name| lookup(name).is_some_and(|value| !value.trim().is_empty()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_vars() {
+        let lookup = |name: &str| match name {
+            "HTTPS_PROXY" => Some("http://proxy.corp:3128".to_string()),
+            "no_proxy" => Some("localhost,.corp".to_string()),
+            "HTTP_PROXY" => Some(" ".to_string()),
+            _ => None,
+        };
+        assert_eq!(set_vars(lookup), ["HTTPS_PROXY", "no_proxy"]);
+        assert!(set_vars(|_| None).is_empty());
+        assert!(build_args(false).is_empty());
+    }
+}
@@ -0,0 +1,165 @@
+//! Working out what a target is: an image, a git repository, a local path or a command
+//!
+//! `--kind` (or `--direct` for images) settles it. Otherwise, in order: an existing
+//! directory, an archive, a git URL, anything with spaces (a command line), something
+//! written like a path, something shaped like an image reference, and finally a command.
+//! `owner/repo` is a Docker Hub image; GitHub repositories are `github:owner/repo`.
+
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::utils::archive_source;
+use crate::utils::git_repository::GitRepository;
+
+/// What a target names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TargetKind {
+    /// An existing container image, run as-is
+    Image,
+    /// A git repository URL
+    Git,
+    /// A local project directory, or an archive file
+    Path,
+    /// A command to auto-containerize, e.g. `uvx mcp-server-time`
+    Command,
+    /// A `.tar.gz`, `.tgz`, `.tar` or `.zip` archive, by URL or file
+    #[value(skip)]
+    Archive,
+}
+
+/// The kind of `target`, honouring an explicit `kind`
+pub fn resolve(target: &str, kind: Option<TargetKind>) -> TargetKind {
+    match kind {
+        Some(TargetKind::Path) if archive_source::is_archive(target) => TargetKind::Archive,
+        Some(kind) => kind,
+        None => guess(target),
+    }
+}
+
+fn guess(target: &str) -> TargetKind {
+    let path = Path::new(target);
+    if path.is_dir() {
+        return TargetKind::Path;
+    }
+    if archive_source::is_archive(target) {
+        return TargetKind::Archive;
+    }
+    if GitRepository::is_git_url(target) {
+        return TargetKind::Git;
+    }
+    if target.contains(char::is_whitespace) {
+        return TargetKind::Command;
+    }
+    // An existing file is a script or binary to run, never an image
+    if path.exists() {
+        return TargetKind::Command;
+    }
+    if looks_like_path(target) {
+        return TargetKind::Path;
+    }
+    if looks_like_image(target) {
+        return TargetKind::Image;
+    }
+    TargetKind::Command
+}
+
+/// Written as a path even if nothing is there: `./x`, `../x`, `/x`, `~/x` or `C:\x`
+pub fn looks_like_path(target: &str) -> bool {
+    let is_drive = target.len() > 2 && target.as_bytes()[0].is_ascii_alphabetic() && target[1..].starts_with([':']) && target[2..].starts_with(['\\', '/']);
+    target == "."
+        || target == ".."
+        || ["./", "../", "/", "~/", ".\\", "..\\"].iter().any(|prefix| target.starts_with(prefix))
+        || is_drive
+}
+
+/// Shaped like an image reference: `name:tag`, `registry/namespace/name[:tag]`,
+/// `owner/name` or `name@sha256:...`
+pub fn looks_like_image(target: &str) -> bool {
+    if target.is_empty() || target.contains(char::is_whitespace) || target.contains("://") || looks_like_path(target) {
+        return false;
+    }
+    if let Some((name, digest)) = target.split_once('@') {
+        return !name.is_empty() && digest.starts_with("sha256:");
+    }
+
+    let (name, tag) = match target.rsplit_once(':') {
+        // A colon before the last slash is a registry port: `localhost:5000/image`
+        Some((name, tag)) if !tag.contains('/') => (name, Some(tag)),
+        _ => (target, None),
+    };
+    let valid_name = !name.is_empty()
+        && name.split('/').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || "._-:".contains(c)));
+    if !valid_name {
+        return false;
+    }
+    match tag {
+        // An all-digit tag on a bare name is more likely `host:port`
+        Some(tag) => {
+            !tag.is_empty()
+                && tag.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+                && (name.contains('/') || !tag.chars().all(|c| c.is_ascii_digit()))
+        }
+        // Untagged, only `owner/name` or deeper
+        None => name.contains('/'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess() {
+        let cases = [
+            ("ghcr.io/user/image:tag", TargetKind::Image),
+            ("mcp/time", TargetKind::Image),
+            ("mcp/time:latest", TargetKind::Image),
+            ("node:20-alpine", TargetKind::Image),
+            ("alpine:3.19", TargetKind::Image),
+            ("localhost:5000/team/server", TargetKind::Image),
+            ("registry.example.com:5000/server:1.2.3", TargetKind::Image),
+            ("mcp/time@sha256:abc123", TargetKind::Image),
+            ("https://github.com/user/repo", TargetKind::Git),
+            ("git@github.com:user/repo.git", TargetKind::Git),
+            ("https://example.com/repo.git", TargetKind::Git),
+            ("git+https://git.example.com/team/server", TargetKind::Git),
+            ("https://github.com/user/repo/releases/download/v1/repo-v1.tar.gz", TargetKind::Archive),
+            (".", TargetKind::Path),
+            ("./missing-server-dir", TargetKind::Path),
+            ("../missing-server-dir", TargetKind::Path),
+            ("/srv/missing-server-dir", TargetKind::Path),
+            ("~/missing-server-dir", TargetKind::Path),
+            ("C:\\servers\\missing", TargetKind::Path),
+            ("uvx", TargetKind::Command),
+            ("uvx mcp-server-time", TargetKind::Command),
+            ("npx -y @modelcontextprotocol/server-time", TargetKind::Command),
+            ("Cargo.toml", TargetKind::Command),
+            ("server:8080", TargetKind::Command),
+            ("python3.11", TargetKind::Command),
+        ];
+        for (target, kind) in cases {
+            assert_eq!(resolve(target, None), kind, "{}", target);
+        }
+    }
+
+    #[test]
+    fn test_explicit_kind_wins() {
+        assert_eq!(resolve("mcp/time", Some(TargetKind::Command)), TargetKind::Command);
+        assert_eq!(resolve("uvx", Some(TargetKind::Image)), TargetKind::Image);
+        assert_eq!(resolve("https://example.com/team/server", Some(TargetKind::Git)), TargetKind::Git);
+        assert_eq!(resolve("https://example.com/server.zip", Some(TargetKind::Path)), TargetKind::Archive);
+        assert_eq!(resolve(".", Some(TargetKind::Image)), TargetKind::Image);
+    }
+
+    #[test]
+    fn test_looks_like_image() {
+        assert!(looks_like_image("alpine:3.19"));
+        assert!(!looks_like_image("https://example.com/docs"));
+        assert!(!looks_like_image("alpine"));
+        assert!(!looks_like_image("my server:1"));
+        assert!(!looks_like_image("host:8080"));
+        assert!(!looks_like_image("user/repo:"));
+        assert!(!looks_like_image("a//b"));
+    }
+}
@@ -0,0 +1,133 @@
+//! Explicit target schemes: `npm:`, `pypi:`, `oci:`, `github:` and `git+<url>`
+//!
+//! Without a scheme finch-mcp guesses whether a target is an image, a repository, a
+//! directory or a command. A scheme says which it is, and `npm:`/`pypi:` targets can pin
+//! a published version, which becomes part of the cache key like any command argument.
+
+use anyhow::Result;
+
+/// What a target with a scheme stands for
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemeTarget {
+    /// A package-runner command to auto-containerize
+    Command { command: String, args: Vec<String> },
+    /// An existing container image
+    Image(String),
+    /// A git repository URL, `git+` prefix included
+    Git(String),
+}
+
+/// Parse a target with a scheme; `None` for one without
+pub fn parse(target: &str) -> Result<Option<SchemeTarget>> {
+    if let Some(package) = target.strip_prefix("npm:") {
+        check_not_empty(package, "npm:@scope/package@1.2.3")?;
+        if package.rfind('@').is_some_and(|at| at > 0 && at == package.len() - 1) {
+            anyhow::bail!("{} has no version after '@'", target);
+        }
+        return Ok(Some(SchemeTarget::Command {
+            command: "npx".to_string(),
+            args: vec!["-y".to_string(), package.to_string()],
+        }));
+    }
+    if let Some(package) = target.strip_prefix("pypi:") {
+        check_not_empty(package, "pypi:package==1.2.3")?;
+        return Ok(Some(SchemeTarget::Command {
+            command: "uvx".to_string(),
+            args: vec![uvx_package(package)?],
+        }));
+    }
+    if let Some(image) = target.strip_prefix("oci:") {
+        check_not_empty(image, "oci:ghcr.io/owner/image:tag")?;
+        return Ok(Some(SchemeTarget::Image(image.to_string())));
+    }
+    if let Some(repository) = target.strip_prefix("github:") {
+        // `owner/repo`, optionally with `#ref`
+        let (path, git_ref) = repository.split_once('#').map_or((repository, None), |(path, git_ref)| (path, Some(git_ref)));
+        let valid = path.split('/').count() == 2 && path.split('/').all(|part| !part.is_empty() && !part.contains(char::is_whitespace));
+        if !valid {
+            anyhow::bail!("{} is not github:owner/repo (optionally with #branch)", target);
+        }
+        let url = format!("https://github.com/{}", path.trim_end_matches(".git"));
+        return Ok(Some(SchemeTarget::Git(match git_ref {
+            Some(git_ref) => format!("{}#{}", url, git_ref),
+            None => url,
+        })));
+    }
+    if target.starts_with("git+") {
+        return Ok(Some(SchemeTarget::Git(target.to_string())));
+    }
+    Ok(None)
+}
+
+fn check_not_empty(rest: &str, example: &str) -> Result<()> {
+    if rest.trim().is_empty() {
+        anyhow::bail!("Nothing after the scheme; expected e.g. {}", example);
+    }
+    Ok(())
+}
+
+/// `package==1.2.3` (or `package@1.2.3`) as `uvx` takes it: `package@1.2.3`
+fn uvx_package(spec: &str) -> Result<String> {
+    if let Some((name, version)) = spec.split_once("==") {
+        if name.is_empty() || version.is_empty() {
+            anyhow::bail!("pypi:{} needs a package name and a version, e.g. pypi:package==1.2.3", spec);
+        }
+        return Ok(format!("{}@{}", name, version));
+    }
+    if spec.contains(['<', '>', '~', '!', '=']) {
+        anyhow::bail!("pypi: targets pin an exact version with ==, e.g. pypi:package==1.2.3");
+    }
+    Ok(spec.to_string())
+}
+
+/// The requirement `uv pip install` takes for a `uvx` package: `package@1.2.3` becomes
+/// `package==1.2.3`
+pub fn pip_requirement(package: &str) -> String {
+    match package.split_once('@') {
+        Some((name, version)) if !name.is_empty() && !version.is_empty() && !version.contains("://") => {
+            format!("{}=={}", name.trim(), version.trim())
+        }
+        _ => package.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(command: &str, args: &[&str]) -> Option<SchemeTarget> {
+        Some(SchemeTarget::Command { command: command.to_string(), args: args.iter().map(|arg| arg.to_string()).collect() })
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("npm:@modelcontextprotocol/server-filesystem@0.6.2").unwrap(),
+            command("npx", &["-y", "@modelcontextprotocol/server-filesystem@0.6.2"])
+        );
+        assert_eq!(parse("npm:@scope/package").unwrap(), command("npx", &["-y", "@scope/package"]));
+        assert_eq!(parse("pypi:mcp-server-time==0.6.2").unwrap(), command("uvx", &["mcp-server-time@0.6.2"]));
+        assert_eq!(parse("pypi:mcp-server-time").unwrap(), command("uvx", &["mcp-server-time"]));
+        assert_eq!(parse("oci:ghcr.io/acme/server:1.0").unwrap(), Some(SchemeTarget::Image("ghcr.io/acme/server:1.0".to_string())));
+        assert_eq!(parse("git+https://example.com/repo").unwrap(), Some(SchemeTarget::Git("git+https://example.com/repo".to_string())));
+        assert_eq!(parse("github:acme/server").unwrap(), Some(SchemeTarget::Git("https://github.com/acme/server".to_string())));
+        assert_eq!(parse("github:acme/server#v1.0").unwrap(), Some(SchemeTarget::Git("https://github.com/acme/server#v1.0".to_string())));
+        assert_eq!(parse("uvx mcp-server-time").unwrap(), None);
+        assert_eq!(parse("./server").unwrap(), None);
+
+        assert!(parse("npm:").is_err());
+        assert!(parse("npm:package@").is_err());
+        assert!(parse("pypi:package>=1.0").is_err());
+        assert!(parse("pypi:==1.0").is_err());
+        assert!(parse("oci:").is_err());
+        assert!(parse("github:acme").is_err());
+        assert!(parse("github:acme/server/extra").is_err());
+    }
+
+    #[test]
+    fn test_pip_requirement() {
+        assert_eq!(pip_requirement("mcp-server-time@0.6.2"), "mcp-server-time==0.6.2");
+        assert_eq!(pip_requirement("mcp-server-time"), "mcp-server-time");
+        assert_eq!(pip_requirement("pkg @ https://example.com/pkg.whl"), "pkg @ https://example.com/pkg.whl");
+    }
+}
@@ -0,0 +1,121 @@
+//! Forwarding the host's timezone and locale into containers (on unless `--no-forward-timezone`)
+//!
+//! Containers start in UTC with no locale, so time servers report the wrong local time.
+//! The host's zone is passed as `TZ` and its `LANG`/`LC_ALL` as-is. Slim images often lack
+//! the zone database `TZ` needs, so generated images install `tzdata` when it is missing.
+
+use std::path::Path;
+
+/// Locale variables forwarded when set on the host
+pub const LOCALE_VARS: [&str; 2] = ["LANG", "LC_ALL"];
+
+/// The host's IANA zone name, e.g. `Europe/London`: `TZ`, else the target of `/etc/localtime`,
+/// else `/etc/timezone`
+pub fn host_timezone() -> Option<String> {
+    if let Ok(tz) = std::env::var("TZ") {
+        let tz = tz.trim().trim_start_matches(':');
+        if !tz.is_empty() {
+            return Some(zone_from_path(Path::new(tz)).unwrap_or_else(|| tz.to_string()));
+        }
+    }
+    if let Some(zone) = std::fs::read_link("/etc/localtime").ok().and_then(|target| zone_from_path(&target)) {
+        return Some(zone);
+    }
+    std::fs::read_to_string("/etc/timezone")
+        .ok()
+        .map(|zone| zone.trim().to_string())
+        .filter(|zone| !zone.is_empty())
+}
+
+/// The zone name in a path under a `zoneinfo` directory
+fn zone_from_path(path: &Path) -> Option<String> {
+    let path = path.to_string_lossy();
+    let (_, zone) = path.split_once("zoneinfo/")?;
+    Some(zone.trim_start_matches("posix/").to_string()).filter(|zone| !zone.is_empty())
+}
+
+/// `TZ` and locale variables to pass to a container, skipping names in `env_vars`; none
+/// unless `forward`
+pub fn env_vars(env_vars: &[String], forward: bool) -> Vec<String> {
+    if !forward {
+        return Vec::new();
+    }
+    let is_set = |name: &str| env_vars.iter().any(|env| env.split('=').next() == Some(name));
+
+    let mut forwarded = Vec::new();
+    if let Some(zone) = host_timezone().filter(|_| !is_set("TZ")) {
+        forwarded.push(format!("TZ={}", zone));
+    }
+    for name in LOCALE_VARS {
+        if let Ok(value) = std::env::var(name) {
+            if !value.trim().is_empty() && !is_set(name) {
+                forwarded.push(format!("{}={}", name, value));
+            }
+        }
+    }
+    forwarded
+}
+
+/// Install `tzdata` at the start of the final stage when the image has no zone database,
+/// if the timezone is forwarded (`forward`)
+pub fn apply(dockerfile: String, forward: bool) -> String {
+    if forward {
+        install_tzdata(&dockerfile)
+    } else {
+        dockerfile
+    }
+}
+
+fn install_tzdata(dockerfile: &str) -> String {
+    let lines: Vec<&str> = dockerfile.lines().collect();
+    let Some(from) = lines.iter().rposition(|line| line.trim_start().to_uppercase().starts_with("FROM ")) else {
+        return dockerfile.to_string();
+    };
+
+    let mut output = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        output.push_str(line);
+        output.push('\n');
+        if index == from {
+            output.push_str(
+                "\n# Zone database for the TZ forwarded from the host\n\
+                 RUN [ -e /usr/share/zoneinfo/UTC ] || \\\n    \
+                 (apk add --no-cache tzdata || (apt-get update && apt-get install -y --no-install-recommends tzdata && rm -rf /var/lib/apt/lists/*)) >/dev/null 2>&1 || true\n",
+            );
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_from_path() {
+        assert_eq!(zone_from_path(Path::new("/usr/share/zoneinfo/Europe/London")).as_deref(), Some("Europe/London"));
+        assert_eq!(zone_from_path(Path::new("/var/db/timezone/zoneinfo/America/New_York")).as_deref(), Some("America/New_York"));
+        assert_eq!(zone_from_path(Path::new("../usr/share/zoneinfo/posix/Asia/Tokyo")).as_deref(), Some("Asia/Tokyo"));
+        assert_eq!(zone_from_path(Path::new("Europe/Paris")), None);
+        assert_eq!(zone_from_path(Path::new("/usr/share/zoneinfo/")), None);
+    }
+
+    #[test]
+    fn test_install_tzdata_in_final_stage() {
+        let dockerfile = "FROM node:20 AS build\nRUN npm ci\n\nFROM node:20-alpine\nUSER node\nENTRYPOINT [\"node\", \"index.js\"]\n";
+        let installed = install_tzdata(dockerfile);
+
+        assert_eq!(installed.matches("apk add --no-cache tzdata").count(), 1);
+        let runtime = installed.split("FROM node:20-alpine").nth(1).unwrap();
+        assert!(runtime.find("tzdata").unwrap() < runtime.find("USER node").unwrap());
+        assert_eq!(install_tzdata("# no stages\n"), "# no stages\n");
+        assert_eq!(apply(dockerfile.to_string(), false), dockerfile);
+    }
+
+    #[test]
+    fn test_env_vars_keep_explicit_values() {
+        let forwarded = env_vars(&["TZ=UTC".to_string(), "LANG=C.UTF-8".to_string()], true);
+        assert!(forwarded.iter().all(|env| !env.starts_with("TZ=") && !env.starts_with("LANG=")));
+        assert!(env_vars(&[], false).is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Parsing and formatting of human-friendly ages (`14d`) and sizes (`10GiB`)
+
+const AGE_UNITS: &str = "s, m, h, d or w";
+
+/// Parse an age such as `14d`, `12h`, `30m` or `2w` into seconds
+pub fn parse_age(value: &str) -> Result<u64, String> {
+    let (number, unit) = split_number(value);
+    let number: u64 = number.parse()
+        .map_err(|_| format!("invalid age '{}': expected a number followed by {}", value, AGE_UNITS))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("invalid age '{}': expected a number followed by {}", value, AGE_UNITS)),
+    };
+    Ok(number * unit_secs)
+}
+
+/// Parse a size such as `500MB`, `10GiB` or `2g` into bytes; a bare number is bytes
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let (number, unit) = split_number(value.trim());
+    let number: f64 = number.parse()
+        .map_err(|_| format!("invalid size '{}': expected a number like 500MB or 10GiB", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "kib" => 1 << 10,
+        "m" | "mb" => 1000 * 1000,
+        "mib" => 1 << 20,
+        "g" | "gb" => 1000 * 1000 * 1000,
+        "gib" => 1 << 30,
+        "t" | "tb" => 1000 * 1000 * 1000 * 1000,
+        "tib" => 1 << 40,
+        _ => return Err(format!("invalid size '{}': unknown unit '{}'", value, unit)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Format a byte count for people, e.g. `1.5 GB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    if size < 1000.0 {
+        return format!("{} B", bytes);
+    }
+    let mut unit = 0;
+    size /= 1000.0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Format a number of seconds as a rough age in the largest whole unit, e.g. `3d`
+pub fn format_age(secs: u64) -> String {
+    const UNITS: [(u64, &str); 4] = [(7 * 24 * 60 * 60, "w"), (24 * 60 * 60, "d"), (60 * 60, "h"), (60, "m")];
+    UNITS.iter()
+        .find(|(unit_secs, _)| secs >= *unit_secs)
+        .map(|(unit_secs, unit)| format!("{}{}", secs / unit_secs, unit))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+fn split_number(value: &str) -> (&str, &str) {
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    value.split_at(split)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("14d"), Ok(1_209_600));
+        assert_eq!(parse_age("12h"), Ok(43_200));
+        assert_eq!(parse_age("2w"), Ok(1_209_600));
+        assert!(parse_age("14").is_err());
+        assert!(parse_age("d").is_err());
+        assert_eq!(format_age(parse_age("3d").unwrap() + 600), "3d");
+        assert_eq!(format_age(90), "1m");
+        assert_eq!(format_age(5), "5s");
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("500MB"), Ok(500_000_000));
+        assert_eq!(parse_size("10GiB"), Ok(10 << 30));
+        assert_eq!(parse_size("1.5g"), Ok(1_500_000_000));
+        assert_eq!(parse_size("2048"), Ok(2048));
+        assert!(parse_size("10 parsecs").is_err());
+        assert_eq!(format_size(1_500_000_000), "1.5 GB");
+        assert_eq!(format_size(512), "512 B");
+    }
+}
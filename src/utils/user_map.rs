@@ -0,0 +1,120 @@
+//! Running containers as the host user (`--user-map`), so files servers write into
+//! mounted host directories belong to the host user instead of root
+//!
+//! On by default whenever a host directory is mounted; `--user-map` turns it on for every
+//! run and `--no-user-map` off. The container runs with `--user UID:GID`, a user with no
+//! passwd entry, so `HOME` points at `/tmp` unless set with `-e`. Generated images make
+//! their working directory writable by any user, so the server can still write there;
+//! images run directly weren't made that way, so they always run as their own user.
+
+use crate::utils::volumes;
+
+/// When containers run as the host user
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UserMap {
+    /// When a host directory is mounted
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The host's `UID:GID`; none off Unix, or for root, whose files are root-owned anyway
+#[cfg(unix)]
+pub fn host_user() -> Option<String> {
+    // SAFETY: getuid and getgid have no preconditions and cannot fail
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    (uid != 0).then(|| format!("{}:{}", uid, gid))
+}
+
+#[cfg(not(unix))]
+pub fn host_user() -> Option<String> {
+    None
+}
+
+/// Arguments for `finch run` that map the host user as `mode` asks, given the run's
+/// volumes and `-e` values
+pub fn run_args(mode: UserMap, volumes: &[String], env_vars: &[String]) -> Vec<String> {
+    let wanted = match mode {
+        UserMap::Auto => volumes::has_host_mount(volumes),
+        UserMap::Always => true,
+        UserMap::Never => false,
+    };
+    match host_user().filter(|_| wanted) {
+        Some(user) => args_for(&user, env_vars),
+        None => Vec::new(),
+    }
+}
+
+fn args_for(user: &str, env_vars: &[String]) -> Vec<String> {
+    let mut args = vec!["--user".to_string(), user.to_string()];
+    if !env_vars.iter().any(|env| env.split('=').next() == Some("HOME")) {
+        args.extend(["-e".to_string(), "HOME=/tmp".to_string()]);
+    }
+    args
+}
+
+/// Make the final stage's `WORKDIR` writable by any user, just before its `ENTRYPOINT`.
+/// Images are cached whichever way they are run, so this is always applied
+pub fn apply(dockerfile: String) -> String {
+    let lines: Vec<&str> = dockerfile.lines().collect();
+    let instruction = |line: &str, name: &str| line.trim_start().to_uppercase().starts_with(&format!("{} ", name));
+    let stage = lines.iter().rposition(|line| instruction(line, "FROM")).unwrap_or(0);
+    let entrypoint = lines.iter().skip(stage).position(|line| instruction(line, "ENTRYPOINT") || instruction(line, "CMD"))
+        .map_or(lines.len(), |index| stage + index);
+    // Without a WORKDIR the server starts in `/`, which stays as it is
+    if !lines[stage..entrypoint].iter().any(|line| instruction(line, "WORKDIR")) {
+        return dockerfile;
+    }
+    let user = lines[stage..entrypoint].iter().rev()
+        .find(|line| instruction(line, "USER"))
+        .map(|line| line.trim_start()[5..].trim());
+
+    let mut section = String::from("# Let a server run as the host user (--user-map) write to its working directory\n");
+    match user {
+        Some(user) => section.push_str(&format!("USER root\nRUN chmod a+rwx .\nUSER {}\n\n", user)),
+        None => section.push_str("RUN chmod a+rwx .\n\n"),
+    }
+
+    let mut output = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        if index == entrypoint {
+            output.push_str(&section);
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    if entrypoint == lines.len() {
+        output.push('\n');
+        output.push_str(section.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_for() {
+        assert_eq!(args_for("501:20", &[]), ["--user", "501:20", "-e", "HOME=/tmp"]);
+        assert_eq!(args_for("1000:1000", &["HOME=/data".to_string()]), ["--user", "1000:1000"]);
+    }
+
+    #[test]
+    fn test_apply() {
+        let dockerfile = "FROM node:20 AS build\nWORKDIR /src\nRUN npm ci\n\nFROM node:20-slim\nWORKDIR /app\nCOPY --from=build /src /app\nUSER node\n\nENTRYPOINT [\"node\", \"index.js\"]\n".to_string();
+        let applied = apply(dockerfile);
+        let runtime = applied.split("FROM node:20-slim").nth(1).unwrap();
+        assert!(runtime.contains("USER node\n\n# Let a server"));
+        assert!(runtime.contains("USER root\nRUN chmod a+rwx .\nUSER node\n\nENTRYPOINT"));
+        assert_eq!(applied.matches("chmod").count(), 1);
+
+        // Root images need no switch; images without a WORKDIR are left alone
+        let root = apply("FROM python:3.12-slim\nWORKDIR /app\nCOPY . .\nCMD [\"python\", \"server.py\"]\n".to_string());
+        assert!(root.contains("COPY . .\n# Let a server run as the host user (--user-map) write to its working directory\nRUN chmod a+rwx .\n\nCMD"));
+        let no_workdir = "FROM alpine:3.19\nUSER mcp\nENTRYPOINT [\"sh\"]\n".to_string();
+        assert_eq!(apply(no_workdir.clone()), no_workdir);
+    }
+}
@@ -0,0 +1,183 @@
+//! Normalizing `-v` volume specs before they reach finch
+//!
+//! finch passes host paths to the VM verbatim, so `./data` or `~/notes` would name paths
+//! inside the VM rather than on the host. Specs are rewritten with `~` expanded and
+//! relative paths made absolute, and host paths are checked before the container starts.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+use console::style;
+
+use crate::finch::vm;
+
+/// Mount options finch accepts after the container path
+const MOUNT_OPTIONS: &[&str] = &[
+    "ro", "rw", "z", "Z", "cached", "delegated", "consistent",
+    "shared", "rshared", "slave", "rslave", "private", "rprivate",
+];
+
+/// Normalize a `-v` value given on the command line, relative to the current directory
+pub fn parse_volume(spec: &str) -> Result<String, String> {
+    let cwd = std::env::current_dir().map_err(|e| format!("cannot resolve relative paths: {}", e))?;
+    normalize(spec, &cwd)
+}
+
+/// Rewrite `SOURCE:TARGET[:OPTIONS]` with an absolute host path, resolving relative
+/// sources against `base_dir`. Named volumes and anonymous `/path` volumes are kept
+pub fn normalize(spec: &str, base_dir: &Path) -> Result<String, String> {
+    let (source, rest) = split_source(spec);
+    let Some(rest) = rest else {
+        // Anonymous volume: only a container path
+        return if source.starts_with('/') {
+            Ok(source.to_string())
+        } else {
+            Err(format!("'{}' is not HOST_PATH:CONTAINER_PATH[:ro]", spec))
+        };
+    };
+
+    let (target, options) = rest.split_once(':').unwrap_or((rest, ""));
+    if !target.starts_with('/') {
+        return Err(format!("container path '{}' must be absolute", target));
+    }
+    if let Some(option) = options.split(',').find(|option| !option.is_empty() && !MOUNT_OPTIONS.contains(option)) {
+        return Err(format!("unknown mount option '{}' (expected ro or rw)", option));
+    }
+
+    let source = match host_path(source, base_dir)? {
+        Some(path) => path.display().to_string(),
+        None => source.to_string(),
+    };
+    Ok(if options.is_empty() {
+        format!("{}:{}", source, target)
+    } else {
+        format!("{}:{}:{}", source, target, options)
+    })
+}
+
+/// Split off the source, keeping a Windows drive letter (`C:\data`) in it
+fn split_source(spec: &str) -> (&str, Option<&str>) {
+    let bytes = spec.as_bytes();
+    let skip = if bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && matches!(bytes[2], b'\\' | b'/') { 2 } else { 0 };
+    match spec[skip..].find(':') {
+        Some(index) => (&spec[..skip + index], Some(&spec[skip + index + 1..])),
+        None => (spec, None),
+    }
+}
+
+/// The absolute host path a source names, or None for a named volume
+fn host_path(source: &str, base_dir: &Path) -> Result<Option<PathBuf>, String> {
+    if source.is_empty() {
+        return Err("empty host path".to_string());
+    }
+    if let Some(rest) = source.strip_prefix('~') {
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return Err(format!("cannot expand '{}': only ~ and ~/path are supported", source));
+        }
+        let home = dirs::home_dir().ok_or("cannot expand ~: no home directory")?;
+        return Ok(Some(clean(&home.join(rest.trim_start_matches('/')))));
+    }
+
+    let path = Path::new(source);
+    let is_path = path.is_absolute() || source.starts_with('.') || source.contains('/') || source.contains('\\');
+    if !is_path {
+        return Ok(None);
+    }
+    Ok(Some(clean(&base_dir.join(path))))
+}
+
+/// Drop `.` and resolve `..` without touching the filesystem
+fn clean(path: &Path) -> PathBuf {
+    let mut cleaned = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                cleaned.pop();
+            }
+            other => cleaned.push(other),
+        }
+    }
+    cleaned
+}
+
+/// Whether any spec mounts a host path, rather than a named or anonymous volume
+pub fn has_host_mount(volumes: &[String]) -> bool {
+    volumes.iter().any(|spec| match split_source(spec) {
+        (source, Some(_)) => matches!(host_path(source, Path::new("/")), Ok(Some(_))),
+        (_, None) => false,
+    })
+}
+
+/// Check that each host path exists, and warn about paths the Finch VM can't see
+pub fn check(volumes: &[String]) -> Result<()> {
+    let shared = vm::shared_directories();
+    for spec in volumes {
+        let (source, rest) = split_source(spec);
+        if rest.is_none() {
+            continue;
+        }
+        let Ok(Some(path)) = host_path(source, Path::new("/")) else {
+            continue;
+        };
+        if !path.exists() {
+            anyhow::bail!("Volume source {} does not exist (from -v {})", path.display(), spec);
+        }
+        if cfg!(target_os = "macos") && !shared.iter().any(|dir| path.starts_with(dir)) {
+            // stdout may carry an MCP session
+            eprintln!(
+                "{} {} is outside the directories the Finch VM shares, so the container will see an empty directory. \
+                 Add it to additional_directories in ~/.finch/finch.yaml and run `finch-mcp vm recreate`",
+                style("⚠️").yellow(),
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let base = Path::new("/work/project");
+        assert_eq!(normalize("./data:/data", base).unwrap(), "/work/project/data:/data");
+        assert_eq!(normalize("../shared/x:/x:ro", base).unwrap(), "/work/shared/x:/x:ro");
+        assert_eq!(normalize("/abs/path:/data:rw,z", base).unwrap(), "/abs/path:/data:rw,z");
+        assert_eq!(normalize("cache:/root/.cache", base).unwrap(), "cache:/root/.cache");
+        assert_eq!(normalize("/scratch", base).unwrap(), "/scratch");
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(normalize("~/notes:/notes", base).unwrap(), format!("{}:/notes", home.join("notes").display()));
+        }
+
+        assert!(normalize("./data:data", base).is_err());
+        assert!(normalize("./data:/data:readonly", base).is_err());
+        assert!(normalize("~bob/notes:/notes", base).is_err());
+        assert!(normalize("relative", base).is_err());
+    }
+
+    #[test]
+    fn test_has_host_mount() {
+        assert!(has_host_mount(&["data:/data".to_string(), "/home/dev/notes:/notes:ro".to_string()]));
+        assert!(!has_host_mount(&["data:/data".to_string(), "/cache".to_string()]));
+        assert!(!has_host_mount(&[]));
+    }
+
+    #[test]
+    fn test_windows_drive_source() {
+        assert_eq!(split_source("C:\\data:/data:ro"), ("C:\\data", Some("/data:ro")));
+        assert_eq!(split_source("/data:/data"), ("/data", Some("/data")));
+    }
+
+    #[test]
+    fn test_check_missing_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = format!("{}:/data", dir.path().display());
+        assert!(check(&[existing, "cache:/cache".to_string(), "/scratch".to_string()]).is_ok());
+
+        let missing = format!("{}:/data", dir.path().join("missing").display());
+        assert!(check(&[missing]).is_err());
+    }
+}
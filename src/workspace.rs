@@ -0,0 +1,160 @@
+//! Scratch directories for clones, build contexts and cache archives
+//!
+//! They live under `workspace/` in the cache directory rather than the system temp
+//! directory, each named after the purpose and process that made it, and are removed
+//! once done with. A process that is killed leaves its directories behind: those are
+//! reaped when they are over a day old, whenever a new one is made, or straight away
+//! by `finch-mcp cleanup --workspace`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use tempfile::TempDir;
+
+/// How old a directory left by an exited process must be before it is reaped automatically
+pub const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+static REAPED: Once = Once::new();
+
+/// The directory holding every scratch directory
+pub fn workspace_dir() -> Result<PathBuf> {
+    Ok(crate::paths::cache_dir()?.join("workspace"))
+}
+
+/// A scratch directory for `purpose` (`clone`, `build`, ...), removed when dropped
+pub fn temp_dir(purpose: &str) -> Result<TempDir> {
+    let dir = workspace_dir()?;
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create workspace directory {}", dir.display()))?;
+    REAPED.call_once(|| {
+        if let Err(e) = reap_in(&dir, STALE_AFTER) {
+            log::debug!("Failed to reap stale workspaces: {:#}", e);
+        }
+    });
+    tempfile::Builder::new()
+        .prefix(&format!("{}-{}-", purpose, std::process::id()))
+        .tempdir_in(&dir)
+        .context("Failed to create temporary directory")
+}
+
+/// What reaping found
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReapReport {
+    pub removed: usize,
+    pub freed_bytes: u64,
+    /// Directories kept because their process is still running, or they are too recent
+    pub kept: usize,
+}
+
+/// Remove directories left by processes that have exited and are older than `min_age`
+pub fn reap(min_age: Duration) -> Result<ReapReport> {
+    let dir = workspace_dir()?;
+    if !dir.exists() {
+        return Ok(ReapReport::default());
+    }
+    reap_in(&dir, min_age)
+}
+
+fn reap_in(dir: &Path, min_age: Duration) -> Result<ReapReport> {
+    let mut report = ReapReport::default();
+    let now = SystemTime::now();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        let age = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        // Where liveness can't be checked, a day without changes stands in for it
+        let abandoned = match owner(name).map(process_alive) {
+            Some(Some(alive)) => !alive && age >= min_age,
+            _ => age >= min_age.max(STALE_AFTER),
+        };
+        if !abandoned {
+            report.kept += 1;
+            continue;
+        }
+
+        let size = dir_size(&path);
+        let removed = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        match removed {
+            Ok(()) => {
+                report.removed += 1;
+                report.freed_bytes += size;
+            }
+            Err(e) => log::debug!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+    Ok(report)
+}
+
+/// The process that made a directory named `<purpose>-<pid>-<random>`
+fn owner(name: &str) -> Option<u32> {
+    let mut parts = name.rsplitn(3, '-');
+    let _random = parts.next()?;
+    let pid = parts.next()?.parse().ok()?;
+    parts.next().map(|_| pid)
+}
+
+/// Whether a process is running; `None` where that can't be told
+#[cfg(unix)]
+fn process_alive(pid: u32) -> Option<bool> {
+    if pid == std::process::id() {
+        return Some(true);
+    }
+    let pid = libc::pid_t::try_from(pid).ok()?;
+    // SAFETY: signal 0 only checks that the process exists and may be signalled
+    let result = unsafe { libc::kill(pid, 0) };
+    Some(result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM))
+}
+
+#[cfg(not(unix))]
+fn process_alive(pid: u32) -> Option<bool> {
+    (pid == std::process::id()).then_some(true)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::read_dir(path)
+            .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| dir_size(&entry.path())).sum())
+            .unwrap_or(0),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owner() {
+        assert_eq!(owner("build-4242-aB3xYz"), Some(4242));
+        assert_eq!(owner("clone-1-x"), Some(1));
+        assert_eq!(owner("notes.txt"), None);
+        assert_eq!(owner("build-abc-x"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reap() {
+        let dir = tempfile::tempdir().unwrap();
+        let own = dir.path().join(format!("build-{}-aaaaaa", std::process::id()));
+        // PIDs are well below this on every supported platform
+        let exited = dir.path().join("clone-2147483000-bbbbbb");
+        fs::create_dir_all(own.join("context")).unwrap();
+        fs::create_dir_all(exited.join("repo")).unwrap();
+        fs::write(exited.join("repo").join("package.json"), "{}").unwrap();
+
+        // Too recent for automatic reaping
+        assert_eq!(reap_in(dir.path(), STALE_AFTER).unwrap(), ReapReport { removed: 0, freed_bytes: 0, kept: 2 });
+
+        let report = reap_in(dir.path(), Duration::ZERO).unwrap();
+        assert_eq!(report, ReapReport { removed: 1, freed_bytes: 2, kept: 1 });
+        assert!(own.exists());
+        assert!(!exited.exists());
+    }
+}
@@ -2,6 +2,8 @@ use finch_mcp::{
     FinchClient,
     RunOptions,
 };
+use finch_mcp::cache::RetentionPolicy;
+use finch_mcp::core::list::{print_list, ListSort};
 use tempfile::TempDir;
 use std::{fs, time::Duration};
 use tokio::time::timeout;
@@ -25,7 +27,7 @@ async fn test_container_lifecycle_operations() {
     let run_options = RunOptions {
         image_name: "alpine:latest".to_string(),
         env_vars: Some(vec!["TEST_ENV=container_lifecycle".to_string()]),
-        volumes: None,
+        ..Default::default()
     };
     
     // Run container with timeout
@@ -38,7 +40,7 @@ async fn test_container_lifecycle_operations() {
     assert!(run_result.is_ok() || run_result.is_err());
     
     // Test container cleanup
-    let cleanup_result = finch_client.cleanup_resources(false, true, false, false).await;
+    let cleanup_result = finch_client.cleanup_resources(false, true, false, &RetentionPolicy::default(), false, false).await;
     assert!(cleanup_result.is_ok());
 }
 
@@ -53,23 +55,24 @@ async fn test_container_resource_management() {
     }
     
     // Test resource listing
-    let list_result = finch_client.list_resources(false).await;
+    let list_result = print_list(false, None).await;
     assert!(list_result.is_ok());
     
     // Test resource listing with --all flag
-    let list_all_result = finch_client.list_resources(true).await;
+    let list_all_result = print_list(true, Some(ListSort::Size)).await;
     assert!(list_all_result.is_ok());
     
     // Test selective cleanup options
-    let cleanup_containers_result = finch_client.cleanup_resources(false, true, false, false).await;
+    let cleanup_containers_result = finch_client.cleanup_resources(false, true, false, &RetentionPolicy::default(), false, false).await;
     assert!(cleanup_containers_result.is_ok());
     
-    let cleanup_images_result = finch_client.cleanup_resources(false, false, true, false).await;
+    let cleanup_images_result = finch_client.cleanup_resources(false, false, true, &RetentionPolicy::default(), false, false).await;
     assert!(cleanup_images_result.is_ok());
 }
 
 #[tokio::test]
 #[ignore = "Container test requiring Finch installation"]
+#[allow(clippy::useless_vec)]
 async fn test_container_environment_variables() {
     let finch_client = FinchClient::new();
     
@@ -91,7 +94,7 @@ async fn test_container_environment_variables() {
         let run_options = RunOptions {
             image_name: "alpine:latest".to_string(),
             env_vars: env_vars.clone(),
-            volumes: None,
+            ..Default::default()
         };
         
         let result = timeout(
@@ -106,6 +109,7 @@ async fn test_container_environment_variables() {
 
 #[tokio::test]
 #[ignore = "Container test requiring Finch installation"]
+#[allow(clippy::useless_vec)]
 async fn test_container_volume_mounting() {
     let finch_client = FinchClient::new();
     
@@ -134,6 +138,7 @@ async fn test_container_volume_mounting() {
             image_name: "alpine:latest".to_string(),
             env_vars: Some(vec![format!("TEST_CASE={}", i)]),
             volumes: volumes.clone(),
+            ..Default::default()
         };
         
         let result = timeout(
@@ -167,7 +172,7 @@ async fn test_container_image_operations() {
         let run_options = RunOptions {
             image_name: image_name.to_string(),
             env_vars: Some(vec![format!("IMAGE_TEST={}", image_name)]),
-            volumes: None,
+            ..Default::default()
         };
         
         let result = timeout(
@@ -193,8 +198,7 @@ async fn test_container_error_scenarios() {
     // Test invalid image name
     let invalid_image_options = RunOptions {
         image_name: "nonexistent-image:invalid-tag".to_string(),
-        env_vars: None,
-        volumes: None,
+        ..Default::default()
     };
     
     let invalid_result = timeout(
@@ -208,8 +212,8 @@ async fn test_container_error_scenarios() {
     // Test invalid volume mount
     let invalid_volume_options = RunOptions {
         image_name: "alpine:latest".to_string(),
-        env_vars: None,
         volumes: Some(vec!["/nonexistent/path:/data".to_string()]),
+        ..Default::default()
     };
     
     let volume_result = timeout(
@@ -229,13 +233,13 @@ fn test_container_option_validation() {
     let valid_configs = vec![
         RunOptions {
             image_name: "alpine:latest".to_string(),
-            env_vars: None,
-            volumes: None,
+            ..Default::default()
         },
         RunOptions {
             image_name: "my-custom-image:v1.0".to_string(),
             env_vars: Some(vec!["VAR1=value1".to_string(), "VAR2=value2".to_string()]),
             volumes: Some(vec!["/host:/container".to_string(), "/data:/app/data:ro".to_string()]),
+            ..Default::default()
         },
     ];
     
@@ -282,7 +286,7 @@ async fn test_container_concurrent_operations() {
         let run_options = RunOptions {
             image_name: "alpine:latest".to_string(),
             env_vars: Some(vec![format!("CONCURRENT_TEST={}", i)]),
-            volumes: None,
+            ..Default::default()
         };
         
         let handle = tokio::spawn(async move {
@@ -340,8 +344,7 @@ fn test_container_configuration_edge_cases() {
     for image_name in complex_image_names {
         let config = RunOptions {
             image_name: image_name.to_string(),
-            env_vars: None,
-            volumes: None,
+            ..Default::default()
         };
         
         assert!(!config.image_name.is_empty());
@@ -359,7 +362,7 @@ fn test_container_configuration_edge_cases() {
         let config = RunOptions {
             image_name: "test:latest".to_string(),
             env_vars,
-            volumes: None,
+            ..Default::default()
         };
         
         if let Some(ref env_vars) = config.env_vars {
@@ -0,0 +1,49 @@
+use finch_mcp::core::git_containerize::{local_build, LocalContainerizeOptions};
+use finch_mcp::Event;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+/// A local build reports each stage to the sink its options carry
+#[cfg(unix)]
+#[tokio::test]
+async fn test_local_build_emits_events() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    finch_mcp::paths::configure(Some(dir.path().join("cache")), Some(dir.path().join("data")));
+
+    // Stands in for finch: the first build fails with a registry 503, the second succeeds
+    let bin = dir.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    let marker = dir.path().join("attempted");
+    let script = format!(
+        "#!/bin/sh\nif [ \"$1\" = build ]; then\n  if [ -e {0} ]; then echo '#5 [1/2] FROM node:20-slim' >&2; exit 0; fi\n  touch {0}; echo 'failed to fetch: 503 Service Unavailable' >&2; exit 1\nfi\nexit 0\n",
+        marker.display()
+    );
+    fs::write(bin.join("finch"), script).unwrap();
+    fs::set_permissions(bin.join("finch"), fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", format!("{}:{}", bin.display(), path));
+
+    let project = dir.path().join("server");
+    fs::create_dir(&project).unwrap();
+    fs::write(project.join("package.json"), r#"{"name": "events-test", "version": "1.0.0", "main": "index.js"}"#).unwrap();
+    fs::write(project.join("index.js"), "console.log('hello');\n").unwrap();
+    fs::write(project.join(".finch-mcp"), "build:\n  retryBackoff: 0\n").unwrap();
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let seen = seen.clone();
+        move |event: &Event| seen.lock().unwrap().push(event.clone())
+    };
+    let options = LocalContainerizeOptions::new(project.display().to_string()).with_event_sink(Arc::new(sink));
+    let image = local_build(options).await.unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert!(matches!(seen.first(), Some(Event::CacheMiss { .. })), "{:?}", seen);
+    assert!(seen.contains(&Event::DetectionResult { project_type: "NodeJs".to_string() }));
+    assert!(seen.iter().any(|event| matches!(event, Event::BuildStarted { image: started, .. } if *started == image)));
+    assert!(seen.iter().any(|event| matches!(event, Event::BuildRetry { retry: 1, .. })));
+    assert!(seen.iter().any(|event| matches!(event, Event::BuildStep(step) if step.current == 1 && step.total == 2)));
+    assert!(matches!(seen.last(), Some(Event::BuildFinished { image: finished, success: true, .. }) if *finished == image));
+}
@@ -90,7 +90,7 @@ fn test_project_detection_filesystem() {
     fs::remove_file(test_path.join("pyproject.toml")).unwrap();
     fs::write(test_path.join("Pipfile"), "[packages]\nflask = \"*\"").unwrap();
     let project_info = project_detector::detect_project_type(test_path).unwrap();
-    assert_eq!(project_info.project_type, project_detector::ProjectType::Unknown); // Pipfile not directly supported
+    assert_eq!(project_info.project_type, project_detector::ProjectType::PythonPipenv);
     
     // Test multiple package managers for Node.js
     fs::remove_file(test_path.join("Pipfile")).unwrap();
@@ -153,14 +153,8 @@ async fn test_auto_containerization_filesystem() {
     create_test_nodejs_project(test_path);
     
     // Test auto-containerization with filesystem operations
-    let auto_options = AutoContainerizeOptions {
-        command: "node".to_string(),
-        args: vec!["index.js".to_string()],
-        env_vars: vec![],
-        volumes: vec![],
-        host_network: false,
-        forward_registry: false,
-    };
+    let auto_options = AutoContainerizeOptions::new("node")
+        .with_args(vec!["index.js".to_string()]);
     
     // This tests the filesystem operations involved in containerization
     let result = timeout(
@@ -251,14 +245,12 @@ fn test_directory_operations() {
     fn count_js_files(dir: &Path) -> usize {
         let mut count = 0;
         if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        count += count_js_files(&path);
-                    } else if path.extension().map_or(false, |ext| ext == "js") {
-                        count += 1;
-                    }
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    count += count_js_files(&path);
+                } else if path.extension().is_some_and(|ext| ext == "js") {
+                    count += 1;
                 }
             }
         }
@@ -371,7 +363,7 @@ fn test_log_directory_operations() {
     
     // Test log directory exists or can be created
     if !log_dir.exists() {
-        fs::create_dir_all(&log_dir).unwrap();
+        fs::create_dir_all(log_dir).unwrap();
     }
     assert!(log_dir.exists());
     assert!(log_dir.is_dir());
@@ -1,9 +1,13 @@
 use finch_mcp::{
     FinchClient,
     StdioRunOptions,
+    NetworkMode,
+    RestartPolicy,
+    Events,
     DockerfileOptions,
     generate_stdio_dockerfile,
 };
+use finch_mcp::utils::user_map::UserMap;
 use tempfile::TempDir;
 use std::fs;
 
@@ -59,7 +63,17 @@ async fn test_finch_client_integration() {
         image_name: "hello-world".to_string(),
         env_vars: vec!["TEST=value".to_string()],
         volumes: vec![],
-        host_network: false,
+        network: NetworkMode::default(),
+        publish: Vec::new(),
+        args: Vec::new(),
+        user_map: UserMap::Never,
+        health_check: false,
+        startup_timeout: None,
+        trace_mcp: None,
+        forward_proxy: false,
+        no_forward_timezone: false,
+        restart: RestartPolicy::No,
+        events: Events::default(),
     };
     
     // This should succeed but we'll ignore errors
@@ -94,8 +108,7 @@ fn test_run_options_creation() {
     // Test with minimal options
     let options = RunOptions {
         image_name: "test-image".to_string(),
-        env_vars: None,
-        volumes: None,
+        ..Default::default()
     };
 
     assert_eq!(options.image_name, "test-image");
@@ -107,6 +120,7 @@ fn test_run_options_creation() {
         image_name: "test-image".to_string(),
         env_vars: Some(vec!["VAR=VALUE".to_string()]),
         volumes: Some(vec!["/host:/container".to_string()]),
+        ..Default::default()
     };
 
     assert_eq!(options.image_name, "test-image");
@@ -1,5 +1,6 @@
 use finch_mcp::{
     RunOptions,
+    NetworkMode,
     core::auto_containerize::{auto_containerize_and_run, AutoContainerizeOptions},
 };
 use tempfile::TempDir;
@@ -18,14 +19,8 @@ async fn test_mcp_server_stdio_communication() {
     create_echo_mcp_server(test_dir.path()).await;
     
     // Test auto-containerization of the echo server
-    let auto_options = AutoContainerizeOptions {
-        command: "node".to_string(),
-        args: vec!["echo-server.js".to_string()],
-        env_vars: vec![],
-        volumes: vec![],
-        host_network: false,
-        forward_registry: false,
-    };
+    let auto_options = AutoContainerizeOptions::new("node")
+        .with_args(vec!["echo-server.js".to_string()]);
     
     // This test verifies that the MCP server can be containerized and started
     let result = timeout(
@@ -91,13 +86,13 @@ fn test_mcp_server_config_validation() {
     let valid_configs = vec![
         RunOptions {
             image_name: "mcp-server:latest".to_string(),
-            env_vars: None,
-            volumes: None,
+            ..Default::default()
         },
         RunOptions {
             image_name: "custom-mcp:v1.0".to_string(),
             env_vars: Some(vec!["MCP_PORT=3000".to_string(), "DEBUG=true".to_string()]),
             volumes: Some(vec!["/data:/app/data".to_string()]),
+            ..Default::default()
         },
     ];
     
@@ -129,8 +124,7 @@ async fn test_mcp_server_error_handling() {
     // Test invalid image name
     let invalid_options = RunOptions {
         image_name: "".to_string(),
-        env_vars: None,
-        volumes: None,
+        ..Default::default()
     };
     
     // This should fail gracefully
@@ -141,7 +135,7 @@ async fn test_mcp_server_error_handling() {
     let valid_options = RunOptions {
         image_name: "alpine:latest".to_string(),
         env_vars: Some(vec!["VALID_ENV_VAR=value".to_string()]),
-        volumes: None,
+        ..Default::default()
     };
     
     // This may succeed or fail depending on environment, but shouldn't panic
@@ -191,14 +185,9 @@ async fn test_mcp_server_volume_mounting() {
     // Create MCP server that reads from mounted volume
     create_file_reader_mcp_server(test_dir.path()).await;
     
-    let auto_options = AutoContainerizeOptions {
-        command: "node".to_string(),
-        args: vec!["file-reader.js".to_string()],
-        env_vars: vec![],
-        volumes: vec![format!("{}:/app/data", data_dir.display())],
-        host_network: false,
-        forward_registry: false,
-    };
+    let auto_options = AutoContainerizeOptions::new("node")
+        .with_args(vec!["file-reader.js".to_string()])
+        .with_volumes(vec![format!("{}:/app/data", data_dir.display())]);
     
     // Test that volume mounting works in containerized environment
     let result = timeout(
@@ -215,28 +204,18 @@ fn test_mcp_server_network_configuration() {
     // Test network configuration options for MCP servers
     
     // Test auto-containerization with host networking
-    let host_network_config = AutoContainerizeOptions {
-        command: "node".to_string(),
-        args: vec!["server.js".to_string()],
-        env_vars: vec!["MCP_HOST=localhost".to_string()],
-        volumes: vec![],
-        host_network: true,
-        forward_registry: false,
-    };
+    let host_network_config = AutoContainerizeOptions::new("node")
+        .with_args(vec!["server.js".to_string()])
+        .with_env_vars(vec!["MCP_HOST=localhost".to_string()])
+        .with_network(NetworkMode::Host);
     
-    assert!(host_network_config.host_network);
+    assert_eq!(host_network_config.network, NetworkMode::Host);
     
     // Test bridge networking (default)
-    let bridge_network_config = AutoContainerizeOptions {
-        command: "node".to_string(),
-        args: vec!["server.js".to_string()],
-        env_vars: vec![],
-        volumes: vec![],
-        host_network: false,
-        forward_registry: false,
-    };
+    let bridge_network_config = AutoContainerizeOptions::new("node")
+        .with_args(vec!["server.js".to_string()]);
     
-    assert!(!bridge_network_config.host_network);
+    assert_eq!(bridge_network_config.network, NetworkMode::Bridge);
 }
 
 // Helper functions to create test MCP servers
@@ -0,0 +1,24 @@
+use finch_mcp::cache::git_refs;
+use finch_mcp::utils::offline;
+use finch_mcp::NetworkMode;
+
+#[test]
+fn test_offline_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    finch_mcp::paths::configure(Some(dir.path().join("cache")), Some(dir.path().join("data")));
+    assert!(offline::ensure_online(false, "Building ./server").is_ok());
+
+    let err = offline::ensure_online(true, "Building ./server").unwrap_err().to_string();
+    assert!(err.starts_with("Building ./server needs network access"));
+    assert!(err.contains("--offline"));
+
+    // Unknown repositories can't be resolved; pinned commits need no remote
+    let err = git_refs::resolve_commit("https://github.com/acme/mcp-server", true, false).unwrap_err().to_string();
+    assert!(err.contains("--offline"), "{}", err);
+    let sha = "0123456789abcdef0123456789abcdef01234567";
+    assert_eq!(git_refs::resolve_commit(&format!("https://github.com/acme/mcp-server#{}", sha), true, false).unwrap(), sha);
+
+    // Servers run offline get no network at all
+    assert_eq!(offline::network(true, &NetworkMode::Host), NetworkMode::None);
+    assert_eq!(offline::network(false, &NetworkMode::Host), NetworkMode::Host);
+}